@@ -0,0 +1,58 @@
+//! Benchmark comparing `classify` with the full parser.
+//!
+//! Run with `cargo bench --bench parse`. Set `PARSE_BENCH_NAMES` to change
+//! the number of generated names (default: 10000).
+
+use anidb2folder::{classify_many, parse_directory_name};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const DEFAULT_NAMES: usize = 10000;
+const ITERATIONS: u32 = 5;
+
+fn bench(label: &str, names: &[&str], run: &dyn Fn(&[&str]) -> usize) {
+    let mut total = Duration::ZERO;
+    let mut recognized = 0;
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        recognized = black_box(run(black_box(names)));
+        total += start.elapsed();
+    }
+
+    println!(
+        "{:<10} {:>6} of {} recognized  avg {:?}",
+        label,
+        recognized,
+        names.len(),
+        total / ITERATIONS
+    );
+}
+
+fn main() {
+    let count = std::env::var("PARSE_BENCH_NAMES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_NAMES);
+
+    // A file manager listing: both formats and unrelated names
+    let owned: Vec<String> = (0..count)
+        .map(|i| match i % 4 {
+            0 => format!("[S{}] {}", i % 10, i),
+            1 => format!("Title {} ／ English Title {} (2020) [anidb-{}]", i, i, i),
+            2 => format!("Holiday photos {}", i),
+            _ => format!("notes-{}.txt", i),
+        })
+        .collect();
+    let names: Vec<&str> = owned.iter().map(String::as_str).collect();
+
+    bench("classify", &names, &|names| {
+        classify_many(names).iter().flatten().count()
+    });
+    bench("parse", &names, &|names| {
+        names
+            .iter()
+            .filter(|name| parse_directory_name(name).is_ok())
+            .count()
+    });
+}
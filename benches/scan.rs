@@ -0,0 +1,54 @@
+//! Benchmark comparing serial and parallel directory scanning.
+//!
+//! Run with `cargo bench --bench scan`. Set `SCAN_BENCH_DIRS` to change the
+//! number of generated subdirectories (default: 5000).
+
+use anidb2folder::{scan_directory_ex, ScanOptions};
+use std::time::{Duration, Instant};
+
+const DEFAULT_DIRS: usize = 5000;
+const ITERATIONS: u32 = 5;
+
+fn bench(label: &str, dir: &std::path::Path, options: &ScanOptions) {
+    let mut total = Duration::ZERO;
+    let mut count = 0;
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        count = scan_directory_ex(dir, options).unwrap().entries.len();
+        total += start.elapsed();
+    }
+
+    println!(
+        "{:<12} {:>6} entries  avg {:?}",
+        label,
+        count,
+        total / ITERATIONS
+    );
+}
+
+fn main() {
+    let dirs = std::env::var("SCAN_BENCH_DIRS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_DIRS);
+
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..dirs {
+        std::fs::create_dir(dir.path().join(format!("[S{}] {}", i % 10, i))).unwrap();
+    }
+
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+
+    bench("serial", dir.path(), &ScanOptions { threads: Some(1) });
+    bench(
+        "parallel",
+        dir.path(),
+        &ScanOptions {
+            threads: Some(threads),
+        },
+    );
+    bench("auto", dir.path(), &ScanOptions::default());
+}
@@ -0,0 +1,8 @@
+fn main() {
+    // Target triple for version::BuildInfo
+    println!(
+        "cargo:rustc-env=ANIDB2FOLDER_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    println!("cargo:rerun-if-changed=build.rs");
+}
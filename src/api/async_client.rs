@@ -0,0 +1,237 @@
+//! AniDB HTTP API client for async code (`async` feature).
+//!
+//! Requests, retries and responses work as in [`AniDbClient`](super::AniDbClient):
+//! the same URL, the same errors retried with the same backoff, the same
+//! XML parsing. Waiting, for the rate limit and between attempts, uses
+//! `tokio::time` instead of blocking the thread. The rate limit is shared
+//! with blocking clients, so a process mixing both still keeps to it.
+
+use std::time::Duration;
+
+use reqwest::Client;
+use tracing::{debug, info, warn};
+
+use super::client::{episodes_text, is_retryable, parse_response, request_url, RateLimiter};
+use super::types::{AnimeInfo, ApiConfig, ApiError};
+
+/// AniDB HTTP API client for use inside a tokio runtime
+pub struct AniDbClientAsync {
+    client: Client,
+    config: ApiConfig,
+    rate_limiter: RateLimiter,
+}
+
+impl AniDbClientAsync {
+    /// Create a new async AniDB client with the given configuration
+    pub fn new(config: ApiConfig) -> Result<Self, ApiError> {
+        if !config.is_configured() {
+            return Err(ApiError::NotConfigured);
+        }
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .gzip(true)
+            .user_agent(format!("{}/{}", config.client_name, config.client_version))
+            .build()
+            .map_err(|e| ApiError::NetworkError(e.to_string()))?;
+
+        let rate_limiter = RateLimiter::new(Duration::from_secs(config.min_request_interval_secs));
+
+        Ok(Self {
+            client,
+            config,
+            rate_limiter,
+        })
+    }
+
+    /// Fetch anime information by AniDB ID with retry logic
+    pub async fn fetch_anime(&self, anidb_id: u32) -> Result<AnimeInfo, ApiError> {
+        let mut last_error = None;
+        let mut delay = Duration::from_secs(1);
+
+        for attempt in 1..=self.config.max_retries {
+            info!(
+                "Fetching anime {} (attempt {}/{})",
+                anidb_id, attempt, self.config.max_retries
+            );
+
+            let wait = self.rate_limiter.reserve();
+            if !wait.is_zero() {
+                debug!("Rate limiting: waiting {:?}", wait);
+                tokio::time::sleep(wait).await;
+            }
+
+            match self.fetch_anime_internal(anidb_id).await {
+                Ok(info) => {
+                    info!(
+                        "Successfully fetched anime {}: {} ({})",
+                        anidb_id,
+                        info.title_main,
+                        episodes_text(info.episode_count)
+                    );
+                    return Ok(info);
+                }
+                Err(e) => {
+                    warn!("Attempt {} failed: {}", attempt, e);
+                    if !is_retryable(&e) {
+                        return Err(e);
+                    }
+                    last_error = Some(e);
+
+                    if attempt < self.config.max_retries {
+                        debug!("Waiting {:?} before retry", delay);
+                        tokio::time::sleep(delay).await;
+                        delay *= 2; // Exponential backoff
+                    }
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(ApiError::MaxRetriesExceeded {
+            attempts: self.config.max_retries,
+        }))
+    }
+
+    async fn fetch_anime_internal(&self, anidb_id: u32) -> Result<AnimeInfo, ApiError> {
+        let url = request_url(&self.config, anidb_id);
+        debug!("Requesting: {}", url);
+
+        let response = self.client.get(&url).send().await?;
+        let status = response.status();
+        debug!("Response status: {}", status);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ApiError::RateLimited);
+        }
+
+        let body = response.text().await?;
+        parse_response(anidb_id, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Instant;
+
+    const BEBOP: &str = r#"<anime id="1"><titles><title xml:lang="x-jat" type="main">Cowboy Bebop</title></titles><startdate>1998-04-03</startdate></anime>"#;
+
+    /// A local HTTP server answering each connection with the next of
+    /// `responses` (status, body), recording when each request arrived
+    fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<Instant>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/httpapi", listener.local_addr().unwrap());
+        let arrivals = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&arrivals);
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line != "\r\n" {
+                    line.clear();
+                }
+                recorded.lock().unwrap().push(Instant::now());
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        (url, arrivals)
+    }
+
+    fn config(base_url: String) -> ApiConfig {
+        ApiConfig {
+            base_url,
+            min_request_interval_secs: 0,
+            ..ApiConfig::new("testclient", 1)
+        }
+    }
+
+    #[test]
+    fn test_client_requires_config() {
+        let result = AniDbClientAsync::new(ApiConfig::default());
+        assert!(matches!(result, Err(ApiError::NotConfigured)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_anime() {
+        let (url, arrivals) = mock_server(vec![(200, BEBOP)]);
+        let client = AniDbClientAsync::new(config(url)).unwrap();
+
+        let info = client.fetch_anime(1).await.unwrap();
+        assert_eq!(info.title_main, "Cowboy Bebop");
+        assert_eq!(info.release_year, Some(1998));
+        assert_eq!(arrivals.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_anime_retries() {
+        let (url, arrivals) = mock_server(vec![
+            (429, ""),
+            (200, "<error>Temporary failure</error>"),
+            (200, BEBOP),
+        ]);
+        let client = AniDbClientAsync::new(config(url)).unwrap();
+
+        let start = Instant::now();
+        let info = client.fetch_anime(1).await.unwrap();
+        assert_eq!(info.title_main, "Cowboy Bebop");
+        assert_eq!(arrivals.lock().unwrap().len(), 3);
+        // Backoff of 1 s, then 2 s
+        assert!(start.elapsed() >= Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_anime_does_not_retry_final_errors() {
+        let (url, arrivals) =
+            mock_server(vec![(200, "<error>Anime not found</error>"), (200, BEBOP)]);
+        let client = AniDbClientAsync::new(config(url)).unwrap();
+
+        assert!(matches!(
+            client.fetch_anime(99999).await,
+            Err(ApiError::NotFound(99999))
+        ));
+        assert_eq!(arrivals.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_anime_rate_limited() {
+        let (url, arrivals) = mock_server(vec![(200, BEBOP), (200, BEBOP), (200, BEBOP)]);
+        let client = AniDbClientAsync::new(ApiConfig {
+            min_request_interval_secs: 1,
+            ..config(url)
+        })
+        .unwrap();
+
+        // Concurrent fetches still go out one interval apart
+        let (a, b, c) = tokio::join!(
+            client.fetch_anime(1),
+            client.fetch_anime(1),
+            client.fetch_anime(1)
+        );
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        let arrivals = arrivals.lock().unwrap();
+        assert_eq!(arrivals.len(), 3);
+        let mut arrivals = arrivals.clone();
+        arrivals.sort();
+        for pair in arrivals.windows(2) {
+            // Allow for the time between claiming a slot and the request arriving
+            assert!(
+                pair[1] - pair[0] >= Duration::from_millis(900),
+                "{:?}",
+                pair
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use super::types::{AnimeInfo, ApiConfig, ApiError};
+use super::types::{AnimeInfo, AnimeType, ApiConfig, ApiError};
+use chrono::{NaiveDate, Utc};
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use reqwest::blocking::Client;
@@ -6,25 +7,40 @@ use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
-const API_BASE_URL: &str = "http://api.anidb.net:9001/httpapi";
 const PROTOCOL_VERSION: u32 = 1;
 
+/// Time of the last request made by any client of this process
+///
+/// AniDB limits requests per client address, so the interval holds across
+/// clients: a run planned in chunks or a watch batch creates a new one.
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
 /// Rate limiter to ensure we don't exceed AniDB's request limits
-struct RateLimiter {
-    last_request: Mutex<Option<Instant>>,
+pub(super) struct RateLimiter {
     min_interval: Duration,
 }
 
 impl RateLimiter {
-    fn new(min_interval: Duration) -> Self {
-        Self {
-            last_request: Mutex::new(None),
-            min_interval,
-        }
+    pub(super) fn new(min_interval: Duration) -> Self {
+        Self { min_interval }
+    }
+
+    /// Claim the next request slot, returning how long to wait for it
+    ///
+    /// For callers that cannot block while holding the lock: the slot is
+    /// recorded at once, so concurrent callers queue up one interval apart.
+    #[cfg(feature = "async")]
+    #[allow(dead_code)] // Used by AniDbClientAsync, not by the binary
+    pub(super) fn reserve(&self) -> Duration {
+        let mut last = LAST_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let slot = last.map_or(now, |last_time| (last_time + self.min_interval).max(now));
+        *last = Some(slot);
+        slot - now
     }
 
     fn wait_if_needed(&self) {
-        let mut last = self.last_request.lock().unwrap();
+        let mut last = LAST_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
 
         if let Some(last_time) = *last {
             let elapsed = last_time.elapsed();
@@ -39,6 +55,9 @@ impl RateLimiter {
     }
 }
 
+/// Long-standing AniDB ID requested by [`AniDbClient::check`] (Cowboy Bebop)
+const CHECK_ANIME_ID: u32 = 1;
+
 /// AniDB HTTP API client
 pub struct AniDbClient {
     client: Client,
@@ -69,6 +88,14 @@ impl AniDbClient {
         })
     }
 
+    /// Verify with a single request that AniDB accepts this client
+    ///
+    /// Fails with the same errors as a real fetch (e.g. `Banned` for rejected
+    /// credentials), so problems surface before any directory is touched.
+    pub fn check(&self) -> Result<(), ApiError> {
+        self.fetch_anime(CHECK_ANIME_ID).map(|_| ())
+    }
+
     /// Fetch anime information by AniDB ID with retry logic
     pub fn fetch_anime(&self, anidb_id: u32) -> Result<AnimeInfo, ApiError> {
         let mut last_error = None;
@@ -85,22 +112,17 @@ impl AniDbClient {
             match self.fetch_anime_internal(anidb_id) {
                 Ok(info) => {
                     info!(
-                        "Successfully fetched anime {}: {}",
-                        anidb_id, info.title_main
+                        "Successfully fetched anime {}: {} ({})",
+                        anidb_id,
+                        info.title_main,
+                        episodes_text(info.episode_count)
                     );
                     return Ok(info);
                 }
                 Err(e) => {
                     warn!("Attempt {} failed: {}", attempt, e);
 
-                    // Don't retry for certain errors
-                    if matches!(
-                        e,
-                        ApiError::NotFound(_)
-                            | ApiError::Banned(_)
-                            | ApiError::NotConfigured
-                            | ApiError::IncompleteData { .. }
-                    ) {
+                    if !is_retryable(&e) {
                         return Err(e);
                     }
 
@@ -121,15 +143,7 @@ impl AniDbClient {
     }
 
     fn fetch_anime_internal(&self, anidb_id: u32) -> Result<AnimeInfo, ApiError> {
-        let url = format!(
-            "{}?request=anime&client={}&clientver={}&protover={}&aid={}",
-            API_BASE_URL,
-            self.config.client_name,
-            self.config.client_version,
-            PROTOCOL_VERSION,
-            anidb_id
-        );
-
+        let url = request_url(&self.config, anidb_id);
         debug!("Requesting: {}", url);
 
         let response = self.client.get(&url).send()?;
@@ -142,151 +156,260 @@ impl AniDbClient {
         }
 
         let body = response.text()?;
+        parse_response(anidb_id, &body)
+    }
+}
 
-        // Check for error responses
-        if body.contains("<error>") {
-            return self.parse_error_response(&body, anidb_id);
-        }
+/// Whether a failed fetch is worth another attempt
+pub(super) fn is_retryable(error: &ApiError) -> bool {
+    !matches!(
+        error,
+        ApiError::NotFound(_)
+            | ApiError::Banned(_)
+            | ApiError::NotConfigured
+            | ApiError::IncompleteData { .. }
+    )
+}
 
-        self.parse_anime_xml(anidb_id, &body)
+/// URL of the anime request for `anidb_id`
+pub(super) fn request_url(config: &ApiConfig, anidb_id: u32) -> String {
+    format!(
+        "{}?request=anime&client={}&clientver={}&protover={}&aid={}",
+        config.base_url, config.client_name, config.client_version, PROTOCOL_VERSION, anidb_id
+    )
+}
+
+/// Anime information from a response body, or the error it reports
+pub(super) fn parse_response(anidb_id: u32, body: &str) -> Result<AnimeInfo, ApiError> {
+    if body.contains("<error>") {
+        return parse_error_response(body, anidb_id);
     }
+    parse_anime_xml(anidb_id, body)
+}
 
-    fn parse_error_response(&self, body: &str, anidb_id: u32) -> Result<AnimeInfo, ApiError> {
-        // Extract error message from XML
-        if let Some(start) = body.find("<error>") {
-            if let Some(end) = body.find("</error>") {
-                let error_msg = &body[start + 7..end];
-                let error_lower = error_msg.to_lowercase();
+fn parse_error_response(body: &str, anidb_id: u32) -> Result<AnimeInfo, ApiError> {
+    // Extract error message from XML
+    if let Some(start) = body.find("<error>") {
+        if let Some(end) = body.find("</error>") {
+            let error_msg = &body[start + 7..end];
+            let error_lower = error_msg.to_lowercase();
 
-                if error_lower.contains("anime not found") || error_lower.contains("no such anime")
-                {
-                    return Err(ApiError::NotFound(anidb_id));
-                }
-
-                if error_lower.contains("banned") || error_lower.contains("client") {
-                    return Err(ApiError::Banned(error_msg.to_string()));
-                }
+            if error_lower.contains("anime not found") || error_lower.contains("no such anime") {
+                return Err(ApiError::NotFound(anidb_id));
+            }
 
-                return Err(ApiError::ServerError(error_msg.to_string()));
+            if error_lower.contains("banned") || error_lower.contains("client") {
+                return Err(ApiError::Banned(error_msg.to_string()));
             }
+
+            return Err(ApiError::ServerError(error_msg.to_string()));
         }
+    }
+
+    Err(ApiError::ServerError(body.to_string()))
+}
 
-        Err(ApiError::ServerError(body.to_string()))
-    }
-
-    fn parse_anime_xml(&self, anidb_id: u32, xml: &str) -> Result<AnimeInfo, ApiError> {
-        let mut reader = Reader::from_str(xml);
-        reader.config_mut().trim_text(true);
-
-        let mut title_main: Option<String> = None;
-        let mut title_en: Option<String> = None;
-        let mut release_year: Option<u16> = None;
-
-        let mut buf = Vec::new();
-        let mut in_titles = false;
-        let mut in_startdate = false;
-        let mut current_title_type: Option<String> = None;
-        let mut current_title_lang: Option<String> = None;
-
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
-                    let name = e.name();
-                    match name.as_ref() {
-                        b"titles" => in_titles = true,
-                        b"title" if in_titles => {
-                            current_title_type = None;
-                            current_title_lang = None;
-
-                            for attr in e.attributes().flatten() {
-                                match attr.key.as_ref() {
-                                    b"type" => {
-                                        current_title_type =
-                                            Some(String::from_utf8_lossy(&attr.value).to_string());
-                                    }
-                                    b"xml:lang" => {
-                                        current_title_lang =
-                                            Some(String::from_utf8_lossy(&attr.value).to_string());
-                                    }
-                                    _ => {}
+fn parse_anime_xml(anidb_id: u32, xml: &str) -> Result<AnimeInfo, ApiError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut title_main: Option<String> = None;
+    let mut title_en: Option<String> = None;
+    let mut title_other: Option<String> = None;
+    let mut restricted = false;
+    let mut release_year: Option<u16> = None;
+    let mut start_date: Option<String> = None;
+    let mut anime_type: Option<AnimeType> = None;
+    let mut episode_count: Option<u16> = None;
+
+    let mut buf = Vec::new();
+    let mut in_titles = false;
+    let mut in_startdate = false;
+    let mut in_type = false;
+    let mut in_episodecount = false;
+    let mut current_title_type: Option<String> = None;
+    let mut current_title_lang: Option<String> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                let name = e.name();
+                match name.as_ref() {
+                    b"anime" => {
+                        restricted = e.attributes().flatten().any(|attr| {
+                            attr.key.as_ref() == b"restricted" && attr.value.as_ref() == b"true"
+                        });
+                    }
+                    b"titles" => in_titles = true,
+                    b"title" if in_titles => {
+                        current_title_type = None;
+                        current_title_lang = None;
+
+                        for attr in e.attributes().flatten() {
+                            match attr.key.as_ref() {
+                                b"type" => {
+                                    current_title_type =
+                                        Some(String::from_utf8_lossy(&attr.value).to_string());
+                                }
+                                b"xml:lang" => {
+                                    current_title_lang =
+                                        Some(String::from_utf8_lossy(&attr.value).to_string());
                                 }
+                                _ => {}
                             }
                         }
-                        b"startdate" => in_startdate = true,
-                        _ => {}
                     }
+                    b"startdate" => in_startdate = true,
+                    b"type" => in_type = true,
+                    b"episodecount" => in_episodecount = true,
+                    _ => {}
                 }
-                Ok(Event::Text(ref e)) => {
-                    let text = e.unescape().unwrap_or_default().to_string();
-
-                    if in_startdate && !text.is_empty() {
-                        // Parse year from startdate (format: YYYY-MM-DD or YYYY)
-                        if let Some(year_str) = text.split('-').next() {
-                            if let Ok(year) = year_str.parse::<u16>() {
-                                release_year = Some(year);
-                            }
+            }
+            Ok(Event::Text(ref e)) => {
+                let text = e.unescape().unwrap_or_default().to_string();
+
+                if in_startdate && !text.is_empty() {
+                    // Parse year from startdate (format: YYYY-MM-DD or YYYY)
+                    if let Some(year_str) = text.split('-').next() {
+                        if let Ok(year) = year_str.parse::<u16>() {
+                            release_year = Some(year);
                         }
-                        in_startdate = false;
                     }
+                    start_date = Some(text.clone());
+                    in_startdate = false;
+                }
+
+                if in_type && !text.is_empty() {
+                    anime_type = AnimeType::from_anidb(&text);
+                    if anime_type.is_none() {
+                        debug!("Unknown type {:?} for AniDB ID {}", text, anidb_id);
+                    }
+                    in_type = false;
+                }
 
-                    if in_titles {
-                        if let (Some(ref t_type), Some(ref t_lang)) =
-                            (&current_title_type, &current_title_lang)
+                // 0 while AniDB does not know the count yet
+                if in_episodecount && !text.is_empty() {
+                    episode_count = text.trim().parse().ok().filter(|&n| n > 0);
+                    in_episodecount = false;
+                }
+
+                if in_titles {
+                    if let (Some(ref t_type), Some(ref t_lang)) =
+                        (&current_title_type, &current_title_lang)
+                    {
+                        // Main title (romaji) or fallback to official romaji
+                        if t_type == "main"
+                            || (t_type == "official" && t_lang == "x-jat" && title_main.is_none())
                         {
-                            // Main title (romaji) or fallback to official romaji
-                            if t_type == "main"
-                                || (t_type == "official"
-                                    && t_lang == "x-jat"
-                                    && title_main.is_none())
-                            {
-                                title_main = Some(text.clone());
-                            }
-                            // English title
-                            else if t_type == "official" && t_lang == "en" {
-                                title_en = Some(text.clone());
-                            }
+                            title_main = Some(text.clone());
+                        }
+                        // English title
+                        else if t_type == "official" && t_lang == "en" {
+                            title_en = Some(text.clone());
+                        }
+                        // Any other title, the last resort for a primary title
+                        else if title_other.is_none() {
+                            title_other = Some(text.clone());
                         }
                     }
                 }
-                Ok(Event::End(ref e)) => match e.name().as_ref() {
-                    b"titles" => in_titles = false,
-                    b"title" => {
-                        current_title_type = None;
-                        current_title_lang = None;
-                    }
-                    b"startdate" => in_startdate = false,
-                    _ => {}
-                },
-                Ok(Event::Eof) => break,
-                Err(e) => {
-                    return Err(ApiError::ParseError(format!(
-                        "XML parse error at position {}: {}",
-                        reader.buffer_position(),
-                        e
-                    )));
+            }
+            Ok(Event::End(ref e)) => match e.name().as_ref() {
+                b"titles" => in_titles = false,
+                b"title" => {
+                    current_title_type = None;
+                    current_title_lang = None;
                 }
+                b"startdate" => in_startdate = false,
+                b"type" => in_type = false,
+                b"episodecount" => in_episodecount = false,
                 _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                return Err(ApiError::ParseError(format!(
+                    "XML parse error at position {}: {}",
+                    reader.buffer_position(),
+                    e
+                )));
             }
-            buf.clear();
+            _ => {}
         }
+        buf.clear();
+    }
 
-        let title_main = title_main.ok_or_else(|| ApiError::IncompleteData {
-            anidb_id,
-            field: "main title".to_string(),
-        })?;
+    // Some entries only carry an English official title; use it as primary
+    let (title_main, title_en, english_fallback) = match (title_main, title_en, title_other) {
+        (Some(main), en, _) => (main, en, false),
+        (None, Some(en), _) => {
+            warn!(
+                "No main title for AniDB ID {}, using English title",
+                anidb_id
+            );
+            (en, None, true)
+        }
+        (None, None, Some(other)) => {
+            warn!(
+                "No main or English title for AniDB ID {}, using {}",
+                anidb_id, other
+            );
+            (other, None, false)
+        }
+        (None, None, None) => {
+            return Err(ApiError::IncompleteData {
+                anidb_id,
+                field: "title".to_string(),
+            })
+        }
+    };
 
-        Ok(AnimeInfo {
-            anidb_id,
-            title_main,
-            title_en,
-            release_year,
-        })
+    let today = Utc::now().date_naive();
+    let unaired = start_date.is_none_or(|date| starts_after(&date, today));
+    if unaired {
+        debug!("AniDB ID {} has not aired yet", anidb_id);
+    }
+
+    Ok(AnimeInfo {
+        anidb_id,
+        title_main,
+        title_en,
+        release_year,
+        english_fallback,
+        restricted,
+        unaired,
+        anime_type,
+        episode_count,
+    })
+}
+
+/// Episode count for logs, e.g. `26 episodes`
+pub(super) fn episodes_text(episode_count: Option<u16>) -> String {
+    match episode_count {
+        Some(1) => "1 episode".to_string(),
+        Some(n) => format!("{} episodes", n),
+        None => "episode count unknown".to_string(),
     }
 }
 
+/// Whether an AniDB start date (`YYYY-MM-DD`, `YYYY-MM` or `YYYY`) is after `today`
+///
+/// Partial dates count from their first day, so an anime announced for the
+/// current year counts as aired. Unparsable dates count as aired.
+fn starts_after(start_date: &str, today: NaiveDate) -> bool {
+    let mut parts = start_date.split('-').map(|p| p.parse::<u32>().ok());
+    let year = parts.next().flatten();
+    let month = parts.next().flatten().unwrap_or(1);
+    let day = parts.next().flatten().unwrap_or(1);
+
+    year.and_then(|y| i32::try_from(y).ok())
+        .and_then(|y| NaiveDate::from_ymd_opt(y, month, day))
+        .is_some_and(|start| start > today)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Datelike;
 
     fn test_config() -> ApiConfig {
         ApiConfig::new("testclient", 1)
@@ -309,22 +432,68 @@ mod tests {
     fn test_parse_anime_xml_full_data() {
         let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
         <anime id="1" restricted="false">
+            <type>TV Series</type>
             <titles>
                 <title xml:lang="x-jat" type="main">Cowboy Bebop</title>
                 <title xml:lang="en" type="official">Cowboy Bebop</title>
                 <title xml:lang="ja" type="official">カウボーイビバップ</title>
             </titles>
+            <episodecount>26</episodecount>
             <startdate>1998-04-03</startdate>
         </anime>"#;
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_anime_xml(1, xml).unwrap();
+        let result = parse_anime_xml(1, xml).unwrap();
 
         assert_eq!(result.anidb_id, 1);
         assert_eq!(result.title_main, "Cowboy Bebop");
         assert_eq!(result.title_en, Some("Cowboy Bebop".to_string()));
         assert_eq!(result.release_year, Some(1998));
+        assert_eq!(result.anime_type, Some(AnimeType::TvSeries));
+        assert_eq!(result.episode_count, Some(26));
+    }
+
+    #[test]
+    fn test_parse_anime_xml_episode_count() {
+        let parse = |element: &str| {
+            let xml = format!(
+                r#"<anime id="5">{}<titles><title xml:lang="x-jat" type="main">Title</title></titles></anime>"#,
+                element
+            );
+            parse_anime_xml(5, &xml).unwrap().episode_count
+        };
+
+        assert_eq!(parse("<episodecount>1</episodecount>"), Some(1));
+        // Not known yet
+        assert_eq!(parse("<episodecount>0</episodecount>"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("<episodecount>many</episodecount>"), None);
+        assert_eq!(episodes_text(Some(26)), "26 episodes");
+        assert_eq!(episodes_text(None), "episode count unknown");
+    }
+
+    #[test]
+    fn test_parse_anime_xml_type() {
+        let parse = |anime_type: &str| {
+            let xml = format!(
+                r#"<anime id="5"><type>{}</type><titles><title xml:lang="x-jat" type="main">Kimi no Na wa.</title></titles><startdate>2016-08-26</startdate></anime>"#,
+                anime_type
+            );
+            parse_anime_xml(5, &xml).unwrap().anime_type
+        };
+
+        assert_eq!(parse("Movie"), Some(AnimeType::Movie));
+        assert_eq!(parse("OVA"), Some(AnimeType::Ova));
+        assert_eq!(parse("TV Special"), Some(AnimeType::TvSpecial));
+        assert_eq!(parse("Music Video"), Some(AnimeType::MusicVideo));
+        assert_eq!(parse("Unknown"), None);
+        // Entries without a type element
+        assert_eq!(parse(""), None);
+        let untyped = r#"<anime id="5"><titles><title xml:lang="x-jat" type="main">Kimi no Na wa.</title></titles></anime>"#;
+        assert_eq!(parse_anime_xml(5, untyped).unwrap().anime_type, None);
+
+        for anime_type in AnimeType::ALL {
+            assert_eq!(parse(anime_type.label()), Some(anime_type));
+        }
     }
 
     #[test]
@@ -336,9 +505,7 @@ mod tests {
             </titles>
         </anime>"#;
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_anime_xml(2, xml).unwrap();
+        let result = parse_anime_xml(2, xml).unwrap();
 
         assert_eq!(result.title_main, "Some Japanese Title");
         assert!(result.title_en.is_none());
@@ -355,9 +522,7 @@ mod tests {
             <startdate>2020</startdate>
         </anime>"#;
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_anime_xml(3, xml).unwrap();
+        let result = parse_anime_xml(3, xml).unwrap();
 
         assert_eq!(result.release_year, Some(2020));
     }
@@ -372,12 +537,90 @@ mod tests {
             </titles>
         </anime>"#;
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_anime_xml(4, xml).unwrap();
+        let result = parse_anime_xml(4, xml).unwrap();
 
         assert_eq!(result.title_main, "Fallback Romaji Title");
         assert_eq!(result.title_en, Some("English Title".to_string()));
+        assert!(!result.english_fallback);
+    }
+
+    #[test]
+    fn test_parse_anime_xml_restricted() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <anime id="8" restricted="true">
+            <titles>
+                <title xml:lang="x-jat" type="main">Restricted Title</title>
+            </titles>
+        </anime>"#;
+
+        assert!(parse_anime_xml(8, xml).unwrap().restricted);
+
+        let unrestricted = xml.replace("restricted=\"true\"", "restricted=\"false\"");
+        assert!(!parse_anime_xml(8, &unrestricted).unwrap().restricted);
+    }
+
+    #[test]
+    fn test_parse_anime_xml_unaired() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <anime id="18999">
+            <titles>
+                <title xml:lang="x-jat" type="main">Next Season</title>
+            </titles>
+            STARTDATE
+        </anime>"#;
+        let parse =
+            |startdate: &str| parse_anime_xml(18999, &xml.replace("STARTDATE", startdate)).unwrap();
+
+        assert!(parse("").unaired);
+        let next_year = Utc::now().year() + 1;
+        let future = parse(&format!("<startdate>{}-04-01</startdate>", next_year));
+        assert!(future.unaired);
+        assert_eq!(future.release_year, Some(next_year as u16));
+        assert!(!parse("<startdate>1998-04-03</startdate>").unaired);
+    }
+
+    #[test]
+    fn test_starts_after() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        assert!(starts_after("2026-10-19", today));
+        assert!(starts_after("2026-11", today));
+        assert!(starts_after("2027", today));
+        assert!(!starts_after("2026-10-18", today));
+        assert!(!starts_after("2026", today));
+        assert!(!starts_after("unknown", today));
+    }
+
+    #[test]
+    fn test_parse_anime_xml_english_only() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <anime id="6">
+            <titles>
+                <title xml:lang="en" type="official">English Only</title>
+            </titles>
+        </anime>"#;
+
+        let result = parse_anime_xml(6, xml).unwrap();
+
+        assert_eq!(result.title_main, "English Only");
+        assert!(result.title_en.is_none());
+        assert!(result.english_fallback);
+    }
+
+    #[test]
+    fn test_parse_anime_xml_japanese_only() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <anime id="7">
+            <titles>
+                <title xml:lang="ja" type="official">日本語のタイトル</title>
+            </titles>
+        </anime>"#;
+
+        let result = parse_anime_xml(7, xml).unwrap();
+
+        assert_eq!(result.title_main, "日本語のタイトル");
+        assert!(result.title_en.is_none());
+        assert!(!result.english_fallback);
     }
 
     #[test]
@@ -388,9 +631,7 @@ mod tests {
             </titles>
         </anime>"#;
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_anime_xml(5, xml);
+        let result = parse_anime_xml(5, xml);
 
         assert!(matches!(
             result,
@@ -402,9 +643,7 @@ mod tests {
     fn test_parse_error_response_not_found() {
         let body = "<error>Anime not found</error>";
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_error_response(body, 99999);
+        let result = parse_error_response(body, 99999);
 
         assert!(matches!(result, Err(ApiError::NotFound(99999))));
     }
@@ -413,9 +652,7 @@ mod tests {
     fn test_parse_error_response_banned() {
         let body = "<error>Banned: Client version outdated</error>";
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_error_response(body, 1);
+        let result = parse_error_response(body, 1);
 
         assert!(matches!(result, Err(ApiError::Banned(_))));
     }
@@ -424,9 +661,7 @@ mod tests {
     fn test_parse_error_response_generic() {
         let body = "<error>Unknown error occurred</error>";
 
-        let config = test_config();
-        let client = AniDbClient::new(config).unwrap();
-        let result = client.parse_error_response(body, 1);
+        let result = parse_error_response(body, 1);
 
         assert!(matches!(result, Err(ApiError::ServerError(_))));
     }
@@ -443,4 +678,13 @@ mod tests {
         // Second call should have waited at least 100ms
         assert!(elapsed >= Duration::from_millis(100));
     }
+
+    #[test]
+    fn test_rate_limit_holds_across_clients() {
+        let start = Instant::now();
+        RateLimiter::new(Duration::from_millis(100)).wait_if_needed();
+        RateLimiter::new(Duration::from_millis(100)).wait_if_needed();
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
 }
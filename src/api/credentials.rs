@@ -0,0 +1,272 @@
+//! AniDB client credentials from a file.
+//!
+//! A credentials file keeps the client name out of a `.env` that may sit,
+//! world-readable, in a shared media directory. It is a small INI-style file:
+//!
+//! ```text
+//! # anidb2folder credentials
+//! client = myclient
+//! client_version = 1
+//! ```
+//!
+//! Blank lines, `#`/`;` comments and `[section]` headers are ignored, and
+//! values may be quoted. On Unix a file readable by group or others is
+//! refused.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use super::types::ApiConfig;
+use super::{config_from_env, ENV_ANIDB_CLIENT, ENV_ANIDB_CLIENT_VERSION};
+
+/// Errors reading a credentials file
+#[derive(Error, Debug)]
+pub enum CredentialsError {
+    #[error("Cannot read credentials file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(
+        "Credentials file {path:?} is readable by other users (mode {mode:03o}); \
+         restrict it with: chmod 600 {}",
+        path.display()
+    )]
+    Insecure { path: PathBuf, mode: u32 },
+
+    #[error("Credentials file {path:?}, line {line}: {message}")]
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+/// Client name and version read from a credentials file; either may be absent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Credentials {
+    client: Option<String>,
+    client_version: Option<u32>,
+}
+
+/// Resolve the API configuration
+///
+/// Each setting comes from the first source that has it:
+///
+/// 1. `credentials_file` (`--credentials-file`), when given
+/// 2. the `ANIDB_CLIENT` and `ANIDB_CLIENT_VERSION` environment variables,
+///    which a `.env` file in the working directory can set
+/// 3. the defaults: no client name, version 1
+pub fn resolve_api_config(credentials_file: Option<&Path>) -> Result<ApiConfig, CredentialsError> {
+    let mut config = config_from_env();
+
+    if let Some(path) = credentials_file {
+        let credentials = read_credentials_file(path)?;
+        if let Some(client) = credentials.client {
+            if std::env::var_os(ENV_ANIDB_CLIENT).is_some() {
+                tracing::debug!("{} overridden by {:?}", ENV_ANIDB_CLIENT, path);
+            }
+            config.client_name = client;
+        }
+        if let Some(version) = credentials.client_version {
+            if std::env::var_os(ENV_ANIDB_CLIENT_VERSION).is_some() {
+                tracing::debug!("{} overridden by {:?}", ENV_ANIDB_CLIENT_VERSION, path);
+            }
+            config.client_version = version;
+        }
+    }
+
+    Ok(config)
+}
+
+/// Read a credentials file, refusing one other users can read
+fn read_credentials_file(path: &Path) -> Result<Credentials, CredentialsError> {
+    let read_error = |source| CredentialsError::Read {
+        path: path.to_path_buf(),
+        source,
+    };
+    check_permissions(path).map_err(|e| match e {
+        PermissionCheck::Io(source) => read_error(source),
+        PermissionCheck::Insecure(mode) => CredentialsError::Insecure {
+            path: path.to_path_buf(),
+            mode,
+        },
+    })?;
+
+    let text = fs::read_to_string(path).map_err(read_error)?;
+    parse_credentials(&text).map_err(|(line, message)| CredentialsError::Invalid {
+        path: path.to_path_buf(),
+        line,
+        message,
+    })
+}
+
+/// Parse credentials text; errors carry the 1-based line number
+fn parse_credentials(text: &str) -> Result<Credentials, (usize, String)> {
+    let mut credentials = Credentials::default();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) || line.starts_with('[') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| (i + 1, "expected key = value".to_string()))?;
+        let value = unquote(value.trim());
+
+        match key.trim() {
+            "client" => credentials.client = Some(value.to_string()),
+            "client_version" => {
+                let version = value
+                    .parse()
+                    .map_err(|_| (i + 1, format!("client_version '{}' is not a number", value)))?;
+                credentials.client_version = Some(version);
+            }
+            other => return Err((i + 1, format!("unknown key '{}'", other))),
+        }
+    }
+
+    Ok(credentials)
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+enum PermissionCheck {
+    Io(std::io::Error),
+    Insecure(u32),
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<(), PermissionCheck> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)
+        .map_err(PermissionCheck::Io)?
+        .permissions()
+        .mode()
+        & 0o777;
+    if mode & 0o044 != 0 {
+        return Err(PermissionCheck::Insecure(mode));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(path: &Path) -> Result<(), PermissionCheck> {
+    fs::metadata(path).map(|_| ()).map_err(PermissionCheck::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::tests::ENV_TEST_MUTEX;
+    use std::env;
+    use tempfile::tempdir;
+
+    fn write_private(dir: &Path, text: &str) -> PathBuf {
+        let path = dir.join("credentials");
+        fs::write(&path, text).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        let text = "# comment\n[anidb]\nclient = \"myclient\"\n; other comment\nclient_version=3\n";
+        assert_eq!(
+            parse_credentials(text).unwrap(),
+            Credentials {
+                client: Some("myclient".to_string()),
+                client_version: Some(3),
+            }
+        );
+
+        assert_eq!(parse_credentials("client myclient").unwrap_err().0, 1);
+        assert!(parse_credentials("\nclient_version = x")
+            .unwrap_err()
+            .1
+            .contains("not a number"));
+        assert!(parse_credentials("password = x")
+            .unwrap_err()
+            .1
+            .contains("unknown key"));
+    }
+
+    #[test]
+    fn test_file_takes_precedence_over_env() {
+        let _lock = ENV_TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let path = write_private(dir.path(), "client = fromfile\n");
+        env::set_var(ENV_ANIDB_CLIENT, "fromenv");
+        env::set_var(ENV_ANIDB_CLIENT_VERSION, "2");
+
+        let from_file = resolve_api_config(Some(&path)).unwrap();
+        let from_env = resolve_api_config(None).unwrap();
+
+        env::remove_var(ENV_ANIDB_CLIENT);
+        env::remove_var(ENV_ANIDB_CLIENT_VERSION);
+
+        // The file sets the name; the version it lacks still comes from the env
+        assert_eq!(from_file.client_name, "fromfile");
+        assert_eq!(from_file.client_version, 2);
+        assert_eq!(from_env.client_name, "fromenv");
+    }
+
+    #[test]
+    fn test_file_only() {
+        let _lock = ENV_TEST_MUTEX.lock().unwrap();
+        env::remove_var(ENV_ANIDB_CLIENT);
+        env::remove_var(ENV_ANIDB_CLIENT_VERSION);
+        let dir = tempdir().unwrap();
+        let path = write_private(dir.path(), "client = fromfile\nclient_version = 4\n");
+
+        let config = resolve_api_config(Some(&path)).unwrap();
+
+        assert_eq!(config.client_name, "fromfile");
+        assert_eq!(config.client_version, 4);
+        assert!(config.is_configured());
+    }
+
+    #[test]
+    fn test_missing_file_is_an_error() {
+        let dir = tempdir().unwrap();
+        let result = resolve_api_config(Some(&dir.path().join("absent")));
+        assert!(matches!(result, Err(CredentialsError::Read { .. })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_readable_by_others_is_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let path = write_private(dir.path(), "client = myclient\n");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let error = read_credentials_file(&path).unwrap_err();
+
+        assert!(matches!(
+            error,
+            CredentialsError::Insecure { mode: 0o644, .. }
+        ));
+        assert!(error.to_string().contains("chmod 600"));
+    }
+}
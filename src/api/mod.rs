@@ -1,8 +1,24 @@
+#[cfg(feature = "async")]
+#[allow(dead_code)] // Library API, not used by the binary
+mod async_client;
+#[cfg(feature = "api")]
 mod client;
+mod credentials;
+#[cfg(feature = "api")]
+mod titles;
 mod types;
 
+#[cfg(feature = "async")]
+#[allow(unused_imports)]
+pub use async_client::AniDbClientAsync;
+#[cfg(feature = "api")]
 pub use client::AniDbClient;
-pub use types::{AnimeInfo, ApiConfig, ApiError};
+pub use credentials::{resolve_api_config, CredentialsError};
+#[cfg(feature = "api")]
+pub use titles::{TitleDump, TitleDumpError};
+#[allow(unused_imports)]
+pub use types::DEFAULT_API_URL;
+pub use types::{AnimeInfo, AnimeType, ApiConfig, ApiError};
 
 use std::env;
 
@@ -16,7 +32,8 @@ pub const ENV_ANIDB_CLIENT_VERSION: &str = "ANIDB_CLIENT_VERSION";
 /// - `ANIDB_CLIENT`: Registered client name (lowercase)
 /// - `ANIDB_CLIENT_VERSION`: Client version number
 ///
-/// These can be set in a `.env` file in the working directory.
+/// These can be set in a `.env` file in the working directory. A
+/// credentials file takes precedence, see [`resolve_api_config`].
 pub fn config_from_env() -> ApiConfig {
     let client_name = env::var(ENV_ANIDB_CLIENT).unwrap_or_default();
     let client_version = env::var(ENV_ANIDB_CLIENT_VERSION)
@@ -28,12 +45,12 @@ pub fn config_from_env() -> ApiConfig {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::sync::Mutex;
 
     // Mutex to serialize env var tests (they share global state)
-    static ENV_TEST_MUTEX: Mutex<()> = Mutex::new(());
+    pub(crate) static ENV_TEST_MUTEX: Mutex<()> = Mutex::new(());
 
     #[test]
     fn test_config_from_env_defaults() {
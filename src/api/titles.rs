@@ -0,0 +1,345 @@
+//! AniDB's anime titles dump (`--titles-file`).
+//!
+//! AniDB publishes every title of every entry once a day as
+//! `anime-titles.xml.gz`. With the dump, readable names are built without
+//! asking the HTTP API: it gives the main and English titles of an id. It
+//! has no start date, type or restricted flag, so names built from it alone
+//! have no year; a cached entry still provides those. The file may be
+//! gzipped or plain XML.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use thiserror::Error;
+use tracing::info;
+
+use super::types::AnimeInfo;
+
+/// Errors reading a titles dump
+#[derive(Error, Debug)]
+pub enum TitleDumpError {
+    #[error("Cannot read titles file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Titles file {path:?} is invalid: {message}")]
+    Invalid { path: PathBuf, message: String },
+}
+
+/// The titles of one entry chosen for folder names
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DumpTitles {
+    main: String,
+    en: Option<String>,
+    /// No main or romaji title exists; `main` is the English title
+    english_fallback: bool,
+}
+
+/// Main and English titles by AniDB ID, read from the titles dump
+#[derive(Debug, Clone, Default)]
+pub struct TitleDump {
+    source: PathBuf,
+    titles: HashMap<u32, DumpTitles>,
+}
+
+impl TitleDump {
+    /// Read a titles dump, gzipped (as published) or not
+    pub fn load(path: &Path) -> Result<Self, TitleDumpError> {
+        let read_error = |source| TitleDumpError::Read {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut reader = BufReader::new(File::open(path).map_err(read_error)?);
+        let gzipped = reader
+            .fill_buf()
+            .map_err(read_error)?
+            .starts_with(&[0x1f, 0x8b]);
+        let mut xml = String::new();
+        if gzipped {
+            GzDecoder::new(reader).read_to_string(&mut xml)
+        } else {
+            reader.read_to_string(&mut xml)
+        }
+        .map_err(read_error)?;
+
+        let mut dump = Self::parse(&xml).map_err(|message| TitleDumpError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        dump.source = path.to_path_buf();
+        info!(
+            "Loaded titles of {} anime from {}",
+            dump.len(),
+            path.display()
+        );
+        Ok(dump)
+    }
+
+    /// Parse the XML of a titles dump
+    ///
+    /// Titles are chosen as from an API response: the main title, else the
+    /// official romaji one, else the English one (flagged as a fallback),
+    /// else any other; the English title is the official `en` one.
+    pub fn parse(xml: &str) -> Result<Self, String> {
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+
+        let mut titles = HashMap::new();
+        let mut entry: Option<EntryTitles> = None;
+        // Type and language of the title element being read
+        let mut title: Option<(String, String)> = None;
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) => match e.name().as_ref() {
+                    b"anime" => {
+                        let aid = e
+                            .attributes()
+                            .flatten()
+                            .find(|attr| attr.key.as_ref() == b"aid")
+                            .and_then(|attr| String::from_utf8_lossy(&attr.value).parse().ok())
+                            .ok_or_else(|| {
+                                format!(
+                                    "anime element without a valid aid at position {}",
+                                    reader.buffer_position()
+                                )
+                            })?;
+                        entry = Some(EntryTitles::new(aid));
+                    }
+                    b"title" => {
+                        let mut title_type = String::new();
+                        let mut lang = String::new();
+                        for attr in e.attributes().flatten() {
+                            let value = String::from_utf8_lossy(&attr.value).to_string();
+                            match attr.key.as_ref() {
+                                b"type" => title_type = value,
+                                b"xml:lang" => lang = value,
+                                _ => {}
+                            }
+                        }
+                        title = Some((title_type, lang));
+                    }
+                    _ => {}
+                },
+                Ok(Event::Text(ref e)) => {
+                    if let (Some(entry), Some((title_type, lang))) = (&mut entry, &title) {
+                        let text = e.unescape().map_err(|e| e.to_string())?;
+                        entry.add(title_type, lang, &text);
+                    }
+                }
+                Ok(Event::End(ref e)) => match e.name().as_ref() {
+                    b"title" => title = None,
+                    b"anime" => {
+                        if let Some((aid, chosen)) = entry.take().and_then(EntryTitles::finish) {
+                            titles.insert(aid, chosen);
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    return Err(format!(
+                        "XML parse error at position {}: {}",
+                        reader.buffer_position(),
+                        e
+                    ))
+                }
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        Ok(Self {
+            source: PathBuf::new(),
+            titles,
+        })
+    }
+
+    /// File the dump was read from
+    #[allow(dead_code)]
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Number of entries with a usable title
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.titles.is_empty()
+    }
+
+    pub fn contains(&self, anidb_id: u32) -> bool {
+        self.titles.contains_key(&anidb_id)
+    }
+
+    /// What the dump knows of `anidb_id`: titles only
+    ///
+    /// The year and type are unknown and the entry counts as aired and not
+    /// restricted.
+    pub fn get(&self, anidb_id: u32) -> Option<AnimeInfo> {
+        self.titles.get(&anidb_id).map(|titles| AnimeInfo {
+            anidb_id,
+            title_main: titles.main.clone(),
+            title_en: titles.en.clone(),
+            release_year: None,
+            english_fallback: titles.english_fallback,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        })
+    }
+}
+
+/// The titles of an `<anime>` element seen so far
+struct EntryTitles {
+    aid: u32,
+    main: Option<String>,
+    official_romaji: Option<String>,
+    en: Option<String>,
+    other: Option<String>,
+}
+
+impl EntryTitles {
+    fn new(aid: u32) -> Self {
+        Self {
+            aid,
+            main: None,
+            official_romaji: None,
+            en: None,
+            other: None,
+        }
+    }
+
+    fn add(&mut self, title_type: &str, lang: &str, text: &str) {
+        let slot = match (title_type, lang) {
+            ("main", _) => &mut self.main,
+            ("official", "x-jat") => &mut self.official_romaji,
+            ("official", "en") => &mut self.en,
+            _ => &mut self.other,
+        };
+        if slot.is_none() && !text.is_empty() {
+            *slot = Some(text.to_string());
+        }
+    }
+
+    /// The id and chosen titles, `None` without any title
+    fn finish(self) -> Option<(u32, DumpTitles)> {
+        let chosen = match (self.main.or(self.official_romaji), self.en, self.other) {
+            (Some(main), en, _) => DumpTitles {
+                main,
+                en,
+                english_fallback: false,
+            },
+            (None, Some(en), _) => DumpTitles {
+                main: en,
+                en: None,
+                english_fallback: true,
+            },
+            (None, None, Some(other)) => DumpTitles {
+                main: other,
+                en: None,
+                english_fallback: false,
+            },
+            (None, None, None) => return None,
+        };
+        Some((self.aid, chosen))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const FIXTURE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/titles/anime-titles.xml"
+    );
+
+    #[test]
+    fn test_load_fixture() {
+        let dump = TitleDump::load(Path::new(FIXTURE)).unwrap();
+        assert_eq!(dump.len(), 5);
+        assert_eq!(dump.source(), Path::new(FIXTURE));
+
+        let info = dump.get(1).unwrap();
+        assert_eq!(info.title_main, "Seikai no Monshou");
+        assert_eq!(info.title_en.as_deref(), Some("Crest of the Stars"));
+        assert_eq!(info.release_year, None);
+        assert!(!info.english_fallback);
+
+        // Synonyms and other languages are not the English title
+        let info = dump.get(9541).unwrap();
+        assert_eq!(info.title_main, "Shingeki no Kyojin");
+        assert_eq!(info.title_en.as_deref(), Some("Attack on Titan"));
+
+        let info = dump.get(6107).unwrap();
+        assert_eq!(info.title_main, "Fate/Zero");
+        assert_eq!(info.title_en, None);
+
+        let info = dump.get(18290).unwrap();
+        assert_eq!(info.title_main, "Heroes & Villains");
+        assert!(info.english_fallback);
+
+        assert!(dump.get(2).is_none());
+        assert!(!dump.contains(2));
+    }
+
+    #[test]
+    fn test_load_gzipped() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("anime-titles.xml.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder.write_all(&std::fs::read(FIXTURE).unwrap()).unwrap();
+        encoder.finish().unwrap();
+
+        let dump = TitleDump::load(&path).unwrap();
+        assert_eq!(dump.len(), 5);
+        assert_eq!(dump.get(23).unwrap().title_main, "Cowboy Bebop");
+    }
+
+    #[test]
+    fn test_official_romaji_stands_in_for_main() {
+        let dump = TitleDump::parse(
+            r#"<animetitles><anime aid="4"><title xml:lang="en" type="official">English Title</title><title xml:lang="x-jat" type="official">Romaji Title</title></anime></animetitles>"#,
+        )
+        .unwrap();
+        let info = dump.get(4).unwrap();
+        assert_eq!(info.title_main, "Romaji Title");
+        assert_eq!(info.title_en.as_deref(), Some("English Title"));
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("titles.xml");
+        std::fs::write(
+            &path,
+            r#"<animetitles><anime aid="x"></anime></animetitles>"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            TitleDump::load(&path),
+            Err(TitleDumpError::Invalid { .. })
+        ));
+        assert!(matches!(
+            TitleDump::load(&dir.path().join("missing.xml")),
+            Err(TitleDumpError::Read { .. })
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Anime information fetched from AniDB
@@ -7,11 +8,98 @@ pub struct AnimeInfo {
     pub title_main: String,
     pub title_en: Option<String>,
     pub release_year: Option<u16>,
+    /// No main or romaji title exists; `title_main` is the English title
+    pub english_fallback: bool,
+    /// Marked `restricted="true"` (adult content) by AniDB
+    pub restricted: bool,
+    /// Not aired yet: AniDB has no start date, or one in the future
+    pub unaired: bool,
+    /// Kind of entry (series, movie, OVA...), when AniDB gives one
+    pub anime_type: Option<AnimeType>,
+    /// Number of episodes, when AniDB knows it
+    pub episode_count: Option<u16>,
 }
 
+/// Kind of an AniDB entry, from its `<type>` element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimeType {
+    TvSeries,
+    Movie,
+    Ova,
+    TvSpecial,
+    Web,
+    MusicVideo,
+    Other,
+}
+
+impl AnimeType {
+    pub const ALL: [AnimeType; 7] = [
+        AnimeType::TvSeries,
+        AnimeType::Movie,
+        AnimeType::Ova,
+        AnimeType::TvSpecial,
+        AnimeType::Web,
+        AnimeType::MusicVideo,
+        AnimeType::Other,
+    ];
+
+    /// Parse AniDB's `<type>` text, `None` for values it does not know
+    pub fn from_anidb(text: &str) -> Option<Self> {
+        match text.trim() {
+            "TV Series" => Some(AnimeType::TvSeries),
+            "Movie" => Some(AnimeType::Movie),
+            "OVA" => Some(AnimeType::Ova),
+            "TV Special" => Some(AnimeType::TvSpecial),
+            "Web" => Some(AnimeType::Web),
+            "Music Video" => Some(AnimeType::MusicVideo),
+            "Other" => Some(AnimeType::Other),
+            _ => None,
+        }
+    }
+
+    /// AniDB's text for the type, e.g. `TV Special`
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnimeType::TvSeries => "TV Series",
+            AnimeType::Movie => "Movie",
+            AnimeType::Ova => "OVA",
+            AnimeType::TvSpecial => "TV Special",
+            AnimeType::Web => "Web",
+            AnimeType::MusicVideo => "Music Video",
+            AnimeType::Other => "Other",
+        }
+    }
+
+    /// Name used on the command line, e.g. `tv-special`
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimeType::TvSeries => "tv-series",
+            AnimeType::Movie => "movie",
+            AnimeType::Ova => "ova",
+            AnimeType::TvSpecial => "tv-special",
+            AnimeType::Web => "web",
+            AnimeType::MusicVideo => "music-video",
+            AnimeType::Other => "other",
+        }
+    }
+
+    /// Inverse of [`AnimeType::name`], ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Address of the AniDB HTTP API
+pub const DEFAULT_API_URL: &str = "http://api.anidb.net:9001/httpapi";
+
 /// API client configuration
 #[derive(Debug, Clone)]
 pub struct ApiConfig {
+    /// Address requests go to, [`DEFAULT_API_URL`] unless testing
+    pub base_url: String,
     pub client_name: String,
     pub client_version: u32,
     pub timeout_secs: u64,
@@ -22,6 +110,7 @@ pub struct ApiConfig {
 impl Default for ApiConfig {
     fn default() -> Self {
         Self {
+            base_url: DEFAULT_API_URL.to_string(),
             client_name: String::new(),
             client_version: 1,
             timeout_secs: 30,
@@ -72,13 +161,14 @@ pub enum ApiError {
     #[error("Max retries exceeded after {attempts} attempts")]
     MaxRetriesExceeded { attempts: u32 },
 
-    #[error("Client not configured: ANIDB_CLIENT and ANIDB_CLIENT_VERSION must be set")]
+    #[error("Client not configured: set ANIDB_CLIENT and ANIDB_CLIENT_VERSION or use --credentials-file")]
     NotConfigured,
 
     #[error("Banned by AniDB: {0}")]
     Banned(String),
 }
 
+#[cfg(feature = "api")]
 impl From<reqwest::Error> for ApiError {
     fn from(err: reqwest::Error) -> Self {
         if err.is_timeout() {
@@ -100,6 +190,11 @@ mod tests {
             title_main: "Cowboy Bebop".to_string(),
             title_en: Some("Cowboy Bebop".to_string()),
             release_year: Some(1998),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         };
 
         assert_eq!(info.anidb_id, 1);
@@ -115,6 +210,11 @@ mod tests {
             title_main: "Some Anime".to_string(),
             title_en: None,
             release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         };
 
         assert!(info.title_en.is_none());
@@ -125,6 +225,7 @@ mod tests {
     fn test_api_config_default() {
         let config = ApiConfig::default();
 
+        assert_eq!(config.base_url, DEFAULT_API_URL);
         assert!(config.client_name.is_empty());
         assert_eq!(config.client_version, 1);
         assert_eq!(config.timeout_secs, 30);
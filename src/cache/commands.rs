@@ -0,0 +1,198 @@
+//! Cache maintenance (`--cache-info`, `--cache-clear`, `--cache-prune`).
+//!
+//! Each command returns what it found or did and prints nothing, so the
+//! binary can show it as text or JSON and library users get the data. A
+//! missing cache file is not an error: there is nothing to report or do.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::store::CacheStore;
+use super::types::{CacheConfig, CacheError, TitleChange};
+
+/// State of the cache of a directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheInfo {
+    pub path: PathBuf,
+    pub exists: bool,
+    pub entries: usize,
+    pub expired: usize,
+    pub expiry_days: u32,
+    /// Size of the cache file in bytes
+    pub size_bytes: u64,
+    /// Recorded upstream title changes, by AniDB ID
+    pub title_changes: BTreeMap<u32, Vec<TitleChange>>,
+}
+
+impl CacheInfo {
+    /// Entries not expired yet
+    pub fn valid(&self) -> usize {
+        self.entries - self.expired
+    }
+
+    /// Number of recorded title changes
+    pub fn title_change_count(&self) -> usize {
+        self.title_changes.values().map(Vec::len).sum()
+    }
+}
+
+/// Outcome of clearing or pruning a cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheCleanup {
+    pub path: PathBuf,
+    /// Whether there was a cache file; without one nothing was done
+    pub exists: bool,
+    /// Entries before and after
+    pub before: usize,
+    pub after: usize,
+    /// Entries removed
+    pub removed: usize,
+    /// How much smaller the cache file got
+    pub bytes_freed: u64,
+}
+
+impl CacheCleanup {
+    fn missing(path: PathBuf) -> Self {
+        Self {
+            path,
+            exists: false,
+            before: 0,
+            after: 0,
+            removed: 0,
+            bytes_freed: 0,
+        }
+    }
+}
+
+/// Describe the cache of `dir`
+pub fn cache_info(dir: &Path, expiry_days: u32) -> CacheInfo {
+    let config = CacheConfig::for_target_dir(dir, expiry_days);
+    let path = config.cache_path.clone();
+    if !path.exists() {
+        return CacheInfo {
+            path,
+            exists: false,
+            entries: 0,
+            expired: 0,
+            expiry_days,
+            size_bytes: 0,
+            title_changes: BTreeMap::new(),
+        };
+    }
+
+    let cache = CacheStore::load(config);
+    CacheInfo {
+        exists: true,
+        entries: cache.len(),
+        expired: cache.expired_count(),
+        expiry_days,
+        size_bytes: file_size(&path),
+        title_changes: cache.title_changes().clone(),
+        path,
+    }
+}
+
+/// Remove every entry of the cache of `dir`
+pub fn clear_cache(dir: &Path, expiry_days: u32) -> Result<CacheCleanup, CacheError> {
+    cleanup(dir, expiry_days, |cache| {
+        let count = cache.len();
+        cache.clear();
+        count
+    })
+}
+
+/// Remove the expired entries of the cache of `dir`
+pub fn prune_cache(dir: &Path, expiry_days: u32) -> Result<CacheCleanup, CacheError> {
+    cleanup(dir, expiry_days, CacheStore::prune_expired)
+}
+
+fn cleanup(
+    dir: &Path,
+    expiry_days: u32,
+    remove: impl FnOnce(&mut CacheStore) -> usize,
+) -> Result<CacheCleanup, CacheError> {
+    let config = CacheConfig::for_target_dir(dir, expiry_days);
+    let path = config.cache_path.clone();
+    if !path.exists() {
+        return Ok(CacheCleanup::missing(path));
+    }
+
+    let size_before = file_size(&path);
+    let mut cache = CacheStore::load(config);
+    let before = cache.len();
+    let removed = remove(&mut cache);
+    cache.save()?;
+    Ok(CacheCleanup {
+        before,
+        after: cache.len(),
+        removed,
+        bytes_freed: size_before.saturating_sub(file_size(&path)),
+        exists: true,
+        path,
+    })
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AnimeInfo;
+    use tempfile::tempdir;
+
+    fn fill(dir: &Path) {
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir, 30));
+        for anidb_id in 1..=3 {
+            cache.insert(&AnimeInfo {
+                anidb_id,
+                title_main: format!("Anime {}", anidb_id),
+                title_en: None,
+                release_year: Some(2020),
+                english_fallback: false,
+                restricted: false,
+                unaired: false,
+                anime_type: None,
+                episode_count: None,
+            });
+        }
+        cache.save().unwrap();
+    }
+
+    #[test]
+    fn test_missing_cache() {
+        let dir = tempdir().unwrap();
+        let info = cache_info(dir.path(), 30);
+        assert!(!info.exists);
+        assert_eq!(info.entries, 0);
+
+        let cleared = clear_cache(dir.path(), 30).unwrap();
+        assert!(!cleared.exists);
+        assert_eq!(cleared.removed, 0);
+        // Nothing is created
+        assert!(!info.path.exists());
+    }
+
+    #[test]
+    fn test_prune_and_clear() {
+        let dir = tempdir().unwrap();
+        fill(dir.path());
+
+        let info = cache_info(dir.path(), 30);
+        assert_eq!((info.entries, info.valid()), (3, 3));
+        assert!(info.size_bytes > 0);
+
+        let pruned = prune_cache(dir.path(), 30).unwrap();
+        assert_eq!((pruned.before, pruned.removed, pruned.after), (3, 0, 3));
+        assert_eq!(pruned.bytes_freed, 0);
+
+        let cleared = clear_cache(dir.path(), 30).unwrap();
+        assert_eq!((cleared.before, cleared.removed, cleared.after), (3, 3, 0));
+        assert!(cleared.bytes_freed > 0);
+        assert_eq!(cache_info(dir.path(), 30).entries, 0);
+    }
+}
@@ -1,5 +1,13 @@
+mod commands;
 mod store;
 mod types;
 
+pub use commands::{cache_info, clear_cache, prune_cache};
+#[allow(unused_imports)]
+pub use commands::{CacheCleanup, CacheInfo};
 pub use store::CacheStore;
-pub use types::{CacheConfig, CacheError};
+#[allow(unused_imports)]
+pub use types::{
+    CacheConfig, CacheError, TitleChange, Titles, CACHE_VERSION, CLOCK_SKEW_TOLERANCE_MINUTES,
+    DEFAULT_CACHE_SAVE_EVERY, TITLE_CHANGES_KEPT,
+};
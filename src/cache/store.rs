@@ -1,15 +1,29 @@
-use super::types::{CacheConfig, CacheEntry, CacheError, CacheFile, CACHE_VERSION};
+use super::types::{
+    CacheConfig, CacheEntry, CacheError, CacheFile, TitleChange, CACHE_VERSION, TITLE_CHANGES_KEPT,
+};
 use crate::api::AnimeInfo;
+#[cfg(feature = "api")]
+use crate::api::TitleDump;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
 use tracing::{debug, info, warn};
 
 /// A persistent cache store for anime metadata
+///
+/// Call [`save`](Self::save) or [`into_saved`](Self::into_saved) to write
+/// changes and see whether that worked. Dropping a store with unsaved
+/// changes makes one best-effort attempt, unless a save already failed.
 pub struct CacheStore {
     config: CacheConfig,
     data: CacheFile,
     dirty: bool,
+    /// Entries inserted since the last save
+    unsaved: usize,
+    /// A save failed; dropping the store does not try again
+    poisoned: bool,
 }
 
 impl CacheStore {
@@ -18,6 +32,19 @@ impl CacheStore {
         let data = match Self::read_cache_file(&config.cache_path) {
             Ok(cache) => {
                 info!("Loaded cache with {} entries", cache.entries.len());
+                let now = Utc::now();
+                let future = cache
+                    .entries
+                    .values()
+                    .filter(|e| e.is_future_dated_at(now))
+                    .count();
+                if future > 0 {
+                    warn!(
+                        "{} cache entries were fetched in the future (is the system clock behind?); \
+                         they will be fetched again",
+                        future
+                    );
+                }
                 cache
             }
             Err(e) => {
@@ -39,6 +66,8 @@ impl CacheStore {
             config,
             data,
             dirty: false,
+            unsaved: 0,
+            poisoned: false,
         }
     }
 
@@ -61,17 +90,31 @@ impl CacheStore {
 
     /// Get cached anime info if it exists and is not expired
     pub fn get(&self, anidb_id: u32) -> Option<AnimeInfo> {
+        self.get_with_meta(anidb_id).map(|(info, _)| info)
+    }
+
+    /// Get cached anime info with when it was fetched, if not expired
+    pub fn get_with_meta(&self, anidb_id: u32) -> Option<(AnimeInfo, DateTime<Utc>)> {
         self.data.entries.get(&anidb_id).and_then(|entry| {
             if entry.is_expired(self.config.expiry_days) {
                 debug!("Cache entry {} expired", anidb_id);
                 None
             } else {
                 debug!("Cache hit for {}", anidb_id);
-                Some(entry.to_anime_info())
+                Some((entry.to_anime_info(), entry.fetched_at))
             }
         })
     }
 
+    /// Whether the entry of `anidb_id` was seeded from the titles dump
+    /// rather than fetched
+    pub fn is_seeded(&self, anidb_id: u32) -> bool {
+        self.data
+            .entries
+            .get(&anidb_id)
+            .is_some_and(|entry| entry.seeded)
+    }
+
     /// Check if a valid (non-expired) entry exists
     #[cfg(test)]
     pub fn has_valid(&self, anidb_id: u32) -> bool {
@@ -79,11 +122,86 @@ impl CacheStore {
     }
 
     /// Insert or update a cache entry
-    pub fn insert(&mut self, info: &AnimeInfo) {
-        let entry = CacheEntry::from_anime_info(info);
+    ///
+    /// Replacing an entry whose titles or year differ records the change
+    /// (the last [`TITLE_CHANGES_KEPT`] per id are kept) and returns it.
+    /// A fetched entry replacing a seeded one is no change: the dump has no
+    /// year to compare.
+    pub fn insert(&mut self, info: &AnimeInfo) -> Option<TitleChange> {
+        self.insert_entry(CacheEntry::from_anime_info(info))
+    }
+
+    fn insert_entry(&mut self, entry: CacheEntry) -> Option<TitleChange> {
         debug!("Caching anime {}", entry.anidb_id);
+        let change = self
+            .data
+            .entries
+            .get(&entry.anidb_id)
+            .filter(|old| !old.seeded && old.titles() != entry.titles())
+            .map(|old| TitleChange {
+                old: old.titles(),
+                new: entry.titles(),
+                changed_at: entry.fetched_at,
+            });
+        if let Some(change) = &change {
+            info!(
+                "Title of {} changed upstream: {} -> {}",
+                entry.anidb_id, change.old, change.new
+            );
+            let log = self.data.changes.entry(entry.anidb_id).or_default();
+            log.push(change.clone());
+            let excess = log.len().saturating_sub(TITLE_CHANGES_KEPT);
+            log.drain(..excess);
+        }
         self.data.entries.insert(entry.anidb_id, entry);
         self.dirty = true;
+        self.unsaved += 1;
+        change
+    }
+
+    /// Cache the titles `dump` has for those of `ids` without a valid entry,
+    /// returning how many were added
+    ///
+    /// Seeded entries have no year or type. They are marked as such: they
+    /// expire like fetched ones, but a run with the API refetches them.
+    #[cfg(feature = "api")]
+    pub fn seed(&mut self, dump: &TitleDump, ids: impl IntoIterator<Item = u32>) -> usize {
+        let mut seeded = 0;
+        for id in ids {
+            if self.get(id).is_some() {
+                continue;
+            }
+            if let Some(info) = dump.get(id) {
+                self.insert_entry(CacheEntry {
+                    seeded: true,
+                    ..CacheEntry::from_anime_info(&info)
+                });
+                seeded += 1;
+            }
+        }
+        if seeded > 0 {
+            info!(
+                "Seeded the cache with {} title(s) from the titles dump",
+                seeded
+            );
+        }
+        seeded
+    }
+
+    /// Title changes recorded when refetching, by id, oldest first
+    pub fn title_changes(&self) -> &BTreeMap<u32, Vec<TitleChange>> {
+        &self.data.changes
+    }
+
+    /// Save once `save_every` entries were inserted since the last save
+    ///
+    /// Keeps fetched entries on disk during a long fetch phase, so a crash
+    /// that skips the final save loses only the last few.
+    pub fn checkpoint(&mut self) -> Result<(), CacheError> {
+        if self.config.save_every == 0 || self.unsaved < self.config.save_every {
+            return Ok(());
+        }
+        self.save()
     }
 
     /// Remove expired entries from cache
@@ -103,9 +221,10 @@ impl CacheStore {
         removed
     }
 
-    /// Clear all cached entries
+    /// Clear all cached entries and their title changes
     pub fn clear(&mut self) {
         self.data.entries.clear();
+        self.data.changes.clear();
         self.dirty = true;
     }
 
@@ -116,6 +235,19 @@ impl CacheStore {
             return Ok(());
         }
 
+        let saved = self.write();
+        self.poisoned = saved.is_err();
+        saved
+    }
+
+    /// Save cache to disk if modified, consuming the store
+    ///
+    /// Unlike dropping the store, this reports a failed save.
+    pub fn into_saved(mut self) -> Result<(), CacheError> {
+        self.save()
+    }
+
+    fn write(&mut self) -> Result<(), CacheError> {
         // Ensure parent directory exists
         if let Some(parent) = self.config.cache_path.parent() {
             fs::create_dir_all(parent)?;
@@ -134,6 +266,7 @@ impl CacheStore {
         fs::rename(&temp_path, &self.config.cache_path)?;
 
         self.dirty = false;
+        self.unsaved = 0;
         info!(
             "Saved cache with {} entries to {:?}",
             self.data.entries.len(),
@@ -142,6 +275,16 @@ impl CacheStore {
         Ok(())
     }
 
+    /// Path of the cache file
+    /// When the most recently fetched entry was fetched
+    pub fn newest_fetched_at(&self) -> Option<DateTime<Utc>> {
+        self.data.entries.values().map(|e| e.fetched_at).max()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.config.cache_path
+    }
+
     /// Get number of cached entries
     pub fn len(&self) -> usize {
         self.data.entries.len()
@@ -164,9 +307,20 @@ impl CacheStore {
 }
 
 impl Drop for CacheStore {
+    /// Best-effort save of changes nobody saved explicitly
+    ///
+    /// Skipped after a failed save, which was already reported. While
+    /// unwinding from a panic a failure is only logged at debug level.
     fn drop(&mut self) {
-        if let Err(e) = self.save() {
-            warn!("Failed to save cache on drop: {}", e);
+        if !self.dirty || self.poisoned {
+            return;
+        }
+        if let Err(e) = self.write() {
+            if std::thread::panicking() {
+                debug!("Failed to save cache while unwinding: {}", e);
+            } else {
+                warn!("Failed to save cache on drop: {}", e);
+            }
         }
     }
 }
@@ -183,6 +337,11 @@ mod tests {
             title_main: format!("Test Anime {}", id),
             title_en: Some(format!("Test Anime {} EN", id)),
             release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         }
     }
 
@@ -192,10 +351,49 @@ mod tests {
             title_main: format!("Expired Anime {}", id),
             title_en: None,
             release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+            seeded: false,
             fetched_at: Utc::now() - Duration::days(60),
         }
     }
 
+    #[cfg(feature = "api")]
+    #[test]
+    fn test_seed_from_title_dump() {
+        let dir = tempdir().unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&create_test_info(1));
+        let dump = TitleDump::parse(
+            r#"<animetitles>
+                <anime aid="1"><title xml:lang="x-jat" type="main">Dumped 1</title></anime>
+                <anime aid="2"><title xml:lang="x-jat" type="main">Dumped 2</title></anime>
+            </animetitles>"#,
+        )
+        .unwrap();
+
+        assert_eq!(cache.seed(&dump, [1, 2, 3]), 1);
+        // Valid entries keep what the API said
+        assert_eq!(cache.get(1).unwrap().title_main, "Test Anime 1");
+        let seeded = cache.get(2).unwrap();
+        assert_eq!(seeded.title_main, "Dumped 2");
+        assert_eq!(seeded.release_year, None);
+        assert!(cache.get(3).is_none());
+        assert!(cache.is_seeded(2) && !cache.is_seeded(1));
+
+        // Fetching replaces the seeded entry without recording a title change
+        let fetched = AnimeInfo {
+            title_main: "Fetched 2".to_string(),
+            ..create_test_info(2)
+        };
+        assert_eq!(cache.insert(&fetched), None);
+        assert!(!cache.is_seeded(2));
+        assert!(cache.title_changes().is_empty());
+    }
+
     #[test]
     fn test_cache_hit() {
         let dir = tempdir().unwrap();
@@ -296,6 +494,11 @@ mod tests {
                 title_main: "Persisted".to_string(),
                 title_en: None,
                 release_year: None,
+                english_fallback: false,
+                restricted: false,
+                unaired: false,
+                anime_type: None,
+                episode_count: Some(26),
             });
             cache.save().unwrap();
         }
@@ -305,10 +508,32 @@ mod tests {
             let cache = CacheStore::load(config);
             let retrieved = cache.get(12345);
             assert!(retrieved.is_some());
-            assert_eq!(retrieved.unwrap().title_main, "Persisted");
+            let retrieved = retrieved.unwrap();
+            assert_eq!(retrieved.title_main, "Persisted");
+            assert_eq!(retrieved.episode_count, Some(26));
         }
     }
 
+    #[test]
+    fn test_entries_without_episode_count_load() {
+        let dir = tempdir().unwrap();
+        let cache_path = dir.path().join(".anidb2folder-cache.json");
+
+        // Written before episode counts were cached, same version
+        let old_cache = format!(
+            r#"{{"version": "{}", "entries": {{"1": {{"anidb_id": 1, "title_main": "Old",
+                "title_en": null, "release_year": 1998, "fetched_at": "{}"}}}}}}"#,
+            CACHE_VERSION,
+            Utc::now().to_rfc3339()
+        );
+        fs::write(&cache_path, old_cache).unwrap();
+
+        let cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        let info = cache.get(1).unwrap();
+        assert_eq!(info.title_main, "Old");
+        assert_eq!(info.episode_count, None);
+    }
+
     #[test]
     fn test_corrupted_cache_handling() {
         let dir = tempdir().unwrap();
@@ -403,6 +628,11 @@ mod tests {
             title_main: "Original".to_string(),
             title_en: None,
             release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         });
 
         cache.insert(&AnimeInfo {
@@ -410,6 +640,11 @@ mod tests {
             title_main: "Updated".to_string(),
             title_en: Some("Updated EN".to_string()),
             release_year: Some(2021),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         });
 
         assert_eq!(cache.len(), 1);
@@ -418,4 +653,120 @@ mod tests {
         assert_eq!(retrieved.title_main, "Updated");
         assert_eq!(retrieved.title_en, Some("Updated EN".to_string()));
     }
+
+    #[test]
+    fn test_title_changes_are_recorded() {
+        let dir = tempdir().unwrap();
+        let config = CacheConfig::for_target_dir(dir.path(), 30);
+        let mut cache = CacheStore::load(config.clone());
+
+        assert!(cache.insert(&create_test_info(1)).is_none());
+        // Fetched again unchanged
+        assert!(cache.insert(&create_test_info(1)).is_none());
+
+        let mut info = create_test_info(1);
+        info.release_year = Some(2021);
+        let change = cache.insert(&info).unwrap();
+        assert_eq!(change.old.release_year, Some(2020));
+        assert_eq!(change.new.release_year, Some(2021));
+        assert_eq!(change.new.title_main, "Test Anime 1");
+
+        // Only the last few are kept
+        for i in 0..TITLE_CHANGES_KEPT + 2 {
+            info.title_main = format!("Title {}", i);
+            cache.insert(&info);
+        }
+        let log = cache.title_changes()[&1].clone();
+        assert_eq!(log.len(), TITLE_CHANGES_KEPT);
+        assert_eq!(log.last().unwrap().new.title_main, "Title 6");
+        assert_eq!(log[0].old.title_main, "Title 1");
+
+        cache.save().unwrap();
+        let reloaded = CacheStore::load(config);
+        assert_eq!(reloaded.title_changes()[&1], log);
+
+        cache.clear();
+        assert!(cache.title_changes().is_empty());
+    }
+
+    #[test]
+    fn test_drop_saves_unsaved_changes() {
+        let dir = tempdir().unwrap();
+        let config = CacheConfig::for_target_dir(dir.path(), 30);
+        {
+            let mut cache = CacheStore::load(config.clone());
+            cache.insert(&create_test_info(1));
+        }
+
+        assert!(CacheStore::load(config).has_valid(1));
+    }
+
+    #[test]
+    fn test_failed_save_is_not_retried_on_drop() {
+        let dir = tempdir().unwrap();
+        let config = CacheConfig::for_target_dir(dir.path(), 30);
+        // A directory in the cache file's place makes the save fail
+        fs::create_dir(&config.cache_path).unwrap();
+
+        let mut cache = CacheStore::load(config.clone());
+        cache.insert(&create_test_info(1));
+        assert!(cache.save().is_err());
+
+        fs::remove_dir(&config.cache_path).unwrap();
+        drop(cache);
+        assert!(!config.cache_path.exists());
+    }
+
+    #[test]
+    fn test_into_saved_reports_failure() {
+        let dir = tempdir().unwrap();
+        let config = CacheConfig::for_target_dir(dir.path(), 30);
+        fs::create_dir(&config.cache_path).unwrap();
+
+        let mut cache = CacheStore::load(config.clone());
+        cache.insert(&create_test_info(1));
+        assert!(cache.into_saved().is_err());
+
+        fs::remove_dir(&config.cache_path).unwrap();
+        let mut cache = CacheStore::load(config.clone());
+        cache.insert(&create_test_info(1));
+        cache.into_saved().unwrap();
+        assert!(CacheStore::load(config).has_valid(1));
+    }
+
+    #[test]
+    fn test_checkpoint_saves_every_n_inserts() {
+        let dir = tempdir().unwrap();
+        let config = CacheConfig {
+            save_every: 2,
+            ..CacheConfig::for_target_dir(dir.path(), 30)
+        };
+        let cache_path = config.cache_path.clone();
+        let mut cache = CacheStore::load(config.clone());
+
+        let info = |id: u32| AnimeInfo {
+            anidb_id: id,
+            title_main: format!("Anime {}", id),
+            title_en: None,
+            release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        };
+
+        cache.insert(&info(1));
+        cache.checkpoint().unwrap();
+        assert!(!cache_path.exists());
+
+        cache.insert(&info(2));
+        cache.checkpoint().unwrap();
+        cache.insert(&info(3));
+        cache.checkpoint().unwrap();
+
+        // Read the file as a crashed run would have left it
+        let on_disk = CacheStore::read_cache_file(&cache_path).unwrap();
+        assert_eq!(on_disk.entries.len(), 2);
+    }
 }
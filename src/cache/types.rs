@@ -1,12 +1,22 @@
-use crate::api::AnimeInfo;
+use crate::api::{AnimeInfo, AnimeType};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use thiserror::Error;
+use tracing::debug;
 
 pub const CACHE_VERSION: &str = "1.0";
 
+/// Expiry for entries of unaired anime, whose titles and dates still change
+pub const UNAIRED_EXPIRY_DAYS: u32 = 1;
+
+/// Clock difference tolerated before a timestamp counts as in the future
+pub const CLOCK_SKEW_TOLERANCE_MINUTES: i64 = 5;
+
+/// Title changes kept per id, the oldest dropped first
+pub const TITLE_CHANGES_KEPT: usize = 5;
+
 /// A single cached anime entry with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
@@ -14,6 +24,25 @@ pub struct CacheEntry {
     pub title_main: String,
     pub title_en: Option<String>,
     pub release_year: Option<u16>,
+    /// The main title is the English title (no main title on AniDB)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub english_fallback: bool,
+    /// AniDB marks the entry as restricted (adult content)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub restricted: bool,
+    /// The anime had not aired when fetched
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unaired: bool,
+    /// Kind of entry; absent in entries cached before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anime_type: Option<AnimeType>,
+    /// Number of episodes; absent in entries cached before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episode_count: Option<u16>,
+    /// Taken from the titles dump, not fetched: no year or type, so a run
+    /// with the API refetches it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub seeded: bool,
     pub fetched_at: DateTime<Utc>,
 }
 
@@ -24,6 +53,12 @@ impl CacheEntry {
             title_main: info.title_main.clone(),
             title_en: info.title_en.clone(),
             release_year: info.release_year,
+            english_fallback: info.english_fallback,
+            restricted: info.restricted,
+            unaired: info.unaired,
+            anime_type: info.anime_type,
+            episode_count: info.episode_count,
+            seeded: false,
             fetched_at: Utc::now(),
         }
     }
@@ -34,36 +69,153 @@ impl CacheEntry {
             title_main: self.title_main.clone(),
             title_en: self.title_en.clone(),
             release_year: self.release_year,
+            english_fallback: self.english_fallback,
+            restricted: self.restricted,
+            unaired: self.unaired,
+            anime_type: self.anime_type,
+            episode_count: self.episode_count,
         }
     }
 
+    /// Whether the entry is older than `expiry_days`
+    ///
+    /// Entries of unaired anime expire after at most [`UNAIRED_EXPIRY_DAYS`].
     pub fn is_expired(&self, expiry_days: u32) -> bool {
-        let age = Utc::now().signed_duration_since(self.fetched_at);
+        self.is_expired_at(expiry_days, Utc::now())
+    }
+
+    /// Whether the entry is older than `expiry_days` at `now`
+    ///
+    /// An entry fetched in the future is stale: either the clock was ahead
+    /// when it was fetched or it is behind now, and either way its age says
+    /// nothing. Without this it would never expire.
+    pub fn is_expired_at(&self, expiry_days: u32, now: DateTime<Utc>) -> bool {
+        if self.is_future_dated_at(now) {
+            debug!(
+                "Cache entry {} fetched in the future ({}), treating it as stale",
+                self.anidb_id, self.fetched_at
+            );
+            return true;
+        }
+        let expiry_days = if self.unaired {
+            expiry_days.min(UNAIRED_EXPIRY_DAYS)
+        } else {
+            expiry_days
+        };
+        let age = now.signed_duration_since(self.fetched_at);
         age.num_days() > expiry_days as i64
     }
+
+    /// Whether `fetched_at` is later than `now`, beyond [`CLOCK_SKEW_TOLERANCE_MINUTES`]
+    pub fn is_future_dated_at(&self, now: DateTime<Utc>) -> bool {
+        self.fetched_at > now + chrono::Duration::minutes(CLOCK_SKEW_TOLERANCE_MINUTES)
+    }
+
+    /// The parts of the entry that end up in a directory name
+    pub fn titles(&self) -> Titles {
+        Titles {
+            title_main: self.title_main.clone(),
+            title_en: self.title_en.clone(),
+            release_year: self.release_year,
+        }
+    }
+}
+
+/// Titles and year of an entry, as they appear in a readable name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Titles {
+    pub title_main: String,
+    pub title_en: Option<String>,
+    pub release_year: Option<u16>,
+}
+
+impl std::fmt::Display for Titles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.title_main)?;
+        if let Some(en) = &self.title_en {
+            write!(f, " / {}", en)?;
+        }
+        if let Some(year) = self.release_year {
+            write!(f, " ({})", year)?;
+        }
+        Ok(())
+    }
+}
+
+/// Titles of an entry that differ after fetching it again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TitleChange {
+    pub old: Titles,
+    pub new: Titles,
+    pub changed_at: DateTime<Utc>,
 }
 
 /// The cache file structure (serialized to JSON)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheFile {
     pub version: String,
-    pub entries: HashMap<u32, CacheEntry>,
+    /// Ordered by id, so saving an unchanged cache writes identical bytes
+    pub entries: BTreeMap<u32, CacheEntry>,
+    /// Title changes seen when refetching, by id, oldest first
+    ///
+    /// Absent in caches written before changes were recorded; changes that
+    /// do not parse are dropped instead of failing the whole cache.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "lenient_changes"
+    )]
+    pub changes: BTreeMap<u32, Vec<TitleChange>>,
 }
 
 impl Default for CacheFile {
     fn default() -> Self {
         Self {
             version: CACHE_VERSION.to_string(),
-            entries: HashMap::new(),
+            entries: BTreeMap::new(),
+            changes: BTreeMap::new(),
         }
     }
 }
 
+/// Read the change log, keeping only what parses
+fn lenient_changes<'de, D>(deserializer: D) -> Result<BTreeMap<u32, Vec<TitleChange>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let Some(ids) = value.as_object() else {
+        debug!("Ignoring cache title changes that are not an object");
+        return Ok(BTreeMap::new());
+    };
+    let mut changes = BTreeMap::new();
+    for (id, list) in ids {
+        let Ok(id) = id.parse::<u32>() else {
+            continue;
+        };
+        let list: Vec<TitleChange> = list
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|change| serde_json::from_value(change.clone()).ok())
+            .collect();
+        if !list.is_empty() {
+            changes.insert(id, list);
+        }
+    }
+    Ok(changes)
+}
+
+/// Inserts between incremental cache saves while fetching
+pub const DEFAULT_CACHE_SAVE_EVERY: usize = 10;
+
 /// Configuration for the cache store
 #[derive(Debug, Clone)]
 pub struct CacheConfig {
     pub expiry_days: u32,
     pub cache_path: PathBuf,
+    /// Save after this many inserts (0 saves only when asked to)
+    pub save_every: usize,
 }
 
 impl CacheConfig {
@@ -72,6 +224,7 @@ impl CacheConfig {
         Self {
             expiry_days,
             cache_path: target.join(".anidb2folder-cache.json"),
+            save_every: DEFAULT_CACHE_SAVE_EVERY,
         }
     }
 
@@ -83,6 +236,7 @@ impl CacheConfig {
         dirs::cache_dir().map(|cache_dir| Self {
             expiry_days,
             cache_path: cache_dir.join("anidb2folder").join("cache.json"),
+            save_every: DEFAULT_CACHE_SAVE_EVERY,
         })
     }
 }
@@ -114,6 +268,11 @@ mod tests {
             title_main: format!("Test Anime {}", id),
             title_en: Some(format!("Test Anime {} EN", id)),
             release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         }
     }
 
@@ -135,6 +294,12 @@ mod tests {
             title_main: "Test".to_string(),
             title_en: Some("Test EN".to_string()),
             release_year: Some(2000),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+            seeded: false,
             fetched_at: Utc::now(),
         };
 
@@ -153,6 +318,12 @@ mod tests {
             title_main: "Test".to_string(),
             title_en: None,
             release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+            seeded: false,
             fetched_at: Utc::now() - Duration::days(31),
         };
 
@@ -165,6 +336,48 @@ mod tests {
         // Fresh entry = not expired
         entry.fetched_at = Utc::now();
         assert!(!entry.is_expired(30));
+
+        // Unaired entries expire much sooner
+        entry.unaired = true;
+        entry.fetched_at = Utc::now() - Duration::days(UNAIRED_EXPIRY_DAYS as i64 + 1);
+        assert!(entry.is_expired(30));
+        entry.fetched_at = Utc::now();
+        assert!(!entry.is_expired(30));
+    }
+
+    #[test]
+    fn test_cache_entry_expiration_with_skewed_clock() {
+        let now: DateTime<Utc> = "2026-06-01T12:00:00Z".parse().unwrap();
+        let mut entry = CacheEntry {
+            anidb_id: 1,
+            title_main: "Test".to_string(),
+            title_en: None,
+            release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+            seeded: false,
+            fetched_at: now + Duration::days(3 * 365),
+        };
+
+        // Fetched in the future (clock now behind): stale, however long the expiry
+        assert!(entry.is_future_dated_at(now));
+        assert!(entry.is_expired_at(30, now));
+        assert!(entry.is_expired_at(u32::MAX, now));
+
+        // Within the tolerance: fresh
+        entry.fetched_at = now + Duration::minutes(1);
+        assert!(!entry.is_future_dated_at(now));
+        assert!(!entry.is_expired_at(30, now));
+
+        // Ancient (clock now ahead): expired, not confused by the large age
+        entry.fetched_at = now - Duration::days(3 * 365);
+        assert!(!entry.is_future_dated_at(now));
+        assert!(entry.is_expired_at(30, now));
+        assert!(entry.is_expired_at(365, now));
+        assert!(!entry.is_expired_at(u32::MAX, now));
     }
 
     #[test]
@@ -175,6 +388,102 @@ mod tests {
         assert!(cache.entries.is_empty());
     }
 
+    /// Entries are written in id order whatever order they were added in
+    #[test]
+    fn test_cache_file_serialization_is_stable() {
+        let fetched_at = "2026-01-01T00:00:00Z".parse().unwrap();
+        let mut cache = CacheFile::default();
+        for (anidb_id, title) in [(30, "Thirty"), (4, "Four"), (100, "Hundred")] {
+            cache.entries.insert(
+                anidb_id,
+                CacheEntry {
+                    anidb_id,
+                    title_main: title.to_string(),
+                    title_en: None,
+                    release_year: Some(2004),
+                    english_fallback: false,
+                    restricted: false,
+                    unaired: false,
+                    anime_type: None,
+                    episode_count: None,
+                    seeded: false,
+                    fetched_at,
+                },
+            );
+        }
+
+        let entry = |id: u32, title: &str| {
+            format!(
+                "\"{id}\":{{\"anidb_id\":{id},\"title_main\":\"{title}\",\"title_en\":null,\
+                 \"release_year\":2004,\"fetched_at\":\"2026-01-01T00:00:00Z\"}}"
+            )
+        };
+        assert_eq!(
+            serde_json::to_string(&cache).unwrap(),
+            format!(
+                "{{\"version\":\"1.0\",\"entries\":{{{},{},{}}}}}",
+                entry(4, "Four"),
+                entry(30, "Thirty"),
+                entry(100, "Hundred")
+            )
+        );
+    }
+
+    #[test]
+    fn test_cache_entry_type_round_trip() {
+        let mut info = create_test_info(7729);
+        info.anime_type = Some(AnimeType::Ova);
+        let json = serde_json::to_string(&CacheEntry::from_anime_info(&info)).unwrap();
+        assert!(json.contains(r#""anime_type":"ova""#), "{}", json);
+        let entry: CacheEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(entry.to_anime_info().anime_type, Some(AnimeType::Ova));
+
+        // Cached before the type was recorded
+        let old: CacheEntry = serde_json::from_str(
+            r#"{"anidb_id":1,"title_main":"Old","title_en":null,"release_year":2000,"fetched_at":"2026-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(old.anime_type, None);
+    }
+
+    #[test]
+    fn test_title_changes_load_leniently() {
+        // Written before changes were recorded
+        let old: CacheFile = serde_json::from_str(r#"{"version":"1.0","entries":{}}"#).unwrap();
+        assert!(old.changes.is_empty());
+
+        let titles = r#"{"title_main":"New","title_en":null,"release_year":2020}"#;
+        let json = format!(
+            r#"{{"version":"1.0","entries":{{}},"changes":{{
+                "1":[{{"old":{t},"new":{t},"changed_at":"2026-01-01T00:00:00Z"}},{{"old":"?"}}],
+                "2":"not a list",
+                "x":[]
+            }}}}"#,
+            t = titles
+        );
+        let cache: CacheFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(cache.changes.len(), 1);
+        assert_eq!(cache.changes[&1].len(), 1);
+        assert_eq!(cache.changes[&1][0].new.title_main, "New");
+
+        let cache: CacheFile =
+            serde_json::from_str(r#"{"version":"1.0","entries":{},"changes":[1]}"#).unwrap();
+        assert!(cache.changes.is_empty());
+    }
+
+    #[test]
+    fn test_titles_display() {
+        let titles = Titles {
+            title_main: "Shingeki no Kyojin".to_string(),
+            title_en: Some("Attack on Titan".to_string()),
+            release_year: Some(2013),
+        };
+        assert_eq!(
+            titles.to_string(),
+            "Shingeki no Kyojin / Attack on Titan (2013)"
+        );
+    }
+
     #[test]
     fn test_cache_config_for_target_dir() {
         let target = std::path::Path::new("/tmp/anime");
@@ -1,13 +1,54 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::api::AnimeType;
+use crate::cache::DEFAULT_CACHE_SAVE_EVERY;
+use crate::events::OutputFormat;
+use crate::fs_profile::FsProfile;
+use crate::i18n::Lang;
+use crate::output::{GroupBy, PreviewFormat};
+use crate::parser::NameTemplate;
+use crate::pipeline::DEFAULT_MAX_UNRECOGNIZED;
+use crate::rename::{
+    ConflictResolution, RestrictedPolicy, UnairedPolicy, DEFAULT_PLACEHOLDER,
+    DEFAULT_RESTRICTED_MARKER,
+};
+use crate::timing::DEFAULT_SLOW_THRESHOLD_SECS;
+use crate::ui::ThemeName;
+use crate::watch::DEFAULT_WATCH_INTERVAL_SECS;
+
+/// Revert modes; they replay history files, so rename-only options are
+/// rejected alongside them rather than silently ignored
+const REVERT_MODES: [&str; 2] = ["revert", "revert_all"];
+// Naming options recorded in rename histories: --redo takes them from there
+const RECORDED_OPTIONS: [&str; 3] = ["revert", "revert_all", "redo"];
 
 #[derive(Parser, Debug)]
 #[command(name = "anidb2folder")]
 #[command(author, version, about, long_about = None)]
 #[command(about = "Rename anime directories between AniDB ID and human-readable formats")]
+// Standalone commands: one at a time, never combined with a rename or revert
+#[command(group(
+    ArgGroup::new("command")
+        .args([
+            "cache_info",
+            "cache_clear",
+            "cache_prune",
+            "where_dir",
+            "history_list",
+            "lint",
+            "check_roundtrip",
+            "parse",
+            "list_exit_codes",
+            "build_info",
+        ])
+        .conflicts_with_all(["target_dir", "revert", "revert_all", "watch", "redo"])
+))]
 pub struct Args {
     /// Target directory containing anime subdirectories
-    #[arg(required_unless_present_any = ["revert", "cache_info", "cache_clear", "cache_prune"])]
+    #[arg(required_unless_present_any = ["revert", "watch", "cache_info", "cache_clear", "cache_prune", "history_list", "lint", "check_roundtrip", "parse", "where_dir", "check_api", "revert_all", "list_exit_codes", "build_info", "redo"])]
     pub target_dir: Option<PathBuf>,
 
     /// Simulate changes without modifying the filesystem
@@ -22,18 +63,269 @@ pub struct Args {
     #[arg(short, long, value_name = "HISTORY_FILE")]
     pub revert: Option<PathBuf>,
 
+    /// Revert every history file matching a glob, e.g. 'lib/**/anidb2folder-history-*.json'
+    #[arg(long, value_name = "PATTERN", conflicts_with_all = ["revert", "target_dir"])]
+    pub revert_all: Option<String>,
+
+    /// Rename the target of a history file again with the naming options it
+    /// recorded, e.g. after reverting it
+    #[arg(
+        long,
+        value_name = "HISTORY_FILE",
+        conflicts_with_all = ["target_dir", "revert", "revert_all", "watch"]
+    )]
+    pub redo: Option<PathBuf>,
+
+    /// Refuse history files whose checksum does not match, plans with names
+    /// the target filesystem would reject, and names that look like mistakes
+    /// (exit code 11); implies --check-permissions
+    #[arg(long)]
+    pub strict: bool,
+
+    /// After renaming or reverting, list the directory again and check every
+    /// destination exists and every source is gone (fails the run with --strict)
+    #[arg(long)]
+    pub post_run_verify: bool,
+
     /// Maximum directory name length
-    #[arg(short = 'l', long, default_value = "255")]
+    #[arg(short = 'l', long, default_value = "255", conflicts_with_all = RECORDED_OPTIONS)]
     pub max_length: usize,
 
     /// Cache expiration in days
     #[arg(short, long, default_value = "30")]
     pub cache_expiry: u32,
 
+    /// Save the cache after every N fetched entries (0: only after fetching)
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_CACHE_SAVE_EVERY,
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub cache_save_every: usize,
+
+    /// Warn about renames taking longer than SECS seconds, naming the directory (0: never)
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = DEFAULT_SLOW_THRESHOLD_SECS,
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub slow_threshold: u64,
+
+    /// Suppress styled output and show only log lines
+    #[arg(long, visible_alias = "log-only")]
+    pub no_ui: bool,
+
+    /// Show timestamps in local time instead of UTC (display only)
+    #[arg(long)]
+    pub local_time: bool,
+
+    /// Color theme for styled output
+    #[arg(long, value_enum, value_name = "THEME", default_value = "default")]
+    pub theme: ThemeName,
+
+    /// Do not print the ASCII banner
+    #[arg(long)]
+    pub no_banner: bool,
+
+    /// Compact output: fewer lines, no blank-line padding
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Language of messages: en or ja (default: from LC_ALL, LC_MESSAGES or LANG)
+    #[arg(long, value_enum, value_name = "LANG")]
+    pub lang: Option<Lang>,
+
+    /// Leave series tags out of readable names (AniDB → readable only)
+    #[arg(long, conflicts_with_all = RECORDED_OPTIONS)]
+    pub drop_series_tag: bool,
+
+    /// Operate even on a filesystem root, the home directory or an unrelated directory
+    #[arg(long, conflicts_with_all = REVERT_MODES)]
+    pub i_know_what_im_doing: bool,
+
+    /// Refuse a target with more subdirectories than this when none is an anime directory
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_MAX_UNRECOGNIZED,
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub max_unrecognized: usize,
+
+    /// List every directory in an unrecognized format instead of the first few
+    #[arg(long, conflicts_with_all = REVERT_MODES)]
+    pub show_all_unrecognized: bool,
+
+    /// Dry-run name for uncached ids; `{id}` is replaced with the AniDB id
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = DEFAULT_PLACEHOLDER,
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub placeholder: String,
+
+    /// Restricted (adult) entries: allow, skip (leave untouched) or tag (prepend a marker)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value = "allow",
+        conflicts_with_all = RECORDED_OPTIONS
+    )]
+    pub restricted: RestrictedPolicy,
+
+    /// Marker prepended to restricted entries with --restricted tag
+    #[arg(
+        long,
+        value_name = "MARKER",
+        default_value = DEFAULT_RESTRICTED_MARKER,
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub restricted_marker: String,
+
+    /// Anime not aired yet: normal, tag (add [Unaired] before the id) or skip (leave untouched)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value = "normal",
+        conflicts_with_all = RECORDED_OPTIONS
+    )]
+    pub unaired: UnairedPolicy,
+
+    /// Replace a character in generated names, e.g. ':= -' or '?=' (repeatable)
+    #[arg(
+        long,
+        value_name = "CHAR=TEXT",
+        value_parser = parse_replacement,
+        conflicts_with_all = RECORDED_OPTIONS
+    )]
+    pub replace: Vec<(char, String)>,
+
+    /// Add '(TEXT)' after the titles of one type of entry, e.g. 'movie=Movie' (repeatable)
+    ///
+    /// Types: tv-series, movie, ova, tv-special, web, music-video, other.
+    #[arg(
+        long,
+        value_name = "TYPE=TEXT",
+        value_parser = parse_type_suffix,
+        conflicts_with_all = RECORDED_OPTIONS
+    )]
+    pub type_suffix: Vec<(AnimeType, String)>,
+
+    /// Add AniDB's type after the year, e.g. 'Title (2011) (OVA) [anidb-7729]'
+    ///
+    /// Not for types given a --type-suffix, nor when the title already
+    /// holds it; fills {type} in a --template.
+    #[arg(long, conflicts_with_all = RECORDED_OPTIONS)]
+    pub include_type: bool,
+
+    /// Layout of readable names, e.g. '{tag} {title_jp} ({year}) [anidb-{id}]'
+    ///
+    /// Placeholders: {tag}, {title_jp}, {title_en}, {year}, {type}, {suffix}, {id} and {episodes};
+    /// '[anidb-{id}]' is required. Words of empty fields are left out.
+    /// Names in this layout are recognized when converting back.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = parse_template,
+        conflicts_with_all = RECORDED_OPTIONS
+    )]
+    pub template: Option<NameTemplate>,
+
+    /// Check the ids of human-readable names against their cached AniDB titles
+    #[arg(long, conflicts_with_all = REVERT_MODES)]
+    pub verify_ids: bool,
+
+    /// With --verify-ids, fetch ids missing from the cache from the API
+    #[arg(long, requires = "verify_ids", conflicts_with_all = REVERT_MODES)]
+    pub fetch: bool,
+
+    /// Naming rules of the target filesystem [default: detected]
+    #[arg(long, value_enum, value_name = "PROFILE", conflicts_with_all = RECORDED_OPTIONS)]
+    pub fs_profile: Option<FsProfile>,
+
+    /// Move directories whose id no longer exists on AniDB into `_unresolved/`
+    #[arg(long, conflicts_with_all = REVERT_MODES)]
+    pub quarantine_unresolved: bool,
+
+    /// With --quarantine-unresolved, move directories to the system trash instead
+    #[arg(long, requires = "quarantine_unresolved", conflicts_with_all = REVERT_MODES)]
+    pub use_trash: bool,
+
+    /// Ask how to resolve each conflict with an existing directory
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    /// How to resolve conflicts when not asking: abort, skip, suffix or aside
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value = "abort",
+        conflicts_with = "redo"
+    )]
+    pub on_conflict: ConflictResolution,
+
+    /// Look for files held open inside source directories first (Linux, best effort)
+    #[arg(long, conflicts_with_all = REVERT_MODES)]
+    pub check_open_files: bool,
+
+    /// Check that every directory may be renamed by this user first (implied by --strict)
+    #[arg(long, conflicts_with_all = REVERT_MODES)]
+    pub check_permissions: bool,
+
+    /// Leave out directories with anything modified within this long, e.g. 30m
+    /// (still being written to); units: s, m, h, d
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub exclude_recent: Option<Duration>,
+
+    /// Number of threads used to scan directories (default: automatic)
+    #[arg(long, value_name = "N", conflicts_with_all = REVERT_MODES)]
+    pub parallel_scan: Option<usize>,
+
+    /// Rename up to N directories at once, for slow network mounts (default: 1)
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub jobs: u64,
+
+    /// List history files in a directory
+    #[arg(long, value_name = "DIR")]
+    pub history_list: Option<PathBuf>,
+
+    /// Check the series tags in a directory for likely typos, without renaming anything
+    #[arg(long, value_name = "DIR")]
+    pub lint: Option<PathBuf>,
+
+    /// Convert a directory to the other format and back in memory, reporting names that would not come back
+    #[arg(long, value_name = "DIR")]
+    pub check_roundtrip: Option<PathBuf>,
+
+    /// Print what a single directory name parses to, or why it does not parse
+    #[arg(long, value_name = "NAME")]
+    pub parse: Option<String>,
+
     /// Show cache information for a directory
     #[arg(long, value_name = "DIR")]
     pub cache_info: Option<PathBuf>,
 
+    /// With --cache-info, list the titles that changed upstream when entries were fetched again
+    #[arg(long, requires = "cache_info")]
+    pub changes: bool,
+
     /// Clear all cached entries for a directory
     #[arg(long, value_name = "DIR")]
     pub cache_clear: Option<PathBuf>,
@@ -41,4 +333,233 @@ pub struct Args {
     /// Remove expired cache entries for a directory
     #[arg(long, value_name = "DIR")]
     pub cache_prune: Option<PathBuf>,
+
+    /// Verify the AniDB client credentials with one request before doing anything else
+    #[arg(long)]
+    pub check_api: bool,
+
+    /// How dry runs and conflict prompts show renames: arrow, or diff to highlight the changed words
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "arrow",
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub preview: PreviewFormat,
+
+    /// Divide the dry run listing into sections by series tag or release year
+    /// (execution order is unchanged)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KEY",
+        default_value = "none",
+        conflicts_with_all = REVERT_MODES
+    )]
+    pub group_by: GroupBy,
+
+    /// Plan like --dry, then only print the AniDB ids missing from the cache, one per line
+    #[arg(long, conflicts_with = "output", conflicts_with_all = REVERT_MODES)]
+    pub print_plan_only: bool,
+
+    /// Read the AniDB client name and version from this file (mode 600), ahead of ANIDB_CLIENT*
+    #[arg(long, value_name = "PATH", conflicts_with_all = REVERT_MODES)]
+    pub credentials_file: Option<PathBuf>,
+
+    /// JSON file of per-series corrections to AniDB titles, years and series tags
+    #[arg(long, value_name = "FILE", conflicts_with_all = RECORDED_OPTIONS)]
+    pub overrides: Option<PathBuf>,
+
+    /// AniDB's anime-titles.xml dump (gzipped or not); titles found there
+    /// need no API request, and names built from it alone have no year
+    #[arg(long, value_name = "PATH", conflicts_with_all = REVERT_MODES)]
+    pub titles_file: Option<PathBuf>,
+
+    /// TOML file of series tags, each listing its AniDB ids; sets or replaces
+    /// the tags of the listed directories in both directions
+    #[arg(long, value_name = "FILE", conflicts_with_all = RECORDED_OPTIONS)]
+    pub tag_map: Option<PathBuf>,
+
+    /// Run this shell command after each completed rename, with ANIDB_ID,
+    /// SRC_NAME, DST_NAME, TARGET_DIR and DRY_RUN set (never in a dry run)
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = REVERT_MODES)]
+    pub exec_after: Option<String>,
+
+    /// Run this shell command once after all renames, with RENAMED, TARGET_DIR and DRY_RUN set
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = REVERT_MODES)]
+    pub exec_after_run: Option<String>,
+
+    /// Fail the run when a hook fails instead of warning
+    #[arg(long, conflicts_with_all = REVERT_MODES)]
+    pub strict_hooks: bool,
+
+    /// Plan and rename N directories at a time, keeping memory bounded for very
+    /// large libraries; the history file is rewritten after each chunk
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = ["group_by", "output", "monitoring_output", "print_plan_only", "watch", "revert", "revert_all"]
+    )]
+    pub chunk_size: Option<u64>,
+
+    /// Print a single Nagios-style status line with perfdata to stdout at the end of the run
+    #[arg(
+        long,
+        requires = "target_dir",
+        conflicts_with_all = ["print_plan_only", "output", "revert", "revert_all"]
+    )]
+    pub monitoring_output: bool,
+
+    /// Write the effective settings and the run's summary as NDJSON to FILE
+    #[arg(long, value_name = "FILE", requires = "target_dir", conflicts_with_all = REVERT_MODES)]
+    pub report: Option<PathBuf>,
+
+    /// Defaults for runs nobody watches, such as from cron: no banner, colors
+    /// or prompts, --on-conflict skip, --monitoring-output, a report file in
+    /// the target directory, and exit code 1 when the run needs attention.
+    /// Options given explicitly win
+    #[arg(long, conflicts_with_all = ["interactive", "watch"])]
+    pub unattended: bool,
+
+    /// Print the settings this invocation would run with, after --unattended, and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Keep running and rename new AniDB-format directories as they appear in this directory
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["target_dir", "revert", "revert_all", "interactive", "print_plan_only", "output"]
+    )]
+    pub watch: Option<PathBuf>,
+
+    /// With --watch, seconds between checks; a new directory waits one interval to settle
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = DEFAULT_WATCH_INTERVAL_SECS,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "watch"
+    )]
+    pub once_per: u64,
+
+    /// Show where the cache and history files for a directory live
+    #[arg(long = "where", value_name = "DIR")]
+    pub where_dir: Option<PathBuf>,
+
+    /// Output format: text, ndjson to stream one JSON event per line while renaming, or json for the object of a standalone command (as --json)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "text",
+        conflicts_with = "interactive",
+        conflicts_with_all = REVERT_MODES,
+    )]
+    pub output: OutputFormat,
+
+    /// List the exit codes with their names and meanings
+    #[arg(long, hide = true)]
+    pub list_exit_codes: bool,
+
+    /// Print the version, enabled features, target and schema versions of this build
+    #[arg(long)]
+    pub build_info: bool,
+
+    /// Print --cache-info, --cache-clear, --cache-prune, --where, --lint, --check-roundtrip, --parse, --revert-all, --list-exit-codes, --build-info, --print-config and --print-plan-only results as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Parse a `TYPE=TEXT` type suffix
+fn parse_type_suffix(value: &str) -> Result<(AnimeType, String), String> {
+    let (name, text) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected TYPE=TEXT, got '{}'", value))?;
+    let anime_type = AnimeType::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = AnimeType::ALL.iter().map(|t| t.name()).collect();
+        format!("unknown type '{}' (expected {})", name, names.join(", "))
+    })?;
+    if text.trim().is_empty() {
+        return Err(format!("empty suffix for '{}'", name));
+    }
+    Ok((anime_type, text.trim().to_string()))
+}
+
+/// Parse a `<number><unit>` duration such as `90s`, `30m` or `2h`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let unit_at = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_at);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("expected a number and a unit, e.g. 30m, got '{}'", value))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}' (expected s, m, h or d)", unit)),
+    };
+    Ok(Duration::from_secs(number.saturating_mul(seconds)))
+}
+
+fn parse_template(value: &str) -> Result<NameTemplate, String> {
+    NameTemplate::parse(value).map_err(|e| e.to_string())
+}
+
+/// Parse a `CHAR=TEXT` character replacement
+fn parse_replacement(value: &str) -> Result<(char, String), String> {
+    let (from, to) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected CHAR=TEXT, got '{}'", value))?;
+    let mut chars = from.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok((c, to.to_string())),
+        _ => Err(format!("'{}' is not a single character", from)),
+    }
+}
+
+/// Report file `--unattended` writes in the target directory
+pub const UNATTENDED_REPORT: &str = ".anidb2folder-report.ndjson";
+
+impl Args {
+    /// Whether standalone commands print JSON (`--json` or `--output json`)
+    pub fn json_output(&self) -> bool {
+        self.json || self.output == OutputFormat::Json
+    }
+
+    /// Parse the command line, applying `--unattended` to the options it leaves out
+    pub fn parse_effective() -> Self {
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        if args.unattended {
+            args.apply_unattended(&matches);
+        }
+        args
+    }
+
+    /// Set the `--unattended` defaults of every option not given on the command line
+    fn apply_unattended(&mut self, matches: &ArgMatches) {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        self.no_banner = true;
+        if !given("on_conflict") {
+            self.on_conflict = ConflictResolution::Skip;
+        }
+        if let Some(target_dir) = &self.target_dir {
+            if !given("report") {
+                self.report = Some(target_dir.join(UNATTENDED_REPORT));
+            }
+            // The monitoring line would conflict with these
+            if self.output == OutputFormat::Text
+                && !self.print_plan_only
+                && self.chunk_size.is_none()
+            {
+                self.monitoring_output = true;
+            }
+        }
+    }
 }
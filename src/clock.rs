@@ -0,0 +1,142 @@
+//! Detection of a system clock that is behind.
+//!
+//! Machines without a battery-backed clock (e.g. a Raspberry Pi) can start
+//! with a date years in the past. Cache expiry and history names then go
+//! wrong without any error, so before a run the newest timestamps recorded
+//! for the target directory (cache entries and history files) are compared
+//! with the current time: a record from the future means the clock is
+//! behind now, or was ahead when the record was written.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use tracing::{debug, warn};
+
+use crate::cache::{CacheConfig, CacheStore, CLOCK_SKEW_TOLERANCE_MINUTES};
+use crate::history::{find_history_files, read_history};
+
+/// A record dated later than the current time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// The newest recorded timestamp
+    pub newest: DateTime<Utc>,
+    /// The cache or history file it comes from
+    pub source: PathBuf,
+    /// How far `newest` is ahead of the current time
+    pub ahead_by: Duration,
+}
+
+impl std::fmt::Display for ClockSkew {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was written at {}, {} ahead of the system clock; \
+             is the system clock behind?",
+            self.source.display(),
+            self.newest.format("%Y-%m-%d %H:%M:%S UTC"),
+            describe(self.ahead_by)
+        )
+    }
+}
+
+/// Compare `now` with the newest cache and history timestamps of `dir`
+///
+/// Unreadable files are left out.
+pub fn detect_skew(dir: &Path, now: DateTime<Utc>) -> Option<ClockSkew> {
+    let cache = CacheStore::load(CacheConfig::for_target_dir(dir, u32::MAX));
+    let mut records: Vec<(DateTime<Utc>, PathBuf)> = cache
+        .newest_fetched_at()
+        .map(|at| (at, cache.path().to_path_buf()))
+        .into_iter()
+        .collect();
+    for path in find_history_files(dir).unwrap_or_default() {
+        match read_history(&path) {
+            Ok(history) => records.push((history.executed_at, path)),
+            Err(e) => debug!("Skipping {:?} in clock check: {}", path, e),
+        }
+    }
+
+    let (newest, source) = records.into_iter().max_by_key(|(at, _)| *at)?;
+    let ahead_by = newest.signed_duration_since(now);
+    if ahead_by <= Duration::minutes(CLOCK_SKEW_TOLERANCE_MINUTES) {
+        return None;
+    }
+    let skew = ClockSkew {
+        newest,
+        source,
+        ahead_by,
+    };
+    warn!("Likely clock skew: {}", skew);
+    Some(skew)
+}
+
+/// A duration in the largest fitting unit, e.g. `3 days`
+fn describe(duration: Duration) -> String {
+    let (count, unit) = if duration.num_days() > 0 {
+        (duration.num_days(), "day")
+    } else if duration.num_hours() > 0 {
+        (duration.num_hours(), "hour")
+    } else {
+        (duration.num_minutes(), "minute")
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AnimeInfo;
+
+    fn write_cache(dir: &Path) {
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir, 30));
+        cache.insert(&AnimeInfo {
+            anidb_id: 1,
+            title_main: "Test".to_string(),
+            title_en: None,
+            release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        cache.save().unwrap();
+    }
+
+    #[test]
+    fn test_clock_behind_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = Utc::now();
+        write_cache(dir.path());
+
+        // The cache entry was fetched just now: three years ahead of a clock
+        // that fell back to an old date
+        let now = written - Duration::days(3 * 365);
+        let skew = detect_skew(dir.path(), now).unwrap();
+        assert!(skew.source.ends_with(".anidb2folder-cache.json"));
+        assert_eq!(skew.ahead_by.num_days(), 3 * 365);
+        assert!(skew.to_string().contains("1095 days ahead"));
+    }
+
+    #[test]
+    fn test_no_skew_with_past_or_no_records() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(detect_skew(dir.path(), Utc::now()), None);
+
+        write_cache(dir.path());
+        assert_eq!(detect_skew(dir.path(), Utc::now()), None);
+        // Records long in the past are normal for a library left alone
+        assert_eq!(
+            detect_skew(dir.path(), Utc::now() + Duration::days(3 * 365)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe(Duration::days(1)), "1 day");
+        assert_eq!(describe(Duration::hours(30)), "1 day");
+        assert_eq!(describe(Duration::hours(5)), "5 hours");
+        assert_eq!(describe(Duration::minutes(7)), "7 minutes");
+    }
+}
@@ -0,0 +1,210 @@
+//! Word-level differences between two names.
+//!
+//! Used by `--preview diff` to show which part of a directory name a rename
+//! changes. Names are split into words (runs of letters and digits),
+//! whitespace runs and single punctuation characters, so a changed year
+//! in `(2019)` shows as just the digits.
+
+/// A piece of a word diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// In both names
+    Same(&'a str),
+    /// Only in the old name
+    Removed(&'a str),
+    /// Only in the new name
+    Added(&'a str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Word,
+    Space,
+    Symbol,
+}
+
+impl Class {
+    fn of(c: char) -> Self {
+        if c.is_alphanumeric() {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Symbol
+        }
+    }
+}
+
+/// Byte ranges of the tokens of `text`
+fn tokens(text: &str) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous = None;
+
+    for (i, c) in text.char_indices() {
+        let class = Class::of(c);
+        if previous.is_some_and(|p| p != class || class == Class::Symbol) {
+            tokens.push((start, i));
+            start = i;
+        }
+        previous = Some(class);
+    }
+    if start < text.len() {
+        tokens.push((start, text.len()));
+    }
+    tokens
+}
+
+/// Word diff turning `old` into `new`
+///
+/// Unchanged tokens are kept in order by a longest common subsequence. Within
+/// each changed stretch the removed text comes before the added text, and
+/// neighbouring tokens of one kind are joined into one segment.
+pub fn word_diff<'a>(old: &'a str, new: &'a str) -> Vec<Segment<'a>> {
+    let a = tokens(old);
+    let b = tokens(new);
+    let token_a = |i: usize| &old[a[i].0..a[i].1];
+    let token_b = |j: usize| &new[b[j].0..b[j].1];
+
+    // lcs[i][j]: common subsequence length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if token_a(i) == token_b(j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut segments = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && token_a(i) == token_b(j) {
+            let start = i;
+            while i < a.len() && j < b.len() && token_a(i) == token_b(j) {
+                i += 1;
+                j += 1;
+            }
+            segments.push(Segment::Same(&old[a[start].0..a[i - 1].1]));
+            continue;
+        }
+
+        // A changed stretch: everything up to the next common token
+        let (start_i, start_j) = (i, j);
+        while (i < a.len() || j < b.len())
+            && !(i < a.len() && j < b.len() && token_a(i) == token_b(j))
+        {
+            if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        if i > start_i {
+            segments.push(Segment::Removed(&old[a[start_i].0..a[i - 1].1]));
+        }
+        if j > start_j {
+            segments.push(Segment::Added(&new[b[start_j].0..b[j - 1].1]));
+        }
+    }
+    segments
+}
+
+/// Render a diff without colors, marking changes like `git diff --word-diff`:
+/// `[-removed-]` and `{+added+}`
+pub fn render_plain(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Same(text) => text.to_string(),
+            Segment::Removed(text) => format!("[-{}-]", text),
+            Segment::Added(text) => format!("{{+{}+}}", text),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let text = "Title ／ Other (2020) [anidb-1]";
+        let words: Vec<&str> = tokens(text).iter().map(|&(s, e)| &text[s..e]).collect();
+        assert_eq!(
+            words,
+            [
+                "Title", " ", "／", " ", "Other", " ", "(", "2020", ")", " ", "[", "anidb", "-",
+                "1", "]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_year() {
+        let diff = word_diff(
+            "Cowboy Bebop (1998) [anidb-23]",
+            "Cowboy Bebop (1999) [anidb-23]",
+        );
+        assert_eq!(
+            diff,
+            [
+                Segment::Same("Cowboy Bebop ("),
+                Segment::Removed("1998"),
+                Segment::Added("1999"),
+                Segment::Same(") [anidb-23]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unicode_titles() {
+        let diff = word_diff(
+            "進撃の巨人 ／ Attack on Titan (2013) [anidb-9541]",
+            "進撃の巨人 ／ Attack on Titan Season 1 (2013) [anidb-9541]",
+        );
+        assert_eq!(
+            diff,
+            [
+                Segment::Same("進撃の巨人 ／ Attack on Titan "),
+                Segment::Added("Season 1 "),
+                Segment::Same("(2013) [anidb-9541]"),
+            ]
+        );
+
+        let diff = word_diff("Fate／stay night [anidb-1]", "Fate／Zero [anidb-1]");
+        assert_eq!(
+            diff,
+            [
+                Segment::Same("Fate／"),
+                Segment::Removed("stay night"),
+                Segment::Added("Zero"),
+                Segment::Same(" [anidb-1]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_id_to_readable() {
+        let diff = word_diff("12345", "Anime (2020) [anidb-12345]");
+        assert_eq!(
+            diff,
+            [
+                Segment::Added("Anime (2020) [anidb-"),
+                Segment::Same("12345"),
+                Segment::Added("]"),
+            ]
+        );
+        assert_eq!(render_plain(&diff), "{+Anime (2020) [anidb-+}12345{+]+}");
+    }
+
+    #[test]
+    fn test_identical_and_empty() {
+        assert_eq!(word_diff("Same", "Same"), [Segment::Same("Same")]);
+        assert_eq!(word_diff("", "New"), [Segment::Added("New")]);
+        assert_eq!(word_diff("Old", ""), [Segment::Removed("Old")]);
+        assert!(word_diff("", "").is_empty());
+    }
+}
@@ -12,6 +12,90 @@ pub enum ExitCode {
     HistoryError = 8,
     RenameError = 9,
     CacheError = 10,
+    AmbiguousNames = 11,
+    NothingToDo = 12,
+}
+
+impl ExitCode {
+    /// Every exit code, in numeric order
+    ///
+    /// Released numbers never change or get reused; new codes are appended.
+    pub fn all() -> &'static [ExitCode] {
+        &[
+            ExitCode::Success,
+            ExitCode::GeneralError,
+            ExitCode::InvalidArguments,
+            ExitCode::DirectoryNotFound,
+            ExitCode::MixedFormats,
+            ExitCode::UnrecognizedFormat,
+            ExitCode::ApiError,
+            ExitCode::PermissionError,
+            ExitCode::HistoryError,
+            ExitCode::RenameError,
+            ExitCode::CacheError,
+            ExitCode::AmbiguousNames,
+            ExitCode::NothingToDo,
+        ]
+    }
+
+    pub fn code(&self) -> i32 {
+        *self as i32
+    }
+
+    /// Stable snake_case name, for scripts that prefer names over numbers
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExitCode::Success => "success",
+            ExitCode::GeneralError => "general_error",
+            ExitCode::InvalidArguments => "invalid_arguments",
+            ExitCode::DirectoryNotFound => "directory_not_found",
+            ExitCode::MixedFormats => "mixed_formats",
+            ExitCode::UnrecognizedFormat => "unrecognized_format",
+            ExitCode::ApiError => "api_error",
+            ExitCode::PermissionError => "permission_error",
+            ExitCode::HistoryError => "history_error",
+            ExitCode::RenameError => "rename_error",
+            ExitCode::CacheError => "cache_error",
+            ExitCode::AmbiguousNames => "ambiguous_names",
+            ExitCode::NothingToDo => "nothing_to_do",
+        }
+    }
+
+    /// Rank used to report one code for several failures, higher first
+    ///
+    /// A failed rename, which may leave a library half done, outranks the
+    /// errors that stop a run before anything moves.
+    pub fn severity(&self) -> u8 {
+        match self {
+            ExitCode::Success => 0,
+            ExitCode::NothingToDo => 1,
+            ExitCode::GeneralError => 2,
+            ExitCode::InvalidArguments => 3,
+            ExitCode::MixedFormats | ExitCode::UnrecognizedFormat | ExitCode::AmbiguousNames => 4,
+            ExitCode::DirectoryNotFound | ExitCode::HistoryError => 5,
+            ExitCode::ApiError | ExitCode::CacheError => 6,
+            ExitCode::PermissionError => 7,
+            ExitCode::RenameError => 8,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ExitCode::Success => "Completed successfully",
+            ExitCode::GeneralError => "Unexpected error, or some of several operations failed",
+            ExitCode::InvalidArguments => "Invalid arguments or refused target directory",
+            ExitCode::DirectoryNotFound => "Target directory missing or not a directory",
+            ExitCode::MixedFormats => "Target mixes AniDB and human-readable names",
+            ExitCode::UnrecognizedFormat => "Directory names in no recognized format",
+            ExitCode::ApiError => "AniDB API request failed or returned incomplete data",
+            ExitCode::PermissionError => "Permission denied",
+            ExitCode::HistoryError => "History file missing, invalid or not revertible",
+            ExitCode::RenameError => "A rename failed on the filesystem",
+            ExitCode::CacheError => "Cache file could not be read or written",
+            ExitCode::AmbiguousNames => "Directory names refused by --strict as likely mistakes",
+            ExitCode::NothingToDo => "--cache-clear or --cache-prune found nothing to remove",
+        }
+    }
 }
 
 impl From<ExitCode> for i32 {
@@ -37,6 +121,53 @@ mod tests {
         assert_eq!(ExitCode::HistoryError as i32, 8);
         assert_eq!(ExitCode::RenameError as i32, 9);
         assert_eq!(ExitCode::CacheError as i32, 10);
+        assert_eq!(ExitCode::AmbiguousNames as i32, 11);
+        assert_eq!(ExitCode::NothingToDo as i32, 12);
+    }
+
+    /// Snapshot of the released mapping: numbers and names must never change
+    #[test]
+    fn test_exit_code_mapping_is_stable() {
+        let mapping: Vec<(i32, &str)> = ExitCode::all()
+            .iter()
+            .map(|c| (c.code(), c.name()))
+            .collect();
+        assert_eq!(
+            mapping,
+            [
+                (0, "success"),
+                (1, "general_error"),
+                (2, "invalid_arguments"),
+                (3, "directory_not_found"),
+                (4, "mixed_formats"),
+                (5, "unrecognized_format"),
+                (6, "api_error"),
+                (7, "permission_error"),
+                (8, "history_error"),
+                (9, "rename_error"),
+                (10, "cache_error"),
+                (11, "ambiguous_names"),
+                (12, "nothing_to_do"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_severity() {
+        let worst = ExitCode::all().iter().max_by_key(|c| c.severity());
+        assert_eq!(worst, Some(&ExitCode::RenameError));
+        assert!(ExitCode::GeneralError.severity() < ExitCode::HistoryError.severity());
+        assert!(ExitCode::Success.severity() < ExitCode::NothingToDo.severity());
+    }
+
+    #[test]
+    fn test_exit_codes_are_unique() {
+        let codes: Vec<i32> = ExitCode::all().iter().map(|c| c.code()).collect();
+        let mut names: Vec<&str> = ExitCode::all().iter().map(|c| c.name()).collect();
+        names.sort();
+        names.dedup();
+        assert!(codes.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(names.len(), ExitCode::all().len());
     }
 
     #[test]
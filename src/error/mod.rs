@@ -2,6 +2,7 @@ mod codes;
 
 pub use codes::ExitCode;
 
+use crate::i18n::Messages;
 use crate::scanner::ScannerError;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -17,6 +18,15 @@ pub enum AppError {
     #[error("Permission denied: {path}")]
     PermissionDenied { path: PathBuf },
 
+    #[error("Target is an anime directory: {path}")]
+    TargetIsAnimeDirectory { path: PathBuf },
+
+    #[error("Target directories overlap: {outer} contains {inner}")]
+    OverlappingTargets { outer: PathBuf, inner: PathBuf },
+
+    #[error("Refusing to operate on {path}: {reason}")]
+    DangerousTarget { path: PathBuf, reason: String },
+
     #[error("Mixed directory formats found")]
     MixedFormats {
         anidb_count: usize,
@@ -25,8 +35,18 @@ pub enum AppError {
         readable_examples: Vec<String>,
     },
 
-    #[error("Unrecognized directory format")]
-    UnrecognizedFormat { directories: Vec<String> },
+    #[error("Unrecognized directory format ({total} directories)")]
+    UnrecognizedFormat {
+        /// The first unrecognized names (see [`UNRECOGNIZED_LIST_CAP`](crate::validator::UNRECOGNIZED_LIST_CAP))
+        directories: Vec<String>,
+        total: usize,
+    },
+
+    #[error("Ambiguous directory names ({} directories)", reasons.len())]
+    AmbiguousNames {
+        /// Each refused name with the reason
+        reasons: Vec<String>,
+    },
 
     #[error("API error for anime {anidb_id}: {message}")]
     ApiError { anidb_id: u32, message: String },
@@ -34,14 +54,20 @@ pub enum AppError {
     #[error("Incomplete data from AniDB for anime {anidb_id}: missing {field}")]
     IncompleteData { anidb_id: u32, field: String },
 
-    /// TODO(feature-42): Implement From<HistoryError> for AppError and use in main.rs revert flow
-    #[allow(dead_code)]
     #[error("History file error: {message}")]
     HistoryError {
         path: Option<PathBuf>,
         message: String,
     },
 
+    #[error("{failed} of {total} history files failed to revert")]
+    BatchFailed {
+        failed: usize,
+        total: usize,
+        /// Code of the most severe failure (see [`ExitCode::severity`])
+        code: ExitCode,
+    },
+
     #[error("Rename failed: {from} -> {to}")]
     RenameError {
         from: String,
@@ -50,9 +76,19 @@ pub enum AppError {
         source: std::io::Error,
     },
 
+    #[error("{} director(y/ies) cannot be renamed by this user", problems.len())]
+    PermissionProblems { problems: Vec<String> },
+
     #[error("Cache error: {message}")]
     CacheError { message: String },
 
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// A command had nothing to do; its output already says so
+    #[error("{0}")]
+    NothingToDo(String),
+
     #[error("{0}")]
     Other(String),
 }
@@ -63,87 +99,123 @@ impl AppError {
             AppError::DirectoryNotFound { .. } => ExitCode::DirectoryNotFound,
             AppError::NotADirectory { .. } => ExitCode::DirectoryNotFound,
             AppError::PermissionDenied { .. } => ExitCode::PermissionError,
+            AppError::TargetIsAnimeDirectory { .. } => ExitCode::InvalidArguments,
+            AppError::OverlappingTargets { .. } => ExitCode::InvalidArguments,
+            AppError::DangerousTarget { .. } => ExitCode::InvalidArguments,
             AppError::MixedFormats { .. } => ExitCode::MixedFormats,
             AppError::UnrecognizedFormat { .. } => ExitCode::UnrecognizedFormat,
+            AppError::AmbiguousNames { .. } => ExitCode::AmbiguousNames,
             AppError::ApiError { .. } => ExitCode::ApiError,
             AppError::IncompleteData { .. } => ExitCode::ApiError,
             AppError::HistoryError { .. } => ExitCode::HistoryError,
+            AppError::BatchFailed { code, .. } => *code,
             AppError::RenameError { .. } => ExitCode::RenameError,
+            AppError::PermissionProblems { .. } => ExitCode::PermissionError,
             AppError::CacheError { .. } => ExitCode::CacheError,
+            AppError::InvalidArgument(_) => ExitCode::InvalidArguments,
+            AppError::NothingToDo(_) => ExitCode::NothingToDo,
             AppError::Other(_) => ExitCode::GeneralError,
         }
     }
 
+    /// Explanation of the error with hints, in English
+    #[allow(dead_code)]
     pub fn detailed_message(&self) -> String {
+        self.detailed_message_in(&Messages::default())
+    }
+
+    /// Explanation of the error with hints, in the language of `messages`
+    pub fn detailed_message_in(&self, messages: &Messages) -> String {
         match self {
             AppError::DirectoryNotFound { path } => {
-                format!(
-                    "The specified directory does not exist:\n  {}\n\n\
-                     Please verify the path and try again.",
-                    path.display()
-                )
+                messages.format("error.directory_not_found", &[("path", &path.display())])
             }
 
             AppError::NotADirectory { path } => {
-                format!(
-                    "The specified path is not a directory:\n  {}\n\n\
-                     Please provide a valid directory path.",
-                    path.display()
-                )
+                messages.format("error.not_a_directory", &[("path", &path.display())])
             }
 
             AppError::PermissionDenied { path } => {
-                format!(
-                    "Permission denied when accessing:\n  {}\n\n\
-                     Please check file permissions or run with appropriate privileges.",
-                    path.display()
+                messages.format("error.permission_denied", &[("path", &path.display())])
+            }
+
+            AppError::TargetIsAnimeDirectory { path } => {
+                let parent = path
+                    .parent()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default();
+                messages.format(
+                    "error.target_is_anime_directory",
+                    &[("path", &path.display()), ("parent", &parent)],
                 )
             }
 
+            AppError::OverlappingTargets { outer, inner } => messages.format(
+                "error.overlapping_targets",
+                &[("outer", &outer.display()), ("inner", &inner.display())],
+            ),
+
+            AppError::DangerousTarget { path, reason } => messages.format(
+                "error.dangerous_target",
+                &[("path", &path.display()), ("reason", reason)],
+            ),
+
             AppError::MixedFormats {
                 anidb_count,
                 readable_count,
                 anidb_examples,
                 readable_examples,
             } => {
-                let mut msg = format!(
-                    "Found directories in multiple formats:\n\
-                     - {} in AniDB format\n\
-                     - {} in human-readable format\n\n",
-                    anidb_count, readable_count
+                let mut msg = messages.format(
+                    "error.mixed_formats",
+                    &[("anidb", anidb_count), ("readable", readable_count)],
                 );
 
                 if !anidb_examples.is_empty() {
-                    msg.push_str("AniDB format examples:\n");
+                    msg.push_str(messages.get("error.mixed_formats.anidb_examples"));
+                    msg.push('\n');
                     for ex in anidb_examples.iter().take(3) {
                         msg.push_str(&format!("  - {}\n", ex));
                     }
                 }
 
                 if !readable_examples.is_empty() {
-                    msg.push_str("\nHuman-readable format examples:\n");
+                    msg.push('\n');
+                    msg.push_str(messages.get("error.mixed_formats.readable_examples"));
+                    msg.push('\n');
                     for ex in readable_examples.iter().take(3) {
                         msg.push_str(&format!("  - {}\n", ex));
                     }
                 }
 
-                msg.push_str(
-                    "\nAll directories must be in the same format.\n\
-                     Manually rename mixed directories before running again.",
-                );
+                msg.push('\n');
+                msg.push_str(messages.get("error.mixed_formats.hint"));
                 msg
             }
 
-            AppError::UnrecognizedFormat { directories } => {
-                let mut msg =
-                    String::from("The following directories do not match any known format:\n");
-                for dir in directories.iter().take(10) {
-                    msg.push_str(&format!("  - {}\n", dir));
+            AppError::UnrecognizedFormat { directories, total } => {
+                let mut msg = format!("{}\n", messages.get("error.unrecognized"));
+                for dir in directories {
+                    match crate::validator::suggest_name(dir) {
+                        Some(name) => msg.push_str(&format!(
+                            "  - {} ({})\n",
+                            dir,
+                            messages.format("error.unrecognized.suggestion", &[("name", &name)])
+                        )),
+                        None => msg.push_str(&format!("  - {}\n", dir)),
+                    }
                 }
-                if directories.len() > 10 {
-                    msg.push_str(&format!("  ... and {} more\n", directories.len() - 10));
+                if *total > directories.len() {
+                    let more = total - directories.len();
+                    msg.push_str(&format!(
+                        "  {}\n",
+                        messages.format("error.unrecognized.more", &[("count", &more)])
+                    ));
                 }
-                msg.push_str("\nExpected formats:\n");
+                msg.push_str(&format!(
+                    "\n{}\n",
+                    messages.get("error.unrecognized.expected")
+                ));
                 msg.push_str("  AniDB:          [<series>] <anidb_id>\n");
                 msg.push_str("                  Examples: 12345, [AS0] 67890\n");
                 msg.push_str("  Human-readable: <title> (<year>) [anidb-<id>]\n");
@@ -151,65 +223,62 @@ impl AppError {
                 msg
             }
 
-            AppError::ApiError { anidb_id, message } => {
-                format!(
-                    "Failed to fetch data for anime ID {}:\n  {}\n\n\
-                     This could be due to:\n\
-                     - Network connectivity issues\n\
-                     - AniDB API rate limiting\n\
-                     - Invalid anime ID\n\n\
-                     Try again later or check your internet connection.",
-                    anidb_id, message
-                )
+            AppError::AmbiguousNames { reasons } => {
+                let mut msg = format!("{}\n", messages.get("error.ambiguous"));
+                for reason in reasons {
+                    msg.push_str(&format!("  - {}\n", reason));
+                }
+                msg.push('\n');
+                msg.push_str(messages.get("error.ambiguous.hint"));
+                msg
             }
 
-            AppError::IncompleteData { anidb_id, field } => {
-                format!(
-                    "AniDB returned incomplete data for anime ID {}:\n  Missing: {}\n\n\
-                     The anime exists in AniDB but is missing required metadata.\n\
-                     This can happen with:\n\
-                     - Very new entries not yet fully populated\n\
-                     - Entries pending moderation\n\
-                     - Rare edge cases in AniDB's database\n\n\
-                     Check the entry at: https://anidb.net/anime/{}",
-                    anidb_id, field, anidb_id
-                )
+            AppError::ApiError { anidb_id, message } => {
+                messages.format("error.api", &[("id", anidb_id), ("message", message)])
             }
 
+            AppError::IncompleteData { anidb_id, field } => messages.format(
+                "error.incomplete_data",
+                &[("id", anidb_id), ("field", field)],
+            ),
+
             AppError::HistoryError { path, message } => {
-                let path_info = path
+                let file = path
                     .as_ref()
-                    .map(|p| format!("File: {}\n", p.display()))
+                    .map(|p| messages.format("error.history.file", &[("path", &p.display())]))
                     .unwrap_or_default();
-
-                format!(
-                    "History file error:\n  {}\n{}\n\
-                     Ensure the history file exists and is valid JSON.",
-                    message, path_info
-                )
+                messages.format("error.history", &[("message", message), ("file", &file)])
             }
 
-            AppError::RenameError { from, to, source } => {
-                format!(
-                    "Failed to rename directory:\n\
-                     From: {}\n\
-                     To:   {}\n\
-                     Error: {}\n\n\
-                     Check file permissions and ensure no files are open.",
-                    from, to, source
-                )
+            AppError::BatchFailed { failed, total, .. } => messages.format(
+                "error.batch_failed",
+                &[("failed", failed), ("total", total)],
+            ),
+
+            AppError::RenameError { from, to, source } => messages.format(
+                "error.rename",
+                &[("from", from), ("to", to), ("error", source)],
+            ),
+
+            AppError::PermissionProblems { problems } => {
+                let mut msg = format!("{}\n", messages.get("error.permission_problems"));
+                for problem in problems {
+                    msg.push_str(&format!("  - {}\n", problem));
+                }
+                msg.push('\n');
+                msg.push_str(messages.get("error.permission_problems.hint"));
+                msg
             }
 
             AppError::CacheError { message } => {
-                format!(
-                    "Cache error: {}\n\n\
-                     The cache file may be corrupted. \
-                     Delete the cache file to rebuild it.",
-                    message
-                )
+                messages.format("error.cache", &[("message", message)])
             }
 
-            AppError::Other(message) => message.clone(),
+            AppError::InvalidArgument(message) => {
+                messages.format("error.invalid_argument", &[("message", message)])
+            }
+
+            AppError::NothingToDo(message) | AppError::Other(message) => message.clone(),
         }
     }
 }
@@ -220,6 +289,13 @@ impl From<ScannerError> for AppError {
             ScannerError::PathNotFound(path) => AppError::DirectoryNotFound { path },
             ScannerError::NotADirectory(path) => AppError::NotADirectory { path },
             ScannerError::PermissionDenied(path) => AppError::PermissionDenied { path },
+            ScannerError::TargetIsAnimeDirectory(path) => AppError::TargetIsAnimeDirectory { path },
+            ScannerError::OverlappingTargets { outer, inner } => {
+                AppError::OverlappingTargets { outer, inner }
+            }
+            ScannerError::DangerousTarget { path, reason } => {
+                AppError::DangerousTarget { path, reason }
+            }
             ScannerError::IoError(e) => AppError::Other(format!("I/O error: {}", e)),
         }
     }
@@ -229,8 +305,8 @@ impl From<crate::validator::ValidationError> for AppError {
     fn from(err: crate::validator::ValidationError) -> Self {
         use crate::validator::ValidationError;
         match err {
-            ValidationError::UnrecognizedDirectories { directories } => {
-                AppError::UnrecognizedFormat { directories }
+            ValidationError::UnrecognizedDirectories { directories, total } => {
+                AppError::UnrecognizedFormat { directories, total }
             }
             ValidationError::MixedFormats { mismatch } => AppError::MixedFormats {
                 anidb_count: mismatch.anidb_dirs.len(),
@@ -241,6 +317,9 @@ impl From<crate::validator::ValidationError> for AppError {
             ValidationError::NoDirectories => {
                 AppError::Other("No subdirectories found in target".to_string())
             }
+            ValidationError::AmbiguousDirectories { reasons } => {
+                AppError::AmbiguousNames { reasons }
+            }
         }
     }
 }
@@ -280,7 +359,7 @@ impl From<crate::api::ApiError> for AppError {
             },
             ApiError::NotConfigured => AppError::ApiError {
                 anidb_id: 0,
-                message: "API client not configured. Set ANIDB_CLIENT and ANIDB_CLIENT_VERSION environment variables or create a .env file".to_string(),
+                message: "API client not configured. Set ANIDB_CLIENT and ANIDB_CLIENT_VERSION environment variables, create a .env file or pass --credentials-file".to_string(),
             },
             ApiError::Banned(msg) => AppError::ApiError {
                 anidb_id: 0,
@@ -290,6 +369,38 @@ impl From<crate::api::ApiError> for AppError {
     }
 }
 
+impl From<crate::api::CredentialsError> for AppError {
+    fn from(err: crate::api::CredentialsError) -> Self {
+        AppError::InvalidArgument(err.to_string())
+    }
+}
+
+impl From<crate::rename::OverridesError> for AppError {
+    fn from(err: crate::rename::OverridesError) -> Self {
+        AppError::InvalidArgument(err.to_string())
+    }
+}
+
+#[cfg(feature = "api")]
+impl From<crate::api::TitleDumpError> for AppError {
+    fn from(err: crate::api::TitleDumpError) -> Self {
+        AppError::InvalidArgument(err.to_string())
+    }
+}
+
+impl From<crate::rename::TagMapError> for AppError {
+    fn from(err: crate::rename::TagMapError) -> Self {
+        AppError::InvalidArgument(err.to_string())
+    }
+}
+
+impl From<crate::hooks::HookError> for AppError {
+    fn from(err: crate::hooks::HookError) -> Self {
+        AppError::Other(err.to_string())
+    }
+}
+
+#[cfg(feature = "cache")]
 impl From<crate::cache::CacheError> for AppError {
     fn from(err: crate::cache::CacheError) -> Self {
         AppError::CacheError {
@@ -298,6 +409,44 @@ impl From<crate::cache::CacheError> for AppError {
     }
 }
 
+impl From<crate::history::HistoryError> for AppError {
+    fn from(err: crate::history::HistoryError) -> Self {
+        AppError::HistoryError {
+            path: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<crate::revert::RevertError> for AppError {
+    fn from(err: crate::revert::RevertError) -> Self {
+        use crate::revert::RevertError;
+        match err {
+            RevertError::History(err) => err.into(),
+            // Reported as the history no longer being revertible
+            RevertError::ValidationFailed(message) => AppError::HistoryError {
+                path: None,
+                message,
+            },
+            RevertError::RenameError { from, to, source } => {
+                AppError::RenameError { from, to, source }
+            }
+            RevertError::SourceVanished(name) => AppError::RenameError {
+                from: name.clone(),
+                to: name,
+                source: std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "the directory vanished after the revert was prepared",
+                ),
+            },
+            RevertError::InputInside { .. } => AppError::InvalidArgument(err.to_string()),
+            RevertError::TargetChanged { .. }
+            | RevertError::WriteError(_)
+            | RevertError::SerializeError(_) => AppError::Other(err.to_string()),
+        }
+    }
+}
+
 impl From<crate::rename::RenameError> for AppError {
     fn from(err: crate::rename::RenameError) -> Self {
         use crate::rename::RenameError;
@@ -319,6 +468,54 @@ impl From<crate::rename::RenameError> for AppError {
                     "Destination already exists",
                 ),
             },
+            RenameError::InvalidPlaceholder(message) => {
+                AppError::InvalidArgument(format!("--placeholder {}", message))
+            }
+            RenameError::InvalidReplacement(message) => {
+                AppError::InvalidArgument(format!("--replace {}", message))
+            }
+            RenameError::PlaceholderName(name) => AppError::RenameError {
+                from: "source".to_string(),
+                to: name,
+                source: std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Placeholder names are never applied",
+                ),
+            },
+            RenameError::InvalidName(crate::parser::ParseError::UnrecognizedFormat(name)) => {
+                AppError::UnrecognizedFormat {
+                    directories: vec![name],
+                    total: 1,
+                }
+            }
+            RenameError::InvalidName(crate::parser::ParseError::Ambiguous(reason)) => {
+                AppError::AmbiguousNames {
+                    reasons: vec![reason],
+                }
+            }
+            RenameError::DirectoryInUse { name, files } => AppError::RenameError {
+                from: name.clone(),
+                to: name,
+                source: std::io::Error::other(format!(
+                    "{} open file(s) inside; close them or use --on-conflict skip",
+                    files
+                )),
+            },
+            RenameError::PermissionProblems(problems) => AppError::PermissionProblems { problems },
+            RenameError::SourceVanished(name) => AppError::RenameError {
+                from: name.clone(),
+                to: name,
+                source: std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "the directory vanished after planning (skipped without --strict)",
+                ),
+            },
+            RenameError::TargetChanged { expected, actual } => AppError::Other(format!(
+                "Target directory changed during the run: {} now resolves to {}. \
+                 Nothing further was renamed",
+                expected.display(),
+                actual.display()
+            )),
             RenameError::ApiNotConfigured => AppError::ApiError {
                 anidb_id: 0,
                 message: "API client not configured. Set ANIDB_CLIENT and ANIDB_CLIENT_VERSION environment variables".to_string(),
@@ -356,6 +553,7 @@ mod tests {
     fn test_detailed_message_includes_context() {
         let err = AppError::UnrecognizedFormat {
             directories: vec!["dir1".to_string(), "dir2".to_string()],
+            total: 2,
         };
 
         let msg = err.detailed_message();
@@ -364,6 +562,70 @@ mod tests {
         assert!(msg.contains("Expected formats"));
     }
 
+    #[test]
+    fn test_detailed_message_suggests_near_misses() {
+        let err = AppError::UnrecognizedFormat {
+            directories: vec![
+                "Naruto (2002) anidb_12345".to_string(),
+                "[AS0 67890".to_string(),
+                "Random Folder".to_string(),
+            ],
+            total: 3,
+        };
+
+        let msg = err.detailed_message();
+        assert!(msg.contains(
+            "  - Naruto (2002) anidb_12345 (did you mean: Naruto (2002) [anidb-12345]?)\n"
+        ));
+        assert!(msg.contains("  - [AS0 67890 (did you mean: [AS0] 67890?)\n"));
+        assert!(msg.contains("  - Random Folder\n"));
+    }
+
+    #[test]
+    fn test_ambiguous_names() {
+        let err: AppError = crate::validator::ValidationError::AmbiguousDirectories {
+            reasons: vec!["20240131: AniDB ID 20240131 is above 1000000".to_string()],
+        }
+        .into();
+        assert_eq!(err.exit_code(), ExitCode::AmbiguousNames);
+        assert!(err
+            .detailed_message()
+            .contains("  - 20240131: AniDB ID 20240131 is above 1000000\n"));
+    }
+
+    #[test]
+    fn test_detailed_message_in_japanese() {
+        let err = AppError::MixedFormats {
+            anidb_count: 1,
+            readable_count: 2,
+            anidb_examples: vec!["12345".to_string()],
+            readable_examples: vec![],
+        };
+
+        let msg = err.detailed_message_in(&Messages::new(crate::i18n::Lang::Ja));
+        assert!(msg.contains("AniDB 形式: 1 個"));
+        assert!(msg.contains("AniDB 形式の例:\n  - 12345"));
+        assert!(!msg.contains("Human-readable format examples"));
+    }
+
+    #[test]
+    fn test_unrecognized_message_is_bounded() {
+        let entries: Vec<crate::scanner::DirectoryEntry> = (0..8_000)
+            .map(|i| crate::scanner::DirectoryEntry::new(format!("Some unrelated folder {}", i)))
+            .collect();
+        let err: AppError = crate::validator::validate_directories(&entries)
+            .unwrap_err()
+            .into();
+
+        let msg = err.detailed_message();
+        assert!(msg.len() < 1024, "{} bytes", msg.len());
+        assert!(msg.contains("... and 7990 more"));
+        assert_eq!(
+            err.to_string(),
+            "Unrecognized directory format (8000 directories)"
+        );
+    }
+
     #[test]
     fn test_scanner_error_conversion() {
         let scanner_err = ScannerError::PathNotFound(PathBuf::from("/missing"));
@@ -0,0 +1,309 @@
+//! Newline-delimited JSON event stream.
+//!
+//! With `--output ndjson` every step of a run is written to stdout as one
+//! JSON object per line, as it happens, so a dashboard can tail the
+//! process. Each event carries the schema version, a sequence number and a
+//! timestamp; the last event of a stream is always a `summary`, including
+//! the error on failure.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::error::AppError;
+use crate::progress::ProgressObserver;
+use crate::rename::RenameResult;
+use crate::scanner::ScanResult;
+use crate::timing::StepTiming;
+use crate::validator::ValidationResult;
+use crate::version::{BuildInfo, JSON_SCHEMA_VERSION};
+
+/// How run results are written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OutputFormat {
+    /// Human-oriented output (the dry-run plan as `source -> destination` lines)
+    #[default]
+    Text,
+    /// One JSON event per line while running
+    Ndjson,
+    /// One JSON object from a standalone command, as with `--json`
+    Json,
+}
+
+/// Writes progress events as NDJSON
+pub struct EventStream<W: Write> {
+    writer: W,
+    seq: u64,
+    fetching: Option<u32>,
+    finished: bool,
+}
+
+impl<W: Write> EventStream<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 0,
+            fetching: None,
+            finished: false,
+        }
+    }
+
+    /// Write one event; `fields` must be a JSON object
+    pub fn emit(&mut self, event: &str, fields: Value) {
+        self.seq += 1;
+        let mut object = Map::new();
+        object.insert("schema".to_string(), json!(JSON_SCHEMA_VERSION));
+        object.insert("seq".to_string(), json!(self.seq));
+        object.insert("timestamp".to_string(), json!(timestamp(&Utc::now())));
+        object.insert("event".to_string(), json!(event));
+        if let Value::Object(fields) = fields {
+            object.extend(fields);
+        }
+
+        let written =
+            writeln!(self.writer, "{}", Value::Object(object)).and_then(|_| self.writer.flush());
+        if let Err(e) = written {
+            debug!("Failed to write {} event: {}", event, e);
+        }
+    }
+
+    /// Emit a `rename` event per planned operation of a dry run
+    pub fn planned(&mut self, result: &RenameResult) {
+        let total = result.operations.len();
+        for (i, op) in result.operations.iter().enumerate() {
+            let mut fields = json!({
+                "current": i + 1,
+                "total": total,
+                "from": op.source_name,
+                "to": op.destination_name,
+                "dry_run": true,
+            });
+            if let Some(problem) = &op.invalid_name {
+                fields["would_fail"] = json!(problem);
+            }
+            if op.override_applied {
+                fields["override_applied"] = json!(true);
+            }
+            if op.disambiguated {
+                fields["disambiguated"] = json!(true);
+            }
+            if let Some(retag) = &op.retag {
+                fields["retag"] = json!({ "from": retag.from, "to": retag.to });
+            }
+            if let Some(age) = op.cache_age {
+                fields["cache_age_secs"] = json!(age.as_secs());
+            }
+            self.emit("rename", fields);
+        }
+    }
+
+    /// Emit the final `summary` event
+    ///
+    /// `result` is the outcome of the run when it got that far; `error` is
+    /// what stopped it. Later calls are ignored.
+    pub fn summary(
+        &mut self,
+        result: Option<&RenameResult>,
+        history_path: Option<&Path>,
+        error: Option<&AppError>,
+    ) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let mut fields = json!({ "ok": error.is_none(), "build": BuildInfo::current() });
+        if let Some(result) = result {
+            fields["run_id"] = json!(result.run_id);
+            fields["dry_run"] = json!(result.dry_run);
+            fields["direction"] = json!(result.direction.description());
+            fields["operations"] = json!(result.operations.len());
+            fields["applied"] = json!(if result.dry_run {
+                0
+            } else {
+                result.applied_count()
+            });
+            fields["skipped"] = json!(result.skipped_count());
+            fields["truncated"] = json!(result.truncated_count());
+            fields["quarantined"] = json!(result.quarantined_count());
+            fields["trashed"] = json!(result.trashed_count());
+            fields["slowest"] = json!(result.timings.slowest());
+            fields["vanished"] = json!(result.vanished);
+            if let Some(check) = &result.post_verify {
+                fields["post_verify"] = json!(check);
+            }
+            fields["permission_skipped"] = json!(result
+                .permission_skipped
+                .iter()
+                .map(|p| json!({ "name": p.source_name, "reason": p.reason }))
+                .collect::<Vec<_>>());
+            if !result.recent_skipped.is_empty() {
+                fields["recent_skipped"] = json!(result.recent_skipped);
+            }
+            if let Some(overrides) = &result.options.overrides {
+                fields["overrides"] = json!(overrides);
+            }
+            if let Some(tag_map) = &result.options.tag_map {
+                fields["tag_map"] = json!(tag_map);
+            }
+        }
+        if let Some(path) = history_path {
+            fields["history_path"] = json!(path);
+        }
+        if let Some(error) = error {
+            let code = error.exit_code();
+            fields["error"] = json!({
+                "code": code.code(),
+                "name": code.name(),
+                "message": error.to_string(),
+            });
+        }
+        self.emit("summary", fields);
+    }
+}
+
+impl<W: Write> Drop for EventStream<W> {
+    /// A stream cut short (e.g. by a panic) still ends with a summary
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.emit(
+                "summary",
+                json!({ "ok": false, "error": { "message": "run ended unexpectedly" } }),
+            );
+        }
+    }
+}
+
+impl<W: Write> ProgressObserver for EventStream<W> {
+    fn scanned(&mut self, scan: &ScanResult) {
+        self.emit(
+            "scan_done",
+            json!({
+                "directories": scan.entries.len(),
+                "skipped_hidden": scan.skipped_hidden,
+                "skipped_files": scan.skipped_files,
+                "anomalies": scan.artifact_dirs,
+            }),
+        );
+    }
+
+    fn validated(&mut self, validation: &ValidationResult) {
+        self.emit(
+            "validated",
+            json!({ "format": format!("{:?}", validation.format) }),
+        );
+    }
+
+    fn set_fetch_plan(&mut self, total: usize, min_interval: Duration) {
+        self.emit(
+            "fetch_plan",
+            json!({ "total": total, "min_interval_secs": min_interval.as_secs() }),
+        );
+    }
+
+    fn fetch_start(&mut self, anidb_id: u32) {
+        self.fetching = Some(anidb_id);
+        self.emit("fetch_start", json!({ "anidb_id": anidb_id }));
+    }
+
+    fn fetch_complete(&mut self) {
+        let anidb_id = self.fetching.take();
+        self.emit("fetch_done", json!({ "anidb_id": anidb_id }));
+    }
+
+    fn using_cache(&mut self, anidb_id: u32) {
+        self.emit("cache_hit", json!({ "anidb_id": anidb_id }));
+    }
+
+    fn warn(&mut self, message: &str) {
+        self.emit("warning", json!({ "message": message }));
+    }
+
+    fn slow_step(&mut self, timing: &StepTiming) {
+        self.emit("slow_step", json!(timing));
+    }
+
+    fn rename_progress(&mut self, current: usize, total: usize, from: &str, to: &str) {
+        self.emit(
+            "rename",
+            json!({ "current": current, "total": total, "from": from, "to": to }),
+        );
+    }
+
+    fn history_written(&mut self, path: &Path) {
+        self.emit("history_written", json!({ "path": path }));
+    }
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(buffer: &[u8]) -> Vec<Value> {
+        String::from_utf8_lossy(buffer)
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_events_are_numbered() {
+        let mut buffer = Vec::new();
+        {
+            let mut events = EventStream::new(&mut buffer);
+            events.fetch_start(12345);
+            events.fetch_complete();
+            events.warn("careful");
+            events.summary(None, None, None);
+        }
+
+        let events = lines(&buffer);
+        let names: Vec<&str> = events
+            .iter()
+            .map(|e| e["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["fetch_start", "fetch_done", "warning", "summary"]);
+        assert_eq!(events[1]["anidb_id"], 12345);
+        assert_eq!(events[3]["seq"], 4);
+        assert!(events[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_summary_carries_error() {
+        let mut buffer = Vec::new();
+        {
+            let mut events = EventStream::new(&mut buffer);
+            let error = AppError::InvalidArgument("bad".to_string());
+            events.summary(None, None, Some(&error));
+            events.summary(None, None, None);
+        }
+
+        let events = lines(&buffer);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["ok"], false);
+        assert_eq!(events[0]["error"]["code"], 2);
+        assert_eq!(events[0]["error"]["name"], "invalid_arguments");
+    }
+
+    #[test]
+    fn test_dropped_stream_ends_with_summary() {
+        let mut buffer = Vec::new();
+        {
+            let mut events = EventStream::new(&mut buffer);
+            events.warn("interrupted");
+        }
+
+        let events = lines(&buffer);
+        assert_eq!(events.last().unwrap()["event"], "summary");
+        assert_eq!(events.last().unwrap()["ok"], false);
+    }
+}
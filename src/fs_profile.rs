@@ -0,0 +1,391 @@
+//! Filesystem detection for the target directory.
+//!
+//! FAT, exFAT and NTFS (and every volume on Windows) compare names without
+//! regard to case, so two names differing only in case are the same
+//! directory. The detected [`FsProfile`] makes collision checks follow the
+//! filesystem's rules.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::parser::normalize_name;
+
+/// Naming rules of the filesystem holding the target directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum FsProfile {
+    /// Case-sensitive names (ext4, btrfs, xfs, ...)
+    #[default]
+    Posix,
+    /// Case-insensitive names with the Windows character rules (FAT, exFAT, NTFS)
+    Windows,
+}
+
+impl FsProfile {
+    /// Profile matching a filesystem type as reported by [`detect_fs_type`]
+    pub fn for_fs_type(fs_type: &str) -> Self {
+        match fs_type {
+            "vfat" | "msdos" | "fat" | "fat12" | "fat16" | "fat32" | "exfat" | "ntfs" | "ntfs3"
+            | "refs" => FsProfile::Windows,
+            _ => FsProfile::Posix,
+        }
+    }
+
+    /// Whether names differing only in case refer to the same entry
+    pub fn case_insensitive(&self) -> bool {
+        *self == FsProfile::Windows
+    }
+
+    /// Key under which two names collide on this filesystem
+    ///
+    /// Names are compared in NFC: a name listed decomposed (as macOS lists
+    /// them) is the same name to the user.
+    pub fn collision_key(&self, name: &str) -> String {
+        let name = normalize_name(name);
+        if self.case_insensitive() {
+            name.to_lowercase()
+        } else {
+            name.into_owned()
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FsProfile::Posix => "posix",
+            FsProfile::Windows => "windows",
+        }
+    }
+}
+
+/// Longest name most filesystems accept, used when it cannot be detected
+pub const DEFAULT_NAME_MAX: usize = 255;
+
+/// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Check that `name` can be created on a filesystem with `profile` rules
+///
+/// `name_max` is the filesystem's limit, in bytes for POSIX and in UTF-16
+/// units for Windows. The error describes the problem, e.g.
+/// `263 bytes > 255`.
+pub fn validate_destination_name(
+    name: &str,
+    profile: FsProfile,
+    name_max: usize,
+) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(format!("'{}' is not a valid name", name));
+    }
+
+    match profile {
+        FsProfile::Posix => {
+            if name.len() > name_max {
+                return Err(format!("{} bytes > {}", name.len(), name_max));
+            }
+            if let Some(c) = name.chars().find(|&c| c == '/' || c == '\0') {
+                return Err(format!("contains {:?}", c));
+            }
+        }
+        FsProfile::Windows => {
+            let units = name.encode_utf16().count();
+            if units > name_max {
+                return Err(format!("{} UTF-16 units > {}", units, name_max));
+            }
+            if let Some(c) = name.chars().find(|&c| c < ' ' || "<>:\"/\\|?*".contains(c)) {
+                return Err(format!("contains {:?}", c));
+            }
+            if name.ends_with(' ') || name.ends_with('.') {
+                return Err("ends with a space or dot".to_string());
+            }
+            let stem = name.split('.').next().unwrap_or(name).trim_end();
+            if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+                return Err(format!("'{}' is a reserved device name", stem));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Longest file name the filesystem holding `path` accepts
+///
+/// Falls back to [`DEFAULT_NAME_MAX`] where it cannot be asked.
+pub fn detect_name_max(path: &Path) -> usize {
+    platform::name_max(path).unwrap_or(DEFAULT_NAME_MAX)
+}
+
+/// Profile to use for `path`: `requested` if given, else the detected one
+///
+/// Also returns the detected filesystem type, if it could be determined.
+pub fn resolve_profile(path: &Path, requested: Option<FsProfile>) -> (FsProfile, Option<String>) {
+    let fs_type = detect_fs_type(path);
+    let profile = requested.unwrap_or_else(|| {
+        if cfg!(windows) {
+            FsProfile::Windows
+        } else {
+            fs_type
+                .as_deref()
+                .map(FsProfile::for_fs_type)
+                .unwrap_or_default()
+        }
+    });
+    (profile, fs_type)
+}
+
+/// Lowercase name of the filesystem type holding `path`, e.g. `ext4` or `exfat`
+///
+/// `None` when the platform offers no way to ask or the call fails. FUSE
+/// mounts (ntfs-3g, exfat-fuse) are reported as `fuse`.
+pub fn detect_fs_type(path: &Path) -> Option<String> {
+    platform::detect(path)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    /// `f_type` magic numbers from `linux/magic.h`
+    const MAGIC: &[(u64, &str)] = &[
+        (0x4d44, "vfat"),
+        (0x2011_bab0, "exfat"),
+        (0x5346_544e, "ntfs"),
+        (0xef53, "ext4"),
+        (0x9123_683e, "btrfs"),
+        (0x5846_5342, "xfs"),
+        (0x2fc1_2fc1, "zfs"),
+        (0x0102_1994, "tmpfs"),
+        (0x794c_7630, "overlay"),
+        (0x6969, "nfs"),
+        (0xff53_4d42, "cifs"),
+        (0xfe53_4d42, "smb2"),
+        (0x6573_5546, "fuse"),
+        (0x0100_2454, "9p"),
+    ];
+
+    pub fn detect(path: &Path) -> Option<String> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: zeroed is a valid statfs; the path is NUL-terminated and outlives the call
+        let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut buf) } != 0 {
+            return None;
+        }
+
+        // f_type is signed on some targets; the magic numbers fit in 32 bits
+        let magic = buf.f_type as u64 & 0xffff_ffff;
+        Some(
+            MAGIC
+                .iter()
+                .find(|(m, _)| *m == magic)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| format!("0x{:x}", magic)),
+        )
+    }
+
+    pub fn name_max(path: &Path) -> Option<usize> {
+        super::statvfs_name_max(path)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+mod platform {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub fn detect(path: &Path) -> Option<String> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: zeroed is a valid statfs; the path is NUL-terminated and outlives the call
+        let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut buf) } != 0 {
+            return None;
+        }
+
+        // SAFETY: the kernel fills f_fstypename with a NUL-terminated name
+        let name = unsafe { CStr::from_ptr(buf.f_fstypename.as_ptr()) };
+        Some(name.to_string_lossy().to_lowercase())
+    }
+
+    pub fn name_max(path: &Path) -> Option<usize> {
+        super::statvfs_name_max(path)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr::null_mut;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetVolumePathNameW(file_name: *const u16, volume_path: *mut u16, len: u32) -> i32;
+        fn GetVolumeInformationW(
+            root_path: *const u16,
+            volume_name: *mut u16,
+            volume_name_len: u32,
+            serial_number: *mut u32,
+            max_component_len: *mut u32,
+            flags: *mut u32,
+            fs_name: *mut u16,
+            fs_name_len: u32,
+        ) -> i32;
+    }
+
+    pub fn detect(path: &Path) -> Option<String> {
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut root = [0u16; 261];
+        let mut fs_name = [0u16; 261];
+
+        // SAFETY: all buffers are valid for the lengths passed and outlive the calls
+        unsafe {
+            if GetVolumePathNameW(wide.as_ptr(), root.as_mut_ptr(), root.len() as u32) == 0 {
+                return None;
+            }
+            if GetVolumeInformationW(
+                root.as_ptr(),
+                null_mut(),
+                0,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+                fs_name.as_mut_ptr(),
+                fs_name.len() as u32,
+            ) == 0
+            {
+                return None;
+            }
+        }
+
+        let len = fs_name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(fs_name.len());
+        Some(String::from_utf16_lossy(&fs_name[..len]).to_lowercase())
+    }
+
+    pub fn name_max(_path: &Path) -> Option<usize> {
+        // NTFS, exFAT and FAT with long names all allow 255 UTF-16 units
+        Some(255)
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    windows
+)))]
+mod platform {
+    use std::path::Path;
+
+    pub fn detect(_path: &Path) -> Option<String> {
+        None
+    }
+
+    pub fn name_max(_path: &Path) -> Option<usize> {
+        None
+    }
+}
+
+/// `f_namemax` of the filesystem holding `path`
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+fn statvfs_name_max(path: &Path) -> Option<usize> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: zeroed is a valid statvfs; the path is NUL-terminated and outlives the call
+    let mut buf: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut buf) } != 0 {
+        return None;
+    }
+    usize::try_from(buf.f_namemax).ok().filter(|&n| n > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_profile_for_fs_type() {
+        assert_eq!(FsProfile::for_fs_type("exfat"), FsProfile::Windows);
+        assert_eq!(FsProfile::for_fs_type("vfat"), FsProfile::Windows);
+        assert_eq!(FsProfile::for_fs_type("msdos"), FsProfile::Windows);
+        assert_eq!(FsProfile::for_fs_type("ntfs"), FsProfile::Windows);
+        assert_eq!(FsProfile::for_fs_type("ext4"), FsProfile::Posix);
+        assert_eq!(FsProfile::for_fs_type("fuse"), FsProfile::Posix);
+    }
+
+    #[test]
+    fn test_collision_key_follows_case_sensitivity() {
+        assert_eq!(FsProfile::Posix.collision_key("[TV] A"), "[TV] A");
+        assert_eq!(FsProfile::Windows.collision_key("[TV] A"), "[tv] a");
+    }
+
+    #[test]
+    fn test_collision_key_is_nfc() {
+        let decomposed = "Poke\u{301}mon [anidb-1]";
+        assert_eq!(
+            FsProfile::Posix.collision_key(decomposed),
+            "Pok\u{e9}mon [anidb-1]"
+        );
+        assert_eq!(
+            FsProfile::Windows.collision_key(decomposed),
+            "pok\u{e9}mon [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_requested_profile_wins() {
+        let dir = tempdir().unwrap();
+
+        let (profile, _) = resolve_profile(dir.path(), Some(FsProfile::Windows));
+        assert_eq!(profile, FsProfile::Windows);
+
+        let (profile, _) = resolve_profile(dir.path(), Some(FsProfile::Posix));
+        assert_eq!(profile, FsProfile::Posix);
+    }
+
+    #[test]
+    fn test_validate_destination_name_posix() {
+        let long = "a".repeat(263);
+        assert_eq!(
+            validate_destination_name(&long, FsProfile::Posix, 255),
+            Err("263 bytes > 255".to_string())
+        );
+        assert!(
+            validate_destination_name("Title： Sub？ [anidb-1]", FsProfile::Posix, 255).is_ok()
+        );
+        assert!(validate_destination_name("AUX (2020) [anidb-1]", FsProfile::Posix, 255).is_ok());
+        assert!(validate_destination_name("..", FsProfile::Posix, 255).is_err());
+    }
+
+    #[test]
+    fn test_validate_destination_name_windows() {
+        let windows = |name: &str| validate_destination_name(name, FsProfile::Windows, 255);
+
+        assert!(windows("Title： Sub？ [anidb-1]").is_ok());
+        assert_eq!(windows("What?"), Err("contains '?'".to_string()));
+        assert!(windows("Title.").is_err());
+        assert!(windows("aux").is_err());
+        assert!(windows("COM1.txt").is_err());
+        assert!(windows("Console").is_ok());
+        // 100 three-byte characters are 300 bytes but 100 UTF-16 units
+        assert!(windows(&"：".repeat(100)).is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_detect_fs_type_on_linux() {
+        let dir = tempdir().unwrap();
+        assert!(detect_fs_type(dir.path()).is_some());
+        assert!(detect_fs_type(&dir.path().join("missing")).is_none());
+    }
+}
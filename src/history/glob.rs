@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Expand a glob pattern into the matching files, sorted by path
+///
+/// Supports `*` and `?` within a path component, `[abc]`, `[a-z]` and
+/// `[!abc]` character classes, and `**` for any number of directories.
+/// Like a shell, wildcards do not match a leading `.`. Unreadable
+/// directories are skipped.
+pub fn expand_glob(pattern: &str) -> Vec<PathBuf> {
+    let pattern = Path::new(pattern);
+    let mut base = PathBuf::new();
+    let mut parts: Vec<String> = Vec::new();
+
+    for component in pattern.components() {
+        let text = component.as_os_str().to_string_lossy();
+        if parts.is_empty() && !is_pattern(&text) {
+            base.push(component);
+        } else {
+            parts.push(text.to_string());
+        }
+    }
+
+    if parts.is_empty() {
+        // No wildcards: the pattern is a plain path
+        return if base.is_file() {
+            vec![base]
+        } else {
+            Vec::new()
+        };
+    }
+    if base.as_os_str().is_empty() {
+        base.push(".");
+    }
+
+    let parts: Vec<&str> = parts.iter().map(String::as_str).collect();
+    let mut matches = Vec::new();
+    expand(&base, &parts, &mut matches);
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+fn is_pattern(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+fn expand(dir: &Path, parts: &[&str], out: &mut Vec<PathBuf>) {
+    let Some((first, rest)) = parts.split_first() else {
+        return;
+    };
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    if *first == "**" {
+        // Zero directories, then each subdirectory with `**` still pending
+        expand(dir, rest, out);
+        for entry in entries.filter_map(|e| e.ok()) {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                expand(&entry.path(), parts, out);
+            }
+        }
+        return;
+    }
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !component_matches(first, &name) {
+            continue;
+        }
+        let path = entry.path();
+        if rest.is_empty() {
+            if path.is_file() {
+                out.push(path);
+            }
+        } else if path.is_dir() {
+            expand(&path, rest, out);
+        }
+    }
+}
+
+/// Match one path component against a pattern component
+fn component_matches(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    wildcard_match(&pattern, &name)
+}
+
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| wildcard_match(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some(close) = rest.iter().skip(1).position(|&c| c == ']').map(|i| i + 1) else {
+                // Unterminated class: a literal `[`
+                return name.first() == Some(&'[') && wildcard_match(rest, &name[1..]);
+            };
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            class_matches(&rest[..close], c) && wildcard_match(&rest[close + 1..], &name[1..])
+        }
+        Some((&literal, rest)) => {
+            name.first() == Some(&literal) && wildcard_match(rest, &name[1..])
+        }
+    }
+}
+
+/// Match a character against the inside of a `[...]` class
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.split_first() {
+        Some(('!', rest)) | Some(('^', rest)) => (true, rest),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "{}").unwrap();
+    }
+
+    #[test]
+    fn test_component_matches() {
+        assert!(component_matches(
+            "anidb2folder-*.json",
+            "anidb2folder-1.json"
+        ));
+        assert!(component_matches("2026011?", "20260115"));
+        assert!(component_matches("[a-c]x", "bx"));
+        assert!(!component_matches("[!a-c]x", "bx"));
+        assert!(!component_matches("*.json", ".hidden.json"));
+        assert!(!component_matches("*.json", "history.txt"));
+    }
+
+    #[test]
+    fn test_expand_double_star() {
+        let dir = tempdir().unwrap();
+        touch(&dir.path().join("a/anidb2folder-history-1.json"));
+        touch(&dir.path().join("b/c/anidb2folder-history-2.json"));
+        touch(&dir.path().join("anidb2folder-history-3.json"));
+        touch(&dir.path().join("b/other.json"));
+
+        let pattern = format!("{}/**/anidb2folder-history-*.json", dir.path().display());
+        let found = expand_glob(&pattern);
+
+        assert_eq!(
+            found,
+            vec![
+                dir.path().join("a/anidb2folder-history-1.json"),
+                dir.path().join("anidb2folder-history-3.json"),
+                dir.path().join("b/c/anidb2folder-history-2.json"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_plain_path() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("history.json");
+        touch(&file);
+
+        assert_eq!(expand_glob(file.to_str().unwrap()), vec![file.clone()]);
+        assert!(expand_glob(dir.path().join("missing.json").to_str().unwrap()).is_empty());
+    }
+}
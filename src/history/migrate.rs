@@ -0,0 +1,177 @@
+use serde_json::{Map, Value};
+use tracing::debug;
+
+use super::types::HISTORY_VERSION;
+use super::writer::HistoryError;
+
+/// Every released history schema version, oldest first
+///
+/// The last one is [`HISTORY_VERSION`], the version written. Released
+/// versions are never removed: each needs a fixture in
+/// `tests/fixtures/history/` and a migration to the next.
+pub const SUPPORTED_VERSIONS: &[&str] = &["1.0", "1.1", "1.2", "1.3", "1.4"];
+
+/// One upgrade step between consecutive schema versions
+struct Migration {
+    from: &'static str,
+    to: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: "1.0",
+        to: "1.1",
+        apply: v1_0_to_v1_1,
+    },
+    Migration {
+        from: "1.1",
+        to: "1.2",
+        apply: v1_1_to_v1_2,
+    },
+    Migration {
+        from: "1.2",
+        to: "1.3",
+        apply: v1_2_to_v1_3,
+    },
+    Migration {
+        from: "1.3",
+        to: "1.4",
+        apply: v1_3_to_v1_4,
+    },
+];
+
+/// Upgrade a history document of any supported version to [`HISTORY_VERSION`]
+///
+/// Fails with [`HistoryError::VersionMismatch`] for unknown versions,
+/// including those written by a newer release.
+pub fn migrate(mut document: Value) -> Result<Value, HistoryError> {
+    let Some(object) = document.as_object_mut() else {
+        return Err(HistoryError::ReadError(
+            "History file is not a JSON object".to_string(),
+        ));
+    };
+
+    loop {
+        let version = object
+            .get("version")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        if version == HISTORY_VERSION {
+            return Ok(document);
+        }
+
+        let Some(step) = MIGRATIONS.iter().find(|m| m.from == version) else {
+            return Err(HistoryError::VersionMismatch {
+                expected: SUPPORTED_VERSIONS.join(", "),
+                found: version,
+            });
+        };
+        debug!("Migrating history from {} to {}", step.from, step.to);
+        (step.apply)(object);
+        object.insert("version".to_string(), Value::from(step.to));
+    }
+}
+
+/// 1.1 adds optional fields (checksum, options, reverts' origin, and per
+/// change conflict, aside, kind and trash_info); 1.0 documents are valid
+/// 1.1 documents with all of them absent.
+fn v1_0_to_v1_1(_document: &mut Map<String, Value>) {}
+
+/// 1.2 adds the optional run_id; 1.1 documents have none.
+fn v1_1_to_v1_2(_document: &mut Map<String, Value>) {}
+
+/// 1.3 adds the optional build info; 1.2 documents have none.
+fn v1_2_to_v1_3(_document: &mut Map<String, Value>) {}
+
+/// 1.4 adds the optional per change suffix; 1.3 documents have none.
+fn v1_3_to_v1_4(_document: &mut Map<String, Value>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{read_history, ChecksumStatus};
+    use crate::progress::ProgressObserver;
+    use crate::revert::{revert_from_history, RevertOptions};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempfile::tempdir;
+
+    struct Quiet;
+    impl ProgressObserver for Quiet {}
+
+    fn fixture(version: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/history")
+            .join(format!("v{}.json", version))
+    }
+
+    #[test]
+    fn test_migrations_chain_every_version() {
+        assert_eq!(SUPPORTED_VERSIONS.last(), Some(&HISTORY_VERSION));
+        assert_eq!(MIGRATIONS.len(), SUPPORTED_VERSIONS.len() - 1);
+        for (step, pair) in MIGRATIONS.iter().zip(SUPPORTED_VERSIONS.windows(2)) {
+            assert_eq!((step.from, step.to), (pair[0], pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_migrate_rejects_unknown_version() {
+        let document = serde_json::json!({ "version": "2.0" });
+        assert!(matches!(
+            migrate(document),
+            Err(HistoryError::VersionMismatch { found, .. }) if found == "2.0"
+        ));
+    }
+
+    /// The current version's fixture is exactly what this build writes, so a
+    /// change to the serialized form fails here until the schema is bumped
+    #[test]
+    fn test_current_fixture_round_trips_unchanged() {
+        let text = fs::read_to_string(fixture(HISTORY_VERSION)).unwrap();
+        let history: crate::history::HistoryFile = serde_json::from_str(&text).unwrap();
+
+        assert_eq!(
+            serde_json::to_string_pretty(&history).unwrap(),
+            text.trim_end()
+        );
+    }
+
+    /// Every fixture loads, migrates to the current version and drives a revert
+    #[test]
+    fn test_every_fixture_migrates_and_reverts() {
+        for version in SUPPORTED_VERSIONS {
+            let text = fs::read_to_string(fixture(version))
+                .unwrap_or_else(|e| panic!("missing fixture for {}: {}", version, e));
+            let mut document: Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(document["version"], *version);
+
+            // Point the fixture at a directory holding its renamed directories
+            let dir = tempdir().unwrap();
+            let target = fs::canonicalize(dir.path()).unwrap();
+            document["target_directory"] = Value::from(target.to_str().unwrap());
+            for change in document["changes"].as_array().unwrap() {
+                if change.get("conflict") != Some(&Value::from("skip")) {
+                    fs::create_dir_all(target.join(change["destination"].as_str().unwrap()))
+                        .unwrap();
+                }
+            }
+            let path = target.join(format!("anidb2folder-history-v{}.json", version));
+            fs::write(&path, serde_json::to_string_pretty(&document).unwrap()).unwrap();
+
+            let history = read_history(&path).unwrap();
+            assert_eq!(history.version, HISTORY_VERSION, "fixture {}", version);
+            assert_ne!(history.checksum_status(), ChecksumStatus::Mismatch);
+
+            let options = RevertOptions {
+                dry_run: true,
+                ..Default::default()
+            };
+            let result = revert_from_history(&path, &options, &mut Quiet)
+                .unwrap_or_else(|e| panic!("fixture {} does not revert: {}", version, e));
+            let applied = history.changes.iter().filter(|c| !c.is_skipped()).count();
+            assert_eq!(result.operations.len(), applied, "fixture {}", version);
+        }
+    }
+}
@@ -1,7 +1,12 @@
+mod glob;
+mod migrate;
 mod reader;
 mod types;
 mod writer;
 
-pub use reader::{read_history, validate_for_revert};
+pub use glob::expand_glob;
+#[allow(unused_imports)]
+pub use migrate::{migrate, SUPPORTED_VERSIONS};
+pub use reader::{find_history_files, read_history, read_history_strict, validate_for_revert};
 pub use types::*;
-pub use writer::{write_history, HistoryError};
+pub use writer::{reserve_unique_path, write_history, HistoryError, IncrementalHistory};
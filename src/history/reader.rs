@@ -1,34 +1,76 @@
-use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
 
+use tracing::warn;
+
+use super::migrate::migrate;
 use super::types::*;
 use super::writer::HistoryError;
 
 /// Read and parse a history file
+///
+/// A checksum mismatch is logged as a warning; use [`read_history_strict`]
+/// to reject edited or corrupted files.
 pub fn read_history(path: &Path) -> Result<HistoryFile, HistoryError> {
+    let history = load_history(path)?;
+
+    if history.checksum_status() == ChecksumStatus::Mismatch {
+        warn!("History checksum mismatch in {:?}", path);
+    }
+
+    Ok(history)
+}
+
+/// Read and parse a history file, failing if its checksum does not match
+pub fn read_history_strict(path: &Path) -> Result<HistoryFile, HistoryError> {
+    let history = load_history(path)?;
+
+    if history.checksum_status() == ChecksumStatus::Mismatch {
+        return Err(HistoryError::ChecksumMismatch);
+    }
+
+    Ok(history)
+}
+
+/// Find history files (renames and reverts) in a directory, sorted by name
+///
+/// Directories by such a name are left out.
+pub fn find_history_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| !t.is_dir()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with("anidb2folder-") && n.ends_with(".json"))
+        })
+        .collect();
+
+    files.sort();
+    Ok(files)
+}
+
+fn load_history(path: &Path) -> Result<HistoryFile, HistoryError> {
     let file = File::open(path)
         .map_err(|e| HistoryError::ReadError(format!("Cannot open file: {}", e)))?;
 
     let reader = BufReader::new(file);
-    let history: HistoryFile = serde_json::from_reader(reader)
+    let document: serde_json::Value = serde_json::from_reader(reader)
         .map_err(|e| HistoryError::ReadError(format!("Invalid JSON: {}", e)))?;
 
-    // Version check
-    if history.version != HISTORY_VERSION {
-        return Err(HistoryError::VersionMismatch {
-            expected: HISTORY_VERSION.to_string(),
-            found: history.version,
-        });
-    }
-
-    Ok(history)
+    // Older versions are upgraded; unknown ones are rejected
+    let document = migrate(document)?;
+    serde_json::from_value(document)
+        .map_err(|e| HistoryError::ReadError(format!("Invalid history file: {}", e)))
 }
 
 /// Validate that a history file can be used for revert on the given target directory
 pub fn validate_for_revert(history: &HistoryFile, target_dir: &Path) -> Result<(), HistoryError> {
-    // Check target directory matches
-    if history.target_directory != target_dir {
+    // Check target directory matches, however either path was spelled
+    let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    if canonical(&history.target_directory) != canonical(target_dir) {
         return Err(HistoryError::ReadError(format!(
             "History file is for different directory: {:?}",
             history.target_directory
@@ -54,12 +96,23 @@ mod tests {
             direction: HistoryDirection::AnidbToReadable,
             target_directory: PathBuf::from("/test/anime"),
             tool_version: "0.1.0".to_string(),
+            build: None,
             changes: vec![HistoryEntry {
                 source: "12345".to_string(),
                 destination: "Anime (2020) [anidb-12345]".to_string(),
                 anidb_id: 12345,
                 truncated: false,
+                conflict: None,
+                aside: None,
+                kind: EntryKind::Rename,
+                trash_info: None,
+                suffix: None,
             }],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
         }
     }
 
@@ -131,4 +184,56 @@ mod tests {
         let result = validate_for_revert(&history, Path::new("/different/path"));
         assert!(matches!(result, Err(HistoryError::ReadError(_))));
     }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history.json");
+
+        let mut history = create_test_history();
+        history.seal();
+        history.changes[0].anidb_id = 1;
+        fs::write(&path, serde_json::to_string(&history).unwrap()).unwrap();
+
+        assert!(read_history(&path).is_ok());
+        assert!(matches!(
+            read_history_strict(&path),
+            Err(HistoryError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_loads_file_without_checksum() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history.json");
+
+        let history = create_test_history();
+        fs::write(&path, serde_json::to_string(&history).unwrap()).unwrap();
+
+        let loaded = read_history_strict(&path).unwrap();
+        assert_eq!(loaded.checksum_status(), ChecksumStatus::Missing);
+    }
+
+    #[test]
+    fn test_find_history_files() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("anidb2folder-history-20260102-000000.json"),
+            "{}",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("anidb2folder-history-20260101-000000.json"),
+            "{}",
+        )
+        .unwrap();
+        fs::write(dir.path().join("anidb2folder-cache.json.tmp"), "{}").unwrap();
+        fs::write(dir.path().join("notes.json"), "{}").unwrap();
+        fs::create_dir(dir.path().join("anidb2folder-history-20250101-000000.json")).unwrap();
+
+        let files = find_history_files(dir.path()).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("anidb2folder-history-20260101-000000.json"));
+    }
 }
@@ -2,7 +2,11 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-pub const HISTORY_VERSION: &str = "1.0";
+use crate::rename::{ConflictResolution, EffectiveOptions};
+use crate::version::BuildInfo;
+
+/// Schema version written; see [`SUPPORTED_VERSIONS`](super::SUPPORTED_VERSIONS) for those read
+pub const HISTORY_VERSION: &str = "1.4";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistoryFile {
@@ -24,8 +28,43 @@ pub struct HistoryFile {
     /// Tool version that created this history
     pub tool_version: String,
 
+    /// Features, target and schema versions of the build (absent in older files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildInfo>,
+
     /// All changes made
     pub changes: Vec<HistoryEntry>,
+
+    /// SHA-256 of the canonical serialization of `changes` (absent in older files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+
+    /// For reverts: filename of the history file that was reverted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverted_from: Option<String>,
+
+    /// For reverts: when the reverted operation was originally executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_executed_at: Option<DateTime<Utc>>,
+
+    /// Options the rename was run with (absent in reverts and older files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<EffectiveOptions>,
+
+    /// Id of the run that wrote this file, as found in its log spans
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+}
+
+/// Result of verifying a history file's checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// The file has no checksum (written by an older version)
+    Missing,
+    /// The checksum matches the changes
+    Valid,
+    /// The changes were edited or corrupted after writing
+    Mismatch,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -55,9 +94,83 @@ pub struct HistoryEntry {
 
     /// Whether the name was truncated
     pub truncated: bool,
+
+    /// How a conflict with an existing destination was resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<ConflictResolution>,
+
+    /// Name the existing destination was moved aside to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aside: Option<String>,
+
+    /// What kind of change this is (absent for plain renames)
+    #[serde(default, skip_serializing_if = "EntryKind::is_rename")]
+    pub kind: EntryKind,
+
+    /// For trash moves: the `.trashinfo` file recording the original location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_info: Option<PathBuf>,
+
+    /// Season or part marker carried over from the source name, e.g. `S2`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+}
+
+/// Kind of change recorded in a history entry
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    /// Renamed in place
+    #[default]
+    Rename,
+    /// Moved into (or, for a revert, out of) the unresolved directory
+    Quarantine,
+    /// Moved into (or, for a revert, out of) the system trash
+    Trash,
+}
+
+impl EntryKind {
+    pub fn is_rename(&self) -> bool {
+        *self == EntryKind::Rename
+    }
+}
+
+impl HistoryEntry {
+    /// Whether this change was skipped and never applied
+    pub fn is_skipped(&self) -> bool {
+        self.conflict == Some(ConflictResolution::Skip)
+    }
 }
 
 impl HistoryFile {
+    /// Compute the SHA-256 checksum of the changes, as lowercase hex
+    ///
+    /// The changes are serialized as compact JSON with fields in declaration order.
+    pub fn compute_checksum(&self) -> String {
+        let canonical = serde_json::to_vec(&self.changes).unwrap_or_default();
+        let digest = ring::digest::digest(&ring::digest::SHA256, &canonical);
+
+        digest
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Store the checksum of the current changes
+    pub fn seal(&mut self) {
+        self.checksum = Some(self.compute_checksum());
+    }
+
+    /// Verify the stored checksum against the changes
+    pub fn checksum_status(&self) -> ChecksumStatus {
+        match &self.checksum {
+            None => ChecksumStatus::Missing,
+            Some(checksum) if *checksum == self.compute_checksum() => ChecksumStatus::Valid,
+            Some(_) => ChecksumStatus::Mismatch,
+        }
+    }
+
     /// Generate the filename for this history file
     pub fn generate_filename(&self) -> String {
         let timestamp = self.executed_at.format("%Y%m%d-%H%M%S");
@@ -69,7 +182,7 @@ impl HistoryFile {
         let original_timestamp = self.executed_at.format("%Y%m%d-%H%M%S");
         let revert_timestamp = revert_time.format("%Y%m%d-%H%M%S");
         format!(
-            "anidb2folder-history-{}-revert-{}.json",
+            "anidb2folder-revert-{}-{}.json",
             original_timestamp, revert_timestamp
         )
     }
@@ -101,7 +214,13 @@ mod tests {
             direction: HistoryDirection::AnidbToReadable,
             target_directory: PathBuf::from("/test"),
             tool_version: "0.1.0".to_string(),
+            build: None,
             changes: vec![],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
         };
 
         assert_eq!(
@@ -110,6 +229,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_generate_revert_filename() {
+        let history = HistoryFile {
+            version: HISTORY_VERSION.to_string(),
+            executed_at: DateTime::parse_from_rfc3339("2026-01-15T10:30:45Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            operation: OperationType::Rename,
+            direction: HistoryDirection::AnidbToReadable,
+            target_directory: PathBuf::from("/test"),
+            tool_version: "0.1.0".to_string(),
+            build: None,
+            changes: vec![],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
+        };
+        let revert_time = DateTime::parse_from_rfc3339("2026-01-16T08:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            history.generate_revert_filename(&revert_time),
+            "anidb2folder-revert-20260115-103045-20260116-080000.json"
+        );
+    }
+
     #[test]
     fn test_history_direction_description() {
         assert_eq!(
@@ -145,4 +293,60 @@ mod tests {
             "\"readable_to_anidb\""
         );
     }
+
+    #[test]
+    fn test_options_absent_in_older_files() {
+        let json = r#"{
+            "version": "1.0",
+            "executed_at": "2026-01-15T10:30:45Z",
+            "operation": "rename",
+            "direction": "anidb_to_readable",
+            "target_directory": "/anime",
+            "tool_version": "0.1.0",
+            "changes": []
+        }"#;
+
+        let history: HistoryFile = serde_json::from_str(json).unwrap();
+        assert!(history.options.is_none());
+
+        let serialized = serde_json::to_string(&history).unwrap();
+        assert!(!serialized.contains("options"));
+    }
+
+    #[test]
+    fn test_checksum_status() {
+        let mut history = HistoryFile {
+            version: HISTORY_VERSION.to_string(),
+            executed_at: Utc::now(),
+            operation: OperationType::Rename,
+            direction: HistoryDirection::AnidbToReadable,
+            target_directory: PathBuf::from("/test"),
+            tool_version: "0.1.0".to_string(),
+            build: None,
+            changes: vec![HistoryEntry {
+                source: "12345".to_string(),
+                destination: "Title [anidb-12345]".to_string(),
+                anidb_id: 12345,
+                truncated: false,
+                conflict: None,
+                aside: None,
+                kind: EntryKind::Rename,
+                trash_info: None,
+                suffix: None,
+            }],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
+        };
+        assert_eq!(history.checksum_status(), ChecksumStatus::Missing);
+
+        history.seal();
+        assert_eq!(history.checksum_status(), ChecksumStatus::Valid);
+        assert_eq!(history.checksum.as_ref().unwrap().len(), 64);
+
+        history.changes[0].destination = "Edited [anidb-12345]".to_string();
+        assert_eq!(history.checksum_status(), ChecksumStatus::Mismatch);
+    }
 }
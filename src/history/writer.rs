@@ -1,11 +1,12 @@
-use std::fs::{self, File};
-use std::io::BufWriter;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter};
 use std::path::{Path, PathBuf};
 
 use chrono::Utc;
 use tracing::{info, warn};
 
-use crate::rename::{RenameDirection, RenameResult};
+use crate::rename::{natural_cmp, RenameDirection, RenameOperation, RenameResult};
+use crate::version::{BuildInfo, VERSION};
 
 use super::types::*;
 
@@ -23,6 +24,9 @@ pub enum HistoryError {
 
     #[error("History file version mismatch: expected {expected}, found {found}")]
     VersionMismatch { expected: String, found: String },
+
+    #[error("History file checksum mismatch: the file was edited or is corrupted")]
+    ChecksumMismatch,
 }
 
 /// Write history file for a rename operation
@@ -37,49 +41,139 @@ fn create_history_from_result(result: &RenameResult, target_dir: &Path) -> Histo
         RenameDirection::ReadableToAniDb => HistoryDirection::ReadableToAnidb,
     };
 
-    let changes: Vec<HistoryEntry> = result
-        .operations
-        .iter()
+    let mut operations: Vec<&RenameOperation> = result.operations.iter().collect();
+    operations.sort_by(|a, b| natural_cmp(&a.source_name, &b.source_name));
+
+    let changes: Vec<HistoryEntry> = operations
+        .into_iter()
         .map(|op| HistoryEntry {
             source: op.source_name.clone(),
             destination: op.destination_name.clone(),
             anidb_id: op.anidb_id,
             truncated: op.truncated,
+            conflict: op.conflict,
+            aside: op.aside_name.clone(),
+            kind: if op.to_trash {
+                EntryKind::Trash
+            } else if op.quarantined {
+                EntryKind::Quarantine
+            } else {
+                EntryKind::Rename
+            },
+            trash_info: op.trash_info.clone(),
+            suffix: op.suffix.clone(),
         })
         .collect();
 
-    HistoryFile {
+    let mut history = HistoryFile {
         version: HISTORY_VERSION.to_string(),
         executed_at: Utc::now(),
         operation: OperationType::Rename,
         direction,
         target_directory: target_dir.to_path_buf(),
-        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        tool_version: VERSION.to_string(),
+        build: Some(BuildInfo::current()),
         changes,
+        checksum: None,
+        reverted_from: None,
+        original_executed_at: None,
+        options: Some(result.options.clone()),
+        run_id: Some(result.run_id.clone()),
+    };
+
+    history.seal();
+    history
+}
+
+/// History of a run executed in chunks
+///
+/// The file is written by the first [`append`](Self::append) and rewritten
+/// (atomically, resealed) with the changes of every later chunk, so a run
+/// stopped halfway leaves a valid history of what it renamed. Only the
+/// history entries are kept in memory.
+#[derive(Debug)]
+pub struct IncrementalHistory {
+    target_dir: PathBuf,
+    history: Option<HistoryFile>,
+    path: Option<PathBuf>,
+}
+
+impl IncrementalHistory {
+    pub fn new(target_dir: &Path) -> Self {
+        Self {
+            target_dir: target_dir.to_path_buf(),
+            history: None,
+            path: None,
+        }
+    }
+
+    /// Add the operations of a chunk and write the file
+    pub fn append(&mut self, result: &RenameResult) -> Result<PathBuf, HistoryError> {
+        let chunk = create_history_from_result(result, &self.target_dir);
+        let history = match &mut self.history {
+            Some(history) => {
+                history.changes.extend(chunk.changes);
+                history.seal();
+                history
+            }
+            None => self.history.insert(chunk),
+        };
+        match &self.path {
+            Some(path) => write_to_path(history, path),
+            None => {
+                let path = write_history_file(history, &self.target_dir)?;
+                self.path = Some(path.clone());
+                Ok(path)
+            }
+        }
     }
 }
 
+/// Maximum number of names tried when a history filename is already taken
+const MAX_FILENAME_ATTEMPTS: u32 = 100;
+
 pub fn write_history_file(
     history: &HistoryFile,
     target_dir: &Path,
 ) -> Result<PathBuf, HistoryError> {
-    let filename = history.generate_filename();
-    let file_path = target_dir.join(&filename);
-
-    // Check if file already exists (shouldn't happen, but be safe)
-    if file_path.exists() {
-        warn!("History file already exists: {:?}", file_path);
-        // Add milliseconds to make unique
-        let unique_filename = format!(
-            "anidb2folder-history-{}-{}.json",
-            history.executed_at.format("%Y%m%d-%H%M%S"),
-            history.executed_at.timestamp_subsec_millis()
-        );
-        let unique_path = target_dir.join(unique_filename);
-        return write_to_path(history, &unique_path);
+    let file_path = reserve_unique_path(target_dir, &history.generate_filename())?;
+
+    write_to_path(history, &file_path).inspect_err(|_| {
+        // Release the reserved name so a failed write leaves no empty file behind
+        let _ = fs::remove_file(&file_path);
+    })
+}
+
+/// Reserve a free path for `filename` in `target_dir`
+///
+/// When the name is taken, a counter is added before the extension
+/// (`...-2.json`, `...-3.json`). The file is created exclusively, so runs
+/// within the same second (or on filesystems with coarse timestamps) never
+/// share a name.
+pub fn reserve_unique_path(target_dir: &Path, filename: &str) -> Result<PathBuf, HistoryError> {
+    let stem = filename.strip_suffix(".json").unwrap_or(filename);
+
+    for attempt in 1..=MAX_FILENAME_ATTEMPTS {
+        let candidate = if attempt == 1 {
+            filename.to_string()
+        } else {
+            format!("{}-{}.json", stem, attempt)
+        };
+        let path = target_dir.join(&candidate);
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                warn!("History file already exists: {:?}", path);
+            }
+            Err(e) => return Err(e.into()),
+        }
     }
 
-    write_to_path(history, &file_path)
+    Err(HistoryError::WriteError(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("no free name for {}", filename),
+    )))
 }
 
 fn write_to_path(history: &HistoryFile, path: &Path) -> Result<PathBuf, HistoryError> {
@@ -103,7 +197,7 @@ fn write_to_path(history: &HistoryFile, path: &Path) -> Result<PathBuf, HistoryE
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::rename::RenameOperation;
+    use crate::rename::EffectiveOptions;
     use tempfile::tempdir;
 
     fn create_test_result() -> RenameResult {
@@ -153,6 +247,23 @@ mod tests {
         assert_eq!(history.changes[0].anidb_id, 12345);
         assert!(!history.changes[0].truncated);
         assert!(history.changes[1].truncated);
+        assert_eq!(history.options, Some(EffectiveOptions::default()));
+        assert_eq!(history.build, Some(BuildInfo::current()));
+    }
+
+    #[test]
+    fn test_history_records_suffix() {
+        let dir = tempdir().unwrap();
+        let mut result = create_test_result();
+        result.operations[0].suffix = Some("S2".to_string());
+
+        let path = write_history(&result, dir.path()).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let history: HistoryFile = serde_json::from_str(&content).unwrap();
+
+        assert_eq!(history.changes[0].suffix.as_deref(), Some("S2"));
+        assert_eq!(history.changes[1].suffix, None);
+        assert_eq!(content.matches("\"suffix\"").count(), 1);
     }
 
     #[test]
@@ -179,4 +290,100 @@ mod tests {
         let temp_path = path.with_extension("json.tmp");
         assert!(!temp_path.exists());
     }
+
+    #[test]
+    fn test_unique_filename_on_collision() {
+        let dir = tempdir().unwrap();
+        let result = create_test_result();
+        let history = create_history_from_result(&result, dir.path());
+
+        // Occupy the name the history would get
+        let first = write_history_file(&history, dir.path()).unwrap();
+        let second = write_history_file(&history, dir.path()).unwrap();
+        let third = write_history_file(&history, dir.path()).unwrap();
+
+        assert_ne!(first, second);
+        assert_ne!(second, third);
+        assert!(second.to_string_lossy().ends_with("-2.json"));
+        assert!(third.to_string_lossy().ends_with("-3.json"));
+
+        for path in [&first, &second, &third] {
+            let content = fs::read_to_string(path).unwrap();
+            let parsed: HistoryFile = serde_json::from_str(&content).unwrap();
+            assert_eq!(parsed.changes.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_history_order_independent_of_input_order() {
+        let dir = tempdir().unwrap();
+        let ids = [10, 2, 1, 33, 4];
+        let build = |order: &[u32]| {
+            let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+            for &id in order {
+                result.add_operation(RenameOperation::new(
+                    dir.path().join(id.to_string()),
+                    format!("Anime {} [anidb-{}]", id, id),
+                    id,
+                    false,
+                ));
+            }
+            let history = create_history_from_result(&result, dir.path());
+            serde_json::to_string(&history.changes).unwrap()
+        };
+
+        let expected = build(&ids);
+        let mut shuffled = ids.to_vec();
+        for _ in 0..ids.len() {
+            shuffled.rotate_left(2);
+            shuffled.swap(0, 3);
+            assert_eq!(build(&shuffled), expected);
+        }
+
+        let history: Vec<HistoryEntry> = serde_json::from_str(&expected).unwrap();
+        let sources: Vec<&str> = history.iter().map(|e| e.source.as_str()).collect();
+        assert_eq!(sources, vec!["1", "2", "4", "10", "33"]);
+    }
+
+    #[test]
+    fn test_incremental_history_rewrites_one_file() {
+        let dir = tempdir().unwrap();
+        let mut history = IncrementalHistory::new(dir.path());
+        let mut first = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        let mut second = first.clone();
+        let mut operations = create_test_result().operations.into_iter();
+        first.add_operation(operations.next().unwrap());
+        second.add_operation(operations.next().unwrap());
+
+        let path = history.append(&first).unwrap();
+        assert_eq!(
+            crate::history::read_history(&path).unwrap().changes.len(),
+            1
+        );
+        assert_eq!(history.append(&second).unwrap(), path);
+
+        let written = crate::history::read_history(&path).unwrap();
+        assert_eq!(written.changes.len(), 2);
+        assert_eq!(written.changes[1].source, "67890");
+        assert_eq!(written.run_id, Some(first.run_id));
+        assert_eq!(written.checksum_status(), ChecksumStatus::Valid);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_reserve_unique_path_keeps_existing_file() {
+        let dir = tempdir().unwrap();
+        let taken = dir.path().join("anidb2folder-history-20260115-103045.json");
+        fs::write(&taken, "existing").unwrap();
+
+        let path =
+            reserve_unique_path(dir.path(), "anidb2folder-history-20260115-103045.json").unwrap();
+
+        assert_eq!(
+            path,
+            dir.path()
+                .join("anidb2folder-history-20260115-103045-2.json")
+        );
+        assert_eq!(fs::read_to_string(&taken).unwrap(), "existing");
+    }
 }
@@ -0,0 +1,276 @@
+//! Commands run after renames (`--exec-after`, `--exec-after-run`).
+//!
+//! A hook is a shell command line (`sh -c` on Unix, `cmd /C` on Windows).
+//! Everything about the rename is passed in environment variables, never
+//! spliced into the command, so names need no quoting:
+//!
+//! | Variable     | `--exec-after`               | `--exec-after-run`      |
+//! |--------------|------------------------------|-------------------------|
+//! | `ANIDB_ID`   | id of the renamed directory  | —                       |
+//! | `SRC_NAME`   | name before the rename       | —                       |
+//! | `DST_NAME`   | name after the rename        | —                       |
+//! | `RENAMED`    | —                            | directories renamed     |
+//! | `TARGET_DIR` | target directory             | target directory        |
+//! | `DRY_RUN`    | `0`                          | `0`                     |
+//!
+//! Hooks never run in a dry run. Their output goes to the log (`-vv`).
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::progress::ProgressObserver;
+use crate::rename::RenameResult;
+
+/// Errors running a hook
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("Cannot run hook {command:?}: {source}")]
+    Spawn {
+        command: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Hook {command:?} failed ({status})")]
+    Failed { command: String, status: ExitStatus },
+}
+
+/// Commands to run after a run's renames
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    /// Run once per completed operation
+    pub after_each: Option<String>,
+    /// Run once after all operations
+    pub after_run: Option<String>,
+    /// A failing hook fails the run instead of being reported as a warning
+    pub strict: bool,
+}
+
+impl Hooks {
+    /// Run the hooks for the completed operations of `result`
+    ///
+    /// Without `strict`, failures are warnings and every hook still runs;
+    /// with it, the first failure stops and is returned.
+    pub fn run(
+        &self,
+        result: &RenameResult,
+        target_dir: &Path,
+        observer: &mut dyn ProgressObserver,
+    ) -> Result<(), HookError> {
+        if result.dry_run {
+            return Ok(());
+        }
+        self.run_each(result, target_dir, observer)?;
+        self.run_after(result.applied_count(), target_dir, observer)
+    }
+
+    /// Run `after_each` for the completed operations of `result`
+    pub fn run_each(
+        &self,
+        result: &RenameResult,
+        target_dir: &Path,
+        observer: &mut dyn ProgressObserver,
+    ) -> Result<(), HookError> {
+        let Some(command) = &self.after_each else {
+            return Ok(());
+        };
+        if result.dry_run {
+            return Ok(());
+        }
+        let target = target_dir.display().to_string();
+        for op in result.operations.iter().filter(|op| !op.is_skipped()) {
+            let env = [
+                ("ANIDB_ID", op.anidb_id.to_string()),
+                ("SRC_NAME", op.source_name.clone()),
+                ("DST_NAME", op.destination_name.clone()),
+                ("TARGET_DIR", target.clone()),
+                ("DRY_RUN", "0".to_string()),
+            ];
+            self.check(run_hook(command, &env), observer)?;
+        }
+        Ok(())
+    }
+
+    /// Run `after_run` for a run that renamed `renamed` directories
+    ///
+    /// Never called for a dry run.
+    pub fn run_after(
+        &self,
+        renamed: usize,
+        target_dir: &Path,
+        observer: &mut dyn ProgressObserver,
+    ) -> Result<(), HookError> {
+        let Some(command) = &self.after_run else {
+            return Ok(());
+        };
+        let env = [
+            ("RENAMED", renamed.to_string()),
+            ("TARGET_DIR", target_dir.display().to_string()),
+            ("DRY_RUN", "0".to_string()),
+        ];
+        self.check(run_hook(command, &env), observer)
+    }
+
+    fn check(
+        &self,
+        outcome: Result<(), HookError>,
+        observer: &mut dyn ProgressObserver,
+    ) -> Result<(), HookError> {
+        match outcome {
+            Err(e) if !self.strict => {
+                warn!("{}", e);
+                observer.warn(&e.to_string());
+                Ok(())
+            }
+            other => other,
+        }
+    }
+}
+
+/// Run `command` with `env` added to the environment, logging its output
+fn run_hook(command: &str, env: &[(&str, String)]) -> Result<(), HookError> {
+    info!("Running hook {:?}", command);
+    for (name, value) in env {
+        debug!("  {}={}", name, value);
+    }
+    let output = shell(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|source| HookError::Spawn {
+            command: command.to_string(),
+            source,
+        })?;
+
+    for (stream, bytes) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        for line in String::from_utf8_lossy(bytes).lines() {
+            debug!("hook {}: {}", stream, line);
+        }
+    }
+    if !output.status.success() {
+        return Err(HookError::Failed {
+            command: command.to_string(),
+            status: output.status,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::progress::Progress;
+    use crate::rename::{ConflictResolution, RenameDirection, RenameOperation};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn result(dry_run: bool) -> RenameResult {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, dry_run);
+        result.add_operation(RenameOperation::new(
+            PathBuf::from("/anime/[X] 1"),
+            "Quote's \"Name\" $HOME (2020) [anidb-1]".to_string(),
+            1,
+            false,
+        ));
+        let mut skipped = RenameOperation::new(
+            PathBuf::from("/anime/2"),
+            "Taken [anidb-2]".to_string(),
+            2,
+            false,
+        );
+        skipped.conflict = Some(ConflictResolution::Skip);
+        result.add_operation(skipped);
+        result
+    }
+
+    /// A hook script that appends its environment to `log`
+    fn echo_script(dir: &Path) -> (String, PathBuf) {
+        let script = dir.join("hook.sh");
+        let log = dir.join("hook.log");
+        fs::write(
+            &script,
+            format!(
+                "printf '%s|%s|%s|%s|%s|%s\\n' \"$ANIDB_ID\" \"$SRC_NAME\" \"$DST_NAME\" \
+                 \"$RENAMED\" \"$TARGET_DIR\" \"$DRY_RUN\" >> '{}'\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        (format!("sh '{}'", script.display()), log)
+    }
+
+    #[test]
+    fn test_hooks_receive_names_in_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let (command, log) = echo_script(dir.path());
+        let hooks = Hooks {
+            after_each: Some(command.clone()),
+            after_run: Some(command),
+            strict: true,
+        };
+
+        hooks
+            .run(&result(false), Path::new("/anime"), &mut Progress::silent())
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&log).unwrap(),
+            "1|[X] 1|Quote's \"Name\" $HOME (2020) [anidb-1]||/anime|0\n\
+             |||1|/anime|0\n"
+        );
+    }
+
+    #[test]
+    fn test_hooks_never_run_in_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let (command, log) = echo_script(dir.path());
+        let hooks = Hooks {
+            after_each: Some(command.clone()),
+            after_run: Some(command),
+            strict: true,
+        };
+
+        hooks
+            .run(&result(true), Path::new("/anime"), &mut Progress::silent())
+            .unwrap();
+
+        assert!(!log.exists());
+    }
+
+    #[test]
+    fn test_failing_hook_is_fatal_only_when_strict() {
+        let mut hooks = Hooks {
+            after_each: Some("exit 3".to_string()),
+            after_run: None,
+            strict: false,
+        };
+        hooks
+            .run(&result(false), Path::new("/anime"), &mut Progress::silent())
+            .unwrap();
+
+        hooks.strict = true;
+        let err = hooks
+            .run(&result(false), Path::new("/anime"), &mut Progress::silent())
+            .unwrap_err();
+        assert!(matches!(err, HookError::Failed { .. }));
+        assert!(err.to_string().contains("exit status: 3"));
+    }
+}
@@ -0,0 +1,439 @@
+//! Translated user-facing messages.
+//!
+//! Messages shown by the UI, progress output and error details come from a
+//! catalog of templates keyed by name, with `{name}` placeholders. The
+//! language is chosen with `--lang` or detected from the locale
+//! (`LC_ALL`, `LC_MESSAGES`, `LANG`); keys missing from a catalog fall back
+//! to English. Log messages (tracing) stay in English.
+
+use std::fmt::Display;
+
+/// Language of user-facing messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// Japanese (日本語)
+    Ja,
+}
+
+impl Lang {
+    /// Language of a locale name such as `ja_JP.UTF-8` (`None`: not translated)
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let language = locale
+            .split(['_', '.', '@', '-'])
+            .next()
+            .unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "ja" => Some(Lang::Ja),
+            "en" | "c" | "posix" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// Language of the environment's locale, English when unknown
+    ///
+    /// Follows the POSIX precedence: the first non-empty of `LC_ALL`,
+    /// `LC_MESSAGES` and `LANG` decides.
+    pub fn detect() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|locale| Self::from_locale(&locale))
+            .unwrap_or_default()
+    }
+}
+
+/// Handle to the message catalog of one language
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Messages {
+    lang: Lang,
+}
+
+impl Messages {
+    pub fn new(lang: Lang) -> Self {
+        Self { lang }
+    }
+
+    /// Template for `key`, from English when this language lacks it
+    pub fn get(&self, key: &str) -> &'static str {
+        lookup(catalog(self.lang), key)
+            .or_else(|| lookup(EN, key))
+            .unwrap_or_else(|| {
+                debug_assert!(false, "unknown message key {}", key);
+                ""
+            })
+    }
+
+    /// Template for `key` with each `{name}` replaced by its value
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let mut text = self.get(key).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        text
+    }
+}
+
+fn lookup(catalog: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+fn catalog(lang: Lang) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        Lang::En => EN,
+        Lang::Ja => JA,
+    }
+}
+
+const EN: &[(&str, &str)] = &[
+    // Progress
+    ("progress.fetching", "Fetching metadata for {id}"),
+    ("progress.fetch_eta", " (fetch {current}/{total}, ~{remaining} remaining)"),
+    ("progress.done", "done"),
+    ("progress.warning", "Warning: {message}"),
+    ("progress.history_saved", "History saved to: {path}"),
+    ("progress.reverting", "Reverting {count} directories from history ({timestamp})"),
+    ("progress.revert_dry_run", "Dry run complete. {count} directories would be reverted."),
+    ("progress.restored", "{count} directories restored"),
+    ("progress.revert_complete", "Revert complete. {count} directories restored."),
+    // Run summary
+    ("summary.dry_run", "{count} directories would be renamed. Run without --dry to apply."),
+    ("summary.renamed", "{count} directories renamed"),
+    // Steps, titles and labels of the styled output
+    ("ui.scanning", "Scanning {path}"),
+    ("ui.validating", "Validating format"),
+    ("ui.new_directory", "{count} new directory"),
+    ("ui.new_directories", "{count} new directories"),
+    ("ui.checking_api", "Checking AniDB API access"),
+    ("ui.dry_run", "DRY RUN"),
+    ("ui.renaming", "Renaming ({direction})"),
+    ("ui.revert_dry_run", "REVERT DRY RUN"),
+    ("ui.revert_complete", "REVERT COMPLETE"),
+    ("ui.batch_revert_dry_run", "BATCH REVERT DRY RUN"),
+    ("ui.batch_revert_complete", "BATCH REVERT COMPLETE"),
+    ("direction.anidb_to_readable", "AniDB → Human-readable"),
+    ("direction.readable_to_anidb", "Human-readable → AniDB"),
+    ("label.target_directory", "Target directory"),
+    ("label.executed_at", "Executed at"),
+    ("label.found", "Found"),
+    ("label.format", "Format"),
+    ("label.history_file", "History file"),
+    ("label.reverted_from", "Reverted from"),
+    ("label.redoing", "Redoing"),
+    ("label.cache_file", "Cache file"),
+    ("label.total_entries", "Total entries"),
+    ("label.valid_entries", "Valid entries"),
+    ("label.expired_entries", "Expired entries"),
+    ("label.expiry_setting", "Expiry setting"),
+    ("label.file_size", "File size"),
+    ("label.title_changes", "Title changes"),
+    ("label.tags", "Tags"),
+    ("label.tagged_directories", "Tagged directories"),
+    ("label.checked", "Checked"),
+    ("label.placeholder_titles", "Placeholder titles"),
+    ("label.title_refreshes", "Title refreshes"),
+    ("label.name", "Name"),
+    ("label.anidb_id", "AniDB ID"),
+    ("label.marker", "Marker"),
+    ("label.series_tag", "Series tag"),
+    ("label.title", "Title"),
+    ("label.english_title", "English title"),
+    ("label.year", "Year"),
+    ("label.cache_entries", "Cache entries"),
+    ("label.history_directory", "History directory"),
+    ("label.history_files", "History files"),
+    ("label.filesystem", "Filesystem"),
+    ("label.entries_before", "Entries before"),
+    ("label.expired_removed", "Expired removed"),
+    ("label.entries_after", "Entries after"),
+    ("value.directories", "{count} directories"),
+    ("value.anidb", "AniDB"),
+    ("value.human_readable", "Human-readable"),
+    ("value.not_recognized", "not recognized"),
+    ("value.unknown_target", "unknown (history unreadable)"),
+    ("value.reverted_from", "{path} (executed {time})"),
+    ("value.days", "{days} days"),
+    ("value.cache_entries", "{entries} ({expired} expired)"),
+    ("value.no_cache_file", "no cache file"),
+    ("value.filesystem", "{type} ({profile} profile{case})"),
+    ("value.ignores_case", ", names ignore case"),
+    ("value.unknown", "unknown"),
+    // Error details
+    (
+        "error.directory_not_found",
+        "The specified directory does not exist:\n  {path}\n\nPlease verify the path and try again.",
+    ),
+    (
+        "error.not_a_directory",
+        "The specified path is not a directory:\n  {path}\n\nPlease provide a valid directory path.",
+    ),
+    (
+        "error.permission_denied",
+        "Permission denied when accessing:\n  {path}\n\nPlease check file permissions or run with appropriate privileges.",
+    ),
+    (
+        "error.target_is_anime_directory",
+        "The target is itself an anime directory:\n  {path}\n\nPoint the tool at the directory containing your anime, e.g.:\n  {parent}",
+    ),
+    (
+        "error.overlapping_targets",
+        "Target directories overlap:\n  {outer}\ncontains\n  {inner}\n\nEach directory may only be processed once; remove one of the targets.",
+    ),
+    (
+        "error.dangerous_target",
+        "Refusing to operate on:\n  {path}\nbecause {reason}.\n\nThis guard protects against a mistyped or empty path variable.\nIf this really is your anime directory, run again with --i-know-what-im-doing.",
+    ),
+    (
+        "error.mixed_formats",
+        "Found directories in multiple formats:\n- {anidb} in AniDB format\n- {readable} in human-readable format\n\n",
+    ),
+    ("error.mixed_formats.anidb_examples", "AniDB format examples:"),
+    ("error.mixed_formats.readable_examples", "Human-readable format examples:"),
+    (
+        "error.mixed_formats.hint",
+        "All directories must be in the same format.\nManually rename mixed directories before running again.",
+    ),
+    ("error.unrecognized", "The following directories do not match any known format:"),
+    ("error.unrecognized.more", "... and {count} more (--show-all-unrecognized lists them all)"),
+    ("error.unrecognized.expected", "Expected formats:"),
+    ("error.unrecognized.suggestion", "did you mean: {name}?"),
+    ("error.ambiguous", "The following directories look like mistakes (--strict):"),
+    (
+        "error.ambiguous.hint",
+        "Rename them by hand, or run without --strict to accept them as they are.",
+    ),
+    (
+        "error.api",
+        "Failed to fetch data for anime ID {id}:\n  {message}\n\nThis could be due to:\n- Network connectivity issues\n- AniDB API rate limiting\n- Invalid anime ID\n\nTry again later or check your internet connection.",
+    ),
+    (
+        "error.incomplete_data",
+        "AniDB returned incomplete data for anime ID {id}:\n  Missing: {field}\n\nThe anime exists in AniDB but is missing required metadata.\nThis can happen with:\n- Very new entries not yet fully populated\n- Entries pending moderation\n- Rare edge cases in AniDB's database\n\nCheck the entry at: https://anidb.net/anime/{id}",
+    ),
+    (
+        "error.history",
+        "History file error:\n  {message}\n{file}\nEnsure the history file exists and is valid JSON.",
+    ),
+    ("error.history.file", "File: {path}\n"),
+    ("error.batch_failed", "{failed} of {total} history files failed to revert (see above)"),
+    (
+        "error.rename",
+        "Failed to rename directory:\nFrom: {from}\nTo:   {to}\nError: {error}\n\nCheck file permissions and ensure no files are open.",
+    ),
+    ("error.permission_problems", "These directories cannot be renamed by this user:"),
+    (
+        "error.permission_problems.hint",
+        "Nothing was renamed. Fix the permissions, or run with --on-conflict skip to leave these directories untouched.",
+    ),
+    (
+        "error.cache",
+        "Cache error: {message}\n\nThe cache file may be corrupted. Delete the cache file to rebuild it.",
+    ),
+    ("error.invalid_argument", "Invalid argument: {message}"),
+];
+
+const JA: &[(&str, &str)] = &[
+    // Progress
+    ("progress.fetching", "{id} のメタデータを取得中"),
+    ("progress.fetch_eta", "（取得 {current}/{total}、残り約 {remaining}）"),
+    ("progress.done", "完了"),
+    ("progress.warning", "警告: {message}"),
+    ("progress.history_saved", "履歴を保存しました: {path}"),
+    ("progress.reverting", "履歴から {count} 個のディレクトリを元に戻しています（{timestamp}）"),
+    ("progress.revert_dry_run", "ドライラン完了。{count} 個のディレクトリが元に戻されます。"),
+    ("progress.restored", "{count} 個のディレクトリを復元しました"),
+    ("progress.revert_complete", "元に戻しました。{count} 個のディレクトリを復元しました。"),
+    // Run summary
+    ("summary.dry_run", "{count} 個のディレクトリがリネームされます。適用するには --dry を付けずに実行してください。"),
+    ("summary.renamed", "{count} 個のディレクトリをリネームしました"),
+    // Steps, titles and labels of the styled output
+    ("ui.scanning", "{path} をスキャン中"),
+    ("ui.validating", "形式を検証中"),
+    ("ui.new_directory", "新しいディレクトリ {count} 個"),
+    ("ui.new_directories", "新しいディレクトリ {count} 個"),
+    ("ui.checking_api", "AniDB API へのアクセスを確認中"),
+    ("ui.dry_run", "ドライラン"),
+    ("ui.renaming", "リネーム（{direction}）"),
+    ("ui.revert_dry_run", "元に戻す（ドライラン）"),
+    ("ui.revert_complete", "元に戻しました"),
+    ("ui.batch_revert_dry_run", "一括で元に戻す（ドライラン）"),
+    ("ui.batch_revert_complete", "一括で元に戻しました"),
+    ("direction.anidb_to_readable", "AniDB → 人が読める形式"),
+    ("direction.readable_to_anidb", "人が読める形式 → AniDB"),
+    ("label.target_directory", "対象ディレクトリ"),
+    ("label.executed_at", "実行日時"),
+    ("label.found", "検出"),
+    ("label.format", "形式"),
+    ("label.history_file", "履歴ファイル"),
+    ("label.reverted_from", "元に戻した履歴"),
+    ("label.redoing", "再実行"),
+    ("label.cache_file", "キャッシュファイル"),
+    ("label.total_entries", "全エントリ"),
+    ("label.valid_entries", "有効なエントリ"),
+    ("label.expired_entries", "期限切れのエントリ"),
+    ("label.expiry_setting", "有効期限"),
+    ("label.file_size", "ファイルサイズ"),
+    ("label.title_changes", "タイトルの変更"),
+    ("label.tags", "タグ"),
+    ("label.tagged_directories", "タグ付きのディレクトリ"),
+    ("label.checked", "確認済み"),
+    ("label.placeholder_titles", "仮のタイトル"),
+    ("label.title_refreshes", "タイトルの更新"),
+    ("label.name", "名前"),
+    ("label.anidb_id", "AniDB ID"),
+    ("label.marker", "マーカー"),
+    ("label.series_tag", "シリーズタグ"),
+    ("label.title", "タイトル"),
+    ("label.english_title", "英語タイトル"),
+    ("label.year", "年"),
+    ("label.cache_entries", "キャッシュのエントリ"),
+    ("label.history_directory", "履歴ディレクトリ"),
+    ("label.history_files", "履歴ファイル数"),
+    ("label.filesystem", "ファイルシステム"),
+    ("label.entries_before", "削除前のエントリ"),
+    ("label.expired_removed", "削除した期限切れエントリ"),
+    ("label.entries_after", "削除後のエントリ"),
+    ("value.directories", "{count} 個のディレクトリ"),
+    ("value.anidb", "AniDB 形式"),
+    ("value.human_readable", "人が読める形式"),
+    ("value.not_recognized", "認識できません"),
+    ("value.unknown_target", "不明（履歴を読み込めません）"),
+    ("value.reverted_from", "{path}（{time} に実行）"),
+    ("value.days", "{days} 日"),
+    ("value.cache_entries", "{entries}（期限切れ {expired}）"),
+    ("value.no_cache_file", "キャッシュファイルなし"),
+    ("value.filesystem", "{type}（{profile} プロファイル{case}）"),
+    ("value.ignores_case", "、大文字と小文字を区別しない"),
+    ("value.unknown", "不明"),
+    // Error details
+    (
+        "error.directory_not_found",
+        "指定されたディレクトリが存在しません:\n  {path}\n\nパスを確認して再実行してください。",
+    ),
+    (
+        "error.not_a_directory",
+        "指定されたパスはディレクトリではありません:\n  {path}\n\n有効なディレクトリのパスを指定してください。",
+    ),
+    (
+        "error.permission_denied",
+        "アクセスが拒否されました:\n  {path}\n\nファイルの権限を確認するか、適切な権限で実行してください。",
+    ),
+    (
+        "error.target_is_anime_directory",
+        "対象自体がアニメのディレクトリです:\n  {path}\n\nアニメを含むディレクトリを指定してください。例:\n  {parent}",
+    ),
+    (
+        "error.overlapping_targets",
+        "対象ディレクトリが重なっています:\n  {outer}\nが次を含んでいます\n  {inner}\n\n各ディレクトリは一度しか処理できません。どちらかの対象を外してください。",
+    ),
+    (
+        "error.dangerous_target",
+        "次の場所では実行しません:\n  {path}\n理由: {reason}\n\nこの保護は、打ち間違えたパスや空のパス変数から守るためのものです。\n本当にアニメのディレクトリであれば、--i-know-what-im-doing を付けて再実行してください。",
+    ),
+    (
+        "error.mixed_formats",
+        "複数の形式のディレクトリが見つかりました:\n- AniDB 形式: {anidb} 個\n- 人が読める形式: {readable} 個\n\n",
+    ),
+    ("error.mixed_formats.anidb_examples", "AniDB 形式の例:"),
+    ("error.mixed_formats.readable_examples", "人が読める形式の例:"),
+    (
+        "error.mixed_formats.hint",
+        "すべてのディレクトリを同じ形式にする必要があります。\n形式が混在しているディレクトリを手動でリネームしてから再実行してください。",
+    ),
+    ("error.unrecognized", "次のディレクトリはどの形式にも一致しません:"),
+    ("error.unrecognized.more", "... ほか {count} 個（--show-all-unrecognized ですべて表示）"),
+    ("error.unrecognized.expected", "対応している形式:"),
+    ("error.unrecognized.suggestion", "もしかして: {name}"),
+    ("error.ambiguous", "次のディレクトリ名は誤りの可能性があります (--strict):"),
+    (
+        "error.ambiguous.hint",
+        "手動で名前を変更するか、--strict なしで実行してそのまま受け入れてください。",
+    ),
+    (
+        "error.api",
+        "アニメ ID {id} のデータを取得できませんでした:\n  {message}\n\n考えられる原因:\n- ネットワーク接続の問題\n- AniDB API のレート制限\n- 無効なアニメ ID\n\n時間をおいて再実行するか、インターネット接続を確認してください。",
+    ),
+    (
+        "error.incomplete_data",
+        "AniDB から返されたアニメ ID {id} のデータが不完全です:\n  不足: {field}\n\nアニメは AniDB に存在しますが、必要なメタデータがありません。\n次のような場合に起こります:\n- 登録されたばかりで情報が揃っていない\n- 承認待ちのエントリ\n- AniDB データベースのまれなケース\n\nエントリを確認してください: https://anidb.net/anime/{id}",
+    ),
+    (
+        "error.history",
+        "履歴ファイルのエラー:\n  {message}\n{file}\n履歴ファイルが存在し、正しい JSON であることを確認してください。",
+    ),
+    ("error.history.file", "ファイル: {path}\n"),
+    ("error.batch_failed", "{total} 個の履歴ファイルのうち {failed} 個を元に戻せませんでした（上記を参照）"),
+    (
+        "error.rename",
+        "ディレクトリをリネームできませんでした:\n変更前: {from}\n変更後: {to}\nエラー: {error}\n\nファイルの権限を確認し、開いているファイルがないことを確認してください。",
+    ),
+    ("error.permission_problems", "次のディレクトリはこのユーザーではリネームできません:"),
+    (
+        "error.permission_problems.hint",
+        "何もリネームしていません。権限を修正するか、--on-conflict skip を付けてこれらのディレクトリをそのままにしてください。",
+    ),
+    (
+        "error.cache",
+        "キャッシュのエラー: {message}\n\nキャッシュファイルが壊れている可能性があります。キャッシュファイルを削除すると再作成されます。",
+    ),
+    ("error.invalid_argument", "無効な引数: {message}"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(template: &str) -> BTreeSet<&str> {
+        template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn test_every_key_in_every_catalog() {
+        for lang in [Lang::En, Lang::Ja] {
+            let keys: BTreeSet<&str> = catalog(lang).iter().map(|(k, _)| *k).collect();
+            let english: BTreeSet<&str> = EN.iter().map(|(k, _)| *k).collect();
+            assert_eq!(keys, english, "{:?}", lang);
+            assert_eq!(keys.len(), catalog(lang).len(), "{:?} has duplicates", lang);
+        }
+    }
+
+    #[test]
+    fn test_translations_keep_placeholders() {
+        for (key, template) in EN {
+            let translated = Messages::new(Lang::Ja).get(key);
+            assert_eq!(placeholders(translated), placeholders(template), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let messages = Messages::new(Lang::En);
+        assert_eq!(
+            messages.format("summary.renamed", &[("count", &3)]),
+            "3 directories renamed"
+        );
+        assert_eq!(
+            Messages::new(Lang::Ja).format("summary.renamed", &[("count", &3)]),
+            "3 個のディレクトリをリネームしました"
+        );
+    }
+
+    #[test]
+    fn test_lang_from_locale() {
+        assert_eq!(Lang::from_locale("ja_JP.UTF-8"), Some(Lang::Ja));
+        assert_eq!(Lang::from_locale("ja"), Some(Lang::Ja));
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Some(Lang::En));
+        assert_eq!(Lang::from_locale("C.UTF-8"), Some(Lang::En));
+        assert_eq!(Lang::from_locale("de_DE"), None);
+        assert_eq!(Lang::from_locale(""), None);
+    }
+}
@@ -1,39 +1,108 @@
 pub mod api;
+#[cfg(feature = "cache")]
 pub mod cache;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "cache")]
+pub mod clock;
+pub mod diff;
 pub mod error;
+pub mod events;
+pub mod fs_profile;
 pub mod history;
+pub mod hooks;
+pub mod i18n;
+pub mod lint;
+#[cfg(feature = "cli")]
 pub mod logging;
+#[cfg(all(feature = "api", feature = "cache"))]
+pub mod monitoring;
+pub mod output;
 pub mod parser;
+#[cfg(all(feature = "api", feature = "cache"))]
+pub mod pipeline;
 pub mod progress;
 pub mod rename;
 pub mod revert;
+#[cfg(all(feature = "api", feature = "cache"))]
+pub mod roundtrip;
+pub mod run_id;
 pub mod scanner;
+pub mod timing;
+pub mod trash;
+#[cfg(feature = "cli")]
 pub mod ui;
 pub mod validator;
+pub mod version;
+pub mod watch;
 
+#[cfg(feature = "async")]
+pub use api::AniDbClientAsync;
 pub use api::{
-    config_from_env, AniDbClient, AnimeInfo, ApiConfig, ApiError, ENV_ANIDB_CLIENT,
-    ENV_ANIDB_CLIENT_VERSION,
+    config_from_env, resolve_api_config, AnimeInfo, AnimeType, ApiConfig, ApiError,
+    CredentialsError, DEFAULT_API_URL, ENV_ANIDB_CLIENT, ENV_ANIDB_CLIENT_VERSION,
+};
+#[cfg(feature = "api")]
+pub use api::{AniDbClient, TitleDump, TitleDumpError};
+#[cfg(feature = "cache")]
+pub use cache::{
+    cache_info, clear_cache, prune_cache, CacheCleanup, CacheConfig, CacheError, CacheInfo,
+    CacheStore, TitleChange, Titles, DEFAULT_CACHE_SAVE_EVERY,
 };
-pub use cache::{CacheConfig, CacheError, CacheStore};
 pub use error::{AppError, ExitCode};
+pub use fs_profile::{
+    detect_fs_type, detect_name_max, resolve_profile, validate_destination_name, FsProfile,
+};
 pub use parser::{
-    parse_directory_name, AniDbFormat, DirectoryFormat, HumanReadableFormat, ParseError,
-    ParsedDirectory,
+    classify, classify_many, classify_with, normalize_name, parse_directory_name,
+    parse_directory_name_with, parse_directory_name_with_options, AniDbFormat, DirectoryFormat,
+    HumanReadableFormat, NameTemplate, ParseError, ParseOptions, ParseReport, ParsedDirectory,
+    TemplateError, TemplateFields, TEMPLATE_ID_TAG,
+};
+#[cfg(all(feature = "api", feature = "cache"))]
+pub use pipeline::{
+    execute, execute_with, plan, ExecOptions, Plan, PlanOptions, RunResult,
+    DEFAULT_MAX_UNRECOGNIZED,
 };
+#[cfg(feature = "cli")]
 pub use progress::Progress;
+pub use progress::ProgressObserver;
+pub use rename::{
+    build_anidb_name, build_anidb_name_with_suffix, build_human_readable_name,
+    build_human_readable_name_with_suffix, comparable_name, compute_destination,
+    execute_operations, execute_operations_parallel, plan_to_anidb, replacement_table,
+    resolve_conflicts, ConflictResolution, ConflictResolver, IdMismatch, NameBuildResult,
+    NameBuilderConfig, Override, Overrides, OverridesError, RenameDirection, RenameError,
+    RenameOperation, RenameResult, Retag, TagMap, TagMapError,
+};
 pub use rename::{
-    build_anidb_name, rename_to_readable, RenameDirection, RenameError, RenameOperation,
-    RenameOptions, RenameResult,
+    exclude_recent, recent_entry, rename_moves, verify_moves, Discrepancy, DiscrepancyKind,
+    PostRunCheck, RecentSkip,
+};
+#[cfg(all(feature = "api", feature = "cache"))]
+pub use rename::{
+    plan_to_readable, rename_to_readable, titles_resemble, verify_ids, RenameOptions, VerifyOptions,
+};
+pub use scanner::{
+    guard_target, is_artifact_name, scan_directory, scan_directory_ex, validate_targets,
+    DirectoryEntry, ScanOptions, ScanResult, ScannerError, PARALLEL_SCAN_THRESHOLD, UNRESOLVED_DIR,
+};
+pub use validator::{
+    suggest_name, validate_directories, validate_directories_for, validate_directories_with,
+    validate_directories_with_options, FormatMismatch, ValidationError, ValidationResult,
+    UNRECOGNIZED_LIST_CAP,
 };
-pub use scanner::{scan_directory, DirectoryEntry, ScannerError};
-pub use validator::{validate_directories, FormatMismatch, ValidationError, ValidationResult};
 // validate_for_revert: TODO(feature-60) - revert safety validation
 #[allow(unused_imports)]
 pub use history::{
-    read_history, validate_for_revert, write_history, HistoryDirection, HistoryEntry, HistoryError,
-    HistoryFile, OperationType, HISTORY_VERSION,
+    find_history_files, read_history, read_history_strict, validate_for_revert, write_history,
+    ChecksumStatus, HistoryDirection, HistoryEntry, HistoryError, HistoryFile, OperationType,
+    HISTORY_VERSION,
+};
+pub use revert::{
+    revert_from_history, revert_from_history_with, RevertConflict, RevertConflictKind, RevertError,
+    RevertOperation, RevertOptions, RevertResult,
 };
-pub use revert::{revert_from_history, RevertError, RevertOperation, RevertOptions, RevertResult};
-pub use ui::{Ui, UiConfig};
+#[cfg(feature = "cli")]
+pub use ui::{Theme, ThemeName, Ui, UiConfig};
+pub use version::BuildInfo;
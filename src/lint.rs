@@ -0,0 +1,241 @@
+//! Consistency checks over the series tags of a library (`--lint`).
+//!
+//! A mistyped tag (`[ASO]` for `[AS0]`) silently splits a franchise in
+//! two. Tags are grouped across the parsed directory names and two kinds
+//! of likely typos are reported, each with the tag it probably meant:
+//!
+//! - tags that are the same after folding case and confusable characters
+//!   (`0`/`O`, `1`/`l`/`I`): the less used one is suggested to be the other
+//! - a tag on a single directory one edit away from a tag on at least
+//!   [`POPULAR_TAG_MIN`] directories
+//!
+//! Nothing is renamed.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::parser::parse_directory_name;
+use crate::rename::natural_cmp;
+
+/// Directories a tag needs for a near-identical singleton tag to look like a typo of it
+pub const POPULAR_TAG_MIN: usize = 3;
+
+/// A series tag and the directories using it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagUse {
+    pub tag: String,
+    /// Directory names, in natural order
+    pub directories: Vec<String>,
+}
+
+/// Why a tag looks like a typo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintReason {
+    /// Same tag up to case and confusable characters
+    Confusable,
+    /// Used once, one edit away from a popular tag
+    Singleton,
+}
+
+impl LintReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            LintReason::Confusable => "confusable characters",
+            LintReason::Singleton => "single use, near a common tag",
+        }
+    }
+}
+
+/// A tag that probably should be another one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagSuggestion {
+    pub tag: String,
+    /// Directories using `tag`
+    pub directories: Vec<String>,
+    /// The tag it probably should be
+    pub suggestion: String,
+    /// Number of directories using `suggestion`
+    pub suggestion_count: usize,
+    pub reason: LintReason,
+}
+
+/// Series tags of a library and the likely typos among them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TagReport {
+    /// Every tag, in natural order
+    pub tags: Vec<TagUse>,
+    pub suggestions: Vec<TagSuggestion>,
+}
+
+/// Group `names` by series tag and look for likely typos
+///
+/// Names that do not parse or have no tag are left out.
+pub fn lint_tags<'a>(names: impl IntoIterator<Item = &'a str>) -> TagReport {
+    let mut by_tag: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for name in names {
+        if let Some(tag) = parse_directory_name(name)
+            .ok()
+            .and_then(|parsed| parsed.series_tag().map(str::to_string))
+        {
+            by_tag.entry(tag).or_default().push(name.to_string());
+        }
+    }
+    let mut tags: Vec<TagUse> = by_tag
+        .into_iter()
+        .map(|(tag, mut directories)| {
+            directories.sort_by(|a, b| natural_cmp(a, b));
+            TagUse { tag, directories }
+        })
+        .collect();
+    tags.sort_by(|a, b| natural_cmp(&a.tag, &b.tag));
+
+    let mut suggestions = Vec::new();
+    for tag in &tags {
+        let suggestion = closest(&tags, tag, LintReason::Confusable, |other| {
+            other.tag != tag.tag && fold(&other.tag) == fold(&tag.tag)
+        })
+        .or_else(|| {
+            if tag.directories.len() != 1 {
+                return None;
+            }
+            closest(&tags, tag, LintReason::Singleton, |other| {
+                other.directories.len() >= POPULAR_TAG_MIN && one_edit_apart(&other.tag, &tag.tag)
+            })
+        });
+        suggestions.extend(suggestion);
+    }
+
+    TagReport { tags, suggestions }
+}
+
+/// The most used tag matching `candidate`, if it is used more than `tag`
+fn closest(
+    tags: &[TagUse],
+    tag: &TagUse,
+    reason: LintReason,
+    candidate: impl Fn(&TagUse) -> bool,
+) -> Option<TagSuggestion> {
+    let best = tags
+        .iter()
+        .filter(|other| candidate(other))
+        .max_by(|a, b| {
+            a.directories
+                .len()
+                .cmp(&b.directories.len())
+                // On a tie the first in natural order wins
+                .then_with(|| natural_cmp(&b.tag, &a.tag))
+        })?;
+    let ahead = match best.directories.len().cmp(&tag.directories.len()) {
+        Ordering::Greater => true,
+        Ordering::Equal => natural_cmp(&best.tag, &tag.tag) == Ordering::Less,
+        Ordering::Less => false,
+    };
+    ahead.then(|| TagSuggestion {
+        tag: tag.tag.clone(),
+        directories: tag.directories.clone(),
+        suggestion: best.tag.clone(),
+        suggestion_count: best.directories.len(),
+        reason,
+    })
+}
+
+/// `tag` lowercased with confusable characters mapped to digits
+fn fold(tag: &str) -> String {
+    tag.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'o' => '0',
+            'l' | 'i' => '1',
+            c => c,
+        })
+        .collect()
+}
+
+/// Whether one insertion, deletion or substitution turns `a` into `b`
+fn one_edit_apart(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let prefix = short.iter().zip(&long).take_while(|(x, y)| x == y).count();
+    if short.len() == long.len() {
+        prefix < short.len() && short[prefix + 1..] == long[prefix + 1..]
+    } else {
+        short[prefix..] == long[prefix + 1..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confusable_tags_point_to_the_common_one() {
+        let report = lint_tags([
+            "[AS0] 1",
+            "[AS0] Two (2002) [anidb-2]",
+            "[ASO] 3",
+            "[as0] 4",
+            "[FMA] 5",
+            "6",
+        ]);
+
+        let tags: Vec<(&str, usize)> = report
+            .tags
+            .iter()
+            .map(|t| (t.tag.as_str(), t.directories.len()))
+            .collect();
+        assert_eq!(tags, [("AS0", 2), ("ASO", 1), ("FMA", 1), ("as0", 1)]);
+        let suggestions: Vec<(&str, &str, LintReason)> = report
+            .suggestions
+            .iter()
+            .map(|s| (s.tag.as_str(), s.suggestion.as_str(), s.reason))
+            .collect();
+        assert_eq!(
+            suggestions,
+            [
+                ("ASO", "AS0", LintReason::Confusable),
+                ("as0", "AS0", LintReason::Confusable)
+            ]
+        );
+        assert_eq!(report.suggestions[0].directories, ["[ASO] 3"]);
+        assert_eq!(report.suggestions[0].suggestion_count, 2);
+    }
+
+    #[test]
+    fn test_singleton_near_popular_tag() {
+        let mut names = vec!["[GITS] 1", "[GITS] 2", "[GITS] 3", "[GIST] 4", "[GTS] 5"];
+        let report = lint_tags(names.iter().copied());
+        assert_eq!(report.suggestions.len(), 1);
+        assert_eq!(report.suggestions[0].tag, "GTS");
+        assert_eq!(report.suggestions[0].suggestion, "GITS");
+        assert_eq!(report.suggestions[0].reason, LintReason::Singleton);
+
+        // Not popular enough to call the other a typo
+        names.remove(0);
+        assert!(lint_tags(names).suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_equal_confusable_tags_pick_one_direction() {
+        let report = lint_tags(["[S1] 1", "[Sl] 2"]);
+        assert_eq!(report.suggestions.len(), 1);
+        assert_eq!(report.suggestions[0].tag, "Sl");
+        assert_eq!(report.suggestions[0].suggestion, "S1");
+    }
+
+    #[test]
+    fn test_one_edit_apart() {
+        assert!(one_edit_apart("GITS", "GTS"));
+        assert!(one_edit_apart("GITS", "GIT5"));
+        assert!(one_edit_apart("AS", "AS0"));
+        assert!(!one_edit_apart("GITS", "GITS"));
+        assert!(!one_edit_apart("GITS", "GIST"));
+        assert!(!one_edit_apart("AS", "AS01"));
+    }
+}
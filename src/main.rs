@@ -1,78 +1,196 @@
 mod api;
 mod cache;
 mod cli;
+mod clock;
+mod diff;
 mod error;
+mod events;
+mod fs_profile;
 mod history;
+mod hooks;
+mod i18n;
+mod lint;
 mod logging;
+mod monitoring;
+mod output;
 mod parser;
+mod pipeline;
 mod progress;
 mod rename;
 mod revert;
+mod roundtrip;
+mod run_id;
 mod scanner;
+mod timing;
+mod trash;
 mod ui;
 mod validator;
+mod version;
+mod watch;
 
-use api::config_from_env;
+use api::{resolve_api_config, AniDbClient, TitleDump};
 use cache::{CacheConfig, CacheStore};
-use clap::Parser;
+use clap::ValueEnum;
 use cli::Args;
-use error::AppError;
-use history::{read_history, validate_for_revert, write_history};
-use parser::DirectoryFormat;
-use progress::Progress;
+use error::{AppError, ExitCode};
+use events::{EventStream, OutputFormat};
+use fs_profile::{resolve_profile, FsProfile};
+use history::{
+    expand_glob, find_history_files, read_history, read_history_strict, validate_for_revert,
+    ChecksumStatus, EntryKind, OperationType,
+};
+use hooks::Hooks;
+use i18n::{Lang, Messages};
+use output::{GroupBy, PreviewFormat};
+use parser::{DirectoryFormat, NameTemplate, ParseOptions, ParseReport};
+use pipeline::{ChunkedRun, ChunkedSummary, ExecOptions, PlanOptions, RunResult};
+use progress::{format_timestamp, Progress, ProgressObserver};
 use rename::{
-    build_anidb_name, rename_to_readable, RenameDirection, RenameOperation, RenameOptions,
-    RenameResult,
+    ConflictResolution, ConflictResolver, EffectiveOptions, Overrides, PostRunCheck, RenameResult,
+    RestrictedPolicy, TagMap, UnairedPolicy,
 };
-use revert::{revert_from_history, RevertOptions};
-use scanner::scan_directory;
-use tracing::{debug, error, info};
-use ui::{Ui, UiConfig};
-use validator::validate_directories;
+use revert::{revert_from_history, revert_from_history_with, RevertOptions};
+use scanner::{ScanOptions, ScanResult, UNRESOLVED_DIR};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use timing::{StepTiming, Timings};
+use tracing::{debug, error, info, warn};
+use ui::{Theme, Ui, UiConfig};
+use validator::ValidationResult;
+use version::{with_schema, BuildInfo, VERSION};
+use watch::Watcher;
 
 fn main() {
     // Load .env file if present (silently ignore if not found)
     let _ = dotenvy::dotenv();
 
-    let args = Args::parse();
-
-    // Convert verbose count to bool for UI/Progress
-    let is_verbose = args.verbose > 0;
+    let args = Args::parse_effective();
 
-    // Initialize logging (only in verbose mode do we show tracing output)
-    logging::init(args.verbose);
-
-    // Create UI
-    let ui_config = UiConfig::new(is_verbose);
+    // Initialize logging; log-only mode shows at least info-level tracing
+    let verbosity = if args.no_ui {
+        args.verbose.max(1)
+    } else {
+        args.verbose
+    };
+    logging::init(verbosity);
+
+    // Create UI (verbose adds tracing output, it does not replace the UI)
+    let mut ui_config = UiConfig::new(args.no_ui);
+    ui_config.banner = !args.no_banner;
+    if args.unattended {
+        ui_config.prompts = false;
+        // FORCE_COLOR still wins, as it does over a terminal check
+        ui_config.colors_enabled = std::env::var("FORCE_COLOR").is_ok();
+    }
+    ui_config.compact = args.compact;
+    ui_config.theme = Theme::new(args.theme);
+    ui_config.messages = Messages::new(args.lang.unwrap_or_else(Lang::detect));
     let mut ui = Ui::new(ui_config);
 
     // Show header
-    ui.print_header(env!("CARGO_PKG_VERSION"));
+    ui.print_header(VERSION);
 
     debug!("Environment loaded, checking API configuration");
 
     if let Err(e) = run(args, &mut ui) {
-        error!("{}", e);
-        ui.error(&e.detailed_message());
+        // Not a failure: the command's own output already told the user
+        if !matches!(e, AppError::NothingToDo(_)) {
+            error!("{}", e);
+            ui.error(&e.detailed_message_in(&ui.messages()));
+        }
         std::process::exit(e.exit_code().into());
     }
 }
 
-fn run(args: Args, ui: &mut Ui) -> Result<(), AppError> {
+fn run(mut args: Args, ui: &mut Ui) -> Result<(), AppError> {
+    let messages = ui.messages();
     // Create progress for internal use (for functions that need it)
-    let mut progress = Progress::new_with_ui(ui.is_verbose(), ui.is_colors_enabled());
+    let mut progress = Progress::new_with_ui(ui.is_log_only(), ui.is_colors_enabled())
+        .with_theme(ui.theme())
+        .with_messages(messages)
+        .with_local_time(args.local_time);
+
+    if args.print_config {
+        handle_print_config(&args, ui);
+        return Ok(());
+    }
+
+    if args.list_exit_codes {
+        handle_list_exit_codes(args.json_output(), ui);
+        return Ok(());
+    }
+
+    if args.build_info {
+        handle_build_info(args.json_output(), ui);
+        return Ok(());
+    }
 
     // Handle cache commands
     if let Some(dir) = &args.cache_info {
-        return handle_cache_info(dir, args.cache_expiry, ui);
+        return handle_cache_info(
+            dir,
+            args.cache_expiry,
+            args.changes,
+            args.local_time,
+            args.json_output(),
+            ui,
+        );
     }
 
     if let Some(dir) = &args.cache_clear {
-        return handle_cache_clear(dir, args.cache_expiry, ui);
+        return handle_cache_clear(dir, args.cache_expiry, args.json_output(), ui);
     }
 
     if let Some(dir) = &args.cache_prune {
-        return handle_cache_prune(dir, args.cache_expiry, ui);
+        return handle_cache_prune(dir, args.cache_expiry, args.json_output(), ui);
+    }
+
+    if let Some(dir) = &args.where_dir {
+        return handle_where(
+            dir,
+            args.cache_expiry,
+            args.fs_profile,
+            args.json_output(),
+            ui,
+        );
+    }
+
+    if let Some(dir) = &args.history_list {
+        return handle_history_list(dir, args.local_time, args.verbose > 0, ui);
+    }
+
+    if let Some(dir) = &args.lint {
+        return handle_lint(dir, args.json_output(), ui);
+    }
+
+    if let Some(dir) = &args.check_roundtrip {
+        return handle_check_roundtrip(dir, &args, ui);
+    }
+
+    if let Some(name) = &args.parse {
+        handle_parse(name, args.json_output(), ui);
+        return Ok(());
+    }
+
+    if let Some(history_file) = args.redo.take() {
+        apply_redo(&history_file, &mut args, ui)?;
+    }
+
+    if args.check_api {
+        handle_check_api(args.credentials_file.as_deref(), ui)?;
+        if args.target_dir.is_none() && args.revert.is_none() && args.revert_all.is_none() {
+            return Ok(());
+        }
+    }
+
+    if let Some(pattern) = &args.revert_all {
+        return handle_revert_all(pattern, &args, ui, &mut progress);
+    }
+
+    if let Some(dir) = &args.watch {
+        return handle_watch(dir, &args, ui, &mut progress);
     }
 
     if let Some(history_file) = &args.revert {
@@ -81,14 +199,26 @@ fn run(args: Args, ui: &mut Ui) -> Result<(), AppError> {
         ui.info(&format!("Loading history from: {}", history_file.display()));
 
         // Read history first for validation and display
-        let history = read_history(history_file)
-            .map_err(|e| AppError::Other(format!("Failed to read history: {}", e)))?;
+        let history = if args.strict {
+            read_history_strict(history_file)
+        } else {
+            read_history(history_file)
+        }
+        .map_err(|e| AppError::Other(format!("Failed to read history: {}", e)))?;
+
+        if history.checksum_status() == ChecksumStatus::Mismatch {
+            ui.warning("History checksum mismatch: the file was edited or is corrupted");
+        }
 
         // Display target directory prominently
         ui.kv(
-            "Target directory",
+            messages.get("label.target_directory"),
             &history.target_directory.display().to_string(),
         );
+        ui.kv(
+            messages.get("label.executed_at"),
+            &format_timestamp(&history.executed_at, args.local_time),
+        );
 
         // If user provided target_dir, validate it matches history
         if let Some(target_dir) = &args.target_dir {
@@ -102,322 +232,2195 @@ fn run(args: Args, ui: &mut Ui) -> Result<(), AppError> {
             ui.success("Target directory verified");
         }
 
-        let options = RevertOptions { dry_run: args.dry };
+        let options = RevertOptions {
+            dry_run: args.dry,
+            on_conflict: args.on_conflict,
+            strict: args.strict,
+            post_run_verify: args.post_run_verify,
+        };
 
-        let result = revert_from_history(history_file, &options, &mut progress)
-            .map_err(|e| AppError::Other(format!("Revert failed: {}", e)))?;
+        let result = if args.interactive {
+            revert_from_history_with(
+                history_file,
+                &options,
+                conflict_resolver(ui, &args).as_mut(),
+                &mut progress,
+            )
+        } else {
+            revert_from_history(history_file, &options, &mut progress)
+        }
+        .map_err(|e| AppError::Other(format!("Revert failed: {}", e)))?;
 
         // Display results
-        display_revert_result(ui, &result);
+        display_revert_result(ui, &result, args.local_time);
+        if args.strict {
+            refuse_discrepancies(result.post_verify.as_ref())?;
+        }
     } else if let Some(target_dir) = &args.target_dir {
-        // Step 1: Scan directory
-        ui.step(&format!("Scanning {}", target_dir.display()));
-        let entries = scan_directory(target_dir)?;
-        ui.step_done();
-        ui.kv("Found", &format!("{} directories", entries.len()));
-
-        info!("Found {} subdirectories", entries.len());
-        for entry in &entries {
-            debug!("  {}", entry.name);
+        let started = Instant::now();
+        let outcome = handle_rename(target_dir, &args, ui, &mut progress);
+        if args.monitoring_output {
+            let elapsed = started.elapsed();
+            let line = match &outcome {
+                Ok(run) => run
+                    .as_ref()
+                    .map(|run| monitoring::status_line(Ok(run), elapsed)),
+                Err(e) => Some(monitoring::status_line(Err(e), elapsed)),
+            };
+            if let Some(Err(e)) = line.map(|line| writeln!(ui.data(), "{}", line)) {
+                debug!("Failed to write monitoring line: {}", e);
+            }
         }
-
-        // Step 2: Validate format
-        ui.step("Validating format");
-        let validation = validate_directories(&entries)?;
-        ui.step_done();
-
-        let format_name = match validation.format {
-            DirectoryFormat::AniDb => "AniDB",
-            DirectoryFormat::HumanReadable => "Human-readable",
-        };
-        ui.kv("Format", format_name);
-
-        info!("All directories are in {:?} format", validation.format);
-
-        // Step 3: Perform rename based on current format
-        ui.blank();
-
-        let direction = match validation.format {
-            DirectoryFormat::AniDb => RenameDirection::AniDbToReadable,
-            DirectoryFormat::HumanReadable => RenameDirection::ReadableToAniDb,
-        };
-
-        if args.dry {
-            ui.boxed_title("DRY RUN");
+        if let Some(path) = &args.report {
+            write_report(path, &args, ui, &outcome);
         }
+        let run = outcome?;
+        if args.unattended
+            && run
+                .as_ref()
+                .is_some_and(|run| monitoring::run_status(run) == monitoring::Status::Warning)
+        {
+            return Err(AppError::Other(
+                "The run left directories untouched or needs attention (see above)".to_string(),
+            ));
+        }
+    }
 
-        ui.section(&format!("Renaming ({})", direction.description()));
-        ui.blank();
-
-        let result = match validation.format {
-            DirectoryFormat::AniDb => {
-                // AniDB -> Human-readable: requires API for metadata
-                let api_config = config_from_env();
-
-                if !api_config.is_configured() && !args.dry {
-                    ui.warning("API not configured, using cached data if available");
-                    info!("API not configured, will use cached data if available");
-                }
-
-                let options = RenameOptions {
-                    max_length: args.max_length,
-                    dry_run: args.dry,
-                    cache_expiry_days: args.cache_expiry,
-                };
-
-                rename_to_readable(
-                    target_dir,
-                    &validation,
-                    &api_config,
-                    &options,
-                    &mut progress,
-                )?
-            }
-            DirectoryFormat::HumanReadable => {
-                // Human-readable -> AniDB: no API needed
-                let mut result = RenameResult::new(RenameDirection::ReadableToAniDb, args.dry);
-                let total = validation.directories.len();
-
-                for (i, parsed) in validation.directories.iter().enumerate() {
-                    let destination_name = build_anidb_name(parsed.series_tag(), parsed.anidb_id());
-
-                    let source_path = target_dir.join(parsed.original_name());
-
-                    let op = RenameOperation::new(
-                        source_path.clone(),
-                        destination_name.clone(),
-                        parsed.anidb_id(),
-                        false,
-                    );
-
-                    // Check destination doesn't exist
-                    if op.destination_path.exists() && !args.dry {
-                        return Err(AppError::RenameError {
-                            from: op.source_name.clone(),
-                            to: op.destination_name.clone(),
-                            source: std::io::Error::new(
-                                std::io::ErrorKind::AlreadyExists,
-                                "Destination already exists",
-                            ),
-                        });
-                    }
+    Ok(())
+}
 
-                    ui.rename_progress(i + 1, total, &op.source_name, &op.destination_name);
+/// Plan and execute the renames of `target_dir`
+///
+/// Returns the outcome of the run, or `None` when the plan was only printed
+/// (`--print-plan-only`) or streamed as events (`--output ndjson`).
+fn handle_rename(
+    target_dir: &std::path::Path,
+    args: &Args,
+    ui: &mut Ui,
+    progress: &mut Progress,
+) -> Result<Option<RunResult>, AppError> {
+    let messages = ui.messages();
+    let plan_options = plan_options(args)?;
+    let api_configured = plan_options.api_config.is_configured();
+    warn_clock_skew(target_dir, ui);
+
+    if args.print_plan_only {
+        return handle_print_plan_only(target_dir, &plan_options, args.json_output(), ui, progress)
+            .map(|_| None);
+    }
 
-                    // Execute rename if not dry run
-                    if !args.dry {
-                        std::fs::rename(&op.source_path, &op.destination_path).map_err(|e| {
-                            AppError::RenameError {
-                                from: op.source_name.clone(),
-                                to: op.destination_name.clone(),
-                                source: e,
-                            }
-                        })?;
+    if args.output == OutputFormat::Json {
+        return Err(AppError::InvalidArgument(
+            "--output json is for standalone commands; renames stream --output ndjson".to_string(),
+        ));
+    }
 
-                        info!("Renamed: {} -> {}", op.source_name, op.destination_name);
-                    }
+    if args.output == OutputFormat::Ndjson {
+        return run_ndjson(target_dir, &plan_options, args, ui).map(|_| None);
+    }
 
-                    result.add_operation(op);
-                }
+    if let Some(chunk_size) = args.chunk_size {
+        return handle_chunked(
+            target_dir,
+            &plan_options,
+            chunk_size as usize,
+            args,
+            ui,
+            progress,
+        )
+        .map(|_| None);
+    }
 
-                result
-            }
+    // Scan, validate and plan; the observer shows each step as it completes
+    ui.step(&messages.format("ui.scanning", &[("path", &target_dir.display())]));
+    let mut plan = {
+        let mut observer = RunObserver {
+            ui,
+            progress,
+            dry_run: args.dry,
+            api_configured,
         };
+        pipeline::plan(target_dir, &plan_options, &mut observer)?
+    };
+    if !args.dry {
+        confirm_id_mismatches(ui, &mut plan.result);
+        if args.strict {
+            refuse_invalid_names(&plan.result)?;
+        }
+    }
 
-        // Summary
-        ui.blank();
+    let exec_options = exec_options(args);
+    let run = pipeline::execute_with(
+        plan,
+        &exec_options,
+        conflict_resolver(ui, args).as_mut(),
+        progress,
+    )?;
+    let result = &run.result;
+
+    // The dry run plan is data: write it to stdout, unless the monitoring
+    // line must be all there is
+    if result.dry_run && !args.monitoring_output {
+        write_dry_run(ui, result, args.preview, args.group_by);
+    }
 
-        let truncated = result.truncated_count();
+    // Summary
+    ui.blank();
 
-        if result.dry_run {
-            ui.dim(&format!(
-                "{} directories would be renamed. Run without --dry to apply.",
-                result.operations.len()
-            ));
-            if truncated > 0 {
-                ui.warning(&format!(
-                    "{} name(s) will be truncated due to length limits",
-                    truncated
-                ));
+    let truncated = result.truncated_count();
+    let quarantined = result.quarantined_count();
+    let trashed = result.trashed_count();
+    if !result.restricted_skipped.is_empty() {
+        ui.dim(&format!(
+            "{} restricted director{} left untouched",
+            result.restricted_skipped.len(),
+            if result.restricted_skipped.len() == 1 {
+                "y"
+            } else {
+                "ies"
             }
-        } else {
-            ui.success(&format!("{} directories renamed", result.operations.len()));
-
-            if truncated > 0 {
-                ui.warning(&format!(
-                    "{} name(s) were truncated due to length limits",
-                    truncated
-                ));
+        ));
+    }
+    if !result.unaired_skipped.is_empty() {
+        ui.dim(&format!(
+            "{} unaired director{} left untouched",
+            result.unaired_skipped.len(),
+            if result.unaired_skipped.len() == 1 {
+                "y"
+            } else {
+                "ies"
             }
-
-            // Write history file
-            if !result.is_empty() {
-                match write_history(&result, target_dir) {
-                    Ok(history_path) => {
-                        ui.dim(&format!("History: {}", history_path.display()));
-                    }
-                    Err(e) => {
-                        ui.warning(&format!("Failed to write history: {}", e));
-                    }
-                }
+        ));
+    }
+    if !result.busy_skipped.is_empty() {
+        ui.warning(&format!(
+            "{} director{} with open files left untouched: {}",
+            result.busy_skipped.len(),
+            if result.busy_skipped.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            result.busy_skipped.join(", ")
+        ));
+    }
+    if !result.permission_skipped.is_empty() {
+        ui.warning(&format!(
+            "{} director{} this user may not rename left untouched: {}",
+            result.permission_skipped.len(),
+            if result.permission_skipped.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            result
+                .permission_skipped
+                .iter()
+                .map(|p| p.source_name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if !result.recent_skipped.is_empty() {
+        ui.warning(&format!(
+            "{} director{} modified recently left untouched (--exclude-recent): {}",
+            result.recent_skipped.len(),
+            if result.recent_skipped.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            result
+                .recent_skipped
+                .iter()
+                .map(|s| s.source_name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        if args.verbose > 0 {
+            for skip in &result.recent_skipped {
+                ui.dim(&format!(
+                    "  {}: {} changed",
+                    skip.source_name,
+                    skip.file.display()
+                ));
             }
         }
-
-        ui.blank();
     }
-
-    Ok(())
-}
-
-fn display_revert_result(ui: &mut Ui, result: &revert::RevertResult) {
-    ui.blank();
+    if !result.vanished.is_empty() {
+        ui.warning(&format!(
+            "{} director{} vanished before renaming and left out of the history: {}",
+            result.vanished.len(),
+            if result.vanished.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            result.vanished.join(", ")
+        ));
+    }
+    display_id_findings(ui, result);
+    let english_fallback = result.english_fallback_count();
+    let english_fallback_note = format!(
+        "{} name(s) used English title as primary (no main title on AniDB)",
+        english_fallback
+    );
 
     if result.dry_run {
-        ui.boxed_title("REVERT DRY RUN");
-        ui.blank();
-        ui.kv(
-            "History file",
-            &result.original_history.display().to_string(),
+        ui.dim(
+            &ui.messages()
+                .format("summary.dry_run", &[("count", &result.operations.len())]),
         );
-        ui.blank();
-        ui.info(&format!(
-            "Would revert {} directories:",
-            result.operations.len()
-        ));
-        ui.blank();
-
-        for op in &result.operations {
-            ui.list_item(&op.current_name, &op.revert_name);
+        if truncated > 0 {
+            ui.warning(&format!(
+                "{} name(s) will be truncated due to length limits",
+                truncated
+            ));
+        }
+        if english_fallback > 0 {
+            ui.dim(&english_fallback_note);
+        }
+        let placeholders = result.placeholder_count();
+        if placeholders > 0 {
+            ui.dim(&format!(
+                "{} name(s) are placeholders for uncached ids (marked \"(placeholder)\")",
+                placeholders
+            ));
+        }
+        let overridden = result.override_count();
+        if overridden > 0 {
+            ui.dim(&format!(
+                "{} name(s) use the overrides file (marked \"(override applied)\")",
+                overridden
+            ));
+        }
+        let retagged = result.retag_count();
+        if retagged > 0 {
+            ui.dim(&format!(
+                "{} name(s) get their series tag from the tag map (marked \"(tag ... -> ...)\")",
+                retagged
+            ));
+        }
+        if quarantined > 0 {
+            ui.warning(&format!(
+                "{} unresolved director{} would be moved to {}/",
+                quarantined,
+                if quarantined == 1 { "y" } else { "ies" },
+                UNRESOLVED_DIR
+            ));
+        }
+        if trashed > 0 {
+            ui.warning(&format!(
+                "{} unresolved director{} would be moved to the trash",
+                trashed,
+                if trashed == 1 { "y" } else { "ies" }
+            ));
+        }
+        let invalid = result.invalid_name_count();
+        if invalid > 0 {
+            ui.warning(&format!(
+                "{} name(s) would fail on the target filesystem (marked \"would fail\")",
+                invalid
+            ));
         }
-
-        ui.blank();
-        ui.dim("Run without --dry to apply these reverts.");
     } else {
-        ui.boxed_title("REVERT COMPLETE");
-        ui.blank();
-        ui.success(&format!("{} directories restored", result.operations.len()));
-        ui.blank();
+        ui.success(
+            &ui.messages()
+                .format("summary.renamed", &[("count", &result.applied_count())]),
+        );
 
-        for op in &result.operations {
-            ui.list_done(&op.current_name, &op.revert_name);
+        let skipped = result.skipped_count();
+        if skipped > 0 {
+            ui.warning(&format!(
+                "{} director{} skipped due to conflicts",
+                skipped,
+                if skipped == 1 { "y" } else { "ies" }
+            ));
         }
 
-        if let Some(ref history_path) = result.revert_history_path {
-            ui.blank();
-            ui.dim(&format!("Revert history: {}", history_path.display()));
+        if truncated > 0 {
+            ui.warning(&format!(
+                "{} name(s) were truncated due to length limits",
+                truncated
+            ));
+        }
+        if english_fallback > 0 {
+            ui.dim(&english_fallback_note);
+        }
+        if quarantined > 0 {
+            ui.warning(&format!(
+                "{} unresolved director{} moved to {}/",
+                quarantined,
+                if quarantined == 1 { "y" } else { "ies" },
+                UNRESOLVED_DIR
+            ));
+        }
+        if trashed > 0 {
+            ui.warning(&format!(
+                "{} unresolved director{} moved to the trash (see the history file to restore)",
+                trashed,
+                if trashed == 1 { "y" } else { "ies" }
+            ));
+        }
+        if result.title_changes > 0 {
+            ui.dim(&format!(
+                "{} cached title(s) changed upstream (see --cache-info {} --changes)",
+                result.title_changes,
+                target_dir.display()
+            ));
+        }
+        if result.deduplicated_fetches > 0 {
+            ui.dim(&format!(
+                "{} API request(s) saved by reusing results for duplicate ids",
+                result.deduplicated_fetches
+            ));
+        }
+
+        if let Some(check) = &result.post_verify {
+            display_post_verify(ui, check);
+        }
+        ui.dim(&format!("Run id: {}", result.run_id));
+        if let Some(history_path) = &run.history_path {
+            ui.dim(&format!("History: {}", history_path.display()));
+            ui.dim(&undo_hint(history_path));
+        }
+        if let Some(e) = &run.history_error {
+            ui.warning(&format!("Failed to write history: {}", e));
         }
     }
 
-    ui.blank();
-}
+    if args.verbose > 0 {
+        display_slowest(ui, &result.timings);
+    }
 
-fn handle_cache_info(
-    dir: &std::path::Path,
-    cache_expiry: u32,
-    ui: &mut Ui,
-) -> Result<(), AppError> {
-    ui.section("Cache Information");
     ui.blank();
 
-    let config = CacheConfig::for_target_dir(dir, cache_expiry);
-    ui.kv("Cache file", &config.cache_path.display().to_string());
-
-    if !config.cache_path.exists() {
-        ui.info("No cache file found");
-        ui.blank();
-        return Ok(());
+    if result.dry_run && args.strict {
+        refuse_invalid_names(result)?;
     }
-
-    let cache = CacheStore::load(config.clone());
-    let total = cache.len();
-    let expired = cache.expired_count();
-    let valid = total - expired;
-
-    ui.kv("Total entries", &total.to_string());
-    ui.kv("Valid entries", &valid.to_string());
-    ui.kv("Expired entries", &expired.to_string());
-    ui.kv("Expiry setting", &format!("{} days", cache_expiry));
-
-    if let Ok(metadata) = std::fs::metadata(&config.cache_path) {
-        let size = metadata.len();
-        let size_str = if size < 1024 {
-            format!("{} B", size)
-        } else if size < 1024 * 1024 {
-            format!("{:.1} KB", size as f64 / 1024.0)
-        } else {
-            format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
-        };
-        ui.kv("File size", &size_str);
+    if args.strict {
+        refuse_discrepancies(result.post_verify.as_ref())?;
     }
-
-    ui.blank();
-    Ok(())
+    Ok(Some(run))
 }
 
-fn handle_cache_clear(
-    dir: &std::path::Path,
-    cache_expiry: u32,
-    ui: &mut Ui,
-) -> Result<(), AppError> {
-    ui.section("Clear Cache");
-    ui.blank();
-
-    let config = CacheConfig::for_target_dir(dir, cache_expiry);
+/// The command reverting the run recorded in `history_path`
+fn undo_hint(history_path: &std::path::Path) -> String {
+    format!(
+        "To undo: anidb2folder --revert '{}'",
+        history_path.display()
+    )
+}
 
-    if !config.cache_path.exists() {
-        ui.info("No cache file found");
-        ui.blank();
-        return Ok(());
+/// Report the `--post-run-verify` check of a rename or revert
+fn display_post_verify(ui: &mut Ui, check: &PostRunCheck) {
+    if check.is_clean() {
+        ui.success(&format!(
+            "Post-run check: all {} path(s) as expected",
+            check.checked
+        ));
+        return;
     }
+    ui.warning(&format!(
+        "Post-run check: {} of {} path(s) not as the run left them (another process may have interfered):",
+        check.discrepancies.len(),
+        check.checked
+    ));
+    for discrepancy in &check.discrepancies {
+        ui.warning(&format!(
+            "  {}: {}",
+            discrepancy.path.display(),
+            discrepancy.kind.description()
+        ));
+    }
+}
 
-    let mut cache = CacheStore::load(config);
-    let count = cache.len();
+/// With --strict, fail a run whose post-run check found discrepancies
+fn refuse_discrepancies(check: Option<&PostRunCheck>) -> Result<(), AppError> {
+    match check.map_or(0, |c| c.discrepancies.len()) {
+        0 => Ok(()),
+        n => Err(AppError::Other(format!(
+            "{} path(s) not as the run left them (--post-run-verify --strict)",
+            n
+        ))),
+    }
+}
 
-    cache.clear();
-    if let Err(e) = cache.save() {
-        return Err(AppError::Other(format!("Failed to save cache: {}", e)));
+/// List the slowest steps of a run (verbose summary)
+fn display_slowest(ui: &mut Ui, timings: &Timings) {
+    if timings.slowest().is_empty() {
+        return;
+    }
+    ui.dim("Slowest steps:");
+    for timing in timings.slowest() {
+        ui.dim(&format!(
+            "  {:.1}s  {:<7}  {}",
+            timing.elapsed.as_secs_f64(),
+            timing.step.as_str(),
+            timing.name
+        ));
     }
+}
 
-    ui.success(&format!("Cleared {} cached entries", count));
-    ui.blank();
-    Ok(())
+/// Write the planned renames of a dry run to stdout
+fn write_dry_run(ui: &mut Ui, result: &RenameResult, preview: PreviewFormat, group_by: GroupBy) {
+    let written = match preview {
+        PreviewFormat::Arrow => {
+            output::display_dry_run_with(result, group_by, ui.data(), &|from, to| {
+                format!("{} -> {}", from, to)
+            })
+        }
+        PreviewFormat::Diff => {
+            let mut lines = Vec::new();
+            output::display_dry_run_with(result, group_by, &mut lines, &|from, to| {
+                ui.render_diff(from, to)
+            })
+            .and_then(|_| ui.data().write_all(&lines))
+        }
+    };
+    if let Err(e) = written {
+        debug!("Failed to write dry run output: {}", e);
+    }
 }
 
-fn handle_cache_prune(
-    dir: &std::path::Path,
-    cache_expiry: u32,
+/// Plan and rename `chunk_size` directories at a time (`--chunk-size`)
+///
+/// A dry run prints each chunk as soon as it is planned; the summary only
+/// has counts and a few examples.
+fn handle_chunked(
+    target_dir: &std::path::Path,
+    plan_options: &PlanOptions,
+    chunk_size: usize,
+    args: &Args,
     ui: &mut Ui,
+    progress: &mut Progress,
 ) -> Result<(), AppError> {
-    ui.section("Prune Expired Cache Entries");
-    ui.blank();
-
-    let config = CacheConfig::for_target_dir(dir, cache_expiry);
+    let messages = ui.messages();
+    let api_configured = plan_options.api_config.is_configured();
+    ui.step(&messages.format("ui.scanning", &[("path", &target_dir.display())]));
+    let mut chunks = {
+        let mut observer = RunObserver {
+            ui,
+            progress,
+            dry_run: args.dry,
+            api_configured,
+        };
+        pipeline::plan_chunked(target_dir, plan_options, chunk_size, &mut observer)?
+    };
+    let total = chunks.remaining();
+    info!("Planning {} directories in chunks of {}", total, chunk_size);
+
+    let exec_options = exec_options(args);
+    let mut run = ChunkedRun::new(&chunks, &exec_options);
+    let mut invalid_names = 0;
+    loop {
+        let next = {
+            let mut observer = RunObserver {
+                ui,
+                progress,
+                dry_run: args.dry,
+                api_configured,
+            };
+            chunks.next_plan(&mut observer)
+        };
+        let Some(plan) = next else {
+            break;
+        };
+        let mut plan = plan?;
+        if !args.dry {
+            confirm_id_mismatches(ui, &mut plan.result);
+            if args.strict {
+                refuse_invalid_names(&plan.result)?;
+            }
+        }
+        let result = run.execute(plan, conflict_resolver(ui, args).as_mut(), progress)?;
+        if result.dry_run {
+            write_dry_run(ui, &result, args.preview, GroupBy::None);
+        }
+        invalid_names += result.invalid_name_count();
+        debug!(
+            "{} of {} directories left to plan",
+            chunks.remaining(),
+            total
+        );
+    }
+    let summary = run.finish(progress)?;
 
-    if !config.cache_path.exists() {
-        ui.info("No cache file found");
+    ui.blank();
+    if !args.dry && !summary.sample.is_empty() {
+        ui.dim(&format!(
+            "First {} of {} operations:",
+            summary.sample.len(),
+            summary.planned
+        ));
+        for op in &summary.sample {
+            ui.list_done(&op.source_name, &op.destination_name);
+        }
         ui.blank();
-        return Ok(());
     }
-
-    let mut cache = CacheStore::load(config);
-    let before = cache.len();
-    let removed = cache.prune_expired();
-    let after = cache.len();
-
-    if let Err(e) = cache.save() {
-        return Err(AppError::Other(format!("Failed to save cache: {}", e)));
+    display_chunked_summary(ui, &summary);
+    if args.verbose > 0 {
+        display_slowest(ui, &summary.timings);
     }
+    ui.blank();
 
-    ui.kv("Entries before", &before.to_string());
-    ui.kv("Expired removed", &removed.to_string());
-    ui.kv("Entries after", &after.to_string());
+    if args.strict {
+        refuse_discrepancies(summary.post_verify.as_ref())?;
+    }
+    if args.dry && args.strict && invalid_names > 0 {
+        return Err(AppError::Other(format!(
+            "{} planned name(s) would fail on the target filesystem (--strict)",
+            invalid_names
+        )));
+    }
+    Ok(())
+}
 
-    if removed > 0 {
-        ui.success(&format!("Pruned {} expired entries", removed));
+fn display_chunked_summary(ui: &mut Ui, summary: &ChunkedSummary) {
+    let directories =
+        |count: usize| format!("{} director{}", count, if count == 1 { "y" } else { "ies" });
+    if summary.dry_run {
+        ui.dim(
+            &ui.messages()
+                .format("summary.dry_run", &[("count", &summary.planned)]),
+        );
     } else {
-        ui.info("No expired entries to prune");
+        ui.success(
+            &ui.messages()
+                .format("summary.renamed", &[("count", &summary.applied)]),
+        );
     }
-
-    ui.blank();
+    ui.dim(&format!(
+        "{} chunk(s), {} API request(s), {} cached",
+        summary.chunks, summary.api_requests, summary.cache_hits
+    ));
+    if summary.untouched > 0 {
+        ui.warning(&format!(
+            "{} left untouched",
+            directories(summary.untouched)
+        ));
+    }
+    if summary.truncated > 0 {
+        ui.warning(&format!(
+            "{} name(s) truncated due to length limits",
+            summary.truncated
+        ));
+    }
+    if summary.quarantined > 0 {
+        ui.warning(&format!(
+            "{} unresolved ({}/ or the trash)",
+            directories(summary.quarantined),
+            UNRESOLVED_DIR
+        ));
+    }
+    if summary.placeholders > 0 {
+        ui.dim(&format!(
+            "{} name(s) are placeholders for uncached ids (marked \"(placeholder)\")",
+            summary.placeholders
+        ));
+    }
+    if summary.invalid_names > 0 {
+        ui.warning(&format!(
+            "{} name(s) would fail on the target filesystem (marked \"would fail\")",
+            summary.invalid_names
+        ));
+    }
+    if !summary.dry_run {
+        if let Some(check) = &summary.post_verify {
+            display_post_verify(ui, check);
+        }
+        ui.dim(&format!("Run id: {}", summary.run_id));
+        if let Some(history_path) = &summary.history_path {
+            ui.dim(&format!("History: {}", history_path.display()));
+            ui.dim(&undo_hint(history_path));
+        }
+        if let Some(e) = &summary.history_error {
+            ui.warning(&format!("Failed to write history: {}", e));
+        }
+    }
+}
+
+/// With --strict, fail a plan containing names the target filesystem would reject
+fn refuse_invalid_names(result: &RenameResult) -> Result<(), AppError> {
+    match result.invalid_name_count() {
+        0 => Ok(()),
+        n => Err(AppError::Other(format!(
+            "{} planned name(s) would fail on the target filesystem (--strict)",
+            n
+        ))),
+    }
+}
+
+/// Planning options for a rename run, from the command line
+fn plan_options(args: &Args) -> Result<PlanOptions, AppError> {
+    Ok(PlanOptions {
+        max_length: args.max_length,
+        cache_expiry_days: args.cache_expiry,
+        cache_save_every: args.cache_save_every,
+        dry_run: args.dry,
+        drop_series_tag: args.drop_series_tag,
+        quarantine_unresolved: args.quarantine_unresolved,
+        placeholder: args.placeholder.clone(),
+        restricted: args.restricted,
+        restricted_marker: args.restricted_marker.clone(),
+        unaired: args.unaired,
+        replacements: args.replace.clone(),
+        type_suffixes: args.type_suffix.clone(),
+        include_type: args.include_type,
+        verify_ids: args.verify_ids,
+        verify_fetch: args.fetch,
+        fs_profile: args.fs_profile,
+        use_trash: args.use_trash,
+        allow_dangerous_target: args.i_know_what_im_doing,
+        max_unrecognized: args.max_unrecognized,
+        show_all_unrecognized: args.show_all_unrecognized,
+        scan: ScanOptions {
+            threads: args.parallel_scan,
+        },
+        parse: ParseOptions {
+            strict: args.strict,
+        },
+        only_names: None,
+        api_config: resolve_api_config(args.credentials_file.as_deref())?,
+        overrides: args.overrides.as_deref().map(Overrides::load).transpose()?,
+        titles: args
+            .titles_file
+            .as_deref()
+            .map(TitleDump::load)
+            .transpose()?
+            .map(Arc::new),
+        tag_map: args.tag_map.as_deref().map(TagMap::load).transpose()?,
+        refuse_altered_tags: args.strict,
+        slow_threshold: Duration::from_secs(args.slow_threshold),
+        exclude_recent: args.exclude_recent,
+        template: args.template.clone(),
+    })
+}
+
+/// Warn when the records of `dir` say the system clock is behind
+fn warn_clock_skew(dir: &std::path::Path, ui: &mut Ui) {
+    if let Some(skew) = clock::detect_skew(dir, chrono::Utc::now()) {
+        ui.warning(&format!(
+            "{} Cache expiry and history names depend on the clock",
+            skew
+        ));
+    }
+}
+
+/// Execution options for a rename run, from the command line
+fn exec_options(args: &Args) -> ExecOptions {
+    ExecOptions {
+        on_conflict: args.on_conflict,
+        write_history: true,
+        check_open_files: args.check_open_files,
+        check_permissions: args.check_permissions || args.strict,
+        strict: args.strict,
+        post_run_verify: args.post_run_verify,
+        jobs: args.jobs as usize,
+        hooks: Hooks {
+            after_each: args.exec_after.clone(),
+            after_run: args.exec_after_run.clone(),
+            strict: args.strict_hooks,
+        },
+    }
+}
+
+/// Rename new AniDB-format directories as they appear in `dir`, until interrupted
+///
+/// Each batch is planned and executed like a normal run restricted to the
+/// new directories, with its own history file. A failed batch is reported
+/// and the watcher keeps going; its directories are not retried.
+fn handle_watch(
+    dir: &std::path::Path,
+    args: &Args,
+    ui: &mut Ui,
+    progress: &mut Progress,
+) -> Result<(), AppError> {
+    let messages = ui.messages();
+    let base_options = plan_options(args)?;
+    let exec_options = exec_options(args);
+    let interval = Duration::from_secs(args.once_per);
+
+    let mut watcher = Watcher::new(dir);
+    watcher.poll()?;
+    warn_clock_skew(dir, ui);
+    if let Err(e) = watch::install_interrupt_handler() {
+        warn!("Cannot handle SIGINT: {}", e);
+    }
+    info!("Watching {:?} every {:?}", dir, interval);
+    ui.info(&format!(
+        "Watching {} every {}s (Ctrl-C to stop)",
+        dir.display(),
+        args.once_per
+    ));
+
+    while !watch::wait(interval) {
+        let ready = match watcher.poll() {
+            Ok(ready) => ready,
+            Err(e) => {
+                warn!("Cannot scan {:?}: {}", dir, e);
+                ui.warning(&format!("Cannot scan {}: {}", dir.display(), e));
+                continue;
+            }
+        };
+        if ready.is_empty() {
+            continue;
+        }
+
+        info!("New directories: {:?}", ready);
+        let key = if ready.len() == 1 {
+            "ui.new_directory"
+        } else {
+            "ui.new_directories"
+        };
+        ui.step(&messages.format(key, &[("count", &ready.len())]));
+        let options = PlanOptions {
+            only_names: Some(ready),
+            ..base_options.clone()
+        };
+        let outcome = pipeline::plan(dir, &options, progress).and_then(|plan| {
+            if args.strict {
+                refuse_invalid_names(&plan.result)?;
+            }
+            let mut policy = args.on_conflict;
+            pipeline::execute_with(plan, &exec_options, &mut policy, progress)
+        });
+        match outcome {
+            Ok(run) => display_watch_batch(ui, &run),
+            Err(e) => {
+                error!("Batch failed: {}", e);
+                ui.error(&e.detailed_message_in(&ui.messages()));
+            }
+        }
+    }
+
+    info!("Interrupted, stopped watching {:?}", dir);
+    ui.info("Stopped watching");
+    Ok(())
+}
+
+/// Report each action of a watch batch
+fn display_watch_batch(ui: &mut Ui, run: &RunResult) {
+    let result = &run.result;
+    if result.dry_run {
+        if let Err(e) = output::display_dry_run_simple(result, ui.data()) {
+            debug!("Failed to write dry run output: {}", e);
+        }
+        return;
+    }
+    for op in &result.operations {
+        if op.is_skipped() {
+            info!("Skipped {} (conflict)", op.source_name);
+            ui.warning(&format!("{} skipped due to a conflict", op.source_name));
+        } else {
+            info!("Renamed {} -> {}", op.source_name, op.destination_name);
+            ui.list_done(&op.source_name, &op.destination_name);
+        }
+    }
+    for name in &result.vanished {
+        ui.warning(&format!("{} vanished before renaming", name));
+    }
+    for problem in &result.permission_skipped {
+        ui.warning(&format!(
+            "{} left untouched: {}",
+            problem.source_name, problem.reason
+        ));
+    }
+    for skip in &result.recent_skipped {
+        ui.warning(&format!(
+            "{} left untouched: modified recently",
+            skip.source_name
+        ));
+    }
+    if let Some(check) = &result.post_verify {
+        display_post_verify(ui, check);
+    }
+    if let Some(path) = &run.history_path {
+        ui.dim(&format!("History: {}", path.display()));
+        ui.dim(&undo_hint(path));
+    }
+    if let Some(e) = &run.history_error {
+        ui.warning(&format!("Failed to write history file: {}", e));
+    }
+}
+
+/// Plan and execute a rename, streaming NDJSON events to stdout
+///
+/// Conflicts are resolved with the fixed `--on-conflict` policy. The stream
+/// always ends with a summary event, carrying the error if the run failed.
+fn run_ndjson(
+    target_dir: &std::path::Path,
+    plan_options: &PlanOptions,
+    args: &Args,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    let mut events = EventStream::new(std::io::stdout());
+    let outcome = pipeline::plan(target_dir, plan_options, &mut events).and_then(|mut plan| {
+        if !args.dry {
+            confirm_id_mismatches(ui, &mut plan.result);
+        }
+        if args.strict {
+            refuse_invalid_names(&plan.result)?;
+        }
+        let exec_options = exec_options(args);
+        let mut policy = args.on_conflict;
+        pipeline::execute_with(plan, &exec_options, &mut policy, &mut events)
+    });
+    let outcome = outcome.and_then(|run| {
+        if args.strict {
+            refuse_discrepancies(run.result.post_verify.as_ref())?;
+        }
+        Ok(run)
+    });
+
+    match &outcome {
+        Ok(run) => {
+            if run.result.dry_run {
+                events.planned(&run.result);
+            }
+            if let Some(path) = &run.history_path {
+                events.history_written(path);
+            }
+            if let Some(e) = &run.history_error {
+                events.warn(&format!("Failed to write history file: {}", e));
+            }
+            events.summary(Some(&run.result), run.history_path.as_deref(), None);
+        }
+        Err(e) => events.summary(None, None, Some(e)),
+    }
+    outcome.map(|_| ())
+}
+
+/// Ask whether to convert each directory whose id failed `--verify-ids`
+///
+/// Without a terminal to ask on, flagged directories are left untouched.
+fn confirm_id_mismatches(ui: &mut Ui, result: &mut RenameResult) {
+    if result.id_mismatches.is_empty() {
+        return;
+    }
+
+    let interactive = ui.can_prompt();
+    if !interactive {
+        ui.warning("Not a terminal, leaving directories with mismatched ids untouched");
+    }
+
+    for mismatch in result.id_mismatches.clone() {
+        let convert = interactive
+            && ui.choose(
+                &format!(
+                    "{} has AniDB ID {}, which is {}. Convert anyway?",
+                    mismatch.source_name,
+                    mismatch.anidb_id,
+                    mismatch
+                        .anidb_title
+                        .as_deref()
+                        .map(|t| format!("\"{}\"", t))
+                        .unwrap_or_else(|| "not on AniDB".to_string())
+                ),
+                &[('y', "convert"), ('n', "leave untouched")],
+            ) == Some('y');
+        if !convert {
+            info!("Leaving {} untouched (id mismatch)", mismatch.source_name);
+            result.skip_source(&mismatch.source_name);
+        }
+    }
+}
+
+/// Report `--verify-ids` findings
+fn display_id_findings(ui: &mut Ui, result: &RenameResult) {
+    for mismatch in &result.id_mismatches {
+        let title = mismatch
+            .anidb_title
+            .as_deref()
+            .map(|t| format!("\"{}\"", t))
+            .unwrap_or_else(|| "not on AniDB".to_string());
+        let outcome = if result.id_skipped.contains(&mismatch.source_name) {
+            ", left untouched"
+        } else {
+            ""
+        };
+        ui.warning(&format!(
+            "ID mismatch: {} (AniDB ID {} is {}){}",
+            mismatch.source_name, mismatch.anidb_id, title, outcome
+        ));
+    }
+    if result.unverified_ids > 0 {
+        ui.dim(&format!(
+            "{} id(s) not verified: not in the cache (use --fetch)",
+            result.unverified_ids
+        ));
+    }
+}
+
+/// Shows planning steps in the UI and forwards fetch progress
+struct RunObserver<'a> {
+    ui: &'a mut Ui,
+    progress: &'a mut Progress,
+    dry_run: bool,
+    api_configured: bool,
+}
+
+impl ProgressObserver for RunObserver<'_> {
+    fn scanned(&mut self, scan: &ScanResult) {
+        let messages = self.ui.messages();
+        self.ui.step_done();
+        self.ui.kv(
+            messages.get("label.found"),
+            &messages.format("value.directories", &[("count", &scan.entries.len())]),
+        );
+
+        if scan.skipped() > 0 {
+            self.ui.dim(&format!(
+                "Skipped {} hidden and {} non-directory entries",
+                scan.skipped_hidden, scan.skipped_files
+            ));
+        }
+
+        self.ui.step(messages.get("ui.validating"));
+    }
+
+    fn validated(&mut self, validation: &ValidationResult) {
+        let messages = self.ui.messages();
+        self.ui.step_done();
+
+        let (format_name, direction) = match validation.format {
+            DirectoryFormat::AniDb => ("value.anidb", "direction.anidb_to_readable"),
+            DirectoryFormat::HumanReadable => {
+                ("value.human_readable", "direction.readable_to_anidb")
+            }
+        };
+        self.ui
+            .kv(messages.get("label.format"), messages.get(format_name));
+        self.ui.blank();
+
+        if self.dry_run {
+            self.ui.boxed_title(messages.get("ui.dry_run"));
+        }
+
+        self.ui
+            .section(&messages.format("ui.renaming", &[("direction", &messages.get(direction))]));
+        self.ui.blank();
+
+        // AniDB -> Human-readable requires API for metadata
+        if validation.format == DirectoryFormat::AniDb && !self.api_configured && !self.dry_run {
+            self.ui
+                .warning("API not configured, using cached data if available");
+            info!("API not configured, will use cached data if available");
+        }
+    }
+
+    fn set_fetch_plan(&mut self, total: usize, min_interval: Duration) {
+        self.progress.set_fetch_plan(total, min_interval);
+    }
+
+    fn fetch_start(&mut self, anidb_id: u32) {
+        self.progress.fetch_start(anidb_id);
+    }
+
+    fn fetch_complete(&mut self) {
+        self.progress.fetch_complete();
+    }
+
+    fn warn(&mut self, message: &str) {
+        self.progress.warn(message);
+    }
+
+    fn slow_step(&mut self, timing: &StepTiming) {
+        self.progress.slow_step(timing);
+    }
+}
+
+/// Asks the user how to resolve each conflict, falling back to a fixed policy
+struct PromptResolver<'a> {
+    ui: &'a mut Ui,
+    fallback: ConflictResolution,
+    preview: PreviewFormat,
+}
+
+impl ConflictResolver for PromptResolver<'_> {
+    fn resolve(&mut self, source_name: &str, destination_name: &str) -> ConflictResolution {
+        if self.preview == PreviewFormat::Diff {
+            self.ui.diff(source_name, destination_name);
+        }
+        let prompt = format!(
+            "'{}' already exists (renaming '{}')",
+            destination_name, source_name
+        );
+        let choices = [
+            ('s', "skip"),
+            ('u', "add suffix"),
+            ('a', "move existing aside"),
+            ('q', "abort"),
+        ];
+
+        match self.ui.choose(&prompt, &choices) {
+            Some('s') => ConflictResolution::Skip,
+            Some('u') => ConflictResolution::Suffix,
+            Some('a') => ConflictResolution::Aside,
+            Some('q') => ConflictResolution::Abort,
+            _ => self.fallback,
+        }
+    }
+}
+
+/// Pick the conflict resolver: prompts when interactive, the configured policy otherwise
+fn conflict_resolver<'a>(ui: &'a mut Ui, args: &Args) -> Box<dyn ConflictResolver + 'a> {
+    if args.interactive && !args.dry {
+        if ui.can_prompt() {
+            return Box::new(PromptResolver {
+                ui,
+                fallback: args.on_conflict,
+                preview: args.preview,
+            });
+        }
+        ui.warning(&format!(
+            "Not a terminal, resolving conflicts with policy: {}",
+            args.on_conflict.description()
+        ));
+    }
+
+    Box::new(args.on_conflict)
+}
+
+fn display_revert_result(ui: &mut Ui, result: &revert::RevertResult, local_time: bool) {
+    let messages = ui.messages();
+    ui.blank();
+
+    if result.dry_run {
+        ui.boxed_title(messages.get("ui.revert_dry_run"));
+        ui.blank();
+        ui.kv(
+            messages.get("label.history_file"),
+            &result.original_history.display().to_string(),
+        );
+        ui.blank();
+        ui.info(&format!(
+            "Would revert {} directories:",
+            result.operations.len()
+        ));
+        ui.blank();
+
+        if let Err(e) = output::display_revert_dry_run_simple(result, ui.data()) {
+            debug!("Failed to write revert dry run output: {}", e);
+        }
+
+        ui.blank();
+        if result.conflicts.is_empty() {
+            ui.dim("Run without --dry to apply these reverts.");
+        } else {
+            ui.warning(&format!(
+                "{} change(s) cannot be reverted, so the revert would fail:",
+                result.conflicts.len()
+            ));
+            for conflict in &result.conflicts {
+                ui.warning(&format!(
+                    "  {} -> {}: {}",
+                    conflict.current_name,
+                    conflict.revert_name,
+                    conflict.kind.description()
+                ));
+            }
+            ui.blank();
+            ui.dim(
+                "Use --on-conflict skip, suffix or aside for names taken again; \
+                 missing directories have to be put back first.",
+            );
+        }
+    } else {
+        ui.boxed_title(messages.get("ui.revert_complete"));
+        ui.blank();
+        ui.kv(
+            messages.get("label.reverted_from"),
+            &messages.format(
+                "value.reverted_from",
+                &[
+                    ("path", &result.original_history.display()),
+                    (
+                        "time",
+                        &format_timestamp(&result.original_executed_at, local_time),
+                    ),
+                ],
+            ),
+        );
+        ui.success(&format!("{} directories restored", result.operations.len()));
+        if !result.vanished.is_empty() {
+            ui.warning(&format!(
+                "{} director{} vanished before reverting and left out of the revert history: {}",
+                result.vanished.len(),
+                if result.vanished.len() == 1 {
+                    "y"
+                } else {
+                    "ies"
+                },
+                result.vanished.join(", ")
+            ));
+        }
+        ui.blank();
+
+        for op in &result.operations {
+            ui.list_done(&op.current_name, &op.revert_name);
+        }
+
+        ui.blank();
+        if let Some(check) = &result.post_verify {
+            display_post_verify(ui, check);
+        }
+        ui.dim(&format!("Run id: {}", result.run_id));
+        if let Some(ref history_path) = result.revert_history_path {
+            ui.dim(&format!("Revert history: {}", history_path.display()));
+        }
+    }
+
+    ui.blank();
+}
+
+/// Point `args` at the target of a rename's history file, with the naming
+/// options the rename recorded
+///
+/// Histories written before options were recorded are redone with the
+/// current defaults, with a warning. A history whose renames are still in
+/// place is refused, as the run would convert the names back instead.
+fn apply_redo(
+    history_file: &std::path::Path,
+    args: &mut Args,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    let messages = ui.messages();
+    let history_error = |message: String| AppError::HistoryError {
+        path: Some(history_file.to_path_buf()),
+        message,
+    };
+    let history = if args.strict {
+        read_history_strict(history_file)
+    } else {
+        read_history(history_file)
+    }
+    .map_err(|e| history_error(e.to_string()))?;
+    if history.operation == OperationType::Revert {
+        return Err(history_error(
+            "--redo takes the history of a rename, not of a revert".to_string(),
+        ));
+    }
+    let target = &history.target_directory;
+    if let Some(entry) = history.changes.iter().find(|entry| {
+        entry.kind == EntryKind::Rename
+            && target.join(&entry.destination).exists()
+            && !target.join(&entry.source).exists()
+    }) {
+        return Err(history_error(format!(
+            "'{}' is still renamed; revert the history before redoing it",
+            entry.destination
+        )));
+    }
+
+    ui.kv(
+        messages.get("label.redoing"),
+        &history_file.display().to_string(),
+    );
+    match history.options {
+        Some(options) => {
+            args.max_length = options.max_length;
+            args.drop_series_tag = options.drop_series_tag;
+            args.on_conflict = options.on_conflict;
+            args.restricted = options.restricted;
+            args.unaired = options.unaired;
+            args.replace = options.replacements;
+            args.type_suffix = options.type_suffixes;
+            args.include_type = options.include_type;
+            args.template = options
+                .template
+                .as_deref()
+                .map(NameTemplate::parse)
+                .transpose()
+                .map_err(|e| history_error(format!("recorded template: {}", e)))?;
+            args.fs_profile = Some(options.fs_profile);
+            args.overrides = options.overrides;
+            args.tag_map = options.tag_map;
+        }
+        None => ui.warning(
+            "The history records no options (written by an older version); redoing with the current defaults",
+        ),
+    }
+    args.target_dir = Some(history.target_directory);
+    Ok(())
+}
+
+/// One history file of a `--revert-all` batch
+struct BatchEntry {
+    history: PathBuf,
+    target: Option<PathBuf>,
+    outcome: Result<revert::RevertResult, AppError>,
+}
+
+/// Revert every history file matching `pattern`, continuing past failures
+fn handle_revert_all(
+    pattern: &str,
+    args: &Args,
+    ui: &mut Ui,
+    progress: &mut Progress,
+) -> Result<(), AppError> {
+    let files = expand_glob(pattern);
+    if files.is_empty() {
+        return Err(AppError::InvalidArgument(format!(
+            "--revert-all: no history files match '{}'",
+            pattern
+        )));
+    }
+    info!("Batch revert: {} history files", files.len());
+
+    // Plan every file with a dry run first, so the whole batch is shown
+    // (and unreadable files reported) before anything moves
+    let plan_options = RevertOptions {
+        dry_run: true,
+        on_conflict: args.on_conflict,
+        strict: args.strict,
+        post_run_verify: args.post_run_verify,
+    };
+    let mut batch: Vec<BatchEntry> = files
+        .into_iter()
+        .map(|history| {
+            let read = if args.strict {
+                read_history_strict(&history)
+            } else {
+                read_history(&history)
+            };
+            let target = read.as_ref().ok().map(|h| h.target_directory.clone());
+            let outcome = read.map_err(AppError::from).and_then(|_| {
+                revert_from_history(&history, &plan_options, &mut Progress::silent())
+                    .map_err(AppError::from)
+                    .and_then(|result| match result.conflicts.as_slice() {
+                        // Left out of the batch, as the real revert would fail
+                        [] => Ok(result),
+                        conflicts => Err(AppError::HistoryError {
+                            path: Some(history.clone()),
+                            message: conflicts
+                                .iter()
+                                .map(ToString::to_string)
+                                .collect::<Vec<_>>()
+                                .join("; "),
+                        }),
+                    })
+            });
+            BatchEntry {
+                history,
+                target,
+                outcome,
+            }
+        })
+        .collect();
+
+    display_batch_plan(ui, &batch, args.local_time);
+
+    let planned: usize = batch
+        .iter()
+        .filter_map(|e| e.outcome.as_ref().ok())
+        .map(|r| r.operations.len())
+        .sum();
+    let readable = batch.iter().filter(|e| e.outcome.is_ok()).count();
+
+    if !args.dry && readable > 0 {
+        if ui.can_prompt() {
+            let answer = ui.choose(
+                &format!(
+                    "Revert {} directories from {} history files?",
+                    planned, readable
+                ),
+                &[('y', "yes"), ('n', "no")],
+            );
+            if answer != Some('y') {
+                ui.info("Batch revert cancelled, nothing was changed");
+                ui.blank();
+                return Ok(());
+            }
+        }
+
+        let options = RevertOptions {
+            dry_run: false,
+            on_conflict: args.on_conflict,
+            strict: args.strict,
+            post_run_verify: args.post_run_verify,
+        };
+        for entry in batch.iter_mut().filter(|e| e.outcome.is_ok()) {
+            entry.outcome = revert_from_history_with(
+                &entry.history,
+                &options,
+                conflict_resolver(ui, args).as_mut(),
+                progress,
+            )
+            .map_err(AppError::from)
+            .and_then(|result| {
+                if args.strict {
+                    refuse_discrepancies(result.post_verify.as_ref())?;
+                }
+                Ok(result)
+            });
+            if let Err(e) = &entry.outcome {
+                error!("Revert of {:?} failed: {}", entry.history, e);
+            }
+        }
+    }
+
+    display_batch_results(ui, &batch, args.dry);
+    if args.json_output() {
+        write_batch_json(ui, &batch);
+    }
+
+    let codes: Vec<ExitCode> = batch
+        .iter()
+        .filter_map(|e| e.outcome.as_ref().err())
+        .map(AppError::exit_code)
+        .collect();
+    match codes.iter().max_by_key(|code| code.severity()) {
+        Some(&code) => Err(AppError::BatchFailed {
+            failed: codes.len(),
+            total: batch.len(),
+            code,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Show the planned reverts of a batch, grouped by target directory
+fn display_batch_plan(ui: &mut Ui, batch: &[BatchEntry], local_time: bool) {
+    let messages = ui.messages();
+    let mut groups: BTreeMap<Option<&PathBuf>, Vec<&BatchEntry>> = BTreeMap::new();
+    for entry in batch {
+        groups.entry(entry.target.as_ref()).or_default().push(entry);
+    }
+
+    ui.section("Batch Revert");
+    ui.blank();
+    for (target, entries) in groups {
+        match target {
+            Some(target) => ui.kv(
+                messages.get("label.target_directory"),
+                &target.display().to_string(),
+            ),
+            None => ui.kv(
+                messages.get("label.target_directory"),
+                messages.get("value.unknown_target"),
+            ),
+        }
+        for entry in entries {
+            match &entry.outcome {
+                Ok(result) => {
+                    ui.info(&format!(
+                        "{} ({} directories, executed {})",
+                        entry.history.display(),
+                        result.operations.len(),
+                        format_timestamp(&result.original_executed_at, local_time)
+                    ));
+                    for op in &result.operations {
+                        ui.list_item(&op.current_name, &op.revert_name);
+                    }
+                }
+                Err(e) => ui.warning(&format!("{}: {}", entry.history.display(), e)),
+            }
+        }
+        ui.blank();
+    }
+}
+
+/// Report the outcome of each history file of a batch
+fn display_batch_results(ui: &mut Ui, batch: &[BatchEntry], dry_run: bool) {
+    let messages = ui.messages();
+    ui.boxed_title(messages.get(if dry_run {
+        "ui.batch_revert_dry_run"
+    } else {
+        "ui.batch_revert_complete"
+    }));
+    ui.blank();
+    for entry in batch {
+        let history = entry.history.display();
+        match &entry.outcome {
+            Ok(result) if result.dry_run => ui.info(&format!(
+                "{}: would revert {} directories",
+                history,
+                result.operations.len()
+            )),
+            Ok(result) => {
+                ui.success(&format!(
+                    "{}: {} directories restored",
+                    history,
+                    result.operations.len()
+                ));
+                if let Some(path) = &result.revert_history_path {
+                    ui.dim(&format!("  Revert history: {}", path.display()));
+                }
+            }
+            Err(e) => ui.error(&format!("{}: {}", history, e)),
+        }
+    }
+    ui.blank();
+    if dry_run {
+        ui.dim("Run without --dry to apply these reverts.");
+        ui.blank();
+    }
+}
+
+/// Write per-file batch results to stdout as JSON
+fn write_batch_json(ui: &mut Ui, batch: &[BatchEntry]) {
+    let files: Vec<serde_json::Value> = batch
+        .iter()
+        .map(|entry| match &entry.outcome {
+            Ok(result) => serde_json::json!({
+                "history": entry.history,
+                "target_directory": entry.target,
+                "status": if result.dry_run { "planned" } else { "reverted" },
+                "directories": result.operations.len(),
+                "revert_history": result.revert_history_path,
+                "vanished": result.vanished,
+                "post_verify": result.post_verify,
+                "run_id": result.run_id,
+            }),
+            Err(e) => serde_json::json!({
+                "history": entry.history,
+                "target_directory": entry.target,
+                "status": "failed",
+                "error": e.to_string(),
+                "exit_code": e.exit_code().code(),
+            }),
+        })
+        .collect();
+    let report = with_schema(serde_json::json!({
+        "files": files,
+        "failed": batch.iter().filter(|e| e.outcome.is_err()).count(),
+        "build": BuildInfo::current(),
+    }));
+    if let Err(e) = writeln!(ui.data(), "{:#}", report) {
+        debug!("Failed to write --revert-all output: {}", e);
+    }
+}
+
+fn handle_history_list(
+    dir: &std::path::Path,
+    local_time: bool,
+    verbose: bool,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    ui.section("History Files");
+    ui.blank();
+
+    let files = find_history_files(dir)
+        .map_err(|e| AppError::Other(format!("Failed to list history files: {}", e)))?;
+
+    if files.is_empty() {
+        ui.info("No history files found");
+        ui.blank();
+        return Ok(());
+    }
+
+    let mut mismatched = 0;
+
+    for path in &files {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let line = match read_history(path) {
+            Ok(history) => {
+                let operation = match history.operation {
+                    OperationType::Rename => "rename",
+                    OperationType::Revert => "revert",
+                };
+                let checksum = match history.checksum_status() {
+                    ChecksumStatus::Valid => history
+                        .checksum
+                        .as_deref()
+                        .map(|c| c[..12].to_string())
+                        .unwrap_or_default(),
+                    ChecksumStatus::Missing => "no checksum".to_string(),
+                    ChecksumStatus::Mismatch => {
+                        mismatched += 1;
+                        "CHECKSUM MISMATCH".to_string()
+                    }
+                };
+                let mut line = format!(
+                    "{}  {}  {}  {} changes  {}",
+                    name,
+                    format_timestamp(&history.executed_at, local_time),
+                    operation,
+                    history.changes.len(),
+                    checksum
+                );
+                if let Some(reverted_from) = &history.reverted_from {
+                    line.push_str(&format!("  reverts {}", reverted_from));
+                }
+                if verbose && history.operation == OperationType::Rename {
+                    line.push_str("\n    options: ");
+                    line.push_str(&match &history.options {
+                        Some(options) => format_options(options),
+                        None => "not recorded".to_string(),
+                    });
+                }
+                line
+            }
+            Err(e) => format!("{}  unreadable: {}", name, e),
+        };
+
+        if let Err(e) = writeln!(ui.data(), "{}", line) {
+            debug!("Failed to write history list: {}", e);
+        }
+    }
+
+    if mismatched > 0 {
+        ui.blank();
+        ui.warning(&format!(
+            "{} history file(s) have a checksum mismatch",
+            mismatched
+        ));
+    }
+
+    ui.blank();
+    Ok(())
+}
+
+/// Format recorded options as `--flag value` pairs
+fn format_options(options: &EffectiveOptions) -> String {
+    let on_conflict = options
+        .on_conflict
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default();
+
+    let mut text = format!(
+        "--max-length {} --on-conflict {}",
+        options.max_length, on_conflict
+    );
+    if options.drop_series_tag {
+        text.push_str(" --drop-series-tag");
+    }
+    if let Some(restricted) = options.restricted.to_possible_value() {
+        if options.restricted != RestrictedPolicy::Allow {
+            text.push_str(&format!(" --restricted {}", restricted.get_name()));
+        }
+    }
+    if let Some(unaired) = options.unaired.to_possible_value() {
+        if options.unaired != UnairedPolicy::Normal {
+            text.push_str(&format!(" --unaired {}", unaired.get_name()));
+        }
+    }
+    for (from, to) in &options.replacements {
+        text.push_str(&format!(" --replace '{}={}'", from, to));
+    }
+    for (anime_type, suffix) in &options.type_suffixes {
+        text.push_str(&format!(
+            " --type-suffix '{}={}'",
+            anime_type.name(),
+            suffix
+        ));
+    }
+    if options.include_type {
+        text.push_str(" --include-type");
+    }
+    if let Some(template) = &options.template {
+        text.push_str(&format!(" --template '{}'", template));
+    }
+    if options.fs_profile != FsProfile::Posix {
+        text.push_str(&format!(" --fs-profile {}", options.fs_profile.as_str()));
+    }
+    if let Some(overrides) = &options.overrides {
+        text.push_str(&format!(" --overrides '{}'", overrides.display()));
+    }
+    if let Some(tag_map) = &options.tag_map {
+        text.push_str(&format!(" --tag-map '{}'", tag_map.display()));
+    }
+    text
+}
+
+fn handle_cache_info(
+    dir: &std::path::Path,
+    cache_expiry: u32,
+    changes: bool,
+    local_time: bool,
+    json: bool,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    let messages = ui.messages();
+    let info = cache::cache_info(dir, cache_expiry);
+    if json {
+        write_json_report(ui, &info, "--cache-info");
+        return Ok(());
+    }
+
+    ui.section("Cache Information");
+    ui.blank();
+    ui.kv(
+        messages.get("label.cache_file"),
+        &info.path.display().to_string(),
+    );
+
+    if !info.exists {
+        ui.info("No cache file found");
+        ui.blank();
+        return Ok(());
+    }
+
+    ui.kv(
+        messages.get("label.total_entries"),
+        &info.entries.to_string(),
+    );
+    ui.kv(
+        messages.get("label.valid_entries"),
+        &info.valid().to_string(),
+    );
+    ui.kv(
+        messages.get("label.expired_entries"),
+        &info.expired.to_string(),
+    );
+    ui.kv(
+        messages.get("label.expiry_setting"),
+        &messages.format("value.days", &[("days", &cache_expiry)]),
+    );
+    ui.kv(
+        messages.get("label.file_size"),
+        &format_size(info.size_bytes),
+    );
+    ui.kv(
+        messages.get("label.title_changes"),
+        &info.title_change_count().to_string(),
+    );
+    ui.blank();
+
+    if changes && !info.title_changes.is_empty() {
+        for (anidb_id, log) in &info.title_changes {
+            for change in log {
+                let line = format!(
+                    "{}  {}  {} -> {}",
+                    format_timestamp(&change.changed_at, local_time),
+                    anidb_id,
+                    change.old,
+                    change.new
+                );
+                if let Err(e) = writeln!(ui.data(), "{}", line) {
+                    debug!("Failed to write --changes output: {}", e);
+                }
+            }
+        }
+        ui.blank();
+    }
+    Ok(())
+}
+
+/// A byte count in B, KB or MB
+fn format_size(size: u64) -> String {
+    if size < 1024 {
+        format!("{} B", size)
+    } else if size < 1024 * 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Print `report` with its schema version as the JSON output of `command`
+fn write_json_report(ui: &mut Ui, report: &impl serde::Serialize, command: &str) {
+    let report = match serde_json::to_value(report) {
+        Ok(report) => with_schema(report),
+        Err(e) => {
+            debug!("Failed to serialize {} output: {}", command, e);
+            return;
+        }
+    };
+    if let Err(e) = writeln!(ui.data(), "{:#}", report) {
+        debug!("Failed to write {} output: {}", command, e);
+    }
+}
+
+fn handle_check_api(
+    credentials_file: Option<&std::path::Path>,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    let api_config = resolve_api_config(credentials_file)?;
+    if !api_config.is_configured() {
+        return Err(AppError::from(api::ApiError::NotConfigured));
+    }
+
+    ui.step(ui.messages().get("ui.checking_api"));
+    AniDbClient::new(api_config)?.check()?;
+    ui.success("AniDB accepted the client");
+    Ok(())
+}
+
+/// Plan without fetching and print the ids a fetching run would need
+fn handle_print_plan_only(
+    target_dir: &std::path::Path,
+    plan_options: &PlanOptions,
+    json: bool,
+    ui: &mut Ui,
+    progress: &mut Progress,
+) -> Result<(), AppError> {
+    let options = PlanOptions {
+        dry_run: true,
+        ..plan_options.clone()
+    };
+    let plan = pipeline::plan(target_dir, &options, progress)?;
+    let ids = plan.result.uncached_ids();
+    info!("{} uncached id(s)", ids.len());
+
+    let written = if json {
+        writeln!(ui.data(), "{}", serde_json::json!(ids))
+    } else {
+        ids.iter().try_for_each(|id| writeln!(ui.data(), "{}", id))
+    };
+    if let Err(e) = written {
+        debug!("Failed to write uncached ids: {}", e);
+    }
+    Ok(())
+}
+
+/// The settings a run uses, as shown by `--print-config` and in `--report` files
+fn effective_config(args: &Args, ui: &Ui) -> serde_json::Value {
+    serde_json::json!({
+        "target_dir": args.target_dir,
+        "dry_run": args.dry,
+        "unattended": args.unattended,
+        "banner": ui.is_banner_enabled(),
+        "colors": ui.is_colors_enabled(),
+        "prompts": ui.prompts_enabled(),
+        "on_conflict": args.on_conflict,
+        "strict": args.strict,
+        "output": args.output.to_possible_value().map(|v| v.get_name().to_string()),
+        "monitoring_output": args.monitoring_output,
+        "report": args.report,
+        "fail_on_warning": args.unattended,
+        "jobs": args.jobs,
+        "template": args.template.as_ref().map(|t| t.as_str()),
+    })
+}
+
+fn handle_print_config(args: &Args, ui: &mut Ui) {
+    let config = effective_config(args, ui);
+    let written = if args.json_output() {
+        writeln!(ui.data(), "{:#}", with_schema(config))
+    } else {
+        config
+            .as_object()
+            .into_iter()
+            .flatten()
+            .try_for_each(|(key, value)| writeln!(ui.data(), "{} = {}", key, value))
+    };
+    if let Err(e) = written {
+        debug!("Failed to write config: {}", e);
+    }
+}
+
+/// Write the effective settings and the summary of a run to `path` (`--report`)
+fn write_report(
+    path: &std::path::Path,
+    args: &Args,
+    ui: &mut Ui,
+    outcome: &Result<Option<RunResult>, AppError>,
+) {
+    let file = match std::fs::File::create(path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Failed to write report {:?}: {}", path, e);
+            ui.warning(&format!("Failed to write report {}: {}", path.display(), e));
+            return;
+        }
+    };
+    let mut events = EventStream::new(std::io::BufWriter::new(file));
+    events.emit("config", effective_config(args, ui));
+    match outcome {
+        Ok(run) => events.summary(
+            run.as_ref().map(|run| &run.result),
+            run.as_ref().and_then(|run| run.history_path.as_deref()),
+            None,
+        ),
+        Err(e) => events.summary(None, None, Some(e)),
+    }
+    info!("Report written to {:?}", path);
+}
+
+fn handle_build_info(json: bool, ui: &mut Ui) {
+    let info = BuildInfo::current();
+    let written = if json {
+        writeln!(ui.data(), "{:#}", with_schema(serde_json::json!(info)))
+    } else {
+        writeln!(ui.data(), "{}", info)
+    };
+    if let Err(e) = written {
+        debug!("Failed to write build info: {}", e);
+    }
+}
+
+fn handle_list_exit_codes(json: bool, ui: &mut Ui) {
+    let written = if json {
+        let codes: Vec<_> = ExitCode::all()
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "code": c.code(),
+                    "name": c.name(),
+                    "description": c.description(),
+                })
+            })
+            .collect();
+        writeln!(ui.data(), "{:#}", serde_json::Value::Array(codes))
+    } else {
+        ExitCode::all().iter().try_for_each(|c| {
+            writeln!(
+                ui.data(),
+                "{:>3}  {:<20} {}",
+                c.code(),
+                c.name(),
+                c.description()
+            )
+        })
+    };
+    if let Err(e) = written {
+        debug!("Failed to write exit codes: {}", e);
+    }
+}
+
+fn handle_lint(dir: &std::path::Path, json: bool, ui: &mut Ui) -> Result<(), AppError> {
+    let messages = ui.messages();
+    let entries = scanner::scan_directory(dir)?;
+    let report = lint::lint_tags(entries.iter().map(|e| e.name.as_str()));
+
+    if json {
+        let report = with_schema(serde_json::json!({
+            "directory": dir,
+            "tags": report.tags,
+            "suggestions": report.suggestions,
+        }));
+        if let Err(e) = writeln!(ui.data(), "{:#}", report) {
+            debug!("Failed to write --lint output: {}", e);
+        }
+        return Ok(());
+    }
+
+    ui.section("Series Tags");
+    ui.blank();
+    let tagged: usize = report.tags.iter().map(|t| t.directories.len()).sum();
+    ui.kv(messages.get("label.tags"), &report.tags.len().to_string());
+    ui.kv(
+        messages.get("label.tagged_directories"),
+        &tagged.to_string(),
+    );
+    ui.blank();
+
+    if report.suggestions.is_empty() {
+        ui.success("No suspicious series tags");
+        ui.blank();
+        return Ok(());
+    }
+
+    let width = report
+        .suggestions
+        .iter()
+        .map(|s| s.tag.chars().count() + 2)
+        .max()
+        .unwrap_or(0);
+    let directories =
+        |count: usize| format!("{} director{}", count, if count == 1 { "y" } else { "ies" });
+    for suggestion in &report.suggestions {
+        let line = format!(
+            "{:<width$}  {}  did you mean [{}]? ({}, {})",
+            format!("[{}]", suggestion.tag),
+            directories(suggestion.directories.len()),
+            suggestion.suggestion,
+            directories(suggestion.suggestion_count),
+            suggestion.reason.description(),
+            width = width
+        );
+        if let Err(e) = writeln!(ui.data(), "{}", line) {
+            debug!("Failed to write --lint output: {}", e);
+        }
+    }
+    ui.blank();
+    ui.warning(&format!(
+        "{} series tag(s) look like typos; nothing was renamed",
+        report.suggestions.len()
+    ));
     Ok(())
 }
+
+fn handle_check_roundtrip(dir: &std::path::Path, args: &Args, ui: &mut Ui) -> Result<(), AppError> {
+    let messages = ui.messages();
+    let report = roundtrip::check_roundtrip(dir, &plan_options(args)?)?;
+    let mismatches = report.mismatches.len();
+
+    if args.json_output() {
+        let report = with_schema(serde_json::json!({
+            "directory": dir,
+            "checked": report.checked,
+            "uncached": report.uncached,
+            "title_refreshes": report.title_refreshes,
+            "mismatches": report.mismatches,
+        }));
+        if let Err(e) = writeln!(ui.data(), "{:#}", report) {
+            debug!("Failed to write --check-roundtrip output: {}", e);
+        }
+    } else {
+        ui.section("Round Trip");
+        ui.blank();
+        ui.kv(messages.get("label.checked"), &report.checked.to_string());
+        ui.kv(
+            messages.get("label.placeholder_titles"),
+            &report.uncached.to_string(),
+        );
+        ui.kv(
+            messages.get("label.title_refreshes"),
+            &report.title_refreshes.to_string(),
+        );
+        ui.blank();
+        if mismatches == 0 {
+            ui.success("Every directory comes back to its name");
+            ui.blank();
+            return Ok(());
+        }
+        for mismatch in &report.mismatches {
+            let line = format!(
+                "{} -> {} -> {} ({})",
+                mismatch.name,
+                mismatch.via,
+                mismatch.back.as_deref().unwrap_or("?"),
+                mismatch.reason.description()
+            );
+            if let Err(e) = writeln!(ui.data(), "{}", line) {
+                debug!("Failed to write --check-roundtrip output: {}", e);
+            }
+        }
+        ui.blank();
+    }
+
+    if mismatches == 0 {
+        return Ok(());
+    }
+    Err(AppError::Other(format!(
+        "{} director{} would not round-trip",
+        mismatches,
+        if mismatches == 1 { "y" } else { "ies" }
+    )))
+}
+
+/// Show what `name` parses to, with the corpus entry recording it
+fn handle_parse(name: &str, json: bool, ui: &mut Ui) {
+    let messages = ui.messages();
+    let report = ParseReport::new(name);
+    if json {
+        write_json_report(ui, &report, "--parse");
+        return;
+    }
+
+    ui.section("Parse");
+    ui.blank();
+    ui.kv(messages.get("label.name"), &format!("{:?}", report.name));
+    match report.format {
+        Some(format) => ui.kv(
+            messages.get("label.format"),
+            messages.get(match format {
+                DirectoryFormat::AniDb => "value.anidb",
+                DirectoryFormat::HumanReadable => "value.human_readable",
+            }),
+        ),
+        None => ui.kv(
+            messages.get("label.format"),
+            messages.get("value.not_recognized"),
+        ),
+    }
+    let fields = [
+        ("label.anidb_id", report.anidb_id.map(|id| id.to_string())),
+        ("label.marker", report.marker.clone()),
+        ("label.series_tag", report.series_tag.clone()),
+        ("label.title", report.title_jp.clone()),
+        ("label.english_title", report.title_en.clone()),
+        ("label.year", report.release_year.map(|y| y.to_string())),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            ui.kv(messages.get(key), &value);
+        }
+    }
+    if let Some(diagnosis) = &report.diagnosis {
+        ui.warning(&format!("Not recognized: {}", diagnosis));
+    }
+    ui.blank();
+
+    // One line, ready to paste into tests/corpus/names.json
+    ui.dim("Corpus entry:");
+    match serde_json::to_string(&report) {
+        Ok(entry) => {
+            if let Err(e) = writeln!(ui.data(), "{}", entry) {
+                debug!("Failed to write --parse output: {}", e);
+            }
+        }
+        Err(e) => debug!("Failed to serialize --parse output: {}", e),
+    }
+    ui.blank();
+}
+
+fn handle_where(
+    dir: &std::path::Path,
+    cache_expiry: u32,
+    fs_profile: Option<FsProfile>,
+    json: bool,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    let messages = ui.messages();
+    let config = CacheConfig::for_target_dir(dir, cache_expiry);
+    let cache_exists = config.cache_path.exists();
+    let (entries, expired) = if cache_exists {
+        let cache = CacheStore::load(config.clone());
+        (cache.len(), cache.expired_count())
+    } else {
+        (0, 0)
+    };
+    // History files are written next to the directories they rename
+    let history_files = find_history_files(dir).map(|f| f.len()).unwrap_or(0);
+    let (profile, fs_type) = resolve_profile(dir, fs_profile);
+
+    if json {
+        let report = with_schema(serde_json::json!({
+            "cache": {
+                "path": config.cache_path,
+                "exists": cache_exists,
+                "entries": entries,
+                "expired": expired,
+                "expiry_days": cache_expiry,
+            },
+            "history": {
+                "directory": dir,
+                "files": history_files,
+            },
+            "filesystem": {
+                "type": fs_type,
+                "profile": profile,
+                "case_insensitive": profile.case_insensitive(),
+            },
+        }));
+        if let Err(e) = writeln!(ui.data(), "{:#}", report) {
+            debug!("Failed to write --where output: {}", e);
+        }
+        return Ok(());
+    }
+
+    ui.section("Locations");
+    ui.blank();
+    ui.kv(
+        messages.get("label.cache_file"),
+        &config.cache_path.display().to_string(),
+    );
+    if cache_exists {
+        ui.kv(
+            messages.get("label.cache_entries"),
+            &messages.format(
+                "value.cache_entries",
+                &[("entries", &entries), ("expired", &expired)],
+            ),
+        );
+    } else {
+        ui.kv(
+            messages.get("label.cache_entries"),
+            messages.get("value.no_cache_file"),
+        );
+    }
+    ui.kv(
+        messages.get("label.history_directory"),
+        &dir.display().to_string(),
+    );
+    ui.kv(
+        messages.get("label.history_files"),
+        &history_files.to_string(),
+    );
+    ui.kv(
+        messages.get("label.filesystem"),
+        &messages.format(
+            "value.filesystem",
+            &[
+                (
+                    "type",
+                    &fs_type
+                        .as_deref()
+                        .unwrap_or_else(|| messages.get("value.unknown")),
+                ),
+                ("profile", &profile.as_str()),
+                (
+                    "case",
+                    &if profile.case_insensitive() {
+                        messages.get("value.ignores_case")
+                    } else {
+                        ""
+                    },
+                ),
+            ],
+        ),
+    );
+    ui.blank();
+    Ok(())
+}
+
+fn handle_cache_clear(
+    dir: &std::path::Path,
+    cache_expiry: u32,
+    json: bool,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    let cleared = cache::clear_cache(dir, cache_expiry)?;
+    if json {
+        write_json_report(ui, &cleared, "--cache-clear");
+    } else {
+        ui.section("Clear Cache");
+        ui.blank();
+        if !cleared.exists {
+            ui.info("No cache file found");
+        } else {
+            ui.success(&format!("Cleared {} cached entries", cleared.removed));
+        }
+        ui.blank();
+    }
+    nothing_to_do(&cleared, "--cache-clear")
+}
+
+fn handle_cache_prune(
+    dir: &std::path::Path,
+    cache_expiry: u32,
+    json: bool,
+    ui: &mut Ui,
+) -> Result<(), AppError> {
+    let messages = ui.messages();
+    let pruned = cache::prune_cache(dir, cache_expiry)?;
+    if json {
+        write_json_report(ui, &pruned, "--cache-prune");
+    } else {
+        ui.section("Prune Expired Cache Entries");
+        ui.blank();
+        if !pruned.exists {
+            ui.info("No cache file found");
+        } else {
+            ui.kv(
+                messages.get("label.entries_before"),
+                &pruned.before.to_string(),
+            );
+            ui.kv(
+                messages.get("label.expired_removed"),
+                &pruned.removed.to_string(),
+            );
+            ui.kv(
+                messages.get("label.entries_after"),
+                &pruned.after.to_string(),
+            );
+            if pruned.removed > 0 {
+                ui.success(&format!("Pruned {} expired entries", pruned.removed));
+            } else {
+                ui.info("No expired entries to prune");
+            }
+        }
+        ui.blank();
+    }
+    nothing_to_do(&pruned, "--cache-prune")
+}
+
+/// Exit with [`ExitCode::NothingToDo`] when `command` removed no entries
+fn nothing_to_do(cleanup: &cache::CacheCleanup, command: &str) -> Result<(), AppError> {
+    if cleanup.removed > 0 {
+        return Ok(());
+    }
+    Err(AppError::NothingToDo(format!(
+        "{}: nothing to remove",
+        command
+    )))
+}
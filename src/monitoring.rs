@@ -0,0 +1,175 @@
+//! One-line run summary for monitoring systems (`--monitoring-output`).
+//!
+//! The line follows the Nagios plugin convention: status and a short text,
+//! then performance data after a `|`:
+//!
+//! ```text
+//! anidb2folder OK - 42 renamed, 0 failed, 3 skipped, 74 cached | renamed=42 failed=0 skipped=3 api_calls=12 duration=38s
+//! ```
+
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::pipeline::RunResult;
+
+/// Status of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// The run completed, but left directories untouched or needs attention
+    Warning,
+    /// The run failed
+    Critical,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Warning => "WARNING",
+            Status::Critical => "CRITICAL",
+        }
+    }
+}
+
+/// Counts reported for a run
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    renamed: usize,
+    failed: usize,
+    skipped: usize,
+    cached: usize,
+    api_calls: usize,
+}
+
+/// The status of a completed run
+///
+/// Directories left untouched for any reason, mismatched ids and a missing
+/// history file are warnings.
+pub fn run_status(run: &RunResult) -> Status {
+    let result = &run.result;
+    if skipped(run) > 0 || !result.id_mismatches.is_empty() || run.history_error.is_some() {
+        Status::Warning
+    } else {
+        Status::Ok
+    }
+}
+
+/// The monitoring line for a run that completed or stopped with an error
+pub fn status_line(outcome: Result<&RunResult, &AppError>, duration: Duration) -> String {
+    let (status, counts, note) = match outcome {
+        Ok(run) => {
+            let result = &run.result;
+            let counts = Counts {
+                renamed: if result.dry_run {
+                    0
+                } else {
+                    result.applied_count()
+                },
+                failed: 0,
+                skipped: skipped(run),
+                cached: result.cache_hits,
+                api_calls: result.api_requests,
+            };
+            let note = if result.dry_run { " (dry run)" } else { "" };
+            (run_status(run), counts, note.to_string())
+        }
+        Err(e) => {
+            let counts = Counts {
+                failed: 1,
+                ..Default::default()
+            };
+            (
+                Status::Critical,
+                counts,
+                format!(": {}", plain(&e.to_string())),
+            )
+        }
+    };
+
+    format!(
+        "anidb2folder {} - {} renamed, {} failed, {} skipped, {} cached{} | \
+         renamed={} failed={} skipped={} api_calls={} duration={}s",
+        status.as_str(),
+        counts.renamed,
+        counts.failed,
+        counts.skipped,
+        counts.cached,
+        note,
+        counts.renamed,
+        counts.failed,
+        counts.skipped,
+        counts.api_calls,
+        duration.as_secs()
+    )
+}
+
+/// Directories the run left untouched, for whatever reason
+fn skipped(run: &RunResult) -> usize {
+    run.result.untouched_count()
+}
+
+/// `text` on one line and without the perfdata separator
+fn plain(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::{RenameDirection, RenameOperation, RenameResult};
+    use std::path::PathBuf;
+
+    fn run(dry_run: bool) -> RunResult {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, dry_run);
+        for id in [1, 2] {
+            result.add_operation(RenameOperation::new(
+                PathBuf::from("/anime").join(id.to_string()),
+                format!("Title (2020) [anidb-{}]", id),
+                id,
+                false,
+            ));
+        }
+        result.cache_hits = 1;
+        result.api_requests = 1;
+        RunResult {
+            result,
+            history_path: None,
+            history_error: None,
+        }
+    }
+
+    #[test]
+    fn test_status_line_ok() {
+        assert_eq!(
+            status_line(Ok(&run(false)), Duration::from_millis(38_400)),
+            "anidb2folder OK - 2 renamed, 0 failed, 0 skipped, 1 cached | \
+             renamed=2 failed=0 skipped=0 api_calls=1 duration=38s"
+        );
+        assert!(status_line(Ok(&run(true)), Duration::ZERO)
+            .starts_with("anidb2folder OK - 0 renamed, 0 failed, 0 skipped, 1 cached (dry run) |"));
+    }
+
+    #[test]
+    fn test_skipped_directories_are_a_warning() {
+        let mut run = run(false);
+        run.result.vanished.push("3".to_string());
+        assert_eq!(run_status(&run), Status::Warning);
+        assert!(status_line(Ok(&run), Duration::ZERO)
+            .starts_with("anidb2folder WARNING - 2 renamed, 0 failed, 1 skipped,"));
+    }
+
+    #[test]
+    fn test_error_is_critical() {
+        let error = AppError::Other("bad\nthings | happened".to_string());
+        let line = status_line(Err(&error), Duration::from_secs(2));
+        assert!(
+            line.starts_with("anidb2folder CRITICAL - 0 renamed, 1 failed, 0 skipped, 0 cached: ")
+        );
+        assert!(line.contains("bad things / happened |"));
+        assert!(line.ends_with("| renamed=0 failed=1 skipped=0 api_calls=0 duration=2s"));
+    }
+}
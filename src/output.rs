@@ -0,0 +1,380 @@
+//! Machine-friendly result output.
+//!
+//! Everything written here is data, not chatter: it is routed to stdout by
+//! `Ui` so that results can be piped or redirected while progress and
+//! messages stay on stderr.
+
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+use crate::parser::{parse_directory_name, ParsedDirectory};
+use crate::rename::{natural_cmp, RenameOperation, RenameResult};
+use crate::revert::RevertResult;
+use crate::timing::format_age;
+
+/// How a dry run shows each planned rename
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PreviewFormat {
+    /// `source -> destination`
+    #[default]
+    Arrow,
+    /// A word diff of source and destination, highlighting what changes
+    Diff,
+}
+
+/// How a dry run listing is divided into sections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum GroupBy {
+    /// One list in execution order
+    #[default]
+    None,
+    /// A section per series tag, untagged last
+    Tag,
+    /// A section per release year, unknown last
+    Year,
+}
+
+/// A section of a grouped listing
+#[derive(Debug)]
+pub struct Group<'a> {
+    /// Series tag or year; `None` for the entries without one
+    pub key: Option<String>,
+    pub operations: Vec<&'a RenameOperation>,
+}
+
+impl Group<'_> {
+    /// Section heading, e.g. `[AS0] (3)`
+    pub fn heading(&self, group_by: GroupBy) -> String {
+        let label = match (&self.key, group_by) {
+            (Some(tag), GroupBy::Tag) => format!("[{}]", tag),
+            (Some(year), _) => year.clone(),
+            (None, GroupBy::Year) => "Unknown year".to_string(),
+            (None, _) => "Untagged".to_string(),
+        };
+        format!("{} ({})", label, self.operations.len())
+    }
+}
+
+/// Divide the operations of `result` into sections
+///
+/// Sections are in natural order of their key with the keyless section
+/// last, and entries in natural order of their source name. A key comes
+/// from the destination name, or from the source name when the destination
+/// has none (e.g. the year when renaming back to AniDB format). Only the
+/// listing is grouped: operations are still executed in global natural order.
+pub fn group_operations(result: &RenameResult, group_by: GroupBy) -> Vec<Group<'_>> {
+    let mut groups: Vec<Group> = Vec::new();
+    for op in &result.operations {
+        let key = match group_by {
+            GroupBy::None => None,
+            GroupBy::Tag => group_key(op, |parsed| parsed.series_tag().map(str::to_string)),
+            GroupBy::Year => group_key(op, |parsed| match parsed {
+                ParsedDirectory::HumanReadable(f) => f.release_year.map(|y| y.to_string()),
+                ParsedDirectory::AniDb(_) => None,
+            }),
+        };
+        match groups.iter_mut().find(|group| group.key == key) {
+            Some(group) => group.operations.push(op),
+            None => groups.push(Group {
+                key,
+                operations: vec![op],
+            }),
+        }
+    }
+
+    groups.sort_by(|a, b| match (&a.key, &b.key) {
+        (Some(a), Some(b)) => natural_cmp(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    for group in &mut groups {
+        group
+            .operations
+            .sort_by(|a, b| natural_cmp(&a.source_name, &b.source_name));
+    }
+    groups
+}
+
+fn group_key(
+    op: &RenameOperation,
+    key: impl Fn(&ParsedDirectory) -> Option<String>,
+) -> Option<String> {
+    [&op.destination_name, &op.source_name]
+        .into_iter()
+        .filter_map(|name| parse_directory_name(name).ok())
+        .find_map(|parsed| key(&parsed))
+}
+
+/// Write the planned rename operations, one `source -> destination` per line
+pub fn display_dry_run_simple(result: &RenameResult, writer: &mut dyn Write) -> io::Result<()> {
+    display_dry_run_with(result, GroupBy::None, writer, &|from, to| {
+        format!("{} -> {}", from, to)
+    })
+}
+
+/// Write the planned rename operations, one per line as rendered by `render`
+/// from the source and destination names
+///
+/// Grouped, each section starts with its heading and its entries are
+/// indented; sections are separated by a blank line.
+pub fn display_dry_run_with(
+    result: &RenameResult,
+    group_by: GroupBy,
+    writer: &mut dyn Write,
+    render: &dyn Fn(&str, &str) -> String,
+) -> io::Result<()> {
+    let line = |op: &RenameOperation| {
+        format!(
+            "{}{}",
+            render(&op.source_name, &op.destination_name),
+            annotation(op)
+        )
+    };
+    if group_by == GroupBy::None {
+        for op in &result.operations {
+            writeln!(writer, "{}", line(op))?;
+        }
+        return writer.flush();
+    }
+
+    for (i, group) in group_operations(result, group_by).iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "{}", group.heading(group_by))?;
+        for op in &group.operations {
+            writeln!(writer, "  {}", line(op))?;
+        }
+    }
+    writer.flush()
+}
+
+/// Why a planned operation will not be applied as shown, if it will not,
+/// or where its name came from when not from AniDB as is
+fn annotation(op: &RenameOperation) -> String {
+    if op.placeholder {
+        " (placeholder)".to_string()
+    } else if let Some(problem) = &op.invalid_name {
+        format!(" (would fail: {})", problem)
+    } else {
+        let notes: Vec<String> = op
+            .retag
+            .iter()
+            .map(ToString::to_string)
+            .chain(op.override_applied.then(|| "override applied".to_string()))
+            .chain(op.disambiguated.then(|| "disambiguated".to_string()))
+            .chain(
+                op.cache_age
+                    .map(|age| format!("cached {}", format_age(age))),
+            )
+            .collect();
+        if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", notes.join(", "))
+        }
+    }
+}
+
+/// Write the planned revert operations, one `current -> original` per line
+pub fn display_revert_dry_run_simple(
+    result: &RevertResult,
+    writer: &mut dyn Write,
+) -> io::Result<()> {
+    for op in &result.operations {
+        writeln!(writer, "{} -> {}", op.current_name, op.revert_name)?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::{RenameDirection, RenameOperation};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_display_dry_run_simple() {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, true);
+        result.add_operation(RenameOperation::new(
+            PathBuf::from("/anime/12345"),
+            "Anime (2020) [anidb-12345]".to_string(),
+            12345,
+            false,
+        ));
+        result.add_operation(RenameOperation::new(
+            PathBuf::from("/anime/[X] 99"),
+            "[X] Other [anidb-99]".to_string(),
+            99,
+            false,
+        ));
+
+        let mut buffer = Vec::new();
+        display_dry_run_simple(&result, &mut buffer).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "12345 -> Anime (2020) [anidb-12345]\n[X] 99 -> [X] Other [anidb-99]\n"
+        );
+    }
+
+    fn op(source: &str, destination: &str) -> RenameOperation {
+        RenameOperation::new(
+            PathBuf::from("/anime").join(source),
+            destination.to_string(),
+            1,
+            false,
+        )
+    }
+
+    /// Each group as `key: source, source`, `-` for the keyless group
+    fn grouped(result: &RenameResult, group_by: GroupBy) -> Vec<String> {
+        group_operations(result, group_by)
+            .into_iter()
+            .map(|group| {
+                let names: Vec<&str> = group
+                    .operations
+                    .iter()
+                    .map(|op| op.source_name.as_str())
+                    .collect();
+                format!(
+                    "{}: {}",
+                    group.key.as_deref().unwrap_or("-"),
+                    names.join(", ")
+                )
+            })
+            .collect()
+    }
+
+    fn library() -> RenameResult {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, true);
+        for (source, destination) in [
+            ("[AS10] 3", "[AS10] Three (2003) [anidb-3]"),
+            ("12", "Twelve (2012) [anidb-12]"),
+            ("[AS2] 20", "[AS2] Twenty (2020) [anidb-20]"),
+            ("[AS2] 4", "[AS2] Four [anidb-4]"),
+            ("5", "Five (2003) [anidb-5]"),
+        ] {
+            result.add_operation(op(source, destination));
+        }
+        result
+    }
+
+    #[test]
+    fn test_group_by_tag_untagged_last() {
+        assert_eq!(
+            grouped(&library(), GroupBy::Tag),
+            ["AS2: [AS2] 4, [AS2] 20", "AS10: [AS10] 3", "-: 5, 12"]
+        );
+    }
+
+    #[test]
+    fn test_group_by_year_unknown_last() {
+        assert_eq!(
+            grouped(&library(), GroupBy::Year),
+            [
+                "2003: 5, [AS10] 3",
+                "2012: 12",
+                "2020: [AS2] 20",
+                "-: [AS2] 4"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_key_falls_back_to_source_name() {
+        let mut result = RenameResult::new(RenameDirection::ReadableToAniDb, true);
+        result.add_operation(op("[AS0] Show (1999) [anidb-7]", "[AS0] 7"));
+        result.add_operation(op("Other (1999) [anidb-8]", "8"));
+
+        assert_eq!(
+            grouped(&result, GroupBy::Year),
+            ["1999: Other (1999) [anidb-8], [AS0] Show (1999) [anidb-7]"]
+        );
+    }
+
+    #[test]
+    fn test_display_dry_run_grouped() {
+        let mut buffer = Vec::new();
+        display_dry_run_with(&library(), GroupBy::Tag, &mut buffer, &|from, to| {
+            format!("{} -> {}", from, to)
+        })
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "[AS2] (2)\n  [AS2] 4 -> [AS2] Four [anidb-4]\n  [AS2] 20 -> [AS2] Twenty (2020) [anidb-20]\n\n\
+             [AS10] (1)\n  [AS10] 3 -> [AS10] Three (2003) [anidb-3]\n\n\
+             Untagged (2)\n  5 -> Five (2003) [anidb-5]\n  12 -> Twelve (2012) [anidb-12]\n"
+        );
+    }
+
+    #[test]
+    fn test_display_dry_run_marks_placeholders() {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, true);
+        result.add_operation(RenameOperation::placeholder(
+            PathBuf::from("/anime/12345"),
+            "⟨uncached 12345⟩".to_string(),
+            12345,
+        ));
+
+        let mut buffer = Vec::new();
+        display_dry_run_simple(&result, &mut buffer).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "12345 -> ⟨uncached 12345⟩ (placeholder)\n"
+        );
+    }
+
+    #[test]
+    fn test_display_dry_run_shows_quarantine() {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, true);
+        result.add_operation(RenameOperation::quarantine(
+            PathBuf::from("/anime/12345"),
+            12345,
+        ));
+
+        let mut buffer = Vec::new();
+        display_dry_run_simple(&result, &mut buffer).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "12345 -> _unresolved/12345\n"
+        );
+    }
+
+    #[test]
+    fn test_display_dry_run_with_diff() {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, true);
+        result.add_operation(RenameOperation::placeholder(
+            PathBuf::from("/anime/12345"),
+            "⟨uncached 12345⟩".to_string(),
+            12345,
+        ));
+
+        let mut buffer = Vec::new();
+        display_dry_run_with(&result, GroupBy::None, &mut buffer, &|from, to| {
+            crate::diff::render_plain(&crate::diff::word_diff(from, to))
+        })
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "{+⟨uncached +}12345{+⟩+} (placeholder)\n"
+        );
+    }
+
+    #[test]
+    fn test_display_dry_run_simple_empty() {
+        let result = RenameResult::new(RenameDirection::ReadableToAniDb, true);
+
+        let mut buffer = Vec::new();
+        display_dry_run_simple(&result, &mut buffer).unwrap();
+
+        assert!(buffer.is_empty());
+    }
+}
@@ -1,36 +1,261 @@
+mod report;
+mod template;
 mod types;
 
+pub use report::ParseReport;
+#[allow(unused_imports)]
+pub use template::{NameTemplate, TemplateError, TemplateFields, TEMPLATE_ID_TAG};
 pub use types::*;
 
+use std::borrow::Cow;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
+use tracing::warn;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+// Season and part markers kept after the id of an AniDB name and before
+// the id tag of a human-readable one, e.g. "S2", "Season 2", "Part 2" or
+// "Cour 2 Part 1". Only markers are accepted, in any case: free text after
+// a number would make names like "1984 Movie" look like AniDB names.
+const SUFFIX_PATTERN: &str = r"(?i:(?:S|Season\s?|Part\s?|Cour\s?)[0-9]{1,3}(?:\s+(?:S|Season\s?|Part\s?|Cour\s?)[0-9]{1,3})*)";
+
+static SUFFIX_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(&format!(r"\s+{}$", SUFFIX_PATTERN)).unwrap());
+
+// AniDB format: [<series>] <anidb_id> [<suffix>]
+// Examples: "12345", "[AS0] 12345", "[My Series] 67890", "[AS0] 12345 Part 2"
+static ANIDB_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"^(?:\[([^\]]+)\]\s*)?(\d+)(?:\s+({}))?$",
+        SUFFIX_PATTERN
+    ))
+    .unwrap()
+});
+
+/// Tag placed before the AniDB id of anime that have not aired yet
+pub const UNAIRED_TAG: &str = "Unaired";
+
+// Optional episode count after the id tag, e.g. "[26 eps]" or "[1 ep]".
+// ASCII digits only, and at most four, so the count always fits a u16.
+const EPISODE_COUNT_PATTERN: &str = r"\s*(?i:\[([0-9]{1,4}) eps?\])";
 
-// AniDB format: [<series>] <anidb_id>
-// Examples: "12345", "[AS0] 12345", "[My Series] 67890"
-static ANIDB_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:\[([^\]]+)\]\s*)?(\d+)$").unwrap());
+static EPISODE_COUNT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(&format!("{}$", EPISODE_COUNT_PATTERN)).unwrap());
 
-// Human-readable format: [<series>] <title_jp> ／ <title_en> (<year>) [anidb-<id>]
-// The unicode slash ／ (U+FF0F) separates JP and EN titles
+// AniDB type label added after the year by --include-type, e.g. "(OVA)"
+const TYPE_LABEL_PATTERN: &str =
+    r"(?i:\((?:TV Series|Movie|OVA|TV Special|Web|Music Video|Other)\))";
+
+// Human-readable format: [<marker>] [<series>] <title_jp> ／ <title_en> (<year>[-<end_year>]) [(<type>)] [<suffix>] [Unaired] [anidb-<id>] [<n> eps]
+// The unicode slash ／ (U+FF0F) separates JP and EN titles. The year may
+// be a range such as (2004-2005), with a hyphen or an en dash; a range ending
+// before it starts stays in the title. With two leading
+// tags the first is a marker (e.g. [18+]); a single tag is the series tag.
+// The [Unaired] tag is optional and not part of the titles. Both tags match
+// in any case, as some filesystems and shares change the case of names.
+// The episode count, kept by some libraries, is the only thing allowed
+// after the id tag. The id tag may also be spelled (anidb-<id>),
+// [anidb <id>] or [aid=<id>], as other tools write it; generated names
+// always use [anidb-<id>]. Only the tag ending the name is the id: an
+// id-looking token before it is part of the title. A season or part marker
+// right before the tags (after the year, if any) is the suffix; without one
+// there, a marker ending the titles before the year is taken instead. A type
+// label between the year and the tags belongs to neither the titles nor the
+// suffix.
 static HUMAN_READABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"^(?:\[([^\]]+)\]\s*)?(.*?)\s*(?:\((\d{4})\))?\s*\[anidb-(\d+)\]$").unwrap()
+    Regex::new(&format!(
+        r"^(?:\[([^\]]+)\]\s*)?(?:\[([^\]]+)\]\s*)?(.*?)\s*(?:\((\d{{4}})(?:\s*[-–]\s*(\d{{4}}))?\))?(?:\s*{})?(?:\s+({}))?\s*(?i:\[{}\]\s*)?(?:\[(?i:anidb)[- ](\d+)\]|\((?i:anidb)[- ](\d+)\)|\[(?i:aid)=(\d+)\])(?:{})?$",
+        TYPE_LABEL_PATTERN, SUFFIX_PATTERN, UNAIRED_TAG, EPISODE_COUNT_PATTERN
+    ))
+    .unwrap()
 });
 
-// Regex to split JP/EN titles on unicode slash
-static TITLE_SPLIT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s*／\s*").unwrap());
+/// AniDB ids above this are refused by strict parsing; real ids are far lower
+pub const STRICT_MAX_ANIDB_ID: u32 = 1_000_000;
+
+// Any spelling of an id tag, anywhere in a name; strict parsing refuses a
+// name holding two different ids
+static ID_TOKEN_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)[\[\(]\s*(?:anidb[- ]|aid=)(\d+)\s*[\]\)]").unwrap());
+
+// Regex to split JP/EN titles on the spaced unicode slash, or a spaced ASCII
+// slash as typed by hand; an unspaced slash of either kind belongs to a title
+// (e.g. "Fate／stay night", "Fate/stay night")
+static TITLE_SPLIT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+[／/]\s+").unwrap());
 
 /// Parse a directory name and return structured data
+///
+/// Trailing whitespace and dots, as some Windows tools leave them, are
+/// ignored when matching, and the name is matched in NFC, as macOS lists
+/// names decomposed (NFD). Parsed fields are NFC; `original_name` keeps the
+/// name as it is on disk, so renames still find the directory.
 pub fn parse_directory_name(name: &str) -> Result<ParsedDirectory, ParseError> {
-    // Try human-readable format first (more specific pattern)
-    if let Some(parsed) = try_parse_human_readable(name) {
-        return Ok(ParsedDirectory::HumanReadable(parsed));
+    parse_directory_name_with(name, None)
+}
+
+/// Parse a directory name, trying names laid out by `template` first
+///
+/// Names in the default layout are still recognized, so a library can move
+/// to a new template one rename at a time.
+pub fn parse_directory_name_with(
+    name: &str,
+    template: Option<&NameTemplate>,
+) -> Result<ParsedDirectory, ParseError> {
+    let trimmed = trim_name(name);
+    let normalized = normalize_name(trimmed);
+    let parsed = template
+        .and_then(|t| t.parse_name(&normalized))
+        // Human-readable format before AniDB format (more specific pattern)
+        .or_else(|| try_parse_human_readable(&normalized))
+        .map(ParsedDirectory::HumanReadable)
+        .or_else(|| try_parse_anidb(&normalized).map(ParsedDirectory::AniDb))
+        .ok_or_else(|| ParseError::UnrecognizedFormat(name.to_string()))?;
+
+    if normalized == name {
+        return Ok(parsed);
+    }
+    if trimmed.len() != name.len() {
+        warn!("Ignoring trailing whitespace or dots in {:?}", name);
     }
+    Ok(parsed.with_original_name(name))
+}
 
-    // Try AniDB format
-    if let Some(parsed) = try_parse_anidb(name) {
-        return Ok(ParsedDirectory::AniDb(parsed));
+/// Parse a directory name, refusing suspicious names with `options.strict`
+///
+/// Without `strict` this is [`parse_directory_name`]. With it, names that
+/// match a format but are likely mistakes give [`ParseError::Ambiguous`]:
+/// an AniDB name whose id is above [`STRICT_MAX_ANIDB_ID`] (a stray number
+/// such as a date), or a name holding two different ids, such as
+/// `Title [anidb-1] [anidb-2]` or `[anidb-1] 2`.
+#[allow(dead_code)] // Library API, the binary validates through validate_directories_with_options
+pub fn parse_directory_name_with_options(
+    name: &str,
+    options: &ParseOptions,
+) -> Result<ParsedDirectory, ParseError> {
+    let parsed = parse_directory_name(name)?;
+    if options.strict {
+        check_strict(&parsed)?;
     }
+    Ok(parsed)
+}
 
-    Err(ParseError::UnrecognizedFormat(name.to_string()))
+/// Refuse `parsed` if strict parsing would
+pub(crate) fn check_strict(parsed: &ParsedDirectory) -> Result<(), ParseError> {
+    let name = parsed.original_name();
+    let ambiguous = |reason: String| Err(ParseError::Ambiguous(format!("{}: {}", name, reason)));
+
+    if let ParsedDirectory::AniDb(f) = parsed {
+        if f.anidb_id > STRICT_MAX_ANIDB_ID {
+            return ambiguous(format!(
+                "AniDB ID {} is above {}",
+                f.anidb_id, STRICT_MAX_ANIDB_ID
+            ));
+        }
+    }
+
+    let mut ids: Vec<u32> = ID_TOKEN_REGEX
+        .captures_iter(name)
+        .filter_map(|c| c[1].parse().ok())
+        .chain(std::iter::once(parsed.anidb_id()))
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.len() > 1 {
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        return ambiguous(format!("holds several AniDB IDs ({})", ids.join(", ")));
+    }
+    Ok(())
+}
+
+/// `name` in Unicode normalization form C, borrowed when it already is
+///
+/// Names are compared and built in NFC, whatever form the filesystem
+/// lists them in.
+pub fn normalize_name(name: &str) -> Cow<'_, str> {
+    if is_nfc(name) {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(name.nfc().collect())
+    }
+}
+
+/// `name` without trailing whitespace and dots
+fn trim_name(name: &str) -> &str {
+    name.trim_end_matches(|c: char| c.is_whitespace() || c == '.')
+}
+
+/// The format of a directory name, without parsing it
+///
+/// Agrees with [`parse_directory_name`] on every name but allocates nothing:
+/// a suffix check rejects most other names, and the remaining ones only
+/// need a regex match and the id range check.
+pub fn classify(name: &str) -> Option<DirectoryFormat> {
+    let name = trim_name(name);
+    match name.chars().next_back()? {
+        ']' | ')' if HUMAN_READABLE_REGEX.is_match(name) => {
+            // The id sits between the last separator and the closing
+            // bracket of the id tag, which ends the name once any episode
+            // count is cut
+            let name = without_episode_count(name);
+            let digits = name[..name.len() - 1].rsplit(['-', ' ', '=']).next()?;
+            fits_id(digits).then_some(DirectoryFormat::HumanReadable)
+        }
+        c if c.is_numeric() && ANIDB_REGEX.is_match(name) => {
+            // The id follows the series tag and any whitespace, and ends
+            // the name once any suffix is cut
+            let name = without_suffix(name);
+            let digits = name
+                .rsplit(|c: char| c == ']' || c.is_whitespace())
+                .next()?;
+            fits_id(digits).then_some(DirectoryFormat::AniDb)
+        }
+        _ => None,
+    }
+}
+
+/// [`classify`] every name of a listing
+#[allow(dead_code)] // Library API, not used by the binary
+pub fn classify_many(names: &[&str]) -> Vec<Option<DirectoryFormat>> {
+    names.iter().map(|name| classify(name)).collect()
+}
+
+/// [`classify`], recognizing names laid out by `template` too
+///
+/// Agrees with [`parse_directory_name_with`]. Templated names are fully
+/// matched, so this only saves allocations for the default layout.
+pub fn classify_with(name: &str, template: Option<&NameTemplate>) -> Option<DirectoryFormat> {
+    match template {
+        Some(t) if t.matches(name) => Some(DirectoryFormat::HumanReadable),
+        _ => classify(name),
+    }
+}
+
+/// The episode count tag of a human-readable name, e.g. `[26 eps]`
+#[allow(dead_code)]
+pub fn episode_count_tag(count: u16) -> String {
+    if count == 1 {
+        "[1 ep]".to_string()
+    } else {
+        format!("[{} eps]", count)
+    }
+}
+
+/// `name` without a trailing episode count tag
+fn without_episode_count(name: &str) -> &str {
+    EPISODE_COUNT_REGEX
+        .find(name)
+        .map_or(name, |m| &name[..m.start()])
+}
+
+/// `name` without a trailing season or part marker
+fn without_suffix(name: &str) -> &str {
+    SUFFIX_REGEX.find(name).map_or(name, |m| &name[..m.start()])
+}
+
+/// Whether matched id digits are a valid AniDB id
+fn fits_id(digits: &str) -> bool {
+    digits.parse::<u32>().is_ok()
 }
 
 fn try_parse_anidb(name: &str) -> Option<AniDbFormat> {
@@ -38,10 +263,12 @@ fn try_parse_anidb(name: &str) -> Option<AniDbFormat> {
 
     let series_tag = captures.get(1).map(|m| m.as_str().to_string());
     let anidb_id: u32 = captures.get(2)?.as_str().parse().ok()?;
+    let suffix = captures.get(3).map(|m| m.as_str().to_string());
 
     Some(AniDbFormat {
         series_tag,
         anidb_id,
+        suffix,
         original_name: name.to_string(),
     })
 }
@@ -49,24 +276,55 @@ fn try_parse_anidb(name: &str) -> Option<AniDbFormat> {
 fn try_parse_human_readable(name: &str) -> Option<HumanReadableFormat> {
     let captures = HUMAN_READABLE_REGEX.captures(name)?;
 
-    let series_tag = captures.get(1).map(|m| m.as_str().to_string());
-    let titles_part = captures.get(2)?.as_str().trim();
-    let release_year: Option<u16> = captures.get(3).and_then(|m| m.as_str().parse().ok());
-    let anidb_id: u32 = captures.get(4)?.as_str().parse().ok()?;
+    let first_tag = captures.get(1).map(|m| m.as_str().to_string());
+    let (marker, series_tag) = match captures.get(2) {
+        Some(tag) => (first_tag, Some(tag.as_str().to_string())),
+        None => (None, first_tag),
+    };
+    let titles = captures.get(3)?;
+    let mut titles_part = titles.as_str().trim();
+    let mut release_year: Option<u16> = captures.get(4).and_then(|m| m.as_str().parse().ok());
+    let mut end_year: Option<u16> = captures.get(5).and_then(|m| m.as_str().parse().ok());
+    // A range ending before it starts is no range, like (2004-05) it is
+    // part of the title; the closing parenthesis follows the end year
+    if let (Some(start), Some(end), Some(m)) = (release_year, end_year, captures.get(5)) {
+        if end < start {
+            titles_part = name[titles.start()..=m.end()].trim();
+            release_year = None;
+            end_year = None;
+        }
+    }
+    let mut suffix = captures.get(6).map(|m| m.as_str().to_string());
+    // "Title S2 (2017)", as typed by hand: the marker moves after the year
+    // when the name is rebuilt
+    if suffix.is_none() && release_year.is_some() {
+        if let Some(m) = SUFFIX_REGEX.find(titles_part) {
+            suffix = Some(m.as_str().trim_start().to_string());
+            titles_part = &titles_part[..m.start()];
+        }
+    }
+    let anidb_id: u32 = captures
+        .get(7)
+        .or_else(|| captures.get(8))
+        .or_else(|| captures.get(9))?
+        .as_str()
+        .parse()
+        .ok()?;
+    let episode_count: Option<u16> = captures.get(10).and_then(|m| m.as_str().parse().ok());
 
+    // An empty title is accepted: some tools leave just "[anidb-12345]"
     let (title_jp, title_en) = split_titles(titles_part);
 
-    // Must have at least a Japanese title
-    if title_jp.is_empty() {
-        return None;
-    }
-
     Some(HumanReadableFormat {
+        marker,
         series_tag,
         title_jp,
         title_en,
         release_year,
+        end_year,
+        suffix,
         anidb_id,
+        episode_count,
         original_name: name.to_string(),
     })
 }
@@ -97,6 +355,78 @@ mod tests {
 
     // ============ AniDB Format Tests ============
 
+    /// Names every test here parses or rejects, plus ids out of range
+    const CLASSIFY_CASES: &[&str] = &[
+        "12345",
+        "[AS0] 12345",
+        "[AS0]12345",
+        "[My Series] 67890",
+        "Cowboy Bebop (1998) [anidb-23]",
+        "[18+] [AS0] Title (2001) [anidb-5]",
+        "Title [Unaired] [ANIDB-7]",
+        "Fate／stay night ／ Fate/stay night [anidb-1]",
+        "[anidb-12345]",
+        "86 [anidb-8]",
+        "2001 (2001) [anidb-2001]",
+        "Random Folder",
+        "",
+        "[AS0]",
+        "Title [anidb-]",
+        "Title [anidb-x]",
+        "Title (2020)",
+        "99999999999",
+        "Title [anidb-99999999999]",
+        "[AS0] ２３",
+        "１2",
+        "Title [anidb-１2]",
+        "12345 ",
+        "12345.",
+        "Title [anidb-1]\t",
+        "Title [anidb-1] . ",
+        " . ",
+        "Title (1998) [anidb-1] [26 eps]",
+        "Title [anidb-1] [26 eps] [anidb-2]",
+        "Title [anidb-1] [99999 eps]",
+        "Title [anidb-99999999999] [1 ep]",
+        "Title (1998) (anidb-1)",
+        "[AS0] Title [aid=1]",
+        "Title [anidb 1] [26 eps]",
+        "Title (anidb-1]",
+        "Title [aid-1]",
+        "Title (anidb-99999999999)",
+        "Title (1998)",
+        "[AS0] 12345 Part 2",
+        "12345 S2",
+        "12345 Season 2 Cour 1",
+        "12345 S2 ",
+        "99999999999 S2",
+        "12345 2",
+        "12345 Movie",
+        "12345S2",
+        "Title (2017) S2 [anidb-99]",
+        "Title S2 [Unaired] [anidb-99] [12 eps]",
+        "Poke\u{301}mon (1997) [anidb-123]",
+        "[Se\u{301}rie] 123",
+        "123\u{301}",
+        "Title [anidb-1]\u{301}",
+        "Monster (2004-2005) [anidb-2038]",
+        "Monster (2004-2005) S2 [anidb-2038]",
+        "Hellsing Ultimate (2006) (OVA) [anidb-4710]",
+        "Title (2011) (ova) S2 [anidb-7729]",
+    ];
+
+    #[test]
+    fn test_classify_agrees_with_parser() {
+        for name in CLASSIFY_CASES {
+            let parsed = parse_directory_name(name).ok().map(|p| p.format());
+            assert_eq!(classify(name), parsed, "{:?}", name);
+        }
+        let formats = classify_many(CLASSIFY_CASES);
+        assert_eq!(formats[0], Some(DirectoryFormat::AniDb));
+        assert_eq!(formats[4], Some(DirectoryFormat::HumanReadable));
+        assert_eq!(formats[11], None);
+    }
+
     #[test]
     fn test_parse_anidb_simple() {
         let result = parse_directory_name("12345").unwrap();
@@ -109,6 +439,236 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_ignores_trailing_whitespace_and_dots() {
+        for (name, id) in [
+            ("12345 ", 12345),
+            ("[AS0] 12345.", 12345),
+            ("12345\t", 12345),
+            ("Naruto (2002) [anidb-1] ", 1),
+            ("Naruto (2002) [anidb-1].", 1),
+            ("Naruto (2002) [anidb-1]\t \t", 1),
+        ] {
+            let parsed = parse_directory_name(name).unwrap();
+            assert_eq!(parsed.anidb_id(), id, "{:?}", name);
+            assert_eq!(parsed.original_name(), name);
+        }
+
+        match parse_directory_name("Naruto (2002) [anidb-1]. ").unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.title_jp, "Naruto");
+                assert_eq!(f.release_year, Some(2002));
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+
+        // Leading whitespace is not ignored
+        assert!(parse_directory_name(" 12345").is_err());
+        assert!(parse_directory_name(" . ").is_err());
+    }
+
+    #[test]
+    fn test_parse_decomposed_names() {
+        // As listed by macOS: "é" as "e" and a combining acute accent
+        let name = "[Se\u{301}rie] Poke\u{301}mon (1997) [anidb-123]";
+        match parse_directory_name(name).unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.series_tag.as_deref(), Some("S\u{e9}rie"));
+                assert_eq!(f.title_jp, "Pok\u{e9}mon");
+                assert_eq!(f.anidb_id, 123);
+                assert_eq!(f.original_name, name);
+            }
+            other => panic!("Expected HumanReadable format, got {:?}", other),
+        }
+
+        let parsed = parse_directory_name("[Se\u{301}rie] 123 ").unwrap();
+        assert_eq!(parsed.series_tag(), Some("S\u{e9}rie"));
+        assert_eq!(parsed.original_name(), "[Se\u{301}rie] 123 ");
+
+        // Already NFC names are kept as they are
+        let parsed = parse_directory_name("[S\u{e9}rie] 123").unwrap();
+        assert_eq!(parsed.original_name(), "[S\u{e9}rie] 123");
+        assert!(matches!(normalize_name("S\u{e9}rie"), Cow::Borrowed(_)));
+        assert_eq!(normalize_name("Se\u{301}rie"), "S\u{e9}rie");
+    }
+
+    #[test]
+    fn test_strict_parse() {
+        let strict = ParseOptions { strict: true };
+        for name in [
+            "1000001",
+            "[AS0] 20240131",
+            "Title [anidb-1] [anidb-2]",
+            "Title (anidb-1) [anidb-2]",
+            "Title [aid=3] (2020) [anidb-2]",
+            "[anidb-1] 2",
+        ] {
+            assert!(parse_directory_name(name).is_ok(), "{}", name);
+            assert!(
+                parse_directory_name_with_options(name, &ParseOptions::default()).is_ok(),
+                "{}",
+                name
+            );
+            match parse_directory_name_with_options(name, &strict) {
+                Err(ParseError::Ambiguous(reason)) => {
+                    assert!(reason.starts_with(name), "{}", reason)
+                }
+                other => panic!("{}: expected Ambiguous, got {:?}", name, other),
+            }
+        }
+
+        for name in [
+            "1000000",
+            "[AS0] 12345",
+            "Naruto (2002) [anidb-12345]",
+            "Title [anidb-2] (2020) [anidb-2]",
+            "Big (2020) [anidb-99999999]",
+        ] {
+            assert!(
+                parse_directory_name_with_options(name, &strict).is_ok(),
+                "{}",
+                name
+            );
+        }
+
+        assert!(matches!(
+            parse_directory_name_with_options("Notes", &strict),
+            Err(ParseError::UnrecognizedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_year_range() {
+        for (name, title_en) in [
+            ("Monster (2004-2005) [anidb-2038]", None),
+            ("Monster (2004–2005) [anidb-2038]", None),
+            (
+                "Monster ／ Monster Anime (2004 - 2005) [anidb-2038]",
+                Some("Monster Anime"),
+            ),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => {
+                    assert_eq!(f.title_jp, "Monster", "{}", name);
+                    assert_eq!(f.title_en.as_deref(), title_en, "{}", name);
+                    assert_eq!(f.release_year, Some(2004), "{}", name);
+                    assert_eq!(f.end_year, Some(2005), "{}", name);
+                    assert_eq!(f.anidb_id, 2038, "{}", name);
+                }
+                other => panic!("{}: expected HumanReadable format, got {:?}", name, other),
+            }
+        }
+
+        match parse_directory_name("Monster (2004) [anidb-2038]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.release_year, Some(2004));
+                assert!(f.end_year.is_none());
+            }
+            other => panic!("Expected HumanReadable format, got {:?}", other),
+        }
+
+        // A backwards range is kept as title text
+        match parse_directory_name("A (1999-1998) [anidb-1]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.title_jp, "A (1999-1998)");
+                assert_eq!((f.release_year, f.end_year), (None, None));
+                assert_eq!(f.anidb_id, 1);
+            }
+            other => panic!("Expected HumanReadable format, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_type_label() {
+        for (name, title, year, suffix) in [
+            ("Title (2011) (OVA) [anidb-7729]", "Title", Some(2011), None),
+            (
+                "Title (2011) (TV Series) S2 [anidb-7729]",
+                "Title",
+                Some(2011),
+                Some("S2"),
+            ),
+            ("Title (Movie) [anidb-7729]", "Title", None, None),
+            // Anything else in parentheses stays in the title
+            (
+                "Title (2011) (Special) [anidb-7729]",
+                "Title (2011) (Special)",
+                None,
+                None,
+            ),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => {
+                    assert_eq!(f.title_jp, title, "{}", name);
+                    assert_eq!(f.release_year, year, "{}", name);
+                    assert_eq!(f.suffix.as_deref(), suffix, "{}", name);
+                    assert_eq!(f.anidb_id, 7729, "{}", name);
+                }
+                other => panic!("{}: expected HumanReadable format, got {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_suffix() {
+        for (name, tag, suffix) in [
+            ("[AS0] 12345 Part 2", Some("AS0"), "Part 2"),
+            ("12345 S2", None, "S2"),
+            ("12345 season 2 part 1", None, "season 2 part 1"),
+            ("[AS0] 12345 Cour 2.", Some("AS0"), "Cour 2"),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::AniDb(f) => {
+                    assert_eq!(f.anidb_id, 12345, "{}", name);
+                    assert_eq!(f.series_tag.as_deref(), tag, "{}", name);
+                    assert_eq!(f.suffix.as_deref(), Some(suffix), "{}", name);
+                }
+                other => panic!("{}: expected AniDb format, got {:?}", name, other),
+            }
+        }
+
+        match parse_directory_name("[AS0] Shingeki no Kyojin (2017) S2 [anidb-99]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.title_jp, "Shingeki no Kyojin");
+                assert_eq!(f.release_year, Some(2017));
+                assert_eq!(f.suffix.as_deref(), Some("S2"));
+            }
+            other => panic!("Expected HumanReadable format, got {:?}", other),
+        }
+        // A marker before the year is the suffix when none follows it
+        for (name, title, suffix) in [
+            (
+                "Shingeki no Kyojin S2 (2017) [anidb-99]",
+                "Shingeki no Kyojin",
+                "S2",
+            ),
+            (
+                "Shingeki no Kyojin ／ Attack on Titan Season 2 (2017) [anidb-99]",
+                "Shingeki no Kyojin",
+                "Season 2",
+            ),
+            (
+                "Shingeki no Kyojin Part 1 (2017) S2 [anidb-99]",
+                "Shingeki no Kyojin Part 1",
+                "S2",
+            ),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => {
+                    assert_eq!(f.title_jp, title, "{}", name);
+                    assert_eq!(f.release_year, Some(2017), "{}", name);
+                    assert_eq!(f.suffix.as_deref(), Some(suffix), "{}", name);
+                }
+                other => panic!("{}: expected HumanReadable format, got {:?}", name, other),
+            }
+        }
+
+        // Only markers may follow the id
+        for name in ["1984 Movie", "12345 2", "12345S2", "12345 Part"] {
+            assert!(parse_directory_name(name).is_err(), "{}", name);
+        }
+    }
+
     #[test]
     fn test_parse_anidb_with_series() {
         let result = parse_directory_name("[AS0] 12345").unwrap();
@@ -152,6 +712,180 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_human_readable_with_marker() {
+        let result = parse_directory_name("[18+] [AS0] Title (2001) [anidb-7]").unwrap();
+
+        match result {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.marker, Some("18+".to_string()));
+                assert_eq!(f.series_tag, Some("AS0".to_string()));
+                assert_eq!(f.title_jp, "Title");
+                assert_eq!(f.anidb_id, 7);
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+
+        // A single tag stays the series tag
+        match parse_directory_name("[18+] Title [anidb-7]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.marker, None);
+                assert_eq!(f.series_tag, Some("18+".to_string()));
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+    }
+
+    #[test]
+    fn test_parse_human_readable_unaired_tag() {
+        let result =
+            parse_directory_name("[AS0] Next Season (2027) [Unaired] [anidb-18999]").unwrap();
+
+        match result {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.series_tag, Some("AS0".to_string()));
+                assert_eq!(f.title_jp, "Next Season");
+                assert_eq!(f.release_year, Some(2027));
+                assert_eq!(f.anidb_id, 18999);
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+
+        match parse_directory_name("Next Season [Unaired] [anidb-18999]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.title_jp, "Next Season");
+                assert_eq!(f.release_year, None);
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+    }
+
+    #[test]
+    fn test_parse_human_readable_episode_count() {
+        match parse_directory_name("[AS0] Title (1998) [anidb-1] [26 eps]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.series_tag, Some("AS0".to_string()));
+                assert_eq!(f.title_jp, "Title");
+                assert_eq!(f.release_year, Some(1998));
+                assert_eq!(f.anidb_id, 1);
+                assert_eq!(f.episode_count, Some(26));
+                assert!(f.has_canonical_id_tag());
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+
+        for (name, count) in [
+            ("Title (1998) [anidb-1]", None),
+            ("Movie [anidb-2] [1 ep]", Some(1)),
+            ("Show [Unaired] [anidb-3][12 EPS]", Some(12)),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => assert_eq!(f.episode_count, count, "{}", name),
+                _ => panic!("Expected human-readable format for {}", name),
+            }
+        }
+
+        // Only the episode count may follow the id tag
+        for name in [
+            "Title [anidb-1] [26 episodes]",
+            "Title [anidb-1] [eps]",
+            "Title [anidb-1] [99999 eps]",
+        ] {
+            assert!(parse_directory_name(name).is_err(), "{}", name);
+        }
+
+        // The generated tag parses back
+        for count in [1, 26, 9999] {
+            let name = format!("Title [anidb-1] {}", episode_count_tag(count));
+            match parse_directory_name(&name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => assert_eq!(f.episode_count, Some(count)),
+                _ => panic!("Expected human-readable format for {}", name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_human_readable_alternate_id_tags() {
+        for name in [
+            "Title (1998) (anidb-1)",
+            "Title (1998) [aid=1]",
+            "Title (1998) [anidb 1]",
+            "Title (1998) (ANIDB-1)",
+            "Title (1998) [AID=1] [26 eps]",
+            "[AS0] Title (1998) (anidb-1)",
+            "[AS0] Title (1998) [aid=1]",
+            "[AS0] Title (1998) [anidb 1]",
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => {
+                    assert_eq!(f.anidb_id, 1, "{}", name);
+                    assert_eq!(f.title_jp, "Title", "{}", name);
+                    assert_eq!(f.release_year, Some(1998), "{}", name);
+                    assert_eq!(
+                        f.series_tag.is_some(),
+                        name.starts_with("[AS0]"),
+                        "{}",
+                        name
+                    );
+                    assert!(!f.has_canonical_id_tag(), "{}", name);
+                    assert!(f.has_alternate_id_tag(), "{}", name);
+                }
+                _ => panic!("Expected human-readable format for {}", name),
+            }
+        }
+
+        // The tag ending the name is the id; earlier ones belong to the title
+        for (name, id, title) in [
+            ("Title [anidb-x] (anidb-1)", 1, "Title [anidb-x]"),
+            ("Title (anidb-1) [anidb-2]", 2, "Title (anidb-1)"),
+            ("Title [anidb-1] [aid=2]", 2, "Title [anidb-1]"),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => {
+                    assert_eq!(f.anidb_id, id, "{}", name);
+                    assert_eq!(f.title_jp, title, "{}", name);
+                }
+                _ => panic!("Expected human-readable format for {}", name),
+            }
+        }
+
+        for name in [
+            "Title (anidb-1]",
+            "Title [aid-1]",
+            "Title (aid=1)",
+            "Title [anidb_1]",
+        ] {
+            assert!(parse_directory_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_human_readable_id_tag_any_case() {
+        for (name, canonical) in [
+            ("NARUTO (2002) [anidb-12345]", true),
+            ("NARUTO (2002) [ANIDB-12345]", false),
+            ("NARUTO (2002) [AniDB-12345]", false),
+            ("NEXT SEASON (2027) [UNAIRED] [ANIDB-18999]", false),
+            ("NARUTO (2002) [ANIDB-12345] [220 eps]", false),
+            ("NARUTO (2002) [anidb-12345] [220 eps]", true),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => {
+                    assert!(f.anidb_id == 12345 || f.anidb_id == 18999, "{}", name);
+                    assert!(f.release_year.is_some(), "{}", name);
+                    assert_eq!(f.has_canonical_id_tag(), canonical, "{}", name);
+                }
+                _ => panic!("Expected human-readable format for {}", name),
+            }
+        }
+
+        // A title with a lowercase tag-like bracket does not mask the id tag
+        match parse_directory_name("[anidb-x] Title [ANIDB-1]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => assert!(!f.has_canonical_id_tag()),
+            _ => panic!("Expected human-readable format"),
+        }
+    }
+
     #[test]
     fn test_parse_human_readable_no_series() {
         let result = parse_directory_name("Naruto (2002) [anidb-12345]").unwrap();
@@ -197,6 +931,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_human_readable_keeps_sanitized_slash() {
+        let result =
+            parse_directory_name("Fate／stay night ／ Fate／stay night： UBW (2014) [anidb-10182]")
+                .unwrap();
+
+        match result {
+            ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.title_jp, "Fate／stay night");
+                assert_eq!(f.title_en, Some("Fate／stay night： UBW".to_string()));
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+    }
+
+    #[test]
+    fn test_parse_human_readable_ascii_slash_separator() {
+        let titles = |name: &str| match parse_directory_name(name).unwrap() {
+            ParsedDirectory::HumanReadable(f) => (f.title_jp, f.title_en),
+            _ => panic!("Expected human-readable format"),
+        };
+
+        assert_eq!(
+            titles("A / B (2020) [anidb-1]"),
+            ("A".to_string(), Some("B".to_string()))
+        );
+        assert_eq!(
+            titles("Fate/stay night (2006) [anidb-2]"),
+            ("Fate/stay night".to_string(), None)
+        );
+        assert_eq!(
+            titles("Fate/stay night / Fate/stay night: UBW (2014) [anidb-3]"),
+            (
+                "Fate/stay night".to_string(),
+                Some("Fate/stay night: UBW".to_string())
+            )
+        );
+        assert_eq!(titles("A／B (2020) [anidb-4]"), ("A／B".to_string(), None));
+    }
+
     // ============ Edge Cases ============
 
     #[test]
@@ -205,6 +979,44 @@ mod tests {
         assert!(matches!(result, Err(ParseError::UnrecognizedFormat(_))));
     }
 
+    #[test]
+    fn test_parse_numeric_title() {
+        // The [anidb-ID] suffix wins over the bare-id AniDB format
+        for (name, title) in [
+            ("12345 [anidb-12345]", "12345"),
+            ("86 (2021) [anidb-15587]", "86"),
+            ("[AS0] 009-1 (2006) [anidb-4505]", "009-1"),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => assert_eq!(f.title_jp, title),
+                _ => panic!("Expected human-readable format for {}", name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_untitled_id_tag() {
+        for (name, series_tag) in [
+            ("[anidb-12345]", None),
+            ("[AS0] [anidb-12345]", Some("AS0")),
+        ] {
+            match parse_directory_name(name).unwrap() {
+                ParsedDirectory::HumanReadable(f) => {
+                    assert!(f.is_untitled());
+                    assert_eq!(f.anidb_id, 12345);
+                    assert_eq!(f.series_tag.as_deref(), series_tag);
+                    assert!(f.title_en.is_none());
+                }
+                _ => panic!("Expected human-readable format"),
+            }
+        }
+
+        match parse_directory_name("Naruto [anidb-1]").unwrap() {
+            ParsedDirectory::HumanReadable(f) => assert!(!f.is_untitled()),
+            _ => panic!("Expected human-readable format"),
+        }
+    }
+
     #[test]
     fn test_parse_title_with_parentheses() {
         let result = parse_directory_name("Steins;Gate (Anime) (2011) [anidb-7729]").unwrap();
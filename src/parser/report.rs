@@ -0,0 +1,173 @@
+//! What a single name parses to (`--parse`), in the shape of the entries
+//! of the parser corpus, `tests/corpus/names.json`.
+//!
+//! A name from a bug report can be run through `--parse` and its output
+//! added to the corpus as is, once the expected fields are checked.
+
+use serde::{Deserialize, Serialize};
+
+use super::{parse_directory_name, DirectoryFormat, ParsedDirectory};
+
+/// The parse result of one name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParseReport {
+    pub name: String,
+    /// `None` when the name is in no recognized format
+    pub format: Option<DirectoryFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anidb_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marker: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series_tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_jp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_en: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_year: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_year: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episode_count: Option<u16>,
+    /// Why the name is not recognized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnosis: Option<String>,
+}
+
+impl ParseReport {
+    /// Parse `name`, diagnosing it when it does not parse
+    pub fn new(name: &str) -> Self {
+        let mut report = Self {
+            name: name.to_string(),
+            format: None,
+            anidb_id: None,
+            marker: None,
+            series_tag: None,
+            title_jp: None,
+            title_en: None,
+            release_year: None,
+            end_year: None,
+            suffix: None,
+            episode_count: None,
+            diagnosis: None,
+        };
+        match parse_directory_name(name) {
+            Ok(parsed) => {
+                report.format = Some(parsed.format());
+                report.anidb_id = Some(parsed.anidb_id());
+                report.series_tag = parsed.series_tag().map(str::to_string);
+                report.suffix = parsed.suffix().map(str::to_string);
+                if let ParsedDirectory::HumanReadable(f) = parsed {
+                    report.marker = f.marker;
+                    report.title_jp = Some(f.title_jp);
+                    report.title_en = f.title_en;
+                    report.release_year = f.release_year;
+                    report.end_year = f.end_year;
+                    report.episode_count = f.episode_count;
+                }
+            }
+            Err(_) => report.diagnosis = Some(diagnose(name).to_string()),
+        }
+        report
+    }
+}
+
+/// The likely reason `name` is in no recognized format
+fn diagnose(name: &str) -> &'static str {
+    // Trailing whitespace is ignored by the parser
+    if name.trim_start() != name && parse_directory_name(name.trim_start()).is_ok() {
+        return "leading whitespace";
+    }
+
+    // ASCII lowercasing keeps byte offsets
+    let lower = name.to_ascii_lowercase();
+    if let Some(at) = lower.rfind("[anidb-") {
+        let tag = &name[at..];
+        return match tag.find(']') {
+            None => "the [anidb-<id>] tag is not closed",
+            Some(end) if end + 1 != tag.len() => "the [anidb-<id>] tag must end the name",
+            Some(end) => {
+                let digits = &tag["[anidb-".len()..end];
+                if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                    "the id in [anidb-<id>] is too large"
+                } else {
+                    "the id in [anidb-<id>] is not a number"
+                }
+            }
+        };
+    }
+    if lower.contains("anidb") {
+        return "the id tag is not written [anidb-<id>]";
+    }
+
+    let last = name
+        .rsplit(|c: char| c == ']' || c.is_whitespace())
+        .next()
+        .unwrap_or_default();
+    if !last.is_empty() && last.bytes().all(|b| b.is_ascii_digit()) {
+        return if last.parse::<u32>().is_err() {
+            "the AniDB id is too large"
+        } else if name.contains('[') {
+            "the series tag is not written [<tag>] <id>"
+        } else {
+            "a series tag must be in brackets, e.g. [AS0] 12345"
+        };
+    }
+    "neither [<tag>] <id> nor a name ending in [anidb-<id>]"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Every entry of the corpus still parses as recorded
+    #[test]
+    fn test_corpus() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/names.json");
+        let corpus: Vec<ParseReport> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(!corpus.is_empty());
+
+        let failures: Vec<String> = corpus
+            .iter()
+            .filter_map(|expected| {
+                let actual = ParseReport::new(&expected.name);
+                (actual != *expected).then(|| {
+                    format!(
+                        "{:?}\n  expected {}\n  actual   {}",
+                        expected.name,
+                        serde_json::to_string(expected).unwrap(),
+                        serde_json::to_string(&actual).unwrap()
+                    )
+                })
+            })
+            .collect();
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_report_json() {
+        let report = ParseReport::new("[AS0] 12345");
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "name": "[AS0] 12345",
+                "format": "anidb",
+                "anidb_id": 12345,
+                "series_tag": "AS0",
+            })
+        );
+
+        let report = ParseReport::new("Title (2020) [anidb-x]");
+        assert_eq!(report.format, None);
+        assert_eq!(
+            report.diagnosis.as_deref(),
+            Some("the id in [anidb-<id>] is not a number")
+        );
+    }
+}
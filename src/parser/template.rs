@@ -0,0 +1,529 @@
+//! Custom layouts of human-readable names (`--template`).
+//!
+//! A template such as `{tag} {title_jp} ／ {title_en} ({year}) [anidb-{id}]`
+//! lays out the fields of a name. It is split into words at whitespace: a
+//! word holding an optional field that is empty is left out as a whole,
+//! with the punctuation-only words around it (the `／` before `{title_en}`),
+//! so no empty brackets or dangling separators are left. The same template
+//! compiles to a pattern that parses the names it produced, so they can be
+//! converted back. `[anidb-{id}]` is mandatory: it is what keeps a name
+//! recognizable whatever the rest of the layout.
+
+use std::fmt;
+
+use regex::Regex;
+use thiserror::Error;
+
+use super::{HumanReadableFormat, SUFFIX_PATTERN, UNAIRED_TAG};
+
+/// The id tag every template must contain
+pub const TEMPLATE_ID_TAG: &str = "[anidb-{id}]";
+
+/// Characters not allowed in the literal text of a template, as the
+/// default replacement table would rewrite them in the names
+const FORBIDDEN_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|', '`'];
+
+/// A field of a name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// Marker and series tag, each in brackets: `[18+] [AS0]`
+    Tag,
+    TitleJp,
+    TitleEn,
+    Year,
+    /// The configured suffix text of the entry's type
+    Type,
+    /// Season or part marker kept from the source name, e.g. `S2`
+    Suffix,
+    Id,
+    /// Number of episodes AniDB gives
+    Episodes,
+}
+
+impl Field {
+    const ALL: [Field; 8] = [
+        Field::Tag,
+        Field::TitleJp,
+        Field::TitleEn,
+        Field::Year,
+        Field::Type,
+        Field::Suffix,
+        Field::Id,
+        Field::Episodes,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Field::Tag => "tag",
+            Field::TitleJp => "title_jp",
+            Field::TitleEn => "title_en",
+            Field::Year => "year",
+            Field::Type => "type",
+            Field::Suffix => "suffix",
+            Field::Id => "id",
+            Field::Episodes => "episodes",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    /// Whether a name may lack this field
+    fn is_optional(&self) -> bool {
+        matches!(
+            self,
+            Field::Tag
+                | Field::TitleEn
+                | Field::Year
+                | Field::Type
+                | Field::Suffix
+                | Field::Episodes
+        )
+    }
+
+    /// Pattern of the field in a name
+    fn pattern(&self) -> String {
+        match self {
+            Field::Tag => r"\[(?P<tag1>[^\]]+)\](?:\s*\[(?P<tag2>[^\]]+)\])?".to_string(),
+            Field::TitleJp => r"(?P<title_jp>.*?)".to_string(),
+            Field::TitleEn => r"(?P<title_en>.+?)".to_string(),
+            Field::Year => r"(?P<year>\d{4})".to_string(),
+            Field::Type => r"(?P<type>.+?)".to_string(),
+            Field::Suffix => format!("(?P<suffix>{})", SUFFIX_PATTERN),
+            Field::Id => r"(?P<id>\d+)".to_string(),
+            Field::Episodes => r"(?P<episodes>\d+)".to_string(),
+        }
+    }
+}
+
+/// Errors in a template
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("unknown placeholder {{{0}}} (expected {{tag}}, {{title_jp}}, {{title_en}}, {{year}}, {{type}}, {{suffix}}, {{id}} or {{episodes}})")]
+    UnknownPlaceholder(String),
+
+    #[error("placeholder {{{0}}} appears more than once")]
+    DuplicatePlaceholder(String),
+
+    #[error("unmatched '{0}'")]
+    UnmatchedBrace(char),
+
+    #[error("'{0}' is not allowed in names")]
+    ForbiddenCharacter(char),
+
+    #[error("the template must contain {}", TEMPLATE_ID_TAG)]
+    MissingIdTag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone)]
+struct Word {
+    pieces: Vec<Piece>,
+    /// A punctuation-only word is left out with the word it belongs to
+    belongs_to: Option<usize>,
+}
+
+impl Word {
+    fn fields(&self) -> impl Iterator<Item = Field> + '_ {
+        self.pieces.iter().filter_map(|p| match p {
+            Piece::Field(f) => Some(*f),
+            Piece::Literal(_) => None,
+        })
+    }
+
+    /// Whether the word is left out when one of its fields is empty;
+    /// the id and main title are always written
+    fn is_droppable(&self) -> bool {
+        self.fields().any(|f| f.is_optional())
+            && !self
+                .fields()
+                .any(|f| matches!(f, Field::Id | Field::TitleJp))
+    }
+
+    fn is_literal(&self) -> bool {
+        self.fields().next().is_none()
+    }
+}
+
+/// Values of the fields of one name, already sanitized
+#[derive(Debug, Clone, Default)]
+pub struct TemplateFields<'a> {
+    pub marker: Option<&'a str>,
+    pub series_tag: Option<&'a str>,
+    pub title_jp: &'a str,
+    pub title_en: Option<&'a str>,
+    pub year: Option<u16>,
+    pub type_text: Option<&'a str>,
+    pub suffix: Option<&'a str>,
+    pub anidb_id: u32,
+    pub episodes: Option<u16>,
+    /// Write the [`UNAIRED_TAG`] before the id tag
+    pub unaired: bool,
+}
+
+impl TemplateFields<'_> {
+    fn value(&self, field: Field) -> Option<String> {
+        match field {
+            Field::Tag => {
+                let tags: Vec<String> = self
+                    .marker
+                    .iter()
+                    .chain(self.series_tag.iter())
+                    .map(|t| format!("[{}]", t))
+                    .collect();
+                (!tags.is_empty()).then(|| tags.join(" "))
+            }
+            Field::TitleJp => Some(self.title_jp.to_string()),
+            Field::TitleEn => self.title_en.map(str::to_string),
+            Field::Year => self.year.map(|y| y.to_string()),
+            Field::Type => self.type_text.map(str::to_string),
+            Field::Suffix => self.suffix.map(str::to_string),
+            Field::Id => Some(self.anidb_id.to_string()),
+            Field::Episodes => self.episodes.map(|n| n.to_string()),
+        }
+        .filter(|v| !v.is_empty())
+    }
+}
+
+/// A parsed naming template
+#[derive(Debug, Clone)]
+pub struct NameTemplate {
+    source: String,
+    words: Vec<Word>,
+    regex: Regex,
+}
+
+impl NameTemplate {
+    /// Parse `template`, checking its placeholders
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut words: Vec<Word> = Vec::new();
+        let mut seen: Vec<Field> = Vec::new();
+        for text in template.split_whitespace() {
+            let pieces = parse_word(text)?;
+            for piece in &pieces {
+                match piece {
+                    Piece::Field(field) if seen.contains(field) => {
+                        return Err(TemplateError::DuplicatePlaceholder(
+                            field.name().to_string(),
+                        ))
+                    }
+                    Piece::Field(field) => seen.push(*field),
+                    Piece::Literal(literal) => {
+                        if let Some(c) = literal
+                            .chars()
+                            .find(|c| c.is_control() || FORBIDDEN_CHARS.contains(c))
+                        {
+                            return Err(TemplateError::ForbiddenCharacter(c));
+                        }
+                    }
+                }
+            }
+            words.push(Word {
+                pieces,
+                belongs_to: None,
+            });
+        }
+        if !template.contains(TEMPLATE_ID_TAG) {
+            return Err(TemplateError::MissingIdTag);
+        }
+
+        for i in 0..words.len() {
+            if !words[i].is_literal() {
+                continue;
+            }
+            let next = words.get(i + 1).filter(|w| w.is_droppable()).map(|_| i + 1);
+            let previous = i.checked_sub(1).filter(|&p| words[p].is_droppable());
+            words[i].belongs_to = next.or(previous);
+        }
+
+        let regex = Regex::new(&pattern(&words)).expect("template pattern is valid");
+        Ok(Self {
+            source: template.to_string(),
+            words,
+            regex,
+        })
+    }
+
+    /// The template as given
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Lay out `fields`; words of empty optional fields are left out
+    pub fn render(&self, fields: &TemplateFields) -> String {
+        let dropped: Vec<bool> = self
+            .words
+            .iter()
+            .map(|w| w.is_droppable() && w.fields().any(|f| fields.value(f).is_none()))
+            .collect();
+
+        let mut out: Vec<String> = Vec::new();
+        for (i, word) in self.words.iter().enumerate() {
+            if dropped[i] || word.belongs_to.is_some_and(|owner| dropped[owner]) {
+                continue;
+            }
+            if fields.unaired && word.fields().any(|f| f == Field::Id) {
+                out.push(format!("[{}]", UNAIRED_TAG));
+            }
+            let text: String = word
+                .pieces
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Literal(literal) => literal.clone(),
+                    Piece::Field(field) => fields.value(*field).unwrap_or_default(),
+                })
+                .collect();
+            if !text.is_empty() {
+                out.push(text);
+            }
+        }
+        out.join(" ")
+    }
+
+    /// Parse a name laid out by this template
+    pub fn parse_name(&self, name: &str) -> Option<HumanReadableFormat> {
+        let captures = self.regex.captures(name)?;
+        let text = |group: &str| {
+            captures
+                .name(group)
+                .map(|m| m.as_str().trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+
+        let first_tag = text("tag1");
+        let (marker, series_tag) = match text("tag2") {
+            Some(tag) => (first_tag, Some(tag)),
+            None => (None, first_tag),
+        };
+        Some(HumanReadableFormat {
+            marker,
+            series_tag,
+            title_jp: text("title_jp").unwrap_or_default(),
+            title_en: text("title_en"),
+            release_year: text("year").and_then(|y| y.parse().ok()),
+            end_year: None,
+            suffix: text("suffix"),
+            anidb_id: captures.name("id")?.as_str().parse().ok()?,
+            episode_count: text("episodes").and_then(|n| n.parse().ok()),
+            original_name: name.to_string(),
+        })
+    }
+
+    /// Whether `name` is laid out by this template, without building it
+    pub fn matches(&self, name: &str) -> bool {
+        self.parse_name(name).is_some()
+    }
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Split one word of a template into literal text and placeholders
+fn parse_word(text: &str) -> Result<Vec<Piece>, TemplateError> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        match rest.find(['{', '}']) {
+            Some(at) if rest[at..].starts_with('}') => {
+                return Err(TemplateError::UnmatchedBrace('}'))
+            }
+            Some(at) => {
+                if at > 0 {
+                    pieces.push(Piece::Literal(rest[..at].to_string()));
+                }
+                let end = rest[at..]
+                    .find('}')
+                    .ok_or(TemplateError::UnmatchedBrace('{'))?;
+                let name = &rest[at + 1..at + end];
+                let field = Field::from_name(name)
+                    .ok_or_else(|| TemplateError::UnknownPlaceholder(name.to_string()))?;
+                pieces.push(Piece::Field(field));
+                rest = &rest[at + end + 1..];
+            }
+            None => {
+                pieces.push(Piece::Literal(rest.to_string()));
+                rest = "";
+            }
+        }
+    }
+    Ok(pieces)
+}
+
+/// The pattern matching names laid out by `words`
+///
+/// Words that may be left out, with the punctuation belonging to them,
+/// become optional groups.
+fn pattern(words: &[Word]) -> String {
+    let mut out = String::from("^");
+    let mut i = 0;
+    while i < words.len() {
+        let owner = words[i].belongs_to.unwrap_or(i);
+        let mut run = String::new();
+        while i < words.len() && words[i].belongs_to.unwrap_or(i) == owner {
+            run.push_str(&word_pattern(&words[i]));
+            run.push_str(r"\s*");
+            i += 1;
+        }
+        if words[owner].is_droppable() {
+            out.push_str(&format!("(?:{})?", run));
+        } else {
+            out.push_str(&run);
+        }
+    }
+    out.push('$');
+    out
+}
+
+fn word_pattern(word: &Word) -> String {
+    let droppable = word.is_droppable();
+    let mut out = String::new();
+    if word.fields().any(|f| f == Field::Id) {
+        out.push_str(&format!(r"(?:\[(?i:{})\]\s*)?", UNAIRED_TAG));
+    }
+    for piece in &word.pieces {
+        match piece {
+            Piece::Literal(literal) => out.push_str(&regex::escape(literal)),
+            // Kept words write an empty optional field as nothing
+            Piece::Field(field) if field.is_optional() && !droppable => {
+                out.push_str(&format!("(?:{})?", field.pattern()))
+            }
+            Piece::Field(field) => out.push_str(&field.pattern()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = "{tag} {title_jp} ／ {title_en} ({year}) [anidb-{id}]";
+
+    fn fields<'a>(title_en: Option<&'a str>, year: Option<u16>) -> TemplateFields<'a> {
+        TemplateFields {
+            series_tag: Some("AS0"),
+            title_jp: "Kimi no Na wa.",
+            title_en,
+            year,
+            anidb_id: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_collapses_empty_fields() {
+        let template = NameTemplate::parse(LAYOUT).unwrap();
+        assert_eq!(
+            template.render(&fields(Some("Your Name."), Some(2016))),
+            "[AS0] Kimi no Na wa. ／ Your Name. (2016) [anidb-1]"
+        );
+        assert_eq!(
+            template.render(&fields(None, Some(2016))),
+            "[AS0] Kimi no Na wa. (2016) [anidb-1]"
+        );
+        assert_eq!(
+            template.render(&TemplateFields {
+                series_tag: None,
+                unaired: true,
+                ..fields(None, None)
+            }),
+            "Kimi no Na wa. [Unaired] [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_rendered_names_parse_back() {
+        for layout in [
+            LAYOUT,
+            "{title_jp} [{year}] [anidb-{id}] {tag}",
+            "[anidb-{id}] {title_jp} - {title_en}",
+            "{tag} {title_jp} ({type}) ({year}) [anidb-{id}]",
+            "{title_jp} ({year}) {suffix} [anidb-{id}]",
+            "{title_jp} ({year}) [anidb-{id}] [{episodes} eps]",
+        ] {
+            let template = NameTemplate::parse(layout).unwrap();
+            for (title_en, year) in [
+                (Some("Your Name."), Some(2016)),
+                (None, Some(2016)),
+                (Some("Your Name."), None),
+                (None, None),
+            ] {
+                let name = template.render(&TemplateFields {
+                    marker: Some("18+"),
+                    type_text: Some("Movie"),
+                    suffix: year.map(|_| "Part 2"),
+                    episodes: year.map(|_| 26),
+                    ..fields(title_en, year)
+                });
+                let parsed = template.parse_name(&name).unwrap_or_else(|| {
+                    panic!("{} does not parse with {}", name, layout);
+                });
+                assert_eq!(parsed.anidb_id, 1, "{}", name);
+                assert_eq!(parsed.title_jp, "Kimi no Na wa.", "{}", name);
+                if layout.contains("{tag}") {
+                    assert_eq!(parsed.marker.as_deref(), Some("18+"), "{}", name);
+                    assert_eq!(parsed.series_tag.as_deref(), Some("AS0"), "{}", name);
+                }
+                if layout.contains("{title_en}") {
+                    assert_eq!(parsed.title_en.as_deref(), title_en, "{}", name);
+                }
+                if layout.contains("{year}") {
+                    assert_eq!(parsed.release_year, year, "{}", name);
+                }
+                if layout.contains("{suffix}") {
+                    let suffix = year.map(|_| "Part 2");
+                    assert_eq!(parsed.suffix.as_deref(), suffix, "{}", name);
+                }
+                if layout.contains("{episodes}") {
+                    let episodes = year.map(|_| 26);
+                    assert_eq!(parsed.episode_count, episodes, "{}", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (layout, err) in [
+            (
+                "{title} [anidb-{id}]",
+                TemplateError::UnknownPlaceholder("title".to_string()),
+            ),
+            (
+                "{year} {year} [anidb-{id}]",
+                TemplateError::DuplicatePlaceholder("year".to_string()),
+            ),
+            ("{title_jp [anidb-{id}]", TemplateError::UnmatchedBrace('{')),
+            ("title_jp} [anidb-{id}]", TemplateError::UnmatchedBrace('}')),
+            (
+                "{title_jp}: [anidb-{id}]",
+                TemplateError::ForbiddenCharacter(':'),
+            ),
+            (
+                "{title_jp} {anidb-{id}}",
+                TemplateError::UnknownPlaceholder("anidb-{id".to_string()),
+            ),
+            ("{title_jp} ({year}) {id}", TemplateError::MissingIdTag),
+        ] {
+            assert_eq!(NameTemplate::parse(layout).unwrap_err(), err, "{}", layout);
+        }
+    }
+
+    #[test]
+    fn test_parse_name_rejects_other_layouts() {
+        let template = NameTemplate::parse("{title_jp} [{year}] [anidb-{id}]").unwrap();
+        assert!(template.matches("Title [1998] [anidb-1]"));
+        assert!(template.matches("Title [anidb-1]"));
+        assert!(!template.matches("Title [anidb-1] extra"));
+        assert!(!template.matches("12345"));
+    }
+}
@@ -1,8 +1,11 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DirectoryFormat {
+    #[serde(rename = "anidb")]
     AniDb,
+    #[serde(rename = "human_readable")]
     HumanReadable,
 }
 
@@ -10,19 +13,57 @@ pub enum DirectoryFormat {
 pub struct AniDbFormat {
     pub series_tag: Option<String>,
     pub anidb_id: u32,
+    /// Season or part marker after the id, e.g. `Part 2`
+    pub suffix: Option<String>,
     pub original_name: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HumanReadableFormat {
+    /// Leading marker before the series tag, e.g. `18+` for restricted entries
+    pub marker: Option<String>,
     pub series_tag: Option<String>,
     pub title_jp: String,
     pub title_en: Option<String>,
     pub release_year: Option<u16>,
+    /// Last year of a range such as `(2004-2005)`; the first is `release_year`
+    pub end_year: Option<u16>,
+    /// Season or part marker before the id tag, e.g. `S2`
+    pub suffix: Option<String>,
     pub anidb_id: u32,
+    /// Trailing `[<n> eps]` tag, not part of the id tag or the titles
+    pub episode_count: Option<u16>,
     pub original_name: String,
 }
 
+impl HumanReadableFormat {
+    /// Whether the name has no title, e.g. a bare `[anidb-12345]`
+    pub fn is_untitled(&self) -> bool {
+        self.title_jp.is_empty()
+    }
+
+    /// Whether the name ends in `[anidb-ID]` as written, not e.g. `[ANIDB-ID]`
+    /// or `(anidb-ID)`
+    ///
+    /// A trailing episode count is not looked at.
+    pub fn has_canonical_id_tag(&self) -> bool {
+        self.id_tag().starts_with("[anidb-")
+    }
+
+    /// Whether the id tag is spelled other than `[anidb-ID]` in any case,
+    /// e.g. `(anidb-ID)`, `[anidb ID]` or `[aid=ID]`
+    pub fn has_alternate_id_tag(&self) -> bool {
+        !self.id_tag().to_ascii_lowercase().starts_with("[anidb-")
+    }
+
+    /// The id tag as written, from its opening bracket or parenthesis
+    fn id_tag(&self) -> &str {
+        let name = super::without_episode_count(&self.original_name);
+        let open = if name.ends_with(')') { '(' } else { '[' };
+        name.rfind(open).map_or(name, |start| &name[start..])
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParsedDirectory {
     AniDb(AniDbFormat),
@@ -51,16 +92,46 @@ impl ParsedDirectory {
         }
     }
 
+    /// The season or part marker kept across renames
+    pub fn suffix(&self) -> Option<&str> {
+        match self {
+            ParsedDirectory::AniDb(f) => f.suffix.as_deref(),
+            ParsedDirectory::HumanReadable(f) => f.suffix.as_deref(),
+        }
+    }
+
     pub fn original_name(&self) -> &str {
         match self {
             ParsedDirectory::AniDb(f) => &f.original_name,
             ParsedDirectory::HumanReadable(f) => &f.original_name,
         }
     }
+
+    /// The same parse, for the directory as named `name` on disk
+    pub(crate) fn with_original_name(mut self, name: &str) -> Self {
+        match &mut self {
+            ParsedDirectory::AniDb(f) => f.original_name = name.to_string(),
+            ParsedDirectory::HumanReadable(f) => f.original_name = name.to_string(),
+        }
+        self
+    }
+}
+
+/// How names are parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Refuse names that match a format but look wrong: an AniDB name with an
+    /// id above [`STRICT_MAX_ANIDB_ID`](super::STRICT_MAX_ANIDB_ID), or a name
+    /// holding two different ids
+    pub strict: bool,
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum ParseError {
     #[error("Directory name does not match any known format: {0}")]
     UnrecognizedFormat(String),
+
+    /// A name refused by strict parsing, with the reason
+    #[error("Ambiguous directory name {0}")]
+    Ambiguous(String),
 }
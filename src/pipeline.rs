@@ -0,0 +1,1251 @@
+//! Library-level pipeline: scan → validate → plan, then execute.
+//!
+//! [`plan`] inspects the target directory and builds the rename plan for
+//! whichever direction applies, without touching the filesystem.
+//! [`execute`] resolves conflicts, applies the plan and writes the history file.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use crate::api::{AnimeType, ApiConfig, TitleDump};
+use crate::cache::DEFAULT_CACHE_SAVE_EVERY;
+use crate::error::AppError;
+use crate::fs_profile::{detect_name_max, resolve_profile, validate_destination_name, FsProfile};
+use crate::history::{write_history, HistoryError, IncrementalHistory};
+use crate::hooks::Hooks;
+use crate::parser::{
+    parse_directory_name_with, DirectoryFormat, NameTemplate, ParseOptions, ParsedDirectory,
+};
+use crate::progress::ProgressObserver;
+use crate::rename::{
+    altered_series_tag, cached_id_conflicts, check_open_files, check_permissions, exclude_recent,
+    execute_operations_parallel, natural_cmp, plan_to_anidb, plan_to_readable, rename_moves,
+    replacement_table, resolve_conflicts, verify_ids, verify_moves, ConflictResolution,
+    ConflictResolver, Overrides, PostRunCheck, RenameDirection, RenameOperation, RenameOptions,
+    RenameResult, RestrictedPolicy, TagMap, UnairedPolicy, VerifyOptions, DEFAULT_PLACEHOLDER,
+    DEFAULT_RESTRICTED_MARKER,
+};
+use crate::run_id::new_run_id;
+use crate::scanner::{
+    guard_target, scan_directory_ex, validate_targets, ScanOptions, ScanResult, UNRESOLVED_DIR,
+};
+use crate::timing::{Timings, DEFAULT_SLOW_THRESHOLD_SECS};
+use crate::trash;
+use crate::validator::{
+    validate_directories_with_options, ValidationError, ValidationResult, UNRECOGNIZED_LIST_CAP,
+};
+
+/// Default for [`PlanOptions::max_unrecognized`]
+pub const DEFAULT_MAX_UNRECOGNIZED: usize = 20;
+
+/// Operations kept as examples by a [`ChunkedSummary`]
+pub const SUMMARY_SAMPLE_SIZE: usize = 10;
+
+/// Options for planning a run
+#[derive(Debug, Clone)]
+pub struct PlanOptions {
+    /// Maximum directory name length
+    pub max_length: usize,
+    /// Cache expiration in days
+    pub cache_expiry_days: u32,
+    /// Save the cache after this many fetched entries (0: only at the end)
+    pub cache_save_every: usize,
+    /// Plan without calling the API (uncached names become placeholders)
+    pub dry_run: bool,
+    /// Leave series tags out of readable names
+    pub drop_series_tag: bool,
+    /// Move directories whose id no longer exists on AniDB into `_unresolved/`
+    pub quarantine_unresolved: bool,
+    /// Format of dry-run names for uncached ids, containing `{id}`
+    pub placeholder: String,
+    /// Handling of entries AniDB marks as restricted
+    pub restricted: RestrictedPolicy,
+    /// Marker prepended to restricted entries with [`RestrictedPolicy::Tag`]
+    pub restricted_marker: String,
+    /// Handling of anime that have not aired yet
+    pub unaired: UnairedPolicy,
+    /// Character replacements overriding the default fullwidth table
+    pub replacements: Vec<(char, String)>,
+    /// Text added after the titles of entries of each type, e.g. `(Movie)`
+    pub type_suffixes: Vec<(AnimeType, String)>,
+    /// Add AniDB's type after the year of types without a suffix text
+    pub include_type: bool,
+    /// Check ids of human-readable names against their cached AniDB titles
+    pub verify_ids: bool,
+    /// With `verify_ids`, fetch ids missing from the cache
+    pub verify_fetch: bool,
+    /// Naming rules of the target filesystem (`None`: detect them)
+    pub fs_profile: Option<FsProfile>,
+    /// Send quarantined directories to the system trash instead of `_unresolved/`
+    pub use_trash: bool,
+    /// Skip the guards against root, home and unrelated directories
+    pub allow_dangerous_target: bool,
+    /// Refuse a target with more immediate children than this when none is recognized
+    pub max_unrecognized: usize,
+    /// Keep every unrecognized name in the error instead of the first few
+    pub show_all_unrecognized: bool,
+    /// How the target directory is scanned
+    pub scan: ScanOptions,
+    /// How names are parsed; strict parsing also refuses readable names
+    /// whose cached AniDB title contradicts their id
+    pub parse: ParseOptions,
+    /// Plan only the subdirectories with these names, ignoring the others
+    pub only_names: Option<BTreeSet<String>>,
+    /// API client configuration, used for AniDB → readable
+    pub api_config: ApiConfig,
+    /// Local corrections to AniDB data (`--overrides`)
+    pub overrides: Option<Overrides>,
+    /// AniDB's titles dump, ahead of the cache and the API (`--titles-file`)
+    pub titles: Option<Arc<TitleDump>>,
+    /// Series tags by AniDB ID (`--tag-map`)
+    pub tag_map: Option<TagMap>,
+    /// Refuse series tags that sanitization would change instead of warning
+    pub refuse_altered_tags: bool,
+    /// Renames taking longer are reported as slow (zero: never)
+    pub slow_threshold: Duration,
+    /// Leave out directories with anything modified this recently
+    pub exclude_recent: Option<Duration>,
+    /// Layout of readable names, also recognized when parsing (`--template`)
+    pub template: Option<NameTemplate>,
+}
+
+impl Default for PlanOptions {
+    fn default() -> Self {
+        Self {
+            max_length: 255,
+            cache_expiry_days: 30,
+            cache_save_every: DEFAULT_CACHE_SAVE_EVERY,
+            dry_run: false,
+            drop_series_tag: false,
+            quarantine_unresolved: false,
+            placeholder: DEFAULT_PLACEHOLDER.to_string(),
+            restricted: RestrictedPolicy::Allow,
+            restricted_marker: DEFAULT_RESTRICTED_MARKER.to_string(),
+            unaired: UnairedPolicy::Normal,
+            replacements: Vec::new(),
+            type_suffixes: Vec::new(),
+            include_type: false,
+            verify_ids: false,
+            verify_fetch: false,
+            fs_profile: None,
+            use_trash: false,
+            allow_dangerous_target: false,
+            max_unrecognized: DEFAULT_MAX_UNRECOGNIZED,
+            show_all_unrecognized: false,
+            scan: ScanOptions::default(),
+            parse: ParseOptions::default(),
+            only_names: None,
+            api_config: ApiConfig::default(),
+            overrides: None,
+            titles: None,
+            tag_map: None,
+            refuse_altered_tags: false,
+            slow_threshold: Duration::from_secs(DEFAULT_SLOW_THRESHOLD_SECS),
+            exclude_recent: None,
+            template: None,
+        }
+    }
+}
+
+impl PlanOptions {
+    /// Options for planning AniDB → readable on a filesystem with `fs_profile` rules
+    pub fn rename_options(&self, fs_profile: FsProfile) -> RenameOptions {
+        RenameOptions {
+            max_length: self.max_length,
+            dry_run: self.dry_run,
+            cache_expiry_days: self.cache_expiry_days,
+            cache_save_every: self.cache_save_every,
+            drop_series_tag: self.drop_series_tag,
+            quarantine_unresolved: self.quarantine_unresolved,
+            placeholder: self.placeholder.clone(),
+            restricted: self.restricted,
+            restricted_marker: self.restricted_marker.clone(),
+            unaired: self.unaired,
+            replacements: self.replacements.clone(),
+            type_suffixes: self.type_suffixes.clone(),
+            include_type: self.include_type,
+            fs_profile,
+            overrides: self.overrides.clone(),
+            tag_map: self.tag_map.clone(),
+            slow_threshold: self.slow_threshold,
+            template: self.template.clone(),
+            titles: self.titles.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A planned run: what was found and what would be renamed
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct Plan {
+    pub target_dir: PathBuf,
+    /// Scan statistics (empty for the chunks of a [`ChunkedPlan`], which keeps them)
+    pub scan: ScanResult,
+    /// Format all directories were validated to
+    pub format: DirectoryFormat,
+    /// Planned operations
+    pub result: RenameResult,
+}
+
+/// Options for executing a plan
+#[derive(Debug, Clone)]
+pub struct ExecOptions {
+    /// How to resolve conflicts with existing destinations
+    pub on_conflict: ConflictResolution,
+    /// Write a history file after renaming
+    pub write_history: bool,
+    /// Look for files held open inside source directories before renaming
+    pub check_open_files: bool,
+    /// Check that the user may rename every source directory before renaming
+    pub check_permissions: bool,
+    /// Fail on a source directory that vanished after planning instead of skipping it
+    pub strict: bool,
+    /// Check every destination exists and every source is gone after renaming
+    pub post_run_verify: bool,
+    /// Renames run at once (1: one after another)
+    pub jobs: usize,
+    /// Commands to run after the renames
+    pub hooks: Hooks,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        Self {
+            on_conflict: ConflictResolution::Abort,
+            write_history: true,
+            check_open_files: false,
+            check_permissions: false,
+            strict: false,
+            post_run_verify: false,
+            jobs: 1,
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+/// Outcome of executing a plan
+#[derive(Debug)]
+pub struct RunResult {
+    /// Operations performed (or planned, for a dry run)
+    pub result: RenameResult,
+    /// History file written, if any
+    pub history_path: Option<PathBuf>,
+    /// Why the history file could not be written (the renames still happened)
+    pub history_error: Option<HistoryError>,
+}
+
+/// Scan, validate and plan renames for a target directory
+pub fn plan(
+    target_dir: &Path,
+    options: &PlanOptions,
+    observer: &mut dyn ProgressObserver,
+) -> Result<Plan, AppError> {
+    let mut chunks = plan_chunked(target_dir, options, usize::MAX, observer)?;
+    let directories = chunks.directories.by_ref().collect();
+    let mut plan = chunks.plan_directories(directories, observer)?;
+    plan.scan = chunks.scan;
+    Ok(plan)
+}
+
+/// Scan and validate a target directory, to be planned `chunk_size`
+/// directories at a time
+///
+/// Every check of [`plan`] that needs the whole directory (guards, format
+/// validation, filesystem rules) is done here; see [`ChunkedPlan`].
+pub fn plan_chunked(
+    target_dir: &Path,
+    options: &PlanOptions,
+    chunk_size: usize,
+    observer: &mut dyn ProgressObserver,
+) -> Result<ChunkedPlan, AppError> {
+    let targets = validate_targets(&[target_dir.to_path_buf()])?;
+    if !options.allow_dangerous_target {
+        for target in &targets {
+            guard_target(target)?;
+        }
+    }
+
+    // Every path is built from the canonical root, so retargeting a
+    // symlink given as the target cannot redirect the renames
+    let target_dir = targets[0].as_path();
+
+    let mut scan = scan_directory_ex(target_dir, &options.scan)?;
+    if let Some(only) = &options.only_names {
+        scan.entries.retain(|entry| only.contains(&entry.name));
+    }
+
+    // Many children and not one anime directory: most likely the wrong path
+    if !options.allow_dangerous_target
+        && scan.entries.len() > options.max_unrecognized
+        && scan
+            .entries
+            .iter()
+            .all(|e| parse_directory_name_with(&e.name, options.template.as_ref()).is_err())
+    {
+        return Err(AppError::DangerousTarget {
+            path: target_dir.to_path_buf(),
+            reason: format!(
+                "none of its {} subdirectories is an anime directory",
+                scan.entries.len()
+            ),
+        });
+    }
+
+    info!(
+        "Found {} subdirectories in {:?} ({} hidden skipped, {} non-directories skipped, {} symlinks)",
+        scan.entries.len(),
+        scan.duration,
+        scan.skipped_hidden,
+        scan.skipped_files,
+        scan.symlinks
+    );
+    for entry in &scan.entries {
+        debug!("  {}", entry.name);
+    }
+    observer.scanned(&scan);
+    if !scan.artifact_dirs.is_empty() {
+        observer.warn(&format!(
+            "{} director{} named like a history or cache file, ignored (an extracted backup?): {}",
+            scan.artifact_dirs.len(),
+            if scan.artifact_dirs.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            scan.artifact_dirs.join(", ")
+        ));
+    }
+
+    let unrecognized_cap = (!options.show_all_unrecognized).then_some(UNRECOGNIZED_LIST_CAP);
+    let validation = validate_directories_with_options(
+        &scan.entries,
+        unrecognized_cap,
+        options.template.as_ref(),
+        &options.parse,
+    )?;
+    if options.parse.strict && validation.format == DirectoryFormat::HumanReadable {
+        let reasons = cached_id_conflicts(
+            target_dir,
+            &validation.directories,
+            options.cache_expiry_days,
+            options.overrides.as_ref(),
+        );
+        if !reasons.is_empty() {
+            warn!(count = reasons.len(), "Ids contradicted by cached titles");
+            return Err(ValidationError::AmbiguousDirectories { reasons }.into());
+        }
+    }
+    info!("All directories are in {:?} format", validation.format);
+    observer.validated(&validation);
+    if !validation.noncanonical_id_tags.is_empty() {
+        observer.warn(&format!(
+            "{} director{} with the AniDB tag in the wrong case (is something changing the case of names?): {}",
+            validation.noncanonical_id_tags.len(),
+            if validation.noncanonical_id_tags.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            validation.noncanonical_id_tags.join(", ")
+        ));
+    }
+
+    for name in &validation.non_utf8 {
+        observer.warn(&format!("{}: non-UTF-8 name, skipped", name));
+    }
+
+    if !validation.untitled.is_empty() {
+        observer.warn(&format!(
+            "{} director{} without a title: converting to AniDB format and back rebuilds the name: {}",
+            validation.untitled.len(),
+            if validation.untitled.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            validation.untitled.join(", ")
+        ));
+    }
+
+    if validation.format == DirectoryFormat::AniDb && !options.drop_series_tag {
+        check_series_tags(&validation.directories, options, observer)?;
+    }
+
+    let (fs_profile, fs_type) = resolve_profile(target_dir, options.fs_profile);
+    info!(
+        "Target filesystem: {} ({} profile)",
+        fs_type.as_deref().unwrap_or("unknown"),
+        fs_profile.as_str()
+    );
+    if fs_profile.case_insensitive() {
+        warn!("Target filesystem ignores case in names");
+        observer.warn(&format!(
+            "{} ignores case: names differing only in case are treated as conflicts",
+            fs_type.as_deref().unwrap_or("The target filesystem")
+        ));
+    }
+
+    let mut directories = validation.directories;
+    directories.sort_by(|a, b| natural_cmp(a.original_name(), b.original_name()));
+
+    Ok(ChunkedPlan {
+        target_dir: target_dir.to_path_buf(),
+        scan,
+        format: validation.format,
+        directories: directories.into_iter(),
+        chunk_size: chunk_size.max(1),
+        fs_profile,
+        options: options.clone(),
+        run_id: new_run_id(),
+    })
+}
+
+/// A target directory planned one chunk of directories at a time
+/// (`--chunk-size`)
+///
+/// Only the parsed names are kept; the operations of a chunk exist from
+/// [`next_plan`](Self::next_plan) until it is executed. Chunks follow the
+/// natural order of the names, so executing them in turn renames in the
+/// same order as one plan, and they share the run id of the history.
+/// Conflicts between destinations of different chunks are found when the
+/// later chunk is executed, as an existing directory.
+#[derive(Debug)]
+pub struct ChunkedPlan {
+    pub target_dir: PathBuf,
+    /// Scan statistics
+    pub scan: ScanResult,
+    /// Format all directories were validated to
+    pub format: DirectoryFormat,
+    directories: std::vec::IntoIter<ParsedDirectory>,
+    chunk_size: usize,
+    fs_profile: FsProfile,
+    options: PlanOptions,
+    run_id: String,
+}
+
+impl ChunkedPlan {
+    /// Directories not planned yet
+    pub fn remaining(&self) -> usize {
+        self.directories.len()
+    }
+
+    /// Plan the next chunk, or `None` once every directory is planned
+    pub fn next_plan(
+        &mut self,
+        observer: &mut dyn ProgressObserver,
+    ) -> Option<Result<Plan, AppError>> {
+        let chunk: Vec<ParsedDirectory> = self.directories.by_ref().take(self.chunk_size).collect();
+        if chunk.is_empty() {
+            return None;
+        }
+        Some(self.plan_directories(chunk, observer))
+    }
+
+    /// The remaining chunks, planned as they are iterated
+    #[allow(dead_code)]
+    pub fn plans<'a>(&'a mut self, observer: &'a mut dyn ProgressObserver) -> Plans<'a> {
+        Plans {
+            chunks: self,
+            observer,
+        }
+    }
+
+    fn plan_directories(
+        &self,
+        mut directories: Vec<ParsedDirectory>,
+        observer: &mut dyn ProgressObserver,
+    ) -> Result<Plan, AppError> {
+        let options = &self.options;
+        let target_dir = self.target_dir.as_path();
+        let fs_profile = self.fs_profile;
+        // Before planning, so no API request is made for them
+        let recent_skipped = match options.exclude_recent {
+            Some(window) => exclude_recent(&mut directories, target_dir, window, observer),
+            None => Vec::new(),
+        };
+        let validation = ValidationResult {
+            format: self.format,
+            directories,
+            noncanonical_id_tags: Vec::new(),
+            untitled: Vec::new(),
+            non_utf8: Vec::new(),
+        };
+
+        let mut result = match validation.format {
+            DirectoryFormat::AniDb => {
+                let rename_options = RenameOptions {
+                    run_id: Some(self.run_id.clone()),
+                    ..options.rename_options(fs_profile)
+                };
+
+                plan_to_readable(
+                    target_dir,
+                    &validation,
+                    &options.api_config,
+                    &rename_options,
+                    observer,
+                )?
+            }
+            DirectoryFormat::HumanReadable => {
+                let mut result = plan_to_anidb(
+                    target_dir,
+                    &validation,
+                    options.dry_run,
+                    options.tag_map.as_ref(),
+                    Some(&self.run_id),
+                );
+                result.timings = Timings::new(options.slow_threshold);
+                if options.verify_ids {
+                    let verify_options = VerifyOptions {
+                        cache_expiry_days: options.cache_expiry_days,
+                        cache_save_every: options.cache_save_every,
+                        fetch: options.verify_fetch,
+                        dry_run: options.dry_run,
+                        overrides: options.overrides.clone(),
+                    };
+                    verify_ids(
+                        target_dir,
+                        &validation,
+                        &mut result,
+                        &options.api_config,
+                        &verify_options,
+                        observer,
+                    )?;
+                }
+                result
+            }
+        };
+        result.recent_skipped = recent_skipped;
+        result.options.fs_profile = fs_profile;
+        result.options.overrides = options
+            .overrides
+            .as_ref()
+            .map(|overrides| overrides.source().to_path_buf());
+        result.options.tag_map = options
+            .tag_map
+            .as_ref()
+            .map(|tag_map| tag_map.source().to_path_buf());
+        result.options.template = options
+            .template
+            .as_ref()
+            .map(|template| template.as_str().to_string());
+        check_destination_names(&mut result, target_dir, observer);
+
+        if options.use_trash && result.quarantined_count() > 0 {
+            if trash::is_supported() {
+                for op in result.operations.iter_mut().filter(|op| op.quarantined) {
+                    op.send_to_trash();
+                }
+            } else {
+                warn!("No trash implementation on this platform");
+                observer.warn(&format!(
+                    "The system trash is not supported on this platform: using {}/ instead",
+                    UNRESOLVED_DIR
+                ));
+            }
+        }
+
+        Ok(Plan {
+            target_dir: target_dir.to_path_buf(),
+            scan: ScanResult::default(),
+            format: self.format,
+            result,
+        })
+    }
+}
+
+/// Iterator over the chunks of a [`ChunkedPlan`], see [`ChunkedPlan::plans`]
+#[allow(dead_code)]
+pub struct Plans<'a> {
+    chunks: &'a mut ChunkedPlan,
+    observer: &'a mut dyn ProgressObserver,
+}
+
+impl Iterator for Plans<'_> {
+    type Item = Result<Plan, AppError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next_plan(self.observer)
+    }
+}
+
+/// Warn about (or with `refuse_altered_tags`, refuse) series tags that
+/// readable names cannot hold as they are
+///
+/// The history keeps the original names, so a revert restores them
+/// exactly; converting the readable names back to AniDB format does not.
+fn check_series_tags(
+    directories: &[ParsedDirectory],
+    options: &PlanOptions,
+    observer: &mut dyn ProgressObserver,
+) -> Result<(), AppError> {
+    let replacements = replacement_table(&options.replacements)?;
+    let altered: Vec<String> = directories
+        .iter()
+        .filter_map(|parsed| {
+            let tag = parsed.series_tag()?;
+            let altered = altered_series_tag(tag, &replacements)?;
+            warn!("Series tag {:?} becomes {:?}", tag, altered);
+            Some(format!("[{}] -> [{}]", tag, altered))
+        })
+        .collect();
+    if altered.is_empty() {
+        return Ok(());
+    }
+
+    if options.refuse_altered_tags {
+        return Err(AppError::Other(format!(
+            "{} series tag(s) would change in the new names (--strict): {}",
+            altered.len(),
+            altered.join(", ")
+        )));
+    }
+    observer.warn(&format!(
+        "{} series tag(s) change in the new names; --revert restores them, \
+         converting back to AniDB format will not: {}",
+        altered.len(),
+        altered.join(", ")
+    ));
+    Ok(())
+}
+
+/// Flag destination names the target filesystem would reject
+///
+/// Names are checked against the rules of the resolved profile and the
+/// filesystem's name length limit, so a dry run shows what would fail.
+fn check_destination_names(
+    result: &mut RenameResult,
+    target_dir: &Path,
+    observer: &mut dyn ProgressObserver,
+) {
+    let profile = result.options.fs_profile;
+    let name_max = detect_name_max(target_dir);
+
+    for op in result.operations.iter_mut().filter(|op| !op.placeholder) {
+        // Quarantine destinations are nested; the last component is the new name
+        let Some(name) = op.destination_path.file_name() else {
+            continue;
+        };
+        if let Err(problem) = validate_destination_name(&name.to_string_lossy(), profile, name_max)
+        {
+            warn!("Invalid destination {:?}: {}", op.destination_name, problem);
+            observer.warn(&format!("{} would fail: {}", op.destination_name, problem));
+            op.invalid_name = Some(problem);
+        }
+    }
+}
+
+/// Execute a plan, resolving conflicts with the fixed `options.on_conflict` policy
+#[allow(dead_code)]
+pub fn execute(
+    plan: Plan,
+    options: &ExecOptions,
+    observer: &mut dyn ProgressObserver,
+) -> Result<RunResult, AppError> {
+    let mut policy = options.on_conflict;
+    execute_with(plan, options, &mut policy, observer)
+}
+
+/// Execute a plan, asking `resolver` how to handle each conflict
+///
+/// A dry-run plan is returned unchanged.
+pub fn execute_with(
+    plan: Plan,
+    options: &ExecOptions,
+    resolver: &mut dyn ConflictResolver,
+    observer: &mut dyn ProgressObserver,
+) -> Result<RunResult, AppError> {
+    let mut result = plan.result;
+    result.sort_operations();
+    result.options.on_conflict = options.on_conflict;
+    let mut run = RunResult {
+        result: RenameResult::new(result.direction, result.dry_run),
+        history_path: None,
+        history_error: None,
+    };
+
+    if options.check_open_files {
+        check_open_files(&mut result, options.on_conflict, observer)?;
+    }
+    if options.check_permissions {
+        check_permissions(&mut result, options.on_conflict, observer)?;
+    }
+
+    if !result.dry_run {
+        resolve_conflicts(&mut result, resolver)?;
+        execute_operations_parallel(
+            &mut result,
+            resolver,
+            observer,
+            options.strict,
+            options.jobs,
+        )?;
+        if options.post_run_verify {
+            result.post_verify = Some(verify_moves(&rename_moves(&result)));
+        }
+
+        if options.write_history && !result.is_empty() {
+            match write_history(&result, &plan.target_dir) {
+                Ok(path) => run.history_path = Some(path),
+                Err(e) => run.history_error = Some(e),
+            }
+        }
+        options.hooks.run(&result, &plan.target_dir, observer)?;
+    }
+
+    run.result = result;
+    Ok(run)
+}
+
+/// What a chunked run did, in counts and a few examples
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct ChunkedSummary {
+    pub direction: RenameDirection,
+    pub dry_run: bool,
+    /// Shared by every chunk and the history file
+    pub run_id: String,
+    /// Chunks executed
+    pub chunks: usize,
+    /// Planned operations
+    pub planned: usize,
+    /// Operations applied, or that would be in a dry run
+    pub applied: usize,
+    /// Directories left untouched, for whatever reason
+    pub untouched: usize,
+    pub truncated: usize,
+    /// Unresolved directories moved to `_unresolved/` or the trash
+    pub quarantined: usize,
+    pub placeholders: usize,
+    pub invalid_names: usize,
+    pub api_requests: usize,
+    pub cache_hits: usize,
+    /// The first operations, at most [`SUMMARY_SAMPLE_SIZE`]
+    pub sample: Vec<RenameOperation>,
+    /// Slowest steps over every chunk
+    pub timings: Timings,
+    /// Post-run checks of every chunk, combined
+    pub post_verify: Option<PostRunCheck>,
+    /// History file written, if any
+    pub history_path: Option<PathBuf>,
+    /// Why the history file could not be written after the last chunk
+    pub history_error: Option<HistoryError>,
+}
+
+impl ChunkedSummary {
+    fn add(&mut self, result: &RenameResult) {
+        self.chunks += 1;
+        self.planned += result.len();
+        self.applied += result.applied_count();
+        self.untouched += result.untouched_count();
+        self.truncated += result.truncated_count();
+        self.quarantined += result.quarantined_count() + result.trashed_count();
+        self.placeholders += result.placeholder_count();
+        self.invalid_names += result.invalid_name_count();
+        self.api_requests += result.api_requests;
+        self.cache_hits += result.cache_hits;
+        self.timings.merge(&result.timings);
+        if let Some(check) = &result.post_verify {
+            let total = self.post_verify.get_or_insert_with(PostRunCheck::default);
+            total.checked += check.checked;
+            total
+                .discrepancies
+                .extend(check.discrepancies.iter().cloned());
+        }
+        let room = SUMMARY_SAMPLE_SIZE.saturating_sub(self.sample.len());
+        self.sample
+            .extend(result.operations.iter().take(room).cloned());
+    }
+}
+
+/// Executes the chunks of a [`ChunkedPlan`] one at a time
+///
+/// Each chunk is executed like a plan of its own, then appended to an
+/// [`IncrementalHistory`] and dropped: memory holds one chunk of
+/// operations, the history entries and a [`ChunkedSummary`]. `after_each`
+/// hooks run per chunk, `after_run` once in [`finish`](Self::finish).
+pub struct ChunkedRun {
+    target_dir: PathBuf,
+    options: ExecOptions,
+    history: IncrementalHistory,
+    summary: ChunkedSummary,
+}
+
+impl ChunkedRun {
+    pub fn new(chunks: &ChunkedPlan, options: &ExecOptions) -> Self {
+        let direction = match chunks.format {
+            DirectoryFormat::AniDb => RenameDirection::AniDbToReadable,
+            DirectoryFormat::HumanReadable => RenameDirection::ReadableToAniDb,
+        };
+        Self {
+            target_dir: chunks.target_dir.clone(),
+            options: options.clone(),
+            history: IncrementalHistory::new(&chunks.target_dir),
+            summary: ChunkedSummary {
+                direction,
+                dry_run: chunks.options.dry_run,
+                run_id: chunks.run_id.clone(),
+                chunks: 0,
+                planned: 0,
+                applied: 0,
+                untouched: 0,
+                truncated: 0,
+                quarantined: 0,
+                placeholders: 0,
+                invalid_names: 0,
+                api_requests: 0,
+                cache_hits: 0,
+                sample: Vec::new(),
+                timings: Timings::default(),
+                post_verify: None,
+                history_path: None,
+                history_error: None,
+            },
+        }
+    }
+
+    /// Execute one chunk, returning its operations for display
+    pub fn execute(
+        &mut self,
+        plan: Plan,
+        resolver: &mut dyn ConflictResolver,
+        observer: &mut dyn ProgressObserver,
+    ) -> Result<RenameResult, AppError> {
+        let chunk_options = ExecOptions {
+            write_history: false,
+            hooks: Hooks::default(),
+            ..self.options.clone()
+        };
+        let result = execute_with(plan, &chunk_options, resolver, observer)?.result;
+
+        if !result.dry_run {
+            if self.options.write_history && !result.is_empty() {
+                // A later chunk rewrites the whole file, recovering from a failed write
+                match self.history.append(&result) {
+                    Ok(path) => {
+                        self.summary.history_path = Some(path);
+                        self.summary.history_error = None;
+                    }
+                    Err(e) => self.summary.history_error = Some(e),
+                }
+            }
+            self.options
+                .hooks
+                .run_each(&result, &self.target_dir, observer)?;
+        }
+
+        self.summary.add(&result);
+        Ok(result)
+    }
+
+    /// Run the `after_run` hook and return the summary
+    pub fn finish(self, observer: &mut dyn ProgressObserver) -> Result<ChunkedSummary, AppError> {
+        if !self.summary.dry_run {
+            self.options
+                .hooks
+                .run_after(self.summary.applied, &self.target_dir, observer)?;
+        }
+        Ok(self.summary)
+    }
+}
+
+/// Plan and execute every chunk of `chunks`, resolving conflicts with `resolver`
+#[allow(dead_code)]
+pub fn execute_chunked(
+    mut chunks: ChunkedPlan,
+    options: &ExecOptions,
+    resolver: &mut dyn ConflictResolver,
+    observer: &mut dyn ProgressObserver,
+) -> Result<ChunkedSummary, AppError> {
+    let mut run = ChunkedRun::new(&chunks, options);
+    while let Some(plan) = chunks.next_plan(observer) {
+        run.execute(plan?, resolver, observer)?;
+    }
+    run.finish(observer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{find_history_files, read_history};
+    use crate::progress::Progress;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plan_and_execute_readable_to_anidb() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+        fs::create_dir(dir.path().join("[X] Other (2019) [anidb-2]")).unwrap();
+        let mut progress = Progress::silent();
+
+        let plan = plan(dir.path(), &PlanOptions::default(), &mut progress).unwrap();
+
+        assert_eq!(plan.format, DirectoryFormat::HumanReadable);
+        assert_eq!(plan.scan.entries.len(), 2);
+        assert_eq!(plan.result.operations.len(), 2);
+        assert!(dir.path().join("Title (2020) [anidb-1]").exists());
+
+        let run = execute(plan, &ExecOptions::default(), &mut progress).unwrap();
+
+        assert_eq!(run.result.applied_count(), 2);
+        assert!(dir.path().join("1").exists());
+        assert!(dir.path().join("[X] 2").exists());
+        assert!(run.history_path.unwrap().exists());
+    }
+
+    #[test]
+    fn test_chunked_plan_follows_natural_order() {
+        let dir = tempdir().unwrap();
+        for id in [10, 2, 33, 1, 5] {
+            fs::create_dir(dir.path().join(format!("T (2020) [anidb-{}]", id))).unwrap();
+        }
+        let mut progress = Progress::silent();
+
+        let mut chunks =
+            plan_chunked(dir.path(), &PlanOptions::default(), 2, &mut progress).unwrap();
+        assert_eq!(chunks.remaining(), 5);
+        assert_eq!(chunks.scan.entries.len(), 5);
+        let plans: Vec<Plan> = chunks
+            .plans(&mut progress)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let ids: Vec<Vec<u32>> = plans
+            .iter()
+            .map(|plan| {
+                plan.result
+                    .operations
+                    .iter()
+                    .map(|op| op.anidb_id)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(ids, [vec![1, 2], vec![5, 10], vec![33]]);
+        assert!(plans
+            .iter()
+            .all(|plan| plan.result.run_id == plans[0].result.run_id));
+    }
+
+    #[test]
+    fn test_execute_chunked_writes_one_history() {
+        let dir = tempdir().unwrap();
+        for id in 1..=5 {
+            fs::create_dir(dir.path().join(format!("T{} (2020) [anidb-{}]", id, id))).unwrap();
+        }
+        let mut progress = Progress::silent();
+        let chunks = plan_chunked(dir.path(), &PlanOptions::default(), 2, &mut progress).unwrap();
+
+        let summary = execute_chunked(
+            chunks,
+            &ExecOptions::default(),
+            &mut ConflictResolution::Abort,
+            &mut progress,
+        )
+        .unwrap();
+
+        assert_eq!(summary.chunks, 3);
+        assert_eq!((summary.planned, summary.applied), (5, 5));
+        assert_eq!(summary.sample.len(), 5);
+        assert!((1..=5).all(|id| dir.path().join(id.to_string()).exists()));
+        let history = read_history(&summary.history_path.unwrap()).unwrap();
+        let sources: Vec<&str> = history.changes.iter().map(|c| c.source.as_str()).collect();
+        assert_eq!(sources[0], "T1 (2020) [anidb-1]");
+        assert_eq!(sources[4], "T5 (2020) [anidb-5]");
+        assert_eq!(history.run_id, Some(summary.run_id));
+        assert_eq!(find_history_files(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_chunked_prepare_spans_carry_the_history_run_id() {
+        use crate::run_id::testing::SpanRecorder;
+
+        let dir = tempdir().unwrap();
+        for id in 1..=3 {
+            fs::create_dir(dir.path().join(format!("T{} (2020) [anidb-{}]", id, id))).unwrap();
+        }
+        let recorder = SpanRecorder::default();
+
+        let summary = tracing::subscriber::with_default(recorder.clone(), || {
+            let mut progress = Progress::silent();
+            let chunks =
+                plan_chunked(dir.path(), &PlanOptions::default(), 2, &mut progress).unwrap();
+            execute_chunked(
+                chunks,
+                &ExecOptions::default(),
+                &mut ConflictResolution::Abort,
+                &mut progress,
+            )
+            .unwrap()
+        });
+
+        let history = read_history(&summary.history_path.unwrap()).unwrap();
+        let prepared: Vec<String> = recorder
+            .spans()
+            .into_iter()
+            .filter(|(name, fields)| name == "directory" && fields["stage"] == "prepare")
+            .map(|(_, fields)| fields["run_id"].clone())
+            .collect();
+        assert_eq!(prepared.len(), 3);
+        assert!(prepared
+            .iter()
+            .all(|id| Some(id) == history.run_id.as_ref()));
+    }
+
+    #[test]
+    fn test_vanished_source_is_skipped_unless_strict() {
+        let mut progress = Progress::silent();
+        for strict in [false, true] {
+            let dir = tempdir().unwrap();
+            fs::create_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+            fs::create_dir(dir.path().join("Other (2019) [anidb-2]")).unwrap();
+            let planned = plan(dir.path(), &PlanOptions::default(), &mut progress).unwrap();
+
+            // Another process deletes a directory between plan and execute
+            fs::remove_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+            let options = ExecOptions {
+                strict,
+                ..Default::default()
+            };
+            let outcome = execute(planned, &options, &mut progress);
+
+            if strict {
+                assert!(matches!(outcome, Err(AppError::RenameError { .. })));
+                continue;
+            }
+            let run = outcome.unwrap();
+            assert_eq!(run.result.vanished, ["Title (2020) [anidb-1]"]);
+            assert_eq!(run.result.operations.len(), 1);
+            assert!(dir.path().join("2").exists());
+            let history = crate::history::read_history(&run.history_path.unwrap()).unwrap();
+            assert_eq!(history.changes.len(), 1);
+            assert_eq!(history.changes[0].anidb_id, 2);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_retargeted_symlink_does_not_redirect_renames() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir().unwrap();
+        let library = dir.path().join("library");
+        let other = dir.path().join("other");
+        fs::create_dir_all(library.join("Title (2020) [anidb-1]")).unwrap();
+        fs::create_dir_all(other.join("Title (2020) [anidb-1]")).unwrap();
+        let link = dir.path().join("link");
+        symlink(&library, &link).unwrap();
+        let mut progress = Progress::silent();
+
+        let plan = plan(&link, &PlanOptions::default(), &mut progress).unwrap();
+        assert_eq!(plan.target_dir, fs::canonicalize(&library).unwrap());
+
+        // Backup tooling retargets the link between plan and execute
+        fs::remove_file(&link).unwrap();
+        symlink(&other, &link).unwrap();
+
+        execute(plan, &ExecOptions::default(), &mut progress).unwrap();
+        assert!(library.join("1").exists());
+        assert!(other.join("Title (2020) [anidb-1]").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_refuses_swapped_target_root() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir().unwrap();
+        let library = dir.path().join("library");
+        let other = dir.path().join("other");
+        fs::create_dir_all(library.join("Title (2020) [anidb-1]")).unwrap();
+        fs::create_dir_all(other.join("Title (2020) [anidb-1]")).unwrap();
+        let mut progress = Progress::silent();
+
+        let plan = plan(&library, &PlanOptions::default(), &mut progress).unwrap();
+
+        // The planned root itself is replaced by a symlink elsewhere
+        fs::rename(&library, dir.path().join("library.old")).unwrap();
+        symlink(&other, &library).unwrap();
+
+        let err = execute(plan, &ExecOptions::default(), &mut progress).unwrap_err();
+        assert!(err.to_string().contains("changed during the run"));
+        assert!(other.join("Title (2020) [anidb-1]").exists());
+        assert!(!other.join("1").exists());
+    }
+
+    #[test]
+    fn test_api_failure_renames_nothing() {
+        use crate::api::AnimeInfo;
+        use crate::cache::{CacheConfig, CacheStore};
+
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("1")).unwrap();
+        fs::create_dir(dir.path().join("2")).unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&AnimeInfo {
+            anidb_id: 1,
+            title_main: "Cached".to_string(),
+            title_en: None,
+            release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        cache.save().unwrap();
+
+        // Every id is resolved while planning, so an API problem with the
+        // uncached id fails before the cached one is renamed
+        let outcome = plan(dir.path(), &PlanOptions::default(), &mut Progress::silent());
+
+        assert!(matches!(outcome, Err(AppError::ApiError { .. })));
+        assert!(dir.path().join("1").exists());
+        assert!(dir.path().join("2").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_altered_series_tag_is_restored_by_revert() {
+        use crate::api::AnimeInfo;
+        use crate::cache::{CacheConfig, CacheStore};
+        use crate::revert::{revert_from_history, RevertOptions};
+
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("[S1:S2] 1")).unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&AnimeInfo {
+            anidb_id: 1,
+            title_main: "Title".to_string(),
+            title_en: None,
+            release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        cache.save().unwrap();
+        let mut progress = Progress::silent();
+
+        let strict = PlanOptions {
+            refuse_altered_tags: true,
+            ..Default::default()
+        };
+        let err = plan(dir.path(), &strict, &mut progress).unwrap_err();
+        assert!(err.to_string().contains("[S1:S2] -> [S1：S2]"), "{}", err);
+
+        let planned = plan(dir.path(), &PlanOptions::default(), &mut progress).unwrap();
+        let run = execute(planned, &ExecOptions::default(), &mut progress).unwrap();
+        assert!(dir.path().join("[S1：S2] Title [anidb-1]").exists());
+
+        revert_from_history(
+            &run.history_path.unwrap(),
+            &RevertOptions::default(),
+            &mut progress,
+        )
+        .unwrap();
+        assert!(dir.path().join("[S1:S2] 1").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_directory_is_never_renamed() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        #[derive(Default)]
+        struct Warnings(Vec<String>);
+        impl ProgressObserver for Warnings {
+            fn warn(&mut self, message: &str) {
+                self.0.push(message.to_string());
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        let raw = OsStr::from_bytes(b"[\xff] Title (2020) [anidb-2]");
+        if fs::create_dir(dir.path().join(raw)).is_err() {
+            return;
+        }
+        fs::create_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+        let mut warnings = Warnings::default();
+
+        let plan = plan(dir.path(), &PlanOptions::default(), &mut warnings).unwrap();
+        assert_eq!(plan.result.operations.len(), 1);
+        assert_eq!(
+            plan.result.operations[0].source_name,
+            "Title (2020) [anidb-1]"
+        );
+        assert_eq!(
+            warnings.0,
+            ["[\u{FFFD}] Title (2020) [anidb-2]: non-UTF-8 name, skipped"]
+        );
+
+        execute(plan, &ExecOptions::default(), &mut Progress::silent()).unwrap();
+        assert!(dir.path().join("1").exists());
+        assert!(dir.path().join(raw).exists());
+        assert!(!dir.path().join("[\u{FFFD}] 2").exists());
+    }
+
+    #[test]
+    fn test_dry_plan_lists_uncached_ids() {
+        use crate::api::AnimeInfo;
+        use crate::cache::{CacheConfig, CacheStore};
+
+        let dir = tempdir().unwrap();
+        for name in ["1", "[AS0] 3", "2", "[AS1] 3"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&AnimeInfo {
+            anidb_id: 2,
+            title_main: "Cached".to_string(),
+            title_en: None,
+            release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        cache.save().unwrap();
+
+        let options = PlanOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let plan = plan(dir.path(), &options, &mut Progress::silent()).unwrap();
+
+        assert_eq!(plan.result.uncached_ids(), [1, 3]);
+    }
+
+    #[test]
+    fn test_execute_dry_run_changes_nothing() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+        let mut progress = Progress::silent();
+
+        let options = PlanOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let plan = plan(dir.path(), &options, &mut progress).unwrap();
+        let run = execute(plan, &ExecOptions::default(), &mut progress).unwrap();
+
+        assert!(run.result.dry_run);
+        assert!(run.history_path.is_none());
+        assert!(dir.path().join("Title (2020) [anidb-1]").exists());
+    }
+
+    #[test]
+    fn test_plan_rejects_empty_directory() {
+        let dir = tempdir().unwrap();
+        let result = plan(dir.path(), &PlanOptions::default(), &mut Progress::silent());
+        assert!(result.is_err());
+    }
+}
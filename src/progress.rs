@@ -1,22 +1,168 @@
 //! Progress output for user-facing status updates.
 //!
 //! This module provides progress output for internal functions (like rename_to_readable).
-//! In verbose mode, output is suppressed since tracing handles everything.
+//! In log-only mode (`--no-ui`), output is suppressed since tracing handles everything.
 //! In normal mode, output is shown with colors to give feedback during API calls etc.
 
+use chrono::{DateTime, Local, Utc};
+#[cfg(feature = "cli")]
 use colored::Colorize;
+#[cfg(feature = "cli")]
 use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+#[cfg(feature = "cli")]
+use std::time::Instant;
+
+#[cfg(feature = "cli")]
+use crate::i18n::Messages;
+use crate::scanner::ScanResult;
+use crate::timing::StepTiming;
+#[cfg(feature = "cli")]
+use crate::ui::Theme;
+use crate::validator::ValidationResult;
 
 /// Progress reporter for user-facing output
+#[cfg(feature = "cli")]
 pub struct Progress {
     writer: Box<dyn Write>,
-    /// When true, all output is suppressed (verbose mode uses tracing instead)
+    /// When true, all output is suppressed (log-only mode uses tracing instead)
     silent: bool,
     /// When true, output is colorized
     colors_enabled: bool,
+    /// Symbols and colors for styled output
+    theme: Theme,
+    /// When true, timestamps are shown in local time instead of UTC
+    local_time: bool,
+    /// When true, fetch lines include an ETA (only on interactive output)
+    eta_enabled: bool,
+    /// API fetches planned, completed, and timing used for the ETA
+    fetch_plan: FetchPlan,
+    /// Catalog of translated messages
+    messages: Messages,
+}
+
+/// Bookkeeping for estimating the remaining fetch time
+#[cfg(feature = "cli")]
+#[derive(Debug, Default)]
+struct FetchPlan {
+    total: usize,
+    done: usize,
+    min_interval: Duration,
+    elapsed: Duration,
+    started_at: Option<Instant>,
+}
+
+#[cfg(feature = "cli")]
+impl FetchPlan {
+    /// Estimate the time left, including the fetch about to start
+    fn remaining(&self) -> Duration {
+        let left = self.total.saturating_sub(self.done) as u32;
+        // Observed average accounts for retries and slow responses
+        let average = if self.done > 0 {
+            self.elapsed / self.done as u32
+        } else {
+            Duration::ZERO
+        };
+        average.max(self.min_interval) * left
+    }
+}
+
+/// Receives progress events from planning and execution
+///
+/// All methods default to doing nothing, so embedders implement only what they display.
+pub trait ProgressObserver {
+    /// The target directory was scanned
+    fn scanned(&mut self, _scan: &ScanResult) {}
+
+    /// All directories were validated to a single format
+    fn validated(&mut self, _validation: &ValidationResult) {}
+
+    /// Number of API fetches expected and the minimum interval between them
+    fn set_fetch_plan(&mut self, _total: usize, _min_interval: Duration) {}
+
+    /// An API fetch started
+    fn fetch_start(&mut self, _anidb_id: u32) {}
+
+    /// The current API fetch completed
+    fn fetch_complete(&mut self) {}
+
+    /// Cached data was used instead of the API
+    fn using_cache(&mut self, _anidb_id: u32) {}
+
+    /// The API would be called (dry run)
+    fn would_fetch(&mut self, _anidb_id: u32) {}
+
+    /// A non-fatal problem occurred
+    fn warn(&mut self, _message: &str) {}
+
+    /// A filesystem operation took longer than the slow threshold
+    fn slow_step(&mut self, _timing: &StepTiming) {}
+
+    /// A directory is being renamed
+    fn rename_progress(&mut self, _current: usize, _total: usize, _from: &str, _to: &str) {}
+
+    /// A history file was written to `path`
+    fn history_written(&mut self, _path: &Path) {}
+
+    /// A revert of `total` entries recorded at `executed_at` is starting
+    fn revert_start(&mut self, _total: usize, _executed_at: &DateTime<Utc>) {}
+
+    /// A single revert is about to be applied
+    fn revert_progress(&mut self, _current: usize, _total: usize, _from: &str, _to: &str) {}
+
+    /// A revert finished with `count` entries (or would have, in a dry run)
+    fn revert_complete(&mut self, _count: usize, _dry_run: bool) {}
+}
+
+/// Stand-in for [`Progress`] in tests of builds without the `cli` feature,
+/// so tests that only need an observer run there too
+#[cfg(all(test, not(feature = "cli")))]
+pub struct Progress;
+
+#[cfg(all(test, not(feature = "cli")))]
+impl Progress {
+    pub fn silent() -> Self {
+        Progress
+    }
+
+    pub fn with_writer(_writer: Box<dyn std::io::Write>) -> Self {
+        Progress
+    }
+}
+
+#[cfg(all(test, not(feature = "cli")))]
+impl ProgressObserver for Progress {}
+
+/// Format a duration compactly, e.g. `45s`, `2m05s`, `1h02m`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Format a timestamp for display, e.g. `2026-01-15 10:30:45 UTC`
+///
+/// With `local` the timestamp is converted to the local offset, e.g.
+/// `2026-01-15 11:30:45 +01:00`. Only used for display, never for filenames.
+pub fn format_timestamp(timestamp: &DateTime<Utc>, local: bool) -> String {
+    if local {
+        timestamp
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string()
+    } else {
+        timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    }
 }
 
 /// Check if we should use colors in output
+#[cfg(feature = "cli")]
 fn should_use_colors() -> bool {
     if std::env::var("NO_COLOR").is_ok() {
         return false;
@@ -27,12 +173,14 @@ fn should_use_colors() -> bool {
     io::stderr().is_terminal()
 }
 
+#[cfg(feature = "cli")]
 impl Default for Progress {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "cli")]
 impl Progress {
     /// Create a new progress reporter writing to stderr
     pub fn new() -> Self {
@@ -41,19 +189,47 @@ impl Progress {
             writer: Box::new(io::stderr()),
             silent: false,
             colors_enabled,
+            theme: Theme::default(),
+            local_time: false,
+            eta_enabled: io::stderr().is_terminal(),
+            fetch_plan: FetchPlan::default(),
+            messages: Messages::default(),
         }
     }
 
     /// Create a progress reporter that respects UI mode
-    /// When verbose=true, output is suppressed (tracing handles it)
-    pub fn new_with_ui(verbose: bool, colors_enabled: bool) -> Self {
+    /// When log_only=true, output is suppressed (tracing handles it)
+    pub fn new_with_ui(log_only: bool, colors_enabled: bool) -> Self {
         Self {
             writer: Box::new(io::stderr()),
-            silent: verbose,
+            silent: log_only,
             colors_enabled,
+            theme: Theme::default(),
+            local_time: false,
+            eta_enabled: io::stderr().is_terminal(),
+            fetch_plan: FetchPlan::default(),
+            messages: Messages::default(),
         }
     }
 
+    /// Use the given theme for styled output
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Show messages from the given catalog
+    pub fn with_messages(mut self, messages: Messages) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    /// Show timestamps in local time instead of UTC
+    pub fn with_local_time(mut self, local_time: bool) -> Self {
+        self.local_time = local_time;
+        self
+    }
+
     /// Create a progress reporter with a custom writer (for testing)
     #[cfg(test)]
     pub fn with_writer(writer: Box<dyn Write>) -> Self {
@@ -61,16 +237,26 @@ impl Progress {
             writer,
             silent: false,
             colors_enabled: false,
+            theme: Theme::default(),
+            local_time: false,
+            eta_enabled: false,
+            fetch_plan: FetchPlan::default(),
+            messages: Messages::default(),
         }
     }
 
-    /// Create a silent progress reporter (for testing or verbose mode)
+    /// Create a silent progress reporter (for testing or log-only mode)
     #[allow(dead_code)]
     pub fn silent() -> Self {
         Self {
             writer: Box::new(io::sink()),
             silent: true,
             colors_enabled: false,
+            theme: Theme::default(),
+            local_time: false,
+            eta_enabled: false,
+            fetch_plan: FetchPlan::default(),
+            messages: Messages::default(),
         }
     }
 
@@ -84,9 +270,9 @@ impl Progress {
             let _ = writeln!(
                 self.writer,
                 "{} {} {} {}",
-                counter.cyan(),
+                self.theme.accent(&counter),
                 from.dimmed(),
-                "→".cyan(),
+                self.theme.accent("→"),
                 to
             );
         } else {
@@ -94,32 +280,61 @@ impl Progress {
         }
     }
 
+    /// Set the number of API fetches expected and the minimum interval between them
+    pub fn set_fetch_plan(&mut self, total: usize, min_interval: Duration) {
+        self.fetch_plan = FetchPlan {
+            total,
+            min_interval,
+            ..Default::default()
+        };
+    }
+
     /// Report fetching metadata from API
     pub fn fetch_start(&mut self, anidb_id: u32) {
+        self.fetch_plan.started_at = Some(Instant::now());
+
         if self.silent {
             return;
         }
+
+        let mut message = self
+            .messages
+            .format("progress.fetching", &[("id", &anidb_id)]);
+        if self.eta_enabled && self.fetch_plan.total > 0 {
+            message.push_str(&self.messages.format(
+                "progress.fetch_eta",
+                &[
+                    ("current", &(self.fetch_plan.done + 1)),
+                    ("total", &self.fetch_plan.total),
+                    ("remaining", &format_duration(self.fetch_plan.remaining())),
+                ],
+            ));
+        }
+        message.push_str("...");
+
         if self.colors_enabled {
-            let _ = write!(
-                self.writer,
-                "{}",
-                format!("Fetching metadata for {}...", anidb_id).dimmed()
-            );
+            let _ = write!(self.writer, "{}", message.dimmed());
         } else {
-            let _ = write!(self.writer, "Fetching metadata for {}...", anidb_id);
+            let _ = write!(self.writer, "{}", message);
         }
         let _ = self.writer.flush();
     }
 
     /// Report fetch complete (same line)
     pub fn fetch_complete(&mut self) {
+        if let Some(started_at) = self.fetch_plan.started_at.take() {
+            self.fetch_plan.elapsed += started_at.elapsed();
+            self.fetch_plan.done += 1;
+        }
+
         if self.silent {
             return;
         }
+        let done = self.messages.get("progress.done");
         if self.colors_enabled {
-            let _ = writeln!(self.writer, " {}", "done".green());
+            let _ = writeln!(self.writer, " {}", self.theme.success(done));
         } else {
-            let _ = writeln!(self.writer, " done");
+            let _ = writeln!(self.writer, " {}", done);
         }
     }
 
@@ -140,50 +355,62 @@ impl Progress {
             return;
         }
         if self.colors_enabled {
-            let _ = writeln!(self.writer, "{} {}", "!".yellow().bold(), message.yellow());
+            let _ = writeln!(
+                self.writer,
+                "{} {}",
+                self.theme.warning(self.theme.warning_symbol).bold(),
+                self.theme.warning(message)
+            );
         } else {
-            let _ = writeln!(self.writer, "Warning: {}", message);
+            let _ = writeln!(
+                self.writer,
+                "{}",
+                self.messages
+                    .format("progress.warning", &[("message", &message)])
+            );
         }
     }
 
+    /// Report a filesystem operation slower than the threshold, naming the directory
+    pub fn slow_step(&mut self, timing: &StepTiming) {
+        self.warn(&format!(
+            "Slow {}: {} took {}",
+            timing.step.as_str(),
+            timing.name,
+            format_duration(timing.elapsed)
+        ));
+    }
+
     /// Report history file written
-    pub fn history_written(&mut self, path: &std::path::Path) {
+    pub fn history_written(&mut self, path: &Path) {
         if self.silent {
             return;
         }
+        let message = self
+            .messages
+            .format("progress.history_saved", &[("path", &path.display())]);
         if self.colors_enabled {
-            let _ = writeln!(
-                self.writer,
-                "{}",
-                format!("History saved to: {}", path.display()).dimmed()
-            );
+            let _ = writeln!(self.writer, "{}", message.dimmed());
         } else {
-            let _ = writeln!(self.writer, "History saved to: {}", path.display());
+            let _ = writeln!(self.writer, "{}", message);
         }
     }
 
     /// Report starting a revert operation
-    pub fn revert_start(&mut self, total: usize, from_timestamp: &str) {
+    pub fn revert_start(&mut self, total: usize, executed_at: &DateTime<Utc>) {
         if self.silent {
             return;
         }
+        let from_timestamp = format_timestamp(executed_at, self.local_time);
+        let message = self.messages.format(
+            "progress.reverting",
+            &[("count", &total), ("timestamp", &from_timestamp)],
+        );
         let _ = writeln!(self.writer);
         if self.colors_enabled {
-            let _ = writeln!(
-                self.writer,
-                "{}",
-                format!(
-                    "Reverting {} directories from history ({})",
-                    total, from_timestamp
-                )
-                .bold()
-            );
+            let _ = writeln!(self.writer, "{}", message.bold());
         } else {
-            let _ = writeln!(
-                self.writer,
-                "Reverting {} directories from history ({})",
-                total, from_timestamp
-            );
+            let _ = writeln!(self.writer, "{}", message);
         }
     }
 
@@ -197,9 +424,9 @@ impl Progress {
             let _ = writeln!(
                 self.writer,
                 "{} {} {} {}",
-                counter.cyan(),
+                self.theme.accent(&counter),
                 from.dimmed(),
-                "→".cyan(),
+                self.theme.accent("→"),
                 to
             );
         } else {
@@ -212,39 +439,91 @@ impl Progress {
         if self.silent {
             return;
         }
+        let count: &dyn std::fmt::Display = &count;
         let _ = writeln!(self.writer);
         if dry_run {
+            let message = self
+                .messages
+                .format("progress.revert_dry_run", &[("count", count)]);
             if self.colors_enabled {
-                let _ = writeln!(
-                    self.writer,
-                    "{}",
-                    format!("Dry run complete. {} directories would be reverted.", count).dimmed()
-                );
+                let _ = writeln!(self.writer, "{}", message.dimmed());
             } else {
-                let _ = writeln!(
-                    self.writer,
-                    "Dry run complete. {} directories would be reverted.",
-                    count
-                );
+                let _ = writeln!(self.writer, "{}", message);
             }
         } else if self.colors_enabled {
             let _ = writeln!(
                 self.writer,
                 "{} {}",
-                "✓".green().bold(),
-                format!("{} directories restored", count).green()
+                self.theme.success(self.theme.success_symbol).bold(),
+                self.theme.success(
+                    &self
+                        .messages
+                        .format("progress.restored", &[("count", count)])
+                )
             );
         } else {
             let _ = writeln!(
                 self.writer,
-                "Revert complete. {} directories restored.",
-                count
+                "{}",
+                self.messages
+                    .format("progress.revert_complete", &[("count", count)])
             );
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(feature = "cli")]
+impl ProgressObserver for Progress {
+    fn set_fetch_plan(&mut self, total: usize, min_interval: Duration) {
+        Progress::set_fetch_plan(self, total, min_interval);
+    }
+
+    fn fetch_start(&mut self, anidb_id: u32) {
+        Progress::fetch_start(self, anidb_id);
+    }
+
+    fn fetch_complete(&mut self) {
+        Progress::fetch_complete(self);
+    }
+
+    fn using_cache(&mut self, anidb_id: u32) {
+        Progress::using_cache(self, anidb_id);
+    }
+
+    fn would_fetch(&mut self, anidb_id: u32) {
+        Progress::would_fetch(self, anidb_id);
+    }
+
+    fn warn(&mut self, message: &str) {
+        Progress::warn(self, message);
+    }
+
+    fn slow_step(&mut self, timing: &StepTiming) {
+        Progress::slow_step(self, timing);
+    }
+
+    fn rename_progress(&mut self, current: usize, total: usize, from: &str, to: &str) {
+        Progress::rename_progress(self, current, total, from, to);
+    }
+
+    fn history_written(&mut self, path: &Path) {
+        Progress::history_written(self, path);
+    }
+
+    fn revert_start(&mut self, total: usize, executed_at: &DateTime<Utc>) {
+        Progress::revert_start(self, total, executed_at);
+    }
+
+    fn revert_progress(&mut self, current: usize, total: usize, from: &str, to: &str) {
+        Progress::revert_progress(self, current, total, from, to);
+    }
+
+    fn revert_complete(&mut self, count: usize, dry_run: bool) {
+        Progress::revert_complete(self, count, dry_run);
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
 mod tests {
     use super::*;
 
@@ -301,4 +580,75 @@ mod tests {
         let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
         assert!(output.is_empty());
     }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m05s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h02m");
+    }
+
+    #[test]
+    fn test_format_timestamp_utc() {
+        let timestamp = DateTime::parse_from_rfc3339("2026-01-15T10:30:45Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            format_timestamp(&timestamp, false),
+            "2026-01-15 10:30:45 UTC"
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp_local_has_offset() {
+        let timestamp = DateTime::parse_from_rfc3339("2026-01-15T10:30:45Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let local = timestamp.with_timezone(&Local);
+
+        assert_eq!(
+            format_timestamp(&timestamp, true),
+            local.format("%Y-%m-%d %H:%M:%S %:z").to_string()
+        );
+    }
+
+    #[test]
+    fn test_fetch_eta_shown_when_enabled() {
+        let (mut progress, buffer) = create_test_progress();
+        progress.eta_enabled = true;
+        progress.set_fetch_plan(74, Duration::from_secs(2));
+
+        progress.fetch_start(12345);
+        progress.fetch_complete();
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("(fetch 1/74, ~2m28s remaining)"));
+    }
+
+    #[test]
+    fn test_fetch_eta_updates_as_fetches_complete() {
+        let (mut progress, buffer) = create_test_progress();
+        progress.eta_enabled = true;
+        progress.set_fetch_plan(3, Duration::from_secs(2));
+
+        progress.fetch_start(1);
+        progress.fetch_complete();
+        progress.fetch_start(2);
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("(fetch 2/3, ~4s remaining)"));
+    }
+
+    #[test]
+    fn test_fetch_eta_suppressed_without_tty() {
+        let (mut progress, buffer) = create_test_progress();
+        progress.set_fetch_plan(10, Duration::from_secs(2));
+
+        progress.fetch_start(12345);
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(!output.contains("remaining"));
+    }
 }
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How a conflict with an existing destination directory is resolved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Stop the whole operation
+    #[default]
+    Abort,
+    /// Leave the source directory untouched
+    Skip,
+    /// Rename into a free name with a numeric suffix
+    Suffix,
+    /// Move the existing directory aside, then rename
+    Aside,
+}
+
+impl ConflictResolution {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ConflictResolution::Abort => "abort",
+            ConflictResolution::Skip => "skip",
+            ConflictResolution::Suffix => "rename with suffix",
+            ConflictResolution::Aside => "move existing aside",
+        }
+    }
+}
+
+/// Decides how to resolve a destination conflict
+pub trait ConflictResolver {
+    fn resolve(&mut self, source_name: &str, destination_name: &str) -> ConflictResolution;
+}
+
+/// A fixed policy resolves every conflict the same way
+impl ConflictResolver for ConflictResolution {
+    fn resolve(&mut self, _source_name: &str, _destination_name: &str) -> ConflictResolution {
+        *self
+    }
+}
+
+/// Upper bound on numbered alternatives tried before giving up
+const MAX_ALTERNATIVES: u32 = 100;
+
+/// Find a free name in `dir` by adding a ` (N)` suffix
+///
+/// The suffix is inserted before a trailing `[anidb-N]` tag so the result
+/// still parses as a human-readable name.
+pub fn suffixed_name(dir: &Path, name: &str) -> Option<String> {
+    let (base, tag) = match name.rfind(" [anidb-") {
+        Some(idx) if name.ends_with(']') => (&name[..idx], &name[idx..]),
+        _ => (name, ""),
+    };
+
+    (2..=MAX_ALTERNATIVES)
+        .map(|n| format!("{} ({}){}", base, n, tag))
+        .find(|candidate| !dir.join(candidate).exists())
+}
+
+/// Find a free name in `dir` to move an existing directory aside
+pub fn aside_name(dir: &Path, name: &str) -> Option<String> {
+    std::iter::once(format!("{}.aside", name))
+        .chain((2..=MAX_ALTERNATIVES).map(|n| format!("{}.aside-{}", name, n)))
+        .find(|candidate| !dir.join(candidate).exists())
+}
+
+/// Rename `from` to `to`, failing with `AlreadyExists` instead of replacing `to`
+///
+/// A plain rename silently replaces an empty directory on Linux. Uses
+/// `renameat2(RENAME_NOREPLACE)` where available; elsewhere, or on
+/// filesystems without support, checks immediately before renaming.
+pub fn rename_no_replace(from: &Path, to: &Path) -> io::Result<()> {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    match renameat2_no_replace(from, to) {
+        Err(e) if matches!(e.raw_os_error(), Some(libc::EINVAL) | Some(libc::ENOSYS)) => {}
+        other => return other,
+    }
+
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "destination already exists",
+        ));
+    }
+    fs::rename(from, to)
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn renameat2_no_replace(from: &Path, to: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both paths are valid NUL-terminated strings that outlive the call
+    let ret = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            from.as_ptr(),
+            libc::AT_FDCWD,
+            to.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_policy_resolver_is_fixed() {
+        let mut policy = ConflictResolution::Skip;
+        assert_eq!(policy.resolve("a", "b"), ConflictResolution::Skip);
+    }
+
+    #[test]
+    fn test_suffixed_name_before_anidb_tag() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+
+        assert_eq!(
+            suffixed_name(dir.path(), "Title (2020) [anidb-1]").unwrap(),
+            "Title (2020) (2) [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_suffixed_name_skips_taken() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("12345 (2)")).unwrap();
+
+        assert_eq!(suffixed_name(dir.path(), "12345").unwrap(), "12345 (3)");
+    }
+
+    #[test]
+    fn test_aside_name() {
+        let dir = tempdir().unwrap();
+        assert_eq!(aside_name(dir.path(), "12345").unwrap(), "12345.aside");
+
+        fs::create_dir(dir.path().join("12345.aside")).unwrap();
+        assert_eq!(aside_name(dir.path(), "12345").unwrap(), "12345.aside-2");
+    }
+
+    #[test]
+    fn test_serialization() {
+        assert_eq!(
+            serde_json::to_string(&ConflictResolution::Aside).unwrap(),
+            "\"aside\""
+        );
+    }
+
+    #[test]
+    fn test_rename_no_replace_refuses_empty_destination() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("12345");
+        let to = dir.path().join("Title [anidb-12345]");
+        fs::create_dir(&from).unwrap();
+        fs::create_dir(&to).unwrap();
+
+        let err = rename_no_replace(&from, &to).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(from.exists());
+
+        fs::remove_dir(&to).unwrap();
+        rename_no_replace(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert!(to.exists());
+    }
+}
@@ -0,0 +1,234 @@
+//! Names truncated to the same name.
+//!
+//! Every readable name ends in its `[anidb-ID]`, so truncation alone cannot
+//! make two ids collide. Two directories of the same id with different
+//! series tags can, though: when the tags leave no room for a title, the
+//! shortest form keeps only a few title characters and the id. Such names
+//! get a short code derived from the source name after the ellipsis, so each
+//! directory keeps a name of its own without depending on plan order.
+//! Collisions that exist without truncation are left to conflict handling.
+
+use std::collections::{BTreeMap, HashSet};
+
+use tracing::warn;
+
+use crate::progress::ProgressObserver;
+
+use super::name_builder::disambiguate_truncated;
+use super::types::RenameResult;
+
+/// Code lengths tried in turn, in hex digits, until the codes of a group differ
+const TOKEN_LENGTHS: &[usize] = &[4, 8, 64];
+
+/// Give distinct names to operations whose truncated names collide,
+/// returning how many were renamed
+pub fn disambiguate_truncation(
+    result: &mut RenameResult,
+    progress: &mut dyn ProgressObserver,
+) -> usize {
+    let profile = result.options.fs_profile;
+    let max_length = result.options.max_length;
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, op) in result.operations.iter().enumerate() {
+        groups
+            .entry(profile.collision_key(&op.destination_name))
+            .or_default()
+            .push(i);
+    }
+
+    let mut renamed = 0;
+    for indices in groups.values().filter(|indices| indices.len() > 1) {
+        let ops = &mut result.operations;
+        let full_names: HashSet<String> = indices
+            .iter()
+            .map(|&i| {
+                let op = &ops[i];
+                profile.collision_key(op.untruncated_name.as_ref().unwrap_or(&op.destination_name))
+            })
+            .collect();
+        if full_names.len() < indices.len() {
+            continue;
+        }
+
+        let Some(tokens) = TOKEN_LENGTHS.iter().find_map(|&len| {
+            let tokens: Vec<String> = indices
+                .iter()
+                .map(|&i| token(&ops[i].source_name, len))
+                .collect();
+            let unique: HashSet<&String> = tokens.iter().collect();
+            (unique.len() == tokens.len()).then_some(tokens)
+        }) else {
+            continue;
+        };
+
+        for (&i, token) in indices.iter().zip(tokens) {
+            let op = &mut ops[i];
+            if !op.truncated {
+                continue;
+            }
+            let Some(name) = disambiguate_truncated(&op.destination_name, &token, max_length)
+            else {
+                continue;
+            };
+            warn!(
+                "Truncated name of {} is not unique, using {}",
+                op.source_name, name
+            );
+            progress.warn(&format!(
+                "{}: truncated to the same name as another directory, disambiguated",
+                op.source_name
+            ));
+            op.set_destination_name(name);
+            op.disambiguated = true;
+            renamed += 1;
+        }
+    }
+    renamed
+}
+
+/// The first `len` hex digits of the SHA-256 of `source_name`
+fn token(source_name: &str, len: usize) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, source_name.as_bytes());
+    let hex: String = digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    hex[..len].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AnimeInfo;
+    use crate::progress::Progress;
+    use crate::rename::name_builder::{build_human_readable_name, NameBuilderConfig};
+    use crate::rename::{RenameDirection, RenameOperation};
+    use std::path::Path;
+
+    fn info(id: u32, title: &str) -> AnimeInfo {
+        AnimeInfo {
+            anidb_id: id,
+            title_main: title.to_string(),
+            title_en: None,
+            release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        }
+    }
+
+    fn plan(entries: &[(&str, Option<&str>, AnimeInfo)], max_length: usize) -> RenameResult {
+        let config = NameBuilderConfig {
+            max_length,
+            ..Default::default()
+        };
+        let unlimited = NameBuilderConfig {
+            max_length: usize::MAX,
+            ..Default::default()
+        };
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, true);
+        result.options.max_length = max_length;
+        for (source, tag, info) in entries {
+            let built = build_human_readable_name(*tag, info, &config);
+            let mut op = RenameOperation::new(
+                Path::new("/anime").join(source),
+                built.name,
+                info.anidb_id,
+                built.truncated,
+            );
+            if built.truncated {
+                op.untruncated_name = Some(build_human_readable_name(*tag, info, &unlimited).name);
+            }
+            result.add_operation(op);
+        }
+        result
+    }
+
+    fn names(result: &RenameResult) -> Vec<&str> {
+        result
+            .operations
+            .iter()
+            .map(|op| op.destination_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_long_titles_differing_after_the_cut_stay_unique() {
+        let common = "A Very Long Title That Goes On ".repeat(3);
+        let mut result = plan(
+            &[
+                ("1", None, info(1, &format!("{}Part One", common))),
+                ("2", None, info(2, &format!("{}Part Two", common))),
+            ],
+            60,
+        );
+        let before: Vec<String> = names(&result).iter().map(|n| n.to_string()).collect();
+        assert_eq!(before[0].replace("[anidb-1]", "[anidb-2]"), before[1]);
+
+        assert_eq!(
+            disambiguate_truncation(&mut result, &mut Progress::silent()),
+            0
+        );
+        assert_eq!(names(&result), before);
+    }
+
+    #[test]
+    fn test_tags_lost_to_truncation_are_disambiguated() {
+        let title = "Some Long Title";
+        let mut result = plan(
+            &[
+                ("[Collection A] 1", Some("Collection A"), info(1, title)),
+                ("[Collection B] 1", Some("Collection B"), info(1, title)),
+                ("[Collection C] 2", Some("Collection C"), info(2, title)),
+            ],
+            24,
+        );
+        assert_eq!(result.operations[0].destination_name, "Som… [anidb-1]");
+        assert_eq!(result.operations[1].destination_name, "Som… [anidb-1]");
+
+        assert_eq!(
+            disambiguate_truncation(&mut result, &mut Progress::silent()),
+            2
+        );
+        let renamed = names(&result);
+        assert_ne!(renamed[0], renamed[1]);
+        for name in &renamed[..2] {
+            assert!(name.len() <= 24, "{}", name);
+            assert!(name.ends_with(" [anidb-1]"), "{}", name);
+            assert!(name.contains("… ~"), "{}", name);
+        }
+        assert!(renamed[0].starts_with("Som… ~"), "{}", renamed[0]);
+        assert!(result.operations[0].disambiguated);
+        assert_eq!(renamed[2], "Som… [anidb-2]");
+        assert!(!result.operations[2].disambiguated);
+
+        // Deterministic: the code comes from the source name
+        let mut again = plan(
+            &[
+                ("[Collection B] 1", Some("Collection B"), info(1, title)),
+                ("[Collection A] 1", Some("Collection A"), info(1, title)),
+            ],
+            24,
+        );
+        disambiguate_truncation(&mut again, &mut Progress::silent());
+        assert_eq!(names(&again), [renamed[1], renamed[0]]);
+    }
+
+    #[test]
+    fn test_collisions_without_truncation_are_left_alone() {
+        // Both names collide before truncation too (e.g. --drop-series-tag)
+        let title = "Some Long Title";
+        let mut result = plan(
+            &[("1", None, info(1, title)), ("[A] 1", None, info(1, title))],
+            20,
+        );
+        assert_eq!(names(&result)[0], names(&result)[1]);
+        assert_eq!(
+            disambiguate_truncation(&mut result, &mut Progress::silent()),
+            0
+        );
+    }
+}
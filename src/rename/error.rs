@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::api::ApiError;
+use crate::parser::ParseError;
+
+/// Errors that can occur during rename operations
+#[derive(Error, Debug)]
+pub enum RenameError {
+    #[error("Failed to fetch anime data for ID {id}: {message}")]
+    ApiError { id: u32, message: String },
+
+    #[error("Failed to rename '{from}' to '{to}': {source}")]
+    FilesystemError {
+        from: String,
+        to: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Destination already exists: {0}")]
+    DestinationExists(String),
+
+    #[error("API client not configured")]
+    ApiNotConfigured,
+
+    #[error("Invalid placeholder format: {0}")]
+    InvalidPlaceholder(String),
+
+    #[error("Refusing to apply placeholder name: {0}")]
+    PlaceholderName(String),
+
+    #[error("Invalid character replacement: {0}")]
+    InvalidReplacement(String),
+
+    #[error(transparent)]
+    InvalidName(#[from] ParseError),
+
+    #[error("{name} has {files} open file(s) inside")]
+    DirectoryInUse { name: String, files: usize },
+
+    #[error("{} director(y/ies) cannot be renamed by this user", .0.len())]
+    PermissionProblems(Vec<String>),
+
+    #[error("Source directory vanished before it was renamed: {0}")]
+    SourceVanished(String),
+
+    #[error("Target directory changed during the run: {expected:?} now resolves to {actual:?}")]
+    TargetChanged { expected: PathBuf, actual: PathBuf },
+}
+
+impl From<ApiError> for RenameError {
+    fn from(err: ApiError) -> Self {
+        RenameError::ApiError {
+            id: 0,
+            message: err.to_string(),
+        }
+    }
+}
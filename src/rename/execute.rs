@@ -0,0 +1,903 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::Instant;
+use tracing::{info, warn};
+
+use crate::fs_profile::FsProfile;
+use crate::progress::ProgressObserver;
+use crate::run_id::directory_span;
+use crate::timing::Step;
+use crate::trash;
+
+use super::conflict::{
+    aside_name, rename_no_replace, suffixed_name, ConflictResolution, ConflictResolver,
+};
+use super::error::RenameError;
+use super::types::{trash_label, RenameOperation, RenameResult};
+
+/// Resolve conflicts with existing destination directories in a planned result
+///
+/// Each conflicting operation is passed to the resolver; the decision is
+/// recorded on the operation so it ends up in the history file.
+pub fn resolve_conflicts(
+    result: &mut RenameResult,
+    resolver: &mut dyn ConflictResolver,
+) -> Result<(), RenameError> {
+    // Destinations taken by earlier operations of this run, compared the way
+    // the target filesystem compares names
+    let profile = result.options.fs_profile;
+    let mut claimed = HashSet::new();
+    // Names on disk per directory, for destinations present in another
+    // normalization form, which `exists` does not see on most filesystems
+    let mut listings: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+
+    for op in &mut result.operations {
+        // The trash picks a free name of its own
+        if op.to_trash {
+            continue;
+        }
+
+        let key = profile.collision_key(&op.destination_name);
+        let exists = op.destination_path.exists()
+            || op.destination_path.parent().is_some_and(|dir| {
+                listings
+                    .entry(dir.to_path_buf())
+                    .or_insert_with(|| listing_keys(dir, profile))
+                    .contains(&key)
+            });
+        if !exists && !claimed.contains(&key) {
+            claimed.insert(key);
+            continue;
+        }
+
+        let resolution = resolver.resolve(&op.source_name, &op.destination_name);
+        warn!(
+            "Destination exists: {} ({})",
+            op.destination_name,
+            resolution.description()
+        );
+        apply_resolution(op, resolution)?;
+        if !op.is_skipped() {
+            claimed.insert(profile.collision_key(&op.destination_name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Collision keys of the names in `dir`; empty when it cannot be listed
+fn listing_keys(dir: &Path, profile: FsProfile) -> HashSet<String> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .map(|name| profile.collision_key(&name))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Record how a conflict is resolved on the operation
+fn apply_resolution(
+    op: &mut RenameOperation,
+    resolution: ConflictResolution,
+) -> Result<(), RenameError> {
+    let dir = match op.source_path.parent() {
+        Some(dir) => dir.to_path_buf(),
+        None => return Err(RenameError::DestinationExists(op.destination_name.clone())),
+    };
+
+    match resolution {
+        ConflictResolution::Abort => {
+            return Err(RenameError::DestinationExists(op.destination_name.clone()));
+        }
+        ConflictResolution::Skip => {}
+        ConflictResolution::Suffix => {
+            let name = suffixed_name(&dir, &op.destination_name)
+                .ok_or_else(|| RenameError::DestinationExists(op.destination_name.clone()))?;
+            op.set_destination_name(name);
+        }
+        ConflictResolution::Aside => {
+            let name = aside_name(&dir, &op.destination_name)
+                .ok_or_else(|| RenameError::DestinationExists(op.destination_name.clone()))?;
+            op.aside_name = Some(name);
+        }
+    }
+
+    op.conflict = Some(resolution);
+    Ok(())
+}
+
+/// Execute all non-skipped operations of a planned result
+///
+/// A destination that appears between planning and its rename is never
+/// replaced: `resolver` decides how to handle it, and the decision is
+/// recorded on the operation like any other conflict.
+///
+/// A source directory that disappeared since planning is dropped from the
+/// result and listed in `result.vanished`, unless `strict` makes it fatal.
+pub fn execute_operations(
+    result: &mut RenameResult,
+    resolver: &mut dyn ConflictResolver,
+    progress: &mut dyn ProgressObserver,
+    strict: bool,
+) -> Result<(), RenameError> {
+    let total = result.applied_count();
+    let run_id = result.run_id.clone();
+    let mut vanished = Vec::new();
+    let timings = &mut result.timings;
+
+    for (i, op) in result
+        .operations
+        .iter_mut()
+        .filter(|op| !op.is_skipped())
+        .enumerate()
+    {
+        let _span = directory_span(&run_id, "execute", op.anidb_id, &op.source_name).entered();
+        progress.rename_progress(i + 1, total, &op.source_name, &op.destination_name);
+        let started_at = Instant::now();
+        let outcome = execute_rename(op, resolver, progress);
+        if let Some(slow) = timings.record(Step::Execute, &op.source_name, started_at.elapsed()) {
+            warn!(
+                "Slow rename: {} took {:.1}s",
+                slow.name,
+                slow.elapsed.as_secs_f64()
+            );
+            progress.slow_step(&slow);
+        }
+        match outcome {
+            Err(RenameError::SourceVanished(name)) if !strict => {
+                warn!("Source vanished, skipping: {}", name);
+                progress.warn(&format!("{}: source vanished, skipped", name));
+                vanished.push(name);
+            }
+            other => other?,
+        }
+    }
+
+    result
+        .operations
+        .retain(|op| !vanished.contains(&op.source_name));
+    result.vanished.extend(vanished);
+
+    info!(
+        "Successfully renamed {} directories",
+        result.applied_count()
+    );
+
+    Ok(())
+}
+
+/// Execute like [`execute_operations`], with up to `jobs` renames in flight
+///
+/// Worker threads take operations from a queue in plan order. Each outcome
+/// goes into the operation's own slot, so the history lists operations in
+/// plan order whichever rename finishes first, and progress is reported from
+/// the calling thread as renames complete. After a failure no further rename
+/// is started; the first failure in plan order is returned once the renames
+/// in flight are done. Destinations that appear after planning are resolved
+/// on the calling thread at the end, in plan order.
+///
+/// Runs serially when `jobs` is 1 or when one operation moves something onto
+/// another's source, which only the plan order keeps right.
+pub fn execute_operations_parallel(
+    result: &mut RenameResult,
+    resolver: &mut dyn ConflictResolver,
+    progress: &mut dyn ProgressObserver,
+    strict: bool,
+    jobs: usize,
+) -> Result<(), RenameError> {
+    let jobs = jobs.min(result.applied_count());
+    if jobs <= 1 || !independent(result) {
+        return execute_operations(result, resolver, progress, strict);
+    }
+    info!("Renaming with {} jobs", jobs);
+
+    let total = result.applied_count();
+    let run_id = result.run_id.as_str();
+    let timings = &mut result.timings;
+    let pending: Vec<&mut RenameOperation> = result
+        .operations
+        .iter_mut()
+        .filter(|op| !op.is_skipped())
+        .collect();
+    let labels: Vec<(String, String)> = pending
+        .iter()
+        .map(|op| (op.source_name.clone(), op.destination_name.clone()))
+        .collect();
+    let queue = Mutex::new(pending.into_iter().enumerate().collect::<VecDeque<_>>());
+    let mut slots: Vec<Option<(&mut RenameOperation, Result<bool, RenameError>)>> =
+        (0..total).map(|_| None).collect();
+
+    std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let queue = &queue;
+            scope.spawn(move || loop {
+                let Some((i, op)) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let _span =
+                    directory_span(run_id, "execute", op.anidb_id, &op.source_name).entered();
+                let started_at = Instant::now();
+                let outcome = try_rename(op);
+                if is_fatal(&outcome, strict) {
+                    queue.lock().unwrap().clear();
+                }
+                if sender.send((i, op, started_at.elapsed(), outcome)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (done, (i, op, elapsed, outcome)) in receiver.into_iter().enumerate() {
+            let (from, to) = &labels[i];
+            progress.rename_progress(done + 1, total, from, to);
+            if let Some(slow) = timings.record(Step::Execute, from, elapsed) {
+                warn!(
+                    "Slow rename: {} took {:.1}s",
+                    slow.name,
+                    slow.elapsed.as_secs_f64()
+                );
+                progress.slow_step(&slow);
+            }
+            slots[i] = Some((op, outcome));
+        }
+    });
+
+    let mut vanished = Vec::new();
+    let mut failure = None;
+    for (op, outcome) in slots.into_iter().flatten() {
+        let outcome = match outcome {
+            Ok(true) => continue,
+            // Nothing new is started after a failure
+            Ok(false) if failure.is_some() => continue,
+            Ok(false) => rename_appeared(op, resolver, progress),
+            Err(e) => Err(e),
+        };
+        match outcome {
+            Err(RenameError::SourceVanished(name)) if !strict => {
+                warn!("Source vanished, skipping: {}", name);
+                progress.warn(&format!("{}: source vanished, skipped", name));
+                vanished.push(name);
+            }
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+            Ok(()) => {}
+        }
+    }
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    result
+        .operations
+        .retain(|op| !vanished.contains(&op.source_name));
+    result.vanished.extend(vanished);
+
+    info!(
+        "Successfully renamed {} directories",
+        result.applied_count()
+    );
+
+    Ok(())
+}
+
+/// Whether no operation moves anything onto the source of another
+fn independent(result: &RenameResult) -> bool {
+    let sources: HashSet<&Path> = result
+        .operations
+        .iter()
+        .map(|op| op.source_path.as_path())
+        .collect();
+    !result.operations.iter().any(|op| {
+        sources.contains(op.destination_path.as_path())
+            || op.aside_name.as_ref().is_some_and(|aside| {
+                sources.contains(op.source_path.with_file_name(aside).as_path())
+            })
+    })
+}
+
+/// Whether an outcome ends the run
+fn is_fatal(outcome: &Result<bool, RenameError>, strict: bool) -> bool {
+    match outcome {
+        Ok(_) => false,
+        Err(RenameError::SourceVanished(_)) => strict,
+        Err(_) => true,
+    }
+}
+
+fn execute_rename(
+    op: &mut RenameOperation,
+    resolver: &mut dyn ConflictResolver,
+    progress: &mut dyn ProgressObserver,
+) -> Result<(), RenameError> {
+    if try_rename(op)? {
+        Ok(())
+    } else {
+        rename_appeared(op, resolver, progress)
+    }
+}
+
+/// Rename without asking anything, `Ok(false)` when a destination unseen
+/// while planning is in the way
+fn try_rename(op: &mut RenameOperation) -> Result<bool, RenameError> {
+    if op.placeholder {
+        return Err(RenameError::PlaceholderName(op.destination_name.clone()));
+    }
+
+    check_parent(&op.source_path)?;
+    if is_missing(&op.source_path) {
+        return Err(RenameError::SourceVanished(op.source_name.clone()));
+    }
+    if op.to_trash {
+        return move_to_trash(op).map(|()| true);
+    }
+    move_aside(op)?;
+
+    if op.quarantined {
+        if let Some(dir) = op.destination_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| RenameError::FilesystemError {
+                from: op.source_name.clone(),
+                to: op.destination_name.clone(),
+                source: e,
+            })?;
+        }
+    }
+
+    info!("Renaming: {} -> {}", op.source_name, op.destination_name);
+
+    match rename_no_replace(&op.source_path, &op.destination_path) {
+        // Only a destination unseen while planning is resolved late; a
+        // second collision after resolving fails
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && op.conflict.is_none() => Ok(false),
+        other => other
+            .map(|()| true)
+            .map_err(|e| RenameError::FilesystemError {
+                from: op.source_name.clone(),
+                to: op.destination_name.clone(),
+                source: e,
+            }),
+    }
+}
+
+/// Resolve a destination that appeared after planning, then rename again
+fn rename_appeared(
+    op: &mut RenameOperation,
+    resolver: &mut dyn ConflictResolver,
+    progress: &mut dyn ProgressObserver,
+) -> Result<(), RenameError> {
+    let resolution = resolver.resolve(&op.source_name, &op.destination_name);
+    warn!(
+        "Destination appeared after planning: {} ({})",
+        op.destination_name,
+        resolution.description()
+    );
+    progress.warn(&format!(
+        "Destination appeared after planning: {} ({})",
+        op.destination_name,
+        resolution.description()
+    ));
+    apply_resolution(op, resolution)?;
+    if op.is_skipped() {
+        return Ok(());
+    }
+    move_aside(op)?;
+
+    info!("Renaming: {} -> {}", op.source_name, op.destination_name);
+    rename_no_replace(&op.source_path, &op.destination_path).map_err(|e| {
+        RenameError::FilesystemError {
+            from: op.source_name.clone(),
+            to: op.destination_name.clone(),
+            source: e,
+        }
+    })
+}
+
+/// Move a quarantined directory to the system trash, recording where it went
+fn move_to_trash(op: &mut RenameOperation) -> Result<(), RenameError> {
+    info!("Moving to trash: {}", op.source_name);
+
+    let trashed =
+        trash::move_to_trash(&op.source_path).map_err(|e| RenameError::FilesystemError {
+            from: op.source_name.clone(),
+            to: op.destination_name.clone(),
+            source: e,
+        })?;
+
+    info!("Trashed {} as {:?}", op.source_name, trashed.path);
+    op.destination_name = trash_label(&trashed.path);
+    op.destination_path = trashed.path;
+    op.trash_info = Some(trashed.info);
+    Ok(())
+}
+
+/// Where the parent of `path` resolves now, if no longer to itself
+///
+/// Operations are built from the canonical target directory, so a parent
+/// resolving elsewhere means a component was swapped for a symlink since
+/// planning. A parent that cannot be resolved is left to the rename to report.
+pub fn moved_parent(path: &Path) -> Option<PathBuf> {
+    let parent = path.parent()?;
+    match fs::canonicalize(parent) {
+        Ok(actual) if actual != parent => Some(actual),
+        _ => None,
+    }
+}
+
+fn check_parent(path: &Path) -> Result<(), RenameError> {
+    match moved_parent(path) {
+        Some(actual) => Err(RenameError::TargetChanged {
+            expected: path.parent().unwrap_or(path).to_path_buf(),
+            actual,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Whether nothing exists at `path` any more (a dangling symlink still exists)
+pub fn is_missing(path: &Path) -> bool {
+    matches!(fs::symlink_metadata(path), Err(e) if e.kind() == io::ErrorKind::NotFound)
+}
+
+/// Move the directory occupying the destination aside, if so resolved
+fn move_aside(op: &RenameOperation) -> Result<(), RenameError> {
+    let Some(aside) = &op.aside_name else {
+        return Ok(());
+    };
+
+    info!(
+        "Moving existing '{}' aside to '{}'",
+        op.destination_name, aside
+    );
+    let aside_path = op.source_path.with_file_name(aside);
+
+    rename_no_replace(&op.destination_path, &aside_path).map_err(|e| RenameError::FilesystemError {
+        from: op.destination_name.clone(),
+        to: aside.clone(),
+        source: e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_profile::FsProfile;
+    use crate::progress::Progress;
+    use crate::rename::RenameDirection;
+    use tempfile::tempdir;
+
+    fn setup_conflict() -> (tempfile::TempDir, RenameResult) {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("12345")).unwrap();
+        fs::create_dir(dir.path().join("Title [anidb-12345]")).unwrap();
+
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        result.add_operation(RenameOperation::new(
+            dir.path().join("12345"),
+            "Title [anidb-12345]".to_string(),
+            12345,
+            false,
+        ));
+        (dir, result)
+    }
+
+    #[test]
+    fn test_execute_spans_carry_run_id() {
+        use crate::run_id::testing::SpanRecorder;
+
+        let dir = tempdir().unwrap();
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        for id in [1, 2] {
+            fs::create_dir(dir.path().join(id.to_string())).unwrap();
+            result.add_operation(RenameOperation::new(
+                dir.path().join(id.to_string()),
+                format!("Title [anidb-{}]", id),
+                id,
+                false,
+            ));
+        }
+        let recorder = SpanRecorder::default();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            execute_operations(
+                &mut result,
+                &mut ConflictResolution::Abort,
+                &mut Progress::silent(),
+                false,
+            )
+        })
+        .unwrap();
+
+        let spans = recorder.spans();
+        assert_eq!(spans.len(), 2);
+        for ((name, fields), (id, source)) in spans.iter().zip([("1", "1"), ("2", "2")]) {
+            assert_eq!(name, "directory");
+            assert_eq!(fields["run_id"], result.run_id);
+            assert_eq!(fields["stage"], "execute");
+            assert_eq!(fields["anidb_id"], id);
+            assert_eq!(fields["source"], source);
+        }
+    }
+
+    #[test]
+    fn test_slow_renames_are_reported() {
+        use crate::timing::{StepTiming, Timings};
+
+        #[derive(Default)]
+        struct SlowSteps(Vec<StepTiming>);
+        impl ProgressObserver for SlowSteps {
+            fn slow_step(&mut self, timing: &StepTiming) {
+                self.0.push(timing.clone());
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        fs::create_dir(dir.path().join("1")).unwrap();
+        result.add_operation(RenameOperation::new(
+            dir.path().join("1"),
+            "Title [anidb-1]".to_string(),
+            1,
+            false,
+        ));
+
+        // Every rename takes longer than a nanosecond
+        result.timings = Timings::new(std::time::Duration::from_nanos(1));
+        let mut observer = SlowSteps::default();
+        execute_operations(
+            &mut result,
+            &mut ConflictResolution::Abort,
+            &mut observer,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(observer.0.len(), 1);
+        assert_eq!(observer.0[0].step, Step::Execute);
+        assert_eq!(observer.0[0].name, "1");
+        assert_eq!(result.timings.slowest(), &observer.0[..]);
+    }
+
+    #[test]
+    fn test_resolve_abort() {
+        let (_dir, mut result) = setup_conflict();
+        let outcome = resolve_conflicts(&mut result, &mut ConflictResolution::Abort);
+        assert!(matches!(outcome, Err(RenameError::DestinationExists(_))));
+    }
+
+    #[test]
+    fn test_resolve_skip() {
+        let (dir, mut result) = setup_conflict();
+        resolve_conflicts(&mut result, &mut ConflictResolution::Skip).unwrap();
+
+        assert!(result.operations[0].is_skipped());
+        assert_eq!(result.applied_count(), 0);
+
+        execute_operations(
+            &mut result,
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+        )
+        .unwrap();
+        assert!(dir.path().join("12345").exists());
+    }
+
+    #[test]
+    fn test_resolve_suffix() {
+        let (dir, mut result) = setup_conflict();
+        resolve_conflicts(&mut result, &mut ConflictResolution::Suffix).unwrap();
+
+        assert_eq!(
+            result.operations[0].destination_name,
+            "Title (2) [anidb-12345]"
+        );
+
+        execute_operations(
+            &mut result,
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+        )
+        .unwrap();
+        assert!(dir.path().join("Title (2) [anidb-12345]").exists());
+        assert!(dir.path().join("Title [anidb-12345]").exists());
+    }
+
+    #[test]
+    fn test_resolve_aside() {
+        let (dir, mut result) = setup_conflict();
+        resolve_conflicts(&mut result, &mut ConflictResolution::Aside).unwrap();
+
+        assert_eq!(
+            result.operations[0].aside_name.as_deref(),
+            Some("Title [anidb-12345].aside")
+        );
+
+        execute_operations(
+            &mut result,
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+        )
+        .unwrap();
+        assert!(dir.path().join("Title [anidb-12345].aside").exists());
+        assert!(dir.path().join("Title [anidb-12345]").exists());
+        assert!(!dir.path().join("12345").exists());
+    }
+
+    #[test]
+    fn test_resolve_case_only_collision_under_windows_profile() {
+        let dir = tempdir().unwrap();
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        for (source, name) in [
+            ("[tv] 1", "[tv] Title [anidb-1]"),
+            ("[TV] 1", "[TV] Title [anidb-1]"),
+        ] {
+            fs::create_dir(dir.path().join(source)).unwrap();
+            result.add_operation(RenameOperation::new(
+                dir.path().join(source),
+                name.to_string(),
+                1,
+                false,
+            ));
+        }
+
+        // Case-sensitive: two distinct destinations
+        resolve_conflicts(&mut result, &mut ConflictResolution::Skip).unwrap();
+        assert_eq!(result.skipped_count(), 0);
+
+        result.options.fs_profile = FsProfile::Windows;
+        resolve_conflicts(&mut result, &mut ConflictResolution::Skip).unwrap();
+        assert!(!result.operations[0].is_skipped());
+        assert!(result.operations[1].is_skipped());
+    }
+
+    #[test]
+    fn test_resolve_destination_present_in_nfd() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("123")).unwrap();
+        // Renamed earlier and listed decomposed, as on macOS
+        fs::create_dir(dir.path().join("Poke\u{301}mon (1997) [anidb-123]")).unwrap();
+
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        result.add_operation(RenameOperation::new(
+            dir.path().join("123"),
+            "Pok\u{e9}mon (1997) [anidb-123]".to_string(),
+            123,
+            false,
+        ));
+
+        let outcome = resolve_conflicts(&mut result, &mut ConflictResolution::Abort);
+        assert!(matches!(outcome, Err(RenameError::DestinationExists(_))));
+        resolve_conflicts(&mut result, &mut ConflictResolution::Skip).unwrap();
+        assert!(result.operations[0].is_skipped());
+    }
+
+    #[test]
+    fn test_execute_quarantine_creates_unresolved_dir() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("12345")).unwrap();
+
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        result.add_operation(RenameOperation::quarantine(dir.path().join("12345"), 12345));
+        execute_operations(
+            &mut result,
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+        )
+        .unwrap();
+
+        assert!(!dir.path().join("12345").exists());
+        assert!(dir.path().join("_unresolved").join("12345").is_dir());
+        assert_eq!(result.quarantined_count(), 1);
+    }
+
+    #[test]
+    fn test_execute_late_conflict_is_not_replaced() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("12345")).unwrap();
+
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        result.add_operation(RenameOperation::new(
+            dir.path().join("12345"),
+            "Title [anidb-12345]".to_string(),
+            12345,
+            false,
+        ));
+        resolve_conflicts(&mut result, &mut ConflictResolution::Abort).unwrap();
+
+        // Empty destination created by another process after planning
+        fs::create_dir(dir.path().join("Title [anidb-12345]")).unwrap();
+
+        let outcome = execute_operations(
+            &mut result.clone(),
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+        );
+        assert!(matches!(outcome, Err(RenameError::DestinationExists(_))));
+        assert!(dir.path().join("12345").exists());
+
+        execute_operations(
+            &mut result,
+            &mut ConflictResolution::Suffix,
+            &mut Progress::silent(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            result.operations[0].conflict,
+            Some(ConflictResolution::Suffix)
+        );
+        assert!(dir.path().join("Title (2) [anidb-12345]").exists());
+        assert!(!dir.path().join("12345").exists());
+    }
+
+    #[test]
+    fn test_execute_refuses_placeholder() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("12345")).unwrap();
+
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        result.add_operation(RenameOperation::placeholder(
+            dir.path().join("12345"),
+            "⟨uncached 12345⟩".to_string(),
+            12345,
+        ));
+        let outcome = execute_operations(
+            &mut result,
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+        );
+
+        assert!(matches!(outcome, Err(RenameError::PlaceholderName(_))));
+        assert!(dir.path().join("12345").exists());
+    }
+
+    fn library(dir: &Path, count: u32) -> RenameResult {
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        for id in 1..=count {
+            fs::create_dir(dir.join(id.to_string())).unwrap();
+            result.add_operation(RenameOperation::new(
+                dir.join(id.to_string()),
+                format!("Title {} [anidb-{}]", id, id),
+                id,
+                false,
+            ));
+        }
+        result
+    }
+
+    fn listing(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        #[derive(Default)]
+        struct Counter(Vec<usize>);
+        impl ProgressObserver for Counter {
+            fn rename_progress(&mut self, current: usize, _total: usize, _from: &str, _to: &str) {
+                self.0.push(current);
+            }
+        }
+
+        let serial_dir = tempdir().unwrap();
+        let parallel_dir = tempdir().unwrap();
+        let mut serial = library(serial_dir.path(), 20);
+        let mut parallel = library(parallel_dir.path(), 20);
+        for (dir, result) in [
+            (serial_dir.path(), &mut serial),
+            (parallel_dir.path(), &mut parallel),
+        ] {
+            fs::create_dir(dir.join("99")).unwrap();
+            result.add_operation(RenameOperation::quarantine(dir.join("99"), 99));
+        }
+
+        execute_operations(
+            &mut serial,
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+        )
+        .unwrap();
+        let mut counter = Counter::default();
+        execute_operations_parallel(
+            &mut parallel,
+            &mut ConflictResolution::Abort,
+            &mut counter,
+            false,
+            4,
+        )
+        .unwrap();
+
+        assert_eq!(listing(parallel_dir.path()), listing(serial_dir.path()));
+        let names = |result: &RenameResult| {
+            result
+                .operations
+                .iter()
+                .map(|op| (op.source_name.clone(), op.destination_name.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&parallel), names(&serial));
+        assert_eq!(counter.0, (1..=21).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parallel_fails_on_placeholder() {
+        let dir = tempdir().unwrap();
+        let mut result = library(dir.path(), 8);
+        fs::create_dir(dir.path().join("12345")).unwrap();
+        result.add_operation(RenameOperation::placeholder(
+            dir.path().join("12345"),
+            "⟨uncached 12345⟩".to_string(),
+            12345,
+        ));
+
+        let outcome = execute_operations_parallel(
+            &mut result,
+            &mut ConflictResolution::Abort,
+            &mut Progress::silent(),
+            false,
+            4,
+        );
+        assert!(matches!(outcome, Err(RenameError::PlaceholderName(_))));
+        assert!(dir.path().join("12345").exists());
+    }
+
+    #[test]
+    fn test_parallel_late_conflict_and_vanished_source() {
+        let dir = tempdir().unwrap();
+        let mut result = library(dir.path(), 6);
+        fs::create_dir(dir.path().join("Title 2 [anidb-2]")).unwrap();
+        fs::remove_dir(dir.path().join("5")).unwrap();
+
+        execute_operations_parallel(
+            &mut result,
+            &mut ConflictResolution::Suffix,
+            &mut Progress::silent(),
+            false,
+            3,
+        )
+        .unwrap();
+
+        assert_eq!(result.vanished, ["5"]);
+        assert_eq!(result.len(), 5);
+        assert_eq!(
+            result.operations[1].destination_name,
+            "Title 2 (2) [anidb-2]"
+        );
+        assert_eq!(
+            result.operations[1].conflict,
+            Some(ConflictResolution::Suffix)
+        );
+        assert!(dir.path().join("Title 2 (2) [anidb-2]").exists());
+        assert!(!dir.path().join("2").exists());
+    }
+
+    #[test]
+    fn test_chained_operations_are_not_independent() {
+        let dir = tempdir().unwrap();
+        let mut result = library(dir.path(), 2);
+        assert!(independent(&result));
+
+        result.operations[0].set_destination_name("2".to_string());
+        assert!(!independent(&result));
+    }
+}
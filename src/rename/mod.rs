@@ -1,7 +1,71 @@
+mod conflict;
+#[cfg(all(feature = "api", feature = "cache"))]
+mod disambiguate;
+mod error;
+mod execute;
 mod name_builder;
+mod open_files;
+mod order;
+mod overrides;
+mod permissions;
+mod placeholder;
+mod post_verify;
+mod recent;
+mod tag_map;
+mod to_anidb;
+#[cfg(all(feature = "api", feature = "cache"))]
 mod to_readable;
 mod types;
+#[cfg(all(feature = "api", feature = "cache"))]
+mod verify;
 
-pub use name_builder::build_anidb_name;
-pub use to_readable::{rename_to_readable, RenameError, RenameOptions};
-pub use types::{RenameDirection, RenameOperation, RenameResult};
+pub use conflict::{
+    aside_name, rename_no_replace, suffixed_name, ConflictResolution, ConflictResolver,
+};
+#[allow(unused_imports)]
+pub use execute::execute_operations;
+pub use execute::{execute_operations_parallel, is_missing, moved_parent, resolve_conflicts};
+// Library entry points not used by the binary
+pub use error::RenameError;
+#[allow(unused_imports)]
+pub use name_builder::{
+    altered_series_tag, build_anidb_name, build_anidb_name_with_suffix, build_human_readable_name,
+    build_human_readable_name_with_suffix, comparable_name, compute_destination,
+    disambiguate_truncated, replacement_table, sanitize_filename, NameBuildResult,
+    NameBuilderConfig,
+};
+#[allow(unused_imports)]
+pub use open_files::{check_open_files, count_open_under, is_under};
+pub use order::natural_cmp;
+#[allow(unused_imports)]
+pub use overrides::{Override, Overrides, OverridesError};
+pub use permissions::check_permissions;
+#[allow(unused_imports)]
+pub use permissions::permission_problem;
+pub use placeholder::DEFAULT_PLACEHOLDER;
+#[allow(unused_imports)]
+pub use placeholder::{placeholder_name, validate_placeholder};
+pub use post_verify::{rename_moves, verify_moves, PostRunCheck};
+#[allow(unused_imports)]
+pub use post_verify::{Discrepancy, DiscrepancyKind};
+pub use recent::exclude_recent;
+#[allow(unused_imports)]
+pub use recent::{recent_entry, RecentSkip};
+pub use tag_map::{TagMap, TagMapError};
+pub use to_anidb::plan_to_anidb;
+#[cfg(all(feature = "api", feature = "cache"))]
+#[allow(unused_imports)]
+pub use to_readable::rename_to_readable;
+#[cfg(all(feature = "api", feature = "cache"))]
+pub use to_readable::{plan_to_readable, RenameOptions};
+#[allow(unused_imports)]
+pub use types::{trash_label, IdMismatch, PermissionProblem, RenameOperation, Retag};
+pub use types::{
+    EffectiveOptions, RenameDirection, RenameResult, RestrictedPolicy, UnairedPolicy,
+    DEFAULT_RESTRICTED_MARKER,
+};
+#[cfg(all(feature = "api", feature = "cache"))]
+#[allow(unused_imports)]
+pub use verify::titles_resemble;
+#[cfg(all(feature = "api", feature = "cache"))]
+pub use verify::{cached_id_conflicts, verify_ids, VerifyOptions};
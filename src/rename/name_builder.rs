@@ -1,14 +1,56 @@
-use crate::api::AnimeInfo;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::api::{AnimeInfo, AnimeType};
+use crate::parser::{
+    normalize_name, parse_directory_name, parse_directory_name_with, NameTemplate, ParseError,
+    ParsedDirectory, TemplateFields, UNAIRED_TAG,
+};
+
+use super::error::RenameError;
+
+/// Trailing `(YYYY)` disambiguator in an AniDB title
+static TRAILING_YEAR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.*?)\s*\((\d{4})\)\s*$").unwrap());
+
+/// `(YYYY-YYYY)` year range in a title, e.g. `Monster (2004-2005)`
+static YEAR_RANGE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\((\d{4})\s*[-–]\s*(\d{4})\)").unwrap());
 
 /// Configuration for name building
 #[derive(Debug, Clone)]
 pub struct NameBuilderConfig {
     pub max_length: usize,
+    /// Leave the series tag out of the generated name
+    pub drop_series_tag: bool,
+    /// Marker prepended to restricted entries (`None` leaves them unmarked)
+    pub restricted_marker: Option<String>,
+    /// Add the [`UNAIRED_TAG`] to anime that have not aired yet
+    pub unaired_tag: bool,
+    /// Text added in parentheses after the titles of each listed type,
+    /// e.g. `(Movie, "Movie")` gives `Title (Movie) (2016) [anidb-1]`
+    pub type_suffixes: Vec<(AnimeType, String)>,
+    /// Add AniDB's type after the year, e.g. `Title (2011) (OVA) [anidb-1]`,
+    /// for types without an entry in `type_suffixes`
+    pub include_type: bool,
+    /// Character replacement table (see [`replacement_table`])
+    pub replacements: Vec<(char, String)>,
+    /// Layout of the names (`None`: the built-in one)
+    pub template: Option<NameTemplate>,
 }
 
 impl Default for NameBuilderConfig {
     fn default() -> Self {
-        Self { max_length: 255 }
+        Self {
+            max_length: 255,
+            drop_series_tag: false,
+            restricted_marker: None,
+            unaired_tag: false,
+            type_suffixes: Vec::new(),
+            include_type: false,
+            replacements: DEFAULT_REPLACEMENTS.clone(),
+            template: None,
+        }
     }
 }
 
@@ -34,14 +76,91 @@ const REPLACEMENTS: &[(char, char)] = &[
     ('`', '\''),  // Backtick to single quote
 ];
 
+static DEFAULT_REPLACEMENTS: Lazy<Vec<(char, String)>> = Lazy::new(|| {
+    REPLACEMENTS
+        .iter()
+        .map(|&(from, to)| (from, to.to_string()))
+        .collect()
+});
+
+/// Characters that build the name structure and can't be replaced
+const STRUCTURAL_CHARS: &[char] = &['[', ']', '(', ')'];
+
+/// Build the replacement table from the defaults and user overrides
+///
+/// An override replaces the default for its character or adds a new one.
+/// Replacements must not contain characters that are replaced themselves,
+/// so sanitizing a generated name again leaves it unchanged.
+pub fn replacement_table(overrides: &[(char, String)]) -> Result<Vec<(char, String)>, RenameError> {
+    let mut table = DEFAULT_REPLACEMENTS.clone();
+
+    for (from, to) in overrides {
+        if from.is_control() || from.is_whitespace() || STRUCTURAL_CHARS.contains(from) {
+            return Err(RenameError::InvalidReplacement(format!(
+                "'{}' can't be replaced",
+                from.escape_default()
+            )));
+        }
+        match table.iter_mut().find(|(c, _)| c == from) {
+            Some(entry) => entry.1 = to.clone(),
+            None => table.push((*from, to.clone())),
+        }
+    }
+
+    for (from, to) in &table {
+        // A lone ／ is the default for '/'; anything longer could form the title separator
+        let bad = to.chars().find(|c| {
+            c.is_control()
+                || (*c == '／' && to != "／")
+                || REPLACEMENTS.iter().any(|(r, _)| r == c)
+                || table.iter().any(|(r, _)| r == c)
+        });
+        if let Some(bad) = bad {
+            return Err(RenameError::InvalidReplacement(format!(
+                "'{}' for '{}' contains '{}', which is not allowed in names",
+                to,
+                from,
+                bad.escape_default()
+            )));
+        }
+    }
+
+    Ok(table)
+}
+
 /// Build a human-readable directory name from anime info
+#[allow(dead_code)] // Library API, not used by the binary
 pub fn build_human_readable_name(
     series_tag: Option<&str>,
     info: &AnimeInfo,
     config: &NameBuilderConfig,
 ) -> NameBuildResult {
+    build_human_readable_name_with_suffix(series_tag, None, info, config)
+}
+
+/// Build a human-readable directory name keeping a season or part marker
+///
+/// The suffix is written after the year, before the id tag, e.g.
+/// `Title (2017) S2 [anidb-99]`, where the parser finds it again.
+pub fn build_human_readable_name_with_suffix(
+    series_tag: Option<&str>,
+    suffix: Option<&str>,
+    info: &AnimeInfo,
+    config: &NameBuilderConfig,
+) -> NameBuildResult {
+    let series_tag = series_tag.filter(|_| !config.drop_series_tag);
+    let info = &extract_title_years(info);
+    if let Some(template) = &config.template {
+        return build_templated_name(template, series_tag, suffix, info, config);
+    }
     let mut parts: Vec<String> = Vec::new();
 
+    // Restricted marker, unless the series tag already is the marker
+    let marker = restricted_marker(series_tag, info, config);
+    if let Some(marker) = &marker {
+        parts.push(format!("[{}]", marker));
+    }
+
     // Series tag
     if let Some(tag) = series_tag {
         parts.push(format!("[{}]", tag));
@@ -51,31 +170,40 @@ pub fn build_human_readable_name(
     let title_part = build_title_part(&info.title_main, info.title_en.as_deref());
     parts.push(title_part);
 
-    // Year - only add if not already present in titles
-    if let Some(year) = info.release_year {
-        let year_str = year.to_string();
-        let title_contains_year = info.title_main.contains(&year_str)
-            || info
-                .title_en
-                .as_ref()
-                .map(|en| en.contains(&year_str))
-                .unwrap_or(false);
-
-        if !title_contains_year {
-            parts.push(format!("({})", year));
-        }
+    // Type suffix, e.g. (Movie)
+    if let Some(suffix) = type_suffix(info, config) {
+        parts.push(format!("({})", suffix));
     }
 
-    // AniDB ID suffix (always required)
-    parts.push(format!("[anidb-{}]", info.anidb_id));
+    if let Some(year) = shown_year(info) {
+        parts.push(format!("({})", year));
+    }
+
+    // AniDB type, e.g. (OVA)
+    if let Some(label) = type_label(info, config) {
+        parts.push(format!("({})", label));
+    }
+
+    if let Some(suffix) = suffix {
+        parts.push(suffix.to_string());
+    }
+
+    // AniDB ID suffix (always required), after the unaired tag if any
+    parts.push(id_suffix(info, config));
 
     // Join and sanitize
     let raw_name = parts.join(" ");
-    let sanitized = sanitize_filename(&raw_name);
+    let sanitized = sanitize_with(&raw_name, &config.replacements);
 
     // Truncate if needed
     if sanitized.len() > config.max_length {
-        let truncated_name = truncate_name(series_tag, info, config.max_length);
+        let prefix: String = marker
+            .iter()
+            .map(|m| m.as_str())
+            .chain(series_tag)
+            .map(|t| format!("[{}] ", t))
+            .collect();
+        let truncated_name = truncate_name(&prefix, suffix, info, config);
 
         NameBuildResult {
             name: truncated_name,
@@ -89,48 +217,288 @@ pub fn build_human_readable_name(
     }
 }
 
-/// Build the title part of the name
+/// Build a name laid out by `template`
+///
+/// As with the built-in layout, a name that is too long loses its English
+/// title and then as much of the main title as needed.
+fn build_templated_name(
+    template: &NameTemplate,
+    series_tag: Option<&str>,
+    suffix: Option<&str>,
+    info: &AnimeInfo,
+    config: &NameBuilderConfig,
+) -> NameBuildResult {
+    let sanitize = |s: &str| sanitize_with(s, &config.replacements);
+    let marker = restricted_marker(series_tag, info, config).map(|m| sanitize(&m));
+    let series_tag = series_tag.map(sanitize);
+    let title_jp = sanitize(&info.title_main);
+    let title_en = shown_title_en(&info.title_main, info.title_en.as_deref()).map(sanitize);
+    let type_text = type_suffix(info, config)
+        .or(type_label(info, config))
+        .map(sanitize);
+    let suffix = suffix.map(sanitize);
+    let fields = TemplateFields {
+        marker: marker.as_deref(),
+        series_tag: series_tag.as_deref(),
+        title_jp: &title_jp,
+        title_en: title_en.as_deref(),
+        year: shown_year(info),
+        type_text: type_text.as_deref(),
+        suffix: suffix.as_deref(),
+        anidb_id: info.anidb_id,
+        episodes: info.episode_count,
+        unaired: config.unaired_tag && info.unaired,
+    };
+    // Template text is kept as written; only repeated spaces are collapsed
+    let render = |fields: &TemplateFields| sanitize_with(&template.render(fields), &[]);
+
+    let name = render(&fields);
+    if name.len() <= config.max_length {
+        return NameBuildResult {
+            name,
+            truncated: false,
+        };
+    }
+
+    // Room left for the main title and its ellipsis
+    let fixed_len = render(&TemplateFields {
+        title_jp: ELLIPSIS,
+        title_en: None,
+        ..fields.clone()
+    })
+    .len()
+        - ELLIPSIS.len();
+    let available = config
+        .max_length
+        .saturating_sub(fixed_len)
+        .saturating_sub(ELLIPSIS.len())
+        .max(3);
+    let title = format!(
+        "{}{}",
+        truncate_string_utf8_safe(&title_jp, available),
+        ELLIPSIS
+    );
+    NameBuildResult {
+        name: render(&TemplateFields {
+            title_jp: &title,
+            title_en: None,
+            ..fields
+        }),
+        truncated: true,
+    }
+}
+
+/// Compute the human-readable name a directory would be renamed to
+///
+/// `name` may be in either format; its series tag (unless
+/// `config.drop_series_tag`) and season or part marker are kept and
+/// everything else comes from `info`.
+/// Nothing is read from disk, the cache or the API.
+///
+/// ```
+/// use anidb2folder::{compute_destination, AnimeInfo, NameBuilderConfig};
+///
+/// let info = AnimeInfo {
+///     anidb_id: 1,
+///     title_main: "Cowboy Bebop".to_string(),
+///     title_en: None,
+///     release_year: Some(1998),
+///     english_fallback: false,
+///     restricted: false,
+///     unaired: false,
+///     anime_type: None,
+///     episode_count: None,
+/// };
+/// let config = NameBuilderConfig::default();
+///
+/// let result = compute_destination("[TV] 1", &info, &config).unwrap();
+/// assert_eq!(result.name, "[TV] Cowboy Bebop (1998) [anidb-1]");
+///
+/// // Already human-readable names keep their tag too
+/// let result = compute_destination("[TV] Old Title (1998) [anidb-1]", &info, &config).unwrap();
+/// assert_eq!(result.name, "[TV] Cowboy Bebop (1998) [anidb-1]");
+///
+/// assert!(compute_destination("Not an anime", &info, &config).is_err());
+/// ```
+pub fn compute_destination(
+    name: &str,
+    info: &AnimeInfo,
+    config: &NameBuilderConfig,
+) -> Result<NameBuildResult, ParseError> {
+    let parsed = parse_directory_name_with(name, config.template.as_ref())?;
+    Ok(build_human_readable_name_with_suffix(
+        parsed.series_tag(),
+        parsed.suffix(),
+        info,
+        config,
+    ))
+}
+
+/// `[anidb-ID]`, preceded by the unaired tag when it applies
+fn id_suffix(info: &AnimeInfo, config: &NameBuilderConfig) -> String {
+    if config.unaired_tag && info.unaired {
+        format!("[{}] [anidb-{}]", UNAIRED_TAG, info.anidb_id)
+    } else {
+        format!("[anidb-{}]", info.anidb_id)
+    }
+}
+
+/// The configured suffix text for the entry's type, if any
+fn type_suffix<'a>(info: &AnimeInfo, config: &'a NameBuilderConfig) -> Option<&'a str> {
+    let anime_type = info.anime_type?;
+    config
+        .type_suffixes
+        .iter()
+        .find(|(t, _)| *t == anime_type)
+        .map(|(_, text)| text.as_str())
+}
+
+/// AniDB's type label to add with [`NameBuilderConfig::include_type`]
+///
+/// Not for types given a suffix text, nor when a title already holds the
+/// label in parentheses, e.g. `Hellsing Ultimate (OVA)`.
+fn type_label(info: &AnimeInfo, config: &NameBuilderConfig) -> Option<&'static str> {
+    if !config.include_type || type_suffix(info, config).is_some() {
+        return None;
+    }
+    let label = info.anime_type?.label();
+    let tag = format!("({})", label.to_lowercase());
+    let in_title = std::iter::once(info.title_main.as_str())
+        .chain(info.title_en.as_deref())
+        .any(|title| title.to_lowercase().contains(&tag));
+    (!in_title).then_some(label)
+}
+
+/// The marker to prepend for a restricted entry, if any
+fn restricted_marker(
+    series_tag: Option<&str>,
+    info: &AnimeInfo,
+    config: &NameBuilderConfig,
+) -> Option<String> {
+    let marker = config.restricted_marker.as_deref()?;
+    if !info.restricted || series_tag == Some(marker) {
+        return None;
+    }
+    Some(marker.to_string())
+}
+
+/// Move trailing `(YYYY)` disambiguators out of the titles into the year
+///
+/// The API's release year wins over a year found in a title, so the year
+/// is emitted exactly once, at the end.
+fn extract_title_years(info: &AnimeInfo) -> AnimeInfo {
+    let (title_main, main_year) = split_trailing_year(&info.title_main);
+    let (title_en, en_year) = match info.title_en.as_deref() {
+        Some(en) => {
+            let (title, year) = split_trailing_year(en);
+            (Some(title.to_string()), year)
+        }
+        None => (None, None),
+    };
+
+    AnimeInfo {
+        anidb_id: info.anidb_id,
+        title_main: title_main.to_string(),
+        title_en,
+        release_year: info.release_year.or(main_year).or(en_year),
+        english_fallback: info.english_fallback,
+        restricted: info.restricted,
+        unaired: info.unaired,
+        anime_type: info.anime_type,
+        episode_count: info.episode_count,
+    }
+}
+
+/// Split `Title (2009)` into `Title` and 2009; other titles are returned unchanged
+fn split_trailing_year(title: &str) -> (&str, Option<u16>) {
+    match TRAILING_YEAR_REGEX.captures(title) {
+        Some(caps) if !caps[1].is_empty() => {
+            let year = caps[2].parse().ok();
+            (caps.get(1).map_or(title, |m| m.as_str()), year)
+        }
+        _ => (title, None),
+    }
+}
+
+/// The year to show in the name
+///
+/// Only when not already present in the titles, as such or within a range
+/// like `(2004-2005)`. Movies always get it: they often share their title
+/// with the series they belong to.
+fn shown_year(info: &AnimeInfo) -> Option<u16> {
+    let year = info.release_year?;
+    let title_contains_year = std::iter::once(info.title_main.as_str())
+        .chain(info.title_en.as_deref())
+        .any(|title| title_mentions_year(title, year));
+
+    (!title_contains_year || info.anime_type == Some(AnimeType::Movie)).then_some(year)
+}
+
+/// Whether `title` contains `year`, or a year range including it
+fn title_mentions_year(title: &str, year: u16) -> bool {
+    title.contains(&year.to_string())
+        || YEAR_RANGE_REGEX.captures_iter(title).any(|caps| {
+            let start: u16 = caps[1].parse().unwrap_or(u16::MAX);
+            let end: u16 = caps[2].parse().unwrap_or(0);
+            (start..=end).contains(&year)
+        })
+}
+
+/// The English title to show next to the main title
 /// Skips EN title if:
 /// - It's the same as main title
 /// - It's empty
 /// - It's contained within the main title (e.g., JP: "Vakhiin/Vakhii", EN: "Vakhii")
+fn shown_title_en<'a>(title_main: &str, title_en: Option<&'a str>) -> Option<&'a str> {
+    title_en.filter(|en| !en.is_empty() && *en != title_main && !title_main.contains(en))
+}
+
+/// Build the title part of the name
 fn build_title_part(title_main: &str, title_en: Option<&str>) -> String {
-    match title_en {
-        Some(en) if !en.is_empty() && en != title_main && !title_main.contains(en) => {
-            // Use fullwidth slash as separator (／)
-            format!("{} ／ {}", title_main, en)
-        }
-        _ => title_main.to_string(),
+    match shown_title_en(title_main, title_en) {
+        // Use fullwidth slash as separator (／)
+        Some(en) => format!("{} ／ {}", title_main, en),
+        None => title_main.to_string(),
     }
 }
 
 /// Sanitize filename by replacing invalid characters with fullwidth Unicode equivalents
 pub fn sanitize_filename(name: &str) -> String {
+    sanitize_with(name, &DEFAULT_REPLACEMENTS)
+}
+
+/// Sanitize filename using a replacement table
+///
+/// The result is in NFC, so built names compare equal to parsed ones
+/// however the filesystem listed them.
+pub fn sanitize_with(name: &str, replacements: &[(char, String)]) -> String {
     let mut result = String::with_capacity(name.len());
     let mut last_was_space = true; // Treat start as after space to trim leading
 
-    for c in name.chars() {
-        // Skip control characters (ASCII 0-31)
-        if c.is_ascii_control() {
-            continue;
-        }
-
+    let mut push = |c: char| {
         // Handle spaces (collapse multiple, trim leading)
         if c == ' ' {
             if !last_was_space {
                 result.push(' ');
                 last_was_space = true;
             }
-            continue;
+            return;
         }
 
         last_was_space = false;
+        result.push(c);
+    };
+
+    for c in normalize_name(name).chars() {
+        // Skip control characters (ASCII 0-31)
+        if c.is_ascii_control() {
+            continue;
+        }
 
-        // Replace invalid characters with fullwidth equivalents
-        if let Some(&(_, replacement)) = REPLACEMENTS.iter().find(|&&(from, _)| from == c) {
-            result.push(replacement);
-        } else {
-            result.push(c);
+        // Replace invalid characters; replacements may add or remove spaces
+        match replacements.iter().find(|(from, _)| *from == c) {
+            Some((_, replacement)) => replacement.chars().for_each(&mut push),
+            None => push(c),
         }
     }
 
@@ -142,26 +510,85 @@ pub fn sanitize_filename(name: &str) -> String {
     result
 }
 
+/// The series tag as it would appear in a human-readable name, when
+/// sanitization changes it
+///
+/// Tags are copied from AniDB-format names, which may hold characters a
+/// readable name cannot (`S1:S2` on Linux becomes `S1：S2`), control
+/// characters or repeated spaces. The altered tag is what converting back
+/// to AniDB format produces; only a revert restores the original.
+pub fn altered_series_tag(tag: &str, replacements: &[(char, String)]) -> Option<String> {
+    let bracketed = format!("[{}]", tag);
+    let sanitized = sanitize_with(&bracketed, replacements);
+    if sanitized == bracketed {
+        return None;
+    }
+    let inner = sanitized
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(&sanitized);
+    Some(inner.to_string())
+}
+
+/// Normalize a name for comparison, mapping fullwidth replacements back to ASCII
+///
+/// Only for comparing: a name built by the tool and one built from the raw
+/// AniDB title compare equal after this, however either was sanitized.
+/// Generated names keep the fullwidth characters.
+#[allow(dead_code)] // Library API, not used by the binary yet
+pub fn comparable_name(name: &str) -> String {
+    sanitize_filename(name)
+        .chars()
+        .map(|c| {
+            REPLACEMENTS
+                .iter()
+                .find(|&&(from, to)| to == c && from != '`')
+                .map_or(c, |&(from, _)| from)
+        })
+        .collect()
+}
+
 /// Single Unicode ellipsis character (3 bytes in UTF-8)
 const ELLIPSIS: &str = "…";
 
 /// Truncate name to fit within max length while preserving required parts
-/// Preserves: series tag, year, anidb suffix
+/// Preserves: series tag, type suffix, year, season marker, anidb suffix
 /// Truncates: title (with ellipsis)
-fn truncate_name(series_tag: Option<&str>, info: &AnimeInfo, max_length: usize) -> String {
-    // Required suffix: [anidb-ID]
-    let suffix = format!("[anidb-{}]", info.anidb_id);
+fn truncate_name(
+    prefix: &str,
+    marker_suffix: Option<&str>,
+    info: &AnimeInfo,
+    config: &NameBuilderConfig,
+) -> String {
+    let max_length = config.max_length;
+
+    // Required suffix: [Unaired] [anidb-ID] or [anidb-ID]
+    let suffix = id_suffix(info, config);
     let suffix_len = suffix.len();
 
-    // Optional prefix: [series_tag]
-    let prefix = series_tag.map(|t| format!("[{}] ", t)).unwrap_or_default();
+    // Optional prefix: [marker] [series_tag]
     let prefix_len = prefix.len();
 
-    // Optional year: (YYYY)
+    // Optional type suffix and year: (Movie) (YYYY)
+    let type_part = type_suffix(info, config)
+        .map(|s| format!(" ({})", sanitize_with(s, &config.replacements)))
+        .unwrap_or_default();
     let year_part = info
         .release_year
-        .map(|y| format!(" ({})", y))
-        .unwrap_or_default();
+        .map(|y| format!("{} ({})", type_part, y))
+        .unwrap_or(type_part);
+    let year_part = match type_label(info, config) {
+        Some(label) => format!("{} ({})", year_part, label),
+        None => year_part,
+    };
+    let year_part = match marker_suffix {
+        Some(marker) => format!(
+            "{} {}",
+            year_part,
+            sanitize_with(marker, &config.replacements)
+        ),
+        None => year_part,
+    };
     let year_len = year_part.len();
 
     // Calculate available space for title
@@ -178,7 +605,7 @@ fn truncate_name(series_tag: Option<&str>, info: &AnimeInfo, max_length: usize)
     let available_for_title = max_length - fixed_len;
 
     // Use only main title when truncating (drop English title)
-    let title = sanitize_filename(&info.title_main);
+    let title = sanitize_with(&info.title_main, &config.replacements);
 
     let truncated_title = if title.len() > available_for_title {
         // Truncate with ellipsis (ellipsis is 3 bytes)
@@ -192,6 +619,24 @@ fn truncate_name(series_tag: Option<&str>, info: &AnimeInfo, max_length: usize)
     format!("{}{}{} {}", prefix, truncated_title, year_part, suffix)
 }
 
+/// Add `token` after the ellipsis of a truncated title, shortening the title
+/// when the name would get longer than `max_length`
+///
+/// `None` when the name has no ellipsis (its title was not cut).
+pub fn disambiguate_truncated(name: &str, token: &str, max_length: usize) -> Option<String> {
+    let at = name.rfind(ELLIPSIS)?;
+    let code = format!(" ~{}", token);
+    let excess = (name.len() + code.len()).saturating_sub(max_length);
+    let title = truncate_string_utf8_safe(&name[..at], at.saturating_sub(excess));
+    Some(format!(
+        "{}{}{}{}",
+        title,
+        ELLIPSIS,
+        code,
+        &name[at + ELLIPSIS.len()..]
+    ))
+}
+
 /// Truncate a string to fit within a byte limit, respecting UTF-8 character boundaries
 /// Also tries to preserve word boundaries when possible
 fn truncate_string_utf8_safe(s: &str, max_bytes: usize) -> String {
@@ -229,10 +674,72 @@ fn truncate_string_utf8_safe(s: &str, max_bytes: usize) -> String {
 }
 
 /// Build an AniDB format directory name
+///
+/// The name ends in the bare id, never in `]`, so it cannot be mistaken for
+/// a human-readable name, even one whose title is a number like `86`.
+#[allow(dead_code)] // Library API, not used by the binary
 pub fn build_anidb_name(series_tag: Option<&str>, anidb_id: u32) -> String {
-    match series_tag {
-        Some(tag) => format!("[{}] {}", tag, anidb_id),
+    build_anidb_name_with_suffix(series_tag, anidb_id, None)
+}
+
+/// Build an AniDB format directory name keeping a season or part marker
+/// after the id, e.g. `[AS0] 12345 Part 2`
+pub fn build_anidb_name_with_suffix(
+    series_tag: Option<&str>,
+    anidb_id: u32,
+    suffix: Option<&str>,
+) -> String {
+    let id = match suffix {
+        Some(suffix) => format!("{} {}", anidb_id, sanitize_filename(suffix)),
         None => anidb_id.to_string(),
+    };
+    let name = match series_tag {
+        Some(tag) => format!("[{}] {}", tag, id),
+        None => id,
+    };
+    debug_assert!(
+        matches!(parse_directory_name(&name), Ok(ParsedDirectory::AniDb(_))),
+        "{} does not parse as an AniDB name",
+        name
+    );
+    name
+}
+
+// Defined here rather than in the parser, which does not depend on `rename`
+impl ParsedDirectory {
+    /// The canonical name of the directory, rebuilt from the parsed fields
+    ///
+    /// AniDB names are rebuilt like [`build_anidb_name`], keeping the season
+    /// or part marker. Readable names are rebuilt from their tags, titles,
+    /// year (or year range), marker and id and sanitized with
+    /// [`sanitize_filename`]; a trailing episode count and an `[Unaired]` tag
+    /// are left out. Nothing is fetched, so the titles are the ones in the
+    /// name. A directory whose `original_name` equals this is already
+    /// canonical.
+    #[allow(dead_code)] // Library API, not used by the binary
+    pub fn to_display_name(&self) -> String {
+        match self {
+            ParsedDirectory::AniDb(f) => build_anidb_name_with_suffix(
+                f.series_tag.as_deref(),
+                f.anidb_id,
+                f.suffix.as_deref(),
+            ),
+            ParsedDirectory::HumanReadable(f) => {
+                let mut parts: Vec<String> = Vec::new();
+                for tag in f.marker.iter().chain(&f.series_tag) {
+                    parts.push(format!("[{}]", tag));
+                }
+                parts.push(build_title_part(&f.title_jp, f.title_en.as_deref()));
+                match (f.release_year, f.end_year) {
+                    (Some(year), Some(end_year)) => parts.push(format!("({}-{})", year, end_year)),
+                    (Some(year), None) => parts.push(format!("({})", year)),
+                    _ => {}
+                }
+                parts.extend(f.suffix.clone());
+                parts.push(format!("[anidb-{}]", f.anidb_id));
+                sanitize_filename(&parts.join(" "))
+            }
+        }
     }
 }
 
@@ -251,6 +758,11 @@ mod tests {
             title_main: title_main.to_string(),
             title_en: title_en.map(|s| s.to_string()),
             release_year: year,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         }
     }
 
@@ -457,6 +969,122 @@ mod tests {
         assert_eq!(result, "'Hello' 'World'");
     }
 
+    // ============ Comparable Names ============
+
+    #[test]
+    fn test_comparable_name_maps_fullwidth_back() {
+        assert_eq!(
+            comparable_name("Title： Part 1／2 ＜Special＞"),
+            "Title: Part 1/2 <Special>"
+        );
+        assert_eq!(
+            comparable_name("Title: Part 1/2 <Special>"),
+            "Title: Part 1/2 <Special>"
+        );
+        // Backticks and quotes both become the quote they are generated as
+        assert_eq!(comparable_name("It`s"), comparable_name("It's"));
+    }
+
+    #[test]
+    fn test_built_name_compares_equal_to_fresh_build() {
+        let titles = [
+            (
+                "Re:Zero kara Hajimeru Isekai Seikatsu",
+                Some("Re:Zero - Starting Life"),
+            ),
+            (
+                "Fate/stay night",
+                Some("Fate/stay night: Unlimited Blade Works"),
+            ),
+            ("Nani? \"Kore\" <desu> | ka*", None),
+            ("It`s  a   test", Some("What?")),
+        ];
+
+        for (main, en) in titles {
+            let info = AnimeInfo {
+                anidb_id: 42,
+                title_main: main.to_string(),
+                title_en: en.map(str::to_string),
+                release_year: Some(2016),
+                english_fallback: false,
+                restricted: false,
+                unaired: false,
+                anime_type: None,
+                episode_count: None,
+            };
+            let config = NameBuilderConfig::default();
+            let on_disk = build_human_readable_name(Some("S"), &info, &config).name;
+            let fresh = build_human_readable_name(Some("S"), &info, &config).name;
+
+            assert_eq!(comparable_name(&on_disk), comparable_name(&fresh));
+
+            // The title parsed back from disk matches the raw API title
+            match crate::parser::parse_directory_name(&on_disk).unwrap() {
+                crate::parser::ParsedDirectory::HumanReadable(f) => {
+                    assert_eq!(comparable_name(&f.title_jp), comparable_name(main));
+                }
+                _ => panic!("Expected human-readable format"),
+            }
+        }
+    }
+
+    // ============ Custom Replacement Table ============
+
+    #[test]
+    fn test_custom_replacements_round_trip() {
+        let replacements = replacement_table(&[
+            (':', " -".to_string()),
+            ('/', "-".to_string()),
+            ('?', String::new()),
+        ])
+        .unwrap();
+        let config = NameBuilderConfig {
+            replacements,
+            ..Default::default()
+        };
+        let info = create_test_info(
+            5,
+            "Re:Zero kara Hajimeru Isekai Seikatsu",
+            Some("Fate/Zero: What?"),
+            Some(2016),
+        );
+
+        let result = build_human_readable_name(Some("S"), &info, &config);
+        assert_eq!(
+            result.name,
+            "[S] Re -Zero kara Hajimeru Isekai Seikatsu ／ Fate-Zero - What (2016) [anidb-5]"
+        );
+        assert_eq!(
+            sanitize_with(&result.name, &config.replacements),
+            result.name
+        );
+
+        match crate::parser::parse_directory_name(&result.name).unwrap() {
+            crate::parser::ParsedDirectory::HumanReadable(f) => {
+                assert_eq!(f.series_tag, Some("S".to_string()));
+                assert_eq!(f.title_jp, "Re -Zero kara Hajimeru Isekai Seikatsu");
+                assert_eq!(f.title_en, Some("Fate-Zero - What".to_string()));
+                assert_eq!(f.release_year, Some(2016));
+                assert_eq!(f.anidb_id, 5);
+            }
+            _ => panic!("Expected human-readable format"),
+        }
+    }
+
+    #[test]
+    fn test_replacement_table_rejects_unsafe_entries() {
+        // Replacement containing a forbidden character
+        assert!(replacement_table(&[(':', "?".to_string())]).is_err());
+        // Replacement containing the title separator
+        assert!(replacement_table(&[('!', " ／ ".to_string())]).is_err());
+        // Structural characters can't be replaced
+        assert!(replacement_table(&[('[', "(".to_string())]).is_err());
+        // Overrides replace the default entry for their character
+        let table = replacement_table(&[('/', "-".to_string())]).unwrap();
+        assert_eq!(table.len(), REPLACEMENTS.len());
+        assert!(table.contains(&('/', "-".to_string())));
+    }
+
     // ============ Multiple Replacements ============
 
     #[test]
@@ -577,7 +1205,10 @@ mod tests {
         let long_title = "A".repeat(300);
         let info = create_test_info(1, &long_title, None, Some(2020));
 
-        let config = NameBuilderConfig { max_length: 100 };
+        let config = NameBuilderConfig {
+            max_length: 100,
+            ..Default::default()
+        };
         let result = build_human_readable_name(None, &info, &config);
 
         assert!(result.truncated);
@@ -592,7 +1223,10 @@ mod tests {
         let jp_title = "日本語タイトルがとても長い名前です";
         let info = create_test_info(1, jp_title, None, Some(2020));
 
-        let config = NameBuilderConfig { max_length: 50 };
+        let config = NameBuilderConfig {
+            max_length: 50,
+            ..Default::default()
+        };
         let result = build_human_readable_name(None, &info, &config);
 
         assert!(result.truncated);
@@ -608,7 +1242,10 @@ mod tests {
         let long_title = "A".repeat(300);
         let info = create_test_info(999, &long_title, None, Some(2020));
 
-        let config = NameBuilderConfig { max_length: 80 };
+        let config = NameBuilderConfig {
+            max_length: 80,
+            ..Default::default()
+        };
         let result = build_human_readable_name(Some("MySeries"), &info, &config);
 
         assert!(result.truncated);
@@ -618,12 +1255,400 @@ mod tests {
         assert!(result.name.len() <= 80);
     }
 
+    #[test]
+    fn test_trailing_year_in_title_not_repeated() {
+        let info = create_test_info(1, "Fullmetal Alchemist (2009)", None, Some(2009));
+        let config = NameBuilderConfig::default();
+
+        let result = build_human_readable_name(None, &info, &config);
+
+        assert_eq!(result.name, "Fullmetal Alchemist (2009) [anidb-1]");
+    }
+
+    #[test]
+    fn test_trailing_year_used_when_api_has_none() {
+        let info = create_test_info(1, "Fullmetal Alchemist (2009)", None, None);
+        let config = NameBuilderConfig::default();
+
+        let result = build_human_readable_name(None, &info, &config);
+
+        assert_eq!(result.name, "Fullmetal Alchemist (2009) [anidb-1]");
+    }
+
+    #[test]
+    fn test_api_year_wins_over_trailing_year() {
+        let info = create_test_info(1, "Hagane no Renkinjutsushi (2009)", None, Some(2010));
+        let config = NameBuilderConfig::default();
+
+        let result = build_human_readable_name(None, &info, &config);
+
+        assert_eq!(result.name, "Hagane no Renkinjutsushi (2010) [anidb-1]");
+    }
+
+    #[test]
+    fn test_trailing_year_in_both_titles() {
+        let info = create_test_info(
+            1,
+            "Hagane no Renkinjutsushi (2009)",
+            Some("Fullmetal Alchemist (2009)"),
+            Some(2009),
+        );
+        let config = NameBuilderConfig::default();
+
+        let result = build_human_readable_name(None, &info, &config);
+
+        assert_eq!(
+            result.name,
+            "Hagane no Renkinjutsushi ／ Fullmetal Alchemist (2009) [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_year_in_middle_of_title_kept() {
+        let info = create_test_info(1, "Tokyo 2040 Chronicles", None, Some(2040));
+        let config = NameBuilderConfig::default();
+
+        let result = build_human_readable_name(None, &info, &config);
+
+        // Year already present in the title, not appended again
+        assert_eq!(result.name, "Tokyo 2040 Chronicles [anidb-1]");
+    }
+
+    #[test]
+    fn test_year_range_in_title_not_duplicated() {
+        let config = NameBuilderConfig::default();
+        for (title, year) in [
+            ("Monster (2004-2005)", 2004),
+            ("Monster (2004-2005)", 2005),
+            ("Monster (2003–2006)", 2004),
+        ] {
+            let info = create_test_info(2038, title, None, Some(year));
+            let name = build_human_readable_name(None, &info, &config).name;
+            assert_eq!(name, format!("{} [anidb-2038]", title));
+
+            // The range parses back as years, and rebuilds unchanged
+            let readable = compute_destination(&name, &info, &config).unwrap().name;
+            assert_eq!(readable, name);
+            let anidb = compute_destination("2038", &info, &config).unwrap().name;
+            assert_eq!(anidb, name);
+        }
+
+        // A range not including the year gets the year
+        let info = create_test_info(2038, "Monster (2010-2011)", None, Some(2004));
+        assert_eq!(
+            build_human_readable_name(None, &info, &config).name,
+            "Monster (2010-2011) (2004) [anidb-2038]"
+        );
+    }
+
+    #[test]
+    fn test_truncation_strips_trailing_year() {
+        let long_title = format!("{} (2009)", "A".repeat(300));
+        let info = create_test_info(1, &long_title, None, Some(2009));
+
+        let config = NameBuilderConfig {
+            max_length: 60,
+            ..Default::default()
+        };
+        let result = build_human_readable_name(None, &info, &config);
+
+        assert!(result.truncated);
+        assert_eq!(result.name.matches("(2009)").count(), 1);
+    }
+
+    #[test]
+    fn test_restricted_marker_not_doubled() {
+        let mut info = create_test_info(1, "Title", None, None);
+        info.restricted = true;
+        let config = NameBuilderConfig {
+            restricted_marker: Some("18+".to_string()),
+            ..Default::default()
+        };
+
+        let tagged = build_human_readable_name(Some("X"), &info, &config);
+        let already = build_human_readable_name(Some("18+"), &info, &config);
+
+        assert_eq!(tagged.name, "[18+] [X] Title [anidb-1]");
+        assert_eq!(already.name, "[18+] Title [anidb-1]");
+    }
+
+    #[test]
+    fn test_unaired_tag() {
+        let mut info = create_test_info(18999, "Next Season", None, Some(2027));
+        info.unaired = true;
+        let config = NameBuilderConfig {
+            unaired_tag: true,
+            ..Default::default()
+        };
+
+        let tagged = build_human_readable_name(Some("AS0"), &info, &config);
+        assert_eq!(
+            tagged.name,
+            "[AS0] Next Season (2027) [Unaired] [anidb-18999]"
+        );
+
+        let untagged = build_human_readable_name(Some("AS0"), &info, &NameBuilderConfig::default());
+        assert_eq!(untagged.name, "[AS0] Next Season (2027) [anidb-18999]");
+
+        let truncated = build_human_readable_name(
+            None,
+            &info,
+            &NameBuilderConfig {
+                max_length: 40,
+                ..config
+            },
+        );
+        assert!(truncated.truncated);
+        assert!(truncated.name.ends_with("(2027) [Unaired] [anidb-18999]"));
+        assert!(truncated.name.len() <= 40);
+    }
+
+    fn typed_info(anime_type: AnimeType, title: &str, year: u16) -> AnimeInfo {
+        let mut info = create_test_info(1, title, None, Some(year));
+        info.anime_type = Some(anime_type);
+        info
+    }
+
+    #[test]
+    fn test_movie_always_has_year() {
+        let config = NameBuilderConfig::default();
+
+        // The year in the title no longer stands in for it
+        let movie = typed_info(AnimeType::Movie, "Detective Conan 2016", 2016);
+        let series = typed_info(AnimeType::TvSeries, "Detective Conan 2016", 2016);
+        assert_eq!(
+            build_human_readable_name(None, &movie, &config).name,
+            "Detective Conan 2016 (2016) [anidb-1]"
+        );
+        assert_eq!(
+            build_human_readable_name(None, &series, &config).name,
+            "Detective Conan 2016 [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_type_suffixes() {
+        let config = NameBuilderConfig {
+            type_suffixes: vec![
+                (AnimeType::Movie, "Movie".to_string()),
+                (AnimeType::Ova, "OVA".to_string()),
+            ],
+            ..Default::default()
+        };
+        let name = |anime_type| {
+            build_human_readable_name(
+                Some("AS0"),
+                &typed_info(anime_type, "Kimi no Na wa.", 2016),
+                &config,
+            )
+            .name
+        };
+
+        assert_eq!(
+            name(AnimeType::Movie),
+            "[AS0] Kimi no Na wa. (Movie) (2016) [anidb-1]"
+        );
+        assert_eq!(
+            name(AnimeType::Ova),
+            "[AS0] Kimi no Na wa. (OVA) (2016) [anidb-1]"
+        );
+        assert_eq!(
+            name(AnimeType::TvSeries),
+            "[AS0] Kimi no Na wa. (2016) [anidb-1]"
+        );
+
+        // Unknown type: no suffix
+        let mut unknown = typed_info(AnimeType::Movie, "Kimi no Na wa.", 2016);
+        unknown.anime_type = None;
+        assert_eq!(
+            build_human_readable_name(None, &unknown, &config).name,
+            "Kimi no Na wa. (2016) [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_type_suffix_survives_truncation() {
+        let info = typed_info(AnimeType::Movie, &"A".repeat(300), 2016);
+        let config = NameBuilderConfig {
+            max_length: 50,
+            type_suffixes: vec![(AnimeType::Movie, "Movie".to_string())],
+            ..Default::default()
+        };
+
+        let result = build_human_readable_name(None, &info, &config);
+
+        assert!(result.truncated);
+        assert!(result.name.ends_with("… (Movie) (2016) [anidb-1]"));
+        assert!(result.name.len() <= 50);
+    }
+
+    #[test]
+    fn test_include_type() {
+        let config = NameBuilderConfig {
+            include_type: true,
+            ..Default::default()
+        };
+        let name = |info: &AnimeInfo| build_human_readable_name(None, info, &config).name;
+
+        assert_eq!(
+            name(&typed_info(AnimeType::Ova, "Hellsing Ultimate", 2006)),
+            "Hellsing Ultimate (2006) (OVA) [anidb-1]"
+        );
+        assert_eq!(
+            name(&typed_info(AnimeType::TvSeries, "Cowboy Bebop", 1998)),
+            "Cowboy Bebop (1998) (TV Series) [anidb-1]"
+        );
+        // Already in the title
+        assert_eq!(
+            name(&typed_info(AnimeType::Ova, "Hellsing Ultimate (ova)", 2006)),
+            "Hellsing Ultimate (ova) (2006) [anidb-1]"
+        );
+        // Unknown type
+        let mut untyped = typed_info(AnimeType::Ova, "Hellsing Ultimate", 2006);
+        untyped.anime_type = None;
+        assert_eq!(name(&untyped), "Hellsing Ultimate (2006) [anidb-1]");
+
+        // A type suffix takes its place
+        let config = NameBuilderConfig {
+            type_suffixes: vec![(AnimeType::Movie, "Film".to_string())],
+            ..config
+        };
+        assert_eq!(
+            build_human_readable_name(
+                None,
+                &typed_info(AnimeType::Movie, "Kimi no Na wa.", 2016),
+                &config
+            )
+            .name,
+            "Kimi no Na wa. (Film) (2016) [anidb-1]"
+        );
+
+        // Kept when truncating, and found again by the parser
+        let long = NameBuilderConfig {
+            max_length: 50,
+            ..config
+        };
+        let result = build_human_readable_name(
+            None,
+            &typed_info(AnimeType::Ova, &"A".repeat(300), 2011),
+            &long,
+        );
+        assert!(result.truncated);
+        assert!(result.name.ends_with("… (2011) (OVA) [anidb-1]"));
+        assert!(result.name.len() <= 50);
+        match parse_directory_name(&result.name).unwrap() {
+            ParsedDirectory::HumanReadable(f) => assert_eq!(f.release_year, Some(2011)),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_destination_normalizes_id_tag_case() {
+        let info = create_test_info(12345, "Naruto", None, Some(2002));
+        let config = NameBuilderConfig::default();
+
+        for name in ["NARUTO (2002) [ANIDB-12345]", "Naruto (2002) [AniDB-12345]"] {
+            let result = compute_destination(name, &info, &config).unwrap();
+            assert_eq!(result.name, "Naruto (2002) [anidb-12345]");
+        }
+    }
+
+    #[test]
+    fn test_typed_names_parse_back() {
+        let config = NameBuilderConfig {
+            type_suffixes: vec![(AnimeType::Movie, "Movie".to_string())],
+            ..Default::default()
+        };
+        let name = build_human_readable_name(
+            Some("AS0"),
+            &typed_info(AnimeType::Movie, "Kimi no Na wa.", 2016),
+            &config,
+        )
+        .name;
+
+        let parsed = parse_directory_name(&name).unwrap();
+        assert_eq!(parsed.anidb_id(), 1);
+        assert_eq!(parsed.series_tag(), Some("AS0"));
+    }
+
+    #[test]
+    fn test_altered_series_tag() {
+        for tag in ["AS0", "18+", "My Favorite Series", "S1／S2", " padded "] {
+            assert_eq!(
+                altered_series_tag(tag, &DEFAULT_REPLACEMENTS),
+                None,
+                "{:?}",
+                tag
+            );
+        }
+        for (tag, altered) in [
+            ("S1:S2", "S1：S2"),
+            ("What?", "What？"),
+            ("A  B", "A B"),
+            ("Tab\tbed", "Tabbed"),
+            ("\"Quoted\"", "＂Quoted＂"),
+        ] {
+            assert_eq!(
+                altered_series_tag(tag, &DEFAULT_REPLACEMENTS).as_deref(),
+                Some(altered),
+                "{:?}",
+                tag
+            );
+        }
+        let custom = replacement_table(&[(':', "-".to_string())]).unwrap();
+        assert_eq!(
+            altered_series_tag("S1:S2", &custom).as_deref(),
+            Some("S1-S2")
+        );
+    }
+
+    #[test]
+    fn test_hostile_tags_round_trip_only_when_unaltered() {
+        let info = create_test_info(12345, "Title", None, Some(2020));
+        let config = NameBuilderConfig::default();
+
+        for tag in ["AS0", "S1:S2", "A  B", "Who?", "S1／S2"] {
+            let original = build_anidb_name(Some(tag), 12345);
+            let readable = build_human_readable_name(Some(tag), &info, &config).name;
+            let parsed = parse_directory_name(&readable).unwrap();
+            let back = build_anidb_name(parsed.series_tag(), parsed.anidb_id());
+
+            let altered = altered_series_tag(tag, &config.replacements);
+            assert_eq!(
+                back == original,
+                altered.is_none(),
+                "{:?} -> {:?}",
+                tag,
+                back
+            );
+            if let Some(altered) = altered {
+                assert_eq!(parsed.series_tag(), Some(altered.as_str()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_drop_series_tag() {
+        let info = create_test_info(999, "Title", None, Some(2020));
+
+        let config = NameBuilderConfig {
+            drop_series_tag: true,
+            ..Default::default()
+        };
+        let result = build_human_readable_name(Some("MySeries"), &info, &config);
+
+        assert_eq!(result.name, "Title (2020) [anidb-999]");
+    }
+
     #[test]
     fn test_truncation_preserves_year() {
         let long_title = "A".repeat(300);
         let info = create_test_info(1, &long_title, None, Some(1999));
 
-        let config = NameBuilderConfig { max_length: 60 };
+        let config = NameBuilderConfig {
+            max_length: 60,
+            ..Default::default()
+        };
         let result = build_human_readable_name(None, &info, &config);
 
         assert!(result.truncated);
@@ -631,12 +1656,94 @@ mod tests {
         assert!(result.name.contains("[anidb-1]"));
     }
 
+    #[test]
+    fn test_decomposed_input_builds_nfc_names() {
+        let info = create_test_info(123, "Poke\u{301}mon", None, Some(1997));
+        let config = NameBuilderConfig::default();
+
+        let result = build_human_readable_name(Some("Se\u{301}rie"), &info, &config);
+        assert_eq!(result.name, "[S\u{e9}rie] Pok\u{e9}mon (1997) [anidb-123]");
+
+        // A decomposed name on disk rebuilds to the same name
+        let on_disk = "[Se\u{301}rie] Poke\u{301}mon (1997) [anidb-123]";
+        let rebuilt = compute_destination(on_disk, &info, &config).unwrap().name;
+        assert_eq!(rebuilt, result.name);
+        assert_eq!(comparable_name(on_disk), comparable_name(&result.name));
+    }
+
+    #[test]
+    fn test_suffix_round_trips() {
+        let info = create_test_info(99, "Shingeki no Kyojin", None, Some(2017));
+        let config = NameBuilderConfig::default();
+
+        for (original, readable) in [
+            (
+                "[AS0] 99 S2",
+                "[AS0] Shingeki no Kyojin (2017) S2 [anidb-99]",
+            ),
+            ("99 Part 2", "Shingeki no Kyojin (2017) Part 2 [anidb-99]"),
+            ("99", "Shingeki no Kyojin (2017) [anidb-99]"),
+        ] {
+            let built = compute_destination(original, &info, &config).unwrap().name;
+            assert_eq!(built, readable);
+            let parsed = parse_directory_name(&built).unwrap();
+            let back = build_anidb_name_with_suffix(
+                parsed.series_tag(),
+                parsed.anidb_id(),
+                parsed.suffix(),
+            );
+            assert_eq!(back, original);
+        }
+
+        // A marker typed before the year survives the trip, after the year
+        let parsed = parse_directory_name("Shingeki no Kyojin S2 (2017) [anidb-99]").unwrap();
+        let back =
+            build_anidb_name_with_suffix(parsed.series_tag(), parsed.anidb_id(), parsed.suffix());
+        assert_eq!(back, "99 S2");
+        assert_eq!(
+            compute_destination(&back, &info, &config).unwrap().name,
+            "Shingeki no Kyojin (2017) S2 [anidb-99]"
+        );
+
+        // Without a year the suffix still comes right before the tags
+        let info = create_test_info(99, "Title", None, None);
+        let built = compute_destination("99 Season 2", &info, &config)
+            .unwrap()
+            .name;
+        assert_eq!(built, "Title Season 2 [anidb-99]");
+        assert_eq!(
+            parse_directory_name(&built).unwrap().suffix(),
+            Some("Season 2")
+        );
+    }
+
+    #[test]
+    fn test_truncation_preserves_suffix() {
+        let info = create_test_info(1, &"A".repeat(300), None, Some(1999));
+        let config = NameBuilderConfig {
+            max_length: 60,
+            ..Default::default()
+        };
+        let result = build_human_readable_name_with_suffix(None, Some("Part 2"), &info, &config);
+
+        assert!(result.truncated);
+        assert!(result.name.len() <= 60);
+        assert!(
+            result.name.ends_with("… (1999) Part 2 [anidb-1]"),
+            "{}",
+            result.name
+        );
+    }
+
     #[test]
     fn test_truncation_word_boundary() {
         let title = "The Quick Brown Fox Jumps Over The Lazy Dog";
         let info = create_test_info(1, title, None, None);
 
-        let config = NameBuilderConfig { max_length: 40 };
+        let config = NameBuilderConfig {
+            max_length: 40,
+            ..Default::default()
+        };
         let result = build_human_readable_name(None, &info, &config);
 
         assert!(result.truncated);
@@ -668,6 +1775,137 @@ mod tests {
 
     // ============ AniDB Name Building ============
 
+    #[test]
+    fn test_compute_destination_matches_builder() {
+        let info = create_test_info(5, "Naruto", None, Some(2002));
+        let config = NameBuilderConfig::default();
+
+        let from_anidb = compute_destination("[AS0] 5", &info, &config).unwrap();
+        let from_readable =
+            compute_destination("[18+] [AS0] Naruto (2002) [anidb-5]", &info, &config).unwrap();
+        let built = build_human_readable_name(Some("AS0"), &info, &config);
+
+        assert_eq!(from_anidb.name, built.name);
+        // The marker follows `info`, only the series tag is carried over
+        assert_eq!(from_readable.name, built.name);
+    }
+
+    fn template_config(template: &str) -> NameBuilderConfig {
+        NameBuilderConfig {
+            template: Some(NameTemplate::parse(template).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_template_layout() {
+        let config = template_config("{title_jp} [{year}] {title_en} {tag} [anidb-{id}]");
+        let info = create_test_info(1, "Kauboi: Bibappu", Some("Cowboy Bebop"), Some(1998));
+        assert_eq!(
+            build_human_readable_name(Some("AS0"), &info, &config).name,
+            "Kauboi： Bibappu [1998] Cowboy Bebop [AS0] [anidb-1]"
+        );
+
+        // Empty fields leave no gaps; the year rule of the built-in layout applies
+        let info = create_test_info(2, "Naruto 2002", None, Some(2002));
+        assert_eq!(
+            build_human_readable_name(None, &info, &config).name,
+            "Naruto 2002 [anidb-2]"
+        );
+    }
+
+    #[test]
+    fn test_template_episode_count() {
+        let config = template_config("{title_jp} ({year}) [anidb-{id}] [{episodes} eps]");
+        let mut info = create_test_info(1, "Cowboy Bebop", None, Some(1998));
+        info.episode_count = Some(26);
+        let name = build_human_readable_name(None, &info, &config).name;
+        assert_eq!(name, "Cowboy Bebop (1998) [anidb-1] [26 eps]");
+        // Also the built-in layout's episode count tag
+        match parse_directory_name(&name).unwrap() {
+            ParsedDirectory::HumanReadable(f) => assert_eq!(f.episode_count, Some(26)),
+            other => panic!("{:?}", other),
+        }
+
+        info.episode_count = None;
+        assert_eq!(
+            build_human_readable_name(None, &info, &config).name,
+            "Cowboy Bebop (1998) [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_template_truncation() {
+        let config = NameBuilderConfig {
+            max_length: 40,
+            ..template_config("{tag} {title_jp} ／ {title_en} ({year}) [anidb-{id}]")
+        };
+        let info = create_test_info(
+            1,
+            "A Very Long Main Title That Does Not Fit",
+            Some("English"),
+            Some(1998),
+        );
+        let result = build_human_readable_name(Some("AS0"), &info, &config);
+
+        assert!(result.truncated);
+        assert!(result.name.len() <= 40, "{}", result.name);
+        assert!(result.name.starts_with("[AS0] A Very"), "{}", result.name);
+        assert!(
+            result.name.ends_with("… (1998) [anidb-1]"),
+            "{}",
+            result.name
+        );
+    }
+
+    #[test]
+    fn test_template_names_parse_back() {
+        let config = template_config("[anidb-{id}] {title_jp} ({year}) {tag}");
+        let info = create_test_info(5, "Naruto", None, Some(2002));
+
+        let readable = compute_destination("[AS0] 5", &info, &config).unwrap().name;
+        assert_eq!(readable, "[anidb-5] Naruto (2002) [AS0]");
+        // Renaming again keeps the tag found through the template
+        assert_eq!(
+            compute_destination(&readable, &info, &config).unwrap().name,
+            readable
+        );
+        // Names in the built-in layout are still recognized
+        assert_eq!(
+            compute_destination("[AS0] Naruto (2002) [anidb-5]", &info, &config)
+                .unwrap()
+                .name,
+            readable
+        );
+    }
+
+    #[test]
+    fn test_compute_destination_writes_canonical_id_tag() {
+        let info = create_test_info(5, "Naruto", None, Some(2002));
+        let config = NameBuilderConfig::default();
+        for source in [
+            "[AS0] Naruto (2002) (anidb-5)",
+            "[AS0] Naruto (2002) [aid=5]",
+            "[AS0] Naruto (2002) [anidb 5]",
+            "[AS0] Naruto (2002) [ANIDB-5] [220 eps]",
+        ] {
+            assert_eq!(
+                compute_destination(source, &info, &config).unwrap().name,
+                "[AS0] Naruto (2002) [anidb-5]",
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_destination_rejects_unparseable_name() {
+        let info = create_test_info(5, "Naruto", None, None);
+        let err = compute_destination("Naruto", &info, &NameBuilderConfig::default());
+
+        assert!(matches!(err, Err(ParseError::UnrecognizedFormat(_))));
+    }
+
     #[test]
     fn test_build_anidb_name_with_series() {
         let result = build_anidb_name(Some("AS0"), 12345);
@@ -679,4 +1917,101 @@ mod tests {
         let result = build_anidb_name(None, 12345);
         assert_eq!(result, "12345");
     }
+
+    #[test]
+    fn test_numeric_titles_round_trip() {
+        let config = NameBuilderConfig::default();
+        for (title, year) in [
+            ("86", Some(2021)),
+            ("009-1", Some(2006)),
+            ("12345", None),
+            ("12345", Some(2020)),
+            ("2020", None),
+        ] {
+            let info = create_test_info(12345, title, None, year);
+            for series_tag in [None, Some("AS0")] {
+                let readable = build_human_readable_name(series_tag, &info, &config).name;
+
+                // The [anidb-ID] suffix makes it human-readable, whatever the title
+                match parse_directory_name(&readable).unwrap() {
+                    ParsedDirectory::HumanReadable(f) => {
+                        assert_eq!(f.title_jp, title, "{}", readable);
+                        assert_eq!(f.release_year, year);
+                        assert_eq!(f.anidb_id, 12345);
+                        assert_eq!(f.series_tag.as_deref(), series_tag);
+                    }
+                    _ => panic!("{} should be human-readable", readable),
+                }
+
+                // Back to AniDB format, and from there to the same readable name
+                let anidb = build_anidb_name(series_tag, 12345);
+                match parse_directory_name(&anidb).unwrap() {
+                    ParsedDirectory::AniDb(f) => {
+                        assert_eq!(f.anidb_id, 12345);
+                        assert_eq!(f.series_tag.as_deref(), series_tag);
+                    }
+                    _ => panic!("{} should be in AniDB format", anidb),
+                }
+                assert_eq!(
+                    compute_destination(&anidb, &info, &config).unwrap().name,
+                    readable
+                );
+            }
+        }
+    }
+
+    fn display_name(name: &str) -> String {
+        parse_directory_name(name).unwrap().to_display_name()
+    }
+
+    #[test]
+    fn test_canonical_names_display_as_themselves() {
+        for name in [
+            "12345",
+            "[AS0] 12345",
+            "[AS0] 12345 Part 2",
+            "Cowboy Bebop (1998) [anidb-23]",
+            "[AS0] Shingeki no Kyojin (2017) S2 [anidb-99]",
+            "Fate／stay night (2006) [anidb-3]",
+            "Shingeki no Kyojin ／ Attack on Titan (2013) [anidb-9541]",
+            "Monster (2004-2005) [anidb-2038]",
+            "[18+] [AS0] Title (2020) [anidb-5]",
+        ] {
+            assert_eq!(display_name(name), name);
+        }
+    }
+
+    #[test]
+    fn test_display_name_of_fullwidth_slash_titles() {
+        // A hand-typed ASCII slash becomes the fullwidth separator
+        assert_eq!(
+            display_name("Shingeki no Kyojin / Attack on Titan (2013) [anidb-9541]"),
+            "Shingeki no Kyojin ／ Attack on Titan (2013) [anidb-9541]"
+        );
+        // An English title equal to the main title is not repeated
+        assert_eq!(
+            display_name("Naruto ／ Naruto (2002) [anidb-1]"),
+            "Naruto (2002) [anidb-1]"
+        );
+    }
+
+    #[test]
+    fn test_display_name_without_year() {
+        assert_eq!(display_name("Some Title [anidb-7]"), "Some Title [anidb-7]");
+        assert_eq!(display_name("[anidb-7]"), "[anidb-7]");
+        assert_eq!(display_name("Some Title (anidb-7)"), "Some Title [anidb-7]");
+    }
+
+    #[test]
+    fn test_display_name_is_sanitized() {
+        assert_eq!(
+            display_name("Re:Zero (2016) [anidb-11]"),
+            "Re：Zero (2016) [anidb-11]"
+        );
+        assert_eq!(
+            display_name("What?  Title (2016) [anidb-11] [12 eps]"),
+            "What？ Title (2016) [anidb-11]"
+        );
+        assert_eq!(display_name("[AS0]  12345 "), "[AS0] 12345");
+    }
 }
@@ -0,0 +1,194 @@
+//! Best-effort check for files held open inside source directories.
+//!
+//! Renaming a directory under a program that has files open inside it (a
+//! torrent client seeding, a media server streaming) succeeds, but the
+//! program keeps using the old path. On Linux the open descriptors of every
+//! readable process are listed from `/proc/*/fd`; processes of other users
+//! are only visible with enough privileges, so the check can miss files.
+//! Other platforms have no check.
+
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::progress::ProgressObserver;
+
+use super::conflict::ConflictResolution;
+use super::error::RenameError;
+use super::types::RenameResult;
+
+/// Whether `path` is `dir` or lies inside it
+///
+/// Compares whole components, so `Show 2/ep1.mkv` is not inside `Show`.
+pub fn is_under(path: &Path, dir: &Path) -> bool {
+    path.starts_with(dir)
+}
+
+/// Number of `open` paths inside `dir`
+pub fn count_open_under(open: &[PathBuf], dir: &Path) -> usize {
+    open.iter().filter(|path| is_under(path, dir)).count()
+}
+
+/// Handle source directories with open files according to `policy`
+///
+/// With `Skip` such directories are left untouched (recorded in
+/// `result.busy_skipped`), with `Abort` the first one fails the run, and
+/// otherwise they are only reported. Unsupported platforms warn and
+/// proceed.
+pub fn check_open_files(
+    result: &mut RenameResult,
+    policy: ConflictResolution,
+    progress: &mut dyn ProgressObserver,
+) -> Result<(), RenameError> {
+    let Some(open) = platform::open_paths() else {
+        warn!("Open file check not supported on this platform");
+        progress.warn("Cannot check for open files on this platform, proceeding without the check");
+        return Ok(());
+    };
+    info!(
+        "Checking {} open files against source directories",
+        open.len()
+    );
+
+    let mut busy = Vec::new();
+    for op in result.operations.iter().filter(|op| !op.is_skipped()) {
+        let count = count_open_under(&open, &op.source_path);
+        if count > 0 {
+            busy.push((op.source_name.clone(), count));
+        }
+    }
+
+    for (name, count) in busy {
+        match policy {
+            ConflictResolution::Abort => {
+                return Err(RenameError::DirectoryInUse { name, files: count });
+            }
+            ConflictResolution::Skip => {
+                warn!("{} has {} open file(s), skipping", name, count);
+                progress.warn(&format!(
+                    "{} has {} open file(s): left untouched",
+                    name, count
+                ));
+                result.operations.retain(|op| op.source_name != name);
+                result.busy_skipped.push(name);
+            }
+            ConflictResolution::Suffix | ConflictResolution::Aside => {
+                warn!("{} has {} open file(s)", name, count);
+                progress.warn(&format!(
+                    "{} has {} open file(s): renaming anyway",
+                    name, count
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Targets of every readable `/proc/<pid>/fd/<n>` link
+    pub fn open_paths() -> Option<Vec<PathBuf>> {
+        let processes = fs::read_dir("/proc").ok()?;
+        let mut paths = Vec::new();
+
+        for process in processes.filter_map(|e| e.ok()) {
+            let is_pid = process
+                .file_name()
+                .to_str()
+                .is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit()));
+            if !is_pid {
+                continue;
+            }
+            // Processes of other users are unreadable without privileges
+            let Ok(fds) = fs::read_dir(process.path().join("fd")) else {
+                continue;
+            };
+            paths.extend(
+                fds.filter_map(|fd| fd.ok())
+                    .filter_map(|fd| fs::read_link(fd.path()).ok())
+                    .filter(|target| target.is_absolute()),
+            );
+        }
+
+        Some(paths)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::path::PathBuf;
+
+    pub fn open_paths() -> Option<Vec<PathBuf>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_under_matches_whole_components() {
+        let dir = Path::new("/anime/Show");
+
+        assert!(is_under(Path::new("/anime/Show/ep1.mkv"), dir));
+        assert!(is_under(Path::new("/anime/Show/extras/op.mkv"), dir));
+        assert!(is_under(Path::new("/anime/Show"), dir));
+        assert!(!is_under(Path::new("/anime/Show 2/ep1.mkv"), dir));
+        assert!(!is_under(Path::new("/anime/Sho"), dir));
+        assert!(!is_under(Path::new("/anime"), dir));
+    }
+
+    #[test]
+    fn test_count_open_under() {
+        let open = [
+            PathBuf::from("/anime/12345/ep1.mkv"),
+            PathBuf::from("/anime/12345/ep2.mkv"),
+            PathBuf::from("/anime/123456/ep1.mkv"),
+            PathBuf::from("/dev/null"),
+        ];
+
+        assert_eq!(count_open_under(&open, Path::new("/anime/12345")), 2);
+        assert_eq!(count_open_under(&open, Path::new("/anime/123456")), 1);
+        assert_eq!(count_open_under(&open, Path::new("/anime/999")), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_check_open_files_skips_busy_directory() {
+        use crate::progress::Progress;
+        use crate::rename::{RenameDirection, RenameOperation};
+        use std::fs::{self, File};
+        use tempfile::tempdir;
+
+        let dir = tempdir().unwrap();
+        let target = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(target.join("12345")).unwrap();
+        fs::create_dir(target.join("67890")).unwrap();
+        let _held = File::create(target.join("12345/ep1.mkv")).unwrap();
+
+        let mut result = RenameResult::new(RenameDirection::AniDbToReadable, false);
+        for id in [12345, 67890] {
+            result.add_operation(RenameOperation::new(
+                target.join(id.to_string()),
+                format!("Anime (2020) [anidb-{}]", id),
+                id,
+                false,
+            ));
+        }
+
+        check_open_files(
+            &mut result,
+            ConflictResolution::Skip,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+
+        assert_eq!(result.busy_skipped, ["12345"]);
+        assert_eq!(result.operations.len(), 1);
+        assert_eq!(result.operations[0].source_name, "67890");
+    }
+}
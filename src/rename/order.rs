@@ -0,0 +1,86 @@
+use std::cmp::Ordering;
+
+/// Compare two names in natural order
+///
+/// Runs of ASCII digits are compared by numeric value, so `Show 2` sorts
+/// before `Show 10`. Names that compare equal that way (e.g. `01` and `1`)
+/// fall back to plain byte order, which keeps the ordering total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut left = a.as_bytes();
+    let mut right = b.as_bytes();
+
+    while let (Some(&l), Some(&r)) = (left.first(), right.first()) {
+        if l.is_ascii_digit() && r.is_ascii_digit() {
+            let (l_num, l_rest) = split_digits(left);
+            let (r_num, r_rest) = split_digits(right);
+
+            let ordering = compare_numeric(l_num, r_num);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+
+            left = l_rest;
+            right = r_rest;
+        } else {
+            if l != r {
+                return l.cmp(&r);
+            }
+            left = &left[1..];
+            right = &right[1..];
+        }
+    }
+
+    left.len().cmp(&right.len()).then_with(|| a.cmp(b))
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let end = s
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Compare digit runs by value without parsing (runs may exceed u64)
+fn compare_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    let a = trim_leading_zeros(a);
+    let b = trim_leading_zeros(b);
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn trim_leading_zeros(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|&c| c != b'0').unwrap_or(s.len());
+    &s[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_compare_by_value() {
+        assert_eq!(natural_cmp("2", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("Show 10", "Show 9"), Ordering::Greater);
+        assert_eq!(natural_cmp("[X] 123", "[X] 45"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_text_compares_bytewise() {
+        assert_eq!(natural_cmp("Alpha", "Beta"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "abcd"), Ordering::Less);
+        assert_eq!(natural_cmp("same", "same"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_leading_zeros_break_ties() {
+        assert_ne!(natural_cmp("01", "1"), Ordering::Equal);
+        assert_eq!(natural_cmp("01", "2"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_sorts_mixed_names() {
+        let mut names = vec!["100", "[AS0] 7", "20", "3", "[AS0] 12"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["3", "20", "100", "[AS0] 7", "[AS0] 12"]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Per-series corrections to AniDB data (`--overrides`).
+//!
+//! Some entries have a title or year on AniDB that makes a poor folder name
+//! (a title nobody uses, a year of a pilot episode). An overrides file fixes
+//! them locally, keyed by AniDB ID:
+//!
+//! ```json
+//! {
+//!   "1530": { "title_main": "Dragon Ball Z", "year": 1989 },
+//!   "23": { "title_en": "", "series_tag": "CB" }
+//! }
+//! ```
+//!
+//! Every field is optional; an empty `title_en` removes the English title.
+//! Overrides are applied after the cache or API lookup and never written
+//! to the cache.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::info;
+
+use crate::api::AnimeInfo;
+
+/// Errors reading an overrides file
+#[derive(Error, Debug)]
+pub enum OverridesError {
+    #[error("Cannot read overrides file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Overrides file {path:?} is invalid: {message}")]
+    Invalid { path: PathBuf, message: String },
+}
+
+/// Corrections for one AniDB entry
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    pub title_main: Option<String>,
+    /// Replaces the English title; empty removes it
+    pub title_en: Option<String>,
+    pub year: Option<u16>,
+    /// Series tag of the AniDB-format name
+    pub series_tag: Option<String>,
+}
+
+impl Override {
+    /// Replace the fields of `info` this override sets
+    pub fn apply(&self, info: &mut AnimeInfo) {
+        if let Some(title) = &self.title_main {
+            info.title_main = title.clone();
+            info.english_fallback = false;
+        }
+        if let Some(title) = &self.title_en {
+            info.title_en = (!title.is_empty()).then(|| title.clone());
+        }
+        if let Some(year) = self.year {
+            info.release_year = Some(year);
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self
+            .title_main
+            .as_deref()
+            .is_some_and(|t| t.trim().is_empty())
+        {
+            return Err("title_main is empty".to_string());
+        }
+        if let Some(tag) = &self.series_tag {
+            if tag.trim().is_empty() || tag.contains(['[', ']']) {
+                return Err(format!("series_tag {:?} is not a valid tag", tag));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Overrides loaded from a file, by AniDB ID
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    source: PathBuf,
+    entries: BTreeMap<u32, Override>,
+}
+
+impl Overrides {
+    /// Read and validate an overrides file
+    pub fn load(path: &Path) -> Result<Self, OverridesError> {
+        let content = fs::read_to_string(path).map_err(|source| OverridesError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let invalid = |message: String| OverridesError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        };
+        let entries: BTreeMap<u32, Override> =
+            serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        for (id, entry) in &entries {
+            entry
+                .validate()
+                .map_err(|message| invalid(format!("anidb {}: {}", id, message)))?;
+        }
+        info!(
+            "Loaded {} override(s) from {}",
+            entries.len(),
+            path.display()
+        );
+        Ok(Self {
+            source: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// File the overrides were read from
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn get(&self, anidb_id: u32) -> Option<&Override> {
+        self.entries.get(&anidb_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> AnimeInfo {
+        AnimeInfo {
+            anidb_id: 23,
+            title_main: "Cowboy Bebop".to_string(),
+            title_en: Some("Cowboy Bebop".to_string()),
+            release_year: Some(1998),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        }
+    }
+
+    fn load(content: &str) -> Result<Overrides, OverridesError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overrides.json");
+        fs::write(&path, content).unwrap();
+        Overrides::load(&path)
+    }
+
+    #[test]
+    fn test_apply_replaces_set_fields_only() {
+        let overrides = load(r#"{"23": {"title_en": "", "year": 1997}}"#).unwrap();
+        let mut info = info();
+        overrides.get(23).unwrap().apply(&mut info);
+
+        assert_eq!(info.title_main, "Cowboy Bebop");
+        assert_eq!(info.title_en, None);
+        assert_eq!(info.release_year, Some(1997));
+        assert!(overrides.get(24).is_none());
+    }
+
+    #[test]
+    fn test_invalid_files_are_rejected() {
+        for content in [
+            r#"{"abc": {}}"#,
+            r#"{"23": {"title": "Typo"}}"#,
+            r#"{"23": {"title_main": " "}}"#,
+            r#"{"23": {"series_tag": "A]B"}}"#,
+            "not json",
+        ] {
+            assert!(
+                matches!(load(content), Err(OverridesError::Invalid { .. })),
+                "accepted {}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let err = Overrides::load(Path::new("/nonexistent/overrides.json")).unwrap_err();
+        assert!(matches!(err, OverridesError::Read { .. }));
+    }
+}
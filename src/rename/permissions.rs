@@ -0,0 +1,264 @@
+//! Preflight check that every planned rename is permitted.
+//!
+//! On shared storage some directories (or their parent) belong to another
+//! user, and their rename fails with EPERM halfway through a run. With
+//! `--check-permissions` every operation is checked before the first
+//! rename: the parent must be writable by the current user, a sticky parent
+//! (mode `1777`, as on many shares) additionally requires owning the
+//! directory or the parent, and a directory moved to another parent must be
+//! writable itself. All problems are reported at once, with owners and modes.
+
+use tracing::{info, warn};
+
+use crate::progress::ProgressObserver;
+
+use super::conflict::ConflictResolution;
+use super::error::RenameError;
+use super::types::{PermissionProblem, RenameOperation, RenameResult};
+
+/// Check every pending operation and handle problems according to `policy`
+///
+/// With `Skip` the affected directories are left untouched (recorded in
+/// `result.permission_skipped`); any other policy fails before anything is
+/// renamed, as the renames would fail anyway.
+pub fn check_permissions(
+    result: &mut RenameResult,
+    policy: ConflictResolution,
+    progress: &mut dyn ProgressObserver,
+) -> Result<(), RenameError> {
+    check_permissions_with(result, policy, progress, &permission_problem)
+}
+
+fn check_permissions_with(
+    result: &mut RenameResult,
+    policy: ConflictResolution,
+    progress: &mut dyn ProgressObserver,
+    probe: &dyn Fn(&RenameOperation) -> Option<String>,
+) -> Result<(), RenameError> {
+    let problems: Vec<PermissionProblem> = result
+        .operations
+        .iter()
+        .filter(|op| !op.is_skipped())
+        .filter_map(|op| {
+            probe(op).map(|reason| PermissionProblem {
+                source_name: op.source_name.clone(),
+                reason,
+            })
+        })
+        .collect();
+    info!(
+        "Checked permissions of {} operations: {} problem(s)",
+        result.operations.len(),
+        problems.len()
+    );
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let listing: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+    warn!("Permission problems: {}", listing.join("; "));
+    progress.warn(&format!(
+        "{} director{} cannot be renamed by this user:\n  {}",
+        problems.len(),
+        if problems.len() == 1 { "y" } else { "ies" },
+        listing.join("\n  ")
+    ));
+
+    if policy != ConflictResolution::Skip {
+        return Err(RenameError::PermissionProblems(listing));
+    }
+    for problem in problems {
+        result
+            .operations
+            .retain(|op| op.source_name != problem.source_name);
+        result.permission_skipped.push(problem);
+    }
+    Ok(())
+}
+
+/// Why the current user cannot perform `op`, if they cannot
+///
+/// Best effort: a source that cannot be inspected (e.g. because it
+/// vanished) is left to the rename itself.
+#[cfg(unix)]
+pub fn permission_problem(op: &RenameOperation) -> Option<String> {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    let parent = op.source_path.parent()?;
+    let parent_meta = fs::metadata(parent).ok()?;
+    let source_meta = fs::symlink_metadata(&op.source_path).ok()?;
+
+    if !platform::writable(parent) {
+        return Some(format!(
+            "{} is not writable ({})",
+            parent.display(),
+            describe(&parent_meta)
+        ));
+    }
+    if sticky_denies(
+        parent_meta.mode(),
+        parent_meta.uid(),
+        source_meta.uid(),
+        platform::euid(),
+    ) {
+        return Some(format!(
+            "its parent is sticky ({}) and it belongs to uid {}",
+            describe(&parent_meta),
+            source_meta.uid()
+        ));
+    }
+
+    // Moving a directory to another parent rewrites its `..` entry
+    let destination_parent = op.destination_path.parent()?;
+    if !op.to_trash && destination_parent != parent && source_meta.is_dir() {
+        if !platform::writable(&op.source_path) {
+            return Some(format!(
+                "it is not writable and moves to another directory ({})",
+                describe(&source_meta)
+            ));
+        }
+        if fs::metadata(destination_parent).is_ok() && !platform::writable(destination_parent) {
+            return Some(format!("{} is not writable", destination_parent.display()));
+        }
+    }
+    None
+}
+
+#[cfg(not(unix))]
+pub fn permission_problem(_op: &RenameOperation) -> Option<String> {
+    None
+}
+
+/// Owner and mode of a file, e.g. `owner uid 1001, mode 755`
+#[cfg(unix)]
+fn describe(meta: &std::fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    format!("owner uid {}, mode {:o}", meta.uid(), meta.mode() & 0o7777)
+}
+
+/// Whether a sticky parent keeps `uid` from renaming an entry it holds
+///
+/// In a sticky directory only the owner of an entry, the owner of the
+/// directory and root may rename the entry.
+fn sticky_denies(parent_mode: u32, parent_uid: u32, source_uid: u32, uid: u32) -> bool {
+    parent_mode & 0o1000 != 0 && uid != 0 && uid != source_uid && uid != parent_uid
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    /// Whether entries can be created and removed in `dir`
+    pub fn writable(dir: &Path) -> bool {
+        let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+            return true;
+        };
+        // SAFETY: the path is NUL-terminated and outlives the call
+        unsafe { libc::access(c_path.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+    }
+
+    pub fn euid() -> u32 {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        unsafe { libc::geteuid() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Progress;
+    use crate::rename::RenameDirection;
+    use std::path::PathBuf;
+
+    fn result_with(names: &[&str]) -> RenameResult {
+        let mut result = RenameResult::new(RenameDirection::ReadableToAniDb, false);
+        for (i, name) in names.iter().enumerate() {
+            result.add_operation(RenameOperation::new(
+                PathBuf::from("/anime").join(name),
+                (i + 1).to_string(),
+                i as u32 + 1,
+                false,
+            ));
+        }
+        result
+    }
+
+    fn probe(op: &RenameOperation) -> Option<String> {
+        op.source_name
+            .starts_with("Locked")
+            .then(|| "/anime is not writable (owner uid 1001, mode 755)".to_string())
+    }
+
+    #[test]
+    fn test_problems_are_skipped_with_skip_policy() {
+        let mut result = result_with(&["Free [anidb-1]", "Locked [anidb-2]", "Locked [anidb-3]"]);
+
+        check_permissions_with(
+            &mut result,
+            ConflictResolution::Skip,
+            &mut Progress::silent(),
+            &probe,
+        )
+        .unwrap();
+
+        assert_eq!(result.operations.len(), 1);
+        assert_eq!(result.operations[0].source_name, "Free [anidb-1]");
+        assert_eq!(result.permission_skipped.len(), 2);
+        assert!(result.permission_skipped[1]
+            .to_string()
+            .contains("mode 755"));
+    }
+
+    #[test]
+    fn test_all_problems_listed_when_aborting() {
+        let mut result = result_with(&["Locked [anidb-1]", "Free [anidb-2]", "Locked [anidb-3]"]);
+
+        let err = check_permissions_with(
+            &mut result,
+            ConflictResolution::Abort,
+            &mut Progress::silent(),
+            &probe,
+        )
+        .unwrap_err();
+
+        match err {
+            RenameError::PermissionProblems(problems) => assert_eq!(problems.len(), 2),
+            other => panic!("unexpected error: {}", other),
+        }
+        assert_eq!(result.operations.len(), 3);
+    }
+
+    #[test]
+    fn test_sticky_denies() {
+        // Sticky parent owned by root, entry owned by another user
+        assert!(sticky_denies(0o41777, 0, 1001, 1000));
+        assert!(!sticky_denies(0o41777, 0, 1000, 1000));
+        assert!(!sticky_denies(0o41777, 1000, 1001, 1000));
+        assert!(!sticky_denies(0o41777, 0, 1001, 0));
+        assert!(!sticky_denies(0o40777, 0, 1001, 1000));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_permission_problem_on_disk() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let parent = dir.path().join("library");
+        fs::create_dir_all(parent.join("Title [anidb-1]")).unwrap();
+        let op = RenameOperation::new(parent.join("Title [anidb-1]"), "1".to_string(), 1, false);
+        assert_eq!(permission_problem(&op), None);
+
+        // Root may write anywhere, so the denial is only visible to others
+        fs::set_permissions(&parent, fs::Permissions::from_mode(0o555)).unwrap();
+        let problem = permission_problem(&op);
+        fs::set_permissions(&parent, fs::Permissions::from_mode(0o755)).unwrap();
+        if platform::euid() != 0 {
+            assert!(problem.unwrap().contains("mode 555"));
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::parser::parse_directory_name;
+
+use super::error::RenameError;
+use super::name_builder::sanitize_filename;
+
+/// Default placeholder for names of uncached ids in a dry run
+pub const DEFAULT_PLACEHOLDER: &str = "⟨uncached {id}⟩";
+
+/// Build the placeholder name for an id from a format containing `{id}`
+///
+/// A placeholder must never parse as a directory name in either format, so
+/// a plan built from placeholders can't be mistaken for (or applied as) a
+/// real one.
+pub fn placeholder_name(format: &str, anidb_id: u32) -> Result<String, RenameError> {
+    if !format.contains("{id}") {
+        return Err(RenameError::InvalidPlaceholder(format!(
+            "'{}' must contain {{id}}",
+            format
+        )));
+    }
+
+    let name = sanitize_filename(&format.replace("{id}", &anidb_id.to_string()));
+
+    if name.is_empty() || parse_directory_name(&name).is_ok() {
+        return Err(RenameError::InvalidPlaceholder(format!(
+            "'{}' produces '{}', which looks like a real directory name",
+            format, name
+        )));
+    }
+
+    Ok(name)
+}
+
+/// Check a placeholder format before planning
+pub fn validate_placeholder(format: &str) -> Result<(), RenameError> {
+    placeholder_name(format, 1).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_placeholder() {
+        assert_eq!(
+            placeholder_name(DEFAULT_PLACEHOLDER, 12345).unwrap(),
+            "⟨uncached 12345⟩"
+        );
+    }
+
+    #[test]
+    fn test_custom_placeholder() {
+        assert_eq!(placeholder_name("TODO-{id}", 7).unwrap(), "TODO-7");
+    }
+
+    #[test]
+    fn test_placeholder_requires_id() {
+        assert!(matches!(
+            validate_placeholder("uncached"),
+            Err(RenameError::InvalidPlaceholder(_))
+        ));
+    }
+
+    #[test]
+    fn test_placeholder_must_not_look_like_a_real_name() {
+        for format in [
+            "{id}",
+            "[X] {id}",
+            "Title [anidb-{id}]",
+            "Title (2020) [anidb-{id}]",
+        ] {
+            assert!(
+                matches!(
+                    validate_placeholder(format),
+                    Err(RenameError::InvalidPlaceholder(_))
+                ),
+                "{} was accepted",
+                format
+            );
+        }
+    }
+}
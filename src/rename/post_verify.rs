@@ -0,0 +1,187 @@
+//! Check of the filesystem after a run (`--post-run-verify`).
+//!
+//! The moves a rename or revert made are replayed on a map of paths, giving
+//! which must exist afterwards (destinations, directories moved aside) and
+//! which must be gone (sources not taken over by a later move). Each parent
+//! directory is then listed once and the names compared exactly, so a
+//! rename differing only in case is checked on case-insensitive filesystems
+//! too. A discrepancy means something else touched the directory during or
+//! right after the run.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use super::conflict::ConflictResolution;
+use super::types::RenameResult;
+
+/// How the filesystem differs from what a run did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// A destination (or directory moved aside) is not there
+    Missing,
+    /// A source is still there
+    Present,
+}
+
+impl DiscrepancyKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            DiscrepancyKind::Missing => "expected but missing",
+            DiscrepancyKind::Present => "should be gone but exists",
+        }
+    }
+}
+
+/// A path not in the state the run left it in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Discrepancy {
+    pub path: PathBuf,
+    pub kind: DiscrepancyKind,
+}
+
+/// Result of checking a run's moves
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PostRunCheck {
+    /// Paths checked
+    pub checked: usize,
+    /// In path order
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl PostRunCheck {
+    pub fn is_clean(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// Check the outcome of `moves`, `(from, to)` in the order they were made
+pub fn verify_moves(moves: &[(PathBuf, PathBuf)]) -> PostRunCheck {
+    let mut expected: BTreeMap<&Path, bool> = BTreeMap::new();
+    for (from, to) in moves {
+        expected.insert(from, false);
+        expected.insert(to, true);
+    }
+
+    let mut listings: HashMap<&Path, Option<HashSet<OsString>>> = HashMap::new();
+    let mut check = PostRunCheck {
+        checked: expected.len(),
+        discrepancies: Vec::new(),
+    };
+    for (path, should_exist) in expected {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let listing = listings.entry(parent).or_insert_with(|| list(parent));
+        let exists = listing.as_ref().is_some_and(|names| names.contains(name));
+        if exists != should_exist {
+            let kind = if should_exist {
+                DiscrepancyKind::Missing
+            } else {
+                DiscrepancyKind::Present
+            };
+            warn!("Post-run check: {:?} {}", path, kind.description());
+            check.discrepancies.push(Discrepancy {
+                path: path.to_path_buf(),
+                kind,
+            });
+        }
+    }
+    check
+}
+
+/// The moves made by an executed rename
+pub fn rename_moves(result: &RenameResult) -> Vec<(PathBuf, PathBuf)> {
+    let mut moves = Vec::new();
+    for op in &result.operations {
+        if op.conflict == Some(ConflictResolution::Skip) {
+            continue;
+        }
+        if let Some(aside) = &op.aside_name {
+            moves.push((
+                op.destination_path.clone(),
+                op.destination_path.with_file_name(aside),
+            ));
+        }
+        moves.push((op.source_path.clone(), op.destination_path.clone()));
+    }
+    moves
+}
+
+/// Names in `dir`, `None` when it cannot be listed (nothing exists in it)
+fn list(dir: &Path) -> Option<HashSet<OsString>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Some(
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name())
+                .collect(),
+        ),
+        Err(e) => {
+            debug!("Cannot list {:?}: {}", dir, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_clean_moves() {
+        let dir = tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        fs::create_dir(path("B")).unwrap();
+        fs::create_dir(path("C")).unwrap();
+        fs::create_dir(path("B.aside")).unwrap();
+
+        // The B that was there is moved aside before A takes its name
+        let moves = [
+            (path("B"), path("B.aside")),
+            (path("A"), path("B")),
+            (path("X"), path("C")),
+        ];
+        let check = verify_moves(&moves);
+        assert_eq!(check.checked, 5);
+        assert!(check.is_clean(), "{:?}", check.discrepancies);
+    }
+
+    #[test]
+    fn test_discrepancies() {
+        let dir = tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        fs::create_dir(path("A")).unwrap();
+        fs::create_dir(path("b")).unwrap();
+
+        let moves = [(path("A"), path("B")), (path("b"), path("c"))];
+        let check = verify_moves(&moves);
+        assert_eq!(
+            check.discrepancies,
+            [
+                Discrepancy {
+                    path: path("A"),
+                    kind: DiscrepancyKind::Present
+                },
+                Discrepancy {
+                    path: path("B"),
+                    kind: DiscrepancyKind::Missing
+                },
+                Discrepancy {
+                    path: path("b"),
+                    kind: DiscrepancyKind::Present
+                },
+                Discrepancy {
+                    path: path("c"),
+                    kind: DiscrepancyKind::Missing
+                },
+            ]
+        );
+    }
+}
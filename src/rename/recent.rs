@@ -0,0 +1,137 @@
+//! Directories still being written to (`--exclude-recent`).
+//!
+//! A download client copying into a directory keeps touching files deep
+//! inside it; renaming it mid-copy breaks the client's paths. A source
+//! directory with anything modified within the window is left out of the
+//! plan. The walk stops at the first recent entry, so a busy directory
+//! costs little; a quiet one is read in full. Symlinks are not followed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tracing::{debug, info};
+
+use crate::parser::ParsedDirectory;
+use crate::progress::ProgressObserver;
+
+/// A source directory left untouched because it changed recently
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecentSkip {
+    pub source_name: String,
+    /// The recent entry found, the directory itself or something inside it
+    pub file: PathBuf,
+}
+
+/// The first entry of `dir` (itself included) modified at or after `cutoff`
+///
+/// Unreadable entries are ignored.
+pub fn recent_entry(dir: &Path, cutoff: SystemTime) -> Option<PathBuf> {
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.modified().is_ok_and(|modified| modified >= cutoff) {
+            return Some(path);
+        }
+        if !metadata.is_dir() {
+            continue;
+        }
+        match fs::read_dir(&path) {
+            Ok(entries) => pending.extend(entries.filter_map(|e| e.ok()).map(|e| e.path())),
+            Err(e) => debug!("Cannot read {:?}: {}", path, e),
+        }
+    }
+    None
+}
+
+/// Remove the directories of `target_dir` modified within `window` from `directories`
+pub fn exclude_recent(
+    directories: &mut Vec<ParsedDirectory>,
+    target_dir: &Path,
+    window: Duration,
+    progress: &mut dyn ProgressObserver,
+) -> Vec<RecentSkip> {
+    let cutoff = SystemTime::now()
+        .checked_sub(window)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut skipped = Vec::new();
+    directories.retain(|parsed| {
+        let name = parsed.original_name();
+        let Some(file) = recent_entry(&target_dir.join(name), cutoff) else {
+            return true;
+        };
+        info!("{} was modified recently ({:?}), skipping", name, file);
+        progress.warn(&format!("{}: modified recently, left untouched", name));
+        skipped.push(RecentSkip {
+            source_name: name.to_string(),
+            file,
+        });
+        false
+    });
+    skipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_directory_name;
+    use crate::progress::Progress;
+    use tempfile::tempdir;
+
+    fn set_old(path: &Path) {
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .read(true)
+            .open(path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_recent_entry_finds_nested_files() {
+        let dir = tempdir().unwrap();
+        let show = dir.path().join("12345");
+        fs::create_dir_all(show.join("Season 1")).unwrap();
+        let file = show.join("Season 1").join("ep01.mkv");
+        fs::write(&file, "").unwrap();
+        let cutoff = SystemTime::now() - Duration::from_secs(60);
+
+        assert_eq!(recent_entry(&show, cutoff), Some(show.clone()));
+
+        set_old(&show);
+        set_old(&show.join("Season 1"));
+        assert_eq!(recent_entry(&show, cutoff), Some(file.clone()));
+
+        set_old(&file);
+        assert_eq!(recent_entry(&show, cutoff), None);
+    }
+
+    #[test]
+    fn test_exclude_recent() {
+        let dir = tempdir().unwrap();
+        for name in ["1", "2"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        set_old(&dir.path().join("1"));
+        let mut directories: Vec<ParsedDirectory> = ["1", "2"]
+            .into_iter()
+            .map(|n| parse_directory_name(n).unwrap())
+            .collect();
+
+        let skipped = exclude_recent(
+            &mut directories,
+            dir.path(),
+            Duration::from_secs(60),
+            &mut Progress::silent(),
+        );
+        assert_eq!(directories.len(), 1);
+        assert_eq!(directories[0].original_name(), "1");
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].source_name, "2");
+        assert_eq!(skipped[0].file, dir.path().join("2"));
+    }
+}
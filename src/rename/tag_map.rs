@@ -0,0 +1,163 @@
+//! Series tags assigned from a user-maintained map (`--tag-map`).
+//!
+//! A tag map lists the AniDB ids of each franchise under the series tag
+//! their directories should carry:
+//!
+//! ```toml
+//! FMA = [979, 6107]
+//! "GitS" = [61, 62, 1404]
+//! ```
+//!
+//! When planning, in either direction, a mapped directory without a tag
+//! gets it and one with another tag has it replaced. Going to readable
+//! names, the `series_tag` of an override takes precedence. An id may be
+//! listed under one tag only.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use tracing::info;
+
+use super::types::Retag;
+
+/// Errors reading a tag map
+#[derive(Error, Debug)]
+pub enum TagMapError {
+    #[error("Cannot read tag map {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Tag map {path:?} is invalid: {message}")]
+    Invalid { path: PathBuf, message: String },
+}
+
+/// Series tags by AniDB ID, loaded from a file
+#[derive(Debug, Clone, Default)]
+pub struct TagMap {
+    source: PathBuf,
+    tags: BTreeMap<u32, String>,
+}
+
+impl TagMap {
+    /// Read and validate a tag map
+    pub fn load(path: &Path) -> Result<Self, TagMapError> {
+        let content = fs::read_to_string(path).map_err(|source| TagMapError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let tags = parse(&content).map_err(|message| TagMapError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        info!(
+            "Loaded series tags of {} id(s) from {}",
+            tags.len(),
+            path.display()
+        );
+        Ok(Self {
+            source: path.to_path_buf(),
+            tags,
+        })
+    }
+
+    /// File the map was read from
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn get(&self, anidb_id: u32) -> Option<&str> {
+        self.tags.get(&anidb_id).map(String::as_str)
+    }
+
+    /// The change to the series tag of a directory currently tagged `current`
+    pub fn retag(&self, anidb_id: u32, current: Option<&str>) -> Option<Retag> {
+        let to = self.get(anidb_id).filter(|&tag| current != Some(tag))?;
+        Some(Retag {
+            from: current.map(str::to_string),
+            to: to.to_string(),
+        })
+    }
+}
+
+/// Invert `franchise = [ids]` tables into tags by id
+fn parse(content: &str) -> Result<BTreeMap<u32, String>, String> {
+    let franchises: BTreeMap<String, Vec<u32>> =
+        toml::from_str(content).map_err(|e| e.message().to_string())?;
+    let mut tags = BTreeMap::new();
+    for (tag, ids) in franchises {
+        if tag.trim().is_empty() || tag.contains(['[', ']']) {
+            return Err(format!("{:?} is not a valid series tag", tag));
+        }
+        for id in ids {
+            if let Some(other) = tags.insert(id, tag.clone()) {
+                return Err(if other == tag {
+                    format!("anidb {} is listed twice under {:?}", id, tag)
+                } else {
+                    format!("anidb {} is listed under {:?} and {:?}", id, other, tag)
+                });
+            }
+        }
+    }
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(content: &str) -> Result<TagMap, TagMapError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tags.toml");
+        fs::write(&path, content).unwrap();
+        TagMap::load(&path)
+    }
+
+    #[test]
+    fn test_tags_by_id() {
+        let map = load("FMA = [979, 6107]\n\"Ghost in the Shell\" = [61]\nEmpty = []\n").unwrap();
+        assert_eq!(map.get(979), Some("FMA"));
+        assert_eq!(map.get(6107), Some("FMA"));
+        assert_eq!(map.get(61), Some("Ghost in the Shell"));
+        assert_eq!(map.get(62), None);
+
+        assert_eq!(map.retag(979, Some("FMA")), None);
+        assert_eq!(map.retag(62, None), None);
+        let retag = map.retag(61, Some("GitS")).unwrap();
+        assert_eq!(retag.to_string(), "tag GitS -> Ghost in the Shell");
+        assert_eq!(map.retag(979, None).unwrap().to_string(), "tag none -> FMA");
+    }
+
+    #[test]
+    fn test_invalid_maps_are_rejected() {
+        for (content, message) in [
+            (
+                "A = [1, 2]\nB = [3, 2]",
+                r#"anidb 2 is listed under "A" and "B""#,
+            ),
+            ("A = [1, 1]", r#"anidb 1 is listed twice under "A""#),
+            ("\"A]B\" = [1]", "not a valid series tag"),
+            ("\" \" = [1]", "not a valid series tag"),
+            ("A = [-1]", ""),
+            ("A = 1", ""),
+            ("A = [1]\nA = [2]", ""),
+        ] {
+            match load(content) {
+                Err(TagMapError::Invalid { message: m, .. }) => {
+                    assert!(m.contains(message), "{:?}: {}", content, m)
+                }
+                other => panic!("{:?} gave {:?}", content, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let err = TagMap::load(Path::new("/nonexistent/tags.toml")).unwrap_err();
+        assert!(matches!(err, TagMapError::Read { .. }));
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::Path;
+
+use tracing::info;
+
+use crate::run_id::directory_span;
+use crate::validator::ValidationResult;
+
+use super::name_builder::build_anidb_name_with_suffix;
+use super::tag_map::TagMap;
+use super::types::{RenameDirection, RenameOperation, RenameResult};
+
+/// Plan renames from human-readable format back to AniDB format
+///
+/// No API access is needed: the series tag and AniDB ID are taken from the
+/// parsed directory names, the tag from `tag_map` when it lists the id.
+/// Conflicts with existing directories are not checked here.
+/// As with `plan_to_readable`, `target_dir` should be canonical, and the
+/// renames get `run_id` when given, a new run id otherwise.
+pub fn plan_to_anidb(
+    target_dir: &Path,
+    validation: &ValidationResult,
+    dry_run: bool,
+    tag_map: Option<&TagMap>,
+    run_id: Option<&str>,
+) -> RenameResult {
+    let mut result = RenameResult::new(RenameDirection::ReadableToAniDb, dry_run);
+    if let Some(run_id) = run_id {
+        result = result.with_run_id(run_id.to_string());
+    }
+    result.options.tag_map = tag_map.map(|map| map.source().to_path_buf());
+
+    info!(
+        "Preparing to rename {} directories to AniDB format",
+        validation.directories.len()
+    );
+
+    for parsed in &validation.directories {
+        let _span = directory_span(
+            &result.run_id,
+            "prepare",
+            parsed.anidb_id(),
+            parsed.original_name(),
+        )
+        .entered();
+        let retag = tag_map.and_then(|map| map.retag(parsed.anidb_id(), parsed.series_tag()));
+        let tag = retag
+            .as_ref()
+            .map_or(parsed.series_tag(), |r| Some(r.to.as_str()));
+        let destination_name =
+            build_anidb_name_with_suffix(tag, parsed.anidb_id(), parsed.suffix());
+
+        let mut op = RenameOperation::new(
+            target_dir.join(parsed.original_name()),
+            destination_name,
+            parsed.anidb_id(),
+            false,
+        );
+        op.retag = retag;
+        op.suffix = parsed.suffix().map(str::to_string);
+        result.add_operation(op);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::DirectoryEntry;
+    use crate::validator::validate_directories;
+
+    #[test]
+    fn test_plan_to_anidb() {
+        let entries = vec![
+            DirectoryEntry::new("Title (2020) [anidb-1]".to_string()),
+            DirectoryEntry::new("[X] Other (2019) [anidb-2]".to_string()),
+            DirectoryEntry::new("[X] Third (2021) [anidb-3] [26 eps]".to_string()),
+        ];
+        let validation = validate_directories(&entries).unwrap();
+
+        let result = plan_to_anidb(Path::new("/anime"), &validation, true, None, None);
+
+        assert_eq!(result.direction, RenameDirection::ReadableToAniDb);
+        assert_eq!(result.operations.len(), 3);
+        assert_eq!(result.operations[0].destination_name, "1");
+        assert_eq!(result.operations[1].destination_name, "[X] 2");
+        // The episode count is dropped with the titles
+        assert_eq!(result.operations[2].destination_name, "[X] 3");
+    }
+
+    #[test]
+    fn test_plan_to_anidb_keeps_trailing_characters_of_sources() {
+        let entries = vec![
+            DirectoryEntry::new("Title (2020) [anidb-1] ".to_string()),
+            DirectoryEntry::new("[X] Other (2019) [anidb-2].".to_string()),
+        ];
+        let validation = validate_directories(&entries).unwrap();
+
+        let result = plan_to_anidb(Path::new("/anime"), &validation, true, None, None);
+
+        assert_eq!(
+            result.operations[0].source_path,
+            Path::new("/anime/Title (2020) [anidb-1] ")
+        );
+        assert_eq!(result.operations[0].destination_name, "1");
+        assert_eq!(
+            result.operations[1].source_path,
+            Path::new("/anime/[X] Other (2019) [anidb-2].")
+        );
+        assert_eq!(result.operations[1].destination_name, "[X] 2");
+    }
+
+    #[test]
+    fn test_plan_to_anidb_keeps_suffix() {
+        let entries = vec![
+            DirectoryEntry::new("[AS0] Shingeki no Kyojin (2017) S2 [anidb-99]".to_string()),
+            DirectoryEntry::new("Title Part 2 [anidb-5]".to_string()),
+        ];
+        let validation = validate_directories(&entries).unwrap();
+
+        let result = plan_to_anidb(Path::new("/anime"), &validation, true, None, None);
+
+        assert_eq!(result.operations[0].destination_name, "[AS0] 99 S2");
+        assert_eq!(result.operations[0].suffix.as_deref(), Some("S2"));
+        assert_eq!(result.operations[1].destination_name, "5 Part 2");
+    }
+
+    #[test]
+    fn test_plan_to_anidb_with_tag_map() {
+        let entries = vec![
+            DirectoryEntry::new("Title (2020) [anidb-1]".to_string()),
+            DirectoryEntry::new("[X] Other (2019) [anidb-2]".to_string()),
+            DirectoryEntry::new("[Y] Third [anidb-3]".to_string()),
+        ];
+        let validation = validate_directories(&entries).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tags.toml");
+        std::fs::write(&path, "Y = [1, 2, 3]").unwrap();
+        let tag_map = TagMap::load(&path).unwrap();
+
+        let result = plan_to_anidb(Path::new("/anime"), &validation, true, Some(&tag_map), None);
+
+        let planned: Vec<(&str, Option<String>)> = result
+            .operations
+            .iter()
+            .map(|op| {
+                (
+                    op.destination_name.as_str(),
+                    op.retag.as_ref().map(|r| r.to_string()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            planned,
+            [
+                ("[Y] 1", Some("tag none -> Y".to_string())),
+                ("[Y] 2", Some("tag X -> Y".to_string())),
+                ("[Y] 3", None),
+            ]
+        );
+        assert_eq!(result.options.tag_map, Some(path));
+    }
+}
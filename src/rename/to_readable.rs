@@ -1,46 +1,35 @@
-use std::fs;
+use chrono::Utc;
+use std::collections::HashMap;
 use std::path::Path;
-use thiserror::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
-use crate::api::{AniDbClient, AnimeInfo, ApiConfig, ApiError};
-use crate::cache::{CacheConfig, CacheStore};
-use crate::parser::{AniDbFormat, ParsedDirectory};
-use crate::progress::Progress;
+use crate::api::{AniDbClient, AnimeInfo, AnimeType, ApiConfig, ApiError, TitleDump};
+use crate::cache::{CacheConfig, CacheStore, DEFAULT_CACHE_SAVE_EVERY};
+use crate::fs_profile::FsProfile;
+use crate::parser::{AniDbFormat, NameTemplate, ParsedDirectory};
+use crate::progress::ProgressObserver;
+use crate::run_id::directory_span;
+use crate::scanner::UNRESOLVED_DIR;
+use crate::timing::{format_age, Step, Timings, DEFAULT_SLOW_THRESHOLD_SECS};
 use crate::validator::ValidationResult;
 
-use super::name_builder::{build_human_readable_name, NameBuildResult, NameBuilderConfig};
-use super::types::{RenameDirection, RenameOperation, RenameResult};
-
-/// Errors that can occur during rename operations
-#[derive(Error, Debug)]
-pub enum RenameError {
-    #[error("Failed to fetch anime data for ID {id}: {message}")]
-    ApiError { id: u32, message: String },
-
-    #[error("Failed to rename '{from}' to '{to}': {source}")]
-    FilesystemError {
-        from: String,
-        to: String,
-        #[source]
-        source: std::io::Error,
-    },
-
-    #[error("Destination already exists: {0}")]
-    DestinationExists(String),
-
-    #[error("API client not configured")]
-    ApiNotConfigured,
-}
-
-impl From<ApiError> for RenameError {
-    fn from(err: ApiError) -> Self {
-        RenameError::ApiError {
-            id: 0,
-            message: err.to_string(),
-        }
-    }
-}
+use super::conflict::ConflictResolution;
+use super::disambiguate::disambiguate_truncation;
+use super::error::RenameError;
+use super::execute::{execute_operations, resolve_conflicts};
+use super::name_builder::{
+    build_human_readable_name_with_suffix, compute_destination, replacement_table, NameBuildResult,
+    NameBuilderConfig,
+};
+use super::overrides::Overrides;
+use super::placeholder::{placeholder_name, validate_placeholder, DEFAULT_PLACEHOLDER};
+use super::tag_map::TagMap;
+use super::types::{
+    EffectiveOptions, RenameDirection, RenameOperation, RenameResult, RestrictedPolicy,
+    UnairedPolicy, DEFAULT_RESTRICTED_MARKER,
+};
 
 /// Options for rename to readable operation
 #[derive(Debug, Clone)]
@@ -48,6 +37,59 @@ pub struct RenameOptions {
     pub max_length: usize,
     pub dry_run: bool,
     pub cache_expiry_days: u32,
+    /// Save the cache after this many fetched entries (0: only at the end)
+    pub cache_save_every: usize,
+    /// How to resolve conflicts with existing destinations
+    pub on_conflict: ConflictResolution,
+    /// Leave series tags out of the readable names (revert still restores them)
+    pub drop_series_tag: bool,
+    /// Move directories whose id no longer exists on AniDB into `_unresolved/`
+    pub quarantine_unresolved: bool,
+    /// Format of dry-run names for uncached ids, containing `{id}`
+    pub placeholder: String,
+    /// Handling of entries AniDB marks as restricted
+    pub restricted: RestrictedPolicy,
+    /// Marker prepended to restricted entries with [`RestrictedPolicy::Tag`]
+    pub restricted_marker: String,
+    /// Handling of anime that have not aired yet
+    pub unaired: UnairedPolicy,
+    /// Character replacements overriding the default fullwidth table
+    pub replacements: Vec<(char, String)>,
+    /// Text added after the titles of entries of each type, e.g. `(Movie)`
+    pub type_suffixes: Vec<(AnimeType, String)>,
+    /// Add AniDB's type after the year of types without a suffix text
+    pub include_type: bool,
+    /// Naming rules of the target filesystem, used for collision checks
+    pub fs_profile: FsProfile,
+    /// Local corrections to AniDB data, by id
+    pub overrides: Option<Overrides>,
+    /// Series tags by id, replacing those of the names
+    pub tag_map: Option<TagMap>,
+    /// Renames taking longer are reported as slow (zero: never)
+    pub slow_threshold: Duration,
+    /// Layout of the readable names (`None`: the built-in one)
+    pub template: Option<NameTemplate>,
+    /// AniDB's titles dump, consulted before the cache and the API
+    pub titles: Option<Arc<TitleDump>>,
+    /// Run id of the planned renames (`None`: a new one)
+    pub run_id: Option<String>,
+}
+
+impl RenameOptions {
+    /// How names are built with these options
+    pub fn name_config(&self) -> Result<NameBuilderConfig, RenameError> {
+        Ok(NameBuilderConfig {
+            max_length: self.max_length,
+            drop_series_tag: self.drop_series_tag,
+            restricted_marker: (self.restricted == RestrictedPolicy::Tag)
+                .then(|| self.restricted_marker.clone()),
+            unaired_tag: self.unaired == UnairedPolicy::Tag,
+            type_suffixes: self.type_suffixes.clone(),
+            include_type: self.include_type,
+            replacements: replacement_table(&self.replacements)?,
+            template: self.template.clone(),
+        })
+    }
 }
 
 impl Default for RenameOptions {
@@ -56,21 +98,80 @@ impl Default for RenameOptions {
             max_length: 255,
             dry_run: false,
             cache_expiry_days: 30,
+            cache_save_every: DEFAULT_CACHE_SAVE_EVERY,
+            on_conflict: ConflictResolution::Abort,
+            drop_series_tag: false,
+            quarantine_unresolved: false,
+            placeholder: DEFAULT_PLACEHOLDER.to_string(),
+            restricted: RestrictedPolicy::Allow,
+            restricted_marker: DEFAULT_RESTRICTED_MARKER.to_string(),
+            unaired: UnairedPolicy::Normal,
+            replacements: Vec::new(),
+            type_suffixes: Vec::new(),
+            include_type: false,
+            fs_profile: FsProfile::Posix,
+            overrides: None,
+            tag_map: None,
+            slow_threshold: Duration::from_secs(DEFAULT_SLOW_THRESHOLD_SECS),
+            template: None,
+            titles: None,
+            run_id: None,
         }
     }
 }
 
 /// Rename directories from AniDB format to human-readable format
+///
+/// Plans all operations, resolves conflicts using `options.on_conflict`,
+/// then executes them (unless dry run).
+#[allow(dead_code)]
 pub fn rename_to_readable(
     target_dir: &Path,
     validation: &ValidationResult,
     api_config: &ApiConfig,
     options: &RenameOptions,
-    progress: &mut Progress,
+    progress: &mut dyn ProgressObserver,
 ) -> Result<RenameResult, RenameError> {
+    let target_dir = std::fs::canonicalize(target_dir).unwrap_or_else(|_| target_dir.to_path_buf());
+    let mut result = plan_to_readable(&target_dir, validation, api_config, options, progress)?;
+
+    result.sort_operations();
+
+    if !options.dry_run {
+        let mut policy = options.on_conflict;
+        resolve_conflicts(&mut result, &mut policy)?;
+        execute_operations(&mut result, &mut policy, progress, false)?;
+    }
+
+    Ok(result)
+}
+
+/// Plan renames from AniDB format to human-readable format without touching the filesystem
+///
+/// Fetches metadata (cache first, then API) and builds destination names.
+/// Conflicts with existing directories are not checked here. Source paths
+/// are built from `target_dir`, which should be canonical: execution
+/// refuses operations whose directory no longer resolves to itself.
+pub fn plan_to_readable(
+    target_dir: &Path,
+    validation: &ValidationResult,
+    api_config: &ApiConfig,
+    options: &RenameOptions,
+    progress: &mut dyn ProgressObserver,
+) -> Result<RenameResult, RenameError> {
+    validate_placeholder(&options.placeholder)?;
+    let name_config = options.name_config()?;
+
     // Setup cache
-    let cache_config = CacheConfig::for_target_dir(target_dir, options.cache_expiry_days);
+    let cache_config = CacheConfig {
+        save_every: options.cache_save_every,
+        ..CacheConfig::for_target_dir(target_dir, options.cache_expiry_days)
+    };
     let mut cache = CacheStore::load(cache_config);
+    // Keep what the dump resolves, so later runs need neither it nor the API
+    if let (Some(titles), false) = (&options.titles, options.dry_run) {
+        cache.seed(titles, validation.directories.iter().map(|p| p.anidb_id()));
+    }
 
     // Setup API client (only if we need to fetch)
     let api_client = if api_config.is_configured() {
@@ -84,11 +185,34 @@ pub fn rename_to_readable(
         None
     };
 
-    let name_config = NameBuilderConfig {
+    let mut result = RenameResult::new(RenameDirection::AniDbToReadable, options.dry_run);
+    if let Some(run_id) = &options.run_id {
+        result = result.with_run_id(run_id.clone());
+    }
+    result.options = EffectiveOptions {
         max_length: options.max_length,
+        drop_series_tag: options.drop_series_tag,
+        on_conflict: options.on_conflict,
+        restricted: options.restricted,
+        unaired: options.unaired,
+        replacements: options.replacements.clone(),
+        type_suffixes: options.type_suffixes.clone(),
+        include_type: options.include_type,
+        template: options
+            .template
+            .as_ref()
+            .map(|template| template.as_str().to_string()),
+        fs_profile: options.fs_profile,
+        overrides: options
+            .overrides
+            .as_ref()
+            .map(|overrides| overrides.source().to_path_buf()),
+        tag_map: options
+            .tag_map
+            .as_ref()
+            .map(|tag_map| tag_map.source().to_path_buf()),
     };
-
-    let mut result = RenameResult::new(RenameDirection::AniDbToReadable, options.dry_run);
+    result.timings = Timings::new(options.slow_threshold);
     let total = validation.directories.len();
 
     info!(
@@ -96,105 +220,323 @@ pub fn rename_to_readable(
         total
     );
 
-    // First pass: prepare all operations (fetch data, build names)
-    for (i, parsed) in validation.directories.iter().enumerate() {
+    // Count ids that will need an API call so progress can estimate time left;
+    // duplicate ids are fetched once
+    if !options.dry_run && api_client.is_some() {
+        let mut uncached: Vec<u32> = validation
+            .directories
+            .iter()
+            .filter(|p| {
+                matches!(p, ParsedDirectory::AniDb(_))
+                    && (cache.get(p.anidb_id()).is_none() || cache.is_seeded(p.anidb_id()))
+            })
+            .filter(|p| {
+                !options
+                    .titles
+                    .as_ref()
+                    .is_some_and(|t| t.contains(p.anidb_id()))
+            })
+            .map(|p| p.anidb_id())
+            .collect();
+        uncached.sort_unstable();
+        uncached.dedup();
+        let uncached = uncached.len();
+        debug!("{} directories require an API fetch", uncached);
+        progress.set_fetch_plan(
+            uncached,
+            Duration::from_secs(api_config.min_request_interval_secs),
+        );
+    }
+
+    let mut fetcher = Fetcher {
+        client: api_client.as_ref(),
+        ..Default::default()
+    };
+
+    for parsed in &validation.directories {
         let anidb_format = match parsed {
             ParsedDirectory::AniDb(f) => f,
             _ => continue, // Skip if somehow wrong format
         };
 
+        let _span = directory_span(
+            &result.run_id,
+            "prepare",
+            anidb_format.anidb_id,
+            &anidb_format.original_name,
+        )
+        .entered();
+        let started_at = Instant::now();
         let operation = prepare_rename_operation(
             target_dir,
             anidb_format,
             &mut cache,
-            api_client.as_ref(),
+            &mut fetcher,
             &name_config,
             progress,
-            options.dry_run,
+            options,
         )?;
-
-        // Check destination doesn't already exist
-        if operation.destination_path.exists() && !options.dry_run {
-            return Err(RenameError::DestinationExists(
-                operation.destination_name.clone(),
+        let name = &anidb_format.original_name;
+        let fetched = fetcher.fetch_time.take();
+        if let Some(elapsed) = fetched {
+            result.timings.record(Step::Fetch, name, elapsed);
+        }
+        let prepared = started_at
+            .elapsed()
+            .saturating_sub(fetched.unwrap_or_default());
+        result.timings.record(Step::Prepare, name, prepared);
+
+        if operation.restricted && options.restricted == RestrictedPolicy::Skip {
+            info!("Skipping restricted entry: {}", operation.source_name);
+            progress.warn(&format!(
+                "Restricted, left untouched: {}",
+                operation.source_name
             ));
+            result.restricted_skipped.push(operation.source_name);
+            continue;
         }
 
-        progress.rename_progress(
-            i + 1,
-            total,
-            &operation.source_name,
-            &operation.destination_name,
-        );
+        if operation.unaired && options.unaired == UnairedPolicy::Skip {
+            info!("Skipping unaired entry: {}", operation.source_name);
+            progress.warn(&format!(
+                "Not aired yet, left untouched: {}",
+                operation.source_name
+            ));
+            result.unaired_skipped.push(operation.source_name);
+            continue;
+        }
 
         result.add_operation(operation);
     }
 
-    // Second pass: execute all renames (unless dry run)
-    if !options.dry_run {
-        for op in &result.operations {
-            execute_rename(op)?;
-        }
+    let disambiguated = disambiguate_truncation(&mut result, progress);
+    if disambiguated > 0 {
+        info!("Disambiguated {} truncated name(s)", disambiguated);
+    }
 
-        info!("Successfully renamed {} directories", result.len());
+    // A dropped tag survives in the history (revert restores it), but
+    // converting the readable names back to AniDB format cannot recover it
+    if options.drop_series_tag {
+        let dropped = validation
+            .directories
+            .iter()
+            .filter(|p| matches!(p, ParsedDirectory::AniDb(f) if f.series_tag.is_some()))
+            .count();
+        if dropped > 0 {
+            warn!("Dropping series tags from {} directories", dropped);
+            progress.warn(&format!(
+                "Series tag dropped from {} name(s): --revert restores it, \
+                 converting back to AniDB format will not",
+                dropped
+            ));
+        }
     }
 
-    // Save cache
-    if let Err(e) = cache.save() {
-        warn!("Failed to save cache: {}", e);
+    if fetcher.deduplicated > 0 {
+        debug!(
+            "Reused {} API result(s) for duplicate ids",
+            fetcher.deduplicated
+        );
+    }
+    if fetcher.dump_hits > 0 {
+        info!("Took {} title(s) from the titles dump", fetcher.dump_hits);
     }
+    result.deduplicated_fetches = fetcher.deduplicated;
+    result.api_requests = fetcher.requests;
+    result.cache_hits = fetcher.cache_hits;
+    result.title_changes = fetcher.title_changes;
+
+    // Save the cache before anything is renamed
+    save_cache(cache, progress);
 
     Ok(result)
 }
 
+/// Save the cache, reporting a failure as a warning
+///
+/// The run goes on without it: what was fetched is only fetched again next time.
+pub(crate) fn save_cache(cache: CacheStore, progress: &mut dyn ProgressObserver) {
+    let path = cache.path().to_path_buf();
+    if let Err(e) = cache.into_saved() {
+        warn!("Failed to save cache to {:?}: {}", path, e);
+        progress.warn(&format!(
+            "Cache not saved to {}: {} (fetched titles will be fetched again next run)",
+            path.display(),
+            e
+        ));
+    }
+}
+
+/// API access for one run, remembering results already fetched by AniDB ID.
+///
+/// `None` records a NotFound, so a duplicate folder of an unknown id is
+/// quarantined without asking the API again.
+#[derive(Default)]
+struct Fetcher<'a> {
+    client: Option<&'a AniDbClient>,
+    results: HashMap<u32, Option<AnimeInfo>>,
+    deduplicated: usize,
+    requests: usize,
+    cache_hits: usize,
+    /// Duration of the request made for the directory being prepared
+    fetch_time: Option<Duration>,
+    /// Fetched entries whose cached titles changed
+    title_changes: usize,
+    /// Ids whose titles came from the titles dump
+    dump_hits: usize,
+}
+
 fn prepare_rename_operation(
     target_dir: &Path,
     anidb: &AniDbFormat,
     cache: &mut CacheStore,
-    api_client: Option<&AniDbClient>,
+    fetcher: &mut Fetcher,
     config: &NameBuilderConfig,
-    progress: &mut Progress,
-    dry_run: bool,
+    progress: &mut dyn ProgressObserver,
+    options: &RenameOptions,
 ) -> Result<RenameOperation, RenameError> {
     debug!("Preparing rename for AniDB ID {}", anidb.anidb_id);
 
-    // Try cache first
-    let info = if let Some(cached) = cache.get(anidb.anidb_id) {
-        debug!("Using cached data for AniDB ID {}", anidb.anidb_id);
+    let source_path = target_dir.join(&anidb.original_name);
+
+    // Titles from the dump first, the rest from the cache when it has the id;
+    // an entry seeded from the dump is refetched when the API can be asked
+    let mut cache_age = None;
+    let refetch_seeded =
+        !options.dry_run && fetcher.client.is_some() && cache.is_seeded(anidb.anidb_id);
+    let dumped = options
+        .titles
+        .as_ref()
+        .and_then(|titles| titles.get(anidb.anidb_id));
+    let info = if let Some(mut info) = dumped {
+        debug!("Using titles dump for AniDB ID {}", anidb.anidb_id);
+        if let Some(cached) = cache.get(anidb.anidb_id) {
+            info.release_year = cached.release_year;
+            info.anime_type = cached.anime_type;
+            info.episode_count = cached.episode_count;
+            info.restricted = cached.restricted;
+            info.unaired = cached.unaired;
+        }
+        fetcher.dump_hits += 1;
+        info
+    } else if let Some((cached, fetched_at)) = cache
+        .get_with_meta(anidb.anidb_id)
+        .filter(|_| !refetch_seeded)
+    {
+        let age = (Utc::now() - fetched_at).to_std().unwrap_or_default();
+        info!(
+            "Using cached data for AniDB ID {} (cached {})",
+            anidb.anidb_id,
+            format_age(age)
+        );
         progress.using_cache(anidb.anidb_id);
+        fetcher.cache_hits += 1;
+        cache_age = Some(age);
         cached
-    } else if dry_run {
-        // In dry run mode, don't call API - use placeholder data
+    } else if options.dry_run {
+        // In dry run mode, don't call API - use a placeholder name
         debug!("Dry run: using placeholder for AniDB ID {}", anidb.anidb_id);
         progress.would_fetch(anidb.anidb_id);
-        AnimeInfo {
-            anidb_id: anidb.anidb_id,
-            title_main: format!("[Title for anidb-{}]", anidb.anidb_id),
-            title_en: None,
-            release_year: None,
+        let name = placeholder_name(&options.placeholder, anidb.anidb_id)?;
+        return Ok(RenameOperation::placeholder(
+            source_path,
+            name,
+            anidb.anidb_id,
+        ));
+    } else if let Some(fetched) = fetcher.results.get(&anidb.anidb_id) {
+        // Duplicate id: reuse the result of the earlier request
+        debug!(
+            "Reusing API result for duplicate AniDB ID {}",
+            anidb.anidb_id
+        );
+        fetcher.deduplicated += 1;
+        match fetched {
+            Some(info) => info.clone(),
+            None => {
+                warn!(
+                    "AniDB ID {} not found, moving to {}/",
+                    anidb.anidb_id, UNRESOLVED_DIR
+                );
+                progress.warn(&format!(
+                    "AniDB ID {} not found: {} will be moved to {}/",
+                    anidb.anidb_id, anidb.original_name, UNRESOLVED_DIR
+                ));
+                return Ok(RenameOperation::quarantine(source_path, anidb.anidb_id));
+            }
         }
     } else {
         // Fetch from API
-        let client = api_client.ok_or(RenameError::ApiNotConfigured)?;
+        let client = fetcher.client.ok_or(RenameError::ApiNotConfigured)?;
 
         info!("Fetching data for AniDB ID {} from API", anidb.anidb_id);
         progress.fetch_start(anidb.anidb_id);
-        let info = client
-            .fetch_anime(anidb.anidb_id)
-            .map_err(|e| RenameError::ApiError {
-                id: anidb.anidb_id,
-                message: e.to_string(),
-            })?;
+        fetcher.requests += 1;
+        let started_at = Instant::now();
+        let fetched = client.fetch_anime(anidb.anidb_id);
+        fetcher.fetch_time = Some(started_at.elapsed());
+        let info = match fetched {
+            Ok(info) => info,
+            // Only a definitive NotFound quarantines; transient errors still fail the run
+            Err(ApiError::NotFound(id)) if options.quarantine_unresolved => {
+                progress.fetch_complete();
+                fetcher.results.insert(id, None);
+                warn!("AniDB ID {} not found, moving to {}/", id, UNRESOLVED_DIR);
+                progress.warn(&format!(
+                    "AniDB ID {} not found: {} will be moved to {}/",
+                    id, anidb.original_name, UNRESOLVED_DIR
+                ));
+                return Ok(RenameOperation::quarantine(source_path, id));
+            }
+            Err(e) => {
+                return Err(RenameError::ApiError {
+                    id: anidb.anidb_id,
+                    message: e.to_string(),
+                })
+            }
+        };
         progress.fetch_complete();
 
-        // Cache the result
-        cache.insert(&info);
+        // Cache the result, saving periodically so a crash keeps what was fetched
+        if cache.insert(&info).is_some() {
+            fetcher.title_changes += 1;
+        }
+        if let Err(e) = cache.checkpoint() {
+            warn!("Failed to save cache: {}", e);
+        }
+        fetcher.results.insert(anidb.anidb_id, Some(info.clone()));
         info
     };
 
+    // Local corrections take precedence over AniDB data
+    let mut info = info;
+    let correction = options
+        .overrides
+        .as_ref()
+        .and_then(|overrides| overrides.get(anidb.anidb_id));
+    if let Some(correction) = correction {
+        debug!("Applying override for AniDB ID {}", anidb.anidb_id);
+        correction.apply(&mut info);
+    }
+
+    // Series tag: the override's, else the tag map's, else the name's
+    let override_tag = correction.and_then(|c| c.series_tag.as_deref());
+    let retag = match (override_tag, &options.tag_map) {
+        (None, Some(tag_map)) => tag_map.retag(anidb.anidb_id, anidb.series_tag.as_deref()),
+        _ => None,
+    };
+    let tag = override_tag.or(retag.as_ref().map(|r| r.to.as_str()));
+
     // Build new name
-    let NameBuildResult { name, truncated } =
-        build_human_readable_name(anidb.series_tag.as_deref(), &info, config);
+    let build = |config: &NameBuilderConfig| match tag {
+        Some(tag) => Ok(build_human_readable_name_with_suffix(
+            Some(tag),
+            anidb.suffix.as_deref(),
+            &info,
+            config,
+        )),
+        None => compute_destination(&anidb.original_name, &info, config),
+    };
+    let NameBuildResult { name, truncated } = build(config)?;
 
     if truncated {
         warn!(
@@ -207,39 +549,45 @@ fn prepare_rename_operation(
         ));
     }
 
-    let source_path = target_dir.join(&anidb.original_name);
-
-    Ok(RenameOperation::new(
-        source_path,
-        name,
-        anidb.anidb_id,
-        truncated,
-    ))
-}
-
-fn execute_rename(op: &RenameOperation) -> Result<(), RenameError> {
-    info!("Renaming: {} -> {}", op.source_name, op.destination_name);
+    let mut op = RenameOperation::new(source_path, name, anidb.anidb_id, truncated);
+    if truncated {
+        let unlimited = NameBuilderConfig {
+            max_length: usize::MAX,
+            ..config.clone()
+        };
+        op.untruncated_name = Some(build(&unlimited)?.name);
+    }
+    op.restricted = info.restricted;
+    op.unaired = info.unaired;
+    op.override_applied = correction.is_some();
+    op.cache_age = cache_age;
+    op.suffix = anidb.suffix.clone();
+    // A dropped tag is not changed by the map
+    op.retag = retag.filter(|_| !options.drop_series_tag);
+
+    if info.english_fallback {
+        info!(
+            "Used English title as primary for AniDB ID {}: {}",
+            anidb.anidb_id, info.title_main
+        );
+        op.english_fallback = true;
+    }
 
-    fs::rename(&op.source_path, &op.destination_path).map_err(|e| RenameError::FilesystemError {
-        from: op.source_name.clone(),
-        to: op.destination_name.clone(),
-        source: e,
-    })
+    Ok(op)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::api::AnimeInfo;
+    use crate::progress::Progress;
     use crate::scanner::DirectoryEntry;
     use crate::validator::validate_directories;
     use std::io::Write;
     use tempfile::tempdir;
 
     fn make_entry(name: &str) -> DirectoryEntry {
-        DirectoryEntry {
-            name: name.to_string(),
-        }
+        DirectoryEntry::new(name.to_string())
     }
 
     /// Create a test progress reporter that writes to a buffer
@@ -276,6 +624,7 @@ mod tests {
         let anidb = AniDbFormat {
             series_tag: None,
             anidb_id: 12345,
+            suffix: None,
             original_name: "12345".to_string(),
         };
 
@@ -284,10 +633,10 @@ mod tests {
             dir.path(),
             &anidb,
             &mut cache,
-            None,
+            &mut Fetcher::default(),
             &config,
             &mut progress,
-            false,
+            &RenameOptions::default(),
         );
 
         assert!(matches!(result, Err(RenameError::ApiNotConfigured)));
@@ -304,6 +653,7 @@ mod tests {
         let anidb = AniDbFormat {
             series_tag: None,
             anidb_id: 12345,
+            suffix: None,
             original_name: "12345".to_string(),
         };
 
@@ -312,15 +662,20 @@ mod tests {
             dir.path(),
             &anidb,
             &mut cache,
-            None,
+            &mut Fetcher::default(),
             &config,
             &mut progress,
-            true,
+            &RenameOptions {
+                dry_run: true,
+                ..Default::default()
+            },
         );
 
         assert!(result.is_ok());
         let op = result.unwrap();
-        assert!(op.destination_name.contains("[Title for anidb-12345]"));
+        assert_eq!(op.destination_name, "⟨uncached 12345⟩");
+        assert!(op.placeholder);
+        assert_eq!(op.cache_age, None);
     }
 
     #[test]
@@ -337,12 +692,18 @@ mod tests {
             title_main: "Test Anime".to_string(),
             title_en: Some("Test Anime EN".to_string()),
             release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         };
         cache.insert(&info);
 
         let anidb = AniDbFormat {
             series_tag: Some("X".to_string()),
             anidb_id: 12345,
+            suffix: None,
             original_name: "[X] 12345".to_string(),
         };
 
@@ -351,10 +712,10 @@ mod tests {
             dir.path(),
             &anidb,
             &mut cache,
-            None,
+            &mut Fetcher::default(),
             &config,
             &mut progress,
-            false,
+            &RenameOptions::default(),
         );
 
         assert!(result.is_ok());
@@ -363,6 +724,218 @@ mod tests {
         assert!(op.destination_name.contains("Test Anime"));
         assert!(op.destination_name.contains("[X]"));
         assert!(op.destination_name.contains("[anidb-12345]"));
+        assert!(!op.english_fallback);
+        assert!(op
+            .cache_age
+            .is_some_and(|age| age < Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_prepare_rename_applies_override_after_cache() {
+        let dir = tempdir().unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&AnimeInfo {
+            anidb_id: 12345,
+            title_main: "Wrong Title".to_string(),
+            title_en: Some("Wrong Title EN".to_string()),
+            release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        let overrides_path = dir.path().join("overrides.json");
+        std::fs::write(
+            &overrides_path,
+            r#"{"12345": {"title_main": "Right Title", "title_en": "", "year": 2019, "series_tag": "Y"}}"#,
+        )
+        .unwrap();
+        let options = RenameOptions {
+            overrides: Some(Overrides::load(&overrides_path).unwrap()),
+            ..Default::default()
+        };
+
+        let anidb = AniDbFormat {
+            series_tag: Some("X".to_string()),
+            anidb_id: 12345,
+            suffix: None,
+            original_name: "[X] 12345".to_string(),
+        };
+        let op = prepare_rename_operation(
+            dir.path(),
+            &anidb,
+            &mut cache,
+            &mut Fetcher::default(),
+            &NameBuilderConfig::default(),
+            &mut test_progress(),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(op.destination_name, "[Y] Right Title (2019) [anidb-12345]");
+        assert!(op.override_applied);
+        // The cache keeps the AniDB data
+        assert_eq!(cache.get(12345).unwrap().title_main, "Wrong Title");
+    }
+
+    #[test]
+    fn test_prepare_rename_applies_tag_map() {
+        let dir = tempdir().unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&AnimeInfo {
+            anidb_id: 12345,
+            title_main: "Title".to_string(),
+            title_en: None,
+            release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        let tag_map_path = dir.path().join("tags.toml");
+        std::fs::write(&tag_map_path, "Z = [12345]").unwrap();
+        let overrides_path = dir.path().join("overrides.json");
+        std::fs::write(&overrides_path, r#"{"12345": {"series_tag": "Y"}}"#).unwrap();
+        let mut options = RenameOptions {
+            tag_map: Some(TagMap::load(&tag_map_path).unwrap()),
+            ..Default::default()
+        };
+        let anidb = AniDbFormat {
+            series_tag: Some("X".to_string()),
+            anidb_id: 12345,
+            suffix: None,
+            original_name: "[X] 12345".to_string(),
+        };
+        let mut prepare = |options: &RenameOptions| {
+            prepare_rename_operation(
+                dir.path(),
+                &anidb,
+                &mut cache,
+                &mut Fetcher::default(),
+                &options.name_config().unwrap(),
+                &mut test_progress(),
+                options,
+            )
+            .unwrap()
+        };
+
+        let op = prepare(&options);
+        assert_eq!(op.destination_name, "[Z] Title (2020) [anidb-12345]");
+        assert_eq!(op.retag.unwrap().to_string(), "tag X -> Z");
+
+        // An override's tag comes first
+        options.overrides = Some(Overrides::load(&overrides_path).unwrap());
+        let op = prepare(&options);
+        assert_eq!(op.destination_name, "[Y] Title (2020) [anidb-12345]");
+        assert!(op.retag.is_none());
+    }
+
+    #[test]
+    fn test_prepare_rename_reuses_fetch_for_duplicate_id() {
+        let dir = tempdir().unwrap();
+        let cache_config = CacheConfig::for_target_dir(dir.path(), 30);
+        let mut cache = CacheStore::load(cache_config);
+        let config = NameBuilderConfig::default();
+        let mut progress = test_progress();
+
+        // Earlier folders in the run already fetched 1 and got NotFound for 2
+        let mut fetcher = Fetcher::default();
+        fetcher.results.insert(
+            1,
+            Some(AnimeInfo {
+                anidb_id: 1,
+                title_main: "Fetched".to_string(),
+                title_en: None,
+                release_year: None,
+                english_fallback: false,
+                restricted: false,
+                unaired: false,
+                anime_type: None,
+                episode_count: None,
+            }),
+        );
+        fetcher.results.insert(2, None);
+
+        let options = RenameOptions {
+            quarantine_unresolved: true,
+            ..Default::default()
+        };
+        let duplicate = |id: u32| AniDbFormat {
+            series_tag: Some("B".to_string()),
+            anidb_id: id,
+            suffix: None,
+            original_name: format!("[B] {}", id),
+        };
+
+        // No API client: both must be answered from earlier results
+        let op = prepare_rename_operation(
+            dir.path(),
+            &duplicate(1),
+            &mut cache,
+            &mut fetcher,
+            &config,
+            &mut progress,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(op.destination_name, "[B] Fetched [anidb-1]");
+
+        let op = prepare_rename_operation(
+            dir.path(),
+            &duplicate(2),
+            &mut cache,
+            &mut fetcher,
+            &config,
+            &mut progress,
+            &options,
+        )
+        .unwrap();
+        assert!(op.quarantined);
+
+        assert_eq!(fetcher.deduplicated, 2);
+    }
+
+    #[test]
+    fn test_prepare_rename_flags_english_fallback() {
+        let dir = tempdir().unwrap();
+        let cache_config = CacheConfig::for_target_dir(dir.path(), 30);
+        let mut cache = CacheStore::load(cache_config);
+        let mut progress = test_progress();
+
+        cache.insert(&AnimeInfo {
+            anidb_id: 7,
+            title_main: "English Only".to_string(),
+            title_en: None,
+            release_year: None,
+            english_fallback: true,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+
+        let anidb = AniDbFormat {
+            series_tag: None,
+            anidb_id: 7,
+            suffix: None,
+            original_name: "7".to_string(),
+        };
+
+        let op = prepare_rename_operation(
+            dir.path(),
+            &anidb,
+            &mut cache,
+            &mut Fetcher::default(),
+            &NameBuilderConfig::default(),
+            &mut progress,
+            &RenameOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(op.destination_name, "English Only [anidb-7]");
+        assert!(op.english_fallback);
     }
 
     #[test]
@@ -381,6 +954,11 @@ mod tests {
             title_main: "Test Anime".to_string(),
             title_en: None,
             release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         });
         cache.save().unwrap();
 
@@ -425,6 +1003,11 @@ mod tests {
             title_main: "Test Anime".to_string(),
             title_en: None,
             release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         });
         cache.save().unwrap();
 
@@ -456,6 +1039,128 @@ mod tests {
         assert!(dir.path().join("Test Anime (2020) [anidb-12345]").exists());
     }
 
+    #[test]
+    fn test_rename_from_titles_dump() {
+        let dir = tempdir().unwrap();
+        let mut progress = test_progress();
+        for name in ["1", "23", "9541"] {
+            std::fs::create_dir(dir.path().join(name)).unwrap();
+        }
+
+        // A cached entry still gives its year
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&AnimeInfo {
+            anidb_id: 23,
+            title_main: "Cowboy Bebop (old title)".to_string(),
+            title_en: None,
+            release_year: Some(1998),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        cache.save().unwrap();
+
+        let titles = TitleDump::load(Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/titles/anime-titles.xml"
+        )))
+        .unwrap();
+        let entries = vec![make_entry("1"), make_entry("23"), make_entry("9541")];
+        let validation = validate_directories(&entries).unwrap();
+        let options = RenameOptions {
+            titles: Some(Arc::new(titles)),
+            ..Default::default()
+        };
+
+        // No API configured: every title comes from the dump
+        let result = rename_to_readable(
+            dir.path(),
+            &validation,
+            &ApiConfig::default(),
+            &options,
+            &mut progress,
+        )
+        .unwrap();
+        assert_eq!(result.api_requests, 0);
+        let mut names: Vec<&str> = result
+            .operations
+            .iter()
+            .map(|op| op.destination_name.as_str())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            [
+                "Cowboy Bebop (1998) [anidb-23]",
+                "Seikai no Monshou ／ Crest of the Stars [anidb-1]",
+                "Shingeki no Kyojin ／ Attack on Titan [anidb-9541]",
+            ]
+        );
+
+        // The dump seeded the cache for the next run
+        let cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        assert_eq!(cache.get(1).unwrap().title_main, "Seikai no Monshou");
+        assert_eq!(
+            cache.get(23).unwrap().title_main,
+            "Cowboy Bebop (old title)"
+        );
+    }
+
+    #[test]
+    fn test_seeded_entry_does_not_block_a_fetch() {
+        let dir = tempdir().unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        let titles = TitleDump::load(Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/titles/anime-titles.xml"
+        )))
+        .unwrap();
+        cache.seed(&titles, [23]);
+        let anidb = AniDbFormat {
+            series_tag: None,
+            anidb_id: 23,
+            suffix: None,
+            original_name: "23".to_string(),
+        };
+        let mut progress = test_progress();
+        let mut prepare = |fetcher: &mut Fetcher| {
+            prepare_rename_operation(
+                dir.path(),
+                &anidb,
+                &mut cache,
+                fetcher,
+                &NameBuilderConfig::default(),
+                &mut progress,
+                &RenameOptions::default(),
+            )
+        };
+
+        // Without the API the seeded titles are all there is
+        let op = prepare(&mut Fetcher::default()).unwrap();
+        assert_eq!(op.destination_name, "Cowboy Bebop [anidb-23]");
+
+        // With it, the entry is fetched again (from nowhere here)
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/httpapi", closed.local_addr().unwrap());
+        drop(closed);
+        let client = AniDbClient::new(ApiConfig {
+            base_url,
+            max_retries: 0,
+            min_request_interval_secs: 0,
+            ..ApiConfig::new("testclient", 1)
+        })
+        .unwrap();
+        let mut fetcher = Fetcher {
+            client: Some(&client),
+            ..Default::default()
+        };
+        let fetched = prepare(&mut fetcher);
+        assert!(matches!(fetched, Err(RenameError::ApiError { id: 23, .. })));
+        assert_eq!((fetcher.requests, fetcher.cache_hits), (1, 0));
+    }
+
     #[test]
     fn test_rename_preserves_series_tag() {
         let dir = tempdir().unwrap();
@@ -472,6 +1177,11 @@ mod tests {
             title_main: "Test Anime".to_string(),
             title_en: None,
             release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         });
         cache.save().unwrap();
 
@@ -500,6 +1210,50 @@ mod tests {
             .exists());
     }
 
+    #[test]
+    fn test_rename_drops_series_tag() {
+        let dir = tempdir().unwrap();
+        let mut progress = test_progress();
+
+        std::fs::create_dir(dir.path().join("[AS0] 12345")).unwrap();
+
+        let cache_config = CacheConfig::for_target_dir(dir.path(), 30);
+        let mut cache = CacheStore::load(cache_config);
+        cache.insert(&AnimeInfo {
+            anidb_id: 12345,
+            title_main: "Test Anime".to_string(),
+            title_en: None,
+            release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        });
+        cache.save().unwrap();
+
+        let entries = vec![make_entry("[AS0] 12345")];
+        let validation = validate_directories(&entries).unwrap();
+
+        let options = RenameOptions {
+            drop_series_tag: true,
+            ..Default::default()
+        };
+
+        let result = rename_to_readable(
+            dir.path(),
+            &validation,
+            &ApiConfig::default(),
+            &options,
+            &mut progress,
+        )
+        .unwrap();
+
+        // The source (recorded in history) keeps the tag so revert restores it
+        assert_eq!(result.operations[0].source_name, "[AS0] 12345");
+        assert!(dir.path().join("Test Anime (2020) [anidb-12345]").exists());
+    }
+
     #[test]
     fn test_rename_error_destination_exists() {
         let dir = tempdir().unwrap();
@@ -517,6 +1271,11 @@ mod tests {
             title_main: "Test Anime".to_string(),
             title_en: None,
             release_year: Some(2020),
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
         });
         cache.save().unwrap();
 
@@ -538,4 +1297,117 @@ mod tests {
 
         assert!(matches!(result, Err(RenameError::DestinationExists(_))));
     }
+
+    #[test]
+    fn test_restricted_skip_and_tag() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("1")).unwrap();
+        std::fs::create_dir(dir.path().join("2")).unwrap();
+
+        let cache_config = CacheConfig::for_target_dir(dir.path(), 30);
+        let mut cache = CacheStore::load(cache_config);
+        for (id, restricted) in [(1, true), (2, false)] {
+            cache.insert(&AnimeInfo {
+                anidb_id: id,
+                title_main: format!("Title {}", id),
+                title_en: None,
+                release_year: None,
+                english_fallback: false,
+                restricted,
+                unaired: false,
+                anime_type: None,
+                episode_count: None,
+            });
+        }
+        cache.save().unwrap();
+
+        let entries = vec![make_entry("1"), make_entry("2")];
+        let validation = validate_directories(&entries).unwrap();
+
+        let plan = |restricted| {
+            let options = RenameOptions {
+                restricted,
+                ..Default::default()
+            };
+            plan_to_readable(
+                dir.path(),
+                &validation,
+                &ApiConfig::default(),
+                &options,
+                &mut test_progress(),
+            )
+            .unwrap()
+        };
+
+        let skipped = plan(RestrictedPolicy::Skip);
+        assert_eq!(skipped.operations.len(), 1);
+        assert_eq!(skipped.operations[0].source_name, "2");
+        assert_eq!(skipped.restricted_skipped, vec!["1".to_string()]);
+
+        let tagged = plan(RestrictedPolicy::Tag);
+        assert_eq!(
+            tagged.operations[0].destination_name,
+            "[18+] Title 1 [anidb-1]"
+        );
+        assert_eq!(tagged.operations[1].destination_name, "Title 2 [anidb-2]");
+
+        let allowed = plan(RestrictedPolicy::Allow);
+        assert_eq!(allowed.operations[0].destination_name, "Title 1 [anidb-1]");
+    }
+
+    #[test]
+    fn test_unaired_skip_and_tag() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("1")).unwrap();
+        std::fs::create_dir(dir.path().join("2")).unwrap();
+
+        let cache_config = CacheConfig::for_target_dir(dir.path(), 30);
+        let mut cache = CacheStore::load(cache_config);
+        for (id, unaired) in [(1, true), (2, false)] {
+            cache.insert(&AnimeInfo {
+                anidb_id: id,
+                title_main: format!("Title {}", id),
+                title_en: None,
+                release_year: None,
+                english_fallback: false,
+                restricted: false,
+                unaired,
+                anime_type: None,
+                episode_count: None,
+            });
+        }
+        cache.save().unwrap();
+
+        let entries = vec![make_entry("1"), make_entry("2")];
+        let validation = validate_directories(&entries).unwrap();
+
+        let plan = |unaired| {
+            let options = RenameOptions {
+                unaired,
+                ..Default::default()
+            };
+            plan_to_readable(
+                dir.path(),
+                &validation,
+                &ApiConfig::default(),
+                &options,
+                &mut test_progress(),
+            )
+            .unwrap()
+        };
+
+        let skipped = plan(UnairedPolicy::Skip);
+        assert_eq!(skipped.operations.len(), 1);
+        assert_eq!(skipped.unaired_skipped, vec!["1".to_string()]);
+
+        let tagged = plan(UnairedPolicy::Tag);
+        assert_eq!(
+            tagged.operations[0].destination_name,
+            "Title 1 [Unaired] [anidb-1]"
+        );
+        assert_eq!(tagged.operations[1].destination_name, "Title 2 [anidb-2]");
+
+        let normal = plan(UnairedPolicy::Normal);
+        assert_eq!(normal.operations[0].destination_name, "Title 1 [anidb-1]");
+    }
 }
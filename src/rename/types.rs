@@ -1,4 +1,19 @@
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::api::AnimeType;
+use crate::fs_profile::FsProfile;
+use crate::run_id::new_run_id;
+use crate::scanner::UNRESOLVED_DIR;
+use crate::timing::Timings;
+
+use super::conflict::ConflictResolution;
+use super::order::natural_cmp;
+use super::post_verify::PostRunCheck;
+use super::recent::RecentSkip;
 
 /// Direction of the rename operation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,6 +33,47 @@ impl RenameDirection {
     }
 }
 
+/// What to do with entries AniDB marks as restricted (adult content)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum RestrictedPolicy {
+    /// Rename like any other entry
+    #[default]
+    Allow,
+    /// Leave the directory untouched
+    Skip,
+    /// Prepend the restricted marker to the name
+    Tag,
+}
+
+/// What to do with entries of anime that have not aired yet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum UnairedPolicy {
+    /// Rename like any other entry
+    #[default]
+    Normal,
+    /// Add an `[Unaired]` tag before the AniDB id
+    Tag,
+    /// Leave the directory untouched
+    Skip,
+}
+
+/// Default marker prepended to restricted entries with [`RestrictedPolicy::Tag`]
+pub const DEFAULT_RESTRICTED_MARKER: &str = "18+";
+
+/// Stand-in for the trash directory in planned destinations
+pub const TRASH_LABEL: &str = "(trash)";
+
+/// Name of an item in the trash as shown in results and history files,
+/// `TRASH_LABEL/<name>`; the absolute path goes with its `.trashinfo`
+pub fn trash_label(trashed: &Path) -> String {
+    let name = trashed.file_name().unwrap_or_default().to_string_lossy();
+    format!("{}/{}", TRASH_LABEL, name)
+}
+
 /// A single rename operation
 #[derive(Debug, Clone)]
 pub struct RenameOperation {
@@ -33,6 +89,58 @@ pub struct RenameOperation {
     pub anidb_id: u32,
     /// Whether the name was truncated to fit filesystem limits
     pub truncated: bool,
+    /// The name before truncation, for truncated names
+    pub untruncated_name: Option<String>,
+    /// A code was added to tell the name apart from another one truncated
+    /// to the same name
+    pub disambiguated: bool,
+    /// How a conflict with an existing destination was resolved, if any
+    pub conflict: Option<ConflictResolution>,
+    /// Name the existing destination was moved to (when resolved by moving it aside)
+    pub aside_name: Option<String>,
+    /// Moved into [`UNRESOLVED_DIR`] because the id no longer exists on AniDB
+    pub quarantined: bool,
+    /// Sent to the system trash instead of [`UNRESOLVED_DIR`]
+    pub to_trash: bool,
+    /// The `.trashinfo` file written when the directory was trashed
+    pub trash_info: Option<PathBuf>,
+    /// Destination is a dry-run placeholder for an uncached id; never applied
+    pub placeholder: bool,
+    /// Why the destination name would be rejected by the target filesystem
+    pub invalid_name: Option<String>,
+    /// AniDB had no main title, so the English title was used as primary
+    pub english_fallback: bool,
+    /// AniDB marks the entry as restricted
+    pub restricted: bool,
+    /// The anime has not aired yet
+    pub unaired: bool,
+    /// The name uses corrections from the overrides file
+    pub override_applied: bool,
+    /// The series tag was set from the tag map
+    pub retag: Option<Retag>,
+    /// Season or part marker kept from the source name, e.g. `S2`
+    pub suffix: Option<String>,
+    /// Age of the cache entry the name was built from (`None`: fetched now, or no data)
+    pub cache_age: Option<Duration>,
+}
+
+/// A series tag set or replaced from the tag map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retag {
+    /// Tag of the source name, if any
+    pub from: Option<String>,
+    pub to: String,
+}
+
+impl fmt::Display for Retag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tag {} -> {}",
+            self.from.as_deref().unwrap_or("none"),
+            self.to
+        )
+    }
 }
 
 impl RenameOperation {
@@ -59,10 +167,156 @@ impl RenameOperation {
             destination_name,
             anidb_id,
             truncated,
+            untruncated_name: None,
+            disambiguated: false,
+            conflict: None,
+            aside_name: None,
+            quarantined: false,
+            to_trash: false,
+            trash_info: None,
+            placeholder: false,
+            invalid_name: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            override_applied: false,
+            retag: None,
+            suffix: None,
+            cache_age: None,
+        }
+    }
+
+    /// A dry-run operation whose destination is a placeholder name
+    pub fn placeholder(source_path: PathBuf, name: String, anidb_id: u32) -> Self {
+        let mut op = Self::new(source_path, name, anidb_id, false);
+        op.placeholder = true;
+        op
+    }
+
+    /// Move a directory into [`UNRESOLVED_DIR`], keeping its name
+    pub fn quarantine(source_path: PathBuf, anidb_id: u32) -> Self {
+        let name = source_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut op = Self::new(
+            source_path,
+            format!("{}/{}", UNRESOLVED_DIR, name),
+            anidb_id,
+            false,
+        );
+        op.quarantined = true;
+        op
+    }
+
+    /// Send a quarantined directory to the system trash instead
+    ///
+    /// Once the directory has been moved, the destination name is the one
+    /// the trash gave it, and `destination_path` and `trash_info` say where.
+    pub fn send_to_trash(&mut self) {
+        self.to_trash = true;
+        self.destination_name = format!("{}/{}", TRASH_LABEL, self.source_name);
+    }
+
+    /// Whether this operation was skipped due to a conflict
+    pub fn is_skipped(&self) -> bool {
+        self.conflict == Some(ConflictResolution::Skip)
+    }
+
+    /// Change the destination name (e.g. when resolving a conflict with a suffix)
+    pub fn set_destination_name(&mut self, destination_name: String) {
+        self.destination_path = self
+            .source_path
+            .parent()
+            .map(|p| p.join(&destination_name))
+            .unwrap_or_else(|| PathBuf::from(&destination_name));
+        self.destination_name = destination_name;
+    }
+}
+
+/// Options that shaped the names of a run, recorded in its history file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectiveOptions {
+    /// Maximum directory name length
+    pub max_length: usize,
+    /// Series tags were left out of readable names
+    #[serde(default)]
+    pub drop_series_tag: bool,
+    /// Conflict policy (the fallback when conflicts were resolved interactively)
+    #[serde(default)]
+    pub on_conflict: ConflictResolution,
+    /// Handling of restricted entries
+    #[serde(default)]
+    pub restricted: RestrictedPolicy,
+    /// Handling of unaired entries
+    #[serde(default)]
+    pub unaired: UnairedPolicy,
+    /// Character replacements overriding the default table
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replacements: Vec<(char, String)>,
+    /// Text added after the titles of entries of each type
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub type_suffixes: Vec<(AnimeType, String)>,
+    /// AniDB's type was added after the year
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_type: bool,
+    /// Layout of the readable names (`--template`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Naming rules of the target filesystem, used for collision checks
+    #[serde(default)]
+    pub fs_profile: FsProfile,
+    /// File of per-series corrections to AniDB data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<PathBuf>,
+    /// File of series tags by AniDB ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_map: Option<PathBuf>,
+}
+
+impl Default for EffectiveOptions {
+    fn default() -> Self {
+        Self {
+            max_length: 255,
+            drop_series_tag: false,
+            on_conflict: ConflictResolution::Abort,
+            restricted: RestrictedPolicy::Allow,
+            unaired: UnairedPolicy::Normal,
+            replacements: Vec::new(),
+            type_suffixes: Vec::new(),
+            include_type: false,
+            template: None,
+            fs_profile: FsProfile::Posix,
+            overrides: None,
+            tag_map: None,
         }
     }
 }
 
+/// A human-readable name whose embedded id belongs to a different title
+#[derive(Debug, Clone)]
+pub struct IdMismatch {
+    pub source_name: String,
+    pub anidb_id: u32,
+    /// Main title AniDB has for the id (`None`: the id does not exist)
+    pub anidb_title: Option<String>,
+}
+
+/// A directory the current user is not allowed to rename
+#[derive(Debug, Clone)]
+pub struct PermissionProblem {
+    pub source_name: String,
+    /// What prevents the rename, with owners and modes
+    pub reason: String,
+}
+
+impl std::fmt::Display for PermissionProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.source_name, self.reason)
+    }
+}
+
 /// Result of a rename batch operation
 #[derive(Debug, Clone)]
 pub struct RenameResult {
@@ -72,6 +326,40 @@ pub struct RenameResult {
     pub operations: Vec<RenameOperation>,
     /// Whether this was a dry run
     pub dry_run: bool,
+    /// Options the operations were planned and executed with
+    pub options: EffectiveOptions,
+    /// Restricted directories left untouched
+    pub restricted_skipped: Vec<String>,
+    /// Unaired directories left untouched
+    pub unaired_skipped: Vec<String>,
+    /// API requests saved by reusing the result for a duplicate id
+    pub deduplicated_fetches: usize,
+    /// Requests sent to the AniDB API
+    pub api_requests: usize,
+    /// Ids answered from the cache
+    pub cache_hits: usize,
+    /// Fetched ids whose cached titles or year had changed upstream
+    pub title_changes: usize,
+    /// Embedded ids whose AniDB title does not match the name (`--verify-ids`)
+    pub id_mismatches: Vec<IdMismatch>,
+    /// Ids that could not be verified because they were not cached
+    pub unverified_ids: usize,
+    /// Directories with a mismatched id left untouched
+    pub id_skipped: Vec<String>,
+    /// Directories with open files left untouched (`--check-open-files`)
+    pub busy_skipped: Vec<String>,
+    /// Directories the user may not rename, left untouched (`--check-permissions`)
+    pub permission_skipped: Vec<PermissionProblem>,
+    /// Directories modified too recently, left untouched (`--exclude-recent`)
+    pub recent_skipped: Vec<RecentSkip>,
+    /// Directories that disappeared between planning and renaming
+    pub vanished: Vec<String>,
+    /// Short random id of this run, carried by its log spans and history
+    pub run_id: String,
+    /// Slowest prepare, fetch and rename steps
+    pub timings: Timings,
+    /// Check of the filesystem after renaming (`--post-run-verify`)
+    pub post_verify: Option<PostRunCheck>,
 }
 
 impl RenameResult {
@@ -80,18 +368,147 @@ impl RenameResult {
             direction,
             operations: Vec::new(),
             dry_run,
+            options: EffectiveOptions::default(),
+            restricted_skipped: Vec::new(),
+            unaired_skipped: Vec::new(),
+            deduplicated_fetches: 0,
+            api_requests: 0,
+            cache_hits: 0,
+            title_changes: 0,
+            id_mismatches: Vec::new(),
+            unverified_ids: 0,
+            id_skipped: Vec::new(),
+            busy_skipped: Vec::new(),
+            permission_skipped: Vec::new(),
+            recent_skipped: Vec::new(),
+            vanished: Vec::new(),
+            run_id: new_run_id(),
+            timings: Timings::default(),
+            post_verify: None,
         }
     }
 
+    /// The same result carrying `run_id`, e.g. the id of a chunked run
+    pub fn with_run_id(mut self, run_id: String) -> Self {
+        self.run_id = run_id;
+        self
+    }
+
     pub fn add_operation(&mut self, op: RenameOperation) {
         self.operations.push(op);
     }
 
+    /// Leave a directory untouched, dropping its planned operation
+    pub fn skip_source(&mut self, source_name: &str) {
+        let before = self.operations.len();
+        self.operations.retain(|op| op.source_name != source_name);
+        if self.operations.len() < before {
+            self.id_skipped.push(source_name.to_string());
+        }
+    }
+
     /// Count of operations where the name was truncated
     pub fn truncated_count(&self) -> usize {
         self.operations.iter().filter(|op| op.truncated).count()
     }
 
+    /// Count of operations skipped due to conflicts
+    pub fn skipped_count(&self) -> usize {
+        self.operations.iter().filter(|op| op.is_skipped()).count()
+    }
+
+    /// Count of directories moved (or to be moved) into the unresolved directory
+    pub fn quarantined_count(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|op| op.quarantined && !op.to_trash && !op.is_skipped())
+            .count()
+    }
+
+    /// Count of unresolved directories moved (or to be moved) to the system trash
+    pub fn trashed_count(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|op| op.to_trash && !op.is_skipped())
+            .count()
+    }
+
+    /// Count of names built from the English title for lack of a main title
+    pub fn english_fallback_count(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|op| op.english_fallback)
+            .count()
+    }
+
+    /// Count of operations whose destination the target filesystem would reject
+    pub fn invalid_name_count(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|op| op.invalid_name.is_some() && !op.is_skipped())
+            .count()
+    }
+
+    /// Count of names that use corrections from the overrides file
+    pub fn override_count(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|op| op.override_applied)
+            .count()
+    }
+
+    /// Count of names whose series tag was set from the tag map
+    pub fn retag_count(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|op| op.retag.is_some())
+            .count()
+    }
+
+    /// Count of operations whose destination is a placeholder
+    pub fn placeholder_count(&self) -> usize {
+        self.operations.iter().filter(|op| op.placeholder).count()
+    }
+
+    /// AniDB ids of placeholder operations: the uncached ids a dry run
+    /// would have fetched, ascending and without duplicates
+    pub fn uncached_ids(&self) -> Vec<u32> {
+        let ids: BTreeSet<u32> = self
+            .operations
+            .iter()
+            .filter(|op| op.placeholder)
+            .map(|op| op.anidb_id)
+            .collect();
+        ids.into_iter().collect()
+    }
+
+    /// Count of operations that are (or would be) executed
+    pub fn applied_count(&self) -> usize {
+        self.len() - self.skipped_count()
+    }
+
+    /// Count of directories left untouched, for whatever reason
+    pub fn untouched_count(&self) -> usize {
+        self.skipped_count()
+            + self.restricted_skipped.len()
+            + self.unaired_skipped.len()
+            + self.id_skipped.len()
+            + self.busy_skipped.len()
+            + self.permission_skipped.len()
+            + self.recent_skipped.len()
+            + self.vanished.len()
+    }
+
+    /// Sort operations by source name in natural order
+    ///
+    /// Applied before executing and before writing history, so the rename
+    /// order and history files are the same from run to run regardless of
+    /// the order in which entries were planned or fetched.
+    pub fn sort_operations(&mut self) {
+        self.operations
+            .sort_by(|a, b| natural_cmp(&a.source_name, &b.source_name));
+    }
+
     pub fn is_empty(&self) -> bool {
         self.operations.is_empty()
     }
@@ -136,6 +553,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rename_operation_quarantine() {
+        let op = RenameOperation::quarantine(PathBuf::from("/anime/[AS0] 12345"), 12345);
+
+        assert!(op.quarantined);
+        assert_eq!(op.destination_name, "_unresolved/[AS0] 12345");
+        assert_eq!(
+            op.destination_path,
+            PathBuf::from("/anime/_unresolved/[AS0] 12345")
+        );
+    }
+
     #[test]
     fn test_rename_result() {
         let mut result = RenameResult::new(RenameDirection::AniDbToReadable, true);
@@ -0,0 +1,441 @@
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::api::{AniDbClient, AnimeInfo, ApiConfig, ApiError};
+use crate::cache::{CacheConfig, CacheStore};
+use crate::parser::{HumanReadableFormat, ParsedDirectory};
+use crate::progress::ProgressObserver;
+use crate::validator::ValidationResult;
+
+use super::error::RenameError;
+use super::name_builder::comparable_name;
+use super::overrides::{Override, Overrides};
+use super::to_readable::save_cache;
+use super::types::{IdMismatch, RenameResult};
+
+/// Minimum normalized edit similarity for two titles to count as the same
+const SIMILARITY_THRESHOLD: f64 = 0.6;
+
+/// Options for checking embedded ids against AniDB titles
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    pub cache_expiry_days: u32,
+    pub cache_save_every: usize,
+    /// Ask the API for ids missing from the cache (never in a dry run)
+    pub fetch: bool,
+    pub dry_run: bool,
+    /// Local corrections; a folder named after its override is correct
+    pub overrides: Option<Overrides>,
+}
+
+/// Check the `[anidb-N]` id of each human-readable directory against its title
+///
+/// Ids whose AniDB title bears no resemblance to the folder's title (or
+/// which do not exist) are recorded in `result.id_mismatches`; ids that
+/// are neither cached nor fetched are counted in `result.unverified_ids`.
+/// Operations are left as they are: the caller decides what to do with
+/// flagged entries.
+pub fn verify_ids(
+    target_dir: &Path,
+    validation: &ValidationResult,
+    result: &mut RenameResult,
+    api_config: &ApiConfig,
+    options: &VerifyOptions,
+    progress: &mut dyn ProgressObserver,
+) -> Result<(), RenameError> {
+    let cache_config = CacheConfig {
+        save_every: options.cache_save_every,
+        ..CacheConfig::for_target_dir(target_dir, options.cache_expiry_days)
+    };
+    let mut cache = CacheStore::load(cache_config);
+
+    let client = if options.fetch && !options.dry_run {
+        if !api_config.is_configured() {
+            return Err(RenameError::ApiNotConfigured);
+        }
+        Some(
+            AniDbClient::new(api_config.clone()).map_err(|e| RenameError::ApiError {
+                id: 0,
+                message: e.to_string(),
+            })?,
+        )
+    } else {
+        None
+    };
+
+    let readable: Vec<&HumanReadableFormat> = validation
+        .directories
+        .iter()
+        .filter_map(|p| match p {
+            ParsedDirectory::HumanReadable(f) => Some(f),
+            _ => None,
+        })
+        .collect();
+
+    if client.is_some() {
+        let uncached = readable
+            .iter()
+            .filter(|f| cache.get(f.anidb_id).is_none())
+            .count();
+        progress.set_fetch_plan(
+            uncached,
+            Duration::from_secs(api_config.min_request_interval_secs),
+        );
+    }
+
+    for folder in readable {
+        let correction = options
+            .overrides
+            .as_ref()
+            .and_then(|overrides| overrides.get(folder.anidb_id));
+        if correction.is_some_and(|c| override_matches(folder, c)) {
+            debug!("AniDB ID {} matches its override", folder.anidb_id);
+            continue;
+        }
+
+        let info = match (cache.get(folder.anidb_id), &client) {
+            (Some(info), _) => {
+                result.cache_hits += 1;
+                Some(info)
+            }
+            (None, Some(client)) => {
+                progress.fetch_start(folder.anidb_id);
+                result.api_requests += 1;
+                let fetched = client.fetch_anime(folder.anidb_id);
+                progress.fetch_complete();
+                match fetched {
+                    Ok(info) => {
+                        cache.insert(&info);
+                        if let Err(e) = cache.checkpoint() {
+                            warn!("Failed to save cache: {}", e);
+                        }
+                        Some(info)
+                    }
+                    Err(ApiError::NotFound(_)) => None,
+                    Err(e) => {
+                        return Err(RenameError::ApiError {
+                            id: folder.anidb_id,
+                            message: e.to_string(),
+                        })
+                    }
+                }
+            }
+            (None, None) => {
+                debug!("AniDB ID {} not cached, not verified", folder.anidb_id);
+                result.unverified_ids += 1;
+                continue;
+            }
+        };
+
+        if info
+            .as_ref()
+            .is_some_and(|info| folder_matches(folder, info))
+        {
+            continue;
+        }
+
+        let anidb_title = info.map(|info| info.title_main);
+        warn!(
+            "AniDB ID {} of {:?} belongs to {:?}",
+            folder.anidb_id, folder.original_name, anidb_title
+        );
+        progress.warn(&format!(
+            "{}: AniDB ID {} is {}",
+            folder.original_name,
+            folder.anidb_id,
+            anidb_title
+                .as_deref()
+                .map(|t| format!("\"{}\"", t))
+                .unwrap_or_else(|| "not on AniDB".to_string())
+        ));
+        result.id_mismatches.push(IdMismatch {
+            source_name: folder.original_name.clone(),
+            anidb_id: folder.anidb_id,
+            anidb_title,
+        });
+    }
+
+    info!(
+        "Verified ids: {} mismatched, {} not verified",
+        result.id_mismatches.len(),
+        result.unverified_ids
+    );
+
+    save_cache(cache, progress);
+
+    Ok(())
+}
+
+/// Human-readable names whose cached AniDB title contradicts their id
+///
+/// Only the cache is read: uncached ids, and folders named after their
+/// override, pass. Each entry names the folder and the cached title, in
+/// the form strict parsing reports refused names.
+pub fn cached_id_conflicts(
+    target_dir: &Path,
+    directories: &[ParsedDirectory],
+    cache_expiry_days: u32,
+    overrides: Option<&Overrides>,
+) -> Vec<String> {
+    let cache = CacheStore::load(CacheConfig::for_target_dir(target_dir, cache_expiry_days));
+    directories
+        .iter()
+        .filter_map(|p| match p {
+            ParsedDirectory::HumanReadable(f) => Some(f),
+            _ => None,
+        })
+        .filter(|folder| {
+            !overrides
+                .and_then(|overrides| overrides.get(folder.anidb_id))
+                .is_some_and(|c| override_matches(folder, c))
+        })
+        .filter_map(|folder| {
+            let info = cache.get(folder.anidb_id)?;
+            (!folder_matches(folder, &info)).then(|| {
+                format!(
+                    "{}: AniDB ID {} is \"{}\" in the cache",
+                    folder.original_name, folder.anidb_id, info.title_main
+                )
+            })
+        })
+        .collect()
+}
+
+/// Whether any title of the folder resembles any AniDB title of the id
+fn folder_matches(folder: &HumanReadableFormat, info: &AnimeInfo) -> bool {
+    let anidb_titles: Vec<&str> = std::iter::once(info.title_main.as_str())
+        .chain(info.title_en.as_deref())
+        .collect();
+    resembles_any(folder, &anidb_titles)
+}
+
+/// Whether any title of the folder resembles a title set by its override
+fn override_matches(folder: &HumanReadableFormat, correction: &Override) -> bool {
+    let titles: Vec<&str> = correction
+        .title_main
+        .as_deref()
+        .into_iter()
+        .chain(correction.title_en.as_deref().filter(|t| !t.is_empty()))
+        .collect();
+    resembles_any(folder, &titles)
+}
+
+fn resembles_any(folder: &HumanReadableFormat, titles: &[&str]) -> bool {
+    let mut folder_titles =
+        std::iter::once(folder.title_jp.as_str()).chain(folder.title_en.as_deref());
+    folder_titles.any(|a| titles.iter().any(|b| titles_resemble(a, b)))
+}
+
+/// Whether two titles plausibly name the same anime
+///
+/// Titles are compared after undoing filename sanitization, lowercasing and
+/// dropping punctuation. One containing the other, or a normalized edit
+/// similarity of at least [`SIMILARITY_THRESHOLD`], counts as a match.
+pub fn titles_resemble(a: &str, b: &str) -> bool {
+    let a = normalize_title(a);
+    let b = normalize_title(b);
+    if a.is_empty() || b.is_empty() || a.contains(&b) || b.contains(&a) {
+        return true;
+    }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let distance = levenshtein(&a, &b) as f64;
+    1.0 - distance / a.len().max(b.len()) as f64 >= SIMILARITY_THRESHOLD
+}
+
+fn normalize_title(title: &str) -> String {
+    comparable_name(title)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Progress;
+    use crate::rename::RenameDirection;
+    use crate::scanner::DirectoryEntry;
+    use crate::validator::validate_directories;
+    use tempfile::tempdir;
+
+    fn info(id: u32, title: &str) -> AnimeInfo {
+        AnimeInfo {
+            anidb_id: id,
+            title_main: title.to_string(),
+            title_en: None,
+            release_year: None,
+            english_fallback: false,
+            restricted: false,
+            unaired: false,
+            anime_type: None,
+            episode_count: None,
+        }
+    }
+
+    #[test]
+    fn test_titles_resemble() {
+        assert!(titles_resemble("Cowboy Bebop", "Cowboy Bebop"));
+        assert!(titles_resemble(
+            "Cowboy Bebop",
+            "Cowboy Bebop: Tengoku no Tobira"
+        ));
+        assert!(titles_resemble(
+            "Shingeki no Kyojin：Season 2",
+            "Shingeki no Kyojin: Season 2"
+        ));
+        assert!(titles_resemble(
+            "Fullmetal Alchemist Brotherhod",
+            "Fullmetal Alchemist: Brotherhood"
+        ));
+        assert!(!titles_resemble("Cowboy Bebop", "Neon Genesis Evangelion"));
+    }
+
+    #[test]
+    fn test_cached_id_conflicts() {
+        let dir = tempdir().unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&info(1, "Cowboy Bebop"));
+        cache.insert(&info(2, "Neon Genesis Evangelion"));
+        cache.save().unwrap();
+
+        let entries = [
+            "Cowboy Bebop (1998) [anidb-1]",
+            "Trigun (1998) [anidb-2]",
+            "Naruto (2002) [anidb-3]",
+        ]
+        .map(|n| DirectoryEntry::new(n.to_string()));
+        let validation = validate_directories(&entries).unwrap();
+
+        assert_eq!(
+            cached_id_conflicts(dir.path(), &validation.directories, 30, None),
+            ["Trigun (1998) [anidb-2]: AniDB ID 2 is \"Neon Genesis Evangelion\" in the cache"]
+        );
+    }
+
+    #[test]
+    fn test_verify_ids_flags_mismatched_title() {
+        let dir = tempdir().unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&info(1, "Cowboy Bebop"));
+        cache.insert(&info(2, "Neon Genesis Evangelion"));
+        cache.save().unwrap();
+
+        let entries = [
+            "Cowboy Bebop (1998) [anidb-1]",
+            "Trigun (1998) [anidb-2]",
+            "Naruto (2002) [anidb-3]",
+        ]
+        .map(|n| DirectoryEntry::new(n.to_string()));
+        let validation = validate_directories(&entries).unwrap();
+        let mut result = RenameResult::new(RenameDirection::ReadableToAniDb, false);
+
+        let options = VerifyOptions {
+            cache_expiry_days: 30,
+            cache_save_every: 0,
+            fetch: false,
+            dry_run: false,
+            overrides: None,
+        };
+        verify_ids(
+            dir.path(),
+            &validation,
+            &mut result,
+            &ApiConfig::default(),
+            &options,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+
+        assert_eq!(result.id_mismatches.len(), 1);
+        assert_eq!(
+            result.id_mismatches[0].source_name,
+            "Trigun (1998) [anidb-2]"
+        );
+        assert_eq!(
+            result.id_mismatches[0].anidb_title.as_deref(),
+            Some("Neon Genesis Evangelion")
+        );
+        assert_eq!(result.unverified_ids, 1);
+    }
+
+    #[test]
+    fn test_verify_ids_fetch_requires_api_config() {
+        let dir = tempdir().unwrap();
+        let entries = [DirectoryEntry::new("Naruto (2002) [anidb-3]".to_string())];
+        let validation = validate_directories(&entries).unwrap();
+        let mut result = RenameResult::new(RenameDirection::ReadableToAniDb, false);
+
+        let options = VerifyOptions {
+            cache_expiry_days: 30,
+            cache_save_every: 0,
+            fetch: true,
+            dry_run: false,
+            overrides: None,
+        };
+        let outcome = verify_ids(
+            dir.path(),
+            &validation,
+            &mut result,
+            &ApiConfig::default(),
+            &options,
+            &mut Progress::silent(),
+        );
+        assert!(matches!(outcome, Err(RenameError::ApiNotConfigured)));
+    }
+
+    #[test]
+    fn test_verify_ids_accepts_overridden_title() {
+        let dir = tempdir().unwrap();
+        let mut cache = CacheStore::load(CacheConfig::for_target_dir(dir.path(), 30));
+        cache.insert(&info(2, "Neon Genesis Evangelion"));
+        cache.save().unwrap();
+        let overrides_path = dir.path().join("overrides.json");
+        std::fs::write(&overrides_path, r#"{"2": {"title_main": "Trigun"}}"#).unwrap();
+
+        let entries = [DirectoryEntry::new("Trigun (1998) [anidb-2]".to_string())];
+        let validation = validate_directories(&entries).unwrap();
+        let mut result = RenameResult::new(RenameDirection::ReadableToAniDb, false);
+
+        let options = VerifyOptions {
+            cache_expiry_days: 30,
+            cache_save_every: 0,
+            fetch: false,
+            dry_run: false,
+            overrides: Some(Overrides::load(&overrides_path).unwrap()),
+        };
+        verify_ids(
+            dir.path(),
+            &validation,
+            &mut result,
+            &ApiConfig::default(),
+            &options,
+            &mut Progress::silent(),
+        )
+        .unwrap();
+
+        assert!(result.id_mismatches.is_empty());
+    }
+}
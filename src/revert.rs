@@ -1,16 +1,23 @@
 use std::fs;
-use std::io::BufWriter;
+use std::io::{self, BufWriter};
 use std::path::{Path, PathBuf};
 
-use chrono::Utc;
-use tracing::{debug, error, info};
+use chrono::{DateTime, Utc};
+use tracing::{debug, error, info, warn};
 
 use crate::history::{
-    read_history, HistoryDirection, HistoryEntry, HistoryError, HistoryFile, OperationType,
-    HISTORY_VERSION,
+    read_history, reserve_unique_path, EntryKind, HistoryDirection, HistoryEntry, HistoryError,
+    HistoryFile, OperationType, HISTORY_VERSION,
 };
-use crate::progress::Progress;
-use crate::rename::RenameDirection;
+use crate::progress::ProgressObserver;
+use crate::rename::{
+    aside_name, is_missing, moved_parent, rename_no_replace, suffixed_name, trash_label,
+    verify_moves, ConflictResolution, ConflictResolver, PostRunCheck, RenameDirection,
+};
+use crate::run_id::{directory_span, new_run_id};
+use crate::scanner::UNRESOLVED_DIR;
+use crate::trash;
+use crate::version::{BuildInfo, VERSION};
 
 #[derive(Debug, thiserror::Error)]
 pub enum RevertError {
@@ -20,6 +27,17 @@ pub enum RevertError {
     #[error("Validation failed: {0}")]
     ValidationFailed(String),
 
+    #[error("Target directory changed during the revert: {expected:?} now resolves to {actual:?}")]
+    TargetChanged { expected: PathBuf, actual: PathBuf },
+
+    #[error(
+        "Reverting '{directory}' would move {input:?}, which the revert needs; move it out of that directory first"
+    )]
+    InputInside { directory: String, input: PathBuf },
+
+    #[error("'{0}' vanished after the revert was prepared")]
+    SourceVanished(String),
+
     #[error("Failed to rename '{from}' to '{to}': {source}")]
     RenameError {
         from: String,
@@ -38,6 +56,12 @@ pub enum RevertError {
 #[derive(Default)]
 pub struct RevertOptions {
     pub dry_run: bool,
+    /// How to resolve an original name that is occupied again
+    pub on_conflict: ConflictResolution,
+    /// Fail on a directory that vanished after preparing instead of skipping it
+    pub strict: bool,
+    /// Check every directory is back under its original name afterwards
+    pub post_run_verify: bool,
 }
 
 /// A single revert operation
@@ -48,6 +72,65 @@ pub struct RevertOperation {
     pub revert_path: PathBuf,
     pub revert_name: String,
     pub anidb_id: u32,
+    /// How a conflict with an occupied original name was resolved, if any
+    pub conflict: Option<ConflictResolution>,
+    /// Name the occupying directory is moved aside to before reverting
+    pub aside_name: Option<String>,
+    /// Directory moved aside by the original rename, restored after reverting
+    pub restore_aside: Option<String>,
+    /// Kind of the reverted change (quarantine moves cross directories)
+    pub kind: EntryKind,
+    /// For trash moves: the `.trashinfo` file of the item in the trash
+    pub trash_info: Option<PathBuf>,
+    /// Season or part marker of the reverted change
+    pub suffix: Option<String>,
+}
+
+/// Why a change cannot be reverted as things are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevertConflictKind {
+    /// The renamed directory is gone
+    Missing,
+    /// The original name is taken again (with `--on-conflict abort`)
+    Occupied,
+    /// No suffixed or aside name is free
+    NoFreeName,
+}
+
+impl RevertConflictKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            RevertConflictKind::Missing => "renamed directory not found",
+            RevertConflictKind::Occupied => "original name already exists",
+            RevertConflictKind::NoFreeName => "no free name to resolve the conflict",
+        }
+    }
+}
+
+/// A change of the history that cannot be reverted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevertConflict {
+    /// Name the original rename gave
+    pub current_name: String,
+    /// Name it would be reverted to
+    pub revert_name: String,
+    pub kind: RevertConflictKind,
+}
+
+impl std::fmt::Display for RevertConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            RevertConflictKind::Missing => write!(
+                f,
+                "Directory not found: '{}' (expected from previous rename)",
+                self.current_name
+            ),
+            RevertConflictKind::Occupied => {
+                write!(f, "Cannot revert: '{}' already exists", self.revert_name)
+            }
+            RevertConflictKind::NoFreeName => write!(f, "No free name for '{}'", self.revert_name),
+        }
+    }
 }
 
 /// Result of a revert operation
@@ -58,19 +141,45 @@ pub struct RevertResult {
     #[allow(dead_code)]
     pub direction: RenameDirection,
     pub original_history: PathBuf,
+    /// When the reverted operation was originally executed
+    pub original_executed_at: DateTime<Utc>,
     pub dry_run: bool,
     pub revert_history_path: Option<PathBuf>,
+    /// Directories that disappeared after the revert was prepared, left out
+    pub vanished: Vec<String>,
+    /// Changes a dry run found could not be reverted, in history order
+    ///
+    /// A real revert fails on these before renaming anything.
+    pub conflicts: Vec<RevertConflict>,
+    /// Check of the filesystem after reverting (`--post-run-verify`)
+    pub post_verify: Option<PostRunCheck>,
+    /// Short random id of this revert, carried by its log spans and history
+    pub run_id: String,
 }
 
 /// Execute a revert operation using a history file
+///
+/// Conflicts are resolved with the fixed `options.on_conflict` policy.
 pub fn revert_from_history(
     history_path: &Path,
     options: &RevertOptions,
-    progress: &mut Progress,
+    progress: &mut dyn ProgressObserver,
+) -> Result<RevertResult, RevertError> {
+    let mut policy = options.on_conflict;
+    revert_from_history_with(history_path, options, &mut policy, progress)
+}
+
+/// Execute a revert operation, asking `resolver` how to handle occupied original names
+pub fn revert_from_history_with(
+    history_path: &Path,
+    options: &RevertOptions,
+    resolver: &mut dyn ConflictResolver,
+    progress: &mut dyn ProgressObserver,
 ) -> Result<RevertResult, RevertError> {
     info!("Loading history from: {:?}", history_path);
+    let run_id = new_run_id();
 
-    // Read history file
+    // Read history file; it is not opened again, but its path must survive
     let history = read_history(history_path)?;
 
     info!(
@@ -79,11 +188,35 @@ pub fn revert_from_history(
         history.executed_at
     );
 
-    progress.revert_start(history.changes.len(), &history.executed_at.to_string());
-
-    // Prepare revert operations
-    let target_dir = &history.target_directory;
-    let operations = prepare_revert_operations(&history, target_dir, progress)?;
+    progress.revert_start(history.changes.len(), &history.executed_at);
+
+    // Prepare revert operations from the canonical root
+    let target_dir = &fs::canonicalize(&history.target_directory)
+        .unwrap_or_else(|_| history.target_directory.clone());
+    let (mut operations, conflicts) =
+        prepare_revert_operations(&history, target_dir, &run_id, resolver, progress);
+    check_inputs_outside(
+        &operations,
+        &[
+            fs::canonicalize(history_path).unwrap_or_else(|_| history_path.to_path_buf()),
+            target_dir.join(CACHE_FILE_NAME),
+        ],
+    )?;
+    if !conflicts.is_empty() {
+        for conflict in &conflicts {
+            warn!("Cannot revert: {}", conflict);
+        }
+        // A dry run shows them all; a real revert renames nothing
+        if !options.dry_run {
+            error!("Revert validation failed:");
+            for conflict in &conflicts {
+                error!("  - {}", conflict);
+                progress.warn(&conflict.to_string());
+            }
+            let errors: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+            return Err(RevertError::ValidationFailed(errors.join("; ")));
+        }
+    }
 
     // Determine reversed direction
     let direction = match history.direction {
@@ -92,16 +225,31 @@ pub fn revert_from_history(
     };
 
     let mut revert_history_path = None;
+    let mut vanished = Vec::new();
+    let mut post_verify = None;
 
     // Execute reverts (unless dry run)
     if !options.dry_run {
-        execute_reverts(&operations, progress)?;
+        vanished = execute_reverts(&mut operations, &run_id, options.strict, resolver, progress)?;
+        operations.retain(|op| !vanished.contains(&op.current_name));
+        if options.post_run_verify {
+            post_verify = Some(verify_moves(&revert_moves(&operations)));
+        }
 
         // Write revert history
         let revert_time = Utc::now();
-        let revert_history = create_revert_history(&history, &operations, &revert_time);
+        let original_filename = history_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string());
+        let revert_history = create_revert_history(
+            &history,
+            original_filename,
+            &operations,
+            &revert_time,
+            &run_id,
+        );
         let filename = history.generate_revert_filename(&revert_time);
-        let revert_path = target_dir.join(&filename);
+        let revert_path = reserve_unique_path(target_dir, &filename)?;
 
         write_revert_history(&revert_history, &revert_path)?;
         progress.history_written(&revert_path);
@@ -116,87 +264,369 @@ pub fn revert_from_history(
         operations,
         direction,
         original_history: history_path.to_path_buf(),
+        original_executed_at: history.executed_at,
         dry_run: options.dry_run,
         revert_history_path,
+        vanished,
+        conflicts,
+        post_verify,
+        run_id,
     })
 }
 
+/// The moves made by executed reverts
+fn revert_moves(operations: &[RevertOperation]) -> Vec<(PathBuf, PathBuf)> {
+    let mut moves = Vec::new();
+    for op in operations {
+        if op.conflict == Some(ConflictResolution::Skip) {
+            continue;
+        }
+        if let Some(aside) = &op.aside_name {
+            moves.push((op.revert_path.clone(), op.revert_path.with_file_name(aside)));
+        }
+        moves.push((op.current_path.clone(), op.revert_path.clone()));
+        if let Some(aside) = &op.restore_aside {
+            moves.push((
+                op.current_path.with_file_name(aside),
+                op.current_path.clone(),
+            ));
+        }
+    }
+    moves
+}
+
+/// Cache file of a target directory, as the cache module names it
+const CACHE_FILE_NAME: &str = ".anidb2folder-cache.json";
+
+/// Fail if an operation would move a directory containing one of `inputs`
+///
+/// A history file kept in a subdirectory that the history itself renames
+/// would otherwise be moved away mid-run, and the revert history after it.
+fn check_inputs_outside(
+    operations: &[RevertOperation],
+    inputs: &[PathBuf],
+) -> Result<(), RevertError> {
+    for op in operations {
+        // A directory occupying the revert path is moved too, aside
+        for dir in [&op.current_path, &op.revert_path] {
+            if let Some(input) = inputs.iter().find(|input| input.starts_with(dir)) {
+                return Err(RevertError::InputInside {
+                    directory: op.current_name.clone(),
+                    input: input.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Plan the reverts of `history`, with the changes that cannot be reverted
 fn prepare_revert_operations(
     history: &HistoryFile,
     target_dir: &Path,
-    progress: &mut Progress,
-) -> Result<Vec<RevertOperation>, RevertError> {
+    run_id: &str,
+    resolver: &mut dyn ConflictResolver,
+    progress: &mut dyn ProgressObserver,
+) -> (Vec<RevertOperation>, Vec<RevertConflict>) {
     let mut operations = Vec::with_capacity(history.changes.len());
-    let mut errors = Vec::new();
+    let mut conflicts = Vec::new();
 
     for entry in &history.changes {
-        // For revert: source becomes destination, destination becomes source
-        let current_path = target_dir.join(&entry.destination);
-        let revert_path = target_dir.join(&entry.source);
+        let _span = directory_span(run_id, "prepare", entry.anidb_id, &entry.destination).entered();
+
+        // Skipped changes were never applied, nothing to revert
+        if entry.is_skipped() {
+            debug!("Skipping unapplied change: {}", entry.source);
+            continue;
+        }
+
+        // For revert: source becomes destination, destination becomes source;
+        // a trashed directory is found through its .trashinfo file
+        let current_path = entry
+            .trash_info
+            .as_deref()
+            .and_then(trash::item_path)
+            .unwrap_or_else(|| target_dir.join(&entry.destination));
+        let mut revert_path = target_dir.join(&entry.source);
+        let mut revert_name = entry.source.clone();
+        let mut conflict = None;
+        let mut aside = None;
+
+        let mut conflict_of = |kind| {
+            conflicts.push(RevertConflict {
+                current_name: entry.destination.clone(),
+                revert_name: entry.source.clone(),
+                kind,
+            })
+        };
 
         debug!("Checking revert: {} -> {}", entry.destination, entry.source);
 
         // Check current (destination) exists
         if !current_path.exists() {
-            errors.push(format!(
-                "Directory not found: '{}' (expected from previous rename)",
-                entry.destination
-            ));
+            conflict_of(RevertConflictKind::Missing);
             continue;
         }
 
         // Check original (source) doesn't exist
         if revert_path.exists() {
-            errors.push(format!("Cannot revert: '{}' already exists", entry.source));
-            continue;
+            let resolution = resolver.resolve(&entry.destination, &entry.source);
+
+            match resolution {
+                ConflictResolution::Abort => {
+                    conflict_of(RevertConflictKind::Occupied);
+                    continue;
+                }
+                ConflictResolution::Skip => {
+                    progress.warn(&format!("Skipped: '{}' already exists", entry.source));
+                    continue;
+                }
+                ConflictResolution::Suffix => match suffixed_name(target_dir, &entry.source) {
+                    Some(name) => {
+                        revert_path = target_dir.join(&name);
+                        revert_name = name;
+                    }
+                    None => {
+                        conflict_of(RevertConflictKind::NoFreeName);
+                        continue;
+                    }
+                },
+                ConflictResolution::Aside => match aside_name(target_dir, &entry.source) {
+                    Some(name) => aside = Some(name),
+                    None => {
+                        conflict_of(RevertConflictKind::NoFreeName);
+                        continue;
+                    }
+                },
+            }
+
+            conflict = Some(resolution);
         }
 
+        // Directory moved aside by the original rename is put back afterwards
+        let restore_aside = match &entry.aside {
+            Some(name) if target_dir.join(name).exists() => Some(name.clone()),
+            Some(name) => {
+                progress.warn(&format!(
+                    "Cannot restore '{}': directory moved aside is missing",
+                    name
+                ));
+                None
+            }
+            None => None,
+        };
+
         operations.push(RevertOperation {
             current_path,
             current_name: entry.destination.clone(),
             revert_path,
-            revert_name: entry.source.clone(),
+            revert_name,
             anidb_id: entry.anidb_id,
+            conflict,
+            aside_name: aside,
+            restore_aside,
+            kind: entry.kind,
+            trash_info: entry.trash_info.clone(),
+            suffix: entry.suffix.clone(),
         });
     }
 
-    if !errors.is_empty() {
-        error!("Revert validation failed:");
-        for err in &errors {
-            error!("  - {}", err);
-            progress.warn(err);
-        }
-        return Err(RevertError::ValidationFailed(errors.join("; ")));
-    }
-
-    Ok(operations)
+    (operations, conflicts)
 }
 
+/// Execute prepared reverts
+///
+/// An original name occupied after preparing is never replaced: `resolver`
+/// decides how to handle it and the decision is recorded on the operation.
+/// Execute prepared reverts, returning the directories that had vanished
+///
+/// With `strict` a vanished directory fails the revert instead.
 fn execute_reverts(
-    operations: &[RevertOperation],
-    progress: &mut Progress,
-) -> Result<(), RevertError> {
+    operations: &mut [RevertOperation],
+    run_id: &str,
+    strict: bool,
+    resolver: &mut dyn ConflictResolver,
+    progress: &mut dyn ProgressObserver,
+) -> Result<Vec<String>, RevertError> {
     let total = operations.len();
+    let mut vanished = Vec::new();
 
-    for (i, op) in operations.iter().enumerate() {
+    for (i, op) in operations.iter_mut().enumerate() {
+        let _span = directory_span(run_id, "revert", op.anidb_id, &op.current_name).entered();
         progress.revert_progress(i + 1, total, &op.current_name, &op.revert_name);
 
+        if let Some(actual) = moved_parent(&op.current_path) {
+            return Err(RevertError::TargetChanged {
+                expected: op
+                    .current_path
+                    .parent()
+                    .unwrap_or(&op.current_path)
+                    .to_path_buf(),
+                actual,
+            });
+        }
+        if is_missing(&op.current_path) {
+            if strict {
+                return Err(RevertError::SourceVanished(op.current_name.clone()));
+            }
+            warn!("Vanished after preparing, skipping: {}", op.current_name);
+            progress.warn(&format!("{}: vanished, skipped", op.current_name));
+            vanished.push(op.current_name.clone());
+            continue;
+        }
+        move_aside(op)?;
+
         info!("Reverting: {} -> {}", op.current_name, op.revert_name);
+        match revert_move(op) {
+            Err(RevertError::RenameError { source, .. })
+                if source.kind() == io::ErrorKind::AlreadyExists && op.conflict.is_none() =>
+            {
+                let resolution = resolver.resolve(&op.current_name, &op.revert_name);
+                warn!(
+                    "'{}' appeared after preparing the revert ({})",
+                    op.revert_name,
+                    resolution.description()
+                );
+                progress.warn(&format!(
+                    "'{}' appeared after preparing the revert ({})",
+                    op.revert_name,
+                    resolution.description()
+                ));
+                if !apply_late_resolution(op, resolution)? {
+                    continue;
+                }
+                move_aside(op)?;
+                info!("Reverting: {} -> {}", op.current_name, op.revert_name);
+                revert_move(op)?;
+            }
+            other => other?,
+        }
 
-        fs::rename(&op.current_path, &op.revert_path).map_err(|e| RevertError::RenameError {
-            from: op.current_name.clone(),
-            to: op.revert_name.clone(),
-            source: e,
-        })?;
+        if let Some(aside) = &op.restore_aside {
+            info!("Restoring '{}' to '{}'", aside, op.current_name);
+            move_dir(
+                &op.current_path.with_file_name(aside),
+                aside,
+                &op.current_name,
+            )?;
+        }
+    }
+
+    Ok(vanished)
+}
+
+/// Record a resolution for an original name occupied at execute time
+///
+/// Returns `false` when the operation is skipped.
+fn apply_late_resolution(
+    op: &mut RevertOperation,
+    resolution: ConflictResolution,
+) -> Result<bool, RevertError> {
+    let occupied = || RevertError::ValidationFailed(format!("'{}' already exists", op.revert_name));
+    let dir = op.revert_path.parent().ok_or_else(occupied)?.to_path_buf();
+
+    match resolution {
+        ConflictResolution::Abort => return Err(occupied()),
+        ConflictResolution::Skip => {}
+        ConflictResolution::Suffix => {
+            let name = suffixed_name(&dir, &op.revert_name).ok_or_else(occupied)?;
+            op.revert_path = dir.join(&name);
+            op.revert_name = name;
+        }
+        ConflictResolution::Aside => {
+            op.aside_name = Some(aside_name(&dir, &op.revert_name).ok_or_else(occupied)?);
+        }
+    }
+
+    op.conflict = Some(resolution);
+    Ok(resolution != ConflictResolution::Skip)
+}
+
+/// Move the directory occupying the original name aside, if so resolved
+fn move_aside(op: &RevertOperation) -> Result<(), RevertError> {
+    match &op.aside_name {
+        Some(aside) => {
+            info!("Moving existing '{}' aside to '{}'", op.revert_name, aside);
+            move_dir(&op.revert_path, &op.revert_name, aside)
+        }
+        None => Ok(()),
+    }
+}
+
+fn revert_move(op: &mut RevertOperation) -> Result<(), RevertError> {
+    match op.kind {
+        EntryKind::Quarantine => move_quarantined(op),
+        EntryKind::Trash => move_trashed(op),
+        EntryKind::Rename => move_dir(&op.current_path, &op.current_name, &op.revert_name),
+    }
+}
+
+/// Restore a directory from the system trash, or trash it again
+///
+/// Reverting a revert sends the directory back to the trash, where it
+/// may get a different name; the operation records where it went.
+fn move_trashed(op: &mut RevertOperation) -> Result<(), RevertError> {
+    let rename_error = |e| RevertError::RenameError {
+        from: op.current_name.clone(),
+        to: op.revert_name.clone(),
+        source: e,
+    };
+
+    if trash::is_trashed(&op.current_path) {
+        trash::restore(&op.current_path, &op.revert_path).map_err(rename_error)?;
+        op.trash_info = None;
+    } else {
+        let trashed = trash::move_to_trash(&op.current_path).map_err(rename_error)?;
+        op.revert_name = trash_label(&trashed.path);
+        op.revert_path = trashed.path;
+        op.trash_info = Some(trashed.info);
     }
 
     Ok(())
 }
 
+/// Move a directory into or out of the unresolved directory
+///
+/// The unresolved directory is created when moving into it and removed
+/// once the last directory has been moved out.
+fn move_quarantined(op: &RevertOperation) -> Result<(), RevertError> {
+    let rename_error = |e| RevertError::RenameError {
+        from: op.current_name.clone(),
+        to: op.revert_name.clone(),
+        source: e,
+    };
+
+    if let Some(dir) = op.revert_path.parent() {
+        fs::create_dir_all(dir).map_err(rename_error)?;
+    }
+    rename_no_replace(&op.current_path, &op.revert_path).map_err(rename_error)?;
+
+    if let Some(dir) = op.current_path.parent() {
+        if dir.file_name().is_some_and(|n| n == UNRESOLVED_DIR) && fs::remove_dir(dir).is_ok() {
+            debug!("Removed empty {:?}", dir);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rename a directory to a sibling name
+fn move_dir(from_path: &Path, from: &str, to: &str) -> Result<(), RevertError> {
+    rename_no_replace(from_path, &from_path.with_file_name(to)).map_err(|e| {
+        RevertError::RenameError {
+            from: from.to_string(),
+            to: to.to_string(),
+            source: e,
+        }
+    })
+}
+
 fn create_revert_history(
     original: &HistoryFile,
+    original_filename: Option<String>,
     operations: &[RevertOperation],
-    revert_time: &chrono::DateTime<Utc>,
+    revert_time: &DateTime<Utc>,
+    run_id: &str,
 ) -> HistoryFile {
     let reversed_direction = match original.direction {
         HistoryDirection::AnidbToReadable => HistoryDirection::ReadableToAnidb,
@@ -210,18 +640,32 @@ fn create_revert_history(
             destination: op.revert_name.clone(),
             anidb_id: op.anidb_id,
             truncated: false,
+            conflict: op.conflict,
+            aside: op.aside_name.clone(),
+            kind: op.kind,
+            trash_info: op.trash_info.clone(),
+            suffix: op.suffix.clone(),
         })
         .collect();
 
-    HistoryFile {
+    let mut history = HistoryFile {
         version: HISTORY_VERSION.to_string(),
         executed_at: *revert_time,
         operation: OperationType::Revert,
         direction: reversed_direction,
         target_directory: original.target_directory.clone(),
-        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        tool_version: VERSION.to_string(),
+        build: Some(BuildInfo::current()),
         changes,
-    }
+        checksum: None,
+        reverted_from: original_filename,
+        original_executed_at: Some(original.executed_at),
+        options: None,
+        run_id: Some(run_id.to_string()),
+    };
+
+    history.seal();
+    history
 }
 
 fn write_revert_history(history: &HistoryFile, path: &Path) -> Result<(), RevertError> {
@@ -241,6 +685,7 @@ fn write_revert_history(history: &HistoryFile, path: &Path) -> Result<(), Revert
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::progress::Progress;
     use std::io::Write;
     use tempfile::tempdir;
 
@@ -272,20 +717,36 @@ mod tests {
             direction: HistoryDirection::AnidbToReadable,
             target_directory: dir.path().to_path_buf(),
             tool_version: "0.1.0".to_string(),
+            build: None,
             changes: vec![
                 HistoryEntry {
                     source: "12345".to_string(),
                     destination: "Anime Title (2020) [anidb-12345]".to_string(),
                     anidb_id: 12345,
                     truncated: false,
+                    conflict: None,
+                    aside: None,
+                    kind: EntryKind::Rename,
+                    trash_info: None,
+                    suffix: None,
                 },
                 HistoryEntry {
                     source: "[X] 99".to_string(),
                     destination: "[X] Other Title (2019) [anidb-99]".to_string(),
                     anidb_id: 99,
                     truncated: false,
+                    conflict: None,
+                    aside: None,
+                    kind: EntryKind::Rename,
+                    trash_info: None,
+                    suffix: None,
                 },
             ],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
         };
 
         let history_path = dir.path().join("anidb2folder-history-20260115-100000.json");
@@ -300,7 +761,10 @@ mod tests {
         let (dir, history_path) = setup_test_scenario();
         let mut progress = test_progress();
 
-        let options = RevertOptions { dry_run: false };
+        let options = RevertOptions {
+            dry_run: false,
+            ..Default::default()
+        };
         let result = revert_from_history(&history_path, &options, &mut progress).unwrap();
 
         assert_eq!(result.operations.len(), 2);
@@ -323,7 +787,10 @@ mod tests {
         let (dir, history_path) = setup_test_scenario();
         let mut progress = test_progress();
 
-        let options = RevertOptions { dry_run: true };
+        let options = RevertOptions {
+            dry_run: true,
+            ..Default::default()
+        };
         let result = revert_from_history(&history_path, &options, &mut progress).unwrap();
 
         assert_eq!(result.operations.len(), 2);
@@ -334,6 +801,91 @@ mod tests {
         assert!(!dir.path().join("12345").exists());
     }
 
+    #[test]
+    fn test_revert_refuses_to_move_its_history_file() {
+        let (dir, history_path) = setup_test_scenario();
+        let inside = dir
+            .path()
+            .join("Anime Title (2020) [anidb-12345]")
+            .join("anidb2folder-history-20260115-100000.json");
+        fs::rename(&history_path, &inside).unwrap();
+
+        let mut progress = test_progress();
+        for dry_run in [true, false] {
+            let options = RevertOptions {
+                dry_run,
+                ..Default::default()
+            };
+            match revert_from_history(&inside, &options, &mut progress) {
+                Err(RevertError::InputInside { directory, .. }) => {
+                    assert_eq!(directory, "Anime Title (2020) [anidb-12345]")
+                }
+                other => panic!("expected InputInside, got {:?}", other),
+            }
+        }
+        // Nothing was renamed
+        assert!(inside.exists());
+        assert!(dir
+            .path()
+            .join("[X] Other Title (2019) [anidb-99]")
+            .exists());
+    }
+
+    #[test]
+    fn test_revert_dry_run_lists_all_conflicts() {
+        let (dir, history_path) = setup_test_scenario();
+        let mut history = read_history(&history_path).unwrap();
+        history.changes.push(HistoryEntry {
+            source: "7".to_string(),
+            destination: "Third (2001) [anidb-7]".to_string(),
+            anidb_id: 7,
+            ..history.changes[0].clone()
+        });
+        serde_json::to_writer_pretty(fs::File::create(&history_path).unwrap(), &history).unwrap();
+        fs::create_dir(dir.path().join("12345")).unwrap();
+        fs::create_dir(dir.path().join("Third (2001) [anidb-7]")).unwrap();
+        fs::remove_dir(dir.path().join("[X] Other Title (2019) [anidb-99]")).unwrap();
+
+        let options = RevertOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let result = revert_from_history(&history_path, &options, &mut test_progress()).unwrap();
+
+        assert_eq!(result.operations.len(), 1);
+        assert_eq!(result.operations[0].revert_name, "7");
+        let conflicts: Vec<(&str, RevertConflictKind)> = result
+            .conflicts
+            .iter()
+            .map(|c| (c.current_name.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            conflicts,
+            [
+                (
+                    "Anime Title (2020) [anidb-12345]",
+                    RevertConflictKind::Occupied
+                ),
+                (
+                    "[X] Other Title (2019) [anidb-99]",
+                    RevertConflictKind::Missing
+                ),
+            ]
+        );
+
+        // The real revert fails on the same conflicts without renaming anything
+        let err = revert_from_history(
+            &history_path,
+            &RevertOptions::default(),
+            &mut test_progress(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, RevertError::ValidationFailed(ref m)
+            if m == "Cannot revert: '12345' already exists; \
+                     Directory not found: '[X] Other Title (2019) [anidb-99]' (expected from previous rename)"));
+        assert!(dir.path().join("Third (2001) [anidb-7]").exists());
+    }
+
     #[test]
     fn test_revert_missing_directory() {
         let dir = tempdir().unwrap();
@@ -347,12 +899,23 @@ mod tests {
             direction: HistoryDirection::AnidbToReadable,
             target_directory: dir.path().to_path_buf(),
             tool_version: "0.1.0".to_string(),
+            build: None,
             changes: vec![HistoryEntry {
                 source: "12345".to_string(),
                 destination: "Missing Dir [anidb-12345]".to_string(),
                 anidb_id: 12345,
                 truncated: false,
+                conflict: None,
+                aside: None,
+                kind: EntryKind::Rename,
+                trash_info: None,
+                suffix: None,
             }],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
         };
 
         let history_path = dir.path().join("test-history.json");
@@ -368,12 +931,28 @@ mod tests {
         let (_dir, history_path) = setup_test_scenario();
         let mut progress = test_progress();
 
-        let options = RevertOptions { dry_run: false };
+        let options = RevertOptions {
+            dry_run: false,
+            ..Default::default()
+        };
         let result = revert_from_history(&history_path, &options, &mut progress).unwrap();
 
         // Check revert history was created
         assert!(result.revert_history_path.is_some());
-        assert!(result.revert_history_path.unwrap().exists());
+        let revert_path = result.revert_history_path.unwrap();
+        assert!(revert_path.exists());
+
+        // Revert history links back to the original
+        let original = read_history(&history_path).unwrap();
+        let revert_history = read_history(&revert_path).unwrap();
+        assert_eq!(
+            revert_history.reverted_from.as_deref(),
+            Some("anidb2folder-history-20260115-100000.json")
+        );
+        assert_eq!(
+            revert_history.original_executed_at,
+            Some(original.executed_at)
+        );
     }
 
     #[test]
@@ -389,15 +968,332 @@ mod tests {
         assert!(matches!(result, Err(RevertError::ValidationFailed(_))));
     }
 
+    #[test]
+    fn test_revert_conflict_skip_policy() {
+        let (dir, history_path) = setup_test_scenario();
+        let mut progress = test_progress();
+
+        fs::create_dir(dir.path().join("12345")).unwrap();
+
+        let options = RevertOptions {
+            dry_run: false,
+            on_conflict: ConflictResolution::Skip,
+            ..Default::default()
+        };
+        let result = revert_from_history(&history_path, &options, &mut progress).unwrap();
+
+        assert_eq!(result.operations.len(), 1);
+        assert!(dir.path().join("Anime Title (2020) [anidb-12345]").exists());
+        assert!(dir.path().join("[X] 99").exists());
+    }
+
+    #[test]
+    fn test_revert_spans_and_history_carry_run_id() {
+        use crate::run_id::testing::SpanRecorder;
+
+        let (_dir, history_path) = setup_test_scenario();
+        let recorder = SpanRecorder::default();
+
+        let result = tracing::subscriber::with_default(recorder.clone(), || {
+            revert_from_history(
+                &history_path,
+                &RevertOptions::default(),
+                &mut test_progress(),
+            )
+        })
+        .unwrap();
+
+        let spans = recorder.spans();
+        let stages: Vec<(&str, &str)> = spans
+            .iter()
+            .filter(|(name, _)| name == "directory")
+            .map(|(_, f)| (f["stage"].as_str(), f["anidb_id"].as_str()))
+            .collect();
+        assert_eq!(
+            stages,
+            [
+                ("prepare", "12345"),
+                ("prepare", "99"),
+                ("revert", "12345"),
+                ("revert", "99")
+            ]
+        );
+        assert!(spans
+            .iter()
+            .filter(|(name, _)| name == "directory")
+            .all(|(_, f)| f["run_id"] == result.run_id));
+
+        let revert_history = read_history(result.revert_history_path.as_ref().unwrap()).unwrap();
+        assert_eq!(revert_history.run_id.as_ref(), Some(&result.run_id));
+        assert_eq!(revert_history.build, Some(BuildInfo::current()));
+    }
+
+    #[test]
+    fn test_revert_late_conflict_uses_policy() {
+        let (dir, history_path) = setup_test_scenario();
+        let mut progress = test_progress();
+        let history = read_history(&history_path).unwrap();
+
+        let (mut operations, _) = prepare_revert_operations(
+            &history,
+            dir.path(),
+            "0badcafe",
+            &mut ConflictResolution::Abort,
+            &mut progress,
+        );
+
+        // Original name taken by another process after preparing
+        fs::create_dir(dir.path().join("12345")).unwrap();
+
+        execute_reverts(
+            &mut operations,
+            "0badcafe",
+            false,
+            &mut ConflictResolution::Suffix,
+            &mut progress,
+        )
+        .unwrap();
+
+        assert_eq!(operations[0].revert_name, "12345 (2)");
+        assert_eq!(operations[0].conflict, Some(ConflictResolution::Suffix));
+        assert!(dir.path().join("12345").exists());
+        assert!(dir.path().join("12345 (2)").exists());
+        assert!(!dir.path().join("Anime Title (2020) [anidb-12345]").exists());
+    }
+
+    #[test]
+    fn test_revert_skips_vanished_directory() {
+        let mut progress = test_progress();
+        for strict in [false, true] {
+            let (dir, history_path) = setup_test_scenario();
+            let history = read_history(&history_path).unwrap();
+            let (mut operations, _) = prepare_revert_operations(
+                &history,
+                dir.path(),
+                "0badcafe",
+                &mut ConflictResolution::Abort,
+                &mut progress,
+            );
+
+            // Deleted by another process after preparing
+            fs::remove_dir(dir.path().join("Anime Title (2020) [anidb-12345]")).unwrap();
+
+            let outcome = execute_reverts(
+                &mut operations,
+                "0badcafe",
+                strict,
+                &mut ConflictResolution::Abort,
+                &mut progress,
+            );
+            if strict {
+                assert!(matches!(outcome, Err(RevertError::SourceVanished(_))));
+            } else {
+                assert_eq!(outcome.unwrap(), ["Anime Title (2020) [anidb-12345]"]);
+                assert!(dir.path().join("[X] 99").exists());
+                assert!(!dir.path().join("12345").exists());
+            }
+        }
+    }
+
+    #[test]
+    fn test_revert_conflict_aside_policy() {
+        let (dir, history_path) = setup_test_scenario();
+        let mut progress = test_progress();
+
+        fs::create_dir(dir.path().join("12345")).unwrap();
+
+        let options = RevertOptions {
+            dry_run: false,
+            on_conflict: ConflictResolution::Aside,
+            ..Default::default()
+        };
+        let result = revert_from_history(&history_path, &options, &mut progress).unwrap();
+
+        assert_eq!(result.operations.len(), 2);
+        assert!(dir.path().join("12345").exists());
+        assert!(dir.path().join("12345.aside").exists());
+
+        let revert_history = read_history(&result.revert_history_path.unwrap()).unwrap();
+        let entry = &revert_history.changes[0];
+        assert_eq!(entry.conflict, Some(ConflictResolution::Aside));
+        assert_eq!(entry.aside.as_deref(), Some("12345.aside"));
+    }
+
+    #[test]
+    fn test_revert_ignores_skipped_and_restores_aside() {
+        let dir = tempdir().unwrap();
+        let mut progress = test_progress();
+
+        fs::create_dir(dir.path().join("Title [anidb-1]")).unwrap();
+        fs::create_dir(dir.path().join("Title [anidb-1].aside")).unwrap();
+        fs::create_dir(dir.path().join("2")).unwrap();
+
+        let history = HistoryFile {
+            version: HISTORY_VERSION.to_string(),
+            executed_at: Utc::now(),
+            operation: OperationType::Rename,
+            direction: HistoryDirection::AnidbToReadable,
+            target_directory: dir.path().to_path_buf(),
+            tool_version: "0.1.0".to_string(),
+            build: None,
+            changes: vec![
+                HistoryEntry {
+                    source: "1".to_string(),
+                    destination: "Title [anidb-1]".to_string(),
+                    anidb_id: 1,
+                    truncated: false,
+                    conflict: Some(ConflictResolution::Aside),
+                    aside: Some("Title [anidb-1].aside".to_string()),
+                    kind: EntryKind::Rename,
+                    trash_info: None,
+                    suffix: None,
+                },
+                HistoryEntry {
+                    source: "2".to_string(),
+                    destination: "Other [anidb-2]".to_string(),
+                    anidb_id: 2,
+                    truncated: false,
+                    conflict: Some(ConflictResolution::Skip),
+                    aside: None,
+                    kind: EntryKind::Rename,
+                    trash_info: None,
+                    suffix: None,
+                },
+            ],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
+        };
+
+        let history_path = dir.path().join("test-history.json");
+        let file = fs::File::create(&history_path).unwrap();
+        serde_json::to_writer_pretty(file, &history).unwrap();
+
+        let result =
+            revert_from_history(&history_path, &RevertOptions::default(), &mut progress).unwrap();
+
+        assert_eq!(result.operations.len(), 1);
+        assert!(dir.path().join("1").exists());
+        assert!(dir.path().join("Title [anidb-1]").exists());
+        assert!(!dir.path().join("Title [anidb-1].aside").exists());
+        assert!(dir.path().join("2").exists());
+    }
+
     #[test]
     fn test_revert_direction_reversed() {
         let (_dir, history_path) = setup_test_scenario();
         let mut progress = test_progress();
 
-        let options = RevertOptions { dry_run: true };
+        let options = RevertOptions {
+            dry_run: true,
+            ..Default::default()
+        };
         let result = revert_from_history(&history_path, &options, &mut progress).unwrap();
 
         // Original was AnidbToReadable, so revert should be ReadableToAniDb
         assert_eq!(result.direction, RenameDirection::ReadableToAniDb);
     }
+
+    #[test]
+    fn test_revert_quarantine_moves_back() {
+        let dir = tempdir().unwrap();
+        let mut progress = test_progress();
+
+        fs::create_dir_all(dir.path().join("_unresolved").join("12345")).unwrap();
+
+        let history = HistoryFile {
+            version: HISTORY_VERSION.to_string(),
+            executed_at: Utc::now(),
+            operation: OperationType::Rename,
+            direction: HistoryDirection::AnidbToReadable,
+            target_directory: dir.path().to_path_buf(),
+            tool_version: "0.1.0".to_string(),
+            build: None,
+            changes: vec![HistoryEntry {
+                source: "12345".to_string(),
+                destination: "_unresolved/12345".to_string(),
+                anidb_id: 12345,
+                truncated: false,
+                conflict: None,
+                aside: None,
+                kind: EntryKind::Quarantine,
+                trash_info: None,
+                suffix: None,
+            }],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
+        };
+
+        let history_path = dir.path().join("test-history.json");
+        let file = fs::File::create(&history_path).unwrap();
+        serde_json::to_writer_pretty(file, &history).unwrap();
+
+        let result =
+            revert_from_history(&history_path, &RevertOptions::default(), &mut progress).unwrap();
+
+        assert!(dir.path().join("12345").is_dir());
+        assert!(!dir.path().join("_unresolved").exists());
+
+        // The revert history keeps the kind, so it can be reverted again
+        let revert_history = read_history(&result.revert_history_path.unwrap()).unwrap();
+        assert_eq!(revert_history.changes[0].kind, EntryKind::Quarantine);
+    }
+
+    #[test]
+    fn test_revert_trash_restores_from_trash() {
+        let dir = tempdir().unwrap();
+        let mut progress = test_progress();
+        let target = dir.path().join("anime");
+        fs::create_dir_all(target.join("12345")).unwrap();
+        let trashed =
+            trash::move_to_trash_in(&target.join("12345"), &dir.path().join("Trash")).unwrap();
+
+        let history = HistoryFile {
+            version: HISTORY_VERSION.to_string(),
+            executed_at: Utc::now(),
+            operation: OperationType::Rename,
+            direction: HistoryDirection::AnidbToReadable,
+            target_directory: target.clone(),
+            tool_version: "0.1.0".to_string(),
+            build: None,
+            changes: vec![HistoryEntry {
+                source: "12345".to_string(),
+                // Found through trash_info, the name is only shown
+                destination: trash_label(&trashed.path),
+                anidb_id: 12345,
+                truncated: false,
+                conflict: None,
+                aside: None,
+                kind: EntryKind::Trash,
+                trash_info: Some(trashed.info.clone()),
+                suffix: None,
+            }],
+            checksum: None,
+            reverted_from: None,
+            original_executed_at: None,
+            options: None,
+            run_id: None,
+        };
+
+        let history_path = target.join("test-history.json");
+        let file = fs::File::create(&history_path).unwrap();
+        serde_json::to_writer_pretty(file, &history).unwrap();
+
+        let result =
+            revert_from_history(&history_path, &RevertOptions::default(), &mut progress).unwrap();
+
+        assert!(target.join("12345").is_dir());
+        assert!(!trashed.path.exists());
+        assert!(!trashed.info.exists());
+
+        let revert_history = read_history(&result.revert_history_path.unwrap()).unwrap();
+        assert_eq!(revert_history.changes[0].kind, EntryKind::Trash);
+        assert_eq!(revert_history.changes[0].source, "(trash)/12345");
+        assert_eq!(revert_history.changes[0].trash_info, None);
+    }
 }
@@ -0,0 +1,394 @@
+//! Idempotence check of a library (`--check-roundtrip`).
+//!
+//! Every directory is taken through both conversions in memory, AniDB →
+//! readable → AniDB or readable → AniDB → readable, with titles from the
+//! cache (or a placeholder title for uncached ids). A directory that does
+//! not come back as it started is reported with the reason: a truncated
+//! name that lost its series tag, a tag changed by sanitization, two
+//! directories collapsing into one name, ...
+//!
+//! A readable name coming back with other titles only reflects newer cache
+//! data; it is counted, not reported. Nothing is renamed and the API is
+//! never called.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::api::AnimeInfo;
+use crate::cache::{CacheConfig, CacheStore};
+use crate::error::AppError;
+use crate::fs_profile::resolve_profile;
+use crate::parser::{parse_directory_name_with, ParsedDirectory};
+use crate::pipeline::PlanOptions;
+use crate::rename::{
+    altered_series_tag, build_anidb_name_with_suffix, build_human_readable_name_with_suffix,
+    natural_cmp, NameBuilderConfig, RenameError, RenameOptions,
+};
+use crate::scanner::scan_directory;
+
+/// Why a directory does not round-trip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MismatchReason {
+    /// Another directory converts to the same name
+    Duplicate,
+    /// The converted name does not parse
+    Unparsable,
+    /// An override replaces the series tag
+    Override,
+    /// `--drop-series-tag` leaves the tag out
+    TagDropped,
+    /// Sanitization changes characters of the tag
+    TagSanitization,
+    /// The name was too long to keep its tag
+    Truncation,
+    /// The restricted marker is added, lost or read back as a tag
+    Marker,
+    /// Anything else
+    Other,
+}
+
+impl MismatchReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MismatchReason::Duplicate => "collapses with another directory",
+            MismatchReason::Unparsable => "converted name does not parse",
+            MismatchReason::Override => "series tag replaced by an override",
+            MismatchReason::TagDropped => "series tag dropped",
+            MismatchReason::TagSanitization => "series tag changed by sanitization",
+            MismatchReason::Truncation => "series tag lost to truncation",
+            MismatchReason::Marker => "restricted marker",
+            MismatchReason::Other => "name changed",
+        }
+    }
+}
+
+/// A directory that does not come back to its name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mismatch {
+    pub name: String,
+    /// The name in the other format
+    pub via: String,
+    /// The name converted back, if `via` parses
+    pub back: Option<String>,
+    pub reason: MismatchReason,
+}
+
+/// Result of a round-trip check
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RoundTripReport {
+    /// Directories with a recognized name
+    pub checked: usize,
+    /// Directories checked with a placeholder title
+    pub uncached: usize,
+    /// Readable names that only come back with other titles
+    pub title_refreshes: usize,
+    /// In natural order of `name`
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Check every recognized directory in `dir`, with titles from its cache
+pub fn check_roundtrip(dir: &Path, options: &PlanOptions) -> Result<RoundTripReport, AppError> {
+    let entries = scan_directory(dir)?;
+    let cache = CacheStore::load(CacheConfig::for_target_dir(dir, options.cache_expiry_days));
+    let (fs_profile, _) = resolve_profile(dir, options.fs_profile);
+    let report = check_names(
+        entries.iter().map(|e| e.name.as_str()),
+        |id| cache.get(id),
+        &options.rename_options(fs_profile),
+    )?;
+    Ok(report)
+}
+
+/// Round-trip `names` with titles from `cached`
+///
+/// Names that do not parse are left out.
+pub fn check_names<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    cached: impl Fn(u32) -> Option<AnimeInfo>,
+    options: &RenameOptions,
+) -> Result<RoundTripReport, RenameError> {
+    let config = options.name_config()?;
+    let mut report = RoundTripReport::default();
+    let mut trips = Vec::new();
+
+    for parsed in names
+        .into_iter()
+        .filter_map(|n| parse_directory_name_with(n, config.template.as_ref()).ok())
+    {
+        report.checked += 1;
+        let id = parsed.anidb_id();
+        let info = cached(id).unwrap_or_else(|| {
+            report.uncached += 1;
+            placeholder_info(id)
+        });
+        let trip = Trip::new(&parsed, info, &config, options);
+        if trip.reason.is_none() && trip.back.as_deref() != Some(parsed.original_name()) {
+            report.title_refreshes += 1;
+        }
+        trips.push(trip);
+    }
+
+    // Names the conversion collapses, as the filesystem compares them
+    let key = |name: &str| {
+        if options.fs_profile.case_insensitive() {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    };
+    let mut sources: BTreeMap<String, usize> = BTreeMap::new();
+    for trip in &trips {
+        *sources.entry(key(&trip.via)).or_default() += 1;
+    }
+
+    report.mismatches = trips
+        .into_iter()
+        .filter_map(|trip| {
+            let reason = if sources[&key(&trip.via)] > 1 {
+                MismatchReason::Duplicate
+            } else {
+                trip.reason?
+            };
+            Some(Mismatch {
+                name: trip.name,
+                via: trip.via,
+                back: trip.back,
+                reason,
+            })
+        })
+        .collect();
+    report
+        .mismatches
+        .sort_by(|a, b| natural_cmp(&a.name, &b.name));
+    Ok(report)
+}
+
+/// Titles for an id missing from the cache
+fn placeholder_info(anidb_id: u32) -> AnimeInfo {
+    AnimeInfo {
+        anidb_id,
+        title_main: format!("Uncached {}", anidb_id),
+        title_en: None,
+        release_year: None,
+        english_fallback: false,
+        restricted: false,
+        unaired: false,
+        anime_type: None,
+        episode_count: None,
+    }
+}
+
+/// One directory taken to the other format and back
+struct Trip {
+    name: String,
+    via: String,
+    back: Option<String>,
+    /// Why it does not come back, `None` when it does
+    reason: Option<MismatchReason>,
+}
+
+impl Trip {
+    fn new(
+        parsed: &ParsedDirectory,
+        mut info: AnimeInfo,
+        config: &NameBuilderConfig,
+        options: &RenameOptions,
+    ) -> Self {
+        let name = parsed.original_name().to_string();
+        let tag = parsed.series_tag();
+        // Local corrections apply as they do when planning
+        let correction = options
+            .overrides
+            .as_ref()
+            .and_then(|overrides| overrides.get(info.anidb_id));
+        if let Some(correction) = correction {
+            correction.apply(&mut info);
+        }
+        let override_tag = correction.and_then(|c| c.series_tag.as_deref());
+        let suffix = parsed.suffix();
+        let readable =
+            |tag: Option<&str>| build_human_readable_name_with_suffix(tag, suffix, &info, config);
+
+        let (via, back, truncated) = match parsed {
+            ParsedDirectory::AniDb(_) => {
+                let result = readable(override_tag.or(tag));
+                let back = parse_directory_name_with(&result.name, config.template.as_ref())
+                    .ok()
+                    .map(|p| {
+                        build_anidb_name_with_suffix(p.series_tag(), p.anidb_id(), p.suffix())
+                    });
+                (result.name, back, result.truncated)
+            }
+            ParsedDirectory::HumanReadable(_) => {
+                let via = build_anidb_name_with_suffix(tag, parsed.anidb_id(), suffix);
+                let result = readable(override_tag.or(tag));
+                (via, Some(result.name), result.truncated)
+            }
+        };
+
+        let back_parsed = back
+            .as_deref()
+            .and_then(|b| parse_directory_name_with(b, config.template.as_ref()).ok());
+        let came_back = match (parsed, &back_parsed) {
+            (ParsedDirectory::AniDb(_), _) => back.as_deref() == Some(name.as_str()),
+            (ParsedDirectory::HumanReadable(a), Some(ParsedDirectory::HumanReadable(b))) => {
+                a.series_tag == b.series_tag
+                    && a.marker == b.marker
+                    && a.suffix == b.suffix
+                    && a.anidb_id == b.anidb_id
+            }
+            _ => false,
+        };
+
+        let reason = (!came_back).then(|| {
+            if back_parsed.is_none() {
+                MismatchReason::Unparsable
+            } else if override_tag.is_some_and(|t| Some(t) != tag) {
+                MismatchReason::Override
+            } else if options.drop_series_tag && tag.is_some() {
+                MismatchReason::TagDropped
+            } else if tag.is_some_and(|t| altered_series_tag(t, &config.replacements).is_some()) {
+                MismatchReason::TagSanitization
+            } else if truncated {
+                MismatchReason::Truncation
+            } else if info.restricted
+                || matches!(parsed, ParsedDirectory::HumanReadable(f) if f.marker.is_some())
+            {
+                MismatchReason::Marker
+            } else {
+                MismatchReason::Other
+            }
+        });
+
+        Self {
+            name,
+            via,
+            back,
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::RestrictedPolicy;
+
+    fn info(id: u32, title: &str, restricted: bool) -> AnimeInfo {
+        AnimeInfo {
+            title_main: title.to_string(),
+            release_year: Some(2000),
+            restricted,
+            ..placeholder_info(id)
+        }
+    }
+
+    fn reasons(report: &RoundTripReport) -> Vec<(&str, MismatchReason)> {
+        report
+            .mismatches
+            .iter()
+            .map(|m| (m.name.as_str(), m.reason))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_library_round_trips() {
+        let cached = |id| (id == 1).then(|| info(1, "Cowboy Bebop", false));
+        let report = check_names(
+            ["[TV] 1", "2", "[TV] Old Title (2000) [anidb-1]", "notes"],
+            cached,
+            &RenameOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.uncached, 1);
+        assert_eq!(report.title_refreshes, 1);
+        assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+    }
+
+    #[test]
+    fn test_mismatch_reasons() {
+        let cached = |id| Some(info(id, "Title", id == 4));
+        let options = RenameOptions {
+            restricted: RestrictedPolicy::Tag,
+            ..Default::default()
+        };
+        let report = check_names(
+            [
+                "[S1:S2] 1",
+                "[X] 2",
+                "3",
+                "4",
+                "[18+] [A] Title (2000) [anidb-5]",
+            ],
+            cached,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            reasons(&report),
+            [
+                ("4", MismatchReason::Marker),
+                ("[18+] [A] Title (2000) [anidb-5]", MismatchReason::Marker),
+                ("[S1:S2] 1", MismatchReason::TagSanitization),
+            ]
+        );
+        assert_eq!(report.mismatches[0].back.as_deref(), Some("[18+] 4"));
+        assert_eq!(report.mismatches[2].via, "[S1：S2] Title (2000) [anidb-1]");
+        assert_eq!(report.mismatches[2].back.as_deref(), Some("[S1：S2] 1"));
+    }
+
+    #[test]
+    fn test_duplicates_and_case_insensitive_filesystems() {
+        let cached = |id| Some(info(id, "Title", false));
+        let names = [
+            "[X] 2",
+            "[x] 2",
+            "[A] T (2000) [anidb-3]",
+            "[A] U [anidb-3]",
+        ];
+
+        let report = check_names(names, cached, &RenameOptions::default()).unwrap();
+        assert_eq!(
+            reasons(&report),
+            [
+                ("[A] T (2000) [anidb-3]", MismatchReason::Duplicate),
+                ("[A] U [anidb-3]", MismatchReason::Duplicate),
+            ]
+        );
+
+        let options = RenameOptions {
+            fs_profile: crate::fs_profile::FsProfile::Windows,
+            ..Default::default()
+        };
+        let report = check_names(names, cached, &options).unwrap();
+        assert_eq!(report.mismatches.len(), 4);
+        assert_eq!(report.mismatches[0].name, "[A] T (2000) [anidb-3]");
+    }
+
+    #[test]
+    fn test_dropped_and_truncated_tags() {
+        let cached = |id| Some(info(id, &"Long ".repeat(20), false));
+        let dropped = RenameOptions {
+            drop_series_tag: true,
+            ..Default::default()
+        };
+        let report = check_names(["[T] 1", "2"], cached, &dropped).unwrap();
+        assert_eq!(reasons(&report), [("[T] 1", MismatchReason::TagDropped)]);
+
+        let short = RenameOptions {
+            max_length: 20,
+            ..Default::default()
+        };
+        let report = check_names(["[Long Tag] 1", "2"], cached, &short).unwrap();
+        assert_eq!(
+            reasons(&report),
+            [("[Long Tag] 1", MismatchReason::Truncation)]
+        );
+    }
+}
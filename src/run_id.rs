@@ -0,0 +1,130 @@
+//! Run ids for correlating log lines.
+//!
+//! Every rename or revert gets a short random id. It is a field of the
+//! tracing span around each directory's processing, recorded in the history
+//! file and shown in the report, so one run can be grepped out of a long
+//! journal even when its API retries, cache writes and renames interleave.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info_span, Span};
+
+/// A new run id: 8 lowercase hex digits
+pub fn new_run_id() -> String {
+    // Each RandomState is randomly seeded; time and pid vary it further
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    hasher.write_u128(nanos);
+    hasher.write_u32(std::process::id());
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+/// Span around one directory's processing in `stage` (prepare, execute or revert)
+pub fn directory_span(run_id: &str, stage: &'static str, anidb_id: u32, source: &str) -> Span {
+    info_span!(
+        "directory",
+        run_id = %run_id,
+        stage,
+        anidb_id,
+        source = %source
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Name and fields of a recorded span
+    pub type RecordedSpan = (String, BTreeMap<String, String>);
+
+    /// Subscriber recording every span created while it is the default
+    #[derive(Clone, Default)]
+    pub struct SpanRecorder {
+        spans: Arc<Mutex<Vec<RecordedSpan>>>,
+    }
+
+    impl SpanRecorder {
+        pub fn spans(&self) -> Vec<RecordedSpan> {
+            self.spans.lock().unwrap().clone()
+        }
+    }
+
+    struct Fields<'a>(&'a mut BTreeMap<String, String>);
+
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = BTreeMap::new();
+            span.record(&mut Fields(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((span.metadata().name().to_string(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::SpanRecorder;
+    use super::*;
+
+    #[test]
+    fn test_run_ids_are_short_and_distinct() {
+        let a = new_run_id();
+        let b = new_run_id();
+
+        assert_eq!(a.len(), 8);
+        assert!(a.bytes().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_directory_span_fields() {
+        let recorder = SpanRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let _span = directory_span("0badcafe", "prepare", 12345, "[AS0] 12345").entered();
+        });
+
+        let spans = recorder.spans();
+        assert_eq!(spans.len(), 1);
+        let (name, fields) = &spans[0];
+        assert_eq!(name, "directory");
+        assert_eq!(fields["run_id"], "0badcafe");
+        assert_eq!(fields["stage"], "prepare");
+        assert_eq!(fields["anidb_id"], "12345");
+        assert_eq!(fields["source"], "[AS0] 12345");
+    }
+}
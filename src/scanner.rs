@@ -1,7 +1,11 @@
+use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
+
+use crate::parser::{parse_directory_name, ParsedDirectory};
 
 #[derive(Error, Debug)]
 pub enum ScannerError {
@@ -14,24 +18,202 @@ pub enum ScannerError {
     #[error("Permission denied: {0}")]
     PermissionDenied(PathBuf),
 
+    #[error("Target is itself an anime directory: {0}")]
+    TargetIsAnimeDirectory(PathBuf),
+
+    #[error("Target directories overlap: {outer} contains {inner}")]
+    OverlappingTargets { outer: PathBuf, inner: PathBuf },
+
+    #[error("Refusing to operate on {path}: {reason}")]
+    DangerousTarget { path: PathBuf, reason: String },
+
     #[error("Failed to read directory: {0}")]
     IoError(#[from] std::io::Error),
 }
 
 #[derive(Debug, Clone)]
 pub struct DirectoryEntry {
+    /// The name as text, with replacement characters for invalid UTF-8
     pub name: String,
+    /// The name as stored on disk
+    pub os_name: OsString,
 }
 
 impl DirectoryEntry {
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self {
+            os_name: OsString::from(&name),
+            name,
+        }
+    }
+
+    /// An entry named `os_name` on disk
+    pub fn from_os_name(os_name: &OsStr) -> Self {
+        Self {
+            name: os_name.to_string_lossy().into_owned(),
+            os_name: os_name.to_os_string(),
+        }
+    }
+
+    /// Whether `name` is exactly the name on disk
+    ///
+    /// A name that is not valid UTF-8 only exists on disk as `os_name`: a
+    /// path built from `name` points somewhere else.
+    pub fn is_utf8(&self) -> bool {
+        self.os_name.to_str().is_some()
+    }
+}
+
+/// Number of candidate entries above which scanning switches to parallel mode
+/// when no explicit thread count is configured
+pub const PARALLEL_SCAN_THRESHOLD: usize = 1000;
+
+/// Options controlling how a directory is scanned
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Number of threads used to stat entries.
+    /// `None` enables parallel scanning automatically above [`PARALLEL_SCAN_THRESHOLD`].
+    pub threads: Option<usize>,
+}
+
+impl ScanOptions {
+    /// Resolve the number of worker threads for the given number of candidates
+    fn effective_threads(&self, candidates: usize) -> usize {
+        let threads = match self.threads {
+            Some(n) => n.max(1),
+            None if candidates > PARALLEL_SCAN_THRESHOLD => std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            None => 1,
+        };
+
+        threads.min(candidates.max(1))
+    }
+}
+
+/// Subdirectory that holds directories whose AniDB id no longer resolves
+///
+/// It is never scanned as an anime directory itself.
+pub const UNRESOLVED_DIR: &str = "_unresolved";
+
+/// Result of scanning a directory, with statistics about skipped entries
+#[derive(Debug, Clone, Default)]
+pub struct ScanResult {
+    /// Subdirectories found, sorted by name
+    pub entries: Vec<DirectoryEntry>,
+    /// Number of hidden entries (starting with `.`, plus [`UNRESOLVED_DIR`]) that were skipped
+    pub skipped_hidden: usize,
+    /// Number of non-directory entries (files, broken links, unreadable entries) that were skipped
+    pub skipped_files: usize,
+    /// Number of symlinks encountered (symlinks to directories are still included)
+    pub symlinks: usize,
+    /// Directories named like a history or cache file (see [`is_artifact_name`]), ignored
+    pub artifact_dirs: Vec<String>,
+    /// Time taken by the scan
+    pub duration: Duration,
+}
+
+impl ScanResult {
+    /// Total number of entries that were skipped
+    pub fn skipped(&self) -> usize {
+        self.skipped_hidden + self.skipped_files + self.artifact_dirs.len()
     }
 }
 
+/// Whether a name is one of anidb2folder's own files: a history file
+/// (`anidb2folder-history-*.json`, reverts included) or the cache file
+///
+/// A directory by such a name, e.g. from a backup extracted in the wrong
+/// place, is neither renamed nor read as history.
+pub fn is_artifact_name(name: &str) -> bool {
+    let name = name.strip_prefix('.').unwrap_or(name);
+    name.starts_with("anidb2folder-") && (name.ends_with(".json") || name.ends_with(".json.tmp"))
+}
+
+/// Check target directories before scanning and return them canonicalized
+///
+/// Refuses a target that is itself a human-readable anime directory (a
+/// common slip: pointing at one series instead of the library), and targets
+/// that are equal to, inside, or around another target.
+pub fn validate_targets(targets: &[PathBuf]) -> Result<Vec<PathBuf>, ScannerError> {
+    let mut canonical: Vec<PathBuf> = Vec::with_capacity(targets.len());
+
+    for target in targets {
+        if !target.exists() {
+            return Err(ScannerError::PathNotFound(target.clone()));
+        }
+        if !target.is_dir() {
+            return Err(ScannerError::NotADirectory(target.clone()));
+        }
+
+        let path = fs::canonicalize(target)?;
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if let Ok(ParsedDirectory::HumanReadable(_)) = parse_directory_name(&name) {
+            return Err(ScannerError::TargetIsAnimeDirectory(path));
+        }
+
+        for other in &canonical {
+            let (outer, inner) = if path.starts_with(other) {
+                (other, &path)
+            } else if other.starts_with(&path) {
+                (&path, other)
+            } else {
+                continue;
+            };
+            return Err(ScannerError::OverlappingTargets {
+                outer: outer.clone(),
+                inner: inner.clone(),
+            });
+        }
+
+        canonical.push(path);
+    }
+
+    Ok(canonical)
+}
+
+/// Refuse a canonicalized target that is a filesystem root or the home directory
+///
+/// A mistyped or empty shell variable easily turns into `/` or `~`.
+pub fn guard_target(path: &Path) -> Result<(), ScannerError> {
+    let reason = if path.parent().is_none() {
+        "it is a filesystem root"
+    } else if dirs::home_dir()
+        .and_then(|home| fs::canonicalize(home).ok())
+        .is_some_and(|home| home == path)
+    {
+        "it is your home directory"
+    } else {
+        return Ok(());
+    };
+
+    Err(ScannerError::DangerousTarget {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    })
+}
+
+/// Scan a directory with default options
+///
+/// Convenience wrapper around [`scan_directory_ex`] returning only the entries.
+#[allow(dead_code)]
 pub fn scan_directory(target: &Path) -> Result<Vec<DirectoryEntry>, ScannerError> {
+    scan_directory_ex(target, &ScanOptions::default()).map(|result| result.entries)
+}
+
+/// Scan a directory, statting entries concurrently when configured
+///
+/// Entries that cannot be inspected (e.g. permission denied on a single
+/// subdirectory) are skipped with a warning instead of failing the scan.
+pub fn scan_directory_ex(target: &Path, options: &ScanOptions) -> Result<ScanResult, ScannerError> {
     debug!(path = ?target, "Scanning directory");
 
+    let started = Instant::now();
+
     if !target.exists() {
         return Err(ScannerError::PathNotFound(target.to_path_buf()));
     }
@@ -40,8 +222,6 @@ pub fn scan_directory(target: &Path) -> Result<Vec<DirectoryEntry>, ScannerError
         return Err(ScannerError::NotADirectory(target.to_path_buf()));
     }
 
-    let mut entries = Vec::new();
-
     let read_dir = fs::read_dir(target).map_err(|e| {
         if e.kind() == std::io::ErrorKind::PermissionDenied {
             ScannerError::PermissionDenied(target.to_path_buf())
@@ -50,36 +230,135 @@ pub fn scan_directory(target: &Path) -> Result<Vec<DirectoryEntry>, ScannerError
         }
     })?;
 
+    let mut result = ScanResult::default();
+
+    // Collect candidate names first; hidden entries are filtered without a stat call
+    let mut candidates = Vec::new();
+
     for entry in read_dir {
-        let entry = entry?;
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                warn!(error = %e, "Failed to read directory entry, skipping");
+                result.skipped_files += 1;
+                continue;
+            }
+        };
         let path = entry.path();
 
         trace!(entry = ?path, "Examining entry");
 
-        if !path.is_dir() {
-            trace!(path = ?path, "Skipping non-directory");
-            continue;
-        }
-
         let name = match path.file_name() {
             Some(n) => n.to_string_lossy().to_string(),
             None => continue,
         };
 
-        if name.starts_with('.') {
-            trace!(name = %name, "Skipping hidden directory");
+        if is_artifact_name(&name) && entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            warn!(name = %name, "Directory named like a history or cache file, ignoring");
+            result.artifact_dirs.push(name);
+            continue;
+        }
+
+        if name.starts_with('.') || name == UNRESOLVED_DIR {
+            trace!(name = %name, "Skipping hidden entry");
+            result.skipped_hidden += 1;
             continue;
         }
 
-        debug!(name = %name, "Found subdirectory");
-        entries.push(DirectoryEntry::new(name));
+        if entry.file_type().map(|t| t.is_symlink()).unwrap_or(false) {
+            trace!(name = %name, "Entry is a symlink");
+            result.symlinks += 1;
+        }
+
+        candidates.push((name, path));
     }
 
+    // A symlink back to the target or one of its ancestors would loop forever
+    // once scanning descends into subdirectories
+    if result.symlinks > 0 {
+        if let Ok(root) = fs::canonicalize(target) {
+            let before = candidates.len();
+            candidates.retain(|(name, path)| match fs::canonicalize(path) {
+                Ok(resolved) if root.starts_with(&resolved) => {
+                    warn!(name = %name, target = ?resolved, "Symlink loops back to the target, skipping");
+                    false
+                }
+                _ => true,
+            });
+            result.skipped_files += before - candidates.len();
+        }
+    }
+
+    let threads = options.effective_threads(candidates.len());
+
+    let mut entries = if threads > 1 {
+        debug!(
+            threads,
+            candidates = candidates.len(),
+            "Statting entries in parallel"
+        );
+        let chunk_size = candidates.len().div_ceil(threads);
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || stat_candidates(chunk)))
+                .collect();
+
+            // A panicked worker would leave out a whole chunk of entries and
+            // count them as skipped; the scan panics too rather than go on
+            // with a partial listing
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect::<Vec<_>>()
+        })
+    } else {
+        stat_candidates(&candidates)
+    };
+
     entries.sort_by(|a, b| a.name.cmp(&b.name));
+    result.artifact_dirs.sort();
+
+    result.skipped_files += candidates.len() - entries.len();
+    result.entries = entries;
+    result.duration = started.elapsed();
+
+    debug!(
+        count = result.entries.len(),
+        skipped_hidden = result.skipped_hidden,
+        skipped_files = result.skipped_files,
+        symlinks = result.symlinks,
+        duration = ?result.duration,
+        "Scan complete"
+    );
+
+    Ok(result)
+}
 
-    debug!(count = entries.len(), "Scan complete");
+/// Stat each candidate and keep those that are directories
+fn stat_candidates(candidates: &[(String, PathBuf)]) -> Vec<DirectoryEntry> {
+    let mut entries = Vec::new();
 
-    Ok(entries)
+    for (name, path) in candidates {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => {
+                debug!(name = %name, "Found subdirectory");
+                entries.push(path.file_name().map_or_else(
+                    || DirectoryEntry::new(name.clone()),
+                    DirectoryEntry::from_os_name,
+                ));
+            }
+            Ok(_) => {
+                trace!(path = ?path, "Skipping non-directory");
+            }
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Cannot inspect entry, skipping");
+            }
+        }
+    }
+
+    entries
 }
 
 #[cfg(test)]
@@ -131,6 +410,19 @@ mod tests {
         assert_eq!(result[0].name, "visible");
     }
 
+    #[test]
+    fn test_ignores_unresolved_directory() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(UNRESOLVED_DIR)).unwrap();
+        fs::create_dir(dir.path().join("12345")).unwrap();
+
+        let result = scan_directory_ex(dir.path(), &ScanOptions::default()).unwrap();
+
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].name, "12345");
+        assert_eq!(result.skipped_hidden, 1);
+    }
+
     #[test]
     fn test_path_not_found() {
         let result = scan_directory(Path::new("/nonexistent/path"));
@@ -160,4 +452,218 @@ mod tests {
         assert_eq!(result[1].name, "beta");
         assert_eq!(result[2].name, "zebra");
     }
+
+    #[test]
+    fn test_parallel_scan_matches_serial() {
+        let dir = tempdir().unwrap();
+        for i in 0..50 {
+            fs::create_dir(dir.path().join(format!("dir{:03}", i))).unwrap();
+        }
+        fs::write(dir.path().join("file.txt"), "content").unwrap();
+
+        let serial = scan_directory_ex(dir.path(), &ScanOptions { threads: Some(1) }).unwrap();
+        let parallel = scan_directory_ex(dir.path(), &ScanOptions { threads: Some(4) }).unwrap();
+
+        assert_eq!(serial.skipped_files, parallel.skipped_files);
+        let serial: Vec<_> = serial.entries.into_iter().map(|e| e.name).collect();
+        let parallel: Vec<_> = parallel.entries.into_iter().map(|e| e.name).collect();
+        assert_eq!(serial.len(), 50);
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn test_effective_threads() {
+        let auto = ScanOptions::default();
+        assert_eq!(auto.effective_threads(10), 1);
+        assert!(auto.effective_threads(PARALLEL_SCAN_THRESHOLD + 1) >= 1);
+
+        let explicit = ScanOptions { threads: Some(8) };
+        assert_eq!(explicit.effective_threads(100), 8);
+        assert_eq!(explicit.effective_threads(3), 3);
+
+        let zero = ScanOptions { threads: Some(0) };
+        assert_eq!(zero.effective_threads(100), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_name_keeps_its_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let raw = OsStr::from_bytes(b"[\xff] 123");
+        // Some filesystems (APFS) refuse names that are not UTF-8
+        if fs::create_dir(dir.path().join(raw)).is_err() {
+            return;
+        }
+        fs::create_dir(dir.path().join("456")).unwrap();
+
+        let result = scan_directory(dir.path()).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert!(result[0].is_utf8());
+        assert_eq!(result[1].name, "[\u{FFFD}] 123");
+        assert_eq!(result[1].os_name, raw);
+        assert!(!result[1].is_utf8());
+        assert!(!dir.path().join(&result[1].name).exists());
+        assert!(dir.path().join(&result[1].os_name).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broken_symlink_is_skipped() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("valid")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("dangling"))
+            .unwrap();
+
+        let result = scan_directory(dir.path()).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "valid");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_to_ancestor_is_skipped() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("valid")).unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("loop")).unwrap();
+
+        let result = scan_directory_ex(dir.path(), &ScanOptions::default()).unwrap();
+
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].name, "valid");
+        assert_eq!(result.skipped_files, 1);
+    }
+
+    #[test]
+    fn test_validate_targets_canonicalizes() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("anime")).unwrap();
+        let target = dir.path().join("anime").join("..").join("anime");
+
+        let result = validate_targets(&[target]).unwrap();
+
+        assert_eq!(
+            result,
+            vec![fs::canonicalize(dir.path().join("anime")).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_validate_targets_rejects_anime_directory() {
+        let dir = tempdir().unwrap();
+        let series = dir.path().join("Naruto (2002) [anidb-12345]");
+        fs::create_dir(&series).unwrap();
+
+        let result = validate_targets(&[series]);
+
+        assert!(matches!(
+            result,
+            Err(ScannerError::TargetIsAnimeDirectory(_))
+        ));
+    }
+
+    #[test]
+    fn test_guard_target() {
+        let dir = tempdir().unwrap();
+
+        assert!(guard_target(dir.path()).is_ok());
+        assert!(matches!(
+            guard_target(Path::new("/")),
+            Err(ScannerError::DangerousTarget { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_targets_rejects_overlap() {
+        let dir = tempdir().unwrap();
+        let inner = dir.path().join("inner");
+        fs::create_dir(&inner).unwrap();
+
+        let nested = validate_targets(&[dir.path().to_path_buf(), inner.clone()]);
+        let reversed = validate_targets(&[inner.clone(), dir.path().to_path_buf()]);
+        let same = validate_targets(&[inner.clone(), inner]);
+
+        for result in [nested, reversed, same] {
+            assert!(matches!(
+                result,
+                Err(ScannerError::OverlappingTargets { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_scan_result_statistics() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("visible")).unwrap();
+        fs::create_dir(dir.path().join(".hidden")).unwrap();
+        fs::write(dir.path().join(".hidden-file"), "content").unwrap();
+        fs::write(dir.path().join("file1.txt"), "content").unwrap();
+        fs::write(dir.path().join("file2.txt"), "content").unwrap();
+
+        let result = scan_directory_ex(dir.path(), &ScanOptions::default()).unwrap();
+
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.skipped_hidden, 2);
+        assert_eq!(result.skipped_files, 2);
+        assert_eq!(result.skipped(), 4);
+        assert_eq!(result.symlinks, 0);
+    }
+
+    #[test]
+    fn test_artifact_named_directories_are_set_aside() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("12345")).unwrap();
+        fs::create_dir(dir.path().join("anidb2folder-history-20250101-000000.json")).unwrap();
+        fs::create_dir(dir.path().join(".anidb2folder-cache.json")).unwrap();
+        // The real files are ordinary skipped files
+        fs::write(
+            dir.path().join("anidb2folder-history-20250102-000000.json"),
+            "{}",
+        )
+        .unwrap();
+
+        let result = scan_directory_ex(dir.path(), &ScanOptions::default()).unwrap();
+
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(
+            result.artifact_dirs,
+            [
+                ".anidb2folder-cache.json",
+                "anidb2folder-history-20250101-000000.json"
+            ]
+        );
+        assert_eq!(result.skipped_hidden, 0);
+        assert_eq!(result.skipped_files, 1);
+        assert_eq!(result.skipped(), 3);
+    }
+
+    #[test]
+    fn test_is_artifact_name() {
+        assert!(is_artifact_name(
+            "anidb2folder-history-20250101-000000.json"
+        ));
+        assert!(is_artifact_name(
+            "anidb2folder-revert-20241231-000000-20250101-000000.json"
+        ));
+        assert!(is_artifact_name(".anidb2folder-cache.json.tmp"));
+        assert!(!is_artifact_name("anidb2folder-history"));
+        assert!(!is_artifact_name("Title (2020) [anidb-1]"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_result_counts_symlinks() {
+        let dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("linked")).unwrap();
+
+        let result = scan_directory_ex(dir.path(), &ScanOptions::default()).unwrap();
+
+        assert_eq!(result.symlinks, 1);
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].name, "linked");
+    }
 }
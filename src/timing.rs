@@ -0,0 +1,196 @@
+//! Per-directory step timing (`--slow-threshold`).
+//!
+//! Planning times how long each directory takes to prepare and to fetch,
+//! execution how long each rename takes. A filesystem operation slower than
+//! the threshold is reported as soon as it completes, naming the directory,
+//! so a run stuck on one folder (an NFS attribute cache, a busy share) can
+//! be told apart from a hung one. The slowest steps are kept for the summary.
+
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+/// Default for `--slow-threshold`, in seconds
+pub const DEFAULT_SLOW_THRESHOLD_SECS: u64 = 5;
+
+/// Steps kept by [`Timings::slowest`]
+pub const SLOWEST_KEPT: usize = 5;
+
+/// A timed step of a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Building the new name, without the API request
+    Prepare,
+    /// The API request for the directory's id
+    Fetch,
+    /// The rename itself
+    Execute,
+}
+
+impl Step {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Step::Prepare => "prepare",
+            Step::Fetch => "fetch",
+            Step::Execute => "execute",
+        }
+    }
+
+    /// Whether the step is a filesystem operation, warned about when slow
+    pub fn is_filesystem(&self) -> bool {
+        matches!(self, Step::Execute)
+    }
+}
+
+/// How long a step took for one directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StepTiming {
+    pub step: Step,
+    /// Source directory name
+    pub name: String,
+    #[serde(rename = "elapsed_ms", serialize_with = "as_millis")]
+    pub elapsed: Duration,
+}
+
+/// How long ago something happened, in the largest fitting unit, e.g. `3 days ago`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (count, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+fn as_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// Step durations of a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timings {
+    /// Filesystem steps taking longer are slow (zero: never)
+    pub threshold: Duration,
+    /// Slowest steps first, at most [`SLOWEST_KEPT`]
+    slowest: Vec<StepTiming>,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_SLOW_THRESHOLD_SECS))
+    }
+}
+
+impl Timings {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            slowest: Vec::new(),
+        }
+    }
+
+    /// Record a step, returning its timing when it is a slow filesystem operation
+    pub fn record(&mut self, step: Step, name: &str, elapsed: Duration) -> Option<StepTiming> {
+        let timing = StepTiming {
+            step,
+            name: name.to_string(),
+            elapsed,
+        };
+        let slow = step.is_filesystem() && !self.threshold.is_zero() && elapsed > self.threshold;
+        let slow = slow.then(|| timing.clone());
+        self.keep(timing);
+        slow
+    }
+
+    /// The slowest steps recorded, slowest first
+    pub fn slowest(&self) -> &[StepTiming] {
+        &self.slowest
+    }
+
+    /// Keep the slowest steps of `other` too
+    pub fn merge(&mut self, other: &Timings) {
+        for timing in &other.slowest {
+            self.keep(timing.clone());
+        }
+    }
+
+    fn keep(&mut self, timing: StepTiming) {
+        let at = self
+            .slowest
+            .partition_point(|kept| kept.elapsed >= timing.elapsed);
+        if at < SLOWEST_KEPT {
+            self.slowest.insert(at, timing);
+            self.slowest.truncate(SLOWEST_KEPT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_only_slow_filesystem_steps_are_reported() {
+        let mut timings = Timings::new(secs(5));
+        assert!(timings.record(Step::Execute, "1", secs(5)).is_none());
+        assert!(timings.record(Step::Fetch, "2", secs(30)).is_none());
+        let slow = timings.record(Step::Execute, "3", secs(21)).unwrap();
+        assert_eq!(slow.name, "3");
+
+        let mut disabled = Timings::new(Duration::ZERO);
+        assert!(disabled.record(Step::Execute, "3", secs(21)).is_none());
+    }
+
+    #[test]
+    fn test_slowest_are_kept_in_order() {
+        let mut timings = Timings::default();
+        for (i, elapsed) in [3, 9, 1, 7, 5, 8, 2].into_iter().enumerate() {
+            timings.record(
+                Step::Prepare,
+                &i.to_string(),
+                Duration::from_millis(elapsed),
+            );
+        }
+        let names: Vec<&str> = timings.slowest().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["1", "5", "3", "4", "0"]);
+
+        let mut other = Timings::default();
+        other.record(Step::Execute, "x", secs(1));
+        timings.merge(&other);
+        assert_eq!(timings.slowest()[0].name, "x");
+        assert_eq!(timings.slowest().len(), SLOWEST_KEPT);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(secs(5)), "just now");
+        assert_eq!(format_age(secs(60)), "1 minute ago");
+        assert_eq!(format_age(secs(2 * 3600 + 59)), "2 hours ago");
+        assert_eq!(format_age(secs(3 * 86_400 + 7200)), "3 days ago");
+    }
+
+    #[test]
+    fn test_timing_json() {
+        let timing = StepTiming {
+            step: Step::Execute,
+            name: "[X] 1".to_string(),
+            elapsed: Duration::from_millis(21_500),
+        };
+        assert_eq!(
+            serde_json::to_value(&timing).unwrap(),
+            serde_json::json!({ "step": "execute", "name": "[X] 1", "elapsed_ms": 21500 })
+        );
+    }
+}
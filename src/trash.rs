@@ -0,0 +1,274 @@
+//! Moving directories to the system trash.
+//!
+//! Implements the freedesktop.org trash specification used by Linux and BSD
+//! desktops: an item goes to the home trash when it lives on the same
+//! filesystem, otherwise to `$topdir/.Trash-$uid` on its own filesystem, and
+//! a `.trashinfo` file next to it records where it came from so file
+//! managers can restore it. Other platforms report [`is_supported`] as
+//! `false` and callers fall back to the unresolved directory.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::rename::rename_no_replace;
+
+/// Where a trashed item went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trashed {
+    /// The item inside the trash's `files/` directory
+    pub path: PathBuf,
+    /// The `.trashinfo` file recording the original location
+    pub info: PathBuf,
+}
+
+/// Whether this platform has a trash implementation
+pub fn is_supported() -> bool {
+    platform::SUPPORTED
+}
+
+/// Move `path` to the trash of the filesystem it lives on
+pub fn move_to_trash(path: &Path) -> io::Result<Trashed> {
+    let root = platform::trash_root(path)?;
+    move_to_trash_in(path, &root)
+}
+
+/// Move `path` into the trash directory `root` (holding `files/` and `info/`)
+pub fn move_to_trash_in(path: &Path, root: &Path) -> io::Result<Trashed> {
+    platform::create_private_dir(&root.join("files"))?;
+    platform::create_private_dir(&root.join("info"))?;
+    // Canonical, so reverting finds the item where it was recorded
+    let root = std::fs::canonicalize(root)?;
+    let files = root.join("files");
+    let info_dir = root.join("info");
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_string_lossy()
+        .to_string();
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(path),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    // Creating the .trashinfo first reserves the name against other trashers
+    for n in 1.. {
+        let candidate = if n == 1 {
+            name.clone()
+        } else {
+            format!("{}.{}", name, n)
+        };
+        let info = info_dir.join(format!("{}.trashinfo", candidate));
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info)
+        {
+            Ok(mut file) => {
+                let trashed = files.join(&candidate);
+                let moved = io::Write::write_all(&mut file, contents.as_bytes())
+                    .and_then(|_| rename_no_replace(path, &trashed));
+                return match moved {
+                    Ok(()) => Ok(Trashed {
+                        path: trashed,
+                        info,
+                    }),
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                        // A stray item without info file: try the next name
+                        let _ = std::fs::remove_file(&info);
+                        continue;
+                    }
+                    Err(e) => {
+                        let _ = std::fs::remove_file(&info);
+                        Err(e)
+                    }
+                };
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+/// Whether `path` is an item inside a trash's `files/` directory
+pub fn is_trashed(path: &Path) -> bool {
+    info_path(path).is_some_and(|info| info.exists())
+}
+
+/// Move a trashed item back to `to` and remove its `.trashinfo`
+pub fn restore(trashed: &Path, to: &Path) -> io::Result<()> {
+    rename_no_replace(trashed, to)?;
+    if let Some(info) = info_path(trashed) {
+        if let Err(e) = std::fs::remove_file(&info) {
+            tracing::warn!("Failed to remove {:?}: {}", info, e);
+        }
+    }
+    Ok(())
+}
+
+/// The item in a trash's `files/` directory that a `.trashinfo` file belongs to
+pub fn item_path(info: &Path) -> Option<PathBuf> {
+    let info_dir = info.parent()?;
+    if info_dir.file_name()? != "info" {
+        return None;
+    }
+    let name = info.file_name()?.to_string_lossy();
+    let name = name.strip_suffix(".trashinfo")?;
+    Some(info_dir.parent()?.join("files").join(name))
+}
+
+/// The `.trashinfo` file belonging to an item in a trash's `files/` directory
+fn info_path(trashed: &Path) -> Option<PathBuf> {
+    let files = trashed.parent()?;
+    if files.file_name()? != "files" {
+        return None;
+    }
+    let name = trashed.file_name()?.to_string_lossy();
+    Some(
+        files
+            .parent()?
+            .join("info")
+            .join(format!("{}.trashinfo", name)),
+    )
+}
+
+/// Percent-encode a path for the `Path=` key, keeping `/` and unreserved bytes
+fn encode_path(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::fs::{self, DirBuilder};
+    use std::io;
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+    use std::path::{Path, PathBuf};
+
+    pub const SUPPORTED: bool = true;
+
+    /// Trash directory for `path`: the home trash if on the same device,
+    /// else `.Trash-$uid` at the top of `path`'s filesystem
+    pub fn trash_root(path: &Path) -> io::Result<PathBuf> {
+        let path = fs::canonicalize(path)?;
+        let device = fs::symlink_metadata(&path)?.dev();
+
+        if let Some(home_trash) = home_trash() {
+            let existing = home_trash.ancestors().find(|p| p.exists());
+            if existing.and_then(|p| fs::metadata(p).ok()).map(|m| m.dev()) == Some(device) {
+                return Ok(home_trash);
+            }
+        }
+
+        let mut top = path.as_path();
+        while let Some(parent) = top.parent() {
+            match fs::metadata(parent) {
+                Ok(meta) if meta.dev() == device => top = parent,
+                _ => break,
+            }
+        }
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = unsafe { libc::getuid() };
+        Ok(top.join(format!(".Trash-{}", uid)))
+    }
+
+    fn home_trash() -> Option<PathBuf> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))?;
+        Some(data_home.join("Trash"))
+    }
+
+    /// Create `dir` and its missing parents, readable only by the user
+    pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+mod platform {
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    pub const SUPPORTED: bool = false;
+
+    pub fn trash_root(_path: &Path) -> io::Result<PathBuf> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no trash implementation on this platform",
+        ))
+    }
+
+    pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_move_to_trash_writes_info() {
+        let dir = tempdir().unwrap();
+        let item = dir.path().join("[AS0] 12345");
+        fs::create_dir(&item).unwrap();
+        let root = dir.path().join("Trash");
+
+        let trashed = move_to_trash_in(&item, &root).unwrap();
+
+        assert!(!item.exists());
+        assert_eq!(trashed.path, root.join("files/[AS0] 12345"));
+        assert!(trashed.path.is_dir());
+        let info = fs::read_to_string(&trashed.info).unwrap();
+        assert!(info.starts_with("[Trash Info]\n"));
+        assert!(info.contains("%5BAS0%5D%2012345\n"));
+        assert!(info.contains("DeletionDate="));
+        assert!(is_trashed(&trashed.path));
+    }
+
+    #[test]
+    fn test_move_to_trash_picks_free_name() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("Trash");
+        let first = dir.path().join("a/12345");
+        let second = dir.path().join("b/12345");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+
+        move_to_trash_in(&first, &root).unwrap();
+        let trashed = move_to_trash_in(&second, &root).unwrap();
+
+        assert_eq!(trashed.path, root.join("files/12345.2"));
+        assert_eq!(trashed.info, root.join("info/12345.2.trashinfo"));
+        assert_eq!(item_path(&trashed.info), Some(trashed.path));
+    }
+
+    #[test]
+    fn test_restore_removes_info() {
+        let dir = tempdir().unwrap();
+        let item = dir.path().join("12345");
+        fs::create_dir(&item).unwrap();
+        let trashed = move_to_trash_in(&item, &dir.path().join("Trash")).unwrap();
+
+        restore(&trashed.path, &item).unwrap();
+
+        assert!(item.is_dir());
+        assert!(!trashed.path.exists());
+        assert!(!trashed.info.exists());
+    }
+}
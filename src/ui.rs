@@ -1,9 +1,16 @@
 //! UI module for styled terminal output.
 //!
-//! Provides colored output in normal mode and plain tracing in verbose mode.
+//! Provides colored output alongside tracing logs; log-only mode (`--no-ui`)
+//! leaves output to tracing alone.
+//!
+//! Output contract: messages and progress go to the message writer (stderr),
+//! results go to the data writer (stdout) so they can be piped.
+
+use colored::{Color, ColoredString, Colorize};
+use std::io::{self, BufRead, IsTerminal, Write};
 
-use colored::Colorize;
-use std::io::{self, IsTerminal, Write};
+use crate::diff::{render_plain, word_diff, Segment};
+use crate::i18n::Messages;
 
 /// ASCII art header lines for the application (for gradient coloring)
 const HEADER_LINE_1: &str = r"              _     _ _     ____   __       _     _           ";
@@ -21,20 +28,145 @@ const ASCII_HEADER_PLAIN: &str = r"
   \__,_|_| |_|_|\__,_|_.__/ |_____|_|  \___/|_|\__,_|\___|_|
 ";
 
+/// Named color themes selectable with `--theme`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ThemeName {
+    /// Green/yellow/red with a cyan accent
+    #[default]
+    Default,
+    /// Blue/orange/magenta with distinct symbols
+    Colorblind,
+    /// No colors, only bold and dim
+    Mono,
+}
+
+/// Orange from the Okabe-Ito palette (falls back to yellow without truecolor support)
+const ORANGE: Color = Color::TrueColor {
+    r: 230,
+    g: 159,
+    b: 0,
+};
+
+/// Symbols and colors used for styled output
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub success_symbol: &'static str,
+    pub warning_symbol: &'static str,
+    pub error_symbol: &'static str,
+    success: Option<Color>,
+    warning: Option<Color>,
+    error: Option<Color>,
+    accent: Option<Color>,
+    /// Colors for the five header lines
+    header: [Option<Color>; 5],
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Default => Self {
+                success_symbol: "✓",
+                warning_symbol: "!",
+                error_symbol: "✗",
+                success: Some(Color::Green),
+                warning: Some(Color::Yellow),
+                error: Some(Color::Red),
+                accent: Some(Color::Cyan),
+                header: [
+                    Some(Color::BrightCyan),
+                    Some(Color::Cyan),
+                    Some(Color::Blue),
+                    Some(Color::BrightMagenta),
+                    Some(Color::Magenta),
+                ],
+            },
+            ThemeName::Colorblind => Self {
+                success_symbol: "✓",
+                warning_symbol: "△",
+                error_symbol: "✗",
+                success: Some(Color::Blue),
+                warning: Some(ORANGE),
+                error: Some(Color::Magenta),
+                accent: Some(Color::BrightBlue),
+                header: [Some(Color::BrightBlue); 5],
+            },
+            ThemeName::Mono => Self {
+                success_symbol: "✓",
+                warning_symbol: "!",
+                error_symbol: "✗",
+                success: None,
+                warning: None,
+                error: None,
+                accent: None,
+                header: [None; 5],
+            },
+        }
+    }
+
+    fn paint(text: &str, color: Option<Color>) -> ColoredString {
+        match color {
+            Some(color) => text.color(color),
+            None => text.normal(),
+        }
+    }
+
+    pub fn success(&self, text: &str) -> ColoredString {
+        Self::paint(text, self.success)
+    }
+
+    pub fn warning(&self, text: &str) -> ColoredString {
+        Self::paint(text, self.warning)
+    }
+
+    pub fn error(&self, text: &str) -> ColoredString {
+        Self::paint(text, self.error)
+    }
+
+    pub fn accent(&self, text: &str) -> ColoredString {
+        Self::paint(text, self.accent)
+    }
+
+    fn header(&self, line: usize, text: &str) -> ColoredString {
+        Self::paint(text, self.header[line])
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(ThemeName::Default)
+    }
+}
+
 /// UI configuration
 #[derive(Debug, Clone)]
 pub struct UiConfig {
     pub colors_enabled: bool,
-    pub verbose: bool,
+    /// Symbols and colors for styled output
+    pub theme: Theme,
+    /// Suppress styled output and leave everything to tracing
+    pub log_only: bool,
+    /// Show the application header
+    pub banner: bool,
+    /// Ask questions when stdin and stderr are terminals (`--unattended` never does)
+    pub prompts: bool,
+    /// Collapse key-value pairs onto one line and drop blank-line padding
+    pub compact: bool,
+    /// Catalog of translated messages
+    pub messages: Messages,
 }
 
 impl UiConfig {
     /// Create UI config from environment and args
-    pub fn new(verbose: bool) -> Self {
+    pub fn new(log_only: bool) -> Self {
         let colors_enabled = should_use_colors();
         Self {
             colors_enabled,
-            verbose,
+            theme: Theme::default(),
+            log_only,
+            banner: true,
+            prompts: true,
+            compact: false,
+            messages: Messages::default(),
         }
     }
 }
@@ -59,49 +191,102 @@ fn should_use_colors() -> bool {
 pub struct Ui {
     config: UiConfig,
     writer: Box<dyn Write>,
+    /// Writer for results (stdout), independent of chatter
+    data_writer: Box<dyn Write>,
+    /// Key-value pairs waiting to be written on a single line (compact mode)
+    pending_kv: Vec<String>,
+    /// Reader for answers to interactive prompts (stdin)
+    input: Box<dyn BufRead>,
 }
 
 impl Ui {
-    /// Create a new UI with stderr output
+    /// Create a new UI with messages on stderr and data on stdout
     pub fn new(config: UiConfig) -> Self {
-        // Set colored crate's global color setting
-        if !config.colors_enabled {
-            colored::control::set_override(false);
-        }
+        Self::with_writers(config, Box::new(io::stdout()), Box::new(io::stderr()))
+    }
+
+    /// Create UI with custom message writer, discarding data (for testing)
+    #[allow(dead_code)]
+    pub fn with_writer(config: UiConfig, writer: Box<dyn Write>) -> Self {
+        Self::with_writers(config, Box::new(io::sink()), writer)
+    }
+
+    /// Create UI with custom data and message writers
+    pub fn with_writers(
+        config: UiConfig,
+        data_writer: Box<dyn Write>,
+        writer: Box<dyn Write>,
+    ) -> Self {
+        // Set colored crate's global color setting to match (honours FORCE_COLOR)
+        colored::control::set_override(config.colors_enabled);
 
         Self {
             config,
-            writer: Box::new(io::stderr()),
+            writer,
+            data_writer,
+            pending_kv: Vec::new(),
+            input: Box::new(io::BufReader::new(io::stdin())),
         }
     }
 
-    /// Create UI with custom writer (for testing)
+    /// Replace the reader used for interactive prompts (for testing)
     #[allow(dead_code)]
-    pub fn with_writer(config: UiConfig, writer: Box<dyn Write>) -> Self {
-        if !config.colors_enabled {
-            colored::control::set_override(false);
-        }
+    pub fn with_input(mut self, input: Box<dyn BufRead>) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Check if prompts can be answered (stdin and stderr are terminals)
+    pub fn is_interactive() -> bool {
+        io::stdin().is_terminal() && io::stderr().is_terminal()
+    }
+
+    /// Whether to ask questions: prompts are enabled and can be answered
+    pub fn can_prompt(&self) -> bool {
+        self.config.prompts && Self::is_interactive()
+    }
+
+    /// Writer for results; shown in all modes
+    pub fn data(&mut self) -> &mut dyn Write {
+        self.flush_kv();
+        let _ = self.writer.flush();
+        self.data_writer.as_mut()
+    }
 
-        Self { config, writer }
+    /// Write any buffered key-value pairs (compact mode)
+    fn flush_kv(&mut self) {
+        if self.pending_kv.is_empty() {
+            return;
+        }
+        let line = self.pending_kv.join("  ");
+        self.pending_kv.clear();
+        let _ = writeln!(self.writer, "{}", line);
     }
 
     /// Print the application header
     pub fn print_header(&mut self, version: &str) {
-        if self.config.verbose {
-            // Minimal header in verbose mode
+        if !self.config.banner {
+            return;
+        }
+
+        if self.config.log_only || self.config.compact {
+            // Minimal header in log-only and compact modes
             let _ = writeln!(self.writer, "anidb2folder v{}", version);
-            let _ = writeln!(self.writer);
+            if !self.config.compact {
+                let _ = writeln!(self.writer);
+            }
             return;
         }
 
         if self.config.colors_enabled {
-            // Gradient effect: cyan -> blue -> magenta
+            // Gradient effect (default theme: cyan -> blue -> magenta)
+            let theme = self.config.theme;
             let _ = writeln!(self.writer);
-            let _ = writeln!(self.writer, "{}", HEADER_LINE_1.bright_cyan().bold());
-            let _ = writeln!(self.writer, "{}", HEADER_LINE_2.cyan().bold());
-            let _ = writeln!(self.writer, "{}", HEADER_LINE_3.blue().bold());
-            let _ = writeln!(self.writer, "{}", HEADER_LINE_4.bright_magenta());
-            let _ = writeln!(self.writer, "{}", HEADER_LINE_5.magenta());
+            let _ = writeln!(self.writer, "{}", theme.header(0, HEADER_LINE_1).bold());
+            let _ = writeln!(self.writer, "{}", theme.header(1, HEADER_LINE_2).bold());
+            let _ = writeln!(self.writer, "{}", theme.header(2, HEADER_LINE_3).bold());
+            let _ = writeln!(self.writer, "{}", theme.header(3, HEADER_LINE_4));
+            let _ = writeln!(self.writer, "{}", theme.header(4, HEADER_LINE_5));
             let _ = writeln!(
                 self.writer,
                 "{}",
@@ -116,10 +301,13 @@ impl Ui {
 
     /// Print a section header
     pub fn section(&mut self, title: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
-        let _ = writeln!(self.writer);
+        self.flush_kv();
+        if !self.config.compact {
+            let _ = writeln!(self.writer);
+        }
         if self.config.colors_enabled {
             let _ = writeln!(self.writer, "{}", title.bold());
         } else {
@@ -129,11 +317,12 @@ impl Ui {
 
     /// Print an info message
     pub fn info(&mut self, msg: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
-            let _ = writeln!(self.writer, "{}", msg.cyan());
+            let _ = writeln!(self.writer, "{}", self.config.theme.accent(msg));
         } else {
             let _ = writeln!(self.writer, "{}", msg);
         }
@@ -141,11 +330,18 @@ impl Ui {
 
     /// Print a success message with checkmark
     pub fn success(&mut self, msg: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
-            let _ = writeln!(self.writer, "{} {}", "✓".green().bold(), msg.green());
+            let theme = self.config.theme;
+            let _ = writeln!(
+                self.writer,
+                "{} {}",
+                theme.success(theme.success_symbol).bold(),
+                theme.success(msg)
+            );
         } else {
             let _ = writeln!(self.writer, "* {}", msg);
         }
@@ -153,11 +349,18 @@ impl Ui {
 
     /// Print a warning message
     pub fn warning(&mut self, msg: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
-            let _ = writeln!(self.writer, "{} {}", "!".yellow().bold(), msg.yellow());
+            let theme = self.config.theme;
+            let _ = writeln!(
+                self.writer,
+                "{} {}",
+                theme.warning(theme.warning_symbol).bold(),
+                theme.warning(msg)
+            );
         } else {
             let _ = writeln!(self.writer, "! {}", msg);
         }
@@ -166,8 +369,15 @@ impl Ui {
     /// Print an error message
     pub fn error(&mut self, msg: &str) {
         // Errors shown in both modes
+        self.flush_kv();
         if self.config.colors_enabled {
-            let _ = writeln!(self.writer, "{} {}", "✗".red().bold(), msg.red());
+            let theme = self.config.theme;
+            let _ = writeln!(
+                self.writer,
+                "{} {}",
+                theme.error(theme.error_symbol).bold(),
+                theme.error(msg)
+            );
         } else {
             let _ = writeln!(self.writer, "X {}", msg);
         }
@@ -175,9 +385,10 @@ impl Ui {
 
     /// Print a dim/muted message
     pub fn dim(&mut self, msg: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
             let _ = writeln!(self.writer, "{}", msg.dimmed());
         } else {
@@ -188,30 +399,38 @@ impl Ui {
     /// Print progress: [current/total] message
     #[allow(dead_code)]
     pub fn progress(&mut self, current: usize, total: usize, msg: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         let counter = format!("[{}/{}]", current, total);
         if self.config.colors_enabled {
-            let _ = writeln!(self.writer, "{} {}", counter.cyan(), msg);
+            let _ = writeln!(
+                self.writer,
+                "{} {}",
+                self.config.theme.accent(&counter),
+                msg
+            );
         } else {
             let _ = writeln!(self.writer, "{} {}", counter, msg);
         }
     }
 
     /// Print rename progress: [current/total] from → to
+    #[allow(dead_code)]
     pub fn rename_progress(&mut self, current: usize, total: usize, from: &str, to: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         let counter = format!("[{}/{}]", current, total);
         if self.config.colors_enabled {
             let _ = writeln!(
                 self.writer,
                 "{} {} {} {}",
-                counter.cyan(),
+                self.config.theme.accent(&counter),
                 from.dimmed(),
-                "→".cyan(),
+                self.config.theme.accent("→"),
                 to
             );
         } else {
@@ -221,9 +440,10 @@ impl Ui {
 
     /// Print a step in progress
     pub fn step(&mut self, msg: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
             let _ = write!(self.writer, "{}", format!("{}... ", msg).dimmed());
         } else {
@@ -234,19 +454,29 @@ impl Ui {
 
     /// Complete a step
     pub fn step_done(&mut self) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        let done = self.config.messages.get("progress.done");
         if self.config.colors_enabled {
-            let _ = writeln!(self.writer, "{}", "done".green());
+            let _ = writeln!(self.writer, "{}", self.config.theme.success(done));
         } else {
-            let _ = writeln!(self.writer, "done");
+            let _ = writeln!(self.writer, "{}", done);
         }
     }
 
     /// Print a key-value pair
     pub fn kv(&mut self, key: &str, value: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
+            return;
+        }
+        if self.config.compact {
+            let pair = if self.config.colors_enabled {
+                format!("{}: {}", key.bold(), value)
+            } else {
+                format!("{}: {}", key, value)
+            };
+            self.pending_kv.push(pair);
             return;
         }
         if self.config.colors_enabled {
@@ -258,7 +488,11 @@ impl Ui {
 
     /// Print a blank line
     pub fn blank(&mut self) {
-        if self.config.verbose {
+        if self.config.log_only {
+            return;
+        }
+        self.flush_kv();
+        if self.config.compact {
             return;
         }
         let _ = writeln!(self.writer);
@@ -267,9 +501,10 @@ impl Ui {
     /// Print a separator line
     #[allow(dead_code)]
     pub fn separator(&mut self) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
             let _ = writeln!(self.writer, "{}", "─".repeat(50).dimmed());
         } else {
@@ -279,7 +514,20 @@ impl Ui {
 
     /// Print a boxed title (for dry run, revert, etc.)
     pub fn boxed_title(&mut self, title: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
+            return;
+        }
+        self.flush_kv();
+        if self.config.compact {
+            if self.config.colors_enabled {
+                let _ = writeln!(
+                    self.writer,
+                    "{}",
+                    self.config.theme.accent(&format!("== {} ==", title)).bold()
+                );
+            } else {
+                let _ = writeln!(self.writer, "== {} ==", title);
+            }
             return;
         }
         let width = 50;
@@ -295,13 +543,21 @@ impl Ui {
             let _ = writeln!(
                 self.writer,
                 "{}",
-                format!("╔{}╗", "═".repeat(width - 2)).cyan()
+                self.config
+                    .theme
+                    .accent(&format!("╔{}╗", "═".repeat(width - 2)))
             );
-            let _ = writeln!(self.writer, "{}", title_line.cyan().bold());
             let _ = writeln!(
                 self.writer,
                 "{}",
-                format!("╚{}╝", "═".repeat(width - 2)).cyan()
+                self.config.theme.accent(&title_line).bold()
+            );
+            let _ = writeln!(
+                self.writer,
+                "{}",
+                self.config
+                    .theme
+                    .accent(&format!("╚{}╝", "═".repeat(width - 2)))
             );
         } else {
             let _ = writeln!(self.writer, "╔{}╗", "═".repeat(width - 2));
@@ -311,16 +567,18 @@ impl Ui {
     }
 
     /// Print a list item with arrow
+    #[allow(dead_code)]
     pub fn list_item(&mut self, from: &str, to: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
             let _ = writeln!(
                 self.writer,
                 "  {} {} {}",
                 from.dimmed(),
-                "→".cyan(),
+                self.config.theme.accent("→"),
                 to.bold()
             );
         } else {
@@ -328,18 +586,46 @@ impl Ui {
         }
     }
 
+    /// Word diff of a rename: unchanged text dim, removed text struck out,
+    /// added text highlighted; `[-removed-]{+added+}` without colors
+    pub fn render_diff(&self, from: &str, to: &str) -> String {
+        let segments = word_diff(from, to);
+        if !self.config.colors_enabled {
+            return render_plain(&segments);
+        }
+        segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Same(text) => text.dimmed().to_string(),
+                Segment::Removed(text) => self.config.theme.error(text).strikethrough().to_string(),
+                Segment::Added(text) => self.config.theme.success(text).bold().to_string(),
+            })
+            .collect()
+    }
+
+    /// Print a rename as a word diff (see [`render_diff`](Self::render_diff))
+    pub fn diff(&mut self, from: &str, to: &str) {
+        if self.config.log_only {
+            return;
+        }
+        self.flush_kv();
+        let line = self.render_diff(from, to);
+        let _ = writeln!(self.writer, "  {}", line);
+    }
+
     /// Print a completed list item with checkmark
     pub fn list_done(&mut self, from: &str, to: &str) {
-        if self.config.verbose {
+        if self.config.log_only {
             return;
         }
+        self.flush_kv();
         if self.config.colors_enabled {
             let _ = writeln!(
                 self.writer,
                 "  {} {} {} {}",
-                "✓".green(),
+                self.config.theme.success(self.config.theme.success_symbol),
                 from.dimmed(),
-                "→".green(),
+                self.config.theme.success("→"),
                 to
             );
         } else {
@@ -347,15 +633,91 @@ impl Ui {
         }
     }
 
-    /// Check if in verbose mode
-    pub fn is_verbose(&self) -> bool {
-        self.config.verbose
+    /// Ask the user to pick one of `choices` by key
+    ///
+    /// Shown in all modes. Returns `None` when input ends without a valid answer.
+    pub fn choose(&mut self, prompt: &str, choices: &[(char, &str)]) -> Option<char> {
+        self.flush_kv();
+
+        let options = choices
+            .iter()
+            .map(|(key, label)| format!("[{}] {}", key, label))
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        if self.config.colors_enabled {
+            let _ = writeln!(
+                self.writer,
+                "  {} {}",
+                self.config.theme.warning("?").bold(),
+                prompt
+            );
+        } else {
+            let _ = writeln!(self.writer, "  ? {}", prompt);
+        }
+
+        loop {
+            let _ = write!(self.writer, "    {} > ", options);
+            let _ = self.writer.flush();
+
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    let _ = writeln!(self.writer);
+                    return None;
+                }
+                Ok(_) => {}
+            }
+
+            let answer = line.trim().to_lowercase();
+            let mut chars = answer.chars();
+            if let (Some(key), None) = (chars.next(), chars.next()) {
+                if choices.iter().any(|(k, _)| *k == key) {
+                    return Some(key);
+                }
+            }
+        }
+    }
+
+    /// Catalog of translated messages
+    pub fn messages(&self) -> Messages {
+        self.config.messages
+    }
+
+    /// Check if in compact mode
+    #[allow(dead_code)]
+    pub fn is_compact(&self) -> bool {
+        self.config.compact
+    }
+
+    /// Check if in log-only mode
+    pub fn is_log_only(&self) -> bool {
+        self.config.log_only
+    }
+
+    /// Theme used for styled output
+    pub fn theme(&self) -> Theme {
+        self.config.theme
     }
 
     /// Check if colors are enabled
     pub fn is_colors_enabled(&self) -> bool {
         self.config.colors_enabled
     }
+
+    pub fn is_banner_enabled(&self) -> bool {
+        self.config.banner
+    }
+
+    pub fn prompts_enabled(&self) -> bool {
+        self.config.prompts
+    }
+}
+
+impl Drop for Ui {
+    fn drop(&mut self) {
+        self.flush_kv();
+    }
 }
 
 #[cfg(test)]
@@ -375,16 +737,58 @@ mod tests {
         }
     }
 
-    fn create_test_ui(verbose: bool) -> (Ui, Arc<Mutex<Vec<u8>>>) {
-        let buffer = Arc::new(Mutex::new(Vec::new()));
-        let config = UiConfig {
+    fn create_test_ui(log_only: bool) -> (Ui, Arc<Mutex<Vec<u8>>>) {
+        create_test_ui_with(UiConfig {
             colors_enabled: false,
-            verbose,
-        };
+            theme: Theme::default(),
+            log_only,
+            banner: true,
+            prompts: true,
+            compact: false,
+            messages: Messages::default(),
+        })
+    }
+
+    /// Serializes changes to the global color override (see `themed_output`)
+    static COLOR_OVERRIDE: Mutex<()> = Mutex::new(());
+
+    fn create_test_ui_with(config: UiConfig) -> (Ui, Arc<Mutex<Vec<u8>>>) {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let _guard = COLOR_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner());
         let ui = Ui::with_writer(config, Box::new(TestWriter(buffer.clone())));
         (ui, buffer)
     }
 
+    /// Render a fixed set of messages with colors forced on
+    fn themed_output(name: ThemeName) -> String {
+        let _guard = COLOR_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner());
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let config = UiConfig {
+            colors_enabled: true,
+            theme: Theme::new(name),
+            log_only: false,
+            banner: true,
+            prompts: true,
+            compact: false,
+            messages: Messages::default(),
+        };
+        let mut ui = Ui::with_writer(config, Box::new(TestWriter(buffer.clone())));
+
+        ui.success("ok");
+        ui.warning("careful");
+        ui.error("failed");
+        ui.info("note");
+        drop(ui);
+
+        let output = output_of(&buffer);
+        colored::control::set_override(false);
+        output
+    }
+
+    fn output_of(buffer: &Arc<Mutex<Vec<u8>>>) -> String {
+        String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+    }
+
     #[test]
     fn test_ui_config_respects_no_color() {
         std::env::set_var("NO_COLOR", "1");
@@ -404,7 +808,7 @@ mod tests {
     }
 
     #[test]
-    fn test_ui_verbose_mode_skips_decorations() {
+    fn test_ui_log_only_mode_skips_decorations() {
         let (mut ui, buffer) = create_test_ui(true);
         ui.info("Should not appear");
         ui.section("Should not appear");
@@ -415,11 +819,183 @@ mod tests {
     }
 
     #[test]
-    fn test_ui_error_shown_in_verbose() {
+    fn test_ui_error_shown_in_log_only() {
         let (mut ui, buffer) = create_test_ui(true);
         ui.error("This error should appear");
 
         let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
         assert!(output.contains("This error should appear"));
     }
+
+    #[test]
+    fn test_ui_data_and_messages_are_separated() {
+        let data = Arc::new(Mutex::new(Vec::new()));
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let config = UiConfig {
+            colors_enabled: false,
+            theme: Theme::default(),
+            log_only: true,
+            banner: true,
+            prompts: true,
+            compact: false,
+            messages: Messages::default(),
+        };
+        let mut ui = {
+            let _guard = COLOR_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner());
+            Ui::with_writers(
+                config,
+                Box::new(TestWriter(data.clone())),
+                Box::new(TestWriter(messages.clone())),
+            )
+        };
+
+        ui.error("failure");
+        let _ = writeln!(ui.data(), "12345 -> Title [anidb-12345]");
+
+        assert_eq!(output_of(&data), "12345 -> Title [anidb-12345]\n");
+        assert!(!output_of(&messages).contains("anidb-12345"));
+    }
+
+    #[test]
+    fn test_ui_no_banner() {
+        let (mut ui, buffer) = create_test_ui_with(UiConfig {
+            colors_enabled: false,
+            theme: Theme::default(),
+            log_only: false,
+            banner: false,
+            prompts: true,
+            compact: false,
+            messages: Messages::default(),
+        });
+        ui.print_header("1.0.0");
+
+        assert!(output_of(&buffer).is_empty());
+    }
+
+    #[test]
+    fn test_ui_compact_mode() {
+        let (mut ui, buffer) = create_test_ui_with(UiConfig {
+            colors_enabled: false,
+            theme: Theme::default(),
+            log_only: false,
+            banner: true,
+            prompts: true,
+            compact: true,
+            messages: Messages::default(),
+        });
+        ui.print_header("1.0.0");
+        ui.kv("Found", "2 directories");
+        ui.kv("Format", "AniDB");
+        ui.blank();
+        ui.boxed_title("DRY RUN");
+        ui.success("Done");
+
+        let output = output_of(&buffer);
+        assert!(!output.contains("|___"));
+        assert!(output.contains("Found: 2 directories  Format: AniDB\n"));
+        assert!(output.contains("== DRY RUN =="));
+        assert!(!output.contains("\n\n"));
+    }
+
+    #[test]
+    fn test_ui_compact_flushes_on_drop() {
+        let (mut ui, buffer) = create_test_ui_with(UiConfig {
+            colors_enabled: false,
+            theme: Theme::default(),
+            log_only: false,
+            banner: true,
+            prompts: true,
+            compact: true,
+            messages: Messages::default(),
+        });
+        ui.kv("Key", "Value");
+        drop(ui);
+
+        assert_eq!(output_of(&buffer), "Key: Value\n");
+    }
+
+    #[test]
+    fn test_choose_reads_answer() {
+        let (ui, buffer) = create_test_ui(true);
+        let mut ui = ui.with_input(Box::new(io::Cursor::new("x\nS\n")));
+
+        let choice = ui.choose("Conflict", &[('s', "skip"), ('q', "abort")]);
+
+        assert_eq!(choice, Some('s'));
+        let output = output_of(&buffer);
+        assert!(output.contains("? Conflict"));
+        assert!(output.contains("[s] skip  [q] abort"));
+    }
+
+    #[test]
+    fn test_choose_end_of_input() {
+        let (ui, _buffer) = create_test_ui(false);
+        let mut ui = ui.with_input(Box::new(io::Cursor::new("")));
+
+        assert_eq!(ui.choose("Conflict", &[('s', "skip")]), None);
+    }
+
+    #[test]
+    fn test_theme_default_snapshot() {
+        assert_eq!(
+            themed_output(ThemeName::Default),
+            "\x1b[1;32m✓\x1b[0m \x1b[32mok\x1b[0m\n\
+             \x1b[1;33m!\x1b[0m \x1b[33mcareful\x1b[0m\n\
+             \x1b[1;31m✗\x1b[0m \x1b[31mfailed\x1b[0m\n\
+             \x1b[36mnote\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_theme_colorblind_snapshot() {
+        // Orange renders as truecolor or nearest basic color depending on COLORTERM
+        let orange = ORANGE.to_fg_str();
+        assert_eq!(
+            themed_output(ThemeName::Colorblind),
+            format!(
+                "\x1b[1;34m✓\x1b[0m \x1b[34mok\x1b[0m\n\
+                 \x1b[1;{orange}m△\x1b[0m \x1b[{orange}mcareful\x1b[0m\n\
+                 \x1b[1;35m✗\x1b[0m \x1b[35mfailed\x1b[0m\n\
+                 \x1b[94mnote\x1b[0m\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_diff() {
+        let (ui, _) = create_test_ui(false);
+        assert_eq!(
+            ui.render_diff("Bebop (1998) [anidb-23]", "Bebop (1999) [anidb-23]"),
+            "Bebop ([-1998-]{+1999+}) [anidb-23]"
+        );
+
+        let _guard = COLOR_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner());
+        let config = UiConfig {
+            colors_enabled: true,
+            theme: Theme::default(),
+            log_only: false,
+            banner: true,
+            prompts: true,
+            compact: false,
+            messages: Messages::default(),
+        };
+        let ui = Ui::with_writer(config, Box::new(io::sink()));
+        let colored = ui.render_diff("A (1998)", "A (1999)");
+        colored::control::set_override(false);
+        assert_eq!(
+            colored,
+            "\x1b[2mA (\x1b[0m\x1b[9;31m1998\x1b[0m\x1b[1;32m1999\x1b[0m\x1b[2m)\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_theme_mono_snapshot() {
+        assert_eq!(
+            themed_output(ThemeName::Mono),
+            "\x1b[1m✓\x1b[0m ok\n\
+             \x1b[1m!\x1b[0m careful\n\
+             \x1b[1m✗\x1b[0m failed\n\
+             note\n"
+        );
+    }
 }
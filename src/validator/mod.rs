@@ -1,52 +1,107 @@
+mod suggest;
 mod types;
 
+pub use suggest::suggest_name;
 pub use types::*;
 
-use crate::parser::{parse_directory_name, DirectoryFormat, ParsedDirectory};
+use crate::parser::{
+    check_strict, classify_with, parse_directory_name_with, DirectoryFormat, NameTemplate,
+    ParseError, ParseOptions, ParsedDirectory,
+};
 use crate::scanner::DirectoryEntry;
 use tracing::{debug, info, warn};
 
 /// Validate that all directories are in the same format
+///
+/// Entries whose name is not valid UTF-8 are left out and listed in
+/// [`ValidationResult::non_utf8`]: a rename built from their lossy name
+/// would target a path that does not exist.
+///
+/// An [`UnrecognizedDirectories`](ValidationError::UnrecognizedDirectories)
+/// error keeps the first [`UNRECOGNIZED_LIST_CAP`] names.
+#[allow(dead_code)] // Library API, the binary passes its template
 pub fn validate_directories(
     entries: &[DirectoryEntry],
 ) -> Result<ValidationResult, ValidationError> {
+    validate_directories_with(entries, Some(UNRECOGNIZED_LIST_CAP))
+}
+
+/// Like [`validate_directories`], keeping up to `unrecognized_cap` names (`None`: all)
+#[allow(dead_code)] // Library API, the binary passes its template
+pub fn validate_directories_with(
+    entries: &[DirectoryEntry],
+    unrecognized_cap: Option<usize>,
+) -> Result<ValidationResult, ValidationError> {
+    validate_directories_for(entries, unrecognized_cap, None)
+}
+
+/// Like [`validate_directories_with`], also recognizing names laid out by `template`
+#[allow(dead_code)] // Library API, the binary passes its parse options
+pub fn validate_directories_for(
+    entries: &[DirectoryEntry],
+    unrecognized_cap: Option<usize>,
+    template: Option<&NameTemplate>,
+) -> Result<ValidationResult, ValidationError> {
+    validate_directories_with_options(
+        entries,
+        unrecognized_cap,
+        template,
+        &ParseOptions::default(),
+    )
+}
+
+/// Like [`validate_directories_for`], parsing names with `options`
+///
+/// With `options.strict`, every name refused by strict parsing is listed in
+/// an [`AmbiguousDirectories`](ValidationError::AmbiguousDirectories) error,
+/// once the listing is known to be in one format.
+pub fn validate_directories_with_options(
+    entries: &[DirectoryEntry],
+    unrecognized_cap: Option<usize>,
+    template: Option<&NameTemplate>,
+    options: &ParseOptions,
+) -> Result<ValidationResult, ValidationError> {
+    let (entries, non_utf8): (Vec<&DirectoryEntry>, Vec<&DirectoryEntry>) =
+        entries.iter().partition(|e| e.is_utf8());
+    let non_utf8: Vec<String> = non_utf8.into_iter().map(|e| e.name.clone()).collect();
+    if !non_utf8.is_empty() {
+        warn!(count = non_utf8.len(), "Directories with a non-UTF-8 name");
+    }
     if entries.is_empty() {
         return Err(ValidationError::NoDirectories);
     }
 
     info!("Validating {} directories", entries.len());
 
-    let mut parsed: Vec<ParsedDirectory> = Vec::with_capacity(entries.len());
     let mut unrecognized: Vec<String> = Vec::new();
+    let mut unrecognized_total = 0;
     let mut anidb_dirs: Vec<String> = Vec::new();
     let mut human_readable_dirs: Vec<String> = Vec::new();
 
-    for entry in entries {
-        match parse_directory_name(&entry.name) {
-            Ok(p) => {
-                debug!(name = %entry.name, format = ?p.format(), "Parsed directory");
-
-                match p.format() {
-                    DirectoryFormat::AniDb => anidb_dirs.push(entry.name.clone()),
-                    DirectoryFormat::HumanReadable => human_readable_dirs.push(entry.name.clone()),
-                }
-
-                parsed.push(p);
-            }
-            Err(_) => {
+    // Fast pre-pass: classify names without parsing them, so a listing that
+    // is rejected anyway is never fully parsed
+    for entry in &entries {
+        match classify_with(&entry.name, template) {
+            Some(DirectoryFormat::AniDb) => anidb_dirs.push(entry.name.clone()),
+            Some(DirectoryFormat::HumanReadable) => human_readable_dirs.push(entry.name.clone()),
+            None => {
                 debug!(name = %entry.name, "Unrecognized format");
-                unrecognized.push(entry.name.clone());
+                unrecognized_total += 1;
+                if unrecognized_cap.is_none_or(|cap| unrecognized.len() < cap) {
+                    unrecognized.push(entry.name.clone());
+                }
             }
         }
     }
 
-    if !unrecognized.is_empty() {
+    if unrecognized_total > 0 {
         warn!(
-            count = unrecognized.len(),
+            count = unrecognized_total,
             "Directories with unrecognized format"
         );
         return Err(ValidationError::UnrecognizedDirectories {
             directories: unrecognized,
+            total: unrecognized_total,
         });
     }
 
@@ -73,15 +128,76 @@ pub fn validate_directories(
         DirectoryFormat::HumanReadable
     };
 
+    // Every name classified, so each one parses
+    let mut parsed: Vec<ParsedDirectory> = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let p = parse_directory_name_with(&entry.name, template).map_err(|_| {
+            ValidationError::UnrecognizedDirectories {
+                directories: vec![entry.name.clone()],
+                total: 1,
+            }
+        })?;
+        debug!(name = %entry.name, format = ?p.format(), "Parsed directory");
+        parsed.push(p);
+    }
+
+    if options.strict {
+        let reasons: Vec<String> = parsed
+            .iter()
+            .filter_map(|p| match check_strict(p) {
+                Err(ParseError::Ambiguous(reason)) => Some(reason),
+                _ => None,
+            })
+            .collect();
+        if !reasons.is_empty() {
+            warn!(count = reasons.len(), "Ambiguous directory names");
+            return Err(ValidationError::AmbiguousDirectories { reasons });
+        }
+    }
+
     info!(
         count = parsed.len(),
         format = ?format,
         "Validation passed"
     );
 
+    // Accepted in any case, but a changed case means something rewrites names.
+    // Other spellings come from other tools and are only rewritten.
+    let noncanonical_id_tags: Vec<String> = parsed
+        .iter()
+        .filter_map(|p| match p {
+            ParsedDirectory::HumanReadable(f)
+                if !f.has_canonical_id_tag() && !f.has_alternate_id_tag() =>
+            {
+                Some(f.original_name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    if !noncanonical_id_tags.is_empty() {
+        warn!(
+            count = noncanonical_id_tags.len(),
+            "Directories with a non-lowercase [anidb-ID] tag"
+        );
+    }
+
+    let untitled: Vec<String> = parsed
+        .iter()
+        .filter_map(|p| match p {
+            ParsedDirectory::HumanReadable(f) if f.is_untitled() => Some(f.original_name.clone()),
+            _ => None,
+        })
+        .collect();
+    if !untitled.is_empty() {
+        warn!(count = untitled.len(), "Directories without a title");
+    }
+
     Ok(ValidationResult {
         format,
         directories: parsed,
+        noncanonical_id_tags,
+        untitled,
+        non_utf8,
     })
 }
 
@@ -90,9 +206,7 @@ mod tests {
     use super::*;
 
     fn make_entry(name: &str) -> DirectoryEntry {
-        DirectoryEntry {
-            name: name.to_string(),
-        }
+        DirectoryEntry::new(name.to_string())
     }
 
     #[test]
@@ -109,6 +223,86 @@ mod tests {
         assert_eq!(result.directories.len(), 3);
     }
 
+    #[test]
+    fn test_validate_lists_noncanonical_id_tags() {
+        let entries = vec![
+            make_entry("Naruto (2002) [anidb-12345]"),
+            make_entry("NARUTO (2002) [ANIDB-12346]"),
+            make_entry("Bebop (1998) [AniDB-1]"),
+        ];
+
+        let result = validate_directories(&entries).unwrap();
+
+        assert_eq!(result.format, DirectoryFormat::HumanReadable);
+        assert_eq!(result.directories.len(), 3);
+        assert_eq!(
+            result.noncanonical_id_tags,
+            ["NARUTO (2002) [ANIDB-12346]", "Bebop (1998) [AniDB-1]"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_skips_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // Lossy, this would be a readable name of another format
+        let entries = vec![
+            make_entry("123"),
+            DirectoryEntry::from_os_name(OsStr::from_bytes(b"[\xff] Title [anidb-2]")),
+        ];
+
+        let result = validate_directories(&entries).unwrap();
+
+        assert_eq!(result.format, DirectoryFormat::AniDb);
+        assert_eq!(result.directories.len(), 1);
+        assert_eq!(result.non_utf8, ["[\u{FFFD}] Title [anidb-2]"]);
+
+        let only_lossy = &entries[1..];
+        assert!(matches!(
+            validate_directories(only_lossy),
+            Err(ValidationError::NoDirectories)
+        ));
+    }
+
+    #[test]
+    fn test_validate_strict_lists_ambiguous_names() {
+        let entries = vec![
+            make_entry("12345"),
+            make_entry("20240131"),
+            make_entry("[anidb-1] 2"),
+        ];
+        let strict = ParseOptions { strict: true };
+
+        assert!(validate_directories(&entries).is_ok());
+        match validate_directories_with_options(&entries, None, None, &strict) {
+            Err(ValidationError::AmbiguousDirectories { reasons }) => {
+                assert_eq!(reasons.len(), 2);
+                assert!(reasons[0].starts_with("20240131: "), "{}", reasons[0]);
+                assert!(reasons[1].starts_with("[anidb-1] 2: "), "{}", reasons[1]);
+            }
+            other => panic!("Expected AmbiguousDirectories, got {:?}", other),
+        }
+
+        let entries = vec![make_entry("12345"), make_entry("[AS0] 67890")];
+        assert!(validate_directories_with_options(&entries, None, None, &strict).is_ok());
+    }
+
+    #[test]
+    fn test_validate_lists_untitled() {
+        let entries = vec![
+            make_entry("Naruto (2002) [anidb-12345]"),
+            make_entry("[anidb-1]"),
+            make_entry("[AS0] [anidb-2]"),
+        ];
+
+        let result = validate_directories(&entries).unwrap();
+
+        assert_eq!(result.format, DirectoryFormat::HumanReadable);
+        assert_eq!(result.untitled, ["[anidb-1]", "[AS0] [anidb-2]"]);
+    }
+
     #[test]
     fn test_validate_all_human_readable() {
         let entries = vec![
@@ -123,6 +317,57 @@ mod tests {
         assert_eq!(result.directories.len(), 3);
     }
 
+    #[test]
+    fn test_validate_alternate_id_tags() {
+        let entries = vec![
+            make_entry("Naruto (2002) (anidb-12345)"),
+            make_entry("[AS0] Cowboy Bebop (1998) [aid=1]"),
+            make_entry("One Piece [anidb 69]"),
+            make_entry("Bebop (1998) [ANIDB-2]"),
+        ];
+
+        let result = validate_directories(&entries).unwrap();
+
+        assert_eq!(result.format, DirectoryFormat::HumanReadable);
+        assert_eq!(result.directories.len(), 4);
+        // Only a changed case is reported
+        assert_eq!(result.noncanonical_id_tags, ["Bebop (1998) [ANIDB-2]"]);
+    }
+
+    #[test]
+    fn test_validate_episode_counts_on_some_names() {
+        let entries = vec![
+            make_entry("Naruto (2002) [anidb-12345] [220 eps]"),
+            make_entry("[AS0] Cowboy Bebop (1998) [anidb-1]"),
+            make_entry("Movie [ANIDB-3] [1 ep]"),
+        ];
+
+        let result = validate_directories(&entries).unwrap();
+
+        assert_eq!(result.format, DirectoryFormat::HumanReadable);
+        assert_eq!(result.directories.len(), 3);
+        assert_eq!(result.noncanonical_id_tags, ["Movie [ANIDB-3] [1 ep]"]);
+    }
+
+    #[test]
+    fn test_validate_template_names() {
+        let template = NameTemplate::parse("[anidb-{id}] {title_jp} {tag}").unwrap();
+        let entries = vec![
+            make_entry("[anidb-1] Cowboy Bebop [AS0]"),
+            make_entry("Naruto (2002) [anidb-12345]"),
+        ];
+
+        assert!(matches!(
+            validate_directories(&entries),
+            Err(ValidationError::UnrecognizedDirectories { .. })
+        ));
+        let result = validate_directories_for(&entries, None, Some(&template)).unwrap();
+        assert_eq!(result.format, DirectoryFormat::HumanReadable);
+        assert_eq!(result.directories[0].anidb_id(), 1);
+        assert_eq!(result.directories[0].series_tag(), Some("AS0"));
+        assert_eq!(result.directories[1].anidb_id(), 12345);
+    }
+
     #[test]
     fn test_validate_mixed_formats_error() {
         let entries = vec![
@@ -155,13 +400,38 @@ mod tests {
             Err(ValidationError::UnrecognizedDirectories { .. })
         ));
 
-        if let Err(ValidationError::UnrecognizedDirectories { directories }) = result {
+        if let Err(ValidationError::UnrecognizedDirectories { directories, total }) = result {
             assert_eq!(directories.len(), 2);
+            assert_eq!(total, 2);
             assert!(directories.contains(&"Random Folder".to_string()));
             assert!(directories.contains(&"Another Invalid".to_string()));
         }
     }
 
+    #[test]
+    fn test_unrecognized_list_is_capped() {
+        let entries: Vec<DirectoryEntry> = (0..10_000)
+            .map(|i| make_entry(&format!("Folder {}", i)))
+            .collect();
+
+        match validate_directories(&entries) {
+            Err(ValidationError::UnrecognizedDirectories { directories, total }) => {
+                assert_eq!(directories.len(), UNRECOGNIZED_LIST_CAP);
+                assert_eq!(directories[0], "Folder 0");
+                assert_eq!(total, 10_000);
+            }
+            other => panic!("expected unrecognized directories, got {:?}", other),
+        }
+
+        match validate_directories_with(&entries, None) {
+            Err(ValidationError::UnrecognizedDirectories { directories, total }) => {
+                assert_eq!(directories.len(), 10_000);
+                assert_eq!(total, 10_000);
+            }
+            other => panic!("expected unrecognized directories, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_validate_empty_error() {
         let entries: Vec<DirectoryEntry> = vec![];
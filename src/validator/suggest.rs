@@ -0,0 +1,141 @@
+//! Suggestions for names in no recognized format.
+//!
+//! Most unrecognized names are near misses: `anidb_12345` for `[anidb-12345]`,
+//! a series tag missing a bracket, words joined by underscores. Each fix
+//! below rewrites one such slip; they are applied in turn and the first
+//! name that parses is suggested. Nothing is renamed, the suggestion only
+//! goes into the error message.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::parser::parse_directory_name;
+
+// An id tag written loosely at the end of the name: missing or mismatched
+// brackets, and `_`, `=`, `:`, `#`, `/`, `\` or whitespace for the dash,
+// e.g. "anidb_12345", "(anidb 12345", "[aid:12345]"
+static LOOSE_ID_TAG_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\s*[\[\(]?\s*(?:anidb|aid)\s*[-_=:#/\\\s]?\s*(\d+)\s*[\]\)]?$").unwrap()
+});
+
+// A series tag missing its closing bracket, e.g. "[AS0 12345"
+static OPEN_SERIES_TAG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\[([^\[\]]+?)\s+(\d+)$").unwrap());
+
+// A series tag missing its opening bracket, e.g. "AS0] 12345"
+static UNOPENED_SERIES_TAG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([^\[\]]+)\]\s*(\d+)$").unwrap());
+
+/// One rewrite of a common slip; `None` when it does not apply
+type Fix = fn(&str) -> Option<String>;
+
+const FIXES: &[Fix] = &[
+    trim_leading_whitespace,
+    underscores_to_spaces,
+    loose_id_tag,
+    series_tag_brackets,
+];
+
+/// A name in a recognized format that `name` was probably meant to be
+///
+/// `None` when `name` already parses or no fix makes it parse, which is
+/// the case for names unrelated to anime directories.
+pub fn suggest_name(name: &str) -> Option<String> {
+    if parse_directory_name(name).is_ok() {
+        return None;
+    }
+    let mut candidate = name.to_string();
+    for fix in FIXES {
+        let Some(fixed) = fix(&candidate) else {
+            continue;
+        };
+        if parse_directory_name(&fixed).is_ok() {
+            return Some(fixed);
+        }
+        candidate = fixed;
+    }
+    None
+}
+
+fn trim_leading_whitespace(name: &str) -> Option<String> {
+    let trimmed = name.trim_start();
+    (trimmed.len() != name.len()).then(|| trimmed.to_string())
+}
+
+/// Underscores for spaces, as in `Cowboy_Bebop_(1998)_[anidb-23]`
+fn underscores_to_spaces(name: &str) -> Option<String> {
+    (name.contains('_') && !name.contains(char::is_whitespace))
+        .then(|| name.replace('_', " ").trim().to_string())
+}
+
+/// `[anidb-ID]` for a loosely written id tag
+fn loose_id_tag(name: &str) -> Option<String> {
+    let captures = LOOSE_ID_TAG_REGEX.captures(name)?;
+    let tag = captures.get(0)?;
+    let title = name[..tag.start()].trim_end();
+    let id = &captures[1];
+    Some(if title.is_empty() {
+        format!("[anidb-{}]", id)
+    } else {
+        format!("{} [anidb-{}]", title, id)
+    })
+}
+
+/// `[<tag>] <id>` for a series tag missing a bracket
+fn series_tag_brackets(name: &str) -> Option<String> {
+    let captures = OPEN_SERIES_TAG_REGEX
+        .captures(name)
+        .or_else(|| UNOPENED_SERIES_TAG_REGEX.captures(name))?;
+    Some(format!("[{}] {}", captures[1].trim(), &captures[2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_near_misses_get_suggestions() {
+        for (name, suggestion) in [
+            ("Naruto (2002) anidb_12345", "Naruto (2002) [anidb-12345]"),
+            ("Naruto (2002) [anidb_12345]", "Naruto (2002) [anidb-12345]"),
+            ("Naruto (2002) [anidb-12345", "Naruto (2002) [anidb-12345]"),
+            ("Naruto (2002) anidb 12345", "Naruto (2002) [anidb-12345]"),
+            ("Naruto (2002) [aid:12345]", "Naruto (2002) [anidb-12345]"),
+            ("Naruto (2002) (anidb/12345)", "Naruto (2002) [anidb-12345]"),
+            (
+                "Cowboy_Bebop_(1998)_anidb_23",
+                "Cowboy Bebop (1998) [anidb-23]",
+            ),
+            ("[AS0 12345", "[AS0] 12345"),
+            ("AS0] 12345", "[AS0] 12345"),
+            ("[My Series 67890", "[My Series] 67890"),
+            (" 12345", "12345"),
+            ("anidb_12345", "[anidb-12345]"),
+        ] {
+            assert_eq!(suggest_name(name).as_deref(), Some(suggestion), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_unrelated_names_get_none() {
+        for name in [
+            "Random Folder",
+            "Notes",
+            ".stfolder",
+            "Season 1",
+            "my_documents",
+            "[Draft] ideas",
+            "Title (2020) [anidb-12a]",
+            "Naruto (2002) [anidb-99999999999]",
+            "",
+        ] {
+            assert_eq!(suggest_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_recognized_names_get_none() {
+        assert_eq!(suggest_name("[AS0] 12345"), None);
+        assert_eq!(suggest_name("Naruto (2002) [anidb-12345]"), None);
+    }
+}
@@ -5,6 +5,12 @@ use thiserror::Error;
 pub struct ValidationResult {
     pub format: DirectoryFormat,
     pub directories: Vec<ParsedDirectory>,
+    /// Names whose `[anidb-ID]` tag is not lowercase, e.g. `[ANIDB-12345]`
+    pub noncanonical_id_tags: Vec<String>,
+    /// Names with no title, e.g. `[anidb-12345]`
+    pub untitled: Vec<String>,
+    /// Lossy names of directories whose name is not valid UTF-8, left out
+    pub non_utf8: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -13,14 +19,29 @@ pub struct FormatMismatch {
     pub human_readable_dirs: Vec<String>,
 }
 
+/// Unrecognized names kept in [`ValidationError::UnrecognizedDirectories`] by default
+pub const UNRECOGNIZED_LIST_CAP: usize = 10;
+
 #[derive(Error, Debug)]
 pub enum ValidationError {
-    #[error("Unrecognized directory format")]
-    UnrecognizedDirectories { directories: Vec<String> },
+    #[error("Unrecognized directory format ({total} directories)")]
+    UnrecognizedDirectories {
+        /// The first unrecognized names, or all of them when uncapped
+        directories: Vec<String>,
+        /// Number of unrecognized directories
+        total: usize,
+    },
 
     #[error("Mixed directory formats found")]
     MixedFormats { mismatch: FormatMismatch },
 
     #[error("No directories found in target")]
     NoDirectories,
+
+    /// Names refused by strict parsing
+    #[error("Ambiguous directory names ({} directories)", reasons.len())]
+    AmbiguousDirectories {
+        /// One line per name: the name and why it was refused
+        reasons: Vec<String>,
+    },
 }
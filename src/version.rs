@@ -0,0 +1,129 @@
+//! Version and build information.
+//!
+//! The crate version alone does not identify a build: which cargo features
+//! it has and which schema versions it writes matter when reading a bug
+//! report or an old history file. [`BuildInfo`] collects them; it is printed
+//! by `--build-info` and recorded in history files and JSON reports.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::history::HISTORY_VERSION;
+
+/// Crate version
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Target triple of this build, e.g. `x86_64-unknown-linux-gnu`
+pub const TARGET: &str = env!("ANIDB2FOLDER_TARGET");
+
+/// Version of the JSON reports printed with `--json` and of `--output ndjson`
+/// events, carried in their `schema` field
+///
+/// Bump it when a field is renamed, removed or changes meaning (adding a
+/// field is compatible) and record the change in CONTRIBUTING.md.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Add the `schema` field to a JSON report object
+pub fn with_schema(mut report: serde_json::Value) -> serde_json::Value {
+    if let Some(object) = report.as_object_mut() {
+        object.insert("schema".to_string(), JSON_SCHEMA_VERSION.into());
+    }
+    report
+}
+
+/// What a build is made of
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Crate version
+    pub version: String,
+    /// Enabled cargo features
+    pub features: Vec<String>,
+    /// Target triple
+    pub target: String,
+    /// History schema version written
+    pub history_version: String,
+    /// Cache schema version written (absent in builds without the cache)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_version: Option<String>,
+}
+
+impl BuildInfo {
+    /// Information about the running build
+    pub fn current() -> Self {
+        #[cfg(feature = "cache")]
+        let cache_version = Some(crate::cache::CACHE_VERSION.to_string());
+        #[cfg(not(feature = "cache"))]
+        let cache_version = None;
+
+        Self {
+            version: VERSION.to_string(),
+            features: enabled_features().iter().map(|f| f.to_string()).collect(),
+            target: TARGET.to_string(),
+            history_version: HISTORY_VERSION.to_string(),
+            cache_version,
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "anidb2folder {}", self.version)?;
+        writeln!(f, "features: {}", self.features.join(", "))?;
+        writeln!(f, "target: {}", self.target)?;
+        writeln!(f, "history schema: {}", self.history_version)?;
+        write!(f, "json schema: {}", JSON_SCHEMA_VERSION)?;
+        if let Some(cache_version) = &self.cache_version {
+            write!(f, "\ncache schema: {}", cache_version)?;
+        }
+        Ok(())
+    }
+}
+
+/// Cargo features enabled in this build
+pub fn enabled_features() -> Vec<&'static str> {
+    [
+        ("api", cfg!(feature = "api")),
+        ("async", cfg!(feature = "async")),
+        ("cache", cfg!(feature = "cache")),
+        ("cli", cfg!(feature = "cli")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_build_info() {
+        let info = BuildInfo::current();
+
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.history_version, HISTORY_VERSION);
+        assert!(!info.target.is_empty());
+        assert_eq!(
+            info.features.contains(&"cache".to_string()),
+            info.cache_version.is_some()
+        );
+        #[cfg(feature = "cli")]
+        {
+            let mut expected = vec!["api", "cache", "cli"];
+            if cfg!(feature = "async") {
+                expected.insert(1, "async");
+            }
+            assert_eq!(info.features, expected);
+        }
+    }
+
+    #[test]
+    fn test_build_info_round_trips() {
+        let info = BuildInfo::current();
+        let json = serde_json::to_string(&info).unwrap();
+
+        assert_eq!(serde_json::from_str::<BuildInfo>(&json).unwrap(), info);
+        assert!(info.to_string().starts_with("anidb2folder "));
+    }
+}
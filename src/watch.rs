@@ -0,0 +1,203 @@
+//! Continuous watch mode (`--watch`).
+//!
+//! The watched directory is polled once per interval. A new AniDB-format
+//! subdirectory becomes ready once it is still there on the next poll, so
+//! a directory that is still being copied or moved in gets an interval to
+//! settle; ready names are then planned and renamed as one batch. Every
+//! other name (including the readable names the batches produce) is only
+//! remembered, so each directory is handled once.
+//!
+//! The watcher polls instead of subscribing to filesystem events: it works
+//! the same on network shares, where change notifications are unreliable.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use crate::parser::{classify, DirectoryFormat};
+use crate::scanner::{scan_directory, ScannerError};
+
+/// Default for `--once-per`, in seconds
+pub const DEFAULT_WATCH_INTERVAL_SECS: u64 = 5;
+
+/// Tracks the subdirectories of a watched directory between polls
+#[derive(Debug)]
+pub struct Watcher {
+    dir: PathBuf,
+    /// Names already handled or ignored
+    known: BTreeSet<String>,
+    /// New AniDB-format names waiting for the next poll
+    pending: BTreeSet<String>,
+}
+
+impl Watcher {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            known: BTreeSet::new(),
+            pending: BTreeSet::new(),
+        }
+    }
+
+    /// Scan the directory and return the names ready to be renamed
+    ///
+    /// Returned names are remembered: they are not returned again, whether
+    /// or not their batch succeeds.
+    pub fn poll(&mut self) -> Result<BTreeSet<String>, ScannerError> {
+        let names: BTreeSet<String> = scan_directory(&self.dir)?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        Ok(self.update(names))
+    }
+
+    fn update(&mut self, names: BTreeSet<String>) -> BTreeSet<String> {
+        // Forget what is gone, so a name that comes back is new again
+        self.known.retain(|name| names.contains(name));
+        self.pending.retain(|name| names.contains(name));
+
+        let ready = std::mem::take(&mut self.pending);
+        for name in names {
+            if self.known.contains(&name) || ready.contains(&name) {
+                continue;
+            }
+            if classify(&name) == Some(DirectoryFormat::AniDb) {
+                debug!("New directory {:?}, waiting for it to settle", name);
+                self.pending.insert(name);
+            } else {
+                debug!("Ignoring {:?}", name);
+                self.known.insert(name);
+            }
+        }
+        self.known.extend(ready.iter().cloned());
+        ready
+    }
+}
+
+/// Sleep for `duration`, waking early when interrupted
+///
+/// Returns whether the watcher should stop.
+pub fn wait(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while !interrupted() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return false;
+        }
+        std::thread::sleep(left.min(Duration::from_millis(100)));
+    }
+    true
+}
+
+/// Stop the watcher at the next check on SIGINT instead of exiting
+///
+/// A batch in progress finishes first (with its history file); a second
+/// SIGINT exits immediately.
+pub fn install_interrupt_handler() -> io::Result<()> {
+    platform::install()
+}
+
+/// Whether SIGINT was received since [`install_interrupt_handler`]
+pub fn interrupted() -> bool {
+    platform::INTERRUPTED.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::io;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_interrupt(_signal: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        // SAFETY: signal() is async-signal-safe
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+    }
+
+    pub fn install() -> io::Result<()> {
+        let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // SAFETY: the handler only stores to an atomic and resets the disposition
+        if unsafe { libc::signal(libc::SIGINT, handler) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use std::io;
+    use std::sync::atomic::AtomicBool;
+
+    /// Never set: SIGINT keeps its default behavior of exiting
+    pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+    pub fn install() -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_new_names_are_ready_on_the_next_poll() {
+        let mut watcher = Watcher::new(Path::new("/incoming"));
+
+        assert!(watcher
+            .update(names(&["12345", "Cowboy Bebop (1998) [anidb-23]", "notes"]))
+            .is_empty());
+        assert_eq!(
+            watcher.update(names(&[
+                "12345",
+                "Cowboy Bebop (1998) [anidb-23]",
+                "notes",
+                "[AS0] 6"
+            ])),
+            names(&["12345"])
+        );
+        assert_eq!(
+            watcher.update(names(&["Test (2020) [anidb-12345]", "[AS0] 6"])),
+            names(&["[AS0] 6"])
+        );
+        assert!(watcher
+            .update(names(&[
+                "Test (2020) [anidb-12345]",
+                "Other (2021) [anidb-6]"
+            ]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_names_gone_before_settling_are_dropped() {
+        let mut watcher = Watcher::new(Path::new("/incoming"));
+        watcher.update(names(&["12345"]));
+
+        assert!(watcher.update(names(&[])).is_empty());
+        // Back again: waits for another poll
+        assert!(watcher.update(names(&["12345"])).is_empty());
+        assert_eq!(watcher.update(names(&["12345"])), names(&["12345"]));
+    }
+
+    #[test]
+    fn test_poll_scans_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("12345")).unwrap();
+        let mut watcher = Watcher::new(dir.path());
+
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(watcher.poll().unwrap(), names(&["12345"]));
+        assert!(watcher.poll().unwrap().is_empty());
+    }
+}
@@ -87,6 +87,55 @@ fn test_version_flag() {
         .stdout(predicate::str::contains(env!("CARGO_PKG_VERSION")));
 }
 
+#[test]
+fn test_lang_selects_message_catalog() {
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--lang", "ja", "/nonexistent/path/12345"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "指定されたディレクトリが存在しません",
+        ));
+
+    // Detected from the locale, overridden by --lang
+    cargo_bin_cmd!("anidb2folder")
+        .env("LANG", "ja_JP.UTF-8")
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .arg("/nonexistent/path/12345")
+        .assert()
+        .stderr(predicate::str::contains(
+            "指定されたディレクトリが存在しません",
+        ));
+    cargo_bin_cmd!("anidb2folder")
+        .env("LANG", "ja_JP.UTF-8")
+        .args(["--lang", "en", "/nonexistent/path/12345"])
+        .assert()
+        .stderr(predicate::str::contains(
+            "The specified directory does not exist",
+        ));
+}
+
+#[test]
+fn test_lang_translates_run_steps() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--lang", "ja", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("をスキャン中"))
+        .stderr(predicate::str::contains("2 個のディレクトリ"))
+        .stderr(predicate::str::contains("形式を検証中"))
+        .stderr(predicate::str::contains("ドライラン"))
+        .stderr(predicate::str::contains(
+            "リネーム（AniDB → 人が読める形式）",
+        ))
+        .stderr(predicate::str::contains("Scanning").not())
+        .stderr(predicate::str::contains("DRY RUN").not());
+}
+
 #[test]
 fn test_missing_target_dir() {
     cargo_bin_cmd!("anidb2folder")
@@ -101,330 +150,2391 @@ fn test_dry_flag() {
     setup_anidb_test(dir.path());
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--dry", dir.path().to_str().unwrap()])
+        .args(["--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        // UI output goes to stderr
+        .stderr(predicate::str::contains("DRY RUN"))
+        .stderr(predicate::str::contains("Human-readable"))
+        .stderr(predicate::str::contains("would be renamed"));
+}
+
+#[test]
+fn test_dry_run_preview_diff() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .env("NO_COLOR", "1")
+        .args(["--dry", "--preview", "diff", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "{+Test Anime ／ Test Anime English (2020) [anidb-+}12345{+]+}",
+        ));
+}
+
+#[test]
+fn test_dry_run_group_by_tag() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--group-by", "tag", dir.path().to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", stdout);
+    assert_eq!(lines[0], "[AS0] (1)");
+    assert!(lines[1].starts_with("  [AS0] 67890 -> [AS0] "));
+    assert_eq!(lines[2], "");
+    assert_eq!(lines[3], "Untagged (1)");
+    assert!(lines[4].starts_with("  12345 -> Test Anime"));
+}
+
+#[test]
+fn test_chunk_size_renames_in_chunks() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--chunk-size", "1", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "12345 -> Test Anime ／ Test Anime English (2020) [anidb-12345]",
+        ))
+        .stdout(predicate::str::contains("[AS0] 67890 -> [AS0] "));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--chunk-size", "1", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 chunk(s)"));
+    assert!(dir
+        .path()
+        .join("Test Anime ／ Test Anime English (2020) [anidb-12345]")
+        .exists());
+    let histories: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("anidb2folder-history-"))
+        .collect();
+    assert_eq!(histories.len(), 1);
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--chunk-size", "1", "--group-by", "tag"])
+        .arg(dir.path())
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_overrides_correct_names() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let config = tempdir().unwrap();
+    let overrides = config.path().join("overrides.json");
+    std::fs::write(
+        &overrides,
+        r#"{"12345": {"title_main": "Fixed Anime", "title_en": "", "year": 2019}}"#,
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--overrides", overrides.to_str().unwrap()])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "12345 -> Fixed Anime (2019) [anidb-12345] (override applied, cached just now)",
+        ))
+        .stdout(
+            predicate::str::contains("Another Anime")
+                .and(predicate::str::contains("[anidb-67890] (override applied").not()),
+        );
+
+    std::fs::write(&overrides, r#"{"12345": {"titel": "Typo"}}"#).unwrap();
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--overrides", overrides.to_str().unwrap()])
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Overrides file"));
+}
+
+#[test]
+fn test_include_type_adds_anidb_type() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("4710")).unwrap();
+    std::fs::write(
+        dir.path().join(".anidb2folder-cache.json"),
+        format!(
+            r#"{{"version":"1.0","entries":{{"4710":{{"anidb_id":4710,"title_main":"Hellsing Ultimate","title_en":null,"release_year":2006,"anime_type":"ova","fetched_at":"{}"}}}}}}"#,
+            chrono::Utc::now().to_rfc3339()
+        ),
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--include-type"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "4710 -> Hellsing Ultimate (2006) (OVA) [anidb-4710]",
+        ));
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg("--include-type")
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(dir
+        .path()
+        .join("Hellsing Ultimate (2006) (OVA) [anidb-4710]")
+        .exists());
+    let history = std::fs::read_to_string(only_history(dir.path())).unwrap();
+    assert!(history.contains("\"include_type\": true"), "{}", history);
+}
+
+#[test]
+fn test_titles_file_resolves_names_offline() {
+    let dir = tempdir().unwrap();
+    for name in ["1", "6107"] {
+        std::fs::create_dir(dir.path().join(name)).unwrap();
+    }
+    let titles = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/titles/anime-titles.xml"
+    );
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--titles-file", titles])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "1 -> Seikai no Monshou ／ Crest of the Stars [anidb-1]",
+        ))
+        .stdout(predicate::str::contains("6107 -> Fate／Zero [anidb-6107]"));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--titles-file"])
+        .arg(dir.path().join("missing.xml"))
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot read titles file"));
+}
+
+/// The history file a run wrote in `dir`, when it wrote only one
+fn only_history(dir: &std::path::Path) -> std::path::PathBuf {
+    let histories: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("anidb2folder-history-")
+        })
+        .collect();
+    assert_eq!(histories.len(), 1, "{:?}", histories);
+    histories[0].clone()
+}
+
+#[test]
+fn test_exclude_recent_skips_fresh_directories() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 3600);
+    std::fs::File::open(dir.path().join("12345"))
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "-v", "--exclude-recent", "1h"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("12345 -> "))
+        .stdout(predicate::str::contains("67890 -> ").not())
+        .stderr(predicate::str::contains(
+            "1 directory modified recently left untouched (--exclude-recent): [AS0] 67890",
+        ))
+        .stderr(predicate::str::contains("67890 changed"));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--exclude-recent", "30x"])
+        .arg(dir.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown unit 'x'"));
+}
+
+#[test]
+fn test_jobs_renames_like_serial() {
+    let serial = tempdir().unwrap();
+    let parallel = tempdir().unwrap();
+    for (dir, jobs) in [(serial.path(), "1"), (parallel.path(), "3")] {
+        setup_anidb_test(dir);
+        cargo_bin_cmd!("anidb2folder")
+            .args(["--jobs", jobs])
+            .arg(dir)
+            .assert()
+            .success();
+    }
+
+    let listing = |dir: &std::path::Path| {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| !name.starts_with("anidb2folder-history-"))
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(listing(parallel.path()), listing(serial.path()));
+    let changes = |dir: &std::path::Path| {
+        let history: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(only_history(dir)).unwrap()).unwrap();
+        history["changes"].clone()
+    };
+    assert_eq!(changes(parallel.path()), changes(serial.path()));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--jobs", "0", "--dry"])
+        .arg(serial.path())
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_trailing_space_names_are_renamed() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("12345 ")).unwrap();
+    std::fs::create_dir(dir.path().join("[AS0] 67890.")).unwrap();
+    create_test_cache(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(!dir.path().join("12345 ").exists());
+    assert!(!dir.path().join("[AS0] 67890.").exists());
+    assert!(dir
+        .path()
+        .join("[AS0] Another Anime (2021) [anidb-67890]")
+        .is_dir());
+}
+
+#[test]
+fn test_template_names_convert_back() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let template = "[anidb-{id}] {title_jp} ({year}) {tag}";
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--template", template])
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(dir.path().join("[anidb-12345] Test Anime (2020)").is_dir());
+    assert!(dir
+        .path()
+        .join("[anidb-67890] Another Anime (2021) [AS0]")
+        .is_dir());
+    cargo_bin_cmd!("anidb2folder")
+        .args(["-v", "--history-list", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "--template '{}'",
+            template
+        )));
+
+    // Only the template tells where the id and tag are
+    cargo_bin_cmd!("anidb2folder")
+        .arg("--dry")
+        .arg(dir.path())
+        .assert()
+        .code(5);
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--template", template])
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(dir.path().join("12345").is_dir());
+    assert!(dir.path().join("[AS0] 67890").is_dir());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--template", "{title} [anidb-{id}]", "--dry"])
+        .arg(dir.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown placeholder {title}"));
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--template", "{title_jp} ({year})", "--dry"])
+        .arg(dir.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("must contain [anidb-{id}]"));
+}
+
+#[test]
+fn test_unattended_run() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let report = dir.path().join(".anidb2folder-report.ndjson");
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .arg("--unattended")
+        .arg(dir.path())
+        .env_remove("FORCE_COLOR")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.starts_with("anidb2folder OK - 2 renamed"),
+        "{}",
+        stdout
+    );
+    assert!(!stderr.contains("|_____|"), "banner shown: {}", stderr);
+    assert!(!stderr.contains('\x1b'), "colored: {}", stderr);
+
+    let events = ndjson_events(&std::fs::read(&report).unwrap());
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "config");
+    assert_eq!(events[0]["unattended"], true);
+    assert_eq!(events[0]["prompts"], false);
+    assert_eq!(events[0]["on_conflict"], "skip");
+    assert_eq!(events[1]["event"], "summary");
+    assert_eq!(events[1]["ok"], true);
+    assert_eq!(events[1]["applied"], 2);
+
+    // Partial failure: a directory left untouched fails the run
+    let revert = only_history(dir.path());
+    cargo_bin_cmd!("anidb2folder")
+        .arg("--revert")
+        .arg(&revert)
+        .assert()
+        .success();
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 3600);
+    std::fs::File::open(dir.path().join("12345"))
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--unattended", "--exclude-recent", "1h"])
+        .arg(dir.path())
+        .assert()
+        .code(1)
+        .stdout(predicate::str::starts_with(
+            "anidb2folder WARNING - 1 renamed",
+        ));
+    let events = ndjson_events(&std::fs::read(&report).unwrap());
+    assert_eq!(events[1]["applied"], 1);
+
+    // Settings given explicitly win; prompts cannot be turned back on
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--unattended", "--on-conflict", "abort", "--print-config"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("on_conflict = \"abort\""))
+        .stdout(predicate::str::contains("monitoring_output = true"));
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--unattended", "--interactive"])
+        .arg(dir.path())
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_post_run_verify_and_undo_hint() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--post-run-verify", "--strict"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Post-run check: all 4 path(s) as expected"),
+        "{}",
+        stderr
+    );
+    let history = only_history(dir.path());
+    let hint = format!("To undo: anidb2folder --revert '{}'", history.display());
+    assert!(stderr.contains(&hint), "{}", stderr);
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--post-run-verify", "--strict", "--revert"])
+        .arg(&history)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Post-run check: all 4 path(s) as expected",
+        ));
+    assert!(dir.path().join("12345").exists());
+
+    // Nothing is checked in a dry run
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--post-run-verify", "--dry"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Post-run check").not());
+}
+
+#[test]
+fn test_tag_map_sets_tags_and_reverts() {
+    let config = tempdir().unwrap();
+    let tag_map = config.path().join("tags.toml");
+    std::fs::write(&tag_map, "FMA = [12345]\nAS = [67890]\n").unwrap();
+    let tag_map = tag_map.to_str().unwrap();
+
+    // AniDB -> readable
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--tag-map", tag_map])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "12345 -> [FMA] Test Anime ／ Test Anime English (2020) [anidb-12345] (tag none -> FMA, cached just now)",
+        ))
+        .stdout(predicate::str::contains("[AS0] 67890 -> [AS] ").and(
+            predicate::str::contains("[anidb-67890] (tag AS0 -> AS, cached just now)"),
+        ));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--tag-map", tag_map])
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(dir
+        .path()
+        .join("[FMA] Test Anime ／ Test Anime English (2020) [anidb-12345]")
+        .exists());
+    cargo_bin_cmd!("anidb2folder")
+        .arg("--revert")
+        .arg(only_history(dir.path()))
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(dir.path().join("12345").exists());
+    assert!(dir.path().join("[AS0] 67890").exists());
+
+    // Readable -> AniDB
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("[GitS] Title (2020) [anidb-67890]")).unwrap();
+    std::fs::create_dir(dir.path().join("Other (2021) [anidb-1]")).unwrap();
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--tag-map", tag_map])
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(dir.path().join("[AS] 67890").exists());
+    assert!(dir.path().join("1").exists());
+    cargo_bin_cmd!("anidb2folder")
+        .arg("--revert")
+        .arg(only_history(dir.path()))
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(dir
+        .path()
+        .join("[GitS] Title (2020) [anidb-67890]")
+        .exists());
+
+    std::fs::write(config.path().join("tags.toml"), "A = [1]\nB = [1]\n").unwrap();
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--tag-map", tag_map])
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("anidb 1 is listed under"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_after_hooks() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let hooks = tempdir().unwrap();
+    let log = hooks.path().join("hooks.log");
+    let each = format!(
+        "printf '%s %s -> %s\\n' \"$ANIDB_ID\" \"$SRC_NAME\" \"$DST_NAME\" >> '{}'",
+        log.display()
+    );
+    let run = format!("echo \"renamed $RENAMED\" >> '{}'", log.display());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--exec-after", &each, "--exec-after-run", &run])
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert!(!log.exists());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--exec-after", &each, "--exec-after-run", &run])
+        .arg(dir.path())
+        .assert()
+        .success();
+    let logged = std::fs::read_to_string(&log).unwrap();
+    assert!(logged.contains("12345 12345 -> Test Anime"), "{}", logged);
+    assert!(logged.ends_with("renamed 2\n"), "{}", logged);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failing_hook_is_a_warning_unless_strict() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--exec-after-run", "exit 4"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("failed"));
+
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--strict-hooks", "--exec-after-run", "exit 4"])
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("exit status: 4"));
+}
+
+#[test]
+fn test_dry_run_plan_on_stdout() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        // Results go to stdout, chatter to stderr
+        .stdout(predicate::str::contains(
+            "12345 -> Test Anime ／ Test Anime English (2020) [anidb-12345]",
+        ))
+        .stdout(predicate::str::contains("DRY RUN").not())
+        .stderr(predicate::str::contains("[anidb-12345]").not());
+}
+
+#[test]
+fn test_dry_run_plan_on_stdout_in_verbose_mode() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["-v", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[AS0] 67890 -> "));
+}
+
+#[test]
+fn test_dry_flag_no_filesystem_changes() {
+    let dir = tempdir().unwrap();
+    let original_name = "12345";
+    std::fs::create_dir(dir.path().join(original_name)).unwrap();
+    create_test_cache(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    // Verify directory unchanged after dry run
+    assert!(dir.path().join(original_name).exists());
+}
+
+#[test]
+fn test_verbose_flag() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--verbose", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("would be renamed"))
+        .stderr(predicate::str::contains("INFO"));
+}
+
+#[test]
+fn test_no_ui_flag() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--no-ui", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[AS0] 67890 -> "))
+        .stderr(predicate::str::contains("would be renamed").not())
+        .stderr(predicate::str::contains("INFO"));
+}
+
+#[test]
+fn test_log_only_alias() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--log-only", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("would be renamed").not());
+}
+
+#[test]
+fn test_revert_flag_missing_file() {
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--revert", "/tmp/nonexistent-history.json"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Cannot open file"));
+}
+
+#[test]
+fn test_max_length_flag() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--max-length", "200", dir.path().to_str().unwrap()])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_cache_expiry_flag() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--cache-expiry", "7", dir.path().to_str().unwrap()])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_all_flags_combined() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args([
+            "--dry",
+            "--verbose",
+            "--max-length",
+            "200",
+            "--cache-expiry",
+            "7",
+            dir.path().to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_nonexistent_directory() {
+    cargo_bin_cmd!("anidb2folder")
+        .arg("/nonexistent/path")
+        .assert()
+        .code(3) // ExitCode::DirectoryNotFound
+        .stderr(predicate::str::contains("does not exist"));
+}
+
+#[test]
+fn test_file_instead_of_directory() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("file.txt");
+    std::fs::write(&file_path, "content").unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(file_path.to_str().unwrap())
+        .assert()
+        .code(3) // ExitCode::DirectoryNotFound (NotADirectory maps to same code)
+        .stderr(predicate::str::contains("not a directory"));
+}
+
+#[test]
+fn test_rejects_anime_directory_as_target() {
+    let dir = tempdir().unwrap();
+    let series = dir.path().join("Naruto (2002) [anidb-12345]");
+    std::fs::create_dir(&series).unwrap();
+    std::fs::create_dir(series.join("Season 1")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(series.to_str().unwrap())
+        .assert()
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("itself an anime directory"));
+}
+
+#[test]
+fn test_uppercase_id_tag_accepted_with_warning() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("NARUTO (2002) [ANIDB-12345]")).unwrap();
+    std::fs::create_dir(dir.path().join("Bebop (1998) [anidb-1]")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "NARUTO (2002) [ANIDB-12345] -> 12345",
+        ))
+        .stderr(predicate::str::contains(
+            "1 directory with the AniDB tag in the wrong case",
+        ))
+        .stderr(predicate::str::contains("Bebop (1998) [anidb-1],").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_refuses_filesystem_root() {
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "/"])
+        .assert()
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("filesystem root"))
+        .stderr(predicate::str::contains("--i-know-what-im-doing"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_refuses_home_directory() {
+    let home = tempdir().unwrap();
+    std::fs::create_dir(home.path().join("12345")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .env("HOME", home.path())
+        .args(["--dry", home.path().to_str().unwrap()])
+        .assert()
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("home directory"));
+}
+
+#[test]
+fn test_refuses_many_unrecognized_children() {
+    let dir = tempdir().unwrap();
+    for name in ["etc", "usr", "var"] {
+        std::fs::create_dir(dir.path().join(name)).unwrap();
+    }
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--max-unrecognized", "2", dir.path().to_str().unwrap()])
+        .assert()
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("none of its 3 subdirectories"));
+
+    // The override falls through to normal validation
+    cargo_bin_cmd!("anidb2folder")
+        .args([
+            "--max-unrecognized",
+            "2",
+            "--i-know-what-im-doing",
+            dir.path().to_str().unwrap(),
+        ])
+        .assert()
+        .code(5); // ExitCode::UnrecognizedFormat
+}
+
+#[test]
+fn test_validates_anidb_format() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["-v", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("AniDb format"));
+}
+
+#[test]
+fn test_rejects_unrecognized_format() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("Invalid Directory")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(dir.path().to_str().unwrap())
+        .assert()
+        .code(5) // ExitCode::UnrecognizedFormat
+        .stderr(predicate::str::contains("do not match any known format"));
+}
+
+#[test]
+fn test_unrecognized_near_misses_get_suggestions() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("Naruto (2002) anidb_12345")).unwrap();
+    std::fs::create_dir(dir.path().join("Invalid Directory")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(dir.path().to_str().unwrap())
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "Naruto (2002) anidb_12345 (did you mean: Naruto (2002) [anidb-12345]?)",
+        ))
+        .stderr(predicate::str::contains("Invalid Directory\n"));
+    // Only explained, never renamed
+    assert!(dir.path().join("Naruto (2002) anidb_12345").exists());
+}
+
+#[test]
+fn test_show_all_unrecognized() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("12345")).unwrap();
+    for i in 0..15 {
+        std::fs::create_dir(dir.path().join(format!("Folder {:02}", i))).unwrap();
+    }
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(dir.path().to_str().unwrap())
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("... and 5 more"))
+        .stderr(predicate::str::contains("Folder 14").not());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--show-all-unrecognized", dir.path().to_str().unwrap()])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("Folder 14"))
+        .stderr(predicate::str::contains("more").not());
+}
+
+#[test]
+fn test_rejects_mixed_formats() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("12345")).unwrap();
+    std::fs::create_dir(dir.path().join("Naruto (2002) [anidb-67890]")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(dir.path().to_str().unwrap())
+        .assert()
+        .code(4) // ExitCode::MixedFormats
+        .stderr(predicate::str::contains("multiple formats"));
+}
+
+#[test]
+fn test_rejects_empty_directory() {
+    let dir = tempdir().unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .arg(dir.path().to_str().unwrap())
+        .assert()
+        .code(1) // ExitCode::GeneralError (NoDirectories)
+        .stderr(predicate::str::contains("No subdirectories"));
+}
+
+/// Create a test history file for revert tests
+fn create_test_history(dir: &std::path::Path, target_dir: &std::path::Path) -> std::path::PathBuf {
+    use chrono::Utc;
+
+    #[derive(serde::Serialize)]
+    struct HistoryEntry {
+        source: String,
+        destination: String,
+        anidb_id: u32,
+        truncated: bool,
+    }
+
+    #[derive(serde::Serialize)]
+    struct HistoryFile {
+        version: String,
+        executed_at: chrono::DateTime<Utc>,
+        operation: String,
+        direction: String,
+        target_directory: std::path::PathBuf,
+        tool_version: String,
+        changes: Vec<HistoryEntry>,
+    }
+
+    let history = HistoryFile {
+        version: "1.0".to_string(),
+        executed_at: Utc::now(),
+        operation: "rename".to_string(),
+        direction: "anidb_to_readable".to_string(),
+        target_directory: target_dir.to_path_buf(),
+        tool_version: "0.1.0".to_string(),
+        changes: vec![HistoryEntry {
+            source: "12345".to_string(),
+            destination: "Test Anime (2020) [anidb-12345]".to_string(),
+            anidb_id: 12345,
+            truncated: false,
+        }],
+    };
+
+    let history_path = dir.join("test-history.json");
+    let content = serde_json::to_string_pretty(&history).unwrap();
+    std::fs::write(&history_path, content).unwrap();
+
+    history_path
+}
+
+#[test]
+fn test_verify_ids_leaves_mismatched_id_untouched() {
+    let dir = tempdir().unwrap();
+    create_test_cache(dir.path());
+    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+    std::fs::create_dir(dir.path().join("Kaiju Battle (2021) [anidb-67890]")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--verify-ids", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("ID mismatch: Kaiju Battle"))
+        .stderr(predicate::str::contains("left untouched"));
+
+    assert!(dir.path().join("12345").exists());
+    assert!(dir
+        .path()
+        .join("Kaiju Battle (2021) [anidb-67890]")
+        .exists());
+}
+
+#[test]
+fn test_fetch_requires_verify_ids() {
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--fetch", "/tmp/test"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_use_trash_requires_quarantine_unresolved() {
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--use-trash", "/tmp/test"])
+        .assert()
+        .code(2);
+}
+
+/// Every rename-only option, with a value where it takes one
+const RENAME_ONLY_OPTIONS: &[&[&str]] = &[
+    &["--max-length", "100"],
+    &["--cache-save-every", "5"],
+    &["--slow-threshold", "10"],
+    &["--drop-series-tag"],
+    &["--i-know-what-im-doing"],
+    &["--max-unrecognized", "5"],
+    &["--show-all-unrecognized"],
+    &["--placeholder", "{id}"],
+    &["--restricted", "skip"],
+    &["--restricted-marker", "R"],
+    &["--unaired", "tag"],
+    &["--replace", ":=-"],
+    &["--type-suffix", "movie=Movie"],
+    &["--include-type"],
+    &["--template", "{title_jp} [anidb-{id}]"],
+    &["--verify-ids"],
+    &["--verify-ids", "--fetch"],
+    &["--fs-profile", "windows"],
+    &["--quarantine-unresolved"],
+    &["--quarantine-unresolved", "--use-trash"],
+    &["--check-open-files"],
+    &["--check-permissions"],
+    &["--exclude-recent", "30m"],
+    &["--credentials-file", "credentials"],
+    &["--overrides", "overrides.json"],
+    &["--titles-file", "anime-titles.xml.gz"],
+    &["--tag-map", "tags.toml"],
+    &["--exec-after", "true"],
+    &["--exec-after-run", "true"],
+    &["--strict-hooks"],
+    &["--watch", "incoming"],
+    &["--monitoring-output"],
+    &["--print-plan-only"],
+    &["--preview", "diff"],
+    &["--group-by", "tag"],
+    &["--chunk-size", "100"],
+    &["--parallel-scan", "2"],
+    &["--jobs", "2"],
+    &["--report", "report.ndjson"],
+    &["--output", "ndjson"],
+];
+
+#[test]
+fn test_rename_only_options_rejected_with_revert() {
+    for mode in [["--revert", "history.json"], ["--revert-all", "*.json"]] {
+        for option in RENAME_ONLY_OPTIONS {
+            cargo_bin_cmd!("anidb2folder")
+                .args(mode)
+                .args(*option)
+                .assert()
+                .code(2)
+                .stderr(predicate::str::contains("cannot be used with"));
+        }
+    }
+}
+
+#[test]
+fn test_revert_accepts_shared_options() {
+    // Fails on the missing file, not on argument parsing
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--revert", "/tmp/nonexistent-history.json"])
+        .args(["--dry", "--strict", "--on-conflict", "skip", "-c", "7"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_standalone_commands_are_exclusive() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().to_str().unwrap();
+    let commands: [&[&str]; 10] = [
+        &["--cache-info", path],
+        &["--cache-clear", path],
+        &["--cache-prune", path],
+        &["--where", path],
+        &["--history-list", path],
+        &["--lint", path],
+        &["--check-roundtrip", path],
+        &["--parse", "x"],
+        &["--list-exit-codes"],
+        &["--build-info"],
+    ];
+
+    for command in commands {
+        for other in [
+            &[path][..],
+            &["--revert", "history.json"],
+            &["--revert-all", "*.json"],
+        ] {
+            cargo_bin_cmd!("anidb2folder")
+                .args(command)
+                .args(other)
+                .assert()
+                .code(2)
+                .stderr(predicate::str::contains("cannot be used with"));
+        }
+    }
+    for (i, command) in commands.iter().enumerate() {
+        for other in &commands[i + 1..] {
+            cargo_bin_cmd!("anidb2folder")
+                .args(*command)
+                .args(*other)
+                .assert()
+                .code(2);
+        }
+    }
+}
+
+#[test]
+fn test_revert_all_continues_past_failures() {
+    let root = tempdir().unwrap();
+    for lib in ["a", "b", "broken"] {
+        let dir = root.path().join(lib);
+        std::fs::create_dir(&dir).unwrap();
+        if lib != "broken" {
+            std::fs::create_dir(dir.join("Test Anime (2020) [anidb-12345]")).unwrap();
+        }
+        create_test_history(&dir, &dir);
+    }
+
+    let pattern = format!("{}/**/test-history.json", root.path().display());
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--revert-all", &pattern, "--json"])
+        .output()
+        .unwrap();
+
+    // The broken library's directory is gone: its history is not revertible
+    assert_eq!(output.status.code(), Some(8)); // ExitCode::HistoryError
+    assert!(root.path().join("a/12345").exists());
+    assert!(root.path().join("b/12345").exists());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let statuses: Vec<&str> = report["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["reverted", "reverted", "failed"]);
+    assert_eq!(report["failed"], 1);
+    assert_eq!(report["files"][2]["exit_code"], 8);
+}
+
+#[test]
+fn test_revert_all_without_matches_fails() {
+    let root = tempdir().unwrap();
+    let pattern = format!("{}/*.json", root.path().display());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--revert-all", &pattern])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("no history files match"));
+}
+
+#[test]
+fn test_revert_with_mismatched_target_dir_fails() {
+    let dir = tempdir().unwrap();
+    let other_dir = tempdir().unwrap();
+
+    // Create history file pointing to dir.path()
+    let history_path = create_test_history(dir.path(), dir.path());
+
+    // Try to revert with a different target directory
+    cargo_bin_cmd!("anidb2folder")
+        .args([
+            "--revert",
+            history_path.to_str().unwrap(),
+            other_dir.path().to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Directory mismatch"));
+}
+
+#[test]
+fn test_revert_shows_target_directory() {
+    let dir = tempdir().unwrap();
+
+    // Create the renamed directory that would exist after a rename
+    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+
+    // Create history file
+    let history_path = create_test_history(dir.path(), dir.path());
+
+    // Revert in dry-run mode should show target directory
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--revert", history_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Target directory"))
+        .stdout(predicate::str::contains(
+            "Test Anime (2020) [anidb-12345] -> 12345",
+        ));
+}
+
+#[test]
+fn test_revert_dry_run_shows_conflicts() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+    std::fs::create_dir(dir.path().join("12345")).unwrap();
+    let history_path = create_test_history(dir.path(), dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--revert", history_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1 change(s) cannot be reverted"))
+        .stderr(predicate::str::contains(
+            "Test Anime (2020) [anidb-12345] -> 12345: original name already exists",
+        ));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--revert", history_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'12345' already exists"));
+}
+
+#[test]
+fn test_revert_with_matching_target_dir_succeeds() {
+    let dir = tempdir().unwrap();
+
+    // Create the renamed directory that would exist after a rename
+    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+
+    // Create history file
+    let history_path = create_test_history(dir.path(), dir.path());
+
+    // Revert with matching target directory should succeed (in dry-run mode)
+    cargo_bin_cmd!("anidb2folder")
+        .args([
+            "--dry",
+            "--revert",
+            history_path.to_str().unwrap(),
+            dir.path().to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Target directory verified"));
+}
+
+#[test]
+fn test_cache_info_no_cache() {
+    let dir = tempdir().unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--cache-info", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Cache Information"))
+        .stderr(predicate::str::contains("No cache file found"));
+}
+
+#[test]
+fn test_cache_info_with_cache() {
+    let dir = tempdir().unwrap();
+    create_test_cache(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--cache-info", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Cache Information"))
+        .stderr(predicate::str::contains("Total entries"))
+        .stderr(predicate::str::contains("2")); // We create 2 entries in test cache
+}
+
+#[test]
+fn test_cache_info_lists_title_changes() {
+    let dir = tempdir().unwrap();
+    let fetched_at = chrono::Utc::now().to_rfc3339();
+    let cache = serde_json::json!({
+        "version": "1.0",
+        "entries": {
+            "1": {
+                "anidb_id": 1,
+                "title_main": "Renamed",
+                "title_en": null,
+                "release_year": 2001,
+                "fetched_at": fetched_at
+            }
+        },
+        "changes": {
+            "1": [{
+                "old": { "title_main": "Original", "title_en": null, "release_year": 2001 },
+                "new": { "title_main": "Renamed", "title_en": null, "release_year": 2001 },
+                "changed_at": fetched_at
+            }]
+        }
+    });
+    std::fs::write(
+        dir.path().join(".anidb2folder-cache.json"),
+        cache.to_string(),
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--cache-info", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Title changes"))
+        .stdout(predicate::str::is_empty());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--cache-info", dir.path().to_str().unwrap(), "--changes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "1  Original (2001) -> Renamed (2001)",
+        ));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--changes"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_where_reports_cache_and_history_paths() {
+    let dir = tempdir().unwrap();
+    create_test_cache(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--where", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(".anidb2folder-cache.json"))
+        .stderr(predicate::str::contains("2 (0 expired)"));
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--where", dir.path().to_str().unwrap(), "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["cache"]["exists"], true);
+    assert_eq!(report["cache"]["entries"], 2);
+    assert_eq!(report["history"]["files"], 0);
+}
+
+#[test]
+fn test_lint_suggests_confusable_series_tags() {
+    let dir = tempdir().unwrap();
+    for name in ["[AS0] 1", "[AS0] 2", "[ASO] 3", "[FMA] 4", "5"] {
+        std::fs::create_dir(dir.path().join(name)).unwrap();
+    }
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--lint", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[ASO]  1 directory  did you mean [AS0]? (2 directories, confusable characters)",
+        ))
+        .stdout(predicate::str::contains("FMA").not());
+    assert!(dir.path().join("[ASO] 3").exists());
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--lint", dir.path().to_str().unwrap(), "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["tags"].as_array().unwrap().len(), 3);
+    assert_eq!(report["suggestions"][0]["tag"], "ASO");
+    assert_eq!(report["suggestions"][0]["suggestion"], "AS0");
+    assert_eq!(report["suggestions"][0]["reason"], "confusable");
+    assert_eq!(report["suggestions"][0]["directories"][0], "[ASO] 3");
+}
+
+#[test]
+fn test_check_roundtrip_reports_altered_tags() {
+    let dir = tempdir().unwrap();
+    for name in ["[S1] 1", "[S1 S2] 2", "3"] {
+        std::fs::create_dir(dir.path().join(name)).unwrap();
+    }
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--check-roundtrip", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    std::fs::create_dir(dir.path().join("[A|B] 4")).unwrap();
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--check-roundtrip", dir.path().to_str().unwrap(), "--json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["checked"], 4);
+    assert_eq!(report["uncached"], 4);
+    assert_eq!(report["mismatches"].as_array().unwrap().len(), 1);
+    assert_eq!(report["mismatches"][0]["name"], "[A|B] 4");
+    assert_eq!(report["mismatches"][0]["back"], "[A｜B] 4");
+    assert_eq!(report["mismatches"][0]["reason"], "tag_sanitization");
+    assert!(dir.path().join("[A|B] 4").exists());
+}
+
+#[test]
+fn test_where_reports_forced_fs_profile() {
+    let dir = tempdir().unwrap();
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--where", dir.path().to_str().unwrap(), "--json"])
+        .args(["--fs-profile", "windows"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["filesystem"]["profile"], "windows");
+    assert_eq!(report["filesystem"]["case_insensitive"], true);
+}
+
+#[test]
+fn test_list_exit_codes_json() {
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--list-exit-codes", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let codes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(codes[2]["code"], 2);
+    assert_eq!(codes[2]["name"], "invalid_arguments");
+    assert_eq!(codes[10]["name"], "cache_error");
+    assert_eq!(codes[11]["name"], "ambiguous_names");
+    assert_eq!(codes[12]["name"], "nothing_to_do");
+}
+
+#[test]
+fn test_build_info() {
+    let features = if cfg!(feature = "async") {
+        "features: api, async, cache, cli"
+    } else {
+        "features: api, cache, cli"
+    };
+    cargo_bin_cmd!("anidb2folder")
+        .arg("--build-info")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "anidb2folder {}",
+            env!("CARGO_PKG_VERSION")
+        )))
+        .stdout(predicate::str::contains(features))
+        .stdout(predicate::str::contains("history schema: 1.4"))
+        .stdout(predicate::str::contains("json schema: 1"));
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--build-info", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert!(info["target"].as_str().is_some_and(|t| !t.is_empty()));
+    assert_eq!(info["cache_version"], "1.0");
+}
+
+#[test]
+fn test_dry_run_flags_names_the_filesystem_would_reject() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("[What?] Test Anime (2020) [anidb-12345]")).unwrap();
+    let target = dir.path().to_str().unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--fs-profile", "windows", target])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "-> [What?] 12345 (would fail: contains '?')",
+        ));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--strict", "--fs-profile", "windows", target])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_strict_refuses_ambiguous_names() {
+    let dir = tempdir().unwrap();
+    for name in ["12345", "20240131"] {
+        std::fs::create_dir(dir.path().join(name)).unwrap();
+    }
+    let target = dir.path().to_str().unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", target])
+        .assert()
+        .success();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--strict", target])
+        .assert()
+        .code(11)
+        .stderr(predicate::str::contains(
+            "20240131: AniDB ID 20240131 is above 1000000",
+        ));
+    assert!(dir.path().join("20240131").exists());
+}
+
+/// The structure of a JSON document: every leaf replaced by its type name
+///
+/// Comparing shapes pins field names and nesting (the schema) without
+/// depending on paths, timestamps or versions.
+fn json_shape(value: &serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::Null => "null".into(),
+        Value::Bool(_) => "bool".into(),
+        Value::Number(_) => "number".into(),
+        Value::String(_) => "string".into(),
+        Value::Array(items) => Value::Array(items.iter().take(1).map(json_shape).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), json_shape(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn test_json_report_schemas() {
+    let dir = tempdir().unwrap();
+    let target = dir.path().to_str().unwrap();
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--where", target, "--json"])
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["schema"], 1);
+    assert_eq!(
+        json_shape(&report),
+        serde_json::json!({
+            "schema": "number",
+            "cache": {
+                "path": "string",
+                "exists": "bool",
+                "entries": "number",
+                "expired": "number",
+                "expiry_days": "number",
+            },
+            "history": { "directory": "string", "files": "number" },
+            "filesystem": {
+                "type": "string",
+                "profile": "string",
+                "case_insensitive": "bool",
+            },
+        })
+    );
+    // Keys are written in a stable (sorted) order
+    let text = String::from_utf8_lossy(&output.stdout);
+    let cache = text.find("\"cache\"").unwrap();
+    assert!(cache < text.find("\"filesystem\"").unwrap());
+    assert!(text.find("\"filesystem\"").unwrap() < text.find("\"schema\"").unwrap());
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--build-info", "--json"])
+        .output()
+        .unwrap();
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json_shape(&info),
+        serde_json::json!({
+            "schema": "number",
+            "version": "string",
+            "features": ["string"],
+            "target": "string",
+            "history_version": "string",
+            "cache_version": "string",
+        })
+    );
+
+    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--output", "ndjson", "--dry", target])
+        .output()
+        .unwrap();
+    let events = ndjson_events(&output.stdout);
+    assert!(events.iter().all(|e| e["schema"] == 1));
+    let mut summary = events.last().unwrap().clone();
+    summary["build"] = serde_json::Value::Null;
+    assert_eq!(
+        json_shape(&summary),
+        serde_json::json!({
+            "schema": "number",
+            "seq": "number",
+            "timestamp": "string",
+            "event": "string",
+            "ok": "bool",
+            "build": "null",
+            "run_id": "string",
+            "dry_run": "bool",
+            "direction": "string",
+            "operations": "number",
+            "applied": "number",
+            "skipped": "number",
+            "truncated": "number",
+            "quarantined": "number",
+            "trashed": "number",
+            "slowest": [],
+            "vanished": [],
+            "permission_skipped": [],
+        })
+    );
+}
+
+#[test]
+fn test_slowest_steps_in_verbose_summary_and_report() {
+    let dir = tempdir().unwrap();
+    let target = dir.path().to_str().unwrap();
+    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--output", "ndjson", "--slow-threshold", "0", target])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let events = ndjson_events(&output.stdout);
+    assert!(events.iter().all(|e| e["event"] != "slow_step"));
+    let summary = events.last().unwrap();
+    assert_eq!(summary["slowest"][0]["step"], "execute");
+    assert_eq!(
+        summary["slowest"][0]["name"],
+        "Test Anime (2020) [anidb-12345]"
+    );
+    assert!(summary["slowest"][0]["elapsed_ms"].is_u64());
+
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("Other (2021) [anidb-2]")).unwrap();
+    cargo_bin_cmd!("anidb2folder")
+        .args(["-v", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Slowest steps:"))
+        .stderr(predicate::str::contains("execute  Other (2021) [anidb-2]"));
+}
+
+fn ndjson_events(stdout: &[u8]) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every line is JSON"))
+        .collect()
+}
+
+#[test]
+fn test_output_ndjson_streams_events_in_order() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--output", "ndjson", dir.path().to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let events = ndjson_events(&output.stdout);
+    let names: Vec<&str> = events
+        .iter()
+        .map(|e| e["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "scan_done",
+            "validated",
+            "rename",
+            "history_written",
+            "summary"
+        ]
+    );
+    for (i, event) in events.iter().enumerate() {
+        assert_eq!(event["seq"], i as u64 + 1);
+    }
+    assert_eq!(events[2]["to"], "12345");
+    assert_eq!(events[4]["ok"], true);
+    assert_eq!(events[4]["applied"], 1);
+}
+
+#[test]
+fn test_dry_run_shows_cache_age() {
+    let dir = tempdir().unwrap();
+    let target = dir.path().to_str().unwrap();
+    create_anidb_dirs(dir.path());
+    create_test_cache(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", target])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[AS0] 67890 -> [AS0] Another Anime (2021) [anidb-67890] (cached just now)",
+        ));
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--output", "ndjson", "--dry", target])
+        .output()
+        .unwrap();
+    let events = ndjson_events(&output.stdout);
+    let renames: Vec<_> = events.iter().filter(|e| e["event"] == "rename").collect();
+    assert_eq!(renames.len(), 2);
+    assert!(renames
+        .iter()
+        .all(|e| e["cache_age_secs"].as_u64() < Some(60)));
+}
+
+#[test]
+fn test_output_ndjson_ends_with_error_summary() {
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--output", "ndjson", "/nonexistent/path/12345"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+
+    let events = ndjson_events(&output.stdout);
+    let summary = events.last().unwrap();
+    assert_eq!(summary["event"], "summary");
+    assert_eq!(summary["ok"], false);
+    assert_eq!(summary["error"]["code"], 3);
+}
+
+#[test]
+fn test_cache_clear() {
+    let dir = tempdir().unwrap();
+    create_test_cache(dir.path());
+
+    // Verify cache exists
+    assert!(dir.path().join(".anidb2folder-cache.json").exists());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--cache-clear", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Clear Cache"))
+        .stderr(predicate::str::contains("Cleared"));
+}
+
+#[test]
+fn test_cache_prune_no_expired() {
+    let dir = tempdir().unwrap();
+    create_test_cache(dir.path()); // Creates fresh entries (not expired)
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--cache-prune", dir.path().to_str().unwrap()])
+        .assert()
+        .code(12) // ExitCode::NothingToDo
+        .stderr(predicate::str::contains("Prune"))
+        .stderr(predicate::str::contains("No expired entries"));
+}
+
+#[test]
+fn test_cache_clear_no_cache() {
+    let dir = tempdir().unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--cache-clear", dir.path().to_str().unwrap()])
+        .assert()
+        .code(12) // ExitCode::NothingToDo
+        .stderr(predicate::str::contains("No cache file found"));
+}
+
+#[test]
+fn test_parse_prints_a_corpus_entry() {
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args([
+            "--parse",
+            "[FMA] Hagane no Renkinjutsushi (2003) [anidb-979]",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let entry: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        entry,
+        serde_json::json!({
+            "name": "[FMA] Hagane no Renkinjutsushi (2003) [anidb-979]",
+            "format": "human_readable",
+            "anidb_id": 979,
+            "series_tag": "FMA",
+            "title_jp": "Hagane no Renkinjutsushi",
+            "release_year": 2003,
+        })
+    );
+
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--parse", "Title (2020) [anidb-12a]", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["format"], serde_json::Value::Null);
+    assert_eq!(
+        report["diagnosis"],
+        "the id in [anidb-<id>] is not a number"
+    );
+    assert!(report["schema"].is_number());
+}
+
+#[test]
+fn test_cache_commands_json() {
+    let dir = tempdir().unwrap();
+    let target = dir.path().to_str().unwrap();
+    // `--output json` is the same as `--json`; a no-op exits with 12 (nothing to do)
+    let report = |command: &str, json: &[&str], code: i32| {
+        let output = cargo_bin_cmd!("anidb2folder")
+            .args([command, target])
+            .args(json)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(code));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let cleared = report("--cache-clear", &["--json"], 12);
+    assert_eq!(cleared["exists"], false);
+    assert_eq!(cleared["removed"], 0);
+
+    create_test_cache(dir.path());
+    let info = report("--cache-info", &["--output", "json"], 0);
+    assert_eq!(info["entries"], 2);
+    assert_eq!(info["title_changes"], serde_json::json!({}));
+    let pruned = report("--cache-prune", &["--output", "json"], 12);
+    assert_eq!(
+        json_shape(&pruned),
+        serde_json::json!({
+            "schema": "number",
+            "path": "string",
+            "exists": "bool",
+            "before": "number",
+            "after": "number",
+            "removed": "number",
+            "bytes_freed": "number",
+        })
+    );
+    assert_eq!(pruned["removed"], 0);
+
+    let cleared = report("--cache-clear", &["--output", "json"], 0);
+    assert_eq!(
+        (cleared["before"].as_u64(), cleared["after"].as_u64()),
+        (Some(2), Some(0))
+    );
+    assert!(cleared["bytes_freed"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn test_output_json_is_for_standalone_commands() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--output", "json", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("--output ndjson"));
+}
+
+#[test]
+fn test_scan_reports_skipped_entries() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    std::fs::write(dir.path().join("notes.txt"), "content").unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("non-directory entries"));
+}
+
+#[test]
+fn test_no_banner_flag() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--no-banner", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("|___").not())
+        .stderr(predicate::str::contains("would be renamed"));
+}
+
+#[test]
+fn test_compact_flag() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--compact", "--dry", dir.path().to_str().unwrap()])
         .assert()
         .success()
-        // UI output goes to stderr
-        .stderr(predicate::str::contains("DRY RUN"))
-        .stderr(predicate::str::contains("Human-readable"))
+        .stderr(predicate::str::contains("|___").not())
+        .stderr(predicate::str::contains("\n\n").not())
         .stderr(predicate::str::contains("would be renamed"));
 }
 
 #[test]
-fn test_dry_flag_no_filesystem_changes() {
+fn test_on_conflict_skip() {
     let dir = tempdir().unwrap();
-    let original_name = "12345";
-    std::fs::create_dir(dir.path().join(original_name)).unwrap();
-    create_test_cache(dir.path());
+    std::fs::create_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+    std::fs::create_dir(dir.path().join("Other (2019) [anidb-2]")).unwrap();
+    // A file occupying the destination is not picked up by the scan
+    std::fs::write(dir.path().join("1"), "occupied").unwrap();
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--dry", dir.path().to_str().unwrap()])
+        .args(["--on-conflict", "skip", dir.path().to_str().unwrap()])
         .assert()
-        .success();
+        .success()
+        .stderr(predicate::str::contains("1 directories renamed"))
+        .stderr(predicate::str::contains("skipped due to conflicts"));
 
-    // Verify directory unchanged after dry run
-    assert!(dir.path().join(original_name).exists());
+    assert!(dir.path().join("Title (2020) [anidb-1]").exists());
+    assert!(dir.path().join("2").exists());
 }
 
 #[test]
-fn test_verbose_flag() {
+fn test_drop_series_tag_warns_in_plan() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("[AS0] 12345")).unwrap();
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--drop-series-tag", "--dry", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[AS0] 12345 -> ⟨uncached 12345⟩ (placeholder)",
+        ))
+        .stderr(predicate::str::contains(
+            "Series tag dropped from 1 name(s)",
+        ));
+}
+
+#[test]
+fn test_placeholder_rejects_real_looking_format() {
     let dir = tempdir().unwrap();
     setup_anidb_test(dir.path());
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--verbose", "--dry", dir.path().to_str().unwrap()])
+        .args([
+            "--placeholder",
+            "Title [anidb-{id}]",
+            "--dry",
+            dir.path().to_str().unwrap(),
+        ])
         .assert()
-        .success();
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("looks like a real directory name"));
 }
 
 #[test]
-fn test_revert_flag_missing_file() {
+fn test_check_api_fails_before_touching_directories() {
+    let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+
     cargo_bin_cmd!("anidb2folder")
-        .args(["--revert", "/tmp/nonexistent-history.json"])
+        .env_remove("ANIDB_CLIENT")
+        .env_remove("ANIDB_CLIENT_VERSION")
+        .args(["--check-api", dir.path().to_str().unwrap()])
         .assert()
-        .failure()
-        .stderr(predicates::str::contains("Cannot open file"));
+        .code(6) // ExitCode::ApiError
+        .stderr(predicate::str::contains("Scanning").not());
+
+    assert!(dir.path().join("12345").exists());
 }
 
 #[test]
-fn test_max_length_flag() {
+fn test_print_plan_only_lists_uncached_ids() {
     let dir = tempdir().unwrap();
     setup_anidb_test(dir.path());
+    std::fs::create_dir(dir.path().join("[AS0] 222")).unwrap();
+    std::fs::create_dir(dir.path().join("111")).unwrap();
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--dry", "--max-length", "200", dir.path().to_str().unwrap()])
+        .env_remove("ANIDB_CLIENT")
+        .args(["--print-plan-only", dir.path().to_str().unwrap()])
         .assert()
-        .success();
+        .success()
+        .stdout("111\n222\n");
+
+    // Nothing was renamed and the cached ids are left out
+    assert!(dir.path().join("12345").exists());
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--print-plan-only", "--json", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout("[111,222]\n");
 }
 
+#[cfg(unix)]
 #[test]
-fn test_cache_expiry_flag() {
+fn test_credentials_file_readable_by_others_is_refused() {
+    use std::os::unix::fs::PermissionsExt;
+
     let dir = tempdir().unwrap();
     setup_anidb_test(dir.path());
+    let credentials = dir.path().join("credentials");
+    std::fs::write(&credentials, "client = myclient\nclient_version = 1\n").unwrap();
+    std::fs::set_permissions(&credentials, std::fs::Permissions::from_mode(0o644)).unwrap();
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--dry", "--cache-expiry", "7", dir.path().to_str().unwrap()])
+        .args(["--credentials-file", credentials.to_str().unwrap()])
+        .args(["--dry", dir.path().to_str().unwrap()])
         .assert()
-        .success();
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("chmod 600"));
 }
 
 #[test]
-fn test_all_flags_combined() {
+fn test_replace_rejects_forbidden_replacement() {
     let dir = tempdir().unwrap();
     setup_anidb_test(dir.path());
 
     cargo_bin_cmd!("anidb2folder")
-        .args([
-            "--dry",
-            "--verbose",
-            "--max-length",
-            "200",
-            "--cache-expiry",
-            "7",
-            dir.path().to_str().unwrap(),
-        ])
+        .args(["--replace", ":=?", "--dry", dir.path().to_str().unwrap()])
         .assert()
-        .success();
+        .code(2) // ExitCode::InvalidArguments
+        .stderr(predicate::str::contains("not allowed in names"));
 }
 
 #[test]
-fn test_nonexistent_directory() {
+fn test_type_suffix_rejects_unknown_type() {
     cargo_bin_cmd!("anidb2folder")
-        .arg("/nonexistent/path")
+        .args(["--type-suffix", "film=Film", "--dry", "."])
         .assert()
-        .code(3) // ExitCode::DirectoryNotFound
-        .stderr(predicate::str::contains("does not exist"));
+        .code(2)
+        .stderr(predicate::str::contains("unknown type 'film'"))
+        .stderr(predicate::str::contains("music-video"));
 }
 
 #[test]
-fn test_file_instead_of_directory() {
+fn test_interactive_without_terminal_uses_policy() {
     let dir = tempdir().unwrap();
-    let file_path = dir.path().join("file.txt");
-    std::fs::write(&file_path, "content").unwrap();
+    std::fs::create_dir(dir.path().join("Title (2020) [anidb-1]")).unwrap();
+    std::fs::write(dir.path().join("1"), "occupied").unwrap();
 
     cargo_bin_cmd!("anidb2folder")
-        .arg(file_path.to_str().unwrap())
+        .args([
+            "--interactive",
+            "--on-conflict",
+            "suffix",
+            dir.path().to_str().unwrap(),
+        ])
         .assert()
-        .code(3) // ExitCode::DirectoryNotFound (NotADirectory maps to same code)
-        .stderr(predicate::str::contains("not a directory"));
+        .success()
+        .stderr(predicate::str::contains("Not a terminal"));
+
+    assert!(dir.path().join("1 (2)").exists());
 }
 
 #[test]
-fn test_validates_anidb_format() {
+fn test_theme_mono_has_no_colors() {
     let dir = tempdir().unwrap();
     setup_anidb_test(dir.path());
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["-v", "--dry", dir.path().to_str().unwrap()])
+        .env("FORCE_COLOR", "1")
+        .args(["--theme", "mono", "--dry", dir.path().to_str().unwrap()])
         .assert()
         .success()
-        .stderr(predicate::str::contains("AniDb format"));
+        .stderr(predicate::str::contains("\x1b[1m"))
+        .stderr(predicate::str::contains("\x1b[3").not());
 }
 
 #[test]
-fn test_rejects_unrecognized_format() {
-    let dir = tempdir().unwrap();
-    std::fs::create_dir(dir.path().join("Invalid Directory")).unwrap();
-
+fn test_theme_rejects_unknown_name() {
     cargo_bin_cmd!("anidb2folder")
-        .arg(dir.path().to_str().unwrap())
+        .args(["--theme", "neon", "/tmp"])
         .assert()
-        .code(5) // ExitCode::UnrecognizedFormat
-        .stderr(predicate::str::contains("do not match any known format"));
+        .failure();
 }
 
-#[test]
-fn test_rejects_mixed_formats() {
-    let dir = tempdir().unwrap();
-    std::fs::create_dir(dir.path().join("12345")).unwrap();
-    std::fs::create_dir(dir.path().join("Naruto (2002) [anidb-67890]")).unwrap();
+/// Run a real rename and return the history file it wrote
+fn rename_and_get_history(dir: &std::path::Path) -> std::path::PathBuf {
+    setup_anidb_test(dir);
 
     cargo_bin_cmd!("anidb2folder")
-        .arg(dir.path().to_str().unwrap())
+        .arg(dir.to_str().unwrap())
         .assert()
-        .code(4) // ExitCode::MixedFormats
-        .stderr(predicate::str::contains("multiple formats"));
+        .success();
+
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| {
+            p.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("anidb2folder-history-")
+        })
+        .unwrap()
 }
 
 #[test]
-fn test_rejects_empty_directory() {
+fn test_untitled_directories_round_trip() {
     let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("[anidb-12345]")).unwrap();
+    std::fs::create_dir(dir.path().join("[AS0] [anidb-67890]")).unwrap();
+    create_test_cache(dir.path());
 
     cargo_bin_cmd!("anidb2folder")
         .arg(dir.path().to_str().unwrap())
         .assert()
-        .code(1) // ExitCode::GeneralError (NoDirectories)
-        .stderr(predicate::str::contains("No subdirectories"));
-}
-
-/// Create a test history file for revert tests
-fn create_test_history(dir: &std::path::Path, target_dir: &std::path::Path) -> std::path::PathBuf {
-    use chrono::Utc;
-
-    #[derive(serde::Serialize)]
-    struct HistoryEntry {
-        source: String,
-        destination: String,
-        anidb_id: u32,
-        truncated: bool,
-    }
-
-    #[derive(serde::Serialize)]
-    struct HistoryFile {
-        version: String,
-        executed_at: chrono::DateTime<Utc>,
-        operation: String,
-        direction: String,
-        target_directory: std::path::PathBuf,
-        tool_version: String,
-        changes: Vec<HistoryEntry>,
+        .success()
+        .stderr(predicate::str::contains("2 directories without a title"));
+    assert!(dir.path().join("12345").exists());
+    assert!(dir.path().join("[AS0] 67890").exists());
+
+    // Reverting restores the untitled names
+    let history = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| {
+            p.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("anidb2folder-history-")
+        })
+        .unwrap();
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--revert", history.to_str().unwrap()])
+        .assert()
+        .success();
+    assert!(dir.path().join("[anidb-12345]").exists());
+    assert!(dir.path().join("[AS0] [anidb-67890]").exists());
+
+    // Converting to AniDB format and back rebuilds the names
+    for _ in 0..2 {
+        cargo_bin_cmd!("anidb2folder")
+            .arg(dir.path().to_str().unwrap())
+            .assert()
+            .success();
     }
-
-    let history = HistoryFile {
-        version: "1.0".to_string(),
-        executed_at: Utc::now(),
-        operation: "rename".to_string(),
-        direction: "anidb_to_readable".to_string(),
-        target_directory: target_dir.to_path_buf(),
-        tool_version: "0.1.0".to_string(),
-        changes: vec![HistoryEntry {
-            source: "12345".to_string(),
-            destination: "Test Anime (2020) [anidb-12345]".to_string(),
-            anidb_id: 12345,
-            truncated: false,
-        }],
-    };
-
-    let history_path = dir.join("test-history.json");
-    let content = serde_json::to_string_pretty(&history).unwrap();
-    std::fs::write(&history_path, content).unwrap();
-
-    history_path
+    assert!(dir
+        .path()
+        .join("Test Anime ／ Test Anime English (2020) [anidb-12345]")
+        .exists());
+    assert!(dir
+        .path()
+        .join("[AS0] Another Anime (2021) [anidb-67890]")
+        .exists());
 }
 
 #[test]
-fn test_revert_with_mismatched_target_dir_fails() {
+fn test_history_list_shows_checksum() {
     let dir = tempdir().unwrap();
-    let other_dir = tempdir().unwrap();
-
-    // Create history file pointing to dir.path()
-    let history_path = create_test_history(dir.path(), dir.path());
+    let history_path = rename_and_get_history(dir.path());
+    let name = history_path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
 
-    // Try to revert with a different target directory
     cargo_bin_cmd!("anidb2folder")
-        .args([
-            "--revert",
-            history_path.to_str().unwrap(),
-            other_dir.path().to_str().unwrap(),
-        ])
+        .args(["--history-list", dir.path().to_str().unwrap()])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Directory mismatch"));
+        .success()
+        .stdout(predicate::str::contains(name))
+        .stdout(predicate::str::contains("rename  2 changes"))
+        .stdout(predicate::str::contains("MISMATCH").not());
 }
 
 #[test]
-fn test_revert_shows_target_directory() {
+fn test_history_list_verbose_shows_options() {
     let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
 
-    // Create the renamed directory that would exist after a rename
-    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
-
-    // Create history file
-    let history_path = create_test_history(dir.path(), dir.path());
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--on-conflict", "skip", dir.path().to_str().unwrap()])
+        .assert()
+        .success();
 
-    // Revert in dry-run mode should show target directory
     cargo_bin_cmd!("anidb2folder")
-        .args(["--dry", "--revert", history_path.to_str().unwrap()])
+        .args(["-v", "--history-list", dir.path().to_str().unwrap()])
         .assert()
         .success()
-        .stderr(predicate::str::contains("Target directory"));
+        .stdout(predicate::str::contains(
+            "options: --max-length 255 --on-conflict skip",
+        ));
 }
 
 #[test]
-fn test_revert_with_matching_target_dir_succeeds() {
+fn test_redo_uses_recorded_options() {
     let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let template = "[anidb-{id}] {title_jp} ({year}) {tag}";
 
-    // Create the renamed directory that would exist after a rename
-    std::fs::create_dir(dir.path().join("Test Anime (2020) [anidb-12345]")).unwrap();
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--template", template, dir.path().to_str().unwrap()])
+        .assert()
+        .success();
+    let history = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| {
+            p.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("anidb2folder-history-")
+        })
+        .unwrap();
+    let history = history.to_str().unwrap();
+
+    // Redoing a rename still in place would convert the names back
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--redo", history])
+        .assert()
+        .code(8) // ExitCode::HistoryError
+        .stderr(predicate::str::contains(
+            "revert the history before redoing it",
+        ));
 
-    // Create history file
-    let history_path = create_test_history(dir.path(), dir.path());
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--revert", history])
+        .assert()
+        .success();
+    assert!(dir.path().join("12345").is_dir());
 
-    // Revert with matching target directory should succeed (in dry-run mode)
     cargo_bin_cmd!("anidb2folder")
-        .args([
-            "--dry",
-            "--revert",
-            history_path.to_str().unwrap(),
-            dir.path().to_str().unwrap(),
-        ])
+        .args(["--redo", history])
         .assert()
-        .success()
-        .stderr(predicate::str::contains("Target directory verified"));
+        .success();
+    assert!(dir.path().join("[anidb-12345] Test Anime (2020)").is_dir());
+    assert!(dir
+        .path()
+        .join("[anidb-67890] Another Anime (2021) [AS0]")
+        .is_dir());
+
+    // Naming options come from the history only
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--redo", history, "--max-length", "100"])
+        .assert()
+        .code(2);
 }
 
 #[test]
-fn test_cache_info_no_cache() {
+fn test_redo_without_recorded_options_uses_defaults() {
     let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("12345")).unwrap();
+    create_test_cache(dir.path());
+    let history = create_test_history(dir.path(), dir.path());
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--cache-info", dir.path().to_str().unwrap()])
+        .args(["--redo", history.to_str().unwrap()])
         .assert()
         .success()
-        .stderr(predicate::str::contains("Cache Information"))
-        .stderr(predicate::str::contains("No cache file found"));
+        .stderr(predicate::str::contains("records no options"));
+    assert!(dir
+        .path()
+        .join("Test Anime ／ Test Anime English (2020) [anidb-12345]")
+        .is_dir());
 }
 
 #[test]
-fn test_cache_info_with_cache() {
+fn test_edited_history_detected() {
     let dir = tempdir().unwrap();
-    create_test_cache(dir.path());
+    let history_path = rename_and_get_history(dir.path());
+
+    let content = std::fs::read_to_string(&history_path).unwrap();
+    std::fs::write(&history_path, content.replace("\"12345\"", "\"54321\"")).unwrap();
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--cache-info", dir.path().to_str().unwrap()])
+        .args(["--history-list", dir.path().to_str().unwrap()])
         .assert()
         .success()
-        .stderr(predicate::str::contains("Cache Information"))
-        .stderr(predicate::str::contains("Total entries"))
-        .stderr(predicate::str::contains("2")); // We create 2 entries in test cache
+        .stdout(predicate::str::contains("CHECKSUM MISMATCH"));
+
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--strict", "--revert", history_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("checksum mismatch"));
 }
 
 #[test]
-fn test_cache_clear() {
+fn test_history_list_links_revert_to_original() {
     let dir = tempdir().unwrap();
-    create_test_cache(dir.path());
+    let history_path = rename_and_get_history(dir.path());
+    let name = history_path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
 
-    // Verify cache exists
-    assert!(dir.path().join(".anidb2folder-cache.json").exists());
+    cargo_bin_cmd!("anidb2folder")
+        .args(["--revert", history_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Reverted from"));
 
     cargo_bin_cmd!("anidb2folder")
-        .args(["--cache-clear", dir.path().to_str().unwrap()])
+        .args(["--history-list", dir.path().to_str().unwrap()])
         .assert()
         .success()
-        .stderr(predicate::str::contains("Clear Cache"))
-        .stderr(predicate::str::contains("Cleared"));
+        .stdout(predicate::str::contains(format!("reverts {}", name)));
 }
 
+#[cfg(unix)]
 #[test]
-fn test_cache_prune_no_expired() {
+fn test_watch_renames_new_directories_until_interrupted() {
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
     let dir = tempdir().unwrap();
-    create_test_cache(dir.path()); // Creates fresh entries (not expired)
+    create_test_cache(dir.path());
+    std::fs::create_dir(dir.path().join("Existing (2001) [anidb-1]")).unwrap();
 
-    cargo_bin_cmd!("anidb2folder")
-        .args(["--cache-prune", dir.path().to_str().unwrap()])
-        .assert()
-        .success()
-        .stderr(predicate::str::contains("Prune"))
-        .stderr(predicate::str::contains("No expired entries"));
+    let child = Command::new(assert_cmd::cargo::cargo_bin!("anidb2folder"))
+        .args(["--no-banner", "--once-per", "1", "--watch"])
+        .arg(dir.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    std::thread::sleep(Duration::from_millis(500));
+    std::fs::create_dir(dir.path().join("12345")).unwrap();
+    let renamed = dir
+        .path()
+        .join("Test Anime ／ Test Anime English (2020) [anidb-12345]");
+    let deadline = Instant::now() + Duration::from_secs(20);
+    while !renamed.exists() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    // SAFETY: plain kill(2) on our own child
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(renamed.exists(), "not renamed: {}", stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("Stopped watching"));
+    assert!(dir.path().join("Existing (2001) [anidb-1]").exists());
+    let histories = std::fs::read_dir(dir.path())
+        .unwrap()
+        .filter(|e| {
+            e.as_ref()
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with("anidb2folder-history-")
+        })
+        .count();
+    assert_eq!(histories, 1);
 }
 
 #[test]
-fn test_cache_clear_no_cache() {
+fn test_monitoring_output_line() {
+    let perfdata = |stdout: &[u8]| {
+        let stdout = String::from_utf8_lossy(stdout).to_string();
+        assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+        let (status, data) = stdout.trim_end().split_once(" | ").unwrap();
+        let duration = regex::Regex::new(r" duration=\d+s$").unwrap();
+        assert!(duration.is_match(data), "{}", data);
+        (status.to_string(), duration.replace(data, "").to_string())
+    };
+
+    // OK: everything renamed from the cache
     let dir = tempdir().unwrap();
+    setup_anidb_test(dir.path());
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--monitoring-output", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    assert_eq!(
+        perfdata(&output.stdout),
+        (
+            "anidb2folder OK - 2 renamed, 0 failed, 0 skipped, 2 cached".to_string(),
+            "renamed=2 failed=0 skipped=0 api_calls=0".to_string()
+        )
+    );
 
-    cargo_bin_cmd!("anidb2folder")
-        .args(["--cache-clear", dir.path().to_str().unwrap()])
+    // WARNING: an id whose cached title does not match the name
+    let dir = tempdir().unwrap();
+    create_test_cache(dir.path());
+    std::fs::create_dir(dir.path().join("Wrong Show (2020) [anidb-12345]")).unwrap();
+    std::fs::create_dir(dir.path().join("Another Anime (2021) [anidb-67890]")).unwrap();
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--dry", "--verify-ids", "--monitoring-output"])
+        .arg(dir.path())
         .assert()
         .success()
-        .stderr(predicate::str::contains("No cache file found"));
+        .get_output()
+        .clone();
+    assert!(perfdata(&output.stdout)
+        .0
+        .starts_with("anidb2folder WARNING - 0 renamed, 0 failed, 0 skipped, 2 cached (dry run)"));
+
+    // CRITICAL: the run failed
+    let output = cargo_bin_cmd!("anidb2folder")
+        .args(["--monitoring-output", "/nonexistent/path/12345"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let (status, data) = perfdata(&output.stdout);
+    assert!(status.starts_with("anidb2folder CRITICAL - 0 renamed, 1 failed"));
+    assert_eq!(data, "renamed=0 failed=1 skipped=0 api_calls=0");
 }